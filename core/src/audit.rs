@@ -0,0 +1,598 @@
+//! Audit trail of what a spawned LLM did.
+//!
+//! Every tool call seen in the runner's event stream (shell commands, file
+//! edits, web fetches, ...) is kept with its arguments and the time it was
+//! made, and appended to `tool_calls.jsonl` in the spawn's log directory as
+//! it happens. [`SpawnObservability`] adds the permissions the LLM was
+//! denied, those granted in response, and the review comments posted on
+//! the PR, and [`format_observability_markdown`] summarizes it all for PR
+//! descriptions and run reports. The finished record is saved as
+//! `observability.json` next to the spawn's other logs, so later reports
+//! and analytics can load it.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::monitor::ProgressSummary;
+use crate::permissions::{PermissionError, PermissionErrorType, PermissionFix};
+
+/// Name of the audit trail file in a spawn's log directory.
+pub const TOOL_CALLS_FILE: &str = "tool_calls.jsonl";
+
+/// Name of the saved [`SpawnObservability`] in a spawn's log directory.
+pub const OBSERVABILITY_FILE: &str = "observability.json";
+
+/// Distinct targets listed per tool in the summary table.
+const MAX_TARGETS: usize = 3;
+
+/// Longest target shown in the summary table, in characters.
+const MAX_TARGET_CHARS: usize = 60;
+
+/// Argument fields that name what a tool acted on, in order of preference.
+const TARGET_FIELDS: &[&str] = &[
+    "command",
+    "file_path",
+    "absolute_path",
+    "path",
+    "url",
+    "pattern",
+    "query",
+];
+
+/// One tool call made by an LLM.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    /// When the call was seen, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Tool name, e.g. `Bash` or `Edit`.
+    pub tool: String,
+    /// Arguments as reported by the CLI (JSON for stream-json output).
+    pub args: String,
+}
+
+impl ToolCallRecord {
+    /// Creates a record of a call made now.
+    pub fn now(tool: impl Into<String>, args: impl Into<String>) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            tool: tool.into(),
+            args: args.into(),
+        }
+    }
+
+    /// Returns what the call acted on: the command, file or URL if the
+    /// arguments name one, otherwise the raw arguments.
+    pub fn target(&self) -> String {
+        let value: Option<serde_json::Value> = serde_json::from_str(&self.args).ok();
+        value
+            .as_ref()
+            .and_then(|v| {
+                TARGET_FIELDS
+                    .iter()
+                    .find_map(|key| v.get(key).and_then(|f| f.as_str()))
+            })
+            .unwrap_or(&self.args)
+            .to_string()
+    }
+}
+
+/// Tool calls made during one spawn, optionally mirrored to a file.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    /// File each record is appended to, if any.
+    path: Option<PathBuf>,
+    /// Calls recorded so far.
+    records: Vec<ToolCallRecord>,
+}
+
+impl AuditLog {
+    /// Creates an in-memory audit log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an audit log that also appends to `path` as JSON lines.
+    pub fn to_file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            records: Vec::new(),
+        }
+    }
+
+    /// Records a tool call made now.
+    ///
+    /// A failure to write the file is logged rather than returned, so an
+    /// unwritable log directory does not stop the spawn.
+    pub fn record(&mut self, tool: &str, args: &str) {
+        let record = ToolCallRecord::now(tool, args);
+        if let Some(path) = &self.path {
+            if let Err(e) = append(path, &record) {
+                tracing::warn!(path = %path.display(), error = %e, "failed to write audit trail");
+            }
+        }
+        self.records.push(record);
+    }
+
+    /// Returns the calls recorded so far.
+    pub fn records(&self) -> &[ToolCallRecord] {
+        &self.records
+    }
+
+    /// Consumes the log, returning its records.
+    pub fn into_records(self) -> Vec<ToolCallRecord> {
+        self.records
+    }
+
+    /// Reads an audit trail written by [`AuditLog::to_file`].
+    pub fn load(path: &Path) -> Result<Vec<ToolCallRecord>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// A permission the LLM needed but did not have.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionRequest {
+    /// Attempt the request was made in, starting at 1.
+    pub iteration: u32,
+    /// What was denied.
+    pub error: PermissionError,
+}
+
+/// A permission added to the manifest before retrying.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionGrant {
+    /// Attempt whose denial led to the grant; the grant applies from the
+    /// next one.
+    pub iteration: u32,
+    /// The fix applied.
+    pub fix: PermissionFix,
+}
+
+/// The model that served one LLM invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelUse {
+    /// Attempt the invocation belongs to, starting at 1.
+    pub iteration: u32,
+    /// Model that answered, after any fallback.
+    pub model: String,
+}
+
+/// A review comment posted on the spawn's PR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostedComment {
+    /// GitHub ID of the line comment, or `None` if the finding was only
+    /// listed in the review summary.
+    pub id: Option<u64>,
+    /// File the finding is about.
+    pub file: String,
+    /// Line the comment is anchored to, if any.
+    pub line: Option<u32>,
+    /// The finding as posted.
+    pub issue: String,
+    /// Commit that fixed the finding, once a reply has said so.
+    #[serde(default)]
+    pub resolved_by: Option<String>,
+    /// Fixes the verifier found did not address the finding.
+    #[serde(default)]
+    pub failed_fixes: u32,
+    /// Whether the finding was handed to a human after too many failed
+    /// fixes.
+    #[serde(default)]
+    pub escalated: bool,
+}
+
+/// Everything recorded about a spawn for auditing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpawnObservability {
+    /// Every tool call, across all attempts.
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Permissions denied at runtime.
+    #[serde(default)]
+    pub permissions_requested: Vec<PermissionRequest>,
+    /// Permissions granted in response.
+    #[serde(default)]
+    pub permissions_granted: Vec<PermissionGrant>,
+    /// New files left out of commits because they are gitignored or denied.
+    #[serde(default)]
+    pub skipped_paths: Vec<PathBuf>,
+    /// Review comments posted on the PR, with their fix status.
+    #[serde(default)]
+    pub posted_comments: Vec<PostedComment>,
+    /// Names of the environment variables passed to the LLM; values are
+    /// never recorded.
+    #[serde(default)]
+    pub environment: Vec<String>,
+    /// Models that served each invocation, in order.
+    #[serde(default)]
+    pub models: Vec<ModelUse>,
+}
+
+impl SpawnObservability {
+    /// Returns whether `request` was granted.
+    pub fn granted(&self, request: &PermissionRequest) -> bool {
+        self.permissions_granted
+            .iter()
+            .any(|g| g.iteration == request.iteration && g.fix == request.error.fix)
+    }
+
+    /// Appends everything recorded in `other`.
+    pub fn merge(&mut self, other: SpawnObservability) {
+        self.tool_calls.extend(other.tool_calls);
+        self.permissions_requested
+            .extend(other.permissions_requested);
+        self.permissions_granted.extend(other.permissions_granted);
+        self.skipped_paths.extend(other.skipped_paths);
+        self.posted_comments.extend(other.posted_comments);
+        self.models.extend(other.models);
+        for name in other.environment {
+            if !self.environment.contains(&name) {
+                self.environment.push(name);
+            }
+        }
+    }
+
+    /// Writes the record to `path` as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("failed to serialize observability: {}", e)))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Reads a record written by [`SpawnObservability::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            Error::Config(format!(
+                "failed to parse observability {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+fn append(path: &Path, record: &ToolCallRecord) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let line = serde_json::to_string(record)?;
+    writeln!(file, "{}", line)
+}
+
+/// Formats a spawn's activity as a markdown section: token usage, a table
+/// of tool calls per tool with the first few things each acted on, and the
+/// permissions requested at runtime.
+pub fn format_observability_markdown(
+    progress: &ProgressSummary,
+    observability: &SpawnObservability,
+) -> String {
+    let calls = &observability.tool_calls;
+    let mut body = String::from("### Observability\n\n");
+    body.push_str(&format!(
+        "- **Duration:** {:.0}s\n- **Tool calls:** {}\n- **Files written:** {}\n",
+        progress.total_duration_secs,
+        calls.len(),
+        progress.files_written.len()
+    ));
+    if progress.usage.total() > 0 {
+        body.push_str(&format!(
+            "- **Tokens:** {} in, {} out, {} cached\n",
+            progress.usage.input_tokens,
+            progress.usage.output_tokens,
+            progress.usage.cache_read_tokens
+        ));
+    }
+    let mut models: Vec<&str> = Vec::new();
+    for used in &observability.models {
+        if !models.contains(&used.model.as_str()) {
+            models.push(&used.model);
+        }
+    }
+    if !models.is_empty() {
+        body.push_str(&format!("- **Models:** {}\n", models.join(", ")));
+    }
+    let comments = &observability.posted_comments;
+    if !comments.is_empty() {
+        body.push_str(&format!(
+            "- **Review comments:** {} posted, {} resolved",
+            comments.len(),
+            comments.iter().filter(|c| c.resolved_by.is_some()).count()
+        ));
+        let escalated = comments.iter().filter(|c| c.escalated).count();
+        if escalated > 0 {
+            body.push_str(&format!(", {} escalated", escalated));
+        }
+        body.push('\n');
+    }
+    body.push('\n');
+
+    if !calls.is_empty() {
+        push_tool_table(&mut body, calls);
+    }
+    if !observability.permissions_requested.is_empty() {
+        body.push_str("| Attempt | Permission requested | Granted |\n");
+        body.push_str("|---------|----------------------|---------|\n");
+        for request in &observability.permissions_requested {
+            body.push_str(&format!(
+                "| {} | {} | {} |\n",
+                request.iteration,
+                describe(&request.error.error_type),
+                if observability.granted(request) {
+                    "yes"
+                } else {
+                    "no"
+                }
+            ));
+        }
+        body.push('\n');
+    }
+    if !observability.environment.is_empty() {
+        let names: Vec<String> = observability
+            .environment
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect();
+        body.push_str(&format!("**Environment passed:** {}\n\n", names.join(", ")));
+    }
+    if !observability.skipped_paths.is_empty() {
+        body.push_str("**Not committed (ignored or denied):**\n\n");
+        for path in &observability.skipped_paths {
+            body.push_str(&format!("- `{}`\n", path.display()));
+        }
+        body.push('\n');
+    }
+    body
+}
+
+/// Appends the per-tool summary table to `body`.
+fn push_tool_table(body: &mut String, calls: &[ToolCallRecord]) {
+    let mut by_tool: BTreeMap<&str, Vec<&ToolCallRecord>> = BTreeMap::new();
+    for call in calls {
+        by_tool.entry(&call.tool).or_default().push(call);
+    }
+
+    body.push_str("| Tool | Calls | Targets |\n|------|-------|---------|\n");
+    for (tool, calls) in by_tool {
+        let mut targets: Vec<String> = Vec::new();
+        for call in &calls {
+            let target = call.target();
+            if !target.is_empty() && !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        let mut cell = targets
+            .iter()
+            .take(MAX_TARGETS)
+            .map(|t| format!("`{}`", table_cell(t)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if targets.len() > MAX_TARGETS {
+            cell.push_str(&format!(" and {} more", targets.len() - MAX_TARGETS));
+        }
+        body.push_str(&format!(
+            "| {} | {} | {} |\n",
+            table_cell(tool),
+            calls.len(),
+            cell
+        ));
+    }
+    body.push('\n');
+}
+
+/// Describes a denied permission in a few words.
+fn describe(error: &PermissionErrorType) -> String {
+    let (what, subject) = match error {
+        PermissionErrorType::FileReadDenied(path) => ("read", path.display().to_string()),
+        PermissionErrorType::FileWriteDenied(path) => ("write", path.display().to_string()),
+        PermissionErrorType::CommandBlocked(cmd) => ("run", cmd.clone()),
+        PermissionErrorType::ToolDisabled(tool) => ("use tool", tool.clone()),
+        PermissionErrorType::EnvVarMissing(var) => ("env var", var.clone()),
+        PermissionErrorType::SecretMissing(secret) => ("secret", secret.clone()),
+        PermissionErrorType::NetworkBlocked(host) => ("network access to", host.clone()),
+    };
+    format!("{} `{}`", what, table_cell(&subject))
+}
+
+/// Makes `text` safe to show inside a markdown table cell code span.
+fn table_cell(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    let mut cell: String = line
+        .chars()
+        .take(MAX_TARGET_CHARS)
+        .map(|c| match c {
+            '|' => '¦',
+            '`' => '\'',
+            c => c,
+        })
+        .collect();
+    if line.chars().count() > MAX_TARGET_CHARS || text.lines().count() > 1 {
+        cell.push('…');
+    }
+    cell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::TokenUsage;
+    use tempfile::TempDir;
+
+    fn summary() -> ProgressSummary {
+        ProgressSummary {
+            files_read: Vec::new(),
+            files_written: vec![PathBuf::from("src/lib.rs")],
+            commits: Vec::new(),
+            output_lines: 10,
+            total_duration_secs: 42.0,
+            budget_remaining_secs: None,
+            tool_calls: 3,
+            usage: TokenUsage {
+                input_tokens: 100,
+                output_tokens: 20,
+                cache_read_tokens: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn records_are_appended_to_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(TOOL_CALLS_FILE);
+        let mut log = AuditLog::to_file(&path);
+
+        log.record("Bash", r#"{"command":"cargo test"}"#);
+        log.record("Edit", r#"{"file_path":"src/lib.rs"}"#);
+
+        let loaded = AuditLog::load(&path).unwrap();
+        assert_eq!(loaded, log.records());
+        assert_eq!(loaded[0].target(), "cargo test");
+        assert!(loaded[0].timestamp_ms > 0);
+    }
+
+    #[test]
+    fn markdown_summarizes_calls_per_tool() {
+        let calls = vec![
+            ToolCallRecord::now("Bash", r#"{"command":"cargo build"}"#),
+            ToolCallRecord::now("Bash", r#"{"command":"cargo test | tail"}"#),
+            ToolCallRecord::now("Edit", r#"{"file_path":"src/lib.rs"}"#),
+        ];
+
+        let observability = SpawnObservability {
+            tool_calls: calls,
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("**Tool calls:** 3"));
+        assert!(markdown.contains("**Tokens:** 100 in, 20 out"));
+        assert!(markdown.contains("| Bash | 2 | `cargo build`, `cargo test ¦ tail` |"));
+        assert!(markdown.contains("| Edit | 1 | `src/lib.rs` |"));
+    }
+
+    #[test]
+    fn markdown_truncates_long_target_lists() {
+        let calls: Vec<_> = (0..5)
+            .map(|i| ToolCallRecord::now("Read", format!(r#"{{"file_path":"f{}.rs"}}"#, i)))
+            .collect();
+
+        let observability = SpawnObservability {
+            tool_calls: calls,
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("`f0.rs`, `f1.rs`, `f2.rs` and 2 more"));
+    }
+
+    #[test]
+    fn markdown_lists_permission_requests() {
+        let denied = PermissionError::tool_denied("Bash", r#"{"command":"npm test"}"#);
+        let observability = SpawnObservability {
+            permissions_granted: vec![PermissionGrant {
+                iteration: 1,
+                fix: denied.fix.clone(),
+            }],
+            permissions_requested: vec![
+                PermissionRequest {
+                    iteration: 1,
+                    error: denied,
+                },
+                PermissionRequest {
+                    iteration: 2,
+                    error: PermissionError::tool_denied("WebFetch", "{}"),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("| 1 | run `npm test` | yes |"));
+        assert!(markdown.contains("| 2 | use tool `WebFetch` | no |"));
+    }
+
+    #[test]
+    fn markdown_lists_serving_models() {
+        let used = |iteration, model: &str| ModelUse {
+            iteration,
+            model: model.to_string(),
+        };
+        let observability = SpawnObservability {
+            models: vec![used(1, "sonnet"), used(2, "sonnet"), used(3, "haiku")],
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("- **Models:** sonnet, haiku\n"));
+    }
+
+    #[test]
+    fn markdown_counts_review_comments() {
+        let comment = PostedComment {
+            id: Some(1),
+            file: "src/lib.rs".to_string(),
+            line: Some(3),
+            issue: "Unchecked result".to_string(),
+            resolved_by: Some("abc123".to_string()),
+            failed_fixes: 0,
+            escalated: false,
+        };
+        let observability = SpawnObservability {
+            posted_comments: vec![
+                comment.clone(),
+                PostedComment {
+                    resolved_by: None,
+                    escalated: true,
+                    ..comment
+                },
+            ],
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("**Review comments:** 2 posted, 1 resolved, 1 escalated"));
+    }
+
+    #[test]
+    fn markdown_lists_environment_names() {
+        let observability = SpawnObservability {
+            environment: vec!["HOME".to_string(), "PATH".to_string()],
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("**Environment passed:** `HOME`, `PATH`\n"));
+    }
+
+    #[test]
+    fn markdown_lists_skipped_paths() {
+        let observability = SpawnObservability {
+            skipped_paths: vec![PathBuf::from("node_modules/x/index.js")],
+            ..Default::default()
+        };
+
+        let markdown = format_observability_markdown(&summary(), &observability);
+
+        assert!(markdown.contains("- `node_modules/x/index.js`"));
+    }
+}
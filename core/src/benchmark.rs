@@ -0,0 +1,498 @@
+//! Benchmarks for the performance review domain.
+//!
+//! Configured benchmark commands (`cargo bench`, hyperfine, ...) run on the
+//! base and on the branch under review. The reviewer of the `performance`
+//! domain is shown both sets of numbers, and every benchmark that got
+//! slower by more than the configured threshold becomes a blocking finding.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::checks::summarize_log;
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::sandbox::{sandbox_environment, SandboxManifest};
+use crate::team::{ReviewSeverity, ReviewSuggestion};
+use crate::verification::VerificationFailure;
+
+/// Review domain that is shown benchmark results.
+pub const PERFORMANCE_DOMAIN: &str = "performance";
+
+/// Settings for benchmarking branches in the performance domain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkConfig {
+    /// Shell commands printing benchmark results; empty disables
+    /// benchmarking.
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Slowdown, in percent, above which a benchmark is a regression.
+    #[serde(default = "default_regression_threshold")]
+    pub regression_threshold: f64,
+    /// Lines of a failed command's output kept in its finding.
+    #[serde(default = "default_max_output_lines")]
+    pub max_output_lines: usize,
+}
+
+fn default_regression_threshold() -> f64 {
+    10.0
+}
+
+fn default_max_output_lines() -> usize {
+    40
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            regression_threshold: default_regression_threshold(),
+            max_output_lines: default_max_output_lines(),
+        }
+    }
+}
+
+impl BenchmarkConfig {
+    /// Returns whether any benchmark command is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    /// Runs the commands in `dir` with the sandbox environment of
+    /// `manifest` and collects their measurements, stopping at the first
+    /// command that fails.
+    pub fn run(
+        &self,
+        dir: &Path,
+        manifest: &SandboxManifest,
+    ) -> std::result::Result<Vec<Measurement>, VerificationFailure> {
+        let mut measurements = Vec::new();
+        let env = sandbox_environment(manifest);
+
+        for command in &self.commands {
+            tracing::info!(command = %command, "running benchmark");
+            let output = Command::new("sh")
+                .current_dir(dir)
+                .args(["-c", command])
+                .env_clear()
+                .envs(&env)
+                .output();
+
+            match output {
+                Ok(output) if output.status.success() => {
+                    measurements.extend(parse_benchmarks(&String::from_utf8_lossy(&output.stdout)));
+                }
+                Ok(output) => {
+                    let log = format!(
+                        "{}{}",
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                    return Err(VerificationFailure {
+                        command: command.clone(),
+                        output: summarize_log(&log, self.max_output_lines),
+                    });
+                }
+                Err(e) => {
+                    return Err(VerificationFailure {
+                        command: command.clone(),
+                        output: format!("command could not start: {}", e),
+                    });
+                }
+            }
+        }
+
+        Ok(measurements)
+    }
+
+    /// Runs the commands on `commit` of the repository at `repo_path`, in a
+    /// temporary worktree. A failing command leaves the base without
+    /// numbers rather than failing the review.
+    pub fn run_at(
+        &self,
+        repo_path: &Path,
+        commit: &str,
+        manifest: &SandboxManifest,
+    ) -> Result<Vec<Measurement>> {
+        let worktree = std::env::temp_dir().join(format!(
+            "improbability-drive-bench-{}",
+            uuid::Uuid::new_v4()
+        ));
+        let worktree_str = worktree
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(worktree.clone()))?;
+
+        SystemGit.run(
+            repo_path,
+            &["worktree", "add", "--detach", worktree_str, commit],
+        )?;
+        let measured = self.run(&worktree, manifest);
+        SystemGit.run(repo_path, &["worktree", "remove", "--force", worktree_str])?;
+
+        Ok(measured.unwrap_or_else(|failure| {
+            tracing::warn!(
+                command = %failure.command,
+                commit = %commit,
+                "benchmark failed on the base, comparing without baseline"
+            );
+            Vec::new()
+        }))
+    }
+
+    /// Returns a blocking finding for each regression in `report`, and for
+    /// a benchmark that failed on the branch.
+    pub fn regressions(&self, report: &BenchmarkReport) -> Vec<ReviewSuggestion> {
+        let mut findings: Vec<ReviewSuggestion> = report
+            .comparisons
+            .iter()
+            .filter_map(|comparison| {
+                let (before, change) = comparison.before.zip(comparison.change_percent())?;
+                (change > self.regression_threshold).then(|| ReviewSuggestion {
+                    file: format!("(benchmark: {})", comparison.name),
+                    line: None,
+                    issue: format!(
+                        "`{}` is {:.1}% slower than the base ({} -> {})",
+                        comparison.name,
+                        change,
+                        format_nanos(before),
+                        format_nanos(comparison.after)
+                    ),
+                    suggestion: format!(
+                        "Find the slowdown and bring it under {:.0}%, or explain in the PR why it is acceptable",
+                        self.regression_threshold
+                    ),
+                    severity: ReviewSeverity::High,
+                })
+            })
+            .collect();
+
+        if let Some(failure) = &report.failure {
+            findings.push(ReviewSuggestion {
+                file: "(benchmark)".to_string(),
+                line: None,
+                issue: format!(
+                    "Benchmark `{}` fails on the branch:\n{}",
+                    failure.command, failure.output
+                ),
+                suggestion: "Make the benchmark run again".to_string(),
+                severity: ReviewSeverity::High,
+            });
+        }
+        findings
+    }
+}
+
+/// One benchmark's time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// Benchmark name.
+    pub name: String,
+    /// Time per iteration in nanoseconds.
+    pub nanos: f64,
+}
+
+/// A benchmark's time on the base and on the branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    /// Benchmark name.
+    pub name: String,
+    /// Nanoseconds on the base, if the benchmark ran there.
+    pub before: Option<f64>,
+    /// Nanoseconds on the branch.
+    pub after: f64,
+}
+
+impl BenchmarkComparison {
+    /// Returns the slowdown in percent, negative for a speedup.
+    pub fn change_percent(&self) -> Option<f64> {
+        self.before
+            .filter(|before| *before > 0.0)
+            .map(|before| (self.after - before) / before * 100.0)
+    }
+}
+
+/// Benchmark results of a branch compared with its base.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// One comparison per benchmark that ran on the branch.
+    pub comparisons: Vec<BenchmarkComparison>,
+    /// The command that failed on the branch, if any.
+    pub failure: Option<VerificationFailure>,
+}
+
+impl BenchmarkReport {
+    /// Compares the branch's results with the base's.
+    pub fn new(
+        before: &[Measurement],
+        after: std::result::Result<Vec<Measurement>, VerificationFailure>,
+    ) -> Self {
+        let (after, failure) = match after {
+            Ok(after) => (after, None),
+            Err(failure) => (Vec::new(), Some(failure)),
+        };
+        let comparisons = after
+            .into_iter()
+            .map(|measurement| BenchmarkComparison {
+                before: before
+                    .iter()
+                    .find(|b| b.name == measurement.name)
+                    .map(|b| b.nanos),
+                name: measurement.name,
+                after: measurement.nanos,
+            })
+            .collect();
+
+        Self {
+            comparisons,
+            failure,
+        }
+    }
+
+    /// Renders the numbers as a markdown table for the reviewer.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        if !self.comparisons.is_empty() {
+            out.push_str(
+                "| Benchmark | Base | Branch | Change |\n|-----------|------|--------|--------|\n",
+            );
+            for comparison in &self.comparisons {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    comparison.name,
+                    comparison
+                        .before
+                        .map_or_else(|| "-".to_string(), format_nanos),
+                    format_nanos(comparison.after),
+                    comparison
+                        .change_percent()
+                        .map_or_else(|| "new".to_string(), |change| format!("{:+.1}%", change))
+                ));
+            }
+        }
+        if let Some(failure) = &self.failure {
+            out.push_str(&format!(
+                "`{}` failed on the branch:\n\n```\n{}\n```\n",
+                failure.command, failure.output
+            ));
+        }
+        if out.is_empty() {
+            out.push_str("The benchmarks reported no results.\n");
+        }
+        out
+    }
+}
+
+/// Extracts benchmark times from the output of libtest's `cargo bench`,
+/// Criterion, or hyperfine.
+pub fn parse_benchmarks(output: &str) -> Vec<Measurement> {
+    let mut measurements = Vec::new();
+    let mut previous = "";
+    let mut hyperfine_name = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+
+        if let Some(rest) = trimmed.strip_prefix("Benchmark ") {
+            // hyperfine: "Benchmark 1: <command>"
+            hyperfine_name = rest
+                .split_once(": ")
+                .map(|(_, name)| name.trim().to_string());
+        } else if trimmed.starts_with("Time (") {
+            // hyperfine: "Time (mean ± σ):     12.3 ms ±   0.4 ms"
+            let time = trimmed.split_once("):").and_then(|(_, rest)| {
+                let mut tokens = rest.split_whitespace();
+                to_nanos(tokens.next()?, tokens.next()?)
+            });
+            if let Some((name, nanos)) = hyperfine_name.take().zip(time) {
+                measurements.push(Measurement { name, nanos });
+            }
+        } else if let Some((name, rest)) = trimmed
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... bench:"))
+        {
+            // libtest: "test parse ... bench:       1,234 ns/iter (+/- 56)"
+            let mut tokens = rest.split_whitespace();
+            let time = tokens.next().zip(tokens.next()).and_then(|(value, unit)| {
+                to_nanos(&value.replace(',', ""), unit.strip_suffix("/iter")?)
+            });
+            if let Some(nanos) = time {
+                measurements.push(Measurement {
+                    name: name.trim().to_string(),
+                    nanos,
+                });
+            }
+        } else if let Some((name, rest)) = trimmed.split_once("time:") {
+            // Criterion: "parse   time:   [1.19 ms 1.21 ms 1.23 ms]", with
+            // long names on the line before
+            let name = match name.trim() {
+                "" => previous,
+                name => name,
+            };
+            let estimates: Vec<&str> = rest
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split_whitespace()
+                .collect();
+            if let [_, _, value, unit, ..] = estimates.as_slice() {
+                if let Some(nanos) = to_nanos(value, unit) {
+                    measurements.push(Measurement {
+                        name: name.to_string(),
+                        nanos,
+                    });
+                }
+            }
+        }
+
+        if !trimmed.is_empty() {
+            previous = trimmed;
+        }
+    }
+
+    measurements
+}
+
+/// Converts `value` in `unit` to nanoseconds.
+fn to_nanos(value: &str, unit: &str) -> Option<f64> {
+    let value: f64 = value.parse().ok()?;
+    let scale = match unit {
+        "ps" => 0.001,
+        "ns" => 1.0,
+        "µs" | "us" => 1_000.0,
+        "ms" => 1_000_000.0,
+        "s" => 1_000_000_000.0,
+        _ => return None,
+    };
+    Some(value * scale)
+}
+
+/// Formats nanoseconds in the largest unit that keeps the number above 1.
+fn format_nanos(nanos: f64) -> String {
+    if nanos >= 1_000_000_000.0 {
+        format!("{:.2} s", nanos / 1_000_000_000.0)
+    } else if nanos >= 1_000_000.0 {
+        format!("{:.2} ms", nanos / 1_000_000.0)
+    } else if nanos >= 1_000.0 {
+        format!("{:.2} µs", nanos / 1_000.0)
+    } else {
+        format!("{:.1} ns", nanos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parses_libtest_criterion_and_hyperfine() {
+        let output = "\
+running 2 tests
+test parse_small ... bench:       1,234 ns/iter (+/- 56)
+test parse_large ... bench:      12,500.50 ns/iter (+/- 80)
+
+parse/json              time:   [1.1900 ms 1.2100 ms 1.2300 ms]
+parse/a_very_long_benchmark_name
+                        time:   [850.00 µs 900.00 µs 950.00 µs]
+                        change: [-2.1% +0.4% +3.0%] (p = 0.71 > 0.05)
+
+Benchmark 1: ./target/release/app --check
+  Time (mean ± σ):      2.512 s ±  0.031 s    [User: 2.4 s, System: 0.1 s]
+  Range (min … max):    2.470 s …  2.560 s    10 runs
+";
+
+        let measurements = parse_benchmarks(output);
+
+        let expected = [
+            ("parse_small", 1_234.0),
+            ("parse_large", 12_500.5),
+            ("parse/json", 1_210_000.0),
+            ("parse/a_very_long_benchmark_name", 900_000.0),
+            ("./target/release/app --check", 2_512_000_000.0),
+        ];
+        assert_eq!(measurements.len(), expected.len());
+        for (measurement, (name, nanos)) in measurements.iter().zip(expected) {
+            assert_eq!(measurement.name, name);
+            assert!(
+                (measurement.nanos - nanos).abs() < 0.01,
+                "{:?}",
+                measurement
+            );
+        }
+    }
+
+    #[test]
+    fn regressions_above_threshold_become_findings() {
+        let measure = |name: &str, nanos| Measurement {
+            name: name.to_string(),
+            nanos,
+        };
+        let before = [measure("parse", 1_000_000.0), measure("render", 2_000.0)];
+        let report = BenchmarkReport::new(
+            &before,
+            Ok(vec![
+                measure("parse", 1_250_000.0),
+                measure("render", 2_100.0),
+                measure("export", 500.0),
+            ]),
+        );
+
+        let findings = BenchmarkConfig::default().regressions(&report);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].file, "(benchmark: parse)");
+        assert_eq!(findings[0].severity, ReviewSeverity::High);
+        assert!(findings[0]
+            .issue
+            .contains("25.0% slower than the base (1.00 ms -> 1.25 ms)"));
+        let table = report.to_markdown();
+        assert!(table.contains("| parse | 1.00 ms | 1.25 ms | +25.0% |"));
+        assert!(table.contains("| render | 2.00 µs | 2.10 µs | +5.0% |"));
+        assert!(table.contains("| export | - | 500.0 ns | new |"));
+    }
+
+    #[test]
+    fn benchmarks_base_commit_and_reports_failures() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path();
+        let run = |args: &[&str]| SystemGit.run(repo, args).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "test@example.com"]);
+        run(&["config", "user.name", "Test"]);
+        std::fs::write(
+            repo.join("bench.txt"),
+            "test parse ... bench: 100 ns/iter\n",
+        )
+        .unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "base"]);
+        std::fs::write(
+            repo.join("bench.txt"),
+            "test parse ... bench: 150 ns/iter\n",
+        )
+        .unwrap();
+
+        let config = BenchmarkConfig {
+            commands: vec!["cat bench.txt".to_string()],
+            ..Default::default()
+        };
+        let manifest = SandboxManifest::default();
+        let before = config.run_at(repo, "HEAD", &manifest).unwrap();
+        let report = BenchmarkReport::new(&before, config.run(repo, &manifest));
+
+        assert_eq!(report.comparisons[0].before, Some(100.0));
+        assert_eq!(report.comparisons[0].change_percent(), Some(50.0));
+        assert_eq!(config.regressions(&report).len(), 1);
+
+        let failing = BenchmarkConfig {
+            commands: vec!["echo 'error: bench failed' >&2; exit 101".to_string()],
+            ..Default::default()
+        };
+        let report = BenchmarkReport::new(&before, failing.run(repo, &manifest));
+        let findings = failing.regressions(&report);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].issue.contains("error: bench failed"));
+        assert!(failing.run_at(repo, "HEAD", &manifest).unwrap().is_empty());
+    }
+}
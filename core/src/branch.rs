@@ -0,0 +1,374 @@
+//! Branch naming.
+//!
+//! Every branch the drive creates — sandbox branches, cruise artifact and
+//! base branches, and branches preserving cancelled or incomplete work — is
+//! named here, so spawner, cruise, PR and cleanup code always agree on what a
+//! branch is called and where its sandbox directory lives.
+//!
+//! Sandbox branches come from a template with these placeholders:
+//!
+//! | Placeholder   | Value                                           |
+//! |---------------|-------------------------------------------------|
+//! | `{timestamp}` | Seconds since the Unix epoch                    |
+//! | `{id}`        | Counter, unique within the process              |
+//! | `{uuid}`      | First 8 hex digits of a random UUID             |
+//! | `{slug}`      | Keywords of the task hint (task id or prompt)   |
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Validate, ValidationResult};
+
+/// Default template for sandbox branches.
+pub const DEFAULT_SANDBOX_TEMPLATE: &str = "spawn-sandbox-{timestamp}-{id}";
+
+/// Default prefix for recorded cruise task outputs.
+pub const DEFAULT_ARTIFACT_PREFIX: &str = "cruise/artifacts/";
+
+/// Default prefix for merged cruise dependency bases.
+pub const DEFAULT_BASE_PREFIX: &str = "cruise/base/";
+
+/// Default prefix for branches preserving cancelled work.
+pub const DEFAULT_CANCELLED_PREFIX: &str = "improbability-drive/cancelled/";
+
+/// Default prefix for branches holding work from failed or timed-out spawns.
+pub const DEFAULT_WIP_PREFIX: &str = "wip/";
+
+/// Longest slug substituted for `{slug}`.
+const MAX_SLUG_LEN: usize = 40;
+
+/// Filler words left out of slugs.
+const SLUG_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "be", "can", "for", "from", "in", "into", "is", "it", "make", "of",
+    "on", "or", "please", "should", "so", "that", "the", "this", "to", "with", "you",
+];
+
+/// Generates branch names from configurable templates and prefixes.
+///
+/// Clones share the `{id}` counter, so names stay unique across every
+/// provider and orchestrator built from the same settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchNamer {
+    /// Template for sandbox branches.
+    #[serde(default = "default_sandbox_template")]
+    pub sandbox_template: String,
+
+    /// Prefix for cruise artifact branches.
+    #[serde(default = "default_artifact_prefix")]
+    pub artifact_prefix: String,
+
+    /// Prefix for cruise dependency base branches.
+    #[serde(default = "default_base_prefix")]
+    pub base_prefix: String,
+
+    /// Prefix for branches preserving cancelled work.
+    #[serde(default = "default_cancelled_prefix")]
+    pub cancelled_prefix: String,
+
+    /// Prefix for branches holding work from failed or timed-out spawns.
+    #[serde(default = "default_wip_prefix")]
+    pub wip_prefix: String,
+
+    #[serde(skip)]
+    counter: Arc<AtomicU64>,
+}
+
+fn default_sandbox_template() -> String {
+    DEFAULT_SANDBOX_TEMPLATE.to_string()
+}
+
+fn default_artifact_prefix() -> String {
+    DEFAULT_ARTIFACT_PREFIX.to_string()
+}
+
+fn default_base_prefix() -> String {
+    DEFAULT_BASE_PREFIX.to_string()
+}
+
+fn default_cancelled_prefix() -> String {
+    DEFAULT_CANCELLED_PREFIX.to_string()
+}
+
+fn default_wip_prefix() -> String {
+    DEFAULT_WIP_PREFIX.to_string()
+}
+
+impl Default for BranchNamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BranchNamer {
+    /// Creates a namer with the default template and prefixes.
+    pub fn new() -> Self {
+        Self {
+            sandbox_template: default_sandbox_template(),
+            artifact_prefix: default_artifact_prefix(),
+            base_prefix: default_base_prefix(),
+            cancelled_prefix: default_cancelled_prefix(),
+            wip_prefix: default_wip_prefix(),
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Sets the sandbox branch template.
+    pub fn with_sandbox_template(mut self, template: impl Into<String>) -> Self {
+        self.sandbox_template = template.into();
+        self
+    }
+
+    /// Returns a new, unique sandbox branch name.
+    pub fn sandbox_branch(&self) -> String {
+        self.sandbox_branch_for("")
+    }
+
+    /// Returns a new, unique sandbox branch name with `{slug}` taken from
+    /// `hint`.
+    pub fn sandbox_branch_for(&self, hint: &str) -> String {
+        let id = self.counter.fetch_add(1, Ordering::SeqCst);
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let uuid = uuid::Uuid::new_v4().simple().to_string();
+
+        self.render(
+            &timestamp.to_string(),
+            &id.to_string(),
+            &uuid[..8],
+            &slugify(hint),
+        )
+    }
+
+    /// Returns the sandbox branch name with placeholders left visible, for
+    /// plans that describe a branch before it exists.
+    pub fn sandbox_placeholder(&self) -> String {
+        self.render("<timestamp>", "<id>", "<uuid>", "<slug>")
+    }
+
+    /// Returns the branch recording `task_id`'s output.
+    pub fn artifact_branch(&self, task_id: &str) -> String {
+        format!("{}{}", self.artifact_prefix, task_id)
+    }
+
+    /// Returns the branch merging `task_id`'s dependencies.
+    pub fn base_branch(&self, task_id: &str) -> String {
+        format!("{}{}", self.base_prefix, task_id)
+    }
+
+    /// Returns a new branch name for preserving cancelled work.
+    pub fn cancelled_branch(&self) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        format!("{}{}", self.cancelled_prefix, &id[..8])
+    }
+
+    /// Returns a new branch for the partial work of a spawn that failed or
+    /// timed out, with a slug of `hint`.
+    pub fn wip_branch(&self, hint: &str) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        format!("{}{}-{}", self.wip_prefix, slugify(hint), &id[..8])
+    }
+
+    /// Returns true if `branch` is one of this namer's cancelled branches.
+    pub fn is_cancelled_branch(&self, branch: &str) -> bool {
+        branch.starts_with(&self.cancelled_prefix)
+    }
+
+    /// Returns true if `branch` looks like one this namer creates: a sandbox
+    /// branch (matched on the template's fixed prefix) or a prefixed branch.
+    pub fn is_drive_branch(&self, branch: &str) -> bool {
+        let sandbox_prefix = self.sandbox_template.split('{').next().unwrap_or_default();
+        [
+            sandbox_prefix,
+            &self.artifact_prefix,
+            &self.base_prefix,
+            &self.cancelled_prefix,
+            &self.wip_prefix,
+        ]
+        .iter()
+        .any(|prefix| !prefix.is_empty() && branch.starts_with(prefix))
+    }
+
+    /// Returns the directory name for a branch's sandbox.
+    ///
+    /// Branch names may contain `/`; directory names may not.
+    pub fn dir_name(branch: &str) -> String {
+        branch.replace('/', "-")
+    }
+
+    fn render(&self, timestamp: &str, id: &str, uuid: &str, slug: &str) -> String {
+        self.sandbox_template
+            .replace("{timestamp}", timestamp)
+            .replace("{id}", id)
+            .replace("{uuid}", uuid)
+            .replace("{slug}", slug)
+    }
+}
+
+impl Validate for BranchNamer {
+    fn validate(&self) -> ValidationResult {
+        let mut result = ValidationResult::default();
+
+        if !self.sandbox_template.contains("{id}") && !self.sandbox_template.contains("{uuid}") {
+            result.add_error("branches.sandbox_template must contain {id} or {uuid}");
+        }
+
+        let names = [
+            ("branches.sandbox_template", self.sandbox_placeholder()),
+            ("branches.artifact_prefix", self.artifact_prefix.clone()),
+            ("branches.base_prefix", self.base_prefix.clone()),
+            ("branches.cancelled_prefix", self.cancelled_prefix.clone()),
+            ("branches.wip_prefix", self.wip_prefix.clone()),
+        ];
+        for (field, name) in names {
+            if !is_valid_ref_part(&name) {
+                result.add_error(format!("{} '{}' is not a valid branch name", field, name));
+            }
+        }
+
+        result
+    }
+}
+
+/// Joins the lowercased keywords of `hint` with `-`, leaving out filler
+/// words and stopping at a word boundary before [`MAX_SLUG_LEN`].
+fn slugify(hint: &str) -> String {
+    let mut slug = String::new();
+    let words = hint
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .filter(|word| !SLUG_STOPWORDS.contains(&word.as_str()));
+    for word in words {
+        if !slug.is_empty() {
+            if slug.len() + 1 + word.len() > MAX_SLUG_LEN {
+                break;
+            }
+            slug.push('-');
+        }
+        slug.push_str(&word);
+    }
+    slug.truncate(MAX_SLUG_LEN);
+    if slug.is_empty() {
+        "task".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Checks the parts of `git check-ref-format` that templates can get wrong.
+fn is_valid_ref_part(name: &str) -> bool {
+    // Placeholders rendered for display are replaced before use
+    let name = name.replace(['<', '>'], "");
+    !name.is_empty()
+        && !name.starts_with('/')
+        && !name.starts_with('-')
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "~^:?*[\\".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_sandbox_branches_keep_legacy_format() {
+        let namer = BranchNamer::new();
+
+        let first = namer.sandbox_branch();
+        let second = namer.sandbox_branch();
+
+        assert!(first.starts_with("spawn-sandbox-"));
+        assert!(first.ends_with("-0"));
+        assert!(second.ends_with("-1"));
+    }
+
+    #[test]
+    fn clones_share_the_counter() {
+        let namer = BranchNamer::new().with_sandbox_template("drive/{id}");
+        let clone = namer.clone();
+
+        assert_eq!(namer.sandbox_branch(), "drive/0");
+        assert_eq!(clone.sandbox_branch(), "drive/1");
+    }
+
+    #[test]
+    fn slug_is_derived_from_hint() {
+        let namer = BranchNamer::new().with_sandbox_template("drive/{slug}-{id}");
+
+        assert_eq!(
+            namer.sandbox_branch_for("Add OAuth login (CRUISE-001)!"),
+            "drive/add-oauth-login-cruise-001-0"
+        );
+        assert_eq!(namer.sandbox_branch_for("***"), "drive/task-1");
+    }
+
+    #[test]
+    fn slug_keeps_keywords_and_whole_words() {
+        assert_eq!(
+            slugify("Please add a login page to the app"),
+            "add-login-page-app"
+        );
+        assert_eq!(
+            slugify("Refactor the configuration loader and validation of profiles"),
+            "refactor-configuration-loader-validation"
+        );
+        assert_eq!(slugify(&"x".repeat(50)).len(), MAX_SLUG_LEN);
+        assert_eq!(slugify("the and of"), "task");
+    }
+
+    #[test]
+    fn prefixed_branches_and_dir_names() {
+        let namer = BranchNamer::new();
+
+        assert_eq!(
+            namer.artifact_branch("CRUISE-001"),
+            "cruise/artifacts/CRUISE-001"
+        );
+        assert_eq!(namer.base_branch("CRUISE-003"), "cruise/base/CRUISE-003");
+        assert!(namer.is_cancelled_branch(&namer.cancelled_branch()));
+        assert!(namer.wip_branch("Fix login").starts_with("wip/fix-login-"));
+        assert!(namer.is_drive_branch(&namer.sandbox_branch()));
+        assert!(namer.is_drive_branch(&namer.artifact_branch("CRUISE-001")));
+        assert!(!namer.is_drive_branch("feature/login"));
+        assert_eq!(
+            BranchNamer::dir_name("drive/add-login-0"),
+            "drive-add-login-0"
+        );
+    }
+
+    #[test]
+    fn placeholder_shows_template_fields() {
+        assert_eq!(
+            BranchNamer::new().sandbox_placeholder(),
+            "spawn-sandbox-<timestamp>-<id>"
+        );
+    }
+
+    #[test]
+    fn validation_rejects_non_unique_or_invalid_templates() {
+        assert!(BranchNamer::new().validate().is_valid());
+        assert!(!BranchNamer::new()
+            .with_sandbox_template("drive/{slug}")
+            .validate()
+            .is_valid());
+        assert!(!BranchNamer::new()
+            .with_sandbox_template("drive {id}")
+            .validate()
+            .is_valid());
+    }
+
+    #[test]
+    fn deserializes_with_defaults() {
+        let namer: BranchNamer = toml::from_str(r#"sandbox_template = "ai/{uuid}""#).unwrap();
+
+        assert_eq!(namer.sandbox_template, "ai/{uuid}");
+        assert_eq!(namer.artifact_prefix, DEFAULT_ARTIFACT_PREFIX);
+    }
+}
@@ -0,0 +1,564 @@
+//! Graceful cancellation of in-progress runs.
+//!
+//! A [`CancellationToken`] is shared by everything working on one run.
+//! Cancelling it (from Ctrl-C or an API call) makes the watcher, review
+//! loop, and approval poller stop and drop their LLM invocations, which
+//! kills the child processes, and stops the spawner starting new sandboxes. The caller then uses [`wind_down`] to keep
+//! the partial work: commit and push it, mark the PR, and write a
+//! [`Checkpoint`] the run can be resumed from.
+//!
+//! Spawns that fail or time out can keep their work too: [`open_partial_pr`]
+//! pushes it to a `wip/` branch behind a draft PR labelled `incomplete`.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::branch::{BranchNamer, DEFAULT_CANCELLED_PREFIX};
+use crate::commit::{CommitContext, CommitMessages};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{pr_title, PRManager, PullRequest};
+
+/// Directory, relative to the repository, checkpoints are written to.
+pub const CHECKPOINT_DIR: &str = ".improbability-drive/checkpoints";
+
+/// Branch prefix for partial work saved from cancelled sandboxes.
+pub const CANCELLED_BRANCH_PREFIX: &str = DEFAULT_CANCELLED_PREFIX;
+
+/// Label on draft PRs holding work from failed or timed-out spawns.
+pub const INCOMPLETE_LABEL: &str = "incomplete";
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    reason: Mutex<Option<String>>,
+    notify: Notify,
+}
+
+/// Shared flag that requests a run to stop.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl CancellationToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation. Only the first reason is kept.
+    pub fn cancel(&self, reason: impl Into<String>) {
+        {
+            let mut current = self.state.reason.lock().unwrap();
+            if current.is_none() {
+                *current = Some(reason.into());
+            }
+        }
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    /// Returns whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the reason given when the token was cancelled.
+    pub fn reason(&self) -> Option<String> {
+        self.state.reason.lock().unwrap().clone()
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Returns [`Error::Cancelled`] if the token has been cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(self.error());
+        }
+        Ok(())
+    }
+
+    /// Returns the error reported by work stopped by this token.
+    pub fn error(&self) -> Error {
+        Error::Cancelled(self.reason().unwrap_or_else(|| "cancelled".to_string()))
+    }
+
+    /// Cancels the token when the process receives Ctrl-C.
+    pub fn cancel_on_ctrl_c(&self) -> JoinHandle<()> {
+        let token = self.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                tracing::warn!("received Ctrl-C, cancelling run");
+                token.cancel("interrupted");
+            }
+        })
+    }
+}
+
+/// State saved when a run is cancelled, enough to resume it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Identifier of the cancelled run.
+    pub run_id: String,
+    /// Phase the run was in (e.g., "primary", "review:security", "building").
+    pub phase: String,
+    /// The run's original prompt.
+    pub prompt: String,
+    /// Branch holding the partial work.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Commit of the partial work, if anything was committed.
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Whether the branch was pushed.
+    #[serde(default)]
+    pub pushed: bool,
+    /// PR for the run, if one was opened.
+    #[serde(default)]
+    pub pr_url: Option<String>,
+    /// Tasks or review domains that finished before cancellation.
+    #[serde(default)]
+    pub completed: Vec<String>,
+    /// Why the run was cancelled.
+    pub reason: String,
+    /// When the run was cancelled, in seconds since the Unix epoch.
+    pub cancelled_at: u64,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint for a run cancelled now.
+    pub fn new(
+        run_id: impl Into<String>,
+        phase: impl Into<String>,
+        prompt: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            run_id: run_id.into(),
+            phase: phase.into(),
+            prompt: prompt.into(),
+            branch: None,
+            commit: None,
+            pushed: false,
+            pr_url: None,
+            completed: Vec::new(),
+            reason: reason.into(),
+            cancelled_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Records the units of work that completed.
+    pub fn with_completed(mut self, completed: Vec<String>) -> Self {
+        self.completed = completed;
+        self
+    }
+
+    /// Returns where this checkpoint is stored under `repo`.
+    pub fn path(&self, repo: &Path) -> PathBuf {
+        repo.join(CHECKPOINT_DIR)
+            .join(format!("{}.json", self.run_id))
+    }
+
+    /// Writes the checkpoint under `repo`, returning its path.
+    pub fn save(&self, repo: &Path) -> Result<PathBuf> {
+        let path = self.path(repo);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("failed to serialize checkpoint: {}", e)))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// Loads the checkpoint for `run_id` from `repo`.
+    pub fn load(repo: &Path, run_id: &str) -> Result<Self> {
+        let path = repo.join(CHECKPOINT_DIR).join(format!("{}.json", run_id));
+        let json = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::Config(format!("invalid checkpoint {}: {}", path.display(), e)))
+    }
+}
+
+/// Commits work left in a cancelled sandbox and keeps it on a branch.
+///
+/// Sandbox cleanup deletes the sandbox branch, so the work is moved to a
+/// branch named by `namer` (under [`CANCELLED_BRANCH_PREFIX`] by default). Returns `None` if the sandbox
+/// has no uncommitted changes and no commits of its own.
+pub fn preserve_partial_work(
+    worktree: &Path,
+    reason: &str,
+    namer: &BranchNamer,
+) -> Result<Option<String>> {
+    let status = SystemGit.run(worktree, &["status", "--porcelain"])?;
+    if !status.trim().is_empty() {
+        SystemGit.run(worktree, &["add", "-A"])?;
+        SystemGit.run(
+            worktree,
+            &["commit", "-m", &format!("WIP: cancelled ({})", reason)],
+        )?;
+    }
+
+    if !has_own_commits(&SystemGit, worktree)? {
+        return Ok(None);
+    }
+
+    let branch = namer.cancelled_branch();
+    SystemGit.run(worktree, &["branch", &branch, "HEAD"])?;
+    tracing::info!(branch = %branch, "saved partial work from cancelled sandbox");
+    Ok(Some(branch))
+}
+
+/// Pushes the work left in a failed or timed-out sandbox to a `wip/` branch
+/// and opens a draft PR labelled [`INCOMPLETE_LABEL`] so it can be salvaged.
+///
+/// Uncommitted changes are committed first, skipping ignored and denied
+/// files, and the branch's history is rewritten following `messages`.
+/// Returns `None` if the sandbox has nothing of its own to keep.
+pub fn open_partial_pr(
+    worktree: &Path,
+    prompt: &str,
+    reason: &str,
+    namer: &BranchNamer,
+    messages: &CommitMessages,
+) -> Result<Option<PullRequest>> {
+    let worktree_path = worktree.to_path_buf();
+    let manager = PRManager::new(worktree_path.clone())
+        .with_draft(true)
+        .with_labels([INCOMPLETE_LABEL])
+        .with_commit_messages(messages.clone());
+    manager.commit_changes(&worktree_path, &format!("WIP: incomplete ({})", reason))?;
+    if !has_own_commits(&SystemGit, worktree)? {
+        return Ok(None);
+    }
+
+    let base = default_branch(&SystemGit, worktree);
+    let title = format!("WIP: {}", pr_title(prompt));
+    if let Err(e) = manager.prepare_for_pr(&worktree_path, &base, &CommitContext::new(&title)) {
+        tracing::warn!(error = %e, "could not rewrite the history of partial work");
+    }
+    let branch = namer.wip_branch(prompt);
+    SystemGit.run(worktree, &["branch", &branch, "HEAD"])?;
+    manager.push_branch(&worktree_path, &branch)?;
+
+    let body = format!(
+        "This spawn stopped before finishing: {}.\n\n\
+         The partial work is kept here so it can be salvaged. \
+         It has not been reviewed and may not build.\n\n\
+         ## Prompt\n\n{}\n",
+        reason, prompt
+    );
+    let pr = manager.create_pr(&title, &body, &branch, &base)?;
+    tracing::info!(branch = %branch, url = %pr.url, "opened draft PR for incomplete spawn");
+    Ok(Some(pr))
+}
+
+/// Returns whether the checked-out branch has commits no other branch has.
+fn has_own_commits(git: &dyn GitExecutor, worktree: &Path) -> Result<bool> {
+    let head_branch = git.run(worktree, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let exclude = format!("--exclude={}", head_branch.trim());
+    let own = git.run(
+        worktree,
+        &["rev-list", "HEAD", "--not", &exclude, "--branches"],
+    )?;
+    Ok(!own.trim().is_empty())
+}
+
+/// Returns the remote's default branch, or `main` if it is unknown.
+fn default_branch(git: &dyn GitExecutor, worktree: &Path) -> String {
+    git.run(
+        worktree,
+        &["symbolic-ref", "--short", "refs/remotes/origin/HEAD"],
+    )
+    .ok()
+    .and_then(|head| head.trim().strip_prefix("origin/").map(str::to_string))
+    .unwrap_or_else(|| "main".to_string())
+}
+
+/// Preserves the partial work of a cancelled run.
+///
+/// Commits anything uncommitted in `worktree`, pushes `branch` if given,
+/// comments on `pr`, and saves `checkpoint` under the manager's repository.
+/// Push and PR failures are logged rather than returned so the checkpoint is
+/// always written.
+pub fn wind_down(
+    manager: &PRManager,
+    worktree: &Path,
+    branch: Option<&str>,
+    pr: Option<&PullRequest>,
+    mut checkpoint: Checkpoint,
+) -> Result<Checkpoint> {
+    let message = format!(
+        "WIP: cancelled during {} ({})",
+        checkpoint.phase, checkpoint.reason
+    );
+    checkpoint.commit = manager.commit_changes(worktree, &message)?;
+    checkpoint.branch = branch.map(str::to_string);
+
+    if let Some(branch) = branch {
+        match manager.push_branch(worktree, branch) {
+            Ok(()) => checkpoint.pushed = true,
+            Err(e) => tracing::warn!(branch = %branch, error = %e, "failed to push partial work"),
+        }
+    }
+
+    if let Some(pr) = pr {
+        checkpoint.pr_url = Some(pr.url.clone());
+        if let Err(e) = manager.mark_cancelled(pr, &checkpoint) {
+            tracing::warn!(pr = pr.number, error = %e, "failed to mark PR cancelled");
+        }
+    }
+
+    let path = checkpoint.save(manager.repo_path())?;
+    tracing::info!(
+        run_id = %checkpoint.run_id,
+        path = %path.display(),
+        "wrote resumable checkpoint"
+    );
+    Ok(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{GitOutput, MockGit};
+    use std::process::Command;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn create_temp_git_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .current_dir(dir.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        run(&["init"]);
+        run(&["config", "user.email", "test@test.com"]);
+        run(&["config", "user.name", "Test"]);
+        std::fs::write(dir.path().join("README.md"), "# Test").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-m", "Initial commit"]);
+        dir
+    }
+
+    #[tokio::test]
+    async fn cancel_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!token.is_cancelled());
+        token.cancel("api request");
+
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(token.check().is_err());
+    }
+
+    #[test]
+    fn first_reason_is_kept() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+
+        token.cancel("interrupted");
+        token.cancel("api request");
+
+        assert_eq!(token.reason().as_deref(), Some("interrupted"));
+        assert!(token.error().to_string().contains("interrupted"));
+    }
+
+    #[tokio::test]
+    async fn already_cancelled_token_returns_immediately() {
+        let token = CancellationToken::new();
+        token.cancel("done");
+
+        tokio::time::timeout(Duration::from_millis(100), token.cancelled())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn checkpoint_round_trips() {
+        let repo = TempDir::new().unwrap();
+        let checkpoint = Checkpoint::new("run-1", "review:security", "Add auth", "interrupted")
+            .with_completed(vec!["primary".to_string()]);
+
+        let path = checkpoint.save(repo.path()).unwrap();
+
+        assert!(path.ends_with("checkpoints/run-1.json"));
+        assert_eq!(Checkpoint::load(repo.path(), "run-1").unwrap(), checkpoint);
+    }
+
+    #[test]
+    fn preserve_partial_work_keeps_sandbox_changes() {
+        let repo = create_temp_git_repo();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .current_dir(repo.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        run(&["checkout", "-b", "sandbox"]);
+
+        // Nothing new on the sandbox branch
+        assert_eq!(
+            preserve_partial_work(repo.path(), "interrupted", &BranchNamer::new()).unwrap(),
+            None
+        );
+
+        std::fs::write(repo.path().join("partial.rs"), "fn half() {}").unwrap();
+        let branch = preserve_partial_work(repo.path(), "interrupted", &BranchNamer::new())
+            .unwrap()
+            .unwrap();
+
+        assert!(branch.starts_with(CANCELLED_BRANCH_PREFIX));
+        let show = run(&["show", "--name-only", "--format=%s", &branch]);
+        let show = String::from_utf8_lossy(&show.stdout);
+        assert!(show.starts_with("WIP: cancelled (interrupted)"));
+        assert!(show.contains("partial.rs"));
+    }
+
+    #[test]
+    fn open_partial_pr_pushes_work_to_wip_branch() {
+        let repo = create_temp_git_repo();
+        let remote = TempDir::new().unwrap();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .current_dir(repo.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        Command::new("git")
+            .args(["init", "--bare", "-q"])
+            .arg(remote.path())
+            .output()
+            .unwrap();
+        run(&["remote", "add", "origin", remote.path().to_str().unwrap()]);
+        run(&["checkout", "-b", "sandbox"]);
+        let namer = BranchNamer::new();
+
+        // Nothing new on the sandbox branch
+        let pr = open_partial_pr(
+            repo.path(),
+            "Add login",
+            "timed out",
+            &namer,
+            &CommitMessages::default(),
+        )
+        .unwrap();
+        assert!(pr.is_none());
+
+        // The push succeeds; opening the PR fails without a GitHub remote
+        std::fs::write(repo.path().join("partial.rs"), "fn half() {}").unwrap();
+        assert!(open_partial_pr(
+            repo.path(),
+            "Add login",
+            "timed out",
+            &namer,
+            &CommitMessages::default(),
+        )
+        .is_err());
+
+        let branches = Command::new("git")
+            .current_dir(remote.path())
+            .args(["branch", "--list", "wip/*"])
+            .output()
+            .unwrap();
+        let branches = String::from_utf8_lossy(&branches.stdout);
+        assert!(
+            branches.trim().starts_with("wip/add-login-"),
+            "{}",
+            branches
+        );
+    }
+
+    #[test]
+    fn wind_down_commits_partial_work_and_saves_checkpoint() {
+        let repo = create_temp_git_repo();
+        std::fs::write(repo.path().join("partial.rs"), "fn half() {}").unwrap();
+        let manager = PRManager::new(repo.path().to_path_buf());
+        let checkpoint = Checkpoint::new("run-2", "primary", "Add feature", "interrupted");
+
+        let checkpoint = wind_down(&manager, repo.path(), None, None, checkpoint).unwrap();
+
+        assert!(checkpoint.commit.is_some());
+        assert!(!checkpoint.pushed);
+        let log = Command::new("git")
+            .current_dir(repo.path())
+            .args(["log", "-1", "--format=%s"])
+            .output()
+            .unwrap();
+        assert!(String::from_utf8_lossy(&log.stdout).starts_with("WIP: cancelled during primary"));
+        assert_eq!(Checkpoint::load(repo.path(), "run-2").unwrap(), checkpoint);
+    }
+
+    #[test]
+    fn own_commits_exclude_the_checked_out_branch_only() {
+        let worktree = Path::new("/sandbox");
+        let git = MockGit::new()
+            .on(
+                &["rev-parse", "--abbrev-ref", "HEAD"],
+                GitOutput::ok("spawn-sandbox-1\n"),
+            )
+            .on(&["rev-list"], GitOutput::ok("abc123\n"));
+
+        assert!(has_own_commits(&git, worktree).unwrap());
+        assert_eq!(
+            git.calls()[1],
+            [
+                "rev-list",
+                "HEAD",
+                "--not",
+                "--exclude=spawn-sandbox-1",
+                "--branches"
+            ]
+        );
+
+        let merged = MockGit::new()
+            .on(&["rev-parse"], GitOutput::ok("spawn-sandbox-1\n"))
+            .on(&["rev-list"], GitOutput::ok(""));
+        assert!(!has_own_commits(&merged, worktree).unwrap());
+    }
+
+    #[test]
+    fn default_branch_falls_back_to_main() {
+        let worktree = Path::new("/sandbox");
+        let known = MockGit::new().on(&["symbolic-ref"], GitOutput::ok("origin/trunk\n"));
+        assert_eq!(default_branch(&known, worktree), "trunk");
+
+        let unknown = MockGit::new().on(
+            &["symbolic-ref"],
+            GitOutput::failed(128, "not a symbolic ref"),
+        );
+        assert_eq!(default_branch(&unknown, worktree), "main");
+    }
+}
@@ -0,0 +1,626 @@
+//! GitHub check runs and their annotations.
+//!
+//! CI reports problems as check runs on a commit. A failing run often
+//! carries annotations pointing at the file and line at fault (compiler
+//! errors, lint findings, failed assertions), which can be handed to a
+//! fixer the same way review findings are. [`CiWatcher`] waits for the
+//! checks on a pushed commit and gathers what failed, including a summary
+//! of each failed job's log.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result, ResultExt};
+use crate::team::{ReviewSeverity, ReviewSuggestion};
+
+/// Check run conclusions that count as failures.
+const FAILED_CONCLUSIONS: &[&str] = &[
+    "failure",
+    "timed_out",
+    "cancelled",
+    "action_required",
+    "startup_failure",
+];
+
+/// Log lines after an error line kept for context.
+const LOG_CONTEXT_LINES: usize = 2;
+
+/// Markers of log lines that report an error.
+const LOG_ERROR_MARKERS: &[&str] = &["error", "failed", "failure", "panicked", "fatal"];
+
+/// Settings for fixing CI failures after a push.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiConfig {
+    /// Fix rounds run for failing checks; `0` turns the loop off.
+    #[serde(default = "default_max_fix_rounds")]
+    pub max_fix_rounds: u32,
+    /// Seconds between polls of the check runs.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+    /// Seconds to wait for the checks to finish.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Seconds to wait for the first check run; commits without any pass.
+    #[serde(default = "default_checks_grace")]
+    pub checks_grace: u64,
+    /// Lines of a failed job's log kept in its summary.
+    #[serde(default = "default_max_log_lines")]
+    pub max_log_lines: usize,
+}
+
+fn default_max_fix_rounds() -> u32 {
+    2
+}
+
+fn default_poll_interval() -> u64 {
+    30
+}
+
+fn default_timeout() -> u64 {
+    1800
+}
+
+fn default_checks_grace() -> u64 {
+    60
+}
+
+fn default_max_log_lines() -> usize {
+    40
+}
+
+impl Default for CiConfig {
+    fn default() -> Self {
+        Self {
+            max_fix_rounds: default_max_fix_rounds(),
+            poll_interval: default_poll_interval(),
+            timeout: default_timeout(),
+            checks_grace: default_checks_grace(),
+            max_log_lines: default_max_log_lines(),
+        }
+    }
+}
+
+/// A check run on a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckRun {
+    /// Check run ID.
+    pub id: u64,
+    /// Check name, e.g. `build (ubuntu-latest)`.
+    pub name: String,
+    /// `queued`, `in_progress`, or `completed`.
+    pub status: String,
+    /// Outcome once completed, e.g. `success` or `failure`.
+    pub conclusion: Option<String>,
+    /// Link to the run's page.
+    pub url: String,
+}
+
+impl CheckRun {
+    /// Returns whether the run has finished.
+    pub fn is_completed(&self) -> bool {
+        self.status == "completed"
+    }
+
+    /// Returns whether the run finished unsuccessfully.
+    pub fn is_failure(&self) -> bool {
+        self.conclusion
+            .as_deref()
+            .is_some_and(|c| FAILED_CONCLUSIONS.contains(&c))
+    }
+}
+
+/// A problem a check run pointed at in the code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckAnnotation {
+    /// Name of the check that reported it.
+    pub check: String,
+    /// File the annotation is on.
+    pub path: String,
+    /// First line of the annotated range.
+    pub line: Option<u32>,
+    /// `notice`, `warning`, or `failure`.
+    pub level: String,
+    /// Short title, if the check gave one.
+    pub title: String,
+    /// What went wrong.
+    pub message: String,
+}
+
+impl CheckAnnotation {
+    /// Returns whether the annotation reports a failure.
+    pub fn is_failure(&self) -> bool {
+        self.level == "failure"
+    }
+
+    /// Converts the annotation into a finding for a fix round.
+    pub fn to_suggestion(&self) -> ReviewSuggestion {
+        let issue = if self.title.is_empty() {
+            format!("{} failed: {}", self.check, self.message)
+        } else {
+            format!("{} failed: {}: {}", self.check, self.title, self.message)
+        };
+        ReviewSuggestion {
+            file: self.path.clone(),
+            line: self.line,
+            issue,
+            suggestion: "Make the check pass.".to_string(),
+            severity: if self.is_failure() {
+                ReviewSeverity::High
+            } else {
+                ReviewSeverity::Medium
+            },
+        }
+    }
+}
+
+/// A failed check run and what it reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiFailure {
+    /// The failed run.
+    pub run: CheckRun,
+    /// Its failure annotations.
+    pub annotations: Vec<CheckAnnotation>,
+    /// Error lines from its job log; empty if the log was unavailable.
+    pub summary: String,
+}
+
+impl CiFailure {
+    /// Converts the failure into findings for a fix round: one per
+    /// annotation, plus one for the run carrying the log summary.
+    pub fn suggestions(&self) -> Vec<ReviewSuggestion> {
+        let mut suggestions: Vec<_> = self
+            .annotations
+            .iter()
+            .map(CheckAnnotation::to_suggestion)
+            .collect();
+        if suggestions.is_empty() || !self.summary.is_empty() {
+            let suggestion = if self.summary.is_empty() {
+                format!("Make the check pass; see {}", self.run.url)
+            } else {
+                format!("Make the check pass. Failing log output:\n{}", self.summary)
+            };
+            suggestions.push(ReviewSuggestion {
+                file: format!("(CI: {})", self.run.name),
+                line: None,
+                issue: format!("Check `{}` failed", self.run.name),
+                suggestion,
+                severity: ReviewSeverity::High,
+            });
+        }
+        suggestions
+    }
+}
+
+/// Where the checks on a commit ended up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CiStatus {
+    /// Every check passed.
+    Passed,
+    /// At least one check failed.
+    Failed(Vec<CiFailure>),
+    /// No check ran on the commit.
+    NoChecks,
+    /// Checks were still running when the wait timed out.
+    TimedOut,
+}
+
+/// Summarizes a job log to the lines reporting errors, each with a little
+/// context, keeping at most `max_lines`. Logs without error lines are
+/// summarized by their tail.
+pub fn summarize_log(log: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = log.lines().map(strip_timestamp).collect();
+    let mut keep = vec![false; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        let lower = line.to_lowercase();
+        if LOG_ERROR_MARKERS.iter().any(|m| lower.contains(m)) {
+            let end = (i + LOG_CONTEXT_LINES + 1).min(lines.len());
+            keep[i..end].iter_mut().for_each(|k| *k = true);
+        }
+    }
+
+    let kept: Vec<&str> = if keep.contains(&true) {
+        lines
+            .iter()
+            .zip(&keep)
+            .filter(|(_, keep)| **keep)
+            .map(|(line, _)| *line)
+            .take(max_lines)
+            .collect()
+    } else {
+        lines[lines.len().saturating_sub(max_lines)..].to_vec()
+    };
+    kept.join("\n").trim().to_string()
+}
+
+/// Strips the timestamp GitHub Actions puts before every log line.
+fn strip_timestamp(line: &str) -> &str {
+    match line.split_once(' ') {
+        Some((stamp, rest))
+            if stamp.ends_with('Z')
+                && stamp.contains('T')
+                && stamp.starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            rest
+        }
+        _ => line,
+    }
+}
+
+/// Parses the check runs API response.
+pub fn parse_check_runs(json: &str) -> Result<Vec<CheckRun>> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("check runs", e))?;
+    Ok(json["check_runs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|run| {
+            Some(CheckRun {
+                id: run["id"].as_u64()?,
+                name: run["name"].as_str().unwrap_or_default().to_string(),
+                status: run["status"].as_str().unwrap_or_default().to_string(),
+                conclusion: run["conclusion"].as_str().map(str::to_string),
+                url: run["html_url"].as_str().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Parses the annotations API response for the check named `check`.
+pub fn parse_annotations(check: &str, json: &str) -> Result<Vec<CheckAnnotation>> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("check annotations", e))?;
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    Ok(json
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|annotation| CheckAnnotation {
+            check: check.to_string(),
+            path: text(&annotation["path"]),
+            line: annotation["start_line"].as_u64().map(|line| line as u32),
+            level: text(&annotation["annotation_level"]),
+            title: text(&annotation["title"]),
+            message: text(&annotation["message"]),
+        })
+        .collect())
+}
+
+/// Source of the check runs on commits.
+pub trait CheckSource: Send + Sync {
+    /// Lists the check runs on `commit`.
+    fn check_runs(&self, commit: &str) -> Result<Vec<CheckRun>>;
+
+    /// Lists the annotations of a check run.
+    fn annotations(&self, run: &CheckRun) -> Result<Vec<CheckAnnotation>>;
+
+    /// Returns the log of the job behind a check run.
+    fn job_log(&self, run: &CheckRun) -> Result<String>;
+}
+
+/// Reads check runs through the GitHub API.
+pub struct ChecksClient {
+    /// Repository the `gh` commands run in.
+    repo_path: PathBuf,
+}
+
+impl ChecksClient {
+    /// Creates a client for the repository at `repo_path`.
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+        }
+    }
+
+    /// Lists the check runs on `commit`.
+    pub fn check_runs(&self, commit: &str) -> Result<Vec<CheckRun>> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/commits/{}/check-runs", commit);
+        let json = self
+            .api(&endpoint)
+            .context(format!("failed to list checks of {}", commit))?;
+        parse_check_runs(&json)
+    }
+
+    /// Lists the annotations of a check run.
+    pub fn annotations(&self, run: &CheckRun) -> Result<Vec<CheckAnnotation>> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/check-runs/{}/annotations", run.id);
+        let json = self
+            .api(&endpoint)
+            .context(format!("failed to read annotations of {}", run.name))?;
+        parse_annotations(&run.name, &json)
+    }
+
+    /// Returns the log of the GitHub Actions job behind a check run.
+    pub fn job_log(&self, run: &CheckRun) -> Result<String> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/actions/jobs/{}/logs", run.id);
+        self.api(&endpoint)
+            .context(format!("failed to read the log of {}", run.name))
+    }
+
+    /// Returns the failure annotations of every failed check on `commit`.
+    pub fn failure_annotations(&self, commit: &str) -> Result<Vec<CheckAnnotation>> {
+        let mut annotations = Vec::new();
+        for run in self.check_runs(commit)?.iter().filter(|r| r.is_failure()) {
+            annotations.extend(
+                self.annotations(run)?
+                    .into_iter()
+                    .filter(CheckAnnotation::is_failure),
+            );
+        }
+        Ok(annotations)
+    }
+
+    /// Runs `gh api` on `endpoint`, returning the response body.
+    fn api(&self, endpoint: &str) -> Result<String> {
+        let args = ["api", endpoint];
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl CheckSource for ChecksClient {
+    fn check_runs(&self, commit: &str) -> Result<Vec<CheckRun>> {
+        ChecksClient::check_runs(self, commit)
+    }
+
+    fn annotations(&self, run: &CheckRun) -> Result<Vec<CheckAnnotation>> {
+        ChecksClient::annotations(self, run)
+    }
+
+    fn job_log(&self, run: &CheckRun) -> Result<String> {
+        ChecksClient::job_log(self, run)
+    }
+}
+
+/// Waits for the checks on pushed commits and gathers their failures.
+pub struct CiWatcher {
+    source: Arc<dyn CheckSource>,
+    config: CiConfig,
+}
+
+impl CiWatcher {
+    /// Creates a watcher reading checks from `source`.
+    pub fn new(source: Arc<dyn CheckSource>, config: CiConfig) -> Self {
+        Self { source, config }
+    }
+
+    /// Returns the watcher's settings.
+    pub fn config(&self) -> &CiConfig {
+        &self.config
+    }
+
+    /// Polls the checks on `commit` until they have all finished, then
+    /// gathers the failures.
+    pub async fn wait(&self, commit: &str) -> Result<CiStatus> {
+        let started = Instant::now();
+        let grace = Duration::from_secs(self.config.checks_grace);
+        let timeout = Duration::from_secs(self.config.timeout);
+
+        loop {
+            let runs = self.source.check_runs(commit)?;
+            let elapsed = started.elapsed();
+            if runs.is_empty() && elapsed >= grace {
+                return Ok(CiStatus::NoChecks);
+            }
+            if !runs.is_empty() && runs.iter().all(CheckRun::is_completed) {
+                let failures = self.failures(&runs)?;
+                if failures.is_empty() {
+                    return Ok(CiStatus::Passed);
+                }
+                return Ok(CiStatus::Failed(failures));
+            }
+            if elapsed >= timeout {
+                return Ok(CiStatus::TimedOut);
+            }
+
+            tracing::debug!(commit, runs = runs.len(), "waiting for checks");
+            tokio::time::sleep(Duration::from_secs(self.config.poll_interval)).await;
+        }
+    }
+
+    /// Gathers the annotations and log summary of each failed run.
+    ///
+    /// Checks that are not GitHub Actions jobs have no log to read; their
+    /// summary is left empty.
+    fn failures(&self, runs: &[CheckRun]) -> Result<Vec<CiFailure>> {
+        let mut failures = Vec::new();
+        for run in runs.iter().filter(|r| r.is_failure()) {
+            let annotations = self
+                .source
+                .annotations(run)?
+                .into_iter()
+                .filter(CheckAnnotation::is_failure)
+                .collect();
+            let summary = match self.source.job_log(run) {
+                Ok(log) => summarize_log(&log, self.config.max_log_lines),
+                Err(e) => {
+                    tracing::warn!(check = %run.name, error = %e, "failed to read job log");
+                    String::new()
+                }
+            };
+            failures.push(CiFailure {
+                run: run.clone(),
+                annotations,
+                summary,
+            });
+        }
+        Ok(failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Check source answering polls from a script, repeating the last
+    /// answer once the script runs out.
+    struct ScriptedChecks {
+        polls: Mutex<VecDeque<Vec<CheckRun>>>,
+        log: String,
+    }
+
+    impl ScriptedChecks {
+        fn new(polls: Vec<Vec<CheckRun>>, log: &str) -> Arc<Self> {
+            Arc::new(Self {
+                polls: Mutex::new(polls.into()),
+                log: log.to_string(),
+            })
+        }
+    }
+
+    impl CheckSource for ScriptedChecks {
+        fn check_runs(&self, _commit: &str) -> Result<Vec<CheckRun>> {
+            let mut polls = self.polls.lock().unwrap();
+            if polls.len() > 1 {
+                Ok(polls.pop_front().unwrap())
+            } else {
+                Ok(polls.front().cloned().unwrap_or_default())
+            }
+        }
+
+        fn annotations(&self, _run: &CheckRun) -> Result<Vec<CheckAnnotation>> {
+            Ok(Vec::new())
+        }
+
+        fn job_log(&self, _run: &CheckRun) -> Result<String> {
+            Ok(self.log.clone())
+        }
+    }
+
+    fn run(status: &str, conclusion: Option<&str>) -> CheckRun {
+        CheckRun {
+            id: 9,
+            name: "test".to_string(),
+            status: status.to_string(),
+            conclusion: conclusion.map(str::to_string),
+            url: "https://github.com/acme/widgets/runs/9".to_string(),
+        }
+    }
+
+    fn config() -> CiConfig {
+        CiConfig {
+            poll_interval: 0,
+            checks_grace: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_check_runs_and_failures() {
+        let json = r#"{"total_count": 3, "check_runs": [
+            {"id": 1, "name": "build", "status": "completed", "conclusion": "success",
+             "html_url": "https://github.com/acme/widgets/runs/1"},
+            {"id": 2, "name": "test", "status": "completed", "conclusion": "failure",
+             "html_url": "https://github.com/acme/widgets/runs/2"},
+            {"id": 3, "name": "lint", "status": "in_progress", "conclusion": null,
+             "html_url": "https://github.com/acme/widgets/runs/3"}
+        ]}"#;
+
+        let runs = parse_check_runs(json).unwrap();
+
+        assert_eq!(runs.len(), 3);
+        assert!(!runs[0].is_failure());
+        assert!(runs[1].is_failure());
+        assert!(!runs[2].is_completed());
+        assert!(!runs[2].is_failure());
+    }
+
+    #[test]
+    fn annotations_become_findings() {
+        let json = r#"[
+            {"path": "src/lib.rs", "start_line": 12, "end_line": 12,
+             "annotation_level": "failure", "title": "E0308",
+             "message": "mismatched types"},
+            {"path": "src/main.rs", "start_line": 3, "end_line": 3,
+             "annotation_level": "warning", "title": null, "message": "unused import"}
+        ]"#;
+
+        let annotations = parse_annotations("build", json).unwrap();
+
+        assert!(annotations[0].is_failure());
+        let finding = annotations[0].to_suggestion();
+        assert_eq!(finding.file, "src/lib.rs");
+        assert_eq!(finding.line, Some(12));
+        assert_eq!(finding.issue, "build failed: E0308: mismatched types");
+        assert_eq!(finding.severity, ReviewSeverity::High);
+        assert_eq!(
+            annotations[1].to_suggestion().issue,
+            "build failed: unused import"
+        );
+    }
+
+    #[test]
+    fn summarizes_logs_to_error_lines() {
+        let log = "2024-05-01T10:00:00.0000000Z Compiling widgets v0.1.0\n\
+                   2024-05-01T10:00:01.0000000Z running 2 tests\n\
+                   2024-05-01T10:00:02.0000000Z thread 'parses' panicked at src/lib.rs:12:5\n\
+                   2024-05-01T10:00:02.0000000Z left: 1\n\
+                   2024-05-01T10:00:02.0000000Z right: 2\n\
+                   2024-05-01T10:00:03.0000000Z note: run with RUST_BACKTRACE=1\n";
+
+        let summary = summarize_log(log, 40);
+
+        assert_eq!(
+            summary,
+            "thread 'parses' panicked at src/lib.rs:12:5\nleft: 1\nright: 2"
+        );
+        assert_eq!(summarize_log("one\ntwo\nthree", 2), "two\nthree");
+        assert_eq!(
+            summarize_log("error: a\nerror: b\nerror: c", 2),
+            "error: a\nerror: b"
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_polls_until_checks_finish() {
+        let source = ScriptedChecks::new(
+            vec![
+                vec![run("queued", None)],
+                vec![run("in_progress", None)],
+                vec![run("completed", Some("failure"))],
+            ],
+            "error[E0308]: mismatched types\n --> src/lib.rs:3:5",
+        );
+
+        let status = CiWatcher::new(source, config()).wait("abc").await.unwrap();
+
+        let CiStatus::Failed(failures) = status else {
+            panic!("expected failures, got {:?}", status);
+        };
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].summary,
+            "error[E0308]: mismatched types\n --> src/lib.rs:3:5"
+        );
+        let suggestions = failures[0].suggestions();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].file, "(CI: test)");
+        assert!(suggestions[0].suggestion.contains("mismatched types"));
+    }
+
+    #[tokio::test]
+    async fn wait_reports_passes_and_missing_checks() {
+        let passing = ScriptedChecks::new(vec![vec![run("completed", Some("success"))]], "");
+        let none = ScriptedChecks::new(Vec::new(), "");
+
+        let passed = CiWatcher::new(passing, config()).wait("abc").await.unwrap();
+        let missing = CiWatcher::new(none, config()).wait("abc").await.unwrap();
+
+        assert_eq!(passed, CiStatus::Passed);
+        assert_eq!(missing, CiStatus::NoChecks);
+    }
+}
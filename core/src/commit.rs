@@ -0,0 +1,710 @@
+//! Commit messages.
+//!
+//! Commits the drive creates are described from a template, so branch
+//! history reads like a human wrote it. The default template follows
+//! [Conventional Commits](https://www.conventionalcommits.org), with the
+//! type inferred from the task:
+//!
+//! | Placeholder   | Value                                              |
+//! |---------------|----------------------------------------------------|
+//! | `{type}`      | Conventional commit type (`feat`, `fix`, ...)      |
+//! | `{scope}`     | `(component)` if the task has one, else empty      |
+//! | `{subject}`   | First line of the task subject                     |
+//! | `{task_id}`   | Task id, e.g. `CRUISE-001` (empty if none)         |
+//! | `{llm}`       | LLM that made the change (empty if unknown)        |
+//! | `{iteration}` | Fix or review round (empty outside a review loop)  |
+//!
+//! Commits for a task carry a `Task-Id` trailer, which lets a
+//! [`GitHistoryPolicy`] squash a branch's iteration commits per task before
+//! its PR is opened. [`CommitTrailers`] add attribution (`AI-Generated-By`,
+//! `Spawn-Id`, `Co-authored-by`), and [`CommitSigning`] signs every commit
+//! with a GPG or SSH key.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Validate, ValidationResult};
+use crate::cruise::CruiseTask;
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+use crate::guardrails::FileGuardrails;
+
+/// Default commit message template.
+pub const DEFAULT_COMMIT_TEMPLATE: &str = "{type}{scope}: {subject}";
+
+/// Trailer naming the task a commit belongs to.
+pub const TASK_TRAILER: &str = "Task-Id";
+
+/// Trailer naming the spawn that made a commit.
+pub const SPAWN_TRAILER: &str = "Spawn-Id";
+
+/// Trailer naming the LLM that made a commit.
+pub const AI_TRAILER: &str = "AI-Generated-By";
+
+/// Conventional commit type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitType {
+    /// A new feature.
+    #[default]
+    Feat,
+    /// A bug fix.
+    Fix,
+    /// Documentation only.
+    Docs,
+    /// Tests only.
+    Test,
+    /// Restructuring without behavior change.
+    Refactor,
+    /// Performance improvement.
+    Perf,
+    /// Build system or dependencies.
+    Build,
+    /// CI configuration.
+    Ci,
+    /// Anything else.
+    Chore,
+}
+
+impl CommitType {
+    /// Returns the type as written in a commit message.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommitType::Feat => "feat",
+            CommitType::Fix => "fix",
+            CommitType::Docs => "docs",
+            CommitType::Test => "test",
+            CommitType::Refactor => "refactor",
+            CommitType::Perf => "perf",
+            CommitType::Build => "build",
+            CommitType::Ci => "ci",
+            CommitType::Chore => "chore",
+        }
+    }
+
+    /// Infers the type from the first word of a task subject, defaulting
+    /// to [`CommitType::Feat`].
+    pub fn infer(subject: &str) -> Self {
+        let first = subject
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(':')
+            .to_lowercase();
+        match first.as_str() {
+            "fix" | "fixes" | "fixed" | "repair" | "resolve" | "correct" => CommitType::Fix,
+            "document" | "docs" | "doc" => CommitType::Docs,
+            "test" | "tests" => CommitType::Test,
+            "refactor" | "restructure" | "rename" | "extract" | "simplify" => CommitType::Refactor,
+            "optimize" | "speed" | "perf" => CommitType::Perf,
+            "bump" | "upgrade" | "build" => CommitType::Build,
+            "ci" => CommitType::Ci,
+            "chore" | "remove" | "clean" | "cleanup" => CommitType::Chore,
+            _ => CommitType::Feat,
+        }
+    }
+}
+
+/// What a commit is for, used to fill in the template.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitContext {
+    /// Conventional commit type.
+    pub kind: CommitType,
+    /// Short description of the change.
+    pub subject: String,
+    /// Component the change belongs to.
+    pub scope: Option<String>,
+    /// Task id.
+    pub task_id: Option<String>,
+    /// LLM that made the change.
+    pub llm: Option<String>,
+    /// Fix or review round.
+    pub iteration: Option<u32>,
+    /// Spawn that made the change.
+    pub spawn_id: Option<String>,
+}
+
+impl CommitContext {
+    /// Creates a context with the type inferred from `subject`.
+    pub fn new(subject: impl Into<String>) -> Self {
+        let subject = subject.into();
+        Self {
+            kind: CommitType::infer(&subject),
+            subject,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a context describing a cruise task.
+    pub fn for_task(task: &CruiseTask) -> Self {
+        Self {
+            scope: task.component.clone(),
+            task_id: Some(task.id.clone()),
+            ..Self::new(task.subject.clone())
+        }
+    }
+
+    /// Sets the commit type.
+    pub fn with_kind(mut self, kind: CommitType) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets the component the change belongs to.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Sets the task the change is for.
+    pub fn with_task_id(mut self, task_id: impl Into<String>) -> Self {
+        self.task_id = Some(task_id.into());
+        self
+    }
+
+    /// Sets the LLM that made the change.
+    pub fn with_llm(mut self, llm: impl Into<String>) -> Self {
+        self.llm = Some(llm.into());
+        self
+    }
+
+    /// Sets the fix or review round.
+    pub fn with_iteration(mut self, iteration: u32) -> Self {
+        self.iteration = Some(iteration);
+        self
+    }
+
+    /// Sets the spawn that made the change.
+    pub fn with_spawn_id(mut self, spawn_id: impl Into<String>) -> Self {
+        self.spawn_id = Some(spawn_id.into());
+        self
+    }
+}
+
+/// Key type used to sign commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    /// GPG key.
+    #[default]
+    Openpgp,
+    /// SSH key.
+    Ssh,
+    /// X.509 certificate (gpgsm).
+    X509,
+}
+
+impl SigningFormat {
+    /// Returns the value of git's `gpg.format`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SigningFormat::Openpgp => "openpgp",
+            SigningFormat::Ssh => "ssh",
+            SigningFormat::X509 => "x509",
+        }
+    }
+}
+
+/// How commits are signed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitSigning {
+    /// Key type.
+    #[serde(default)]
+    pub format: SigningFormat,
+    /// Key to sign with: a GPG key id, or the path of an SSH key or its
+    /// `key::` literal. Git's `user.signingkey` is used if unset.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+impl CommitSigning {
+    /// Returns the `-c` options selecting the format and key, to pass before
+    /// a git subcommand that is given `-S`.
+    pub fn config_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-c".to_string(),
+            format!("gpg.format={}", self.format.as_str()),
+        ];
+        if let Some(key) = &self.key {
+            args.push("-c".to_string());
+            args.push(format!("user.signingkey={}", key));
+        }
+        args
+    }
+}
+
+/// Attribution trailers added to every commit message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitTrailers {
+    /// Adds `AI-Generated-By: <llm>` when the LLM is known.
+    #[serde(default = "default_true")]
+    pub ai_generated: bool,
+    /// Adds `Spawn-Id: <id>` when the spawn is known.
+    #[serde(default = "default_true")]
+    pub spawn_id: bool,
+    /// Adds a `Co-authored-by` trailer for each entry, e.g.
+    /// `"Jane Doe <jane@example.com>"`.
+    #[serde(default)]
+    pub co_authored_by: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for CommitTrailers {
+    fn default() -> Self {
+        Self {
+            ai_generated: true,
+            spawn_id: true,
+            co_authored_by: Vec::new(),
+        }
+    }
+}
+
+/// How a branch's history is rewritten before its PR is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitHistoryPolicy {
+    /// Keep every commit.
+    #[default]
+    KeepAll,
+    /// Squash consecutive commits with the same `Task-Id` trailer into the
+    /// first of them. Commits without the trailer are kept.
+    SquashPerTask,
+    /// Squash the whole branch into one commit.
+    SquashAll,
+}
+
+impl GitHistoryPolicy {
+    /// Rewrites the commits since the worktree's branch forked from
+    /// `base_ref`. `message` describes the commit left by
+    /// [`GitHistoryPolicy::SquashAll`].
+    ///
+    /// Rewritten commits are signed with `signing`, if given. The worktree
+    /// and index are left as they are. Returns the new head, or `None` if
+    /// there was nothing to rewrite.
+    pub fn apply(
+        &self,
+        worktree: &Path,
+        base_ref: &str,
+        message: &str,
+        signing: Option<&CommitSigning>,
+    ) -> Result<Option<String>> {
+        if *self == GitHistoryPolicy::KeepAll {
+            return Ok(None);
+        }
+
+        let fork_point = SystemGit.run(worktree, &["merge-base", base_ref, "HEAD"])?;
+        let fork_point = fork_point.trim();
+        let range = format!("{}..HEAD", fork_point);
+        let commits = SystemGit.run(worktree, &["rev-list", "--reverse", &range])?;
+
+        // Runs of commits that become one commit each: (message, task, last commit)
+        let mut groups: Vec<(String, Option<String>, String)> = Vec::new();
+        for commit in commits.lines() {
+            let body = SystemGit.run(worktree, &["log", "-1", "--format=%B", commit])?;
+            let task = match self {
+                GitHistoryPolicy::SquashAll => Some(String::new()),
+                _ => task_trailer(&body),
+            };
+            match groups.last_mut() {
+                Some((_, last_task, last)) if task.is_some() && *last_task == task => {
+                    *last = commit.to_string();
+                }
+                _ => groups.push((body.trim().to_string(), task, commit.to_string())),
+            }
+        }
+        if groups.len() == commits.lines().count() {
+            return Ok(None);
+        }
+
+        let mut head = fork_point.to_string();
+        for (body, _, last) in &groups {
+            let body = match self {
+                GitHistoryPolicy::SquashAll => message,
+                _ => body.as_str(),
+            };
+            let tree = format!("{}^{{tree}}", last);
+            let mut args: Vec<String> = signing.map(|s| s.config_args()).unwrap_or_default();
+            args.extend(["commit-tree", &tree, "-p", &head, "-m", body].map(String::from));
+            if signing.is_some() {
+                args.push("-S".to_string());
+            }
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            head = SystemGit.run(worktree, &args)?.trim().to_string();
+        }
+        SystemGit.run(worktree, &["reset", "--soft", &head])?;
+        Ok(Some(head))
+    }
+}
+
+/// Returns the value of the last `Task-Id` trailer in a commit message.
+pub(crate) fn task_trailer(message: &str) -> Option<String> {
+    let prefix = format!("{}:", TASK_TRAILER);
+    message
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .map(|value| value.trim().to_string())
+}
+
+/// Commit message settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitMessages {
+    /// Template for the first line of each commit message.
+    #[serde(default = "default_template")]
+    pub template: String,
+
+    /// How a branch's history is rewritten before its PR is opened.
+    #[serde(default)]
+    pub history: GitHistoryPolicy,
+
+    /// Attribution trailers.
+    #[serde(default)]
+    pub trailers: CommitTrailers,
+
+    /// Signs every commit. Commits are unsigned if unset.
+    #[serde(default)]
+    pub signing: Option<CommitSigning>,
+
+    /// Size and binary checks on staged files.
+    #[serde(default)]
+    pub guardrails: FileGuardrails,
+}
+
+fn default_template() -> String {
+    DEFAULT_COMMIT_TEMPLATE.to_string()
+}
+
+impl Default for CommitMessages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommitMessages {
+    /// Creates settings with the default template, keeping all history.
+    pub fn new() -> Self {
+        Self {
+            template: default_template(),
+            history: GitHistoryPolicy::default(),
+            trailers: CommitTrailers::default(),
+            signing: None,
+            guardrails: FileGuardrails::default(),
+        }
+    }
+
+    /// Sets the template.
+    pub fn with_template(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Sets how history is rewritten before PRs.
+    pub fn with_history(mut self, history: GitHistoryPolicy) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Sets how commits are signed.
+    pub fn with_signing(mut self, signing: CommitSigning) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    /// Sets the size and binary checks on staged files.
+    pub fn with_guardrails(mut self, guardrails: FileGuardrails) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    /// Returns the full commit message for `context`: the rendered template,
+    /// then the task and attribution trailers that apply.
+    pub fn message(&self, context: &CommitContext) -> String {
+        let mut trailers = Vec::new();
+        if let Some(task_id) = &context.task_id {
+            trailers.push(format!("{}: {}", TASK_TRAILER, task_id));
+        }
+        if let Some(spawn_id) = context.spawn_id.as_ref().filter(|_| self.trailers.spawn_id) {
+            trailers.push(format!("{}: {}", SPAWN_TRAILER, spawn_id));
+        }
+        if let Some(llm) = context.llm.as_ref().filter(|_| self.trailers.ai_generated) {
+            trailers.push(format!("{}: {}", AI_TRAILER, llm));
+        }
+        for author in &self.trailers.co_authored_by {
+            trailers.push(format!("Co-authored-by: {}", author));
+        }
+
+        let mut message = self.render(context);
+        if !trailers.is_empty() {
+            message.push_str("\n\n");
+            message.push_str(&trailers.join("\n"));
+        }
+        message
+    }
+
+    /// Returns the git arguments for committing with `message`: signing
+    /// options, the `commit` subcommand, and `-S` if signing.
+    pub fn commit_args(&self, message: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(signing) = &self.signing {
+            args.extend(signing.config_args());
+        }
+        args.extend(["commit", "-m", message].map(String::from));
+        if self.signing.is_some() {
+            args.push("-S".to_string());
+        }
+        args
+    }
+
+    /// Rewrites the worktree branch's history following
+    /// [`CommitMessages::history`], signing rewritten commits.
+    pub fn rewrite_history(
+        &self,
+        worktree: &Path,
+        base_ref: &str,
+        message: &str,
+    ) -> Result<Option<String>> {
+        self.history
+            .apply(worktree, base_ref, message, self.signing.as_ref())
+    }
+
+    /// Renders the first line of the message for `context`.
+    pub fn render(&self, context: &CommitContext) -> String {
+        let subject = context
+            .subject
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_end_matches('.');
+        let scope = context
+            .scope
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("({})", s))
+            .unwrap_or_default();
+
+        self.template
+            .replace("{type}", context.kind.as_str())
+            .replace("{scope}", &scope)
+            .replace("{task_id}", context.task_id.as_deref().unwrap_or_default())
+            .replace("{llm}", context.llm.as_deref().unwrap_or_default())
+            .replace(
+                "{iteration}",
+                &context.iteration.map(|i| i.to_string()).unwrap_or_default(),
+            )
+            .replace("{subject}", subject)
+    }
+}
+
+impl Validate for CommitMessages {
+    fn validate(&self) -> ValidationResult {
+        let mut result = ValidationResult::default();
+
+        if !self.template.contains("{subject}") {
+            result.add_error("commits.template must contain {subject}");
+        }
+        if self.template.contains('\n') {
+            result.add_error("commits.template must be a single line");
+        }
+        for author in &self.trailers.co_authored_by {
+            if !(author.contains('<') && author.ends_with('>')) {
+                result.add_error(format!(
+                    "commits.trailers.co_authored_by '{}' must be \"Name <email>\"",
+                    author
+                ));
+            }
+        }
+        result.merge(self.guardrails.validate());
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Creates a repo with one commit on `main` and `feature` checked out.
+    fn create_branched_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        SystemGit.run(path, &["init", "-q", "-b", "main"]).unwrap();
+        SystemGit
+            .run(path, &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(path, &["config", "user.name", "Test User"])
+            .unwrap();
+        std::fs::write(path.join("README.md"), "# Test\n").unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", "Initial commit"])
+            .unwrap();
+        SystemGit
+            .run(path, &["checkout", "-q", "-b", "feature"])
+            .unwrap();
+        dir
+    }
+
+    fn commit(path: &Path, file: &str, message: &str) {
+        std::fs::write(path.join(file), file).unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", message])
+            .unwrap();
+    }
+
+    #[test]
+    fn default_template_is_conventional() {
+        let task = CruiseTask::new("CRUISE-002", "Fix token refresh race.").with_component("auth");
+
+        let message = CommitMessages::new().render(&CommitContext::for_task(&task));
+
+        assert_eq!(message, "fix(auth): Fix token refresh race");
+    }
+
+    #[test]
+    fn custom_template_fills_every_placeholder() {
+        let messages =
+            CommitMessages::new().with_template("[{task_id}] {subject} ({llm} #{iteration})");
+        let context = CommitContext::new("Add login form\n\nWith validation")
+            .with_llm("claude-code")
+            .with_iteration(2);
+        let context = CommitContext {
+            task_id: Some("CRUISE-001".to_string()),
+            ..context
+        };
+
+        assert_eq!(
+            messages.render(&context),
+            "[CRUISE-001] Add login form (claude-code #2)"
+        );
+    }
+
+    #[test]
+    fn type_is_inferred_from_subject() {
+        assert_eq!(CommitType::infer("Add OAuth login"), CommitType::Feat);
+        assert_eq!(CommitType::infer("Document the API"), CommitType::Docs);
+        assert_eq!(
+            CommitType::infer("refactor: split parser"),
+            CommitType::Refactor
+        );
+        assert_eq!(CommitType::infer(""), CommitType::Feat);
+    }
+
+    #[test]
+    fn validation_requires_subject() {
+        assert!(CommitMessages::new().validate().is_valid());
+        assert!(!CommitMessages::new()
+            .with_template("{type}: update")
+            .validate()
+            .is_valid());
+    }
+
+    #[test]
+    fn squash_per_task_keeps_one_commit_per_task() {
+        let repo = create_branched_repo();
+        let path = repo.path();
+        let messages = CommitMessages::new();
+        let login = CommitContext::new("Add login").with_task_id("CRUISE-001");
+        let logout = CommitContext::new("Add logout").with_task_id("CRUISE-002");
+        commit(path, "a.rs", &messages.message(&login));
+        commit(
+            path,
+            "b.rs",
+            &messages.message(&login.clone().with_iteration(2)),
+        );
+        commit(path, "c.rs", &messages.message(&logout));
+        commit(path, "d.rs", "Manual tweak");
+
+        let head = GitHistoryPolicy::SquashPerTask
+            .apply(path, "main", "unused", None)
+            .unwrap();
+
+        assert!(head.is_some());
+        let log = SystemGit
+            .run(path, &["log", "--format=%s", "main..HEAD"])
+            .unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec!["Manual tweak", "feat: Add logout", "feat: Add login"]
+        );
+        assert!(SystemGit
+            .run(path, &["status", "--porcelain"])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn squash_all_and_keep_all() {
+        let repo = create_branched_repo();
+        let path = repo.path();
+        commit(path, "a.rs", "WIP 1");
+        commit(path, "b.rs", "WIP 2");
+
+        assert!(GitHistoryPolicy::KeepAll
+            .apply(path, "main", "feat: Add parser", None)
+            .unwrap()
+            .is_none());
+        GitHistoryPolicy::SquashAll
+            .apply(path, "main", "feat: Add parser", None)
+            .unwrap();
+
+        let log = SystemGit
+            .run(path, &["log", "--format=%s", "main..HEAD"])
+            .unwrap();
+        assert_eq!(log.trim(), "feat: Add parser");
+        assert!(SystemGit.run(path, &["ls-files"]).unwrap().contains("b.rs"));
+    }
+
+    #[test]
+    fn message_adds_attribution_trailers() {
+        let mut messages = CommitMessages::new();
+        messages.trailers.co_authored_by = vec!["Jane Doe <jane@example.com>".to_string()];
+        let context = CommitContext::new("Add login")
+            .with_task_id("CRUISE-001")
+            .with_spawn_id("abc123")
+            .with_llm("claude-code");
+
+        assert_eq!(
+            messages.message(&context),
+            "feat: Add login\n\n\
+             Task-Id: CRUISE-001\n\
+             Spawn-Id: abc123\n\
+             AI-Generated-By: claude-code\n\
+             Co-authored-by: Jane Doe <jane@example.com>"
+        );
+
+        messages.trailers.ai_generated = false;
+        messages.trailers.co_authored_by[0] = "jane".to_string();
+        assert!(!messages.message(&context).contains(AI_TRAILER));
+        assert!(!messages.validate().is_valid());
+    }
+
+    #[test]
+    fn signing_selects_format_and_key() {
+        let messages = CommitMessages::new().with_signing(CommitSigning {
+            format: SigningFormat::Ssh,
+            key: Some("~/.ssh/id_ed25519.pub".to_string()),
+        });
+
+        assert_eq!(
+            messages.commit_args("feat: Add login"),
+            vec![
+                "-c",
+                "gpg.format=ssh",
+                "-c",
+                "user.signingkey=~/.ssh/id_ed25519.pub",
+                "commit",
+                "-m",
+                "feat: Add login",
+                "-S",
+            ]
+        );
+    }
+}
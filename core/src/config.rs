@@ -2,6 +2,7 @@
 //!
 //! Validates configuration before spawning to catch errors early.
 
+use std::collections::HashSet;
 use std::time::Duration;
 
 use crate::error::{Error, Result};
@@ -141,6 +142,13 @@ impl Validate for SandboxManifest {
             }
         }
 
+        // Warn about passing the whole parent environment
+        if self.env_allow.iter().any(|p| p == "*") {
+            result.add_warning(
+                "env_allow '*' passes the whole environment, including unrelated tokens",
+            );
+        }
+
         result
     }
 }
@@ -161,6 +169,15 @@ impl Validate for WatcherConfig {
             );
         }
 
+        // Stalls are meant to catch LLMs that talk without acting
+        if let Some(stall) = self.timeout.stall_timeout {
+            if stall <= self.timeout.idle_timeout {
+                result.add_warning(
+                    "stall_timeout <= idle_timeout means silent LLMs are treated as stalled",
+                );
+            }
+        }
+
         result
     }
 }
@@ -195,6 +212,59 @@ impl Validate for SpawnTeamConfig {
             );
         }
 
+        // A log threshold above the block threshold never logs anything
+        let policies = std::iter::once(("default", &self.gating.default)).chain(
+            self.gating
+                .domains
+                .iter()
+                .map(|(domain, policy)| (domain.as_str(), policy)),
+        );
+        for (domain, policy) in policies {
+            if policy.log_at > policy.block_at {
+                result.add_warning(format!(
+                    "gating for '{}' has log_at above block_at - no findings will be logged",
+                    domain
+                ));
+            }
+        }
+
+        // Polling without a pause hammers the GitHub API
+        if self.ci.max_fix_rounds > 0 && self.ci.poll_interval == 0 {
+            result.add_warning("ci.poll_interval of 0 polls GitHub checks without pausing");
+        }
+
+        // Benchmarks vary between runs, so a threshold of 0 flags noise
+        if self.benchmarks.is_enabled() && self.benchmarks.regression_threshold <= 0.0 {
+            result.add_warning(
+                "benchmarks.regression_threshold of 0 or less reports run-to-run noise as regressions",
+            );
+        }
+
+        // Roles are told apart by name, and custom ones need instructions
+        let mut names = HashSet::new();
+        for role in &self.roles {
+            if !names.insert(role.name.as_str()) {
+                result.add_error(format!("role '{}' is defined more than once", role.name));
+            }
+            if !KNOWN_LLMS.contains(&role.llm.as_str()) {
+                result.add_warning(format!(
+                    "unknown llm '{}' for role '{}'",
+                    role.llm, role.name
+                ));
+            }
+            if role.instructions().is_none() {
+                result.add_error(format!(
+                    "role '{}' is not built in and needs a prompt",
+                    role.name
+                ));
+            }
+        }
+
+        // An ungated tester's tests may fail without anyone noticing
+        if self.tester.enabled && !self.tester.gate.is_enabled() {
+            result.add_warning("tester.gate.commands is empty - tests are not run before review");
+        }
+
         result
     }
 }
@@ -224,7 +294,14 @@ pub fn validate_spawn_team_operation(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::team::CoordinationMode;
+    use crate::benchmark::BenchmarkConfig;
+    use crate::checks::CiConfig;
+    use crate::diff::DiffConfig;
+    use crate::team::{
+        CoordinationMode, DomainSelection, PhaseModels, ReviewGating, ReviewSeverity, TeamRole,
+        TesterConfig,
+    };
+    use crate::verification::VerificationConfig;
 
     // ========================================
     // SpawnConfig validation tests
@@ -241,10 +318,15 @@ mod tests {
     fn spawn_config_empty_prompt_fails() {
         let config = SpawnConfig {
             prompt: "".to_string(),
+            model: None,
             mode: Default::default(),
             idle_timeout: Duration::from_secs(120),
             total_timeout: Duration::from_secs(1800),
             max_permission_escalations: 1,
+            partial_pr: false,
+            closes_issues: vec![],
+            context: Default::default(),
+            write_mode: Default::default(),
         };
         let result = config.validate();
         assert!(!result.is_valid());
@@ -255,10 +337,15 @@ mod tests {
     fn spawn_config_whitespace_prompt_fails() {
         let config = SpawnConfig {
             prompt: "   \n\t  ".to_string(),
+            model: None,
             mode: Default::default(),
             idle_timeout: Duration::from_secs(120),
             total_timeout: Duration::from_secs(1800),
             max_permission_escalations: 1,
+            partial_pr: false,
+            closes_issues: vec![],
+            context: Default::default(),
+            write_mode: Default::default(),
         };
         let result = config.validate();
         assert!(!result.is_valid());
@@ -398,6 +485,15 @@ mod tests {
         assert!(result.warnings.iter().any(|w| w.contains("0")));
     }
 
+    #[test]
+    fn watcher_config_short_stall_timeout_warns() {
+        let mut config = WatcherConfig::default();
+        config.timeout.stall_timeout = Some(config.timeout.idle_timeout);
+        let result = config.validate();
+        assert!(result.is_valid());
+        assert!(result.warnings.iter().any(|w| w.contains("stall_timeout")));
+    }
+
     // ========================================
     // SpawnTeamConfig validation tests
     // ========================================
@@ -409,6 +505,16 @@ mod tests {
         assert!(result.is_valid());
     }
 
+    #[test]
+    fn spawn_team_config_inverted_gating_warns() {
+        let mut config = SpawnTeamConfig::default();
+        config.gating.default.log_at = ReviewSeverity::Critical;
+
+        let result = config.validate();
+        assert!(result.is_valid());
+        assert!(result.warnings.iter().any(|w| w.contains("log_at")));
+    }
+
     #[test]
     fn spawn_team_config_zero_iterations_fails() {
         let config = SpawnTeamConfig {
@@ -416,6 +522,19 @@ mod tests {
             max_iterations: 0,
             primary_llm: "claude-code".to_string(),
             reviewer_llm: "gemini-cli".to_string(),
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
         };
         let result = config.validate();
         assert!(!result.is_valid());
@@ -429,6 +548,19 @@ mod tests {
             max_iterations: 20,
             primary_llm: "claude-code".to_string(),
             reviewer_llm: "gemini-cli".to_string(),
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
         };
         let result = config.validate();
         assert!(result.is_valid());
@@ -442,6 +574,19 @@ mod tests {
             max_iterations: 3,
             primary_llm: "unknown-llm".to_string(),
             reviewer_llm: "gemini-cli".to_string(),
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
         };
         let result = config.validate();
         assert!(result.is_valid());
@@ -455,6 +600,19 @@ mod tests {
             max_iterations: 3,
             primary_llm: "claude-code".to_string(),
             reviewer_llm: "gpt-4".to_string(),
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
         };
         let result = config.validate();
         assert!(result.is_valid());
@@ -468,6 +626,19 @@ mod tests {
             max_iterations: 3,
             primary_llm: "claude-code".to_string(),
             reviewer_llm: "claude-code".to_string(),
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
         };
         let result = config.validate();
         assert!(result.is_valid());
@@ -478,6 +649,41 @@ mod tests {
     // Combined validation tests
     // ========================================
 
+    #[test]
+    fn spawn_team_config_ci_polling_without_pause_warns() {
+        let config = SpawnTeamConfig {
+            ci: CiConfig {
+                poll_interval: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = config.validate();
+        assert!(result.is_valid());
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("ci.poll_interval")));
+    }
+
+    #[test]
+    fn spawn_team_config_zero_benchmark_threshold_warns() {
+        let config = SpawnTeamConfig {
+            benchmarks: BenchmarkConfig {
+                commands: vec!["cargo bench".to_string()],
+                regression_threshold: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = config.validate();
+        assert!(result.is_valid());
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("benchmarks.regression_threshold")));
+    }
+
     #[test]
     fn validate_spawn_operation_combines_results() {
         let config = SpawnConfig::new("test").with_idle_timeout(Duration::from_secs(5));
@@ -491,6 +697,39 @@ mod tests {
         assert!(result.warnings.len() >= 2); // Both should have warnings
     }
 
+    #[test]
+    fn spawn_team_config_custom_roles_need_prompts() {
+        let config = SpawnTeamConfig {
+            roles: vec![
+                TeamRole::new("architect", "claude-code"),
+                TeamRole::new("architect", "gemini-cli"),
+                TeamRole::new("docs", "claude-code"),
+            ],
+            ..Default::default()
+        };
+
+        let result = config.validate();
+
+        assert_eq!(
+            result.errors,
+            vec![
+                "role 'architect' is defined more than once",
+                "role 'docs' is not built in and needs a prompt",
+            ]
+        );
+    }
+
+    #[test]
+    fn spawn_team_config_ungated_tester_warns() {
+        let mut config = SpawnTeamConfig::default();
+        config.tester.enabled = true;
+
+        let result = config.validate();
+
+        assert!(result.is_valid());
+        assert!(result.warnings.iter().any(|w| w.contains("tester.gate")));
+    }
+
     #[test]
     fn validate_spawn_team_operation_combines_all() {
         let config = SpawnConfig::new("test");
@@ -500,6 +739,19 @@ mod tests {
             max_iterations: 3,
             primary_llm: "claude-code".to_string(),
             reviewer_llm: "claude-code".to_string(), // Same - should warn
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
         };
 
         let result = validate_spawn_team_operation(&config, &manifest, &team_config);
@@ -0,0 +1,756 @@
+//! Merge conflict resolution pipeline.
+//!
+//! Brings a feature branch up to date by rebasing it onto its base. Conflicts
+//! the rebase cannot apply are handed to a "conflict resolver" LLM, the
+//! result is verified with a build command, and the branch is force-pushed.
+//! Delete/modify and binary conflicts are left for a human: the rebase is
+//! aborted and they are reported as unresolved.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{ConflictFile, ConflictStrategy};
+use crate::prompt::{PromptTemplates, TemplateVars};
+use crate::runner::{run_in_sandbox, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::{sandbox_environment, SandboxManifest};
+
+/// Configuration for conflict resolution.
+#[derive(Debug, Clone)]
+pub struct ConflictResolutionConfig {
+    /// How to handle conflicts the rebase cannot apply.
+    pub strategy: ConflictStrategy,
+    /// Maximum LLM resolution rounds (one per conflicting commit).
+    pub max_rounds: u32,
+    /// Shell command that must succeed after resolution (e.g., "cargo build").
+    pub verify_command: Option<String>,
+    /// Whether to push the resolved branch.
+    pub push: bool,
+    /// Model passed to the resolver runner.
+    pub model: Option<String>,
+}
+
+impl Default for ConflictResolutionConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ConflictStrategy::AutoResolve,
+            max_rounds: 5,
+            verify_command: None,
+            push: true,
+            model: None,
+        }
+    }
+}
+
+/// Outcome of a conflict resolution attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionOutcome {
+    /// The branch rebased without conflicts.
+    Clean,
+    /// Conflicts were resolved by the resolver LLM.
+    Resolved {
+        /// Files that were resolved.
+        files: Vec<PathBuf>,
+        /// Number of resolution rounds used.
+        rounds: u32,
+    },
+    /// Conflicts remain; the rebase was aborted and the branch is unchanged.
+    Unresolved(Vec<ConflictFile>),
+    /// The rebased branch failed verification and was not pushed.
+    VerificationFailed(String),
+}
+
+/// State of an in-progress rebase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RebaseState {
+    /// The rebase finished.
+    Done,
+    /// The rebase stopped on a conflicting commit.
+    Stopped,
+}
+
+/// Rebases branches and resolves conflicts with an LLM.
+pub struct ConflictResolver {
+    runner: Arc<dyn LLMRunner>,
+    config: ConflictResolutionConfig,
+    templates: PromptTemplates,
+    context: Option<String>,
+    manifest: SandboxManifest,
+}
+
+impl ConflictResolver {
+    /// Creates a new resolver using the given runner.
+    pub fn new(runner: Arc<dyn LLMRunner>, config: ConflictResolutionConfig) -> Self {
+        Self {
+            runner,
+            config,
+            templates: PromptTemplates::builtin(),
+            context: None,
+            manifest: SandboxManifest::default(),
+        }
+    }
+
+    /// Sets the prompt templates.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Sets the manifest whose sandbox environment the verify command runs
+    /// with.
+    pub fn with_manifest(mut self, manifest: SandboxManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Sets context for the resolver (e.g., the original task prompt).
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Rebases `branch` (checked out in `worktree`) onto `base_ref`, resolving
+    /// conflicts according to the configured strategy.
+    pub async fn resolve(
+        &self,
+        worktree: &Path,
+        branch: &str,
+        base_ref: &str,
+    ) -> Result<ResolutionOutcome> {
+        let (resolved, rounds) = match start_rebase(worktree, base_ref)? {
+            RebaseState::Done => (Vec::new(), 0),
+            RebaseState::Stopped => match self.finish_rebase(worktree, branch, base_ref).await {
+                Ok(Ok(done)) => done,
+                Ok(Err(unresolved)) => {
+                    abort_rebase(worktree);
+                    return Ok(ResolutionOutcome::Unresolved(unresolved));
+                }
+                Err(e) => {
+                    abort_rebase(worktree);
+                    return Err(e);
+                }
+            },
+        };
+
+        if let Some(command) = &self.config.verify_command {
+            if let Err(output) = verify(worktree, command, &self.manifest) {
+                tracing::warn!(branch = %branch, "resolved branch failed verification");
+                return Ok(ResolutionOutcome::VerificationFailed(output));
+            }
+        }
+
+        if self.config.push {
+            SystemGit.run(worktree, &["push", "--force-with-lease", "origin", branch])?;
+        }
+
+        if resolved.is_empty() {
+            Ok(ResolutionOutcome::Clean)
+        } else {
+            Ok(ResolutionOutcome::Resolved {
+                files: resolved,
+                rounds,
+            })
+        }
+    }
+
+    /// Resolves conflicts until the stopped rebase completes.
+    ///
+    /// Returns the resolved files and the rounds used, or the conflicts that
+    /// could not be resolved. The caller aborts the rebase unless it
+    /// completed.
+    async fn finish_rebase(
+        &self,
+        worktree: &Path,
+        branch: &str,
+        base_ref: &str,
+    ) -> Result<std::result::Result<(Vec<PathBuf>, u32), Vec<ConflictFile>>> {
+        let mut state = RebaseState::Stopped;
+        let mut rounds = 0;
+        let mut resolved = Vec::new();
+
+        while state == RebaseState::Stopped {
+            let conflicts = conflicted_files(worktree)?;
+            for path in &conflicts.deleted {
+                // Deleted on both sides: there is nothing to merge
+                SystemGit.run(worktree, &["rm", "--quiet", "--", path_str(path)?])?;
+            }
+            resolved.extend(conflicts.deleted);
+            let unresolved: Vec<ConflictFile> = conflicts
+                .other
+                .iter()
+                .chain(&conflicts.text)
+                .cloned()
+                .collect();
+            if unresolved.is_empty() {
+                // Nothing left to resolve in this commit
+                state = continue_rebase(worktree)?;
+                continue;
+            }
+
+            match self.config.strategy {
+                ConflictStrategy::Fail => {
+                    return Err(Error::RebaseConflict {
+                        branch: branch.to_string(),
+                        onto: base_ref.to_string(),
+                        paths: unresolved.iter().map(|c| c.path.clone()).collect(),
+                    });
+                }
+                ConflictStrategy::Mark => return Ok(Err(unresolved)),
+                ConflictStrategy::AutoResolve => {}
+            }
+
+            if !conflicts.other.is_empty() {
+                tracing::warn!(
+                    branch = %branch,
+                    files = %describe_paths(&conflicts.other),
+                    "delete/modify or binary conflicts need a human"
+                );
+                return Ok(Err(unresolved));
+            }
+
+            if rounds >= self.config.max_rounds {
+                tracing::warn!(
+                    branch = %branch,
+                    rounds,
+                    "conflict resolution round limit reached"
+                );
+                return Ok(Err(unresolved));
+            }
+            rounds += 1;
+
+            let conflicts = conflicts.text;
+            tracing::info!(
+                branch = %branch,
+                round = rounds,
+                files = conflicts.len(),
+                "asking resolver to fix merge conflicts"
+            );
+            self.run_resolver(worktree, branch, base_ref, &conflicts)
+                .await?;
+
+            let remaining = remaining_conflicts(worktree, &conflicts)?;
+            if !remaining.is_empty() {
+                return Ok(Err(remaining));
+            }
+
+            for conflict in &conflicts {
+                SystemGit.run(worktree, &["add", "--", path_str(&conflict.path)?])?;
+            }
+            resolved.extend(conflicts.into_iter().map(|c| c.path));
+            state = continue_rebase(worktree)?;
+        }
+
+        Ok(Ok((resolved, rounds)))
+    }
+
+    /// Builds the resolver prompt for a set of conflicts.
+    pub fn build_prompt(&self, branch: &str, base_ref: &str, conflicts: &[ConflictFile]) -> String {
+        let list = conflicts
+            .iter()
+            .map(|c| {
+                format!(
+                    "- `{}` ({} conflict{})",
+                    c.path.display(),
+                    c.conflict_count,
+                    if c.conflict_count == 1 { "" } else { "s" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut vars = TemplateVars::new();
+        vars.insert("branch", branch.to_string());
+        vars.insert("base_ref", base_ref.to_string());
+        vars.insert("conflicts", list);
+        if let Some(context) = &self.context {
+            vars.insert("context", context.clone());
+        }
+        self.templates.render(PromptTemplates::CONFLICT, &vars)
+    }
+
+    async fn run_resolver(
+        &self,
+        worktree: &Path,
+        branch: &str,
+        base_ref: &str,
+        conflicts: &[ConflictFile],
+    ) -> Result<()> {
+        let manifest = SandboxManifest {
+            writable_paths: conflicts
+                .iter()
+                .map(|c| c.path.display().to_string())
+                .collect(),
+            allowed_tools: vec!["Read".to_string(), "Edit".to_string(), "Write".to_string()],
+            ..Default::default()
+        };
+        let config = LLMSpawnConfig {
+            prompt: self.build_prompt(branch, base_ref, conflicts),
+            working_dir: worktree.to_path_buf(),
+            manifest,
+            model: self.config.model.clone(),
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let run = run_in_sandbox(self.runner.as_ref(), config).await?;
+        if !run.result.success {
+            tracing::warn!(branch = %branch, "conflict resolver exited unsuccessfully");
+        }
+        Ok(())
+    }
+}
+
+/// Starts rebasing the checked-out branch onto `base_ref`.
+fn start_rebase(worktree: &Path, base_ref: &str) -> Result<RebaseState> {
+    rebase_step(worktree, &["rebase", base_ref])
+}
+
+/// Continues a stopped rebase.
+fn continue_rebase(worktree: &Path) -> Result<RebaseState> {
+    rebase_step(worktree, &["rebase", "--continue"])
+}
+
+fn rebase_step(worktree: &Path, args: &[&str]) -> Result<RebaseState> {
+    let output = Command::new("git")
+        .current_dir(worktree)
+        .env("GIT_EDITOR", "true")
+        .args(args)
+        .output()?;
+
+    if output.status.success() {
+        return Ok(RebaseState::Done);
+    }
+    if rebase_in_progress(worktree)? {
+        return Ok(RebaseState::Stopped);
+    }
+    Err(Error::git_command(args, &output))
+}
+
+fn rebase_in_progress(worktree: &Path) -> Result<bool> {
+    let git_dir = SystemGit.run(worktree, &["rev-parse", "--absolute-git-dir"])?;
+    let git_dir = PathBuf::from(git_dir.trim());
+    Ok(git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists())
+}
+
+fn abort_rebase(worktree: &Path) {
+    if !rebase_in_progress(worktree).unwrap_or(true) {
+        return;
+    }
+    if let Err(e) = SystemGit.run(worktree, &["rebase", "--abort"]) {
+        tracing::warn!(error = %e, "failed to abort rebase");
+    }
+}
+
+/// Unmerged files of a stopped rebase, by what can be done about them.
+#[derive(Debug, Default)]
+struct Conflicts {
+    /// Text conflicts the resolver can edit.
+    text: Vec<ConflictFile>,
+    /// Delete/modify and binary conflicts, which need a human.
+    other: Vec<ConflictFile>,
+    /// Files deleted on both sides.
+    deleted: Vec<PathBuf>,
+}
+
+/// Sorts the unmerged files by their `git status` code, counting conflict
+/// markers in text conflicts.
+fn conflicted_files(worktree: &Path) -> Result<Conflicts> {
+    let status = SystemGit.run(worktree, &["status", "--porcelain", "-z"])?;
+    let mut conflicts = Conflicts::default();
+
+    for (code, path) in unmerged_paths(&status) {
+        let path = PathBuf::from(path);
+        match code {
+            "DD" => conflicts.deleted.push(path),
+            "AA" | "UU" if !is_binary(&worktree.join(&path))? => {
+                conflicts.text.push(conflict_file(worktree, &path)?);
+            }
+            // DU, UD, AU, UA: one side deleted or renamed the file
+            _ => conflicts.other.push(ConflictFile {
+                path,
+                conflict_count: 0,
+                is_simple: false,
+            }),
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Returns the status code and path of each unmerged entry in
+/// `git status --porcelain -z` output.
+fn unmerged_paths(status: &str) -> Vec<(&str, &str)> {
+    let mut entries = status.split('\0').filter(|e| !e.is_empty());
+    let mut unmerged = Vec::new();
+
+    while let Some(entry) = entries.next() {
+        let (Some(code), Some(path)) = (entry.get(..2), entry.get(3..)) else {
+            continue;
+        };
+        if code.starts_with(['R', 'C']) {
+            // Renames and copies are followed by their source path
+            entries.next();
+        } else if matches!(code, "DD" | "AU" | "UD" | "UA" | "DU" | "AA" | "UU") {
+            unmerged.push((code, path));
+        }
+    }
+    unmerged
+}
+
+/// Returns whether the file looks binary to git: a NUL byte in its first
+/// 8000 bytes.
+fn is_binary(path: &Path) -> Result<bool> {
+    let content = std::fs::read(path)?;
+    Ok(content.iter().take(8000).any(|b| *b == 0))
+}
+
+/// Returns the given files that still contain conflict markers.
+fn remaining_conflicts(worktree: &Path, files: &[ConflictFile]) -> Result<Vec<ConflictFile>> {
+    let mut remaining = Vec::new();
+    for file in files {
+        let file = conflict_file(worktree, &file.path)?;
+        if file.conflict_count > 0 {
+            remaining.push(file);
+        }
+    }
+    Ok(remaining)
+}
+
+fn conflict_file(worktree: &Path, path: &Path) -> Result<ConflictFile> {
+    let content = std::fs::read_to_string(worktree.join(path))
+        .map_err(|e| Error::from(e).context(format!("failed to read {}", path.display())))?;
+    let conflict_count = content.lines().filter(|l| l.starts_with("<<<<<<<")).count();
+    Ok(ConflictFile {
+        path: path.to_path_buf(),
+        conflict_count,
+        is_simple: conflict_count <= 2,
+    })
+}
+
+/// Runs the verification command with the sandbox environment of
+/// `manifest`, returning its output on failure.
+fn verify(
+    worktree: &Path,
+    command: &str,
+    manifest: &SandboxManifest,
+) -> std::result::Result<(), String> {
+    let output = Command::new("sh")
+        .current_dir(worktree)
+        .args(["-c", command])
+        .env_clear()
+        .envs(sandbox_environment(manifest))
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+fn describe_paths(conflicts: &[ConflictFile]) -> String {
+    conflicts
+        .iter()
+        .map(|c| c.path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| Error::InvalidPath(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{LLMOutput, LLMResult};
+    use async_trait::async_trait;
+    use std::fs;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    /// Runner that overwrites conflicted files with fixed content.
+    struct FakeResolver {
+        content: Option<String>,
+    }
+
+    #[async_trait]
+    impl LLMRunner for FakeResolver {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            if let Some(content) = &self.content {
+                for path in &config.manifest.writable_paths {
+                    fs::write(config.working_dir.join(path), content)?;
+                }
+            }
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "fake"
+        }
+    }
+
+    /// Runner that fails without touching the worktree.
+    struct BrokenResolver;
+
+    #[async_trait]
+    impl LLMRunner for BrokenResolver {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            Err(Error::Invocation("resolver crashed".to_string()))
+        }
+
+        fn name(&self) -> &str {
+            "broken"
+        }
+    }
+
+    fn resolver(content: Option<&str>, config: ConflictResolutionConfig) -> ConflictResolver {
+        let runner = FakeResolver {
+            content: content.map(String::from),
+        };
+        ConflictResolver::new(Arc::new(runner), config)
+    }
+
+    fn local_config() -> ConflictResolutionConfig {
+        ConflictResolutionConfig {
+            push: false,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a repo where `feature` and the default branch both edit
+    /// `shared.txt`. Returns the repo with `feature` checked out.
+    fn create_conflicting_repo(base_edit: &str) -> TempDir {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let repo = temp_dir.path();
+
+        SystemGit.run(repo, &["init", "-q", "-b", "main"]).unwrap();
+        SystemGit
+            .run(repo, &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(repo, &["config", "user.name", "Test User"])
+            .unwrap();
+        fs::write(repo.join("shared.txt"), "original\n").unwrap();
+        SystemGit.run(repo, &["add", "."]).unwrap();
+        SystemGit
+            .run(repo, &["commit", "-q", "-m", "Initial commit"])
+            .unwrap();
+
+        SystemGit
+            .run(repo, &["checkout", "-q", "-b", "feature"])
+            .unwrap();
+        fs::write(repo.join("shared.txt"), "feature\n").unwrap();
+        SystemGit
+            .run(repo, &["commit", "-q", "-am", "Feature change"])
+            .unwrap();
+
+        SystemGit.run(repo, &["checkout", "-q", "main"]).unwrap();
+        fs::write(repo.join(base_edit), "main\n").unwrap();
+        SystemGit.run(repo, &["add", "."]).unwrap();
+        SystemGit
+            .run(repo, &["commit", "-q", "-m", "Main change"])
+            .unwrap();
+
+        SystemGit.run(repo, &["checkout", "-q", "feature"]).unwrap();
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn clean_rebase_needs_no_resolver() {
+        let repo = create_conflicting_repo("other.txt");
+
+        let outcome = resolver(None, local_config())
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap();
+
+        assert_eq!(outcome, ResolutionOutcome::Clean);
+        assert!(repo.path().join("other.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn resolver_fixes_conflicts_and_completes_rebase() {
+        let repo = create_conflicting_repo("shared.txt");
+
+        let outcome = resolver(Some("merged\n"), local_config())
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            ResolutionOutcome::Resolved {
+                files: vec![PathBuf::from("shared.txt")],
+                rounds: 1,
+            }
+        );
+        assert!(!rebase_in_progress(repo.path()).unwrap());
+        assert_eq!(
+            fs::read_to_string(repo.path().join("shared.txt")).unwrap(),
+            "merged\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn unresolved_conflicts_abort_the_rebase() {
+        let repo = create_conflicting_repo("shared.txt");
+        let before = SystemGit.run(repo.path(), &["rev-parse", "HEAD"]).unwrap();
+
+        // Resolver leaves the markers in place
+        let outcome = resolver(None, local_config())
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome, ResolutionOutcome::Unresolved(files) if files.len() == 1));
+        assert!(!rebase_in_progress(repo.path()).unwrap());
+        assert_eq!(
+            SystemGit.run(repo.path(), &["rev-parse", "HEAD"]).unwrap(),
+            before
+        );
+    }
+
+    #[tokio::test]
+    async fn resolver_errors_abort_the_rebase() {
+        let repo = create_conflicting_repo("shared.txt");
+        let before = SystemGit.run(repo.path(), &["rev-parse", "HEAD"]).unwrap();
+
+        let err = ConflictResolver::new(Arc::new(BrokenResolver), local_config())
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("resolver crashed"));
+        assert!(!rebase_in_progress(repo.path()).unwrap());
+        assert_eq!(
+            SystemGit.run(repo.path(), &["rev-parse", "HEAD"]).unwrap(),
+            before
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_modify_conflicts_are_left_for_a_human() {
+        // feature edits shared.txt while main deletes it
+        let repo = create_conflicting_repo("other.txt");
+        SystemGit
+            .run(repo.path(), &["checkout", "-q", "main"])
+            .unwrap();
+        SystemGit
+            .run(repo.path(), &["rm", "-q", "shared.txt"])
+            .unwrap();
+        SystemGit
+            .run(repo.path(), &["commit", "-q", "-m", "Delete shared"])
+            .unwrap();
+        SystemGit
+            .run(repo.path(), &["checkout", "-q", "feature"])
+            .unwrap();
+        let before = SystemGit.run(repo.path(), &["rev-parse", "HEAD"]).unwrap();
+
+        let outcome = resolver(Some("merged\n"), local_config())
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            ResolutionOutcome::Unresolved(vec![ConflictFile {
+                path: PathBuf::from("shared.txt"),
+                conflict_count: 0,
+                is_simple: false,
+            }])
+        );
+        assert!(!rebase_in_progress(repo.path()).unwrap());
+        assert_eq!(
+            SystemGit.run(repo.path(), &["rev-parse", "HEAD"]).unwrap(),
+            before
+        );
+        assert_eq!(
+            fs::read_to_string(repo.path().join("shared.txt")).unwrap(),
+            "feature\n"
+        );
+    }
+
+    #[test]
+    fn unmerged_paths_are_read_from_porcelain_status() {
+        let status = "UU src/lib.rs\0DU gone.txt\0R  new.rs\0old.rs\0 M other.rs\0DD both.txt\0";
+
+        assert_eq!(
+            unmerged_paths(status),
+            [("UU", "src/lib.rs"), ("DU", "gone.txt"), ("DD", "both.txt")]
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_strategy_returns_error() {
+        let repo = create_conflicting_repo("shared.txt");
+        let config = ConflictResolutionConfig {
+            strategy: ConflictStrategy::Fail,
+            ..local_config()
+        };
+
+        let err = resolver(Some("merged\n"), config)
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("shared.txt"));
+        assert!(!rebase_in_progress(repo.path()).unwrap());
+    }
+
+    #[tokio::test]
+    async fn failed_verification_is_reported() {
+        let repo = create_conflicting_repo("shared.txt");
+        let config = ConflictResolutionConfig {
+            verify_command: Some("grep -q expected shared.txt".to_string()),
+            ..local_config()
+        };
+
+        let outcome = resolver(Some("merged\n"), config)
+            .resolve(repo.path(), "feature", "main")
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome, ResolutionOutcome::VerificationFailed(_)));
+    }
+
+    #[test]
+    fn prompt_lists_conflicts_and_context() {
+        let prompt = resolver(None, local_config())
+            .with_context("Add login")
+            .build_prompt(
+                "feature",
+                "main",
+                &[ConflictFile {
+                    path: PathBuf::from("src/lib.rs"),
+                    conflict_count: 2,
+                    is_simple: true,
+                }],
+            );
+
+        assert!(prompt.contains("Rebasing `feature` onto `main`"));
+        assert!(prompt.contains("- `src/lib.rs` (2 conflicts)"));
+        assert!(prompt.contains("Add login"));
+    }
+}
@@ -0,0 +1,536 @@
+//! Context packs for spawned LLMs.
+//!
+//! Before the primary LLM starts, a [`ContextPack`] is gathered from its
+//! sandbox: a summary of the repository layout, excerpts of the files that
+//! best match the prompt's keywords, recent commits touching the same
+//! subjects, and the project's written conventions. It is appended to the
+//! prompt so the LLM spends less time exploring.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::{GitExecutor, SystemGit};
+use crate::prompt::budget;
+
+/// Files whose conventions are quoted in the pack, in order.
+pub const CONVENTION_FILES: &[&str] = &["CONTRIBUTING.md", "CLAUDE.md"];
+
+/// Files larger than this are never excerpted.
+const MAX_FILE_BYTES: u64 = 100_000;
+
+/// Tracked files scanned for keywords, so huge repositories stay fast.
+const MAX_SCANNED_FILES: usize = 2_000;
+
+/// Lines shown around each keyword match.
+const EXCERPT_CONTEXT_LINES: usize = 3;
+
+/// Lines shown per excerpted file.
+const MAX_EXCERPT_LINES: usize = 40;
+
+/// Lines quoted per convention file.
+const MAX_CONVENTION_LINES: usize = 60;
+
+/// Directory entries in the layout summary.
+const MAX_TREE_ENTRIES: usize = 40;
+
+/// Commit subjects searched for relevant commits.
+const COMMIT_HISTORY: usize = 200;
+
+/// Words too common in prompts to pick out files.
+const STOPWORDS: &[&str] = &[
+    "about", "added", "after", "also", "before", "been", "being", "code", "could", "does", "each",
+    "file", "files", "from", "have", "into", "make", "more", "must", "need", "only", "other",
+    "should", "some", "that", "them", "then", "there", "these", "they", "this", "when", "where",
+    "which", "will", "with", "without", "would", "your",
+];
+
+/// Settings for building context packs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextConfig {
+    /// Whether a context pack is appended to the primary's prompt.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Approximate token budget for the whole pack.
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: usize,
+    /// Most files excerpted.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Most commits listed.
+    #[serde(default = "default_max_commits")]
+    pub max_commits: usize,
+    /// Directory depth of the layout summary.
+    #[serde(default = "default_tree_depth")]
+    pub tree_depth: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_tokens() -> usize {
+    4_000
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+fn default_max_commits() -> usize {
+    5
+}
+
+fn default_tree_depth() -> usize {
+    2
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_tokens: default_max_tokens(),
+            max_files: default_max_files(),
+            max_commits: default_max_commits(),
+            tree_depth: default_tree_depth(),
+        }
+    }
+}
+
+impl ContextConfig {
+    /// Gathers the context for `prompt` from the repository at `dir`.
+    ///
+    /// Sections that cannot be gathered, e.g. outside a git repository,
+    /// are left empty.
+    pub fn build(&self, dir: &Path, prompt: &str) -> ContextPack {
+        let files = tracked_files(dir);
+        let terms = keywords(prompt);
+
+        let mut pack = ContextPack {
+            tree: summarize_tree(&files, self.tree_depth),
+            files: find_relevant_files(dir, &files, &terms, self.max_files),
+            commits: relevant_commits(dir, &terms, self.max_commits),
+            conventions: read_conventions(dir),
+        };
+        pack.fit(self.max_tokens);
+        pack
+    }
+}
+
+/// Excerpt of a file that matched the prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileExcerpt {
+    /// Path relative to the repository root.
+    pub path: String,
+    /// Matching lines with their line numbers and surrounding context.
+    pub excerpt: String,
+}
+
+/// Conventions quoted from a project file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conventions {
+    /// File the conventions were read from.
+    pub path: String,
+    /// The file's leading lines.
+    pub text: String,
+}
+
+/// Repository context gathered for a prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextPack {
+    /// Directories with their file counts, and top-level files.
+    pub tree: Vec<String>,
+    /// Files matching the prompt, best first.
+    pub files: Vec<FileExcerpt>,
+    /// Commits as `<hash> <subject>`, relevant ones first.
+    pub commits: Vec<String>,
+    /// Project conventions.
+    pub conventions: Vec<Conventions>,
+}
+
+impl ContextPack {
+    /// Returns whether nothing was gathered.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+            && self.files.is_empty()
+            && self.commits.is_empty()
+            && self.conventions.is_empty()
+    }
+
+    /// Appends the pack to `prompt`; an empty pack leaves it unchanged.
+    pub fn inject(&self, prompt: &str) -> String {
+        if self.is_empty() {
+            return prompt.to_string();
+        }
+        format!("{}\n\n---\n\n{}", prompt, self.to_markdown())
+    }
+
+    /// Formats the pack as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from(
+            "## Repository Context\n\n\
+             Gathered before you started; use it instead of exploring from scratch.\n",
+        );
+
+        if !self.tree.is_empty() {
+            out.push_str("\n### Layout\n\n");
+            for entry in &self.tree {
+                out.push_str(entry);
+                out.push('\n');
+            }
+        }
+
+        if !self.files.is_empty() {
+            out.push_str("\n### Relevant Files\n");
+            for file in &self.files {
+                out.push_str(&format!(
+                    "\n`{}`:\n\n```\n{}\n```\n",
+                    file.path, file.excerpt
+                ));
+            }
+        }
+
+        if !self.commits.is_empty() {
+            out.push_str("\n### Recent Commits\n\n");
+            for commit in &self.commits {
+                out.push_str(&format!("- {}\n", commit));
+            }
+        }
+
+        if !self.conventions.is_empty() {
+            out.push_str("\n### Conventions\n");
+            for conventions in &self.conventions {
+                out.push_str(&format!(
+                    "\nFrom `{}`:\n\n{}\n",
+                    conventions.path, conventions.text
+                ));
+            }
+        }
+
+        out
+    }
+
+    /// Drops the least relevant excerpts, then shortens the rest, until
+    /// the pack fits in `max_tokens`.
+    fn fit(&mut self, max_tokens: usize) {
+        let tokens = |pack: &ContextPack| budget::estimate_tokens(&pack.to_markdown(), "");
+        while tokens(self) > max_tokens && self.files.len() > 1 {
+            self.files.pop();
+        }
+        if tokens(self) <= max_tokens {
+            return;
+        }
+
+        // Split what is left between the excerpt and the conventions
+        let pieces = self.files.len() + self.conventions.len();
+        if pieces == 0 {
+            return;
+        }
+        let share = max_tokens / 2 / pieces;
+        for file in &mut self.files {
+            file.excerpt = budget::truncate_text(&file.excerpt, share, "");
+        }
+        for conventions in &mut self.conventions {
+            conventions.text = budget::truncate_text(&conventions.text, share, "");
+        }
+    }
+}
+
+/// Returns the distinct lowercase words of `prompt` that can pick out
+/// files: at least four characters and not a stopword.
+fn keywords(prompt: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in prompt.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let word = word.to_lowercase();
+        if word.len() >= 4
+            && !word.chars().all(|c| c.is_ascii_digit())
+            && !STOPWORDS.contains(&word.as_str())
+            && !terms.contains(&word)
+        {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Returns the repository's tracked files.
+fn tracked_files(dir: &Path) -> Vec<String> {
+    SystemGit
+        .run(dir, &["ls-files"])
+        .map(|out| out.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Summarizes `files` as directories up to `depth` levels deep, with
+/// their file counts, followed by the top-level files.
+fn summarize_tree(files: &[String], depth: usize) -> Vec<String> {
+    let mut dirs: Vec<(String, usize)> = Vec::new();
+    let mut top_level = Vec::new();
+    for file in files {
+        let parts: Vec<&str> = file.split('/').collect();
+        if parts.len() == 1 {
+            top_level.push(format!("- `{}`", file));
+            continue;
+        }
+        for level in 1..=depth.min(parts.len() - 1) {
+            let dir = parts[..level].join("/");
+            match dirs.iter_mut().find(|(d, _)| *d == dir) {
+                Some((_, count)) => *count += 1,
+                None => dirs.push((dir, 1)),
+            }
+        }
+    }
+    dirs.sort();
+
+    let mut tree: Vec<String> = dirs
+        .into_iter()
+        .map(|(dir, count)| {
+            let indent = "  ".repeat(dir.matches('/').count());
+            format!(
+                "{}- `{}/` ({} file{})",
+                indent,
+                dir,
+                count,
+                if count == 1 { "" } else { "s" }
+            )
+        })
+        .collect();
+    tree.extend(top_level);
+    if tree.len() > MAX_TREE_ENTRIES {
+        let hidden = tree.len() - MAX_TREE_ENTRIES;
+        tree.truncate(MAX_TREE_ENTRIES);
+        tree.push(format!("- ... {} more", hidden));
+    }
+    tree
+}
+
+/// Scores files by keyword matches in their path and content and returns
+/// excerpts of the best `max_files`.
+fn find_relevant_files(
+    dir: &Path,
+    files: &[String],
+    terms: &[String],
+    max_files: usize,
+) -> Vec<FileExcerpt> {
+    if terms.is_empty() || max_files == 0 {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(usize, &String, String)> = Vec::new();
+    for file in files.iter().take(MAX_SCANNED_FILES) {
+        let path = dir.join(file);
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.len() > MAX_FILE_BYTES {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+
+        let lower_path = file.to_lowercase();
+        let lower_content = content.to_lowercase();
+        let score: usize = terms
+            .iter()
+            .map(|term| {
+                let in_path = if lower_path.contains(term.as_str()) {
+                    5
+                } else {
+                    0
+                };
+                in_path + lower_content.matches(term.as_str()).count().min(5)
+            })
+            .sum();
+        if score > 0 {
+            scored.push((score, file, content));
+        }
+    }
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+    scored
+        .into_iter()
+        .take(max_files)
+        .map(|(_, file, content)| FileExcerpt {
+            path: file.clone(),
+            excerpt: excerpt(&content, terms),
+        })
+        .collect()
+}
+
+/// Returns the lines of `content` around keyword matches, numbered, or
+/// the file's first lines when only its path matched.
+fn excerpt(content: &str, terms: &[String]) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut shown = vec![false; lines.len()];
+    let mut count = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let lower = line.to_lowercase();
+        if !terms.iter().any(|term| lower.contains(term.as_str())) {
+            continue;
+        }
+        let start = index.saturating_sub(EXCERPT_CONTEXT_LINES);
+        let end = (index + EXCERPT_CONTEXT_LINES + 1).min(lines.len());
+        for flag in &mut shown[start..end] {
+            if !*flag && count < MAX_EXCERPT_LINES {
+                *flag = true;
+                count += 1;
+            }
+        }
+        if count >= MAX_EXCERPT_LINES {
+            break;
+        }
+    }
+    if count == 0 {
+        let end = lines.len().min(MAX_EXCERPT_LINES / 2);
+        shown[..end].iter_mut().for_each(|flag| *flag = true);
+    }
+
+    let mut out = Vec::new();
+    let mut previous = None;
+    for (index, line) in lines.iter().enumerate().filter(|(i, _)| shown[*i]) {
+        if previous.is_some_and(|p: usize| p + 1 != index) {
+            out.push("...".to_string());
+        }
+        out.push(format!("{:>4}: {}", index + 1, line));
+        previous = Some(index);
+    }
+    out.join("\n")
+}
+
+/// Returns up to `max` recent commits, those whose subject mentions a
+/// keyword first.
+fn relevant_commits(dir: &Path, terms: &[String], max: usize) -> Vec<String> {
+    let Ok(log) = SystemGit.run(
+        dir,
+        &["log", &format!("-{}", COMMIT_HISTORY), "--format=%h %s"],
+    ) else {
+        return Vec::new();
+    };
+
+    let (mut relevant, recent): (Vec<&str>, Vec<&str>) = log.lines().partition(|line| {
+        let lower = line.to_lowercase();
+        terms.iter().any(|term| lower.contains(term.as_str()))
+    });
+    relevant.extend(recent);
+    relevant.into_iter().take(max).map(str::to_string).collect()
+}
+
+/// Reads the leading lines of each convention file present in `dir`.
+fn read_conventions(dir: &Path) -> Vec<Conventions> {
+    CONVENTION_FILES
+        .iter()
+        .filter_map(|file| {
+            let content = std::fs::read_to_string(dir.join(file)).ok()?;
+            let mut text = content
+                .lines()
+                .take(MAX_CONVENTION_LINES)
+                .collect::<Vec<_>>()
+                .join("\n");
+            if content.lines().count() > MAX_CONVENTION_LINES {
+                text.push_str(&format!("\n\n(see `{}` for the rest)", file));
+            }
+            (!text.trim().is_empty()).then(|| Conventions {
+                path: file.to_string(),
+                text,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let run = |args: &[&str]| {
+            let output = Command::new("git")
+                .current_dir(dir.path())
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+        };
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "test@example.com"]);
+        run(&["config", "user.name", "Test"]);
+
+        std::fs::create_dir_all(dir.path().join("src/auth")).unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Widgets\n").unwrap();
+        std::fs::write(
+            dir.path().join("CLAUDE.md"),
+            "# Conventions\n\n- Errors use thiserror\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("src/lib.rs"),
+            "pub mod auth;\npub mod render;\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("src/auth/token.rs"),
+            "use std::time::Duration;\n\n/// Issues session tokens.\npub fn issue_token(user: &str) -> String {\n    format!(\"token-{}\", user)\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/render.rs"), "pub fn render() {}\n").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "Add token issuing"]);
+        std::fs::write(dir.path().join("src/render.rs"), "pub fn render() { }\n").unwrap();
+        run(&["commit", "-q", "-am", "Tidy renderer"]);
+        dir
+    }
+
+    #[test]
+    fn extracts_keywords() {
+        assert_eq!(
+            keywords("Add token expiry to the auth module; it should refresh tokens"),
+            vec!["token", "expiry", "auth", "module", "refresh", "tokens"]
+        );
+    }
+
+    #[test]
+    fn builds_pack_for_prompt() {
+        let dir = repo();
+        let pack = ContextConfig::default().build(dir.path(), "Add expiry to the session token");
+
+        assert!(pack.tree.contains(&"- `src/` (3 files)".to_string()));
+        assert!(pack.tree.contains(&"  - `src/auth/` (1 file)".to_string()));
+        assert!(pack.tree.contains(&"- `CLAUDE.md`".to_string()));
+
+        assert_eq!(pack.files[0].path, "src/auth/token.rs");
+        assert!(pack.files[0]
+            .excerpt
+            .contains("   4: pub fn issue_token(user: &str) -> String {"));
+        assert!(pack.files.iter().all(|f| f.path != "src/render.rs"));
+
+        // The matching commit comes before the more recent one
+        assert!(pack.commits[0].ends_with("Add token issuing"));
+        assert!(pack.commits[1].ends_with("Tidy renderer"));
+
+        assert_eq!(pack.conventions[0].path, "CLAUDE.md");
+        let prompt = pack.inject("Add expiry to the session token");
+        assert!(
+            prompt.starts_with("Add expiry to the session token\n\n---\n\n## Repository Context")
+        );
+        assert!(prompt.contains("- Errors use thiserror"));
+    }
+
+    #[test]
+    fn fits_token_budget_and_tolerates_non_repos() {
+        let dir = repo();
+        let config = ContextConfig {
+            max_tokens: 150,
+            ..Default::default()
+        };
+        let pack = config.build(dir.path(), "tokens render auth");
+        assert!(pack.files.len() <= 1);
+
+        let empty = TempDir::new().unwrap();
+        let pack = ContextConfig::default().build(empty.path(), "anything");
+        assert!(pack.is_empty());
+        assert_eq!(pack.inject("anything"), "anything");
+    }
+}
@@ -0,0 +1,376 @@
+//! Aborting and rolling back a cruise run.
+//!
+//! `cruise abort` is the escape hatch for a run that has gone wrong. Using
+//! the run's saved [`CruiseStatus`], it stops the cruise process and its
+//! LLM children, abandons (or closes) the PRs it opened, removes its
+//! sandboxes, optionally deletes its work branches, and reopens the beads
+//! issues of its plan that it had started or finished. The process is only
+//! signalled if its start time and command line still match the ones
+//! recorded, so a reused PID is left alone. Every step is attempted even if an
+//! earlier one fails; failures are collected in the [`AbortReport`].
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use super::beads;
+use super::config::AbortConfig;
+use super::planner::BEADS_DIR;
+use super::status::{process_identity, CruiseStatus};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::PRManager;
+
+/// Phase recorded for an aborted run.
+pub const ABORTED_PHASE: &str = "aborted";
+
+/// Beads statuses reset to `pending` on abort.
+const REOPENED_STATUSES: &[&str] = &["in_progress", "completed", "blocked"];
+
+/// What an abort did.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AbortReport {
+    /// Whether the cruise process was signalled.
+    pub stopped: bool,
+    /// PRs closed or marked abandoned.
+    pub abandoned_prs: Vec<String>,
+    /// Sandboxes removed.
+    pub removed_sandboxes: Vec<PathBuf>,
+    /// Work branches deleted.
+    pub deleted_branches: Vec<String>,
+    /// Beads issues set back to `pending`.
+    pub reopened_issues: Vec<String>,
+    /// Steps that failed.
+    pub errors: Vec<String>,
+}
+
+impl AbortReport {
+    /// Formats the report for a terminal.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.stopped {
+            out.push_str("Stopped the running cruise\n");
+        }
+        for (label, items) in [
+            ("Abandoned PR", &self.abandoned_prs),
+            ("Deleted branch", &self.deleted_branches),
+            ("Reopened issue", &self.reopened_issues),
+        ] {
+            for item in items {
+                out.push_str(&format!("{}: {}\n", label, item));
+            }
+        }
+        for sandbox in &self.removed_sandboxes {
+            out.push_str(&format!("Removed sandbox: {}\n", sandbox.display()));
+        }
+        for error in &self.errors {
+            out.push_str(&format!("Failed: {}\n", error));
+        }
+        out
+    }
+}
+
+/// Rolls back a cruise run.
+pub struct RunAborter {
+    repo_path: PathBuf,
+    config: AbortConfig,
+}
+
+impl RunAborter {
+    /// Creates an aborter for runs in `repo_path`.
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            config: AbortConfig::default(),
+        }
+    }
+
+    /// Sets what the abort undoes.
+    pub fn with_config(mut self, config: AbortConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Aborts the run described by `status` and records it as aborted.
+    pub fn abort(&self, status: &mut CruiseStatus) -> Result<AbortReport> {
+        let mut report = AbortReport::default();
+
+        if let Some(pid) = status.pid.filter(|pid| *pid != std::process::id()) {
+            match stop_process(pid, status.process.as_deref()) {
+                Ok(stopped) => report.stopped = stopped,
+                Err(e) => report.errors.push(format!("stop process {}: {}", pid, e)),
+            }
+        }
+
+        let manager = PRManager::new(self.repo_path.clone());
+        for pr in &status.pr_urls {
+            match manager.mark_abandoned(pr, self.config.close_prs, self.config.delete_branches) {
+                Ok(()) => report.abandoned_prs.push(pr.clone()),
+                Err(e) => report.errors.push(format!("abandon {}: {}", pr, e)),
+            }
+        }
+
+        // Removing a worktree from inside it works whichever repository
+        // it belongs to
+        for sandbox in &status.sandboxes {
+            if !sandbox.exists() {
+                continue;
+            }
+            let path = sandbox.display().to_string();
+            match SystemGit.run(sandbox, &["worktree", "remove", "--force", &path]) {
+                Ok(_) => report.removed_sandboxes.push(sandbox.clone()),
+                Err(e) => report
+                    .errors
+                    .push(format!("remove sandbox {}: {}", path, e)),
+            }
+        }
+        let mut repos = vec![self.repo_path.clone()];
+        repos.extend(
+            status
+                .repos
+                .iter()
+                .filter(|r| **r != self.repo_path)
+                .cloned(),
+        );
+        for repo in &repos {
+            if let Err(e) = SystemGit.run(repo, &["worktree", "prune"]) {
+                report.errors.push(format!("prune worktrees: {}", e));
+            }
+        }
+
+        if self.config.delete_branches {
+            for branch in &status.branches {
+                // A branch is in at least one of the run's repositories
+                let mut errors = Vec::new();
+                for repo in &repos {
+                    if let Err(e) = SystemGit.run(repo, &["branch", "-D", branch]) {
+                        errors.push(e);
+                    }
+                    // Closing a PR with --delete-branch may already have removed it
+                    let _ = SystemGit.run(repo, &["push", "origin", "--delete", branch]);
+                }
+                if errors.len() < repos.len() {
+                    report.deleted_branches.push(branch.clone());
+                } else {
+                    report
+                        .errors
+                        .push(format!("delete {}: {}", branch, errors[0]));
+                }
+            }
+        }
+
+        match reopen_beads(&self.repo_path.join(BEADS_DIR), &status.task_ids) {
+            Ok(reopened) => report.reopened_issues = reopened,
+            Err(e) => report.errors.push(format!("reopen beads issues: {}", e)),
+        }
+
+        status.set_phase(ABORTED_PHASE);
+        status.cancelled = Some("aborted".to_string());
+        status.pid = None;
+        status.process = None;
+        status.save(&self.repo_path)?;
+
+        tracing::info!(
+            run_id = %status.run_id,
+            errors = report.errors.len(),
+            "aborted cruise run"
+        );
+        Ok(report)
+    }
+}
+
+/// Sends SIGTERM to `pid`'s children and then to `pid`. Returns `false` if
+/// the process had already exited.
+///
+/// Fails without signalling anything unless `pid` is still the process
+/// described by `expected` (see [`process_identity`]).
+fn stop_process(pid: u32, expected: Option<&str>) -> Result<bool> {
+    let Some(identity) = process_identity(pid) else {
+        return Ok(false);
+    };
+    if expected != Some(identity.as_str()) {
+        return Err(Error::ForeignProcess { pid, identity });
+    }
+
+    let pid = pid.to_string();
+    // LLM CLIs are only killed when the cruise drops them, which a signal
+    // skips, so stop them first
+    let _ = Command::new("pkill").args(["-TERM", "-P", &pid]).output();
+    let output = Command::new("kill").args(["-TERM", &pid]).output()?;
+    if !output.status.success() {
+        return Err(Error::command("kill", &output));
+    }
+    Ok(true)
+}
+
+/// Sets the issues in `task_ids` that were started, finished, or blocked
+/// back to `pending`, returning their IDs. Issues of other runs are left
+/// alone.
+fn reopen_beads(beads_dir: &Path, task_ids: &[String]) -> Result<Vec<String>> {
+    let mut reopened = Vec::new();
+    for id in task_ids {
+        let path = beads_dir.join(format!("{}.md", id));
+        if !path.exists() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let Some(rewritten) = reopen_issue(&content) else {
+            continue;
+        };
+        std::fs::write(&path, rewritten)?;
+        reopened.push(id.clone());
+    }
+    reopened.sort();
+    Ok(reopened)
+}
+
+/// Rewrites the frontmatter status of an issue to `pending`, or `None` if
+/// it does not need reopening.
+fn reopen_issue(content: &str) -> Option<String> {
+    let status = beads::status(content)?;
+    REOPENED_STATUSES
+        .contains(&status)
+        .then(|| beads::set_status(content, "pending"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for args in [
+            vec!["init", "-q", "-b", "main"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+            vec!["commit", "-q", "--allow-empty", "-m", "Initial"],
+        ] {
+            SystemGit.run(dir.path(), &args).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn reopen_issue_resets_started_tasks() {
+        let issue = "---\nid: CRUISE-001\nstatus: completed\n---\n\n# Setup\nstatus: completed\n";
+
+        let reopened = reopen_issue(issue).unwrap();
+
+        assert!(reopened.starts_with("---\nid: CRUISE-001\nstatus: pending\n---"));
+        assert!(reopened.ends_with("# Setup\nstatus: completed\n"));
+        assert!(reopen_issue("---\nstatus: skipped\n---\n").is_none());
+    }
+
+    #[test]
+    fn abort_removes_sandboxes_branches_and_reopens_issues() {
+        let repo = create_test_repo();
+        let sandboxes = TempDir::new().unwrap();
+        let sandbox = sandboxes.path().join("task-1");
+        SystemGit
+            .run(
+                repo.path(),
+                &[
+                    "worktree",
+                    "add",
+                    "-q",
+                    "-b",
+                    "cruise/task-1",
+                    sandbox.to_str().unwrap(),
+                ],
+            )
+            .unwrap();
+        let beads = repo.path().join(BEADS_DIR);
+        std::fs::create_dir_all(&beads).unwrap();
+        std::fs::write(
+            beads.join("CRUISE-001.md"),
+            "---\nstatus: in_progress\n---\n",
+        )
+        .unwrap();
+        std::fs::write(beads.join("CRUISE-002.md"), "---\nstatus: pending\n---\n").unwrap();
+        // Another run's task
+        std::fs::write(beads.join("CRUISE-009.md"), "---\nstatus: completed\n---\n").unwrap();
+
+        let mut status = CruiseStatus::new("run-1", "Build an API");
+        status.pid = None;
+        status.task_ids = vec!["CRUISE-001".to_string(), "CRUISE-002".to_string()];
+        status.add_branch("cruise/task-1");
+        status.add_sandbox(&sandbox);
+        let aborter = RunAborter::new(repo.path()).with_config(AbortConfig {
+            close_prs: true,
+            delete_branches: true,
+        });
+
+        let report = aborter.abort(&mut status).unwrap();
+
+        assert!(!sandbox.exists());
+        assert_eq!(report.removed_sandboxes, vec![sandbox]);
+        assert_eq!(report.deleted_branches, vec!["cruise/task-1"]);
+        assert_eq!(report.reopened_issues, vec!["CRUISE-001"]);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        let saved = CruiseStatus::load(repo.path(), "run-1").unwrap();
+        assert_eq!(saved.phase, ABORTED_PHASE);
+        assert_eq!(
+            std::fs::read_to_string(beads.join("CRUISE-009.md")).unwrap(),
+            "---\nstatus: completed\n---\n"
+        );
+    }
+
+    #[test]
+    fn reused_pids_are_not_signalled() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+
+        let err = stop_process(pid, Some("Thu Jan  1 00:00:00 1970 cruise")).unwrap_err();
+        assert!(err.to_string().contains("not signalling"));
+        let err = stop_process(pid, None).unwrap_err();
+        assert!(err.to_string().contains("not signalling"));
+        assert!(child.try_wait().unwrap().is_none());
+
+        let identity = process_identity(pid).unwrap();
+        assert!(stop_process(pid, Some(&identity)).unwrap());
+        assert!(child.wait().unwrap().code().is_none());
+    }
+
+    #[test]
+    fn abort_deletes_branches_in_every_repository() {
+        let (api, client) = (create_test_repo(), create_test_repo());
+        SystemGit
+            .run(client.path(), &["branch", "cruise/client-task"])
+            .unwrap();
+        let mut status = CruiseStatus::new("run-1", "Build an API");
+        status.pid = None;
+        status.add_repo(client.path());
+        status.add_branch("cruise/client-task");
+        status.add_branch("cruise/missing");
+        let aborter = RunAborter::new(api.path()).with_config(AbortConfig {
+            close_prs: false,
+            delete_branches: true,
+        });
+
+        let report = aborter.abort(&mut status).unwrap();
+
+        assert_eq!(report.deleted_branches, vec!["cruise/client-task"]);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].starts_with("delete cruise/missing"));
+    }
+
+    #[test]
+    fn abort_keeps_branches_by_default() {
+        let repo = create_test_repo();
+        SystemGit
+            .run(repo.path(), &["branch", "cruise/task-1"])
+            .unwrap();
+        let mut status = CruiseStatus::new("run-1", "Build an API");
+        status.pid = None;
+        status.add_branch("cruise/task-1");
+
+        let report = RunAborter::new(repo.path()).abort(&mut status).unwrap();
+
+        assert!(report.deleted_branches.is_empty());
+        SystemGit
+            .run(repo.path(), &["rev-parse", "--verify", "cruise/task-1"])
+            .unwrap();
+    }
+}
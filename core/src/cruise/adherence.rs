@@ -0,0 +1,481 @@
+//! Plan adherence checking.
+//!
+//! Validation compares what the build phase produced with what the plan
+//! asked for. Commits are attributed to tasks by their `Task-Id` trailer,
+//! and an LLM then classifies every task from its acceptance criteria, the
+//! commits and the changed files as implemented, partial, missing or
+//! deviated. Tasks the LLM leaves out, or all tasks when no LLM is
+//! configured or its answer cannot be parsed, are classified from the
+//! trailers and the task status alone.
+//!
+//! The checks then decide which beads issues are closed: only tasks found
+//! implemented are marked completed, and the rest stay open with a note on
+//! their progress.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use super::beads::{set_status, take_section};
+use super::planner::extract_json;
+use super::result::{AdherenceCheck, AdherenceStatus};
+use super::task::{CruisePlan, CruiseTask, TaskStatus};
+use crate::commit::task_trailer;
+use crate::error::{Error, Result};
+use crate::runner::{LLMOutput, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::SandboxManifest;
+use crate::spawn::{ChangeKind, CommitInfo, FileChange};
+
+/// Heading of the progress note added to beads issues left open.
+const PROGRESS_HEADING: &str = "## Progress";
+
+/// Changed files listed in the classification prompt; the rest are
+/// summarized as a count.
+const MAX_PROMPT_FILES: usize = 200;
+
+/// Classifies how well a branch implements a plan.
+#[derive(Default)]
+pub struct AdherenceChecker {
+    runner: Option<Arc<dyn LLMRunner>>,
+}
+
+impl AdherenceChecker {
+    /// Creates a checker that classifies from commit trailers only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the LLM that classifies tasks.
+    pub fn with_runner(mut self, runner: Arc<dyn LLMRunner>) -> Self {
+        self.runner = Some(runner);
+        self
+    }
+
+    /// Returns one check per task of `plan`, in plan order.
+    ///
+    /// `files` and `commits` are the branch's changes since its base, as
+    /// returned by [`branch_changes`](crate::spawn::branch_changes).
+    pub async fn check(
+        &self,
+        plan: &CruisePlan,
+        files: &[FileChange],
+        commits: &[CommitInfo],
+        work_dir: &Path,
+    ) -> Vec<AdherenceCheck> {
+        let fallback = attributed_checks(plan, commits);
+        let Some(runner) = &self.runner else {
+            return fallback;
+        };
+
+        let classified = match self.classify(runner, plan, files, commits, work_dir).await {
+            Ok(classified) => classified,
+            Err(e) => {
+                tracing::warn!(error = %e, "adherence classification failed, using commit trailers");
+                return fallback;
+            }
+        };
+        fallback
+            .into_iter()
+            .map(|check| {
+                classified
+                    .iter()
+                    .find(|c| c.task_id == check.task_id)
+                    .cloned()
+                    .unwrap_or(check)
+            })
+            .collect()
+    }
+
+    async fn classify(
+        &self,
+        runner: &Arc<dyn LLMRunner>,
+        plan: &CruisePlan,
+        files: &[FileChange],
+        commits: &[CommitInfo],
+        work_dir: &Path,
+    ) -> Result<Vec<AdherenceCheck>> {
+        let config = LLMSpawnConfig {
+            prompt: adherence_prompt(plan, files, commits),
+            working_dir: work_dir.to_path_buf(),
+            manifest: SandboxManifest::default(),
+            model: None,
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
+        let (result, output) = tokio::join!(runner.spawn(config, tx), async {
+            let mut lines = Vec::new();
+            while let Some(output) = rx.recv().await {
+                if let LLMOutput::Stdout(line) = output {
+                    lines.push(line);
+                }
+            }
+            lines.join("\n")
+        });
+        if !result?.success {
+            tracing::warn!(runner = %runner.name(), "adherence check exited unsuccessfully");
+        }
+
+        parse_adherence_json(&output, plan)
+    }
+}
+
+/// Classifies each task of `plan` from the commits carrying its `Task-Id`
+/// trailer: a completed task with commits is implemented, an unfinished
+/// one partial, and a task without commits missing.
+pub fn attributed_checks(plan: &CruisePlan, commits: &[CommitInfo]) -> Vec<AdherenceCheck> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for commit in commits {
+        if let Some(task_id) = task_trailer(&commit.message) {
+            *counts.entry(task_id).or_default() += 1;
+        }
+    }
+
+    plan.tasks
+        .iter()
+        .map(|task| {
+            let commits = counts.get(&task.id).copied().unwrap_or(0);
+            let (status, notes) = match (commits, task.status) {
+                (0, TaskStatus::Skipped) => {
+                    (AdherenceStatus::Missing, "task was skipped".to_string())
+                }
+                (0, _) => (
+                    AdherenceStatus::Missing,
+                    "no commits for this task".to_string(),
+                ),
+                (n, TaskStatus::Completed) => (AdherenceStatus::Implemented, commit_count(n)),
+                (n, _) => (
+                    AdherenceStatus::Partial,
+                    format!("{}, but the task did not complete", commit_count(n)),
+                ),
+            };
+            check(task, status, Some(notes))
+        })
+        .collect()
+}
+
+fn commit_count(n: usize) -> String {
+    format!("{} commit{}", n, if n == 1 { "" } else { "s" })
+}
+
+fn check(task: &CruiseTask, status: AdherenceStatus, notes: Option<String>) -> AdherenceCheck {
+    AdherenceCheck {
+        task_id: task.id.clone(),
+        subject: task.subject.clone(),
+        status,
+        notes,
+    }
+}
+
+/// Builds the prompt asking an LLM to classify each task of `plan`.
+pub fn adherence_prompt(plan: &CruisePlan, files: &[FileChange], commits: &[CommitInfo]) -> String {
+    let mut prompt = String::from(
+        "You are checking whether a branch implements its plan. For each task, \
+         decide from the commits and changed files whether it was implemented \
+         as planned, partially implemented, not implemented (missing), or \
+         implemented differently than planned (deviated).\n\n## Tasks\n\n",
+    );
+    for task in &plan.tasks {
+        prompt.push_str(&format!("### {}: {}\n\n", task.id, task.subject));
+        if !task.description.is_empty() {
+            prompt.push_str(&format!("{}\n\n", task.description));
+        }
+        for criterion in &task.acceptance_criteria {
+            prompt.push_str(&format!("- [ ] {}\n", criterion));
+        }
+        if !task.acceptance_criteria.is_empty() {
+            prompt.push('\n');
+        }
+    }
+
+    prompt.push_str("## Commits\n\n");
+    if commits.is_empty() {
+        prompt.push_str("(none)\n");
+    }
+    for commit in commits {
+        let short = commit.hash.get(..12).unwrap_or(&commit.hash);
+        let subject = commit.message.lines().next().unwrap_or_default();
+        prompt.push_str(&format!("- `{}` {}\n", short, subject));
+        if let Some(task_id) = task_trailer(&commit.message) {
+            prompt.push_str(&format!("  Task-Id: {}\n", task_id));
+        }
+    }
+
+    prompt.push_str("\n## Changed files\n\n");
+    if files.is_empty() {
+        prompt.push_str("(none)\n");
+    }
+    for file in files.iter().take(MAX_PROMPT_FILES) {
+        let kind = match file.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+        };
+        prompt.push_str(&format!(
+            "- `{}` ({}, +{} -{})\n",
+            file.path.display(),
+            kind,
+            file.additions,
+            file.deletions
+        ));
+    }
+    if files.len() > MAX_PROMPT_FILES {
+        prompt.push_str(&format!(
+            "- ... and {} more\n",
+            files.len() - MAX_PROMPT_FILES
+        ));
+    }
+
+    prompt.push_str(
+        "\nRespond with only this JSON, one entry per task:\n\n```json\n\
+         {\"tasks\": [{\"id\": \"CRUISE-001\", \"status\": \"implemented|partial|missing|deviated\", \
+         \"notes\": \"one sentence of evidence\"}]}\n```\n",
+    );
+    prompt
+}
+
+/// LLM answer to [`adherence_prompt`].
+#[derive(Debug, Deserialize)]
+struct AdherenceJson {
+    tasks: Vec<TaskAdherenceJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskAdherenceJson {
+    id: String,
+    status: AdherenceStatus,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// Parses the LLM's classification of the tasks of `plan`, ignoring
+/// entries for tasks the plan does not have.
+pub fn parse_adherence_json(output: &str, plan: &CruisePlan) -> Result<Vec<AdherenceCheck>> {
+    let json = extract_json(output).ok_or_else(|| Error::NoJson("adherence output".to_string()))?;
+    let parsed: AdherenceJson =
+        serde_json::from_str(json).map_err(|e| Error::json("adherence output", e))?;
+
+    Ok(parsed
+        .tasks
+        .into_iter()
+        .filter_map(|entry| {
+            let task = plan.tasks.iter().find(|t| t.id == entry.id)?;
+            Some(check(
+                task,
+                entry.status,
+                entry.notes.filter(|n| !n.trim().is_empty()),
+            ))
+        })
+        .collect())
+}
+
+/// Beads issues updated from adherence checks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BeadsClosure {
+    /// Issues marked completed.
+    pub closed: Vec<String>,
+    /// Issues left open with a progress note.
+    pub open: Vec<String>,
+}
+
+/// Updates the beads issue of each checked task in `beads_dir`.
+///
+/// Implemented tasks are marked `completed`. Partial and deviated tasks are
+/// kept `in_progress` and missing ones `pending`, each with a progress note
+/// replacing any earlier one. Checks without an issue file are skipped.
+pub fn close_beads_issues(beads_dir: &Path, checks: &[AdherenceCheck]) -> Result<BeadsClosure> {
+    let mut closure = BeadsClosure::default();
+    for check in checks {
+        let path = beads_dir.join(format!("{}.md", check.task_id));
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let updated = match check.status {
+            AdherenceStatus::Implemented => {
+                closure.closed.push(check.task_id.clone());
+                set_status(&content, "completed")
+            }
+            status => {
+                closure.open.push(check.task_id.clone());
+                let open = if status == AdherenceStatus::Missing {
+                    "pending"
+                } else {
+                    "in_progress"
+                };
+                with_progress_note(&set_status(&content, open), check)
+            }
+        };
+        std::fs::write(&path, updated)?;
+    }
+    tracing::info!(
+        closed = closure.closed.len(),
+        open = closure.open.len(),
+        "updated beads issues from plan adherence"
+    );
+    Ok(closure)
+}
+
+/// Appends a progress note for `check` to a beads issue, dropping the one
+/// an earlier run left.
+fn with_progress_note(content: &str, check: &AdherenceCheck) -> String {
+    let (body, _) = take_section(content, PROGRESS_HEADING);
+    let mut note = format!(
+        "{}\n\n{}\n\nValidation found this task {}.",
+        body.trim_end(),
+        PROGRESS_HEADING,
+        check.status.description()
+    );
+    if let Some(notes) = &check.notes {
+        note.push_str(&format!(" {}", notes.trim()));
+    }
+    note.push('\n');
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn plan() -> CruisePlan {
+        let mut plan = CruisePlan::new("Build an API");
+        plan.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Setup"),
+            CruiseTask::new("CRUISE-002", "Auth"),
+            CruiseTask::new("CRUISE-003", "Docs"),
+        ];
+        plan.tasks[0].status = TaskStatus::Completed;
+        plan.tasks[1].status = TaskStatus::Blocked;
+        plan.tasks[2].status = TaskStatus::Skipped;
+        plan
+    }
+
+    fn commit(hash: &str, message: &str) -> CommitInfo {
+        CommitInfo {
+            hash: hash.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Runner that prints a fixed answer.
+    struct Classifier(String);
+
+    #[async_trait::async_trait]
+    impl LLMRunner for Classifier {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            assert!(config.prompt.contains("`src/auth.rs` (added, +40 -0)"));
+            let _ = output_tx.send(LLMOutput::Stdout(self.0.clone())).await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "classifier"
+        }
+    }
+
+    #[test]
+    fn commits_are_attributed_by_task_trailer() {
+        let commits = [
+            commit("a1", "feat: scaffold\n\nTask-Id: CRUISE-001"),
+            commit("b2", "feat: login\n\nTask-Id: CRUISE-002"),
+            commit("c3", "chore: tidy"),
+        ];
+
+        let checks = attributed_checks(&plan(), &commits);
+
+        let statuses: Vec<_> = checks.iter().map(|c| c.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                AdherenceStatus::Implemented,
+                AdherenceStatus::Partial,
+                AdherenceStatus::Missing
+            ]
+        );
+        assert_eq!(checks[0].notes.as_deref(), Some("1 commit"));
+        assert_eq!(checks[2].notes.as_deref(), Some("task was skipped"));
+    }
+
+    #[tokio::test]
+    async fn llm_classification_overrides_trailers() {
+        let answer = r#"```json
+{"tasks": [
+  {"id": "CRUISE-002", "status": "deviated", "notes": "Uses sessions instead of JWT"},
+  {"id": "CRUISE-009", "status": "implemented"}
+]}
+```"#;
+        let checker = AdherenceChecker::new().with_runner(Arc::new(Classifier(answer.to_string())));
+        let files = [FileChange {
+            path: PathBuf::from("src/auth.rs"),
+            kind: ChangeKind::Added,
+            additions: 40,
+            deletions: 0,
+        }];
+        let commits = [commit("a1", "feat: scaffold\n\nTask-Id: CRUISE-001")];
+
+        let checks = checker
+            .check(&plan(), &files, &commits, Path::new("."))
+            .await;
+
+        assert_eq!(checks.len(), 3);
+        assert_eq!(checks[0].status, AdherenceStatus::Implemented);
+        assert_eq!(checks[1].status, AdherenceStatus::Deviated);
+        assert_eq!(
+            checks[1].notes.as_deref(),
+            Some("Uses sessions instead of JWT")
+        );
+        assert_eq!(checks[2].status, AdherenceStatus::Missing);
+
+        let garbled = AdherenceChecker::new().with_runner(Arc::new(Classifier("no idea".into())));
+        let checks = garbled
+            .check(&plan(), &files, &commits, Path::new("."))
+            .await;
+        assert_eq!(checks[1].status, AdherenceStatus::Missing);
+        assert_eq!(checks[1].notes.as_deref(), Some("no commits for this task"));
+    }
+
+    #[test]
+    fn beads_close_only_for_implemented_tasks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut plan = plan();
+        for task in &mut plan.tasks {
+            task.status = TaskStatus::Completed;
+        }
+        crate::cruise::planner::plan_to_beads(&plan, dir.path()).unwrap();
+        let mut checks = attributed_checks(&plan, &[commit("a1", "x\n\nTask-Id: CRUISE-001")]);
+        checks[1].status = AdherenceStatus::Partial;
+        checks[1].notes = Some("Refresh tokens are missing.".to_string());
+
+        close_beads_issues(dir.path(), &checks).unwrap();
+        // A second run replaces the progress note instead of adding another
+        let closure = close_beads_issues(dir.path(), &checks).unwrap();
+
+        assert_eq!(closure.closed, ["CRUISE-001"]);
+        assert_eq!(closure.open, ["CRUISE-002", "CRUISE-003"]);
+        let read =
+            |id: &str| std::fs::read_to_string(dir.path().join(format!("{}.md", id))).unwrap();
+        assert!(read("CRUISE-001").contains("status: completed\n"));
+        let partial = read("CRUISE-002");
+        assert!(partial.contains("status: in_progress\n"));
+        assert!(partial.ends_with(
+            "## Progress\n\nValidation found this task partially implemented. \
+             Refresh tokens are missing.\n"
+        ));
+        assert_eq!(partial.matches(PROGRESS_HEADING).count(), 1);
+        assert!(read("CRUISE-003").contains("status: pending\n"));
+    }
+}
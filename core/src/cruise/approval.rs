@@ -1,10 +1,27 @@
 //! GitHub PR approval polling.
+//!
+//! Waits for a human decision on a PR by polling its review state with
+//! exponential backoff. Optionally, a local webhook listener receives
+//! `pull_request_review` and `pull_request` events (e.g., forwarded with
+//! `gh webhook forward`) so decisions are picked up without polling.
+//! Webhook requests must be signed with the configured `webhook_secret`
+//! (`X-Hub-Signature-256`); anything else is rejected.
 
 use std::process::Command;
 use std::time::{Duration, Instant};
 
-use crate::error::{Error, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
 use super::config::ApprovalConfig;
+use crate::cancel::CancellationToken;
+use crate::error::{Error, Result};
+
+/// Maximum webhook request size accepted by the listener.
+const MAX_WEBHOOK_BYTES: usize = 4 * 1024 * 1024;
+
+/// How long a webhook connection has to send its request.
+const WEBHOOK_READ_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Status of a PR.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -13,21 +30,41 @@ pub enum PrStatus {
     Open,
     /// PR has been approved.
     Approved,
+    /// A reviewer requested changes.
+    ChangesRequested,
     /// PR has been merged.
     Merged,
     /// PR has been closed without merging.
     Closed,
 }
 
+impl PrStatus {
+    /// Returns true if the status is a reviewer decision the workflow
+    /// should act on.
+    pub fn is_decision(&self) -> bool {
+        !matches!(self, PrStatus::Open)
+    }
+}
+
 /// Approval poller for GitHub PRs.
 pub struct ApprovalPoller {
     config: ApprovalConfig,
+    cancel: CancellationToken,
 }
 
 impl ApprovalPoller {
     /// Creates a new approval poller with the given configuration.
     pub fn new(config: ApprovalConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Stops waiting with [`Error::Cancelled`] when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
     }
 
     /// Creates a poller with default configuration.
@@ -37,47 +74,29 @@ impl ApprovalPoller {
 
     /// Checks the status of a PR using gh CLI.
     pub fn check_pr_status(&self, pr_url: &str) -> Result<PrStatus> {
+        let args = ["pr", "view", pr_url, "--json", "state,reviewDecision"];
         let output = Command::new("gh")
-            .args(["pr", "view", pr_url, "--json", "state,reviewDecision"])
+            .args(args)
             .output()
-            .map_err(|e| Error::GitHub(format!("failed to run gh: {}", e)))?;
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::GitHub(format!("gh pr view failed: {}", stderr)));
+            return Err(Error::gh_command(&args, &output));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let json: serde_json::Value = serde_json::from_str(&stdout)
-            .map_err(|e| Error::GitHub(format!("failed to parse gh output: {}", e)))?;
-
-        let state = json["state"].as_str().unwrap_or("UNKNOWN");
-        let review_decision = json["reviewDecision"].as_str();
-
-        match state {
-            "MERGED" => Ok(PrStatus::Merged),
-            "CLOSED" => Ok(PrStatus::Closed),
-            "OPEN" => {
-                if review_decision == Some("APPROVED") {
-                    Ok(PrStatus::Approved)
-                } else {
-                    Ok(PrStatus::Open)
-                }
-            }
-            _ => Ok(PrStatus::Open),
-        }
+        parse_pr_status(&String::from_utf8_lossy(&output.stdout))
     }
 
     /// Approves a PR using gh CLI (for test mode).
     pub fn approve_pr(&self, pr_url: &str) -> Result<()> {
+        let args = ["pr", "review", pr_url, "--approve"];
         let output = Command::new("gh")
-            .args(["pr", "review", pr_url, "--approve"])
+            .args(args)
             .output()
-            .map_err(|e| Error::GitHub(format!("failed to run gh: {}", e)))?;
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::GitHub(format!("gh pr review failed: {}", stderr)));
+            return Err(Error::gh_command(&args, &output));
         }
 
         Ok(())
@@ -85,14 +104,14 @@ impl ApprovalPoller {
 
     /// Merges a PR using gh CLI.
     pub fn merge_pr(&self, pr_url: &str) -> Result<()> {
+        let args = ["pr", "merge", pr_url, "--merge", "--delete-branch"];
         let output = Command::new("gh")
-            .args(["pr", "merge", pr_url, "--merge", "--delete-branch"])
+            .args(args)
             .output()
-            .map_err(|e| Error::GitHub(format!("failed to run gh: {}", e)))?;
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::GitHub(format!("gh pr merge failed: {}", stderr)));
+            return Err(Error::gh_command(&args, &output));
         }
 
         Ok(())
@@ -104,44 +123,382 @@ impl ApprovalPoller {
         next.min(self.config.poll_max)
     }
 
-    /// Polls for PR approval with exponential backoff.
-    /// Returns Ok(()) when approved, Err on timeout or other error.
-    pub async fn poll_for_approval(&self, pr_url: &str, timeout: Duration) -> Result<()> {
+    /// Waits for a reviewer decision on a PR.
+    ///
+    /// Returns [`PrStatus::Approved`], [`PrStatus::Merged`], or
+    /// [`PrStatus::ChangesRequested`] so the caller can resume the workflow
+    /// (proceed, or schedule a fix round). Fails if the PR is closed or
+    /// `timeout` elapses.
+    ///
+    /// If `webhook_port` and `webhook_secret` are configured, signed webhook
+    /// events are used and polling only runs at `poll_max` as a fallback.
+    pub async fn poll_for_approval(&self, pr_url: &str, timeout: Duration) -> Result<PrStatus> {
+        let status = tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => return Err(self.cancel.error()),
+            status = self.wait_for_decision(pr_url, timeout) => status?,
+        };
+
+        match status {
+            PrStatus::Closed => Err(Error::GitHub("PR was closed without approval".to_string())),
+            status => {
+                tracing::info!(pr_url = %pr_url, status = ?status, "received PR decision");
+                Ok(status)
+            }
+        }
+    }
+
+    /// Waits for a decision from webhooks or polling, whichever comes first.
+    async fn wait_for_decision(&self, pr_url: &str, timeout: Duration) -> Result<PrStatus> {
+        match (
+            self.config.webhook_port,
+            self.config.webhook_secret.as_deref(),
+        ) {
+            (Some(port), Some(secret)) => {
+                let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+                tracing::info!(port, pr_url = %pr_url, "listening for approval webhooks");
+
+                tokio::select! {
+                    status = wait_for_webhook(listener, pr_url, secret) => status,
+                    status = self.poll_loop(pr_url, timeout, self.config.poll_max) => status,
+                }
+            }
+            (port, _) => {
+                if port.is_some() {
+                    tracing::warn!("webhook_port is set without webhook_secret; polling instead");
+                }
+                self.poll_loop(pr_url, timeout, self.config.poll_initial)
+                    .await
+            }
+        }
+    }
+
+    /// Polls until the PR has a decision or the timeout elapses.
+    async fn poll_loop(
+        &self,
+        pr_url: &str,
+        timeout: Duration,
+        initial: Duration,
+    ) -> Result<PrStatus> {
         let start = Instant::now();
-        let mut interval = self.config.poll_initial;
+        let mut interval = initial;
 
         loop {
             // Check if we've exceeded the timeout
-            if start.elapsed() >= timeout {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
                 return Err(Error::ApprovalTimeout(timeout.as_secs()));
             }
 
             // Check PR status
-            match self.check_pr_status(pr_url)? {
-                PrStatus::Approved | PrStatus::Merged => return Ok(()),
-                PrStatus::Closed => {
-                    return Err(Error::GitHub("PR was closed without approval".to_string()));
-                }
-                PrStatus::Open => {
-                    // Wait and try again
-                    tokio::time::sleep(interval).await;
-                    interval = self.next_interval(interval);
-                }
+            let status = self.check_pr_status(pr_url)?;
+            if status.is_decision() {
+                return Ok(status);
             }
+
+            tracing::debug!(pr_url = %pr_url, next_poll = ?interval, "waiting for approval");
+            tokio::time::sleep(interval.min(timeout - elapsed)).await;
+            interval = self.next_interval(interval);
         }
     }
 }
 
+/// Parses `gh pr view --json state,reviewDecision` output.
+pub fn parse_pr_status(json: &str) -> Result<PrStatus> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("gh output", e))?;
+
+    let state = json["state"].as_str().unwrap_or("UNKNOWN");
+    let review_decision = json["reviewDecision"].as_str();
+
+    Ok(match state {
+        "MERGED" => PrStatus::Merged,
+        "CLOSED" => PrStatus::Closed,
+        _ => match review_decision {
+            Some("APPROVED") => PrStatus::Approved,
+            Some("CHANGES_REQUESTED") => PrStatus::ChangesRequested,
+            _ => PrStatus::Open,
+        },
+    })
+}
+
+/// Parses a GitHub webhook payload into a status for `pr_url`.
+///
+/// Returns `None` for events about other PRs or that carry no decision.
+pub fn parse_webhook_event(body: &str, pr_url: &str) -> Option<PrStatus> {
+    let json: serde_json::Value = serde_json::from_str(body).ok()?;
+    let pr = &json["pull_request"];
+
+    let html_url = pr["html_url"].as_str()?;
+    if html_url.trim_end_matches('/') != pr_url.trim_end_matches('/') {
+        return None;
+    }
+
+    if let Some(state) = json["review"]["state"].as_str() {
+        return match state.to_lowercase().as_str() {
+            "approved" => Some(PrStatus::Approved),
+            "changes_requested" => Some(PrStatus::ChangesRequested),
+            _ => None,
+        };
+    }
+
+    if json["action"].as_str() == Some("closed") {
+        return Some(if pr["merged"].as_bool() == Some(true) {
+            PrStatus::Merged
+        } else {
+            PrStatus::Closed
+        });
+    }
+
+    None
+}
+
+/// Accepts webhook requests until one carries a decision for `pr_url`.
+///
+/// Each connection is handled on its own task, so a client that connects
+/// and never sends anything cannot hold up later webhooks.
+async fn wait_for_webhook(listener: TcpListener, pr_url: &str, secret: &str) -> Result<PrStatus> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let tx = tx.clone();
+                let pr_url = pr_url.to_string();
+                let secret = secret.to_string();
+                tokio::spawn(async move {
+                    if let Some(status) = handle_webhook(stream, &pr_url, &secret).await {
+                        let _ = tx.send(status).await;
+                    }
+                });
+            }
+            Some(status) = rx.recv() => return Ok(status),
+        }
+    }
+}
+
+/// Reads and answers one webhook request, returning its decision for
+/// `pr_url` if it is signed with `secret` and carries one.
+async fn handle_webhook(mut stream: TcpStream, pr_url: &str, secret: &str) -> Option<PrStatus> {
+    let request =
+        match tokio::time::timeout(WEBHOOK_READ_TIMEOUT, read_http_request(&mut stream)).await {
+            Ok(Ok(request)) => request,
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "failed to read webhook request");
+                let _ = respond(&mut stream, "400 Bad Request").await;
+                return None;
+            }
+            Err(_) => {
+                tracing::warn!("webhook request timed out");
+                let _ = respond(&mut stream, "408 Request Timeout").await;
+                return None;
+            }
+        };
+
+    if !verify_signature(secret, &request.body, request.signature.as_deref()) {
+        tracing::warn!("rejected webhook request without a valid signature");
+        let _ = respond(&mut stream, "401 Unauthorized").await;
+        return None;
+    }
+    let _ = respond(&mut stream, "200 OK").await;
+
+    parse_webhook_event(&String::from_utf8_lossy(&request.body), pr_url)
+}
+
+/// A webhook request: its body and `X-Hub-Signature-256` header.
+struct WebhookRequest {
+    signature: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Reads an HTTP request's signature header and body.
+async fn read_http_request(stream: &mut TcpStream) -> Result<WebhookRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(Error::GitHub("webhook connection closed early".to_string()));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = find_subslice(&buf, b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_WEBHOOK_BYTES {
+            return Err(Error::GitHub("webhook headers too large".to_string()));
+        }
+    };
+
+    let headers = String::from_utf8_lossy(&buf[..header_end]);
+    let header = |wanted: &str| {
+        headers.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case(wanted)
+                .then(|| value.trim().to_string())
+        })
+    };
+    let content_length = header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    let signature = header("x-hub-signature-256");
+
+    if content_length > MAX_WEBHOOK_BYTES {
+        return Err(Error::GitHub("webhook body too large".to_string()));
+    }
+
+    while buf.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let end = buf.len().min(header_end + content_length);
+    Ok(WebhookRequest {
+        signature,
+        body: buf[header_end..end].to_vec(),
+    })
+}
+
+/// Checks an `X-Hub-Signature-256` value (`sha256=<hex>`) against the
+/// HMAC-SHA256 of `body` keyed with `secret`.
+fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(hex) = signature.and_then(|s| s.strip_prefix("sha256=")) else {
+        return false;
+    };
+    let expected = to_hex(&hmac_sha256(secret.as_bytes(), body));
+    let given = hex.to_ascii_lowercase();
+
+    // Compare without short-circuiting so timing does not leak the digest
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block_key = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block_key[..32].copy_from_slice(&sha256(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block_key.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block_key.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+/// SHA-256 (FIPS 180-4).
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (out, word) in digest.chunks_mut(4).zip(h) {
+        out.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+async fn respond(stream: &mut TcpStream, status: &str) -> Result<()> {
+    let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const PR_URL: &str = "https://github.com/epiphytic/demo/pull/7";
+
     #[test]
     fn next_interval_applies_backoff() {
         let config = ApprovalConfig {
             poll_initial: Duration::from_secs(60),
             poll_max: Duration::from_secs(1800),
             poll_backoff: 2.0,
+            webhook_port: None,
+            ..Default::default()
         };
         let poller = ApprovalPoller::new(config);
 
@@ -158,6 +515,8 @@ mod tests {
             poll_initial: Duration::from_secs(60),
             poll_max: Duration::from_secs(300),
             poll_backoff: 2.0,
+            webhook_port: None,
+            ..Default::default()
         };
         let poller = ApprovalPoller::new(config);
 
@@ -170,4 +529,222 @@ mod tests {
         assert_eq!(PrStatus::Open, PrStatus::Open);
         assert_ne!(PrStatus::Open, PrStatus::Approved);
     }
+
+    #[test]
+    fn parse_pr_status_detects_review_decisions() {
+        let status = |json| parse_pr_status(json).unwrap();
+
+        assert_eq!(
+            status(r#"{"state":"OPEN","reviewDecision":"APPROVED"}"#),
+            PrStatus::Approved
+        );
+        assert_eq!(
+            status(r#"{"state":"OPEN","reviewDecision":"CHANGES_REQUESTED"}"#),
+            PrStatus::ChangesRequested
+        );
+        assert_eq!(
+            status(r#"{"state":"OPEN","reviewDecision":"REVIEW_REQUIRED"}"#),
+            PrStatus::Open
+        );
+        assert_eq!(
+            status(r#"{"state":"OPEN","reviewDecision":null}"#),
+            PrStatus::Open
+        );
+        assert_eq!(
+            status(r#"{"state":"MERGED","reviewDecision":"APPROVED"}"#),
+            PrStatus::Merged
+        );
+        assert_eq!(status(r#"{"state":"CLOSED"}"#), PrStatus::Closed);
+        assert!(parse_pr_status("not json").is_err());
+    }
+
+    #[test]
+    fn parse_webhook_event_handles_reviews() {
+        let approved = format!(
+            r#"{{"action":"submitted","review":{{"state":"approved"}},"pull_request":{{"html_url":"{}"}}}}"#,
+            PR_URL
+        );
+        let changes = approved.replace("approved", "changes_requested");
+        let comment = approved.replace("approved", "commented");
+
+        assert_eq!(
+            parse_webhook_event(&approved, PR_URL),
+            Some(PrStatus::Approved)
+        );
+        assert_eq!(
+            parse_webhook_event(&changes, PR_URL),
+            Some(PrStatus::ChangesRequested)
+        );
+        assert_eq!(parse_webhook_event(&comment, PR_URL), None);
+    }
+
+    #[test]
+    fn parse_webhook_event_handles_close_and_other_prs() {
+        let merged = format!(
+            r#"{{"action":"closed","pull_request":{{"html_url":"{}","merged":true}}}}"#,
+            PR_URL
+        );
+        let closed = merged.replace("true", "false");
+
+        assert_eq!(parse_webhook_event(&merged, PR_URL), Some(PrStatus::Merged));
+        assert_eq!(parse_webhook_event(&closed, PR_URL), Some(PrStatus::Closed));
+        assert_eq!(
+            parse_webhook_event(&merged, "https://github.com/epiphytic/demo/pull/8"),
+            None
+        );
+        assert_eq!(parse_webhook_event("garbage", PR_URL), None);
+    }
+
+    const SECRET: &str = "It's a Secret to Everybody";
+
+    /// Sends `body` to `addr` with `signature`, returning the response.
+    async fn send(addr: std::net::SocketAddr, body: &str, signature: Option<String>) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let signature = signature
+            .map(|s| format!("X-Hub-Signature-256: {}\r\n", s))
+            .unwrap_or_default();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\n\r\n{}",
+            signature,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn sign(body: &str) -> Option<String> {
+        Some(format!(
+            "sha256={}",
+            to_hex(&hmac_sha256(SECRET.as_bytes(), body.as_bytes()))
+        ))
+    }
+
+    #[test]
+    fn hmac_sha256_matches_known_digests() {
+        assert_eq!(
+            to_hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            to_hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // RFC 4231, test case 2
+        assert_eq!(
+            to_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // RFC 4231, test case 6: a key longer than the block size
+        assert_eq!(
+            to_hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn signatures_are_verified_against_the_secret() {
+        // Example from GitHub's webhook documentation
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+        assert!(verify_signature(SECRET, b"Hello, World!", Some(signature)));
+        assert!(!verify_signature(SECRET, b"Hello, World?", Some(signature)));
+        assert!(!verify_signature(
+            "other secret",
+            b"Hello, World!",
+            Some(signature)
+        ));
+        assert!(!verify_signature(
+            SECRET,
+            b"Hello, World!",
+            Some(&signature[7..])
+        ));
+        assert!(!verify_signature(SECRET, b"Hello, World!", None));
+    }
+
+    #[tokio::test]
+    async fn webhook_listener_returns_matching_decision() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let wait = tokio::spawn(async move { wait_for_webhook(listener, PR_URL, SECRET).await });
+
+        // An event for another PR is acknowledged but ignored
+        let other = r#"{"review":{"state":"approved"},"pull_request":{"html_url":"https://github.com/o/r/pull/1"}}"#;
+        assert!(send(addr, other, sign(other))
+            .await
+            .starts_with("HTTP/1.1 200"));
+
+        let changes = format!(
+            r#"{{"review":{{"state":"changes_requested"}},"pull_request":{{"html_url":"{}"}}}}"#,
+            PR_URL
+        );
+        send(addr, &changes, sign(&changes)).await;
+
+        let status = wait.await.unwrap().unwrap();
+        assert_eq!(status, PrStatus::ChangesRequested);
+    }
+
+    #[tokio::test]
+    async fn webhook_listener_rejects_unsigned_requests() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let wait = tokio::spawn(async move { wait_for_webhook(listener, PR_URL, SECRET).await });
+
+        let approved = format!(
+            r#"{{"review":{{"state":"approved"}},"pull_request":{{"html_url":"{}"}}}}"#,
+            PR_URL
+        );
+        let forged = Some(format!("sha256={}", "0".repeat(64)));
+        assert!(send(addr, &approved, None)
+            .await
+            .starts_with("HTTP/1.1 401"));
+        assert!(send(addr, &approved, forged)
+            .await
+            .starts_with("HTTP/1.1 401"));
+        assert!(!wait.is_finished());
+
+        send(addr, &approved, sign(&approved)).await;
+        assert_eq!(wait.await.unwrap().unwrap(), PrStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn idle_connections_do_not_block_webhooks() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let wait = tokio::spawn(async move { wait_for_webhook(listener, PR_URL, SECRET).await });
+
+        // Connects and never sends a request
+        let _idle = TcpStream::connect(addr).await.unwrap();
+
+        let merged = format!(
+            r#"{{"action":"closed","pull_request":{{"html_url":"{}","merged":true}}}}"#,
+            PR_URL
+        );
+        send(addr, &merged, sign(&merged)).await;
+
+        let status = tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .expect("idle connection blocked the listener");
+        assert_eq!(status.unwrap().unwrap(), PrStatus::Merged);
+    }
+
+    #[tokio::test]
+    async fn cancelled_poller_stops_waiting() {
+        let token = CancellationToken::new();
+        token.cancel("interrupted");
+        let poller = ApprovalPoller::with_defaults().with_cancellation(token);
+
+        let err = poller
+            .poll_for_approval(PR_URL, Duration::from_secs(60))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Cancelled(reason) if reason == "interrupted"));
+    }
 }
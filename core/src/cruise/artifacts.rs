@@ -0,0 +1,322 @@
+//! Artifact passing between dependent cruise tasks.
+//!
+//! Each task runs in its own sandbox, so its output is invisible to other
+//! tasks until merge. When a task completes, its sandbox branch is recorded
+//! as an artifact branch. A dependent task then starts from the merged
+//! result of its `blocked_by` tasks instead of the original `HEAD`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::task::CruiseTask;
+use crate::branch::{BranchNamer, DEFAULT_ARTIFACT_PREFIX, DEFAULT_BASE_PREFIX};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+
+/// Branch prefix for recorded task outputs.
+pub const ARTIFACT_BRANCH_PREFIX: &str = DEFAULT_ARTIFACT_PREFIX;
+
+/// Branch prefix for merged dependency bases.
+pub const BASE_BRANCH_PREFIX: &str = DEFAULT_BASE_PREFIX;
+
+/// Store of completed task outputs, kept as git branches.
+pub struct ArtifactStore {
+    /// Path to the git repository.
+    repo_path: PathBuf,
+    /// Artifact branch for each recorded task.
+    artifacts: HashMap<String, String>,
+    /// Base branches created for dependent tasks.
+    bases: Vec<String>,
+    /// Names artifact and base branches.
+    branches: BranchNamer,
+    /// Runs the git commands.
+    git: Arc<dyn GitExecutor>,
+}
+
+impl ArtifactStore {
+    /// Creates an empty artifact store for a repository.
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            artifacts: HashMap::new(),
+            bases: Vec::new(),
+            branches: BranchNamer::new(),
+            git: Arc::new(SystemGit),
+        }
+    }
+
+    /// Sets how git commands are run.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Sets the namer for artifact and base branches.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    /// Records the output of a completed task.
+    ///
+    /// `source_ref` is usually the task's sandbox branch. The commit it points
+    /// to is kept on an artifact branch, so the sandbox can be cleaned up.
+    /// Returns the artifact branch name.
+    pub fn record(&mut self, task_id: &str, source_ref: &str) -> Result<String> {
+        let branch = self.branches.artifact_branch(task_id);
+        self.git
+            .run(&self.repo_path, &["branch", "-f", &branch, source_ref])?;
+
+        tracing::debug!(task_id = %task_id, branch = %branch, "recorded task artifact");
+        self.artifacts.insert(task_id.to_string(), branch.clone());
+        Ok(branch)
+    }
+
+    /// Returns the artifact branch for a task, if recorded.
+    pub fn artifact(&self, task_id: &str) -> Option<&str> {
+        self.artifacts.get(task_id).map(String::as_str)
+    }
+
+    /// Returns the ref a task's sandbox should start from.
+    ///
+    /// - No dependencies: `HEAD`.
+    /// - One dependency: that task's artifact branch.
+    /// - Several: a base branch merging all dependency artifacts.
+    ///
+    /// Fails if a dependency has not been recorded or the merge conflicts.
+    pub fn base_for(&mut self, task: &CruiseTask) -> Result<String> {
+        let deps = task
+            .blocked_by
+            .iter()
+            .map(|dep| {
+                self.artifact(dep)
+                    .map(String::from)
+                    .ok_or_else(|| Error::MissingArtifacts {
+                        task_id: task.id.clone(),
+                        dependency: dep.clone(),
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        match deps.as_slice() {
+            [] => Ok("HEAD".to_string()),
+            [single] => Ok(single.clone()),
+            [first, rest @ ..] => self.merge_base(&task.id, first, rest),
+        }
+    }
+
+    /// Creates a base branch for `task_id` merging several artifact branches.
+    fn merge_base(&mut self, task_id: &str, first: &str, rest: &[String]) -> Result<String> {
+        let branch = self.branches.base_branch(task_id);
+        let worktree = std::env::temp_dir().join(format!(
+            "improbability-drive-merge-{}",
+            uuid::Uuid::new_v4()
+        ));
+
+        self.git.run(
+            &self.repo_path,
+            &[
+                "worktree",
+                "add",
+                "-B",
+                &branch,
+                path_str(&worktree)?,
+                first,
+            ],
+        )?;
+
+        let mut merge_args = vec!["merge", "--no-edit", "--no-ff"];
+        merge_args.extend(rest.iter().map(String::as_str));
+        let merged = self.git.run(&worktree, &merge_args);
+
+        if merged.is_err() {
+            let _ = self.git.run(&worktree, &["merge", "--abort"]);
+        }
+        let removed = self.git.run(
+            &self.repo_path,
+            &["worktree", "remove", "--force", path_str(&worktree)?],
+        );
+
+        if let Err(e) = merged {
+            let _ = self.git.run(&self.repo_path, &["branch", "-D", &branch]);
+            return Err(e.context(format!("failed to merge dependencies of task {}", task_id)));
+        }
+        removed?;
+
+        tracing::info!(task_id = %task_id, branch = %branch, "merged task dependencies");
+        self.bases.push(branch.clone());
+        Ok(branch)
+    }
+
+    /// Deletes all artifact and base branches created by this store.
+    pub fn cleanup(&mut self) -> Result<()> {
+        for branch in self.artifacts.values().chain(self.bases.iter()) {
+            if let Err(e) = self.git.run(&self.repo_path, &["branch", "-D", branch]) {
+                tracing::warn!(branch = %branch, error = %e, "failed to delete artifact branch");
+            }
+        }
+        self.artifacts.clear();
+        self.bases.clear();
+        Ok(())
+    }
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| Error::InvalidPath(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{GitOutput, MockGit};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) -> Result<String> {
+        SystemGit.run(dir, args).map(|out| out.trim().to_string())
+    }
+
+    /// Helper to create a temp git repo for testing.
+    fn create_temp_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let path = temp_dir.path();
+
+        git(path, &["init"]).unwrap();
+        git(path, &["config", "user.email", "test@test.com"]).unwrap();
+        git(path, &["config", "user.name", "Test User"]).unwrap();
+        fs::write(path.join("README.md"), "# Test Repo\n").unwrap();
+        git(path, &["add", "."]).unwrap();
+        git(path, &["commit", "-m", "Initial commit"]).unwrap();
+
+        temp_dir
+    }
+
+    /// Creates a branch from HEAD with one committed file.
+    fn commit_on_branch(repo: &Path, branch: &str, file: &str, content: &str) {
+        git(repo, &["checkout", "-q", "-b", branch]).unwrap();
+        fs::write(repo.join(file), content).unwrap();
+        git(repo, &["add", "."]).unwrap();
+        git(repo, &["commit", "-m", branch]).unwrap();
+        git(repo, &["checkout", "-q", "-"]).unwrap();
+    }
+
+    fn show(repo: &Path, rev_path: &str) -> Option<String> {
+        git(repo, &["show", rev_path]).ok()
+    }
+
+    #[test]
+    fn task_without_dependencies_starts_from_head() {
+        let repo = create_temp_git_repo();
+        let mut store = ArtifactStore::new(repo.path());
+
+        let base = store
+            .base_for(&CruiseTask::new("CRUISE-001", "First"))
+            .unwrap();
+
+        assert_eq!(base, "HEAD");
+    }
+
+    #[test]
+    fn single_dependency_uses_its_artifact_branch() {
+        let repo = create_temp_git_repo();
+        commit_on_branch(repo.path(), "sandbox-a", "a.txt", "a");
+        let mut store = ArtifactStore::new(repo.path());
+
+        store.record("CRUISE-001", "sandbox-a").unwrap();
+        // Sandbox branches are deleted on cleanup; the artifact must survive
+        git(repo.path(), &["branch", "-D", "sandbox-a"]).unwrap();
+
+        let task =
+            CruiseTask::new("CRUISE-002", "Second").with_blocked_by(vec!["CRUISE-001".to_string()]);
+        let base = store.base_for(&task).unwrap();
+
+        assert_eq!(base, "cruise/artifacts/CRUISE-001");
+        assert_eq!(show(repo.path(), &format!("{}:a.txt", base)).unwrap(), "a");
+    }
+
+    #[test]
+    fn multiple_dependencies_are_merged() {
+        let repo = create_temp_git_repo();
+        commit_on_branch(repo.path(), "sandbox-a", "a.txt", "a");
+        commit_on_branch(repo.path(), "sandbox-b", "b.txt", "b");
+        let mut store = ArtifactStore::new(repo.path());
+        store.record("CRUISE-001", "sandbox-a").unwrap();
+        store.record("CRUISE-002", "sandbox-b").unwrap();
+
+        let task = CruiseTask::new("CRUISE-003", "Third")
+            .with_blocked_by(vec!["CRUISE-001".to_string(), "CRUISE-002".to_string()]);
+        let base = store.base_for(&task).unwrap();
+
+        assert_eq!(base, "cruise/base/CRUISE-003");
+        assert!(show(repo.path(), &format!("{}:a.txt", base)).is_some());
+        assert!(show(repo.path(), &format!("{}:b.txt", base)).is_some());
+    }
+
+    #[test]
+    fn conflicting_dependencies_fail() {
+        let repo = create_temp_git_repo();
+        commit_on_branch(repo.path(), "sandbox-a", "same.txt", "a");
+        commit_on_branch(repo.path(), "sandbox-b", "same.txt", "b");
+        let mut store = ArtifactStore::new(repo.path());
+        store.record("CRUISE-001", "sandbox-a").unwrap();
+        store.record("CRUISE-002", "sandbox-b").unwrap();
+
+        let task = CruiseTask::new("CRUISE-003", "Third")
+            .with_blocked_by(vec!["CRUISE-001".to_string(), "CRUISE-002".to_string()]);
+        let err = store.base_for(&task).unwrap_err();
+
+        assert!(err.to_string().contains("CRUISE-003"));
+        assert!(show(repo.path(), "cruise/base/CRUISE-003").is_none());
+    }
+
+    #[test]
+    fn unrecorded_dependency_fails() {
+        let repo = create_temp_git_repo();
+        let mut store = ArtifactStore::new(repo.path());
+
+        let task =
+            CruiseTask::new("CRUISE-002", "Second").with_blocked_by(vec!["CRUISE-001".to_string()]);
+
+        assert!(store.base_for(&task).is_err());
+    }
+
+    #[test]
+    fn cleanup_deletes_branches() {
+        let repo = create_temp_git_repo();
+        commit_on_branch(repo.path(), "sandbox-a", "a.txt", "a");
+        let mut store = ArtifactStore::new(repo.path());
+        store.record("CRUISE-001", "sandbox-a").unwrap();
+
+        store.cleanup().unwrap();
+
+        assert!(store.artifact("CRUISE-001").is_none());
+        assert!(show(repo.path(), "cruise/artifacts/CRUISE-001").is_none());
+    }
+
+    #[test]
+    fn cleanup_deletes_recorded_branches_despite_failures() {
+        let git = Arc::new(
+            MockGit::new()
+                .on(&["branch", "-f"], GitOutput::ok(""))
+                .on(&["branch", "-D"], GitOutput::failed(1, "branch not found")),
+        );
+        let mut store = ArtifactStore::new("/repo").with_git(git.clone());
+
+        assert_eq!(
+            store.record("CRUISE-001", "sandbox-a").unwrap(),
+            "cruise/artifacts/CRUISE-001"
+        );
+        store.cleanup().unwrap();
+
+        assert_eq!(
+            git.calls(),
+            [
+                vec!["branch", "-f", "cruise/artifacts/CRUISE-001", "sandbox-a"],
+                vec!["branch", "-D", "cruise/artifacts/CRUISE-001"],
+            ]
+        );
+        assert!(store.artifact("CRUISE-001").is_none());
+    }
+}
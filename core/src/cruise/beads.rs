@@ -0,0 +1,192 @@
+//! Keeping beads issues in step with task execution.
+//!
+//! Plans are written as beads issues before the build phase starts.
+//! [`BeadsSync`] updates them as tasks run, so the tracker shows live
+//! progress rather than jumping from open to closed: an issue moves to
+//! `in_progress` when its task's spawn starts, gains a comment for each
+//! commit made for it, and becomes `blocked` when the task or one of its
+//! dependencies fails. Closing issues is left to validation (see
+//! [`close_beads_issues`](super::adherence::close_beads_issues)).
+
+use std::path::{Path, PathBuf};
+
+use super::planner::BEADS_DIR;
+use crate::error::Result;
+use crate::spawn::CommitInfo;
+
+/// Heading of the comment section of a beads issue.
+const COMMENTS_HEADING: &str = "## Comments";
+
+/// Updates the beads issues of a plan during execution.
+#[derive(Debug, Clone)]
+pub struct BeadsSync {
+    beads_dir: PathBuf,
+}
+
+impl BeadsSync {
+    /// Creates a sync for the beads issues of the repository at
+    /// `repo_path`.
+    pub fn new(repo_path: impl AsRef<Path>) -> Self {
+        Self {
+            beads_dir: repo_path.as_ref().join(BEADS_DIR),
+        }
+    }
+
+    /// Marks a task's issue `in_progress`.
+    pub fn start(&self, task_id: &str) -> Result<bool> {
+        self.update(task_id, |content| set_status(content, "in_progress"))
+    }
+
+    /// Adds a comment listing `commits` to a task's issue.
+    pub fn record_commits(&self, task_id: &str, commits: &[CommitInfo]) -> Result<bool> {
+        if commits.is_empty() {
+            return Ok(false);
+        }
+        let comment = commits
+            .iter()
+            .map(|c| {
+                let short = c.hash.get(..12).unwrap_or(&c.hash);
+                let subject = c.message.lines().next().unwrap_or_default();
+                format!("Committed `{}` {}", short, subject)
+            })
+            .collect::<Vec<_>>();
+        self.update(task_id, |content| {
+            comment.iter().fold(content.to_string(), |content, line| {
+                add_comment(&content, line)
+            })
+        })
+    }
+
+    /// Marks a task's issue `blocked`, with `reason` as a comment.
+    pub fn block(&self, task_id: &str, reason: &str) -> Result<bool> {
+        self.update(task_id, |content| {
+            add_comment(
+                &set_status(content, "blocked"),
+                &format!("Blocked: {}", reason),
+            )
+        })
+    }
+
+    /// Rewrites a task's issue with `edit`, returning `false` if the task
+    /// has no issue.
+    fn update(&self, task_id: &str, edit: impl FnOnce(&str) -> String) -> Result<bool> {
+        let path = self.beads_dir.join(format!("{}.md", task_id));
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Ok(false);
+        };
+        std::fs::write(&path, edit(&content))?;
+        Ok(true)
+    }
+}
+
+/// Returns the frontmatter status of a beads issue.
+pub(crate) fn status(content: &str) -> Option<&str> {
+    let mut lines = content.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+    lines
+        .take_while(|line| line.trim_end() != "---")
+        .find_map(|line| line.strip_prefix("status: "))
+        .map(str::trim)
+}
+
+/// Rewrites the frontmatter status of a beads issue to `status`.
+pub(crate) fn set_status(content: &str, status: &str) -> String {
+    let mut in_frontmatter = false;
+    let mut out = String::with_capacity(content.len());
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        if line.trim_end() == "---" {
+            in_frontmatter = i == 0;
+        } else if in_frontmatter && line.starts_with("status: ") {
+            out.push_str(&format!("status: {}\n", status));
+            continue;
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Removes the section under `heading` from a beads issue, returning the
+/// issue without it and the section's body.
+pub(crate) fn take_section(content: &str, heading: &str) -> (String, Option<String>) {
+    let marker = format!("\n{}\n", heading);
+    let Some(start) = content.find(&marker) else {
+        return (content.to_string(), None);
+    };
+    let body_start = start + marker.len();
+    let end = content[body_start..]
+        .find("\n## ")
+        .map_or(content.len(), |i| body_start + i + 1);
+    let before = content[..start].trim_end();
+    let after = &content[end..];
+    let rest = if after.is_empty() {
+        format!("{}\n", before)
+    } else {
+        format!("{}\n\n{}", before, after)
+    };
+    (rest, Some(content[body_start..end].trim().to_string()))
+}
+
+/// Appends `comment` to the comment section of a beads issue, creating it
+/// at the end if needed.
+fn add_comment(content: &str, comment: &str) -> String {
+    let (rest, comments) = take_section(content, COMMENTS_HEADING);
+    let mut comments = comments.unwrap_or_default();
+    if !comments.is_empty() {
+        comments.push('\n');
+    }
+    comments.push_str(&format!("- {}", comment));
+    format!(
+        "{}\n\n{}\n\n{}\n",
+        rest.trim_end(),
+        COMMENTS_HEADING,
+        comments
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::planner::plan_to_beads;
+    use crate::cruise::task::{CruisePlan, CruiseTask};
+    use tempfile::TempDir;
+
+    #[test]
+    fn issues_follow_task_execution() {
+        let repo = TempDir::new().unwrap();
+        let mut plan = CruisePlan::new("Build an API");
+        plan.tasks = vec![CruiseTask::new("CRUISE-001", "Setup").with_description("Scaffold")];
+        plan_to_beads(&plan, &repo.path().join(BEADS_DIR)).unwrap();
+        let sync = BeadsSync::new(repo.path());
+        let read = || std::fs::read_to_string(repo.path().join(".beads/CRUISE-001.md")).unwrap();
+
+        assert!(sync.start("CRUISE-001").unwrap());
+        assert!(read().contains("status: in_progress\n"));
+
+        let commits = [
+            CommitInfo {
+                hash: "0123456789abcdef".to_string(),
+                message: "feat: scaffold\n\nTask-Id: CRUISE-001".to_string(),
+            },
+            CommitInfo {
+                hash: "fedcba".to_string(),
+                message: "test: cover setup".to_string(),
+            },
+        ];
+        sync.record_commits("CRUISE-001", &commits).unwrap();
+        sync.block("CRUISE-001", "tests failed").unwrap();
+
+        let issue = read();
+        assert!(issue.contains("status: blocked\n"));
+        assert!(issue.starts_with("---\nid: CRUISE-001\n"));
+        assert!(issue.contains("# Setup\n\nScaffold\n"));
+        assert!(issue.ends_with(
+            "## Comments\n\n\
+             - Committed `0123456789ab` feat: scaffold\n\
+             - Committed `fedcba` test: cover setup\n\
+             - Blocked: tests failed\n"
+        ));
+        assert!(!sync.start("CRUISE-404").unwrap());
+    }
+}
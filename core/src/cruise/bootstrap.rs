@@ -0,0 +1,517 @@
+//! Repository bootstrap for `RepoLifecycle::CreateNew`.
+//!
+//! Creates a GitHub repository (optionally from a template, with a license
+//! and `.gitignore`), clones it, seeds it from a local fixture directory, a
+//! README and a default CI workflow, and pushes the result so plan and
+//! build phases can run against it. The default branch can then be
+//! protected, so runs face the same rules as an existing codebase.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::config::{BootstrapConfig, BranchProtection};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::scaffold::Scaffold;
+
+/// Path of the seeded CI workflow.
+pub const CI_WORKFLOW_PATH: &str = ".github/workflows/ci.yml";
+
+const RUST_CI: &str = "\
+name: CI
+on: [push, pull_request]
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@stable
+        with:
+          components: clippy
+      - run: cargo build --workspace
+      - run: cargo clippy --workspace --all-targets -- -D warnings
+      - run: cargo test --workspace
+";
+
+const NODE_CI: &str = "\
+name: CI
+on: [push, pull_request]
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: actions/setup-node@v4
+        with:
+          node-version: 20
+      - run: npm ci
+      - run: npm test
+";
+
+const PYTHON_CI: &str = "\
+name: CI
+on: [push, pull_request]
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: actions/setup-python@v5
+        with:
+          python-version: '3.12'
+      - run: pip install -e '.[test]' || pip install -r requirements.txt
+      - run: python -m pytest
+";
+
+/// Returns the default CI workflow for a stack, if one is built in.
+pub fn ci_workflow(stack: &str) -> Option<&'static str> {
+    match stack {
+        "rust" => Some(RUST_CI),
+        "node" => Some(NODE_CI),
+        "python" => Some(PYTHON_CI),
+        _ => None,
+    }
+}
+
+/// Creates and seeds a new repository.
+pub struct RepoBootstrapper {
+    config: BootstrapConfig,
+    workdir: PathBuf,
+}
+
+impl RepoBootstrapper {
+    /// Creates a bootstrapper that clones into `workdir`.
+    pub fn new(config: BootstrapConfig, workdir: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            workdir: workdir.into(),
+        }
+    }
+
+    /// Returns `owner/name` for the new repository.
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.config.owner, self.config.name)
+    }
+
+    /// Returns the path the repository is cloned to.
+    pub fn repo_path(&self) -> PathBuf {
+        self.workdir.join(&self.config.name)
+    }
+
+    /// Creates, clones, seeds and pushes the repository for `prompt`.
+    ///
+    /// Returns the path of the local clone.
+    pub fn bootstrap(&self, prompt: &str) -> Result<PathBuf> {
+        self.validate()?;
+        std::fs::create_dir_all(&self.workdir)?;
+
+        let output = Command::new("gh")
+            .current_dir(&self.workdir)
+            .args(self.create_args())
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::gh_command(&["repo", "create"], &output)
+                .context(format!("failed to create repository {}", self.full_name())));
+        }
+        tracing::info!(repo = %self.full_name(), "created repository");
+
+        let path = self.repo_path();
+        if self.seed(&path, prompt)? {
+            SystemGit.run(&path, &["push", "-u", "origin", "HEAD"])?;
+        }
+        if let Some(protection) = &self.config.branch_protection {
+            self.protect(&path, protection)?;
+        }
+        Ok(path)
+    }
+
+    /// Applies `protection` to the checked-out branch of the clone at
+    /// `repo`.
+    fn protect(&self, repo: &Path, protection: &BranchProtection) -> Result<()> {
+        let branch = SystemGit.run(repo, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let branch = branch.trim();
+        let endpoint = format!("repos/{}/branches/{}/protection", self.full_name(), branch);
+        let args = ["api", "-X", "PUT", endpoint.as_str(), "--input", "-"];
+        let mut child = Command::new("gh")
+            .current_dir(repo)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+        {
+            use std::io::Write;
+            let mut stdin = child.stdin.take().expect("stdin was piped");
+            stdin.write_all(protection_request(protection).to_string().as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output)
+                .context(format!("failed to protect branch {}", branch)));
+        }
+        tracing::info!(repo = %self.full_name(), branch = %branch, "protected default branch");
+        Ok(())
+    }
+
+    /// Builds the `gh repo create` arguments.
+    pub fn create_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "repo".to_string(),
+            "create".to_string(),
+            self.full_name(),
+            "--clone".to_string(),
+            if self.config.private {
+                "--private"
+            } else {
+                "--public"
+            }
+            .to_string(),
+        ];
+        if let Some(description) = &self.config.description {
+            args.extend(["--description".to_string(), description.clone()]);
+        }
+        if let Some(template) = &self.config.template {
+            args.extend(["--template".to_string(), template.clone()]);
+        } else {
+            // --add-readme gives the repository an initial commit to seed on
+            args.push("--add-readme".to_string());
+            if let Some(license) = &self.config.license {
+                args.extend(["--license".to_string(), license.clone()]);
+            }
+            if let Some(gitignore) = &self.config.gitignore {
+                args.extend(["--gitignore".to_string(), gitignore.clone()]);
+            }
+        }
+        args
+    }
+
+    /// Returns the scaffold to apply for `prompt`, if any.
+    ///
+    /// `auto` picks a scaffold from the prompt and applies none if no stack
+    /// is recognised.
+    pub fn scaffold_for(&self, prompt: &str) -> Result<Option<Scaffold>> {
+        match self.config.scaffold.as_deref() {
+            None => Ok(None),
+            Some("auto") => Ok(Scaffold::detect(prompt)),
+            Some(name) => Scaffold::from_name(name).map(Some),
+        }
+    }
+
+    /// Seeds a cloned repository with the configured fixture and scaffold,
+    /// a README describing `prompt` and the configured CI workflow, and
+    /// commits them.
+    ///
+    /// Fixture files replace those of the same name. Otherwise existing
+    /// files (e.g. from a template or the fixture) are left alone. Returns
+    /// whether a commit was made.
+    pub fn seed(&self, repo: &Path, prompt: &str) -> Result<bool> {
+        if let Some(fixture) = &self.config.fixture {
+            copy_fixture(fixture, repo)?;
+        }
+        if let Some(scaffold) = self.scaffold_for(prompt)? {
+            scaffold.apply(repo, &self.config.name)?;
+        }
+
+        let readme = repo.join("README.md");
+        let existing = std::fs::read_to_string(&readme).unwrap_or_default();
+        if !existing.contains(prompt.trim()) {
+            let mut content = if existing.trim().is_empty() {
+                format!("# {}\n", self.config.name)
+            } else {
+                existing
+            };
+            if let Some(description) = &self.config.description {
+                if !content.contains(description.as_str()) {
+                    content.push_str(&format!("\n{}\n", description));
+                }
+            }
+            content.push_str(&format!("\n## Goal\n\n{}\n", prompt.trim()));
+            std::fs::write(&readme, content)?;
+        }
+
+        if let Some(stack) = &self.config.ci {
+            let workflow = ci_workflow(stack).ok_or_else(|| {
+                Error::Config(format!(
+                    "unknown bootstrap.ci '{}' - known: rust, node, python",
+                    stack
+                ))
+            })?;
+            let path = repo.join(CI_WORKFLOW_PATH);
+            if !path.exists() {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, workflow)?;
+            }
+        }
+
+        SystemGit.run(repo, &["add", "-A"])?;
+        if SystemGit
+            .run(repo, &["status", "--porcelain"])?
+            .trim()
+            .is_empty()
+        {
+            return Ok(false);
+        }
+        SystemGit.run(
+            repo,
+            &["commit", "-m", "Bootstrap repository for cruise-control"],
+        )?;
+        Ok(true)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.config.name.trim().is_empty() {
+            return Err(Error::Config(
+                "bootstrap.name is required to create a repository".to_string(),
+            ));
+        }
+        if let Some(fixture) = self.config.fixture.as_ref().filter(|f| !f.is_dir()) {
+            return Err(Error::Config(format!(
+                "bootstrap.fixture '{}' is not a directory",
+                fixture.display()
+            )));
+        }
+        if let Some(name) = self.config.scaffold.as_deref().filter(|n| *n != "auto") {
+            Scaffold::from_name(name)?;
+        }
+        if let Some(stack) = &self.config.ci {
+            if ci_workflow(stack).is_none() {
+                return Err(Error::Config(format!(
+                    "unknown bootstrap.ci '{}' - known: rust, node, python",
+                    stack
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the body of the GitHub branch protection request.
+pub fn protection_request(protection: &BranchProtection) -> serde_json::Value {
+    let checks = (!protection.required_checks.is_empty()).then(|| {
+        serde_json::json!({
+            "strict": true,
+            "contexts": protection.required_checks,
+        })
+    });
+    let reviews = (protection.required_approvals > 0).then(|| {
+        serde_json::json!({
+            "required_approving_review_count": protection.required_approvals,
+        })
+    });
+    serde_json::json!({
+        "required_status_checks": checks,
+        "enforce_admins": protection.enforce_admins,
+        "required_pull_request_reviews": reviews,
+        "restrictions": null,
+    })
+}
+
+/// Copies the files under `fixture` into `repo`, skipping any `.git`.
+fn copy_fixture(fixture: &Path, repo: &Path) -> Result<()> {
+    std::fs::create_dir_all(repo)?;
+    for entry in std::fs::read_dir(fixture)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let target = repo.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_fixture(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn config() -> BootstrapConfig {
+        BootstrapConfig {
+            owner: "acme".to_string(),
+            name: "billing".to_string(),
+            description: Some("Billing service".to_string()),
+            license: Some("mit".to_string()),
+            gitignore: Some("Rust".to_string()),
+            ci: Some("rust".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn create_args_include_license_and_gitignore() {
+        let bootstrapper = RepoBootstrapper::new(config(), "/tmp");
+
+        let args = bootstrapper.create_args();
+
+        assert_eq!(
+            &args[..5],
+            ["repo", "create", "acme/billing", "--clone", "--private"]
+        );
+        assert!(args.windows(2).any(|w| w == ["--license", "mit"]));
+        assert!(args.windows(2).any(|w| w == ["--gitignore", "Rust"]));
+        assert!(args.contains(&"--add-readme".to_string()));
+    }
+
+    #[test]
+    fn template_replaces_license_and_gitignore() {
+        let config = BootstrapConfig {
+            template: Some("acme/rust-template".to_string()),
+            private: false,
+            ..config()
+        };
+
+        let args = RepoBootstrapper::new(config, "/tmp").create_args();
+
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--template", "acme/rust-template"]));
+        assert!(args.contains(&"--public".to_string()));
+        assert!(!args.contains(&"--license".to_string()));
+        assert!(!args.contains(&"--add-readme".to_string()));
+    }
+
+    #[test]
+    fn seed_writes_readme_and_ci_and_commits() {
+        let repo = TempDir::new().unwrap();
+        SystemGit.run(repo.path(), &["init", "-q"]).unwrap();
+        SystemGit
+            .run(repo.path(), &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(repo.path(), &["config", "user.name", "Test User"])
+            .unwrap();
+        let bootstrapper = RepoBootstrapper::new(config(), repo.path());
+
+        assert!(bootstrapper
+            .seed(repo.path(), "Build a billing API")
+            .unwrap());
+
+        let readme = std::fs::read_to_string(repo.path().join("README.md")).unwrap();
+        assert!(readme.starts_with("# billing\n"));
+        assert!(readme.contains("Billing service"));
+        assert!(readme.contains("## Goal\n\nBuild a billing API"));
+        let ci = std::fs::read_to_string(repo.path().join(CI_WORKFLOW_PATH)).unwrap();
+        assert!(ci.contains("cargo test --workspace"));
+
+        // Seeding again changes nothing
+        assert!(!bootstrapper
+            .seed(repo.path(), "Build a billing API")
+            .unwrap());
+    }
+
+    #[test]
+    fn bootstrap_requires_name_and_known_ci() {
+        let dir = TempDir::new().unwrap();
+        let unnamed = BootstrapConfig::default();
+        let err = RepoBootstrapper::new(unnamed, dir.path())
+            .bootstrap("x")
+            .unwrap_err();
+        assert!(err.to_string().contains("bootstrap.name"));
+
+        let unknown_ci = BootstrapConfig {
+            ci: Some("cobol".to_string()),
+            ..config()
+        };
+        let err = RepoBootstrapper::new(unknown_ci, dir.path())
+            .bootstrap("x")
+            .unwrap_err();
+        assert!(err.to_string().contains("cobol"));
+    }
+
+    #[test]
+    fn seed_applies_detected_scaffold() {
+        let repo = TempDir::new().unwrap();
+        SystemGit.run(repo.path(), &["init", "-q"]).unwrap();
+        SystemGit
+            .run(repo.path(), &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(repo.path(), &["config", "user.name", "Test User"])
+            .unwrap();
+        let config = BootstrapConfig {
+            scaffold: Some("auto".to_string()),
+            ..config()
+        };
+
+        RepoBootstrapper::new(config, repo.path())
+            .seed(repo.path(), "A Rust library for billing math")
+            .unwrap();
+
+        let manifest = std::fs::read_to_string(repo.path().join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("name = \"billing\""));
+        assert!(repo.path().join("src/lib.rs").exists());
+    }
+
+    #[test]
+    fn seed_copies_fixture_before_readme() {
+        let fixture = TempDir::new().unwrap();
+        std::fs::create_dir_all(fixture.path().join("src")).unwrap();
+        std::fs::create_dir_all(fixture.path().join(".git")).unwrap();
+        std::fs::write(fixture.path().join("src/lib.rs"), "pub fn total() {}\n").unwrap();
+        std::fs::write(fixture.path().join("README.md"), "# Legacy billing\n").unwrap();
+        std::fs::write(fixture.path().join(".git/HEAD"), "ref: refs/heads/old\n").unwrap();
+        let repo = TempDir::new().unwrap();
+        SystemGit.run(repo.path(), &["init", "-q"]).unwrap();
+        SystemGit
+            .run(repo.path(), &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(repo.path(), &["config", "user.name", "Test User"])
+            .unwrap();
+        let seeded = BootstrapConfig {
+            fixture: Some(fixture.path().to_path_buf()),
+            ..config()
+        };
+
+        RepoBootstrapper::new(seeded, repo.path())
+            .seed(repo.path(), "Add invoices")
+            .unwrap();
+
+        let readme = std::fs::read_to_string(repo.path().join("README.md")).unwrap();
+        assert!(readme.starts_with("# Legacy billing\n"));
+        assert!(readme.contains("## Goal\n\nAdd invoices"));
+        assert!(repo.path().join("src/lib.rs").exists());
+        assert_ne!(
+            SystemGit
+                .run(repo.path(), &["symbolic-ref", "HEAD"])
+                .unwrap()
+                .trim(),
+            "refs/heads/old"
+        );
+
+        let missing = BootstrapConfig {
+            fixture: Some(fixture.path().join("missing")),
+            ..config()
+        };
+        let err = RepoBootstrapper::new(missing, repo.path())
+            .bootstrap("x")
+            .unwrap_err();
+        assert!(err.to_string().contains("bootstrap.fixture"));
+    }
+
+    #[test]
+    fn protection_request_sets_only_configured_rules() {
+        let request = protection_request(&BranchProtection {
+            required_checks: vec!["test".to_string()],
+            required_approvals: 1,
+            enforce_admins: true,
+        });
+        assert_eq!(request["required_status_checks"]["contexts"][0], "test");
+        assert_eq!(
+            request["required_pull_request_reviews"]["required_approving_review_count"],
+            1
+        );
+        assert_eq!(request["enforce_admins"], true);
+
+        let request = protection_request(&BranchProtection::default());
+        assert!(request["required_status_checks"].is_null());
+        assert!(request["required_pull_request_reviews"].is_null());
+        assert!(request["restrictions"].is_null());
+    }
+}
@@ -0,0 +1,569 @@
+//! Triage of human comments on PRs during the build phase.
+//!
+//! Build phases can run for a long time, and humans may comment on a task
+//! PR while it is still being worked on. A background poller picks up new
+//! comments, classifies them, and hands change requests to the build loop
+//! so a fixer round can run right away instead of waiting for the
+//! GeneralPolish review.
+
+use std::collections::HashSet;
+use std::process::Command;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::error::{Error, Result};
+use crate::team::{ReviewSeverity, ReviewSuggestion};
+
+/// Classification of a human PR comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentKind {
+    /// A question for the author; surfaced but does not trigger a fix.
+    Question,
+    /// A requested change; schedules a fixer round.
+    ChangeRequest,
+    /// A minor style remark; deferred to the GeneralPolish phase.
+    Nit,
+    /// Acknowledgements and other comments needing no action.
+    Other,
+}
+
+/// A comment left on a PR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrComment {
+    /// GitHub node ID of the comment or review.
+    pub id: String,
+    /// Login of the comment author.
+    pub author: String,
+    /// Comment body.
+    pub body: String,
+    /// Whether the comment is the body of a "changes requested" review.
+    #[serde(default)]
+    pub changes_requested: bool,
+    /// Classification of the comment.
+    pub kind: CommentKind,
+}
+
+/// Prefixes that mark a comment as a nit.
+const NIT_PREFIXES: &[&str] = &["nit", "nitpick", "minor", "optional", "style"];
+
+/// Phrases that open thanks, approval or praise.
+const ACKNOWLEDGEMENTS: &[&str] = &[
+    "thanks",
+    "thank you",
+    "thx",
+    "lgtm",
+    "looks good",
+    "looks great",
+    "nice",
+    "great",
+    "good",
+    "awesome",
+    "perfect",
+    "love",
+    "cool",
+    "ship it",
+    "approved",
+    "well done",
+    "+1",
+    "agreed",
+    "makes sense",
+    "sounds good",
+];
+
+/// Modal phrases that ask for a change wherever they appear.
+const MODAL_PHRASES: &[&str] = &[
+    "should",
+    "shouldn't",
+    "must",
+    "mustn't",
+    "need to",
+    "needs to",
+    "have to",
+    "has to",
+    "ought to",
+];
+
+/// Words that may precede the verb of a request ("but please add ...").
+const REQUEST_LEAD_INS: &[&str] = &["please", "but", "and", "also", "so", "then", "maybe"];
+
+/// Words that turn "<word> you" into a polite request ("could you ...").
+const POLITE_MODALS: &[&str] = &["could", "can", "would", "will"];
+
+/// Verbs that, in the imperative, ask for a change to the code.
+const CHANGE_VERBS: &[&str] = &[
+    "add", "avoid", "change", "check", "consider", "convert", "delete", "document", "don't",
+    "drop", "extract", "fix", "guard", "handle", "inline", "keep", "make", "move", "never", "pass",
+    "refactor", "remove", "rename", "replace", "return", "reuse", "revert", "simplify", "split",
+    "switch", "update", "use", "validate", "wrap",
+];
+
+/// Words that start a question.
+const QUESTION_WORDS: &[&str] = &[
+    "why", "what", "how", "when", "where", "which", "who", "is", "are", "does", "do", "can",
+    "could", "would",
+];
+
+/// Classifies a comment body.
+///
+/// Nits are detected by prefix (`nit:`, `minor:`, ...). Clauses of thanks,
+/// approval or praise are set aside next, so "LGTM, please merge" needs no
+/// action. A change request needs a modal ("this should ...") or an
+/// imperative change verb, possibly softened ("please add ...", "could you
+/// rename ...?"); other questions are left alone.
+pub fn classify_comment(body: &str) -> CommentKind {
+    let text = body.trim().to_lowercase();
+    if text.is_empty() {
+        return CommentKind::Other;
+    }
+
+    let first_word: String = text
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '\'')
+        .collect();
+    if NIT_PREFIXES.contains(&first_word.as_str()) || text.starts_with("[nit]") {
+        return CommentKind::Nit;
+    }
+
+    let clauses: Vec<&str> = text
+        .split(['.', '!', '?', ',', ';', '\n'])
+        .map(str::trim)
+        .filter(|clause| !clause.is_empty())
+        .filter(|clause| {
+            !ACKNOWLEDGEMENTS
+                .iter()
+                .any(|ack| starts_with_word(clause, ack))
+        })
+        .collect();
+    let Some(first) = clauses.first() else {
+        return CommentKind::Other;
+    };
+
+    if clauses.iter().any(|clause| requests_change(clause)) {
+        return CommentKind::ChangeRequest;
+    }
+
+    let opening = words(first).next().unwrap_or_default();
+    if text.contains('?') || QUESTION_WORDS.contains(&opening) {
+        return CommentKind::Question;
+    }
+
+    CommentKind::Other
+}
+
+/// Returns true if `clause` uses a modal or starts with an imperative
+/// change verb.
+fn requests_change(clause: &str) -> bool {
+    if MODAL_PHRASES
+        .iter()
+        .any(|phrase| contains_word(clause, phrase))
+    {
+        return true;
+    }
+
+    let words: Vec<&str> = words(clause).collect();
+    let mut rest = words.as_slice();
+    loop {
+        match rest {
+            [word, tail @ ..] if REQUEST_LEAD_INS.contains(word) => rest = tail,
+            [word, "you", tail @ ..] if POLITE_MODALS.contains(word) => rest = tail,
+            _ => break,
+        }
+    }
+    match rest {
+        ["do", "not", ..] => true,
+        [verb, ..] => CHANGE_VERBS.contains(verb),
+        [] => false,
+    }
+}
+
+/// Splits `text` into words, keeping apostrophes ("don't").
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|word| !word.is_empty())
+}
+
+/// Returns true if `text` starts with `phrase` followed by a word boundary.
+fn starts_with_word(text: &str, phrase: &str) -> bool {
+    text.strip_prefix(phrase)
+        .is_some_and(|rest| !rest.starts_with(char::is_alphanumeric))
+}
+
+/// Returns true if `phrase` occurs in `text` on word boundaries.
+fn contains_word(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Parses `gh pr view --json comments,reviews` output into comments.
+///
+/// Reviews without a body are skipped; the body of a "changes requested"
+/// review is always treated as a change request.
+pub fn parse_pr_comments(json: &str) -> Result<Vec<PrComment>> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("gh output", e))?;
+
+    let mut comments = Vec::new();
+    let entries = json["comments"]
+        .as_array()
+        .into_iter()
+        .chain(json["reviews"].as_array())
+        .flatten();
+
+    for entry in entries {
+        let (Some(id), Some(body)) = (entry["id"].as_str(), entry["body"].as_str()) else {
+            continue;
+        };
+        if body.trim().is_empty() {
+            continue;
+        }
+
+        let changes_requested = entry["state"].as_str() == Some("CHANGES_REQUESTED");
+        let kind = if changes_requested {
+            CommentKind::ChangeRequest
+        } else {
+            classify_comment(body)
+        };
+
+        comments.push(PrComment {
+            id: id.to_string(),
+            author: entry["author"]["login"].as_str().unwrap_or("").to_string(),
+            body: body.to_string(),
+            changes_requested,
+            kind,
+        });
+    }
+
+    Ok(comments)
+}
+
+/// New comments from one poll, grouped by classification.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommentTriage {
+    /// Questions for the author.
+    pub questions: Vec<PrComment>,
+    /// Requested changes.
+    pub change_requests: Vec<PrComment>,
+    /// Nits deferred to GeneralPolish.
+    pub nits: Vec<PrComment>,
+    /// Comments needing no action.
+    pub other: Vec<PrComment>,
+}
+
+impl CommentTriage {
+    /// Groups comments by their classification.
+    pub fn from_comments(comments: Vec<PrComment>) -> Self {
+        let mut triage = Self::default();
+        for comment in comments {
+            match comment.kind {
+                CommentKind::Question => triage.questions.push(comment),
+                CommentKind::ChangeRequest => triage.change_requests.push(comment),
+                CommentKind::Nit => triage.nits.push(comment),
+                CommentKind::Other => triage.other.push(comment),
+            }
+        }
+        triage
+    }
+
+    /// Returns true if there are no comments.
+    pub fn is_empty(&self) -> bool {
+        self.questions.is_empty()
+            && self.change_requests.is_empty()
+            && self.nits.is_empty()
+            && self.other.is_empty()
+    }
+
+    /// Returns true if a fixer round should be scheduled.
+    pub fn needs_fix_round(&self) -> bool {
+        !self.change_requests.is_empty()
+    }
+
+    /// Converts change requests into suggestions for [`crate::FixPromptBuilder`].
+    pub fn fix_suggestions(&self) -> Vec<ReviewSuggestion> {
+        self.change_requests
+            .iter()
+            .map(|comment| ReviewSuggestion {
+                file: "(PR comment)".to_string(),
+                line: None,
+                issue: format!("@{}: {}", comment.author, comment.body.trim()),
+                suggestion: "Make the change the reviewer requested".to_string(),
+                severity: ReviewSeverity::High,
+            })
+            .collect()
+    }
+}
+
+/// Polls a PR for new human comments.
+pub struct CommentPoller {
+    /// Comment IDs already returned.
+    seen: HashSet<String>,
+    /// Authors whose comments are ignored (e.g., the orchestrator's account).
+    ignored_authors: Vec<String>,
+}
+
+impl CommentPoller {
+    /// Creates a poller that returns every comment once.
+    pub fn new() -> Self {
+        Self {
+            seen: HashSet::new(),
+            ignored_authors: Vec::new(),
+        }
+    }
+
+    /// Ignores comments by these authors.
+    pub fn with_ignored_authors(mut self, authors: Vec<String>) -> Self {
+        self.ignored_authors = authors;
+        self
+    }
+
+    /// Marks comments as seen without triaging them.
+    ///
+    /// Call this before starting to poll so comments that existed when the
+    /// PR was opened are not treated as new.
+    pub fn mark_seen(&mut self, comments: &[PrComment]) {
+        self.seen.extend(comments.iter().map(|c| c.id.clone()));
+    }
+
+    /// Returns the comments not yet seen, and marks them as seen.
+    pub fn take_new(&mut self, comments: Vec<PrComment>) -> CommentTriage {
+        let new = comments
+            .into_iter()
+            .filter(|c| !self.ignored_authors.contains(&c.author))
+            .filter(|c| self.seen.insert(c.id.clone()))
+            .collect();
+        CommentTriage::from_comments(new)
+    }
+
+    /// Fetches the comments on a PR using gh CLI.
+    pub fn fetch_comments(&self, pr_url: &str) -> Result<Vec<PrComment>> {
+        let args = ["pr", "view", pr_url, "--json", "comments,reviews"];
+        let output = Command::new("gh")
+            .args(args)
+            .output()
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+
+        parse_pr_comments(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Fetches and triages new comments on a PR.
+    pub fn poll(&mut self, pr_url: &str) -> Result<CommentTriage> {
+        let comments = self.fetch_comments(pr_url)?;
+        Ok(self.take_new(comments))
+    }
+
+    /// Polls a PR in the background every `interval`.
+    ///
+    /// Each non-empty triage is sent on the returned channel. Polling stops
+    /// when the receiver is dropped; fetch errors are logged and retried.
+    pub fn spawn(
+        mut self,
+        pr_url: String,
+        interval: Duration,
+    ) -> (mpsc::Receiver<CommentTriage>, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel(16);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if tx.is_closed() {
+                    break;
+                }
+
+                match self.poll(&pr_url) {
+                    Ok(triage) if triage.is_empty() => {}
+                    Ok(triage) => {
+                        tracing::info!(
+                            pr_url = %pr_url,
+                            change_requests = triage.change_requests.len(),
+                            questions = triage.questions.len(),
+                            nits = triage.nits.len(),
+                            "new PR comments"
+                        );
+                        if tx.send(triage).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!(pr_url = %pr_url, error = %e, "failed to poll PR comments");
+                    }
+                }
+            }
+        });
+
+        (rx, handle)
+    }
+}
+
+impl Default for CommentPoller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(id: &str, author: &str, body: &str) -> PrComment {
+        PrComment {
+            id: id.to_string(),
+            author: author.to_string(),
+            body: body.to_string(),
+            changes_requested: false,
+            kind: classify_comment(body),
+        }
+    }
+
+    #[test]
+    fn classify_comment_detects_nits() {
+        assert_eq!(classify_comment("nit: trailing space"), CommentKind::Nit);
+        assert_eq!(classify_comment("Nitpick - rename this"), CommentKind::Nit);
+        assert_eq!(classify_comment("[nit] extra newline"), CommentKind::Nit);
+        assert_eq!(classify_comment("Minor: typo"), CommentKind::Nit);
+    }
+
+    #[test]
+    fn classify_comment_detects_change_requests() {
+        assert_eq!(
+            classify_comment("Please add a test for the error path."),
+            CommentKind::ChangeRequest
+        );
+        assert_eq!(
+            classify_comment("This should return a Result"),
+            CommentKind::ChangeRequest
+        );
+        assert_eq!(
+            classify_comment("Could you rename this to `load`?"),
+            CommentKind::ChangeRequest
+        );
+    }
+
+    #[test]
+    fn classify_comment_needs_an_imperative_or_modal() {
+        assert_eq!(
+            classify_comment("But please add a test; the old code was broken"),
+            CommentKind::ChangeRequest
+        );
+        assert_eq!(
+            classify_comment("Don't log the token here."),
+            CommentKind::ChangeRequest
+        );
+        assert_eq!(
+            classify_comment("The change to the parser looks wrong to me"),
+            CommentKind::Other
+        );
+        assert_eq!(
+            classify_comment("Can you explain the retry logic?"),
+            CommentKind::Question
+        );
+    }
+
+    #[test]
+    fn classify_comment_ignores_thanks_and_approval() {
+        assert_eq!(classify_comment("Thanks for the fix!"), CommentKind::Other);
+        assert_eq!(classify_comment("Nice change"), CommentKind::Other);
+        assert_eq!(classify_comment("LGTM, please merge"), CommentKind::Other);
+        assert_eq!(
+            classify_comment("Looks good, but you need to update the docs"),
+            CommentKind::ChangeRequest
+        );
+        assert_eq!(
+            classify_comment("Thanks! Why is the timeout 30 seconds?"),
+            CommentKind::Question
+        );
+    }
+
+    #[test]
+    fn classify_comment_detects_questions() {
+        assert_eq!(
+            classify_comment("Why is the timeout 30 seconds?"),
+            CommentKind::Question
+        );
+        assert_eq!(
+            classify_comment("Is this used anywhere else"),
+            CommentKind::Question
+        );
+    }
+
+    #[test]
+    fn classify_comment_ignores_partial_words() {
+        // "prefix" contains "fix", "shoulder" contains "should"
+        assert_eq!(
+            classify_comment("LGTM, nice prefix handling"),
+            CommentKind::Other
+        );
+        assert_eq!(classify_comment("shoulder"), CommentKind::Other);
+        assert_eq!(classify_comment(""), CommentKind::Other);
+    }
+
+    #[test]
+    fn parse_pr_comments_reads_comments_and_reviews() {
+        let json = r#"{
+            "comments": [
+                {"id": "IC_1", "author": {"login": "alice"}, "body": "Why this approach?"}
+            ],
+            "reviews": [
+                {"id": "PRR_1", "author": {"login": "bob"}, "body": "", "state": "APPROVED"},
+                {"id": "PRR_2", "author": {"login": "bob"}, "body": "See inline", "state": "CHANGES_REQUESTED"}
+            ]
+        }"#;
+
+        let comments = parse_pr_comments(json).unwrap();
+
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].author, "alice");
+        assert_eq!(comments[0].kind, CommentKind::Question);
+        assert_eq!(comments[1].id, "PRR_2");
+        assert!(comments[1].changes_requested);
+        assert_eq!(comments[1].kind, CommentKind::ChangeRequest);
+        assert!(parse_pr_comments("not json").is_err());
+    }
+
+    #[test]
+    fn take_new_returns_each_comment_once() {
+        let mut poller =
+            CommentPoller::new().with_ignored_authors(vec!["improbability-bot".to_string()]);
+        poller.mark_seen(&[comment("1", "alice", "Please fix the typo")]);
+
+        let triage = poller.take_new(vec![
+            comment("1", "alice", "Please fix the typo"),
+            comment("2", "alice", "Please add docs"),
+            comment("3", "improbability-bot", "Please review"),
+            comment("4", "carol", "nit: spacing"),
+        ]);
+
+        assert_eq!(triage.change_requests.len(), 1);
+        assert_eq!(triage.change_requests[0].id, "2");
+        assert_eq!(triage.nits.len(), 1);
+        assert!(triage.needs_fix_round());
+
+        let again = poller.take_new(vec![comment("2", "alice", "Please add docs")]);
+        assert!(again.is_empty());
+    }
+
+    #[test]
+    fn fix_suggestions_cover_change_requests_only() {
+        let triage = CommentTriage::from_comments(vec![
+            comment("1", "alice", "Please handle the None case"),
+            comment("2", "bob", "nit: spacing"),
+            comment("3", "carol", "What does this do?"),
+        ]);
+
+        let suggestions = triage.fix_suggestions();
+
+        assert_eq!(suggestions.len(), 1);
+        assert!(suggestions[0].issue.contains("@alice"));
+        assert!(suggestions[0].issue.contains("handle the None case"));
+        assert!(!CommentTriage::default().needs_fix_round());
+    }
+}
@@ -1,8 +1,14 @@
 //! Configuration for cruise-control operations.
 
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::time::Duration;
 
+use super::coverage::CoverageConfig;
+use super::docs::DocsConfig;
+use super::followup::FollowUpConfig;
+use crate::team::{DomainSelection, ReviewGating};
+
 /// PR strategy for task completion.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -27,6 +33,9 @@ pub enum RepoLifecycle {
     Persistent,
     /// Keep all artifacts, create new repos per run.
     Accumulating,
+    /// Create a brand-new repository (see [`BootstrapConfig`]) and keep it.
+    #[serde(rename = "create-new")]
+    CreateNew,
 }
 
 /// Test success criteria level.
@@ -40,6 +49,10 @@ pub enum TestLevel {
     Functional,
     /// All phases, app works, AND no critical audit findings.
     Strict,
+    /// Each task writes failing tests before implementing; the app passes
+    /// tests AND no task modified the tests written for it.
+    #[serde(rename = "test-first")]
+    TestFirst,
 }
 
 /// Configuration for the planning phase.
@@ -51,6 +64,9 @@ pub struct PlanningConfig {
     /// Reviewer LLM identifier.
     #[serde(default = "default_reviewer_llm")]
     pub reviewer_llm: String,
+    /// Which plan review findings force another iteration, per review phase.
+    #[serde(default)]
+    pub gating: ReviewGating,
 }
 
 fn default_ping_pong_iterations() -> u32 {
@@ -66,6 +82,7 @@ impl Default for PlanningConfig {
         Self {
             ping_pong_iterations: default_ping_pong_iterations(),
             reviewer_llm: default_reviewer_llm(),
+            gating: ReviewGating::default(),
         }
     }
 }
@@ -82,6 +99,16 @@ pub struct BuildingConfig {
     /// Reviewer LLM for sequential mode.
     #[serde(default = "default_reviewer_llm")]
     pub sequential_reviewer: String,
+    /// Open task PRs as drafts and mark them ready once review approves.
+    #[serde(default)]
+    pub draft_prs: bool,
+    /// Review domains to skip or force for task PRs.
+    #[serde(default)]
+    pub review_domains: DomainSelection,
+    /// Runner flags a plan's spawn instances may pass in `cli_params`.
+    /// Others are dropped, since the plan is written by an LLM.
+    #[serde(default)]
+    pub allowed_cli_params: Vec<String>,
 }
 
 fn default_max_parallel() -> usize {
@@ -94,6 +121,9 @@ impl Default for BuildingConfig {
             max_parallel: default_max_parallel(),
             pr_strategy: PrStrategy::default(),
             sequential_reviewer: default_reviewer_llm(),
+            draft_prs: false,
+            review_domains: DomainSelection::default(),
+            allowed_cli_params: Vec::new(),
         }
     }
 }
@@ -107,6 +137,16 @@ pub struct ValidationConfig {
     /// Curl timeout in seconds.
     #[serde(default = "default_curl_timeout")]
     pub curl_timeout: u64,
+    /// Command running the project's tests. Tests written first for a task
+    /// must fail it before the task is implemented and pass it after.
+    #[serde(default)]
+    pub test_command: Option<String>,
+    /// Coverage collection and thresholds.
+    #[serde(default)]
+    pub coverage: CoverageConfig,
+    /// How unfinished tasks are filed as follow-ups.
+    #[serde(default)]
+    pub follow_ups: FollowUpConfig,
 }
 
 fn default_curl_timeout() -> u64 {
@@ -118,13 +158,30 @@ impl Default for ValidationConfig {
         Self {
             test_level: TestLevel::default(),
             curl_timeout: default_curl_timeout(),
+            test_command: None,
+            coverage: CoverageConfig::default(),
+            follow_ups: FollowUpConfig::default(),
         }
     }
 }
 
-/// Configuration for PR approval polling.
+/// Where plans are approved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalMode {
+    /// Open a plan PR and wait for a review decision.
+    #[default]
+    Pr,
+    /// Show the plan in the terminal and ask there.
+    Terminal,
+}
+
+/// Configuration for plan approval and PR approval polling.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApprovalConfig {
+    /// Where plans are approved.
+    #[serde(default)]
+    pub mode: ApprovalMode,
     /// Initial poll interval.
     #[serde(default = "default_poll_initial")]
     pub poll_initial: Duration,
@@ -134,6 +191,18 @@ pub struct ApprovalConfig {
     /// Exponential backoff multiplier.
     #[serde(default = "default_poll_backoff")]
     pub poll_backoff: f64,
+    /// Local port for a GitHub webhook listener.
+    ///
+    /// When set, review events are received on `127.0.0.1:<port>` and
+    /// polling only runs at `poll_max` as a fallback.
+    #[serde(default)]
+    pub webhook_port: Option<u16>,
+    /// Secret webhook requests are signed with (`X-Hub-Signature-256`).
+    ///
+    /// The listener only starts when this is set; requests without a valid
+    /// signature are rejected.
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
 }
 
 fn default_poll_initial() -> Duration {
@@ -151,9 +220,12 @@ fn default_poll_backoff() -> f64 {
 impl Default for ApprovalConfig {
     fn default() -> Self {
         Self {
+            mode: ApprovalMode::default(),
             poll_initial: default_poll_initial(),
             poll_max: default_poll_max(),
             poll_backoff: default_poll_backoff(),
+            webhook_port: None,
+            webhook_secret: None,
         }
     }
 }
@@ -167,6 +239,10 @@ pub struct TestConfig {
     /// Repository lifecycle.
     #[serde(default)]
     pub repo_lifecycle: RepoLifecycle,
+    /// Pool the repositories are leased from with the `persistent`
+    /// lifecycle.
+    #[serde(default)]
+    pub pool: PoolConfig,
 }
 
 fn default_org() -> String {
@@ -178,6 +254,144 @@ impl Default for TestConfig {
         Self {
             default_org: default_org(),
             repo_lifecycle: RepoLifecycle::default(),
+            pool: PoolConfig::default(),
+        }
+    }
+}
+
+/// Pool of test repositories reused between fixtures (see
+/// [`super::pool::RepoPool`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Most repositories the pool holds, leased or free.
+    #[serde(default = "default_pool_size")]
+    pub max_size: usize,
+    /// Seconds after which a lease that was never released expires.
+    #[serde(default = "default_lease_secs")]
+    pub lease_secs: u64,
+    /// Name prefix of the repositories the pool creates.
+    #[serde(default = "default_pool_prefix")]
+    pub prefix: String,
+}
+
+fn default_pool_size() -> usize {
+    4
+}
+
+fn default_lease_secs() -> u64 {
+    3600
+}
+
+fn default_pool_prefix() -> String {
+    "e2e-pool".to_string()
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: default_pool_size(),
+            lease_secs: default_lease_secs(),
+            prefix: default_pool_prefix(),
+        }
+    }
+}
+
+/// Configuration for creating a new repository before planning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapConfig {
+    /// GitHub owner (user or organization).
+    #[serde(default = "default_org")]
+    pub owner: String,
+    /// Repository name.
+    #[serde(default)]
+    pub name: String,
+    /// Repository description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether the repository is private.
+    #[serde(default = "default_private")]
+    pub private: bool,
+    /// Template repository (`owner/repo`) to create from.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// License keyword (e.g. `mit`, `apache-2.0`). Ignored with a template.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// `.gitignore` template name (e.g. `Rust`). Ignored with a template.
+    #[serde(default)]
+    pub gitignore: Option<String>,
+    /// Default CI workflow to seed: `rust`, `node`, or `python`.
+    #[serde(default)]
+    pub ci: Option<String>,
+    /// Project scaffold to apply before planning (e.g. `axum-service`), or
+    /// `auto` to pick one from the prompt.
+    #[serde(default)]
+    pub scaffold: Option<String>,
+    /// Local directory whose files (starter code, CI config, ...) are
+    /// copied into the clone before it is seeded.
+    #[serde(default)]
+    pub fixture: Option<PathBuf>,
+    /// Protection applied to the default branch once it is pushed.
+    #[serde(default)]
+    pub branch_protection: Option<BranchProtection>,
+}
+
+/// Branch protection for a bootstrapped repository's default branch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchProtection {
+    /// Status checks that must pass before merging.
+    #[serde(default)]
+    pub required_checks: Vec<String>,
+    /// Approving reviews required before merging; `0` requires none.
+    #[serde(default)]
+    pub required_approvals: u32,
+    /// Whether the rules also apply to administrators.
+    #[serde(default)]
+    pub enforce_admins: bool,
+}
+
+fn default_private() -> bool {
+    true
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        Self {
+            owner: default_org(),
+            name: String::new(),
+            description: None,
+            private: default_private(),
+            template: None,
+            license: None,
+            gitignore: None,
+            ci: None,
+            scaffold: None,
+            fixture: None,
+            branch_protection: None,
+        }
+    }
+}
+
+/// What `cruise abort` undoes besides stopping the run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbortConfig {
+    /// Closes the run's PRs; otherwise they are only marked abandoned.
+    #[serde(default = "default_true")]
+    pub close_prs: bool,
+    /// Deletes the run's work branches, locally and on the remote.
+    #[serde(default)]
+    pub delete_branches: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for AbortConfig {
+    fn default() -> Self {
+        Self {
+            close_prs: true,
+            delete_branches: false,
         }
     }
 }
@@ -200,6 +414,15 @@ pub struct CruiseConfig {
     /// E2E test configuration.
     #[serde(default)]
     pub test: TestConfig,
+    /// New repository settings for `repo_lifecycle = "create-new"`.
+    #[serde(default)]
+    pub bootstrap: BootstrapConfig,
+    /// What `cruise abort` undoes.
+    #[serde(default)]
+    pub abort: AbortConfig,
+    /// Documentation pass after approval.
+    #[serde(default)]
+    pub docs: DocsConfig,
 }
 
 #[cfg(test)]
@@ -245,6 +468,10 @@ mod tests {
             serde_json::to_string(&RepoLifecycle::Persistent).unwrap(),
             "\"persistent\""
         );
+        assert_eq!(
+            serde_json::to_string(&RepoLifecycle::CreateNew).unwrap(),
+            "\"create-new\""
+        );
     }
 
     #[test]
@@ -261,6 +488,10 @@ mod tests {
             serde_json::to_string(&TestLevel::Strict).unwrap(),
             "\"strict\""
         );
+        assert_eq!(
+            serde_json::to_string(&TestLevel::TestFirst).unwrap(),
+            "\"test-first\""
+        );
     }
 
     #[test]
@@ -273,9 +504,20 @@ mod tests {
             [building]
             max_parallel = 5
             pr_strategy = "batch"
+            draft_prs = true
+
+            [building.review_domains]
+            skip = ["task_granularity"]
+
+            [building.review_domains.skip_when_only]
+            security = ["docs/**", "**/*.md"]
 
             [validation]
             test_level = "strict"
+
+            [validation.coverage]
+            command = "cargo llvm-cov --summary-only"
+            min_percent = 80.0
         "#;
 
         let config: CruiseConfig = toml::from_str(toml).unwrap();
@@ -283,6 +525,23 @@ mod tests {
         assert_eq!(config.planning.reviewer_llm, "claude-code");
         assert_eq!(config.building.max_parallel, 5);
         assert_eq!(config.building.pr_strategy, PrStrategy::Batch);
+        assert!(config.building.draft_prs);
+        assert_eq!(
+            config
+                .building
+                .review_domains
+                .skip_reason("security", &["docs/guide.md".to_string()])
+                .as_deref(),
+            Some("only docs/**, **/*.md changed")
+        );
+        assert!(config
+            .building
+            .review_domains
+            .skip_reason("task_granularity", &[])
+            .is_some());
         assert_eq!(config.validation.test_level, TestLevel::Strict);
+        assert!(config.validation.coverage.is_enabled());
+        assert_eq!(config.validation.coverage.min_percent, Some(80.0));
+        assert_eq!(config.validation.coverage.max_drop, None);
     }
 }
@@ -0,0 +1,301 @@
+//! Coverage tracking for cruise tasks.
+//!
+//! A configured coverage command (`cargo llvm-cov --summary-only`,
+//! `pytest --cov`, `go test -cover ./...`, ...) is run against a task's base
+//! commit and against its result. The difference is recorded per task in
+//! [`ValidationResult`](super::result::ValidationResult), and a task that
+//! leaves coverage under the configured threshold fails validation.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use super::result::{AuditFinding, FindingSeverity};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::sandbox::{sandbox_environment, SandboxManifest};
+
+/// Category of the audit findings for tasks that lowered coverage too far.
+pub const COVERAGE_CATEGORY: &str = "coverage";
+
+/// Settings for collecting coverage during validation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageConfig {
+    /// Shell command printing a coverage summary; unset disables coverage.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Lowest line coverage, in percent, a task may leave behind.
+    #[serde(default)]
+    pub min_percent: Option<f64>,
+    /// Largest drop in percentage points a single task may cause.
+    #[serde(default)]
+    pub max_drop: Option<f64>,
+}
+
+impl CoverageConfig {
+    /// Returns whether a coverage command is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.command.is_some()
+    }
+
+    /// Runs the coverage command in `worktree` with the sandbox environment
+    /// of `manifest` and returns the line coverage it reports, or `None`
+    /// when coverage is disabled.
+    pub fn measure(&self, worktree: &Path, manifest: &SandboxManifest) -> Result<Option<f64>> {
+        let Some(command) = &self.command else {
+            return Ok(None);
+        };
+
+        tracing::info!(command = %command, "collecting coverage");
+        let output = Command::new("sh")
+            .current_dir(worktree)
+            .args(["-c", command])
+            .env_clear()
+            .envs(sandbox_environment(manifest))
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        if !output.status.success() {
+            return Err(Error::command(
+                format!("coverage command `{}`", command),
+                &output,
+            ));
+        }
+
+        parse_coverage(&stdout)
+            .map(Some)
+            .ok_or_else(|| Error::NoCoverageTotal(command.to_string()))
+    }
+
+    /// Measures coverage at `base` and in `worktree` for `task_id`. The base
+    /// is checked out in a temporary worktree of `repo_path`.
+    pub fn measure_delta(
+        &self,
+        task_id: &str,
+        repo_path: &Path,
+        worktree: &Path,
+        base: &str,
+        manifest: &SandboxManifest,
+    ) -> Result<Option<CoverageDelta>> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+
+        let before = self.measure_at(repo_path, base, manifest)?;
+        let after = self.measure(worktree, manifest)?;
+        Ok(after.map(|after| CoverageDelta {
+            task_id: task_id.to_string(),
+            before,
+            after,
+        }))
+    }
+
+    /// Measures coverage at `commit` in a temporary worktree.
+    fn measure_at(
+        &self,
+        repo_path: &Path,
+        commit: &str,
+        manifest: &SandboxManifest,
+    ) -> Result<Option<f64>> {
+        let worktree = std::env::temp_dir().join(format!(
+            "improbability-drive-coverage-{}",
+            uuid::Uuid::new_v4()
+        ));
+        let worktree_str = worktree
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(worktree.clone()))?;
+
+        SystemGit.run(
+            repo_path,
+            &["worktree", "add", "--detach", worktree_str, commit],
+        )?;
+        let measured = self.measure(&worktree, manifest);
+        SystemGit.run(repo_path, &["worktree", "remove", "--force", worktree_str])?;
+
+        measured
+    }
+
+    /// Returns a critical finding when `delta` breaks the configured
+    /// threshold or maximum drop.
+    pub fn check(&self, delta: &CoverageDelta) -> Option<AuditFinding> {
+        let description = if let Some(min) = self.min_percent.filter(|min| delta.after < *min) {
+            format!(
+                "Task {} left line coverage at {:.1}%, below the minimum of {:.1}%",
+                delta.task_id, delta.after, min
+            )
+        } else if let Some((change, max)) = delta
+            .change()
+            .zip(self.max_drop)
+            .filter(|(change, max)| -change > *max)
+        {
+            format!(
+                "Task {} lowered line coverage by {:.1} points, more than the allowed {:.1}",
+                delta.task_id, -change, max
+            )
+        } else {
+            return None;
+        };
+
+        Some(AuditFinding {
+            severity: FindingSeverity::Critical,
+            category: COVERAGE_CATEGORY.to_string(),
+            description,
+            file: None,
+            line: None,
+            suggestion: Some("Add tests for the code the task introduced".to_string()),
+        })
+    }
+}
+
+/// Line coverage before and after a task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageDelta {
+    /// Task the coverage was measured for.
+    pub task_id: String,
+    /// Coverage at the task's base, in percent, if it could be measured.
+    pub before: Option<f64>,
+    /// Coverage after the task, in percent.
+    pub after: f64,
+}
+
+impl CoverageDelta {
+    /// Returns the change in percentage points, negative for a drop.
+    pub fn change(&self) -> Option<f64> {
+        self.before.map(|before| self.after - before)
+    }
+}
+
+/// Extracts the total line coverage from a coverage tool's output.
+///
+/// Understands `cargo llvm-cov --json`, the `TOTAL` row of
+/// `cargo llvm-cov` and coverage.py, Go's `total:` line, tarpaulin's
+/// "% coverage" summary, and the `All files` row of Istanbul.
+pub fn parse_coverage(output: &str) -> Option<f64> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(output.trim()) {
+        return json
+            .pointer("/data/0/totals/lines/percent")
+            .and_then(serde_json::Value::as_f64);
+    }
+
+    output.lines().rev().find_map(|line| {
+        let lower = line.trim_start().to_lowercase();
+        if lower.starts_with("all files") {
+            line.split(|c: char| c == '|' || c.is_whitespace())
+                .rev()
+                .find_map(|token| token.parse().ok())
+        } else if lower.starts_with("total") {
+            percentages(line).last()
+        } else if lower.contains("% coverage") {
+            percentages(line).next()
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the numbers written as percentages in `line`.
+fn percentages(line: &str) -> impl Iterator<Item = f64> + '_ {
+    line.split_whitespace()
+        .filter_map(|token| token.strip_suffix('%'))
+        .filter_map(|number| number.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parses_common_coverage_tools() {
+        let llvm_cov = "Filename  Regions  Missed Regions  Cover  Functions  Missed Functions  Executed  Lines  Missed Lines  Cover\n\
+                        TOTAL  120  10  91.67%  20  1  95.00%  400  30  92.50%  0  0  -";
+        assert_eq!(parse_coverage(llvm_cov), Some(92.5));
+        assert_eq!(
+            parse_coverage(r#"{"data":[{"totals":{"lines":{"percent":81.25}}}]}"#),
+            Some(81.25)
+        );
+        assert_eq!(
+            parse_coverage("Name  Stmts  Miss  Cover\nTOTAL  100  15  85%"),
+            Some(85.0)
+        );
+        assert_eq!(
+            parse_coverage("ok  example  0.1s\ntotal:\t(statements)\t80.0%"),
+            Some(80.0)
+        );
+        assert_eq!(
+            parse_coverage("|| 73.50% coverage, 147/200 lines covered"),
+            Some(73.5)
+        );
+        assert_eq!(
+            parse_coverage("File | % Stmts | % Branch | % Funcs | % Lines\nAll files | 85.5 | 70 | 90 | 84.2 |"),
+            Some(84.2)
+        );
+        assert_eq!(parse_coverage("running 3 tests\ntest result: ok"), None);
+    }
+
+    #[test]
+    fn thresholds_produce_findings() {
+        let config = CoverageConfig {
+            min_percent: Some(80.0),
+            max_drop: Some(1.0),
+            ..Default::default()
+        };
+        let delta = |before, after| CoverageDelta {
+            task_id: "CRUISE-001".to_string(),
+            before,
+            after,
+        };
+
+        assert!(config.check(&delta(Some(82.0), 81.5)).is_none());
+        assert!(config.check(&delta(None, 81.0)).is_none());
+
+        let below = config.check(&delta(Some(80.5), 79.0)).unwrap();
+        assert_eq!(below.category, COVERAGE_CATEGORY);
+        assert!(below
+            .description
+            .contains("79.0%, below the minimum of 80.0%"));
+
+        let dropped = config.check(&delta(Some(95.0), 90.0)).unwrap();
+        assert_eq!(dropped.severity, FindingSeverity::Critical);
+        assert!(dropped.description.contains("by 5.0 points"));
+    }
+
+    #[test]
+    fn measures_base_and_worktree() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path();
+        let run = |args: &[&str]| SystemGit.run(repo, args).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "test@example.com"]);
+        run(&["config", "user.name", "Test"]);
+        std::fs::write(repo.join("coverage.txt"), "TOTAL 10 1 90%\n").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "base"]);
+        std::fs::write(repo.join("coverage.txt"), "TOTAL 20 5 75%\n").unwrap();
+
+        let config = CoverageConfig {
+            command: Some("cat coverage.txt".to_string()),
+            ..Default::default()
+        };
+        let manifest = SandboxManifest::default();
+        let delta = config
+            .measure_delta("CRUISE-002", repo, repo, "HEAD", &manifest)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(delta.before, Some(90.0));
+        assert_eq!(delta.after, 75.0);
+        assert_eq!(delta.change(), Some(-15.0));
+        assert_eq!(
+            CoverageConfig::default().measure(repo, &manifest).unwrap(),
+            None
+        );
+
+        let failing = CoverageConfig {
+            command: Some("echo no tool >&2; exit 1".to_string()),
+            ..Default::default()
+        };
+        assert!(failing.measure(repo, &manifest).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Documentation pass after approval.
+//!
+//! Once a run's changes are approved, an optional docs-focused LLM pass
+//! brings the README, CHANGELOG and doc comments in line with what the
+//! branch changed. Its edits are committed on their own, so reviewers can
+//! tell them apart from the implementation, and pushed to the same branch
+//! so they land in the same PR.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::task::CruisePlan;
+use crate::audit::SpawnObservability;
+use crate::commit::{CommitContext, CommitMessages, CommitType};
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+use crate::runner::{run_in_sandbox, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::{sandbox_environment_names, SandboxManifest};
+use crate::spawn::{branch_changes, ChangeKind, CommitInfo, FileChange};
+
+/// Most changed files listed in the docs prompt.
+const MAX_PROMPT_FILES: usize = 100;
+
+/// Settings for the documentation pass.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocsConfig {
+    /// Runs the documentation pass after approval.
+    #[serde(default)]
+    pub enabled: bool,
+    /// LLM that writes the documentation; defaults to the spawn default.
+    #[serde(default)]
+    pub llm: Option<String>,
+    /// Model passed to the LLM.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Documentation files to keep current, relative to the repository.
+    #[serde(default = "default_files")]
+    pub files: Vec<String>,
+    /// Also updates the doc comments of changed code.
+    #[serde(default = "default_true")]
+    pub doc_comments: bool,
+}
+
+fn default_files() -> Vec<String> {
+    vec!["README.md".to_string(), "CHANGELOG.md".to_string()]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for DocsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            llm: None,
+            model: None,
+            files: default_files(),
+            doc_comments: default_true(),
+        }
+    }
+}
+
+/// What the documentation pass did.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocsOutcome {
+    /// Commit holding the documentation changes, if there were any.
+    pub commit: Option<String>,
+    /// Files the pass changed.
+    pub files: Vec<PathBuf>,
+    /// What the LLM did.
+    pub observability: SpawnObservability,
+}
+
+/// Runs the documentation pass on an approved branch.
+pub struct DocsUpdater {
+    runner: Arc<dyn LLMRunner>,
+    config: DocsConfig,
+    messages: CommitMessages,
+    git: Arc<dyn GitExecutor>,
+}
+
+impl DocsUpdater {
+    /// Creates an updater writing documentation with `runner`.
+    pub fn new(runner: Arc<dyn LLMRunner>, config: DocsConfig) -> Self {
+        Self {
+            runner,
+            config,
+            messages: CommitMessages::default(),
+            git: Arc::new(SystemGit),
+        }
+    }
+
+    /// Sets how the documentation commit is written and signed.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    /// Runs git through `git` instead of the system binary.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Updates the documentation for the changes made on `worktree` since
+    /// `base`, commits the result and pushes it to `branch`.
+    ///
+    /// Nothing is committed when the branch has no changes or the LLM
+    /// leaves the documentation as it is.
+    pub async fn run(
+        &self,
+        worktree: &Path,
+        plan: &CruisePlan,
+        base: &str,
+        branch: &str,
+    ) -> Result<DocsOutcome> {
+        let mut outcome = DocsOutcome::default();
+        let (files, commits) = branch_changes(self.git.as_ref(), worktree, base)?;
+        if files.is_empty() {
+            return Ok(outcome);
+        }
+
+        let manifest = SandboxManifest::default();
+        outcome.observability.environment = sandbox_environment_names(&manifest);
+        let config = LLMSpawnConfig {
+            prompt: docs_prompt(plan, &self.config, &files, &commits),
+            working_dir: worktree.to_path_buf(),
+            manifest,
+            model: self.config.model.clone(),
+            extra_args: Vec::new(),
+            session: None,
+        };
+        let run = run_in_sandbox(self.runner.as_ref(), config).await?;
+        outcome.observability.tool_calls = run.tool_calls;
+        if !run.result.success {
+            tracing::warn!(runner = %self.runner.name(), "documentation pass exited unsuccessfully");
+        }
+
+        let status = self.git.run(worktree, &["status", "--porcelain"])?;
+        outcome.files = status
+            .lines()
+            .filter_map(|line| line.get(3..))
+            .map(PathBuf::from)
+            .collect();
+        if outcome.files.is_empty() {
+            tracing::info!("documentation already up to date");
+            return Ok(outcome);
+        }
+
+        let title = if plan.title.is_empty() {
+            &plan.prompt
+        } else {
+            &plan.title
+        };
+        let context = CommitContext::new(format!("update documentation for {}", title))
+            .with_kind(CommitType::Docs)
+            .with_llm(self.runner.name());
+        let message = self.messages.message(&context);
+        let args = self.messages.commit_args(&message);
+        self.git.run(worktree, &["add", "-A"])?;
+        self.git.run(
+            worktree,
+            &args.iter().map(String::as_str).collect::<Vec<_>>(),
+        )?;
+        let commit = self.git.run(worktree, &["rev-parse", "HEAD"])?;
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        self.git.run(worktree, &["push", "origin", &refspec])?;
+
+        tracing::info!(files = outcome.files.len(), branch = %branch, "pushed documentation update");
+        outcome.commit = Some(commit.trim().to_string());
+        Ok(outcome)
+    }
+}
+
+/// Builds the prompt asking for documentation of `files` and `commits`.
+pub fn docs_prompt(
+    plan: &CruisePlan,
+    config: &DocsConfig,
+    files: &[FileChange],
+    commits: &[CommitInfo],
+) -> String {
+    let mut prompt = String::from(
+        "You are updating the documentation of a branch whose changes have been \
+         approved. Bring the documentation in line with what the branch changed. \
+         Change only documentation: do not change behaviour, tests, or formatting \
+         of code.\n\n",
+    );
+    prompt.push_str(&format!("## Feature\n\n{}\n\n", plan.prompt.trim()));
+
+    prompt.push_str("## Documentation to update\n\n");
+    for file in &config.files {
+        prompt.push_str(&format!("- `{}`\n", file));
+    }
+    if config.doc_comments {
+        prompt.push_str("- Doc comments of the changed code\n");
+    }
+    prompt.push_str(
+        "\nAdd changelog entries under the unreleased section, creating it if \
+         needed. Leave a file untouched if nothing in it is out of date.\n",
+    );
+
+    prompt.push_str("\n## Commits\n\n");
+    if commits.is_empty() {
+        prompt.push_str("(none)\n");
+    }
+    for commit in commits {
+        let subject = commit.message.lines().next().unwrap_or_default();
+        prompt.push_str(&format!("- {}\n", subject));
+    }
+
+    prompt.push_str("\n## Changed files\n\n");
+    for file in files.iter().take(MAX_PROMPT_FILES) {
+        let kind = match file.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+        };
+        prompt.push_str(&format!("- `{}` ({})\n", file.path.display(), kind));
+    }
+    if files.len() > MAX_PROMPT_FILES {
+        prompt.push_str(&format!(
+            "- ... and {} more\n",
+            files.len() - MAX_PROMPT_FILES
+        ));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{GitOutput, MockGit};
+    use crate::runner::{LLMOutput, LLMResult};
+    use async_trait::async_trait;
+    use std::process::Command;
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    /// Writer that records its prompt.
+    #[derive(Default)]
+    struct Writer {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LLMRunner for Writer {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            self.prompts.lock().unwrap().push(config.prompt);
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "claude-code"
+        }
+    }
+
+    #[tokio::test]
+    async fn documentation_is_committed_and_pushed_separately() {
+        let git = Arc::new(
+            MockGit::new()
+                .on(
+                    &["diff", "--name-status"],
+                    GitOutput::ok("A\0src/auth.rs\0"),
+                )
+                .on(
+                    &["diff", "--numstat"],
+                    GitOutput::ok("40\t0\tsrc/auth.rs\0"),
+                )
+                .on(&["log"], GitOutput::ok("abc\x1ffeat: add login\x1e"))
+                .on(
+                    &["status"],
+                    GitOutput::ok(" M README.md\n?? CHANGELOG.md\n"),
+                )
+                .on(&["add"], GitOutput::ok(""))
+                .on(&["commit"], GitOutput::ok(""))
+                .on(&["rev-parse"], GitOutput::ok("def456\n"))
+                .on(&["push"], GitOutput::ok("")),
+        );
+        let writer = Arc::new(Writer::default());
+        let updater = DocsUpdater::new(writer.clone(), DocsConfig::default()).with_git(git.clone());
+        let mut plan = CruisePlan::new("Add login");
+        plan.title = "Login".to_string();
+
+        let outcome = updater
+            .run(Path::new("/repo"), &plan, "main", "cruise/login")
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.commit.as_deref(), Some("def456"));
+        assert_eq!(
+            outcome.files,
+            [PathBuf::from("README.md"), PathBuf::from("CHANGELOG.md")]
+        );
+        let prompt = &writer.prompts.lock().unwrap()[0];
+        assert!(prompt.contains("- `README.md`\n- `CHANGELOG.md`\n- Doc comments"));
+        assert!(prompt.contains("- feat: add login\n"));
+        assert!(prompt.contains("- `src/auth.rs` (added)\n"));
+
+        let calls = git.calls();
+        let commit = calls.iter().find(|c| c[0] == "commit").unwrap();
+        assert!(commit[2].starts_with("docs: update documentation for Login"));
+        assert_eq!(
+            calls.last().unwrap(),
+            &["push", "origin", "HEAD:refs/heads/cruise/login"]
+        );
+    }
+}
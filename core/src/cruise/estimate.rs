@@ -0,0 +1,442 @@
+//! Duration and cost estimates for cruise plans.
+//!
+//! After planning, every task gets an estimated duration and model cost so
+//! approvers of the plan PR know what the build phase will take. Estimates
+//! come from the task history registry when it has enough finished tasks
+//! of the same complexity, and from per-complexity defaults otherwise.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::config::BuildingConfig;
+use super::executor::TaskExecutor;
+use super::planner::compute_execution_waves;
+use super::result::BuildResult;
+use super::status::format_secs;
+use super::task::{CruisePlan, TaskComplexity, TaskStatus};
+use crate::error::{Error, Result};
+use crate::prompt::budget;
+
+/// File, relative to the repository, finished tasks are recorded in.
+pub const TASK_HISTORY_FILE: &str = ".improbability-drive/task-history.jsonl";
+
+/// Finished tasks of a complexity needed before history replaces the
+/// defaults.
+pub const MIN_HISTORY_SAMPLES: usize = 3;
+
+/// Most recent finished tasks of a complexity that estimates average over.
+const HISTORY_WINDOW: usize = 20;
+
+/// A finished task in the history registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskSample {
+    /// Complexity the task was planned with.
+    pub complexity: TaskComplexity,
+    /// How long the task took.
+    pub duration_secs: u64,
+    /// What its LLM invocations cost in USD, if known.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+/// Registry of finished tasks, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskHistory {
+    /// Every recorded task.
+    pub samples: Vec<TaskSample>,
+}
+
+impl TaskHistory {
+    /// Returns where the registry is stored under `repo`.
+    pub fn path(repo: &Path) -> PathBuf {
+        repo.join(TASK_HISTORY_FILE)
+    }
+
+    /// Loads the registry from `repo`; a missing registry is empty. Lines
+    /// that cannot be parsed are skipped.
+    pub fn load(repo: &Path) -> Result<Self> {
+        let path = Self::path(repo);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let samples = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(sample) => Some(sample),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "skipping task history entry");
+                    None
+                }
+            })
+            .collect();
+        Ok(Self { samples })
+    }
+
+    /// Appends `samples` to the registry in `repo`.
+    pub fn append(repo: &Path, samples: &[TaskSample]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+
+        let path = Self::path(repo);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        for sample in samples {
+            let line =
+                serde_json::to_string(sample).map_err(|e| Error::json("task history entry", e))?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a sample for each task `build` completed, with its planned
+    /// complexity from `plan`.
+    pub fn samples_from_build(plan: &CruisePlan, build: &BuildResult) -> Vec<TaskSample> {
+        build
+            .task_results
+            .iter()
+            .filter(|result| result.status == TaskStatus::Completed)
+            .filter_map(|result| {
+                let task = plan.tasks.iter().find(|t| t.id == result.task_id)?;
+                Some(TaskSample {
+                    complexity: task.complexity,
+                    duration_secs: result.duration.as_secs(),
+                    cost_usd: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the average duration and, if recorded, cost of recent tasks
+    /// of `complexity`, or `None` with fewer than [`MIN_HISTORY_SAMPLES`].
+    pub fn average(&self, complexity: TaskComplexity) -> Option<(Duration, Option<f64>)> {
+        let recent: Vec<&TaskSample> = self
+            .samples
+            .iter()
+            .rev()
+            .filter(|s| s.complexity == complexity)
+            .take(HISTORY_WINDOW)
+            .collect();
+        if recent.len() < MIN_HISTORY_SAMPLES {
+            return None;
+        }
+
+        let secs = recent.iter().map(|s| s.duration_secs).sum::<u64>() / recent.len() as u64;
+        let costs: Vec<f64> = recent.iter().filter_map(|s| s.cost_usd).collect();
+        let cost = (costs.len() >= MIN_HISTORY_SAMPLES)
+            .then(|| costs.iter().sum::<f64>() / costs.len() as f64);
+        Some((Duration::from_secs(secs), cost))
+    }
+}
+
+/// Duration assumed for a task of `complexity` without history.
+fn default_duration(complexity: TaskComplexity) -> Duration {
+    Duration::from_secs(match complexity {
+        TaskComplexity::Low => 15 * 60,
+        TaskComplexity::Medium => 45 * 60,
+        TaskComplexity::High => 90 * 60,
+    })
+}
+
+/// Tokens (input, output) assumed per invocation for a task of
+/// `complexity`. Agents re-read files as they work, so input dominates.
+fn default_tokens(complexity: TaskComplexity) -> (usize, usize) {
+    match complexity {
+        TaskComplexity::Low => (60_000, 8_000),
+        TaskComplexity::Medium => (150_000, 20_000),
+        TaskComplexity::High => (400_000, 50_000),
+    }
+}
+
+/// Estimate for one task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskEstimate {
+    /// Task ID.
+    pub task_id: String,
+    /// Planned complexity.
+    pub complexity: TaskComplexity,
+    /// Expected duration.
+    pub duration: Duration,
+    /// Expected model cost in USD.
+    pub cost_usd: f64,
+    /// Whether the duration came from the history registry.
+    pub from_history: bool,
+}
+
+/// Estimate for a whole plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanEstimate {
+    /// One estimate per task, in plan order.
+    pub tasks: Vec<TaskEstimate>,
+    /// Time from the first task starting to the last finishing, running
+    /// each wave with the configured parallelism.
+    pub wall_clock: Duration,
+}
+
+impl PlanEstimate {
+    /// Returns the sum of all task durations.
+    pub fn total_duration(&self) -> Duration {
+        self.tasks.iter().map(|t| t.duration).sum()
+    }
+
+    /// Returns the total model cost in USD.
+    pub fn total_cost(&self) -> f64 {
+        self.tasks.iter().map(|t| t.cost_usd).sum()
+    }
+
+    /// Renders the estimate as a markdown section for the plan PR.
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::from("## Estimate\n\n");
+        md.push_str(&format!(
+            "- **Build time**: ~{} ({} of task time)\n- **Model cost**: ~${:.2}\n",
+            format_secs(self.wall_clock.as_secs()),
+            format_secs(self.total_duration().as_secs()),
+            self.total_cost()
+        ));
+        let from_history = self.tasks.iter().filter(|t| t.from_history).count();
+        md.push_str(&format!(
+            "- **Based on**: {}\n\n",
+            match from_history {
+                0 => "default estimates per complexity".to_string(),
+                n if n == self.tasks.len() => "past tasks of the same complexity".to_string(),
+                n => format!(
+                    "past tasks for {} of {} tasks, defaults for the rest",
+                    n,
+                    self.tasks.len()
+                ),
+            }
+        ));
+
+        md.push_str("| ID | Complexity | Duration | Cost |\n");
+        md.push_str("|----|------------|----------|------|\n");
+        for task in &self.tasks {
+            md.push_str(&format!(
+                "| {} | {} | {} | ${:.2} |\n",
+                task.task_id,
+                format!("{:?}", task.complexity).to_lowercase(),
+                format_secs(task.duration.as_secs()),
+                task.cost_usd
+            ));
+        }
+        md.push('\n');
+        md
+    }
+}
+
+/// Estimates plans from the history registry and build configuration.
+pub struct PlanEstimator {
+    history: TaskHistory,
+    config: BuildingConfig,
+}
+
+impl PlanEstimator {
+    /// Creates an estimator for builds run with `config`.
+    pub fn new(config: BuildingConfig) -> Self {
+        Self {
+            history: TaskHistory::default(),
+            config,
+        }
+    }
+
+    /// Sets the registry of finished tasks estimates are drawn from.
+    pub fn with_history(mut self, history: TaskHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Estimates every task of `plan` and the plan's wall-clock time.
+    ///
+    /// Without recorded costs, each instance of a task is priced at the
+    /// default token counts for its complexity and its model's list price,
+    /// and instances run through spawn-team add one review at the
+    /// reviewer's price.
+    pub fn estimate(&self, plan: &CruisePlan) -> PlanEstimate {
+        let executor = TaskExecutor::new(self.config.clone());
+        let tasks: Vec<TaskEstimate> = plan
+            .tasks
+            .iter()
+            .map(|task| {
+                let history = self.history.average(task.complexity);
+                let (input, output) = default_tokens(task.complexity);
+                let default_cost = executor
+                    .plan_task(task)
+                    .iter()
+                    .map(|instance| {
+                        let model = instance.model.as_deref().unwrap_or(&instance.llm);
+                        let review = instance.team.as_ref().map_or(0.0, |team| {
+                            budget::estimate_cost(&team.reviewer_llm, input, output)
+                        });
+                        budget::estimate_cost(model, input, output) + review
+                    })
+                    .sum();
+
+                TaskEstimate {
+                    task_id: task.id.clone(),
+                    complexity: task.complexity,
+                    duration: history.map_or_else(|| default_duration(task.complexity), |(d, _)| d),
+                    cost_usd: history.and_then(|(_, cost)| cost).unwrap_or(default_cost),
+                    from_history: history.is_some(),
+                }
+            })
+            .collect();
+
+        let wall_clock = compute_execution_waves(plan)
+            .iter()
+            .map(|wave| {
+                let mut durations: Vec<Duration> = wave
+                    .iter()
+                    .filter_map(|id| tasks.iter().find(|t| &t.task_id == id))
+                    .map(|t| t.duration)
+                    .collect();
+                durations.sort_unstable_by(|a, b| b.cmp(a));
+                // Longest tasks first, `max_parallel` at a time
+                durations
+                    .chunks(self.config.max_parallel.max(1))
+                    .map(|batch| batch[0])
+                    .sum::<Duration>()
+            })
+            .sum();
+
+        PlanEstimate { tasks, wall_clock }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::result::TaskResult;
+    use crate::cruise::task::{CruiseTask, SpawnInstance};
+    use tempfile::TempDir;
+
+    fn task(id: &str, complexity: TaskComplexity) -> CruiseTask {
+        CruiseTask::new(id, "Task").with_complexity(complexity)
+    }
+
+    #[test]
+    fn defaults_per_complexity_and_parallel_waves() {
+        let mut plan = CruisePlan::new("test");
+        plan.tasks = vec![
+            task("CRUISE-001", TaskComplexity::Low),
+            task("CRUISE-002", TaskComplexity::High)
+                .with_blocked_by(vec!["CRUISE-001".to_string()]),
+            task("CRUISE-003", TaskComplexity::Medium)
+                .with_blocked_by(vec!["CRUISE-001".to_string()]),
+        ];
+        plan.tasks[2].spawn_instances = vec![SpawnInstance {
+            model: Some("opus".to_string()),
+            use_spawn_team: true,
+            ..Default::default()
+        }];
+
+        let estimate = PlanEstimator::new(BuildingConfig::default()).estimate(&plan);
+
+        // Wave 1 runs the low task, wave 2 both others side by side
+        assert_eq!(estimate.wall_clock, Duration::from_secs((15 + 90) * 60));
+        assert_eq!(estimate.total_duration(), Duration::from_secs(150 * 60));
+        assert!(estimate.tasks.iter().all(|t| !t.from_history));
+        // Opus plus a review costs more than a sonnet-priced high task
+        assert!(estimate.tasks[2].cost_usd > estimate.tasks[1].cost_usd);
+
+        let sequential = PlanEstimator::new(BuildingConfig {
+            max_parallel: 1,
+            ..Default::default()
+        })
+        .estimate(&plan);
+        assert_eq!(sequential.wall_clock, sequential.total_duration());
+
+        let md = estimate.to_markdown();
+        assert!(md.contains("- **Build time**: ~1h 45m (2h 30m of task time)"));
+        assert!(md.contains("default estimates per complexity"));
+        assert!(md.contains("| CRUISE-002 | high | 1h 30m |"));
+    }
+
+    #[test]
+    fn history_replaces_defaults_once_there_are_enough_samples() {
+        let sample = |secs, cost| TaskSample {
+            complexity: TaskComplexity::Medium,
+            duration_secs: secs,
+            cost_usd: cost,
+        };
+        let mut history = TaskHistory {
+            samples: vec![sample(600, Some(1.0)), sample(1200, Some(2.0))],
+        };
+        assert_eq!(history.average(TaskComplexity::Medium), None);
+
+        history.samples.push(sample(1800, Some(3.0)));
+        assert_eq!(
+            history.average(TaskComplexity::Medium),
+            Some((Duration::from_secs(1200), Some(2.0)))
+        );
+        assert_eq!(history.average(TaskComplexity::Low), None);
+
+        let mut plan = CruisePlan::new("test");
+        plan.tasks = vec![
+            task("CRUISE-001", TaskComplexity::Medium),
+            task("CRUISE-002", TaskComplexity::Low),
+        ];
+        let estimate = PlanEstimator::new(BuildingConfig::default())
+            .with_history(history)
+            .estimate(&plan);
+
+        assert!(estimate.tasks[0].from_history);
+        assert_eq!(estimate.tasks[0].cost_usd, 2.0);
+        assert!(!estimate.tasks[1].from_history);
+        assert!(estimate
+            .to_markdown()
+            .contains("past tasks for 1 of 2 tasks, defaults for the rest"));
+    }
+
+    #[test]
+    fn records_completed_tasks_in_registry() {
+        let dir = TempDir::new().unwrap();
+        let mut plan = CruisePlan::new("test");
+        plan.tasks = vec![
+            task("CRUISE-001", TaskComplexity::High),
+            task("CRUISE-002", TaskComplexity::Low),
+        ];
+        let result = |id: &str, status| TaskResult {
+            task_id: id.to_string(),
+            status,
+            pr_url: None,
+            duration: Duration::from_secs(4000),
+            error: None,
+        };
+        let build = BuildResult {
+            success: false,
+            task_results: vec![
+                result("CRUISE-001", TaskStatus::Completed),
+                result("CRUISE-002", TaskStatus::Blocked),
+            ],
+            max_parallelism: 2,
+            duration: Duration::from_secs(4000),
+            completed_count: 1,
+            blocked_count: 1,
+            observability: Vec::new(),
+        };
+
+        assert_eq!(
+            TaskHistory::load(dir.path()).unwrap(),
+            TaskHistory::default()
+        );
+        let samples = TaskHistory::samples_from_build(&plan, &build);
+        TaskHistory::append(dir.path(), &samples).unwrap();
+        TaskHistory::append(dir.path(), &samples).unwrap();
+
+        let history = TaskHistory::load(dir.path()).unwrap();
+        assert_eq!(history.samples.len(), 2);
+        assert_eq!(history.samples[0].complexity, TaskComplexity::High);
+        assert_eq!(history.samples[0].duration_secs, 4000);
+    }
+}
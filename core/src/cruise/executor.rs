@@ -0,0 +1,409 @@
+//! Task execution planning for the cruise-control build phase.
+//!
+//! Resolves each task's spawn instances into the runner, permissions, and
+//! spawn-team configuration used to execute it.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::config::BuildingConfig;
+use super::memory::CruiseMemory;
+use super::task::{CruiseTask, SpawnInstance, TaskComplexity};
+use crate::error::{Error, Result};
+use crate::patch::glob_matches;
+use crate::runner::{ClaudeRunner, GeminiConfig, GeminiRunner, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::{SandboxManifest, TaskComplexity as SandboxComplexity};
+use crate::team::SpawnTeamConfig;
+
+/// Resolved configuration for one spawned instance of a task.
+#[derive(Debug, Clone)]
+pub struct InstanceExecution {
+    /// ID of the task being executed.
+    pub task_id: String,
+    /// Index of the instance within the task.
+    pub index: usize,
+    /// LLM runner identifier.
+    pub llm: String,
+    /// Model passed to the runner.
+    pub model: Option<String>,
+    /// Prompt for this instance.
+    pub prompt: String,
+    /// Additional CLI arguments for the runner.
+    pub extra_args: Vec<String>,
+    /// Sandbox permissions for this instance.
+    pub manifest: SandboxManifest,
+    /// Spawn-team configuration, if the instance runs with a reviewer.
+    pub team: Option<SpawnTeamConfig>,
+}
+
+impl InstanceExecution {
+    /// Builds the runner spawn configuration for a sandbox directory.
+    pub fn spawn_config(&self, working_dir: PathBuf) -> LLMSpawnConfig {
+        LLMSpawnConfig {
+            prompt: self.prompt.clone(),
+            working_dir,
+            manifest: self.manifest.clone(),
+            model: self.model.clone(),
+            extra_args: self.extra_args.clone(),
+            session: None,
+        }
+    }
+}
+
+/// Plans how tasks are executed during the build phase.
+pub struct TaskExecutor {
+    config: BuildingConfig,
+    base_manifest: SandboxManifest,
+    memory: Option<CruiseMemory>,
+}
+
+impl TaskExecutor {
+    /// Creates a new executor with the given build configuration.
+    pub fn new(config: BuildingConfig) -> Self {
+        Self {
+            config,
+            base_manifest: SandboxManifest::default(),
+            memory: None,
+        }
+    }
+
+    /// Sets the manifest used when an instance does not override permissions.
+    pub fn with_manifest(mut self, manifest: SandboxManifest) -> Self {
+        self.base_manifest = manifest;
+        self
+    }
+
+    /// Shares decisions between tasks through `memory`.
+    ///
+    /// Task prompts carry the decisions recorded so far and ask the task to
+    /// list its own; record them with [`CruiseMemory::record`].
+    pub fn with_memory(mut self, memory: CruiseMemory) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Returns the memory shared between tasks, if any.
+    pub fn memory(&self) -> Option<&CruiseMemory> {
+        self.memory.as_ref()
+    }
+
+    /// Resolves the instances that execute a task.
+    ///
+    /// Tasks without `spawn_instances` run as a single default instance.
+    pub fn plan_task(&self, task: &CruiseTask) -> Vec<InstanceExecution> {
+        let default_instance = [SpawnInstance::default()];
+        let instances = if task.spawn_instances.is_empty() {
+            &default_instance[..]
+        } else {
+            &task.spawn_instances[..]
+        };
+
+        instances
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| self.resolve_instance(task, index, instance))
+            .collect()
+    }
+
+    fn resolve_instance(
+        &self,
+        task: &CruiseTask,
+        index: usize,
+        instance: &SpawnInstance,
+    ) -> InstanceExecution {
+        // Plans are written by an LLM, so instances may only narrow the
+        // base permissions
+        let mut manifest = self.base_manifest.clone();
+        manifest.allowed_tools =
+            narrow(&manifest.allowed_tools, &instance.allowed_tools, |b, t| {
+                b == t
+            });
+        manifest.writable_paths = narrow(
+            &manifest.writable_paths,
+            &instance.writable_paths,
+            glob_matches,
+        );
+        manifest.complexity = match task.complexity {
+            TaskComplexity::Low => SandboxComplexity::Low,
+            TaskComplexity::Medium => SandboxComplexity::Medium,
+            TaskComplexity::High => SandboxComplexity::High,
+        };
+
+        let team = instance.use_spawn_team.then(|| SpawnTeamConfig {
+            primary_llm: instance.llm.clone(),
+            primary_model: instance.model.clone(),
+            reviewer_llm: self.config.sequential_reviewer.clone(),
+            review_domains: self.config.review_domains.clone(),
+            ..Default::default()
+        });
+
+        let mut prompt = build_task_prompt(task, instance);
+        if let Some(memory) = &self.memory {
+            prompt = memory.inject(&prompt);
+        }
+
+        InstanceExecution {
+            task_id: task.id.clone(),
+            index,
+            llm: instance.llm.clone(),
+            model: instance.model.clone(),
+            prompt,
+            extra_args: allowed_cli_params(
+                &task.id,
+                &instance.cli_params,
+                &self.config.allowed_cli_params,
+            ),
+            manifest,
+            team,
+        }
+    }
+}
+
+/// Narrows the `base` permission list to the entries of `requested` that
+/// some base entry `covers`.
+///
+/// An empty list grants everything, so an empty base takes the request as
+/// is, and a request with nothing left keeps the base.
+fn narrow(
+    base: &[String],
+    requested: &[String],
+    covers: impl Fn(&str, &str) -> bool,
+) -> Vec<String> {
+    if base.is_empty() {
+        return requested.to_vec();
+    }
+    let kept: Vec<String> = requested
+        .iter()
+        .filter(|entry| base.iter().any(|b| covers(b, entry)))
+        .cloned()
+        .collect();
+    if kept.is_empty() {
+        base.to_vec()
+    } else {
+        kept
+    }
+}
+
+/// Keeps the CLI parameters whose flags are in `allowed`, along with the
+/// values that follow them.
+fn allowed_cli_params(task_id: &str, params: &[String], allowed: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut keep_values = false;
+    for param in params {
+        if param.starts_with('-') {
+            let flag = param.split('=').next().unwrap_or(param);
+            keep_values = allowed.iter().any(|a| a == flag);
+            if !keep_values {
+                tracing::warn!(task = %task_id, flag = %flag, "dropping CLI parameter not allowed for plans");
+            }
+        }
+        if keep_values {
+            kept.push(param.clone());
+        }
+    }
+    kept
+}
+
+/// Creates the runner for an LLM identifier.
+pub fn create_runner(llm: &str) -> Result<Arc<dyn LLMRunner>> {
+    create_runner_with(llm, &GeminiConfig::default())
+}
+
+/// Creates the runner for an LLM identifier, invoking Gemini CLI as
+/// `gemini` configures.
+pub fn create_runner_with(llm: &str, gemini: &GeminiConfig) -> Result<Arc<dyn LLMRunner>> {
+    match llm {
+        "claude-code" => Ok(Arc::new(ClaudeRunner::new())),
+        "gemini-cli" => Ok(Arc::new(GeminiRunner::new().with_config(gemini.clone()))),
+        other => Err(Error::Config(format!("unknown LLM runner '{}'", other))),
+    }
+}
+
+/// Builds the prompt for one instance of a task.
+fn build_task_prompt(task: &CruiseTask, instance: &SpawnInstance) -> String {
+    let mut prompt = format!("## Task {}: {}\n\n", task.id, task.subject);
+
+    if !task.description.is_empty() {
+        prompt.push_str(&task.description);
+        prompt.push_str("\n\n");
+    }
+
+    if !task.acceptance_criteria.is_empty() {
+        prompt.push_str("### Acceptance Criteria\n\n");
+        for criterion in &task.acceptance_criteria {
+            prompt.push_str(&format!("- {}\n", criterion));
+        }
+        prompt.push('\n');
+    }
+
+    if let Some(focus) = &instance.focus {
+        prompt.push_str("### Focus\n\n");
+        prompt.push_str(focus);
+        prompt.push('\n');
+    }
+
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task() -> CruiseTask {
+        let mut task = CruiseTask::new("CRUISE-001", "Add login")
+            .with_description("Implement the login endpoint")
+            .with_complexity(TaskComplexity::High);
+        task.acceptance_criteria = vec!["Returns a token".to_string()];
+        task
+    }
+
+    #[test]
+    fn task_without_instances_uses_default() {
+        let executor = TaskExecutor::new(BuildingConfig::default());
+
+        let executions = executor.plan_task(&task());
+
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].llm, "claude-code");
+        assert!(executions[0].team.is_none());
+        assert!(executions[0].extra_args.is_empty());
+        assert_eq!(executions[0].manifest.complexity, SandboxComplexity::High);
+    }
+
+    #[test]
+    fn instances_override_runner_and_permissions() {
+        let base = SandboxManifest {
+            allowed_tools: vec!["Read".to_string(), "Edit".to_string()],
+            writable_paths: vec!["src/".to_string(), "tests/**".to_string()],
+            ..Default::default()
+        };
+        let config = BuildingConfig {
+            allowed_cli_params: vec!["--max-turns".to_string()],
+            ..Default::default()
+        };
+        let executor = TaskExecutor::new(config).with_manifest(base);
+        let task = task().with_spawn_instances(vec![
+            SpawnInstance {
+                llm: "gemini-cli".to_string(),
+                model: Some("gemini-2.5-pro".to_string()),
+                cli_params: vec!["--max-turns".to_string(), "5".to_string()],
+                allowed_tools: vec!["Read".to_string()],
+                ..Default::default()
+            },
+            SpawnInstance {
+                focus: Some("Write tests only".to_string()),
+                writable_paths: vec!["tests/auth/**".to_string()],
+                ..Default::default()
+            },
+        ]);
+
+        let executions = executor.plan_task(&task);
+
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].llm, "gemini-cli");
+        assert_eq!(executions[0].manifest.allowed_tools, vec!["Read"]);
+        assert_eq!(
+            executions[0].manifest.writable_paths,
+            vec!["src/", "tests/**"]
+        );
+
+        let config = executions[0].spawn_config("/tmp/sandbox".into());
+        assert_eq!(config.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(config.extra_args, vec!["--max-turns", "5"]);
+
+        assert_eq!(executions[1].index, 1);
+        assert_eq!(executions[1].manifest.allowed_tools, vec!["Read", "Edit"]);
+        assert_eq!(executions[1].manifest.writable_paths, vec!["tests/auth/**"]);
+        assert!(executions[1].prompt.contains("Write tests only"));
+    }
+
+    #[test]
+    fn instances_cannot_widen_permissions() {
+        let base = SandboxManifest {
+            allowed_tools: vec!["Read".to_string()],
+            writable_paths: vec!["src/**".to_string()],
+            ..Default::default()
+        };
+        let executor = TaskExecutor::new(BuildingConfig::default()).with_manifest(base);
+        let widening = task().with_spawn_instances(vec![SpawnInstance {
+            cli_params: vec![
+                "--yolo".to_string(),
+                "--approval-mode=yolo".to_string(),
+                "--include-directories".to_string(),
+                "/".to_string(),
+            ],
+            allowed_tools: vec!["Read".to_string(), "Bash".to_string()],
+            writable_paths: vec![".github/**".to_string(), "src/lib.rs".to_string()],
+            ..Default::default()
+        }]);
+
+        let execution = &executor.plan_task(&widening)[0];
+
+        assert_eq!(execution.manifest.allowed_tools, vec!["Read"]);
+        assert_eq!(execution.manifest.writable_paths, vec!["src/lib.rs"]);
+        assert!(execution.extra_args.is_empty());
+
+        let outside = task().with_spawn_instances(vec![SpawnInstance {
+            allowed_tools: vec!["Bash".to_string()],
+            writable_paths: vec!["/etc/**".to_string()],
+            ..Default::default()
+        }]);
+        let execution = &executor.plan_task(&outside)[0];
+
+        assert_eq!(execution.manifest.allowed_tools, vec!["Read"]);
+        assert_eq!(execution.manifest.writable_paths, vec!["src/**"]);
+    }
+
+    #[test]
+    fn use_spawn_team_enables_reviewer() {
+        let config = BuildingConfig {
+            sequential_reviewer: "gemini-cli".to_string(),
+            ..Default::default()
+        };
+        let executor = TaskExecutor::new(config);
+        let task = task().with_spawn_instances(vec![SpawnInstance {
+            use_spawn_team: true,
+            ..Default::default()
+        }]);
+
+        let team = executor.plan_task(&task)[0].team.clone().unwrap();
+
+        assert_eq!(team.primary_llm, "claude-code");
+        assert_eq!(team.reviewer_llm, "gemini-cli");
+    }
+
+    #[test]
+    fn task_prompt_includes_details() {
+        let prompt = build_task_prompt(&task(), &SpawnInstance::default());
+
+        assert!(prompt.contains("## Task CRUISE-001: Add login"));
+        assert!(prompt.contains("Implement the login endpoint"));
+        assert!(prompt.contains("- Returns a token"));
+        assert!(!prompt.contains("### Focus"));
+    }
+
+    #[test]
+    fn memory_is_injected_into_task_prompts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let memory = CruiseMemory::new(dir.path());
+        memory
+            .append(
+                &CruiseTask::new("CRUISE-000", "Add sessions"),
+                &["Sessions live in `auth::session`".to_string()],
+            )
+            .unwrap();
+        let executor = TaskExecutor::new(BuildingConfig::default()).with_memory(memory);
+
+        let prompt = &executor.plan_task(&task())[0].prompt;
+
+        assert!(prompt.starts_with("## Task CRUISE-001: Add login"));
+        assert!(prompt.contains("- Sessions live in `auth::session`"));
+        assert!(prompt.contains("### Decisions"));
+    }
+
+    #[test]
+    fn create_runner_rejects_unknown_llm() {
+        assert_eq!(create_runner("claude-code").unwrap().name(), "claude-code");
+        assert!(create_runner("gpt").is_err());
+    }
+}
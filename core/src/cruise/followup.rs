@@ -0,0 +1,361 @@
+//! Follow-up issues for unfinished work.
+//!
+//! When validation finds tasks that were only partly implemented, never
+//! implemented, or implemented differently than planned, each is filed as
+//! a follow-up instead of only failing the run. A follow-up records what was
+//! attempted (the task's commits and its error), what remains (the notes and
+//! acceptance criteria), and the files the attempt touched. It is written as
+//! a beads issue and, if enabled, opened as a GitHub issue.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::planner::{plan_to_beads, BEADS_DIR};
+use super::result::{AdherenceCheck, AdherenceStatus};
+use super::task::{CruisePlan, CruiseTask};
+use crate::commit::task_trailer;
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+use crate::issue::IssueTracker;
+use crate::spawn::CommitInfo;
+
+/// Settings for filing follow-ups.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowUpConfig {
+    /// Writes a beads issue per follow-up.
+    #[serde(default = "default_true")]
+    pub beads: bool,
+    /// Opens a GitHub issue per follow-up.
+    #[serde(default)]
+    pub github: bool,
+    /// Labels put on GitHub follow-up issues.
+    #[serde(default = "default_labels")]
+    pub labels: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_labels() -> Vec<String> {
+    vec!["follow-up".to_string()]
+}
+
+impl Default for FollowUpConfig {
+    fn default() -> Self {
+        Self {
+            beads: default_true(),
+            github: false,
+            labels: default_labels(),
+        }
+    }
+}
+
+/// Unfinished work on one planned task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowUp {
+    /// Task the work belongs to.
+    pub task_id: String,
+    /// Task subject.
+    pub subject: String,
+    /// How validation classified the task.
+    pub status: AdherenceStatus,
+    /// Validation's notes on the task.
+    pub notes: Option<String>,
+    /// Why the task stopped, if it failed.
+    pub error: Option<String>,
+    /// First lines of the task's commits.
+    pub attempted: Vec<String>,
+    /// Acceptance criteria still to be checked.
+    pub remaining: Vec<String>,
+    /// Files the task's commits changed.
+    pub files: Vec<PathBuf>,
+}
+
+impl FollowUp {
+    /// Returns the follow-up's id, derived from its task's.
+    pub fn id(&self) -> String {
+        format!("{}-followup", self.task_id)
+    }
+
+    /// Returns the issue title.
+    pub fn title(&self) -> String {
+        format!("Follow up on {}: {}", self.task_id, self.subject)
+    }
+
+    /// Returns the issue description in Markdown.
+    pub fn body(&self) -> String {
+        let mut body = format!(
+            "Validation found task {} ({}) {}.",
+            self.task_id,
+            self.subject,
+            self.status.description()
+        );
+        if let Some(notes) = &self.notes {
+            body.push_str(&format!(" {}", notes.trim()));
+        }
+        body.push_str("\n\n## Attempted\n\n");
+        if self.attempted.is_empty() && self.error.is_none() {
+            body.push_str("Nothing was committed for this task.\n");
+        }
+        for commit in &self.attempted {
+            body.push_str(&format!("- {}\n", commit));
+        }
+        if let Some(error) = &self.error {
+            body.push_str(&format!("- Stopped: {}\n", error));
+        }
+        if !self.remaining.is_empty() {
+            body.push_str("\n## Remaining\n\n");
+            for criterion in &self.remaining {
+                body.push_str(&format!("- [ ] {}\n", criterion));
+            }
+        }
+        if !self.files.is_empty() {
+            body.push_str("\n## Relevant files\n\n");
+            for file in &self.files {
+                body.push_str(&format!("- `{}`\n", file.display()));
+            }
+        }
+        body
+    }
+
+    /// Returns the follow-up as a plan task, for writing as a beads issue.
+    fn as_task(&self, task: Option<&CruiseTask>) -> CruiseTask {
+        let mut follow_up = CruiseTask::new(self.id(), self.title()).with_description(self.body());
+        if let Some(task) = task {
+            follow_up.component = task.component.clone();
+            follow_up.repo = task.repo.clone();
+            follow_up.complexity = task.complexity;
+        }
+        follow_up.acceptance_criteria = self.remaining.clone();
+        follow_up
+    }
+}
+
+/// Collects and files follow-ups for a cruise run.
+pub struct FollowUpFiler {
+    repo_path: PathBuf,
+    config: FollowUpConfig,
+    git: Arc<dyn GitExecutor>,
+}
+
+impl FollowUpFiler {
+    /// Creates a filer for the repository at `repo_path`.
+    pub fn new(repo_path: impl Into<PathBuf>, config: FollowUpConfig) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            config,
+            git: Arc::new(SystemGit),
+        }
+    }
+
+    /// Runs git through `git` instead of the system binary.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Returns a follow-up for every task of `plan` that `checks` did not
+    /// find fully implemented, with the files its commits in `commits`
+    /// changed.
+    pub fn collect(
+        &self,
+        plan: &CruisePlan,
+        checks: &[AdherenceCheck],
+        commits: &[CommitInfo],
+    ) -> Vec<FollowUp> {
+        checks
+            .iter()
+            .filter(|check| check.status != AdherenceStatus::Implemented)
+            .map(|check| {
+                let task = plan.tasks.iter().find(|t| t.id == check.task_id);
+                let task_commits: Vec<&CommitInfo> = commits
+                    .iter()
+                    .filter(|c| task_trailer(&c.message).as_deref() == Some(&check.task_id))
+                    .collect();
+                FollowUp {
+                    task_id: check.task_id.clone(),
+                    subject: check.subject.clone(),
+                    status: check.status,
+                    notes: check.notes.clone(),
+                    error: task.and_then(|t| t.error.clone()),
+                    attempted: task_commits
+                        .iter()
+                        .map(|c| {
+                            let short = c.hash.get(..12).unwrap_or(&c.hash);
+                            let subject = c.message.lines().next().unwrap_or_default();
+                            format!("`{}` {}", short, subject)
+                        })
+                        .collect(),
+                    remaining: task
+                        .map(|t| t.acceptance_criteria.clone())
+                        .unwrap_or_default(),
+                    files: self.files_of(&task_commits),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the sorted paths `commits` changed. Commits git cannot read
+    /// are skipped.
+    fn files_of(&self, commits: &[&CommitInfo]) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = commits
+            .iter()
+            .filter_map(|commit| {
+                self.git
+                    .run(
+                        &self.repo_path,
+                        &[
+                            "diff-tree",
+                            "--no-commit-id",
+                            "--name-only",
+                            "-r",
+                            &commit.hash,
+                        ],
+                    )
+                    .ok()
+            })
+            .flat_map(|names| {
+                names
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Files `follow_ups` as configured, returning where each was filed:
+    /// beads issue paths and GitHub issue URLs. A GitHub issue that cannot
+    /// be opened is logged and skipped so the rest are still filed.
+    pub fn file(&self, plan: &CruisePlan, follow_ups: &[FollowUp]) -> Result<Vec<String>> {
+        let mut filed = Vec::new();
+        if follow_ups.is_empty() {
+            return Ok(filed);
+        }
+
+        if self.config.beads {
+            let mut beads = CruisePlan::new(&plan.prompt);
+            beads.tasks = follow_ups
+                .iter()
+                .map(|f| f.as_task(plan.tasks.iter().find(|t| t.id == f.task_id)))
+                .collect();
+            let paths = plan_to_beads(&beads, &self.repo_path.join(BEADS_DIR))?;
+            filed.extend(paths.iter().map(|p| display(&self.repo_path, p)));
+        }
+
+        if self.config.github {
+            let tracker = IssueTracker::new(&self.repo_path);
+            for follow_up in follow_ups {
+                match tracker.create(&follow_up.title(), &follow_up.body(), &self.config.labels) {
+                    Ok(url) => filed.push(url),
+                    Err(e) => {
+                        tracing::warn!(task = %follow_up.task_id, error = %e, "could not open follow-up issue")
+                    }
+                }
+            }
+        }
+
+        tracing::info!(
+            count = follow_ups.len(),
+            "filed follow-ups for unfinished tasks"
+        );
+        Ok(filed)
+    }
+}
+
+/// Returns `path` relative to `repo` where possible.
+fn display(repo: &Path, path: &Path) -> String {
+    path.strip_prefix(repo)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::task::TaskStatus;
+    use crate::git::{GitOutput, MockGit};
+    use tempfile::TempDir;
+
+    fn plan() -> CruisePlan {
+        let mut plan = CruisePlan::new("Build an API");
+        plan.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Setup"),
+            CruiseTask::new("CRUISE-002", "Auth").with_component("api"),
+        ];
+        plan.tasks[0].status = TaskStatus::Completed;
+        plan.tasks[1].status = TaskStatus::Blocked;
+        plan.tasks[1].error = Some("tests failed".to_string());
+        plan.tasks[1].acceptance_criteria = vec!["Tokens expire".to_string()];
+        plan
+    }
+
+    fn checks() -> Vec<AdherenceCheck> {
+        vec![
+            AdherenceCheck {
+                task_id: "CRUISE-001".to_string(),
+                subject: "Setup".to_string(),
+                status: AdherenceStatus::Implemented,
+                notes: None,
+            },
+            AdherenceCheck {
+                task_id: "CRUISE-002".to_string(),
+                subject: "Auth".to_string(),
+                status: AdherenceStatus::Partial,
+                notes: Some("Login works, refresh is missing.".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn unfinished_tasks_become_follow_ups_with_context() {
+        let repo = TempDir::new().unwrap();
+        let git = MockGit::new().on(&["diff-tree"], GitOutput::ok("src/auth.rs\nsrc/lib.rs\n"));
+        let filer =
+            FollowUpFiler::new(repo.path(), FollowUpConfig::default()).with_git(Arc::new(git));
+        let commits = [
+            CommitInfo {
+                hash: "0123456789abcdef".to_string(),
+                message: "feat(api): login\n\nTask-Id: CRUISE-002".to_string(),
+            },
+            CommitInfo {
+                hash: "fedcba".to_string(),
+                message: "feat: scaffold\n\nTask-Id: CRUISE-001".to_string(),
+            },
+        ];
+
+        let follow_ups = filer.collect(&plan(), &checks(), &commits);
+
+        assert_eq!(follow_ups.len(), 1);
+        let follow_up = &follow_ups[0];
+        assert_eq!(follow_up.attempted, ["`0123456789ab` feat(api): login"]);
+        assert_eq!(
+            follow_up.files,
+            [PathBuf::from("src/auth.rs"), PathBuf::from("src/lib.rs")]
+        );
+        let body = follow_up.body();
+        assert!(body.starts_with(
+            "Validation found task CRUISE-002 (Auth) partially implemented. \
+             Login works, refresh is missing."
+        ));
+        assert!(body.contains("- Stopped: tests failed\n"));
+        assert!(body.contains("## Remaining\n\n- [ ] Tokens expire\n"));
+        assert!(body.contains("## Relevant files\n\n- `src/auth.rs`\n"));
+
+        let filed = filer.file(&plan(), &follow_ups).unwrap();
+
+        assert_eq!(filed, [".beads/CRUISE-002-followup.md"]);
+        let beads =
+            std::fs::read_to_string(repo.path().join(".beads/CRUISE-002-followup.md")).unwrap();
+        assert!(beads.contains("subject: Follow up on CRUISE-002: Auth\n"));
+        assert!(beads.contains("status: pending\n"));
+        assert!(beads.contains("component: api\n"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Shared memory across cruise tasks.
+//!
+//! Tasks run in separate sandboxes, often in parallel, and cannot see each
+//! other's choices until their branches merge. Each task ends its output
+//! with the decisions later tasks should follow (APIs it created, names it
+//! chose), which are appended to `.cruise/memory.md`. Every task prompt
+//! built afterwards carries the recorded decisions.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::task::CruiseTask;
+use crate::error::Result;
+use crate::prompt::budget;
+
+/// Memory file, relative to the repository root.
+pub const MEMORY_FILE: &str = ".cruise/memory.md";
+
+/// Heading that introduces a task's decisions in its output.
+pub const DECISIONS_HEADING: &str = "### Decisions";
+
+/// Default limit on the memory injected into a prompt, in tokens.
+const DEFAULT_MAX_TOKENS: usize = 2000;
+
+/// Most decisions recorded for one task.
+const MAX_DECISIONS: usize = 10;
+
+const MEMORY_HEADER: &str = "# Cruise Memory\n\nKey decisions recorded by completed tasks.\n";
+
+/// Decisions shared between the tasks of a cruise plan.
+#[derive(Debug, Clone)]
+pub struct CruiseMemory {
+    path: PathBuf,
+    max_tokens: usize,
+}
+
+impl CruiseMemory {
+    /// Creates the memory for the repository at `repo`.
+    pub fn new(repo: &Path) -> Self {
+        Self {
+            path: repo.join(MEMORY_FILE),
+            max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
+
+    /// Limits the memory injected into a prompt to roughly `max_tokens`.
+    ///
+    /// The oldest task entries are dropped first.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Returns the path of the memory file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the recorded task entries, oldest first.
+    pub fn entries(&self) -> Vec<String> {
+        let Ok(text) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<String> = Vec::new();
+        for line in text.lines() {
+            if line.starts_with("## ") {
+                entries.push(String::new());
+            }
+            if let Some(entry) = entries.last_mut() {
+                entry.push_str(line);
+                entry.push('\n');
+            }
+        }
+        entries
+            .into_iter()
+            .map(|e| e.trim_end().to_string())
+            .collect()
+    }
+
+    /// Appends a task's decisions to the memory file.
+    ///
+    /// Does nothing when there are no decisions.
+    pub fn append(&self, task: &CruiseTask, decisions: &[String]) -> Result<()> {
+        if decisions.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut entry = String::new();
+        if !self.path.exists() {
+            entry.push_str(MEMORY_HEADER);
+        }
+        entry.push_str(&format!("\n## {}: {}\n\n", task.id, task.subject));
+        for decision in decisions {
+            entry.push_str(&format!("- {}\n", decision));
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(entry.as_bytes())?;
+
+        tracing::debug!(task_id = %task.id, decisions = decisions.len(), "recorded task decisions");
+        Ok(())
+    }
+
+    /// Records the decisions listed in a task's output. Returns them.
+    pub fn record(&self, task: &CruiseTask, output: &str) -> Result<Vec<String>> {
+        let decisions = parse_decisions(output);
+        self.append(task, &decisions)?;
+        Ok(decisions)
+    }
+
+    /// Adds the recorded decisions to a task prompt, along with the request
+    /// to list the task's own decisions.
+    pub fn inject(&self, prompt: &str) -> String {
+        let mut prompt = format!("{}\n\n", prompt.trim_end());
+
+        let entries = self.fit(self.entries());
+        if !entries.is_empty() {
+            prompt.push_str(
+                "### Shared Memory\n\n\
+                 Earlier tasks in this plan made these decisions. Stay consistent with them.\n\n",
+            );
+            for entry in entries {
+                prompt.push_str(&entry);
+                prompt.push_str("\n\n");
+            }
+        }
+
+        prompt.push_str(&format!(
+            "{heading}\n\n\
+             End your final message with a `{heading}` section listing, one bullet each, \
+             the APIs, names, and conventions you introduced that later tasks should reuse.\n",
+            heading = DECISIONS_HEADING
+        ));
+        prompt
+    }
+
+    /// Keeps the newest entries that fit within the token limit.
+    fn fit(&self, entries: Vec<String>) -> Vec<String> {
+        let mut kept = Vec::new();
+        let mut tokens = 0;
+        for entry in entries.into_iter().rev() {
+            tokens += budget::estimate_tokens(&entry, "");
+            if tokens > self.max_tokens {
+                break;
+            }
+            kept.push(entry);
+        }
+        kept.reverse();
+        kept
+    }
+}
+
+/// Extracts the decisions a task listed under [`DECISIONS_HEADING`].
+///
+/// Uses the last such section and keeps its bullet items, up to ten.
+pub fn parse_decisions(output: &str) -> Vec<String> {
+    let Some(start) = output.rfind(DECISIONS_HEADING) else {
+        return Vec::new();
+    };
+
+    output[start + DECISIONS_HEADING.len()..]
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            line.strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .map(str::trim)
+        })
+        .filter(|decision| !decision.is_empty())
+        .take(MAX_DECISIONS)
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_decisions_reads_last_section() {
+        let output = "Mentioned ### Decisions in passing.\n\nDone.\n\n### Decisions\n\n\
+                      - Added `SessionStore::get(id)`\n* Tokens live in `auth::token`\n\n\
+                      Not a bullet\n## Next\n- ignored\n";
+
+        assert_eq!(
+            parse_decisions(output),
+            vec![
+                "Added `SessionStore::get(id)`",
+                "Tokens live in `auth::token`"
+            ]
+        );
+        assert!(parse_decisions("No decisions here").is_empty());
+    }
+
+    #[test]
+    fn recorded_decisions_reach_later_prompts() {
+        let dir = TempDir::new().unwrap();
+        let memory = CruiseMemory::new(dir.path());
+        let login = CruiseTask::new("CRUISE-001", "Add login");
+        let logout = CruiseTask::new("CRUISE-002", "Add logout");
+
+        assert!(!memory.inject("## Task").contains("### Shared Memory"));
+
+        memory
+            .record(&login, "### Decisions\n\n- Added `POST /login`\n")
+            .unwrap();
+        memory.record(&logout, "Nothing to note").unwrap();
+        memory
+            .append(&logout, &["Added `POST /logout`".to_string()])
+            .unwrap();
+
+        let text = std::fs::read_to_string(dir.path().join(MEMORY_FILE)).unwrap();
+        assert!(text.starts_with("# Cruise Memory"));
+        assert_eq!(
+            memory.entries(),
+            vec![
+                "## CRUISE-001: Add login\n\n- Added `POST /login`",
+                "## CRUISE-002: Add logout\n\n- Added `POST /logout`",
+            ]
+        );
+
+        let prompt = memory.inject("## Task CRUISE-003: Add refresh");
+        assert!(prompt.contains("### Shared Memory"));
+        assert!(prompt.contains("- Added `POST /login`"));
+        assert!(prompt.ends_with("later tasks should reuse.\n"));
+
+        let small = CruiseMemory::new(dir.path()).with_max_tokens(15);
+        let prompt = small.inject("## Task");
+        assert!(!prompt.contains("POST /login"));
+        assert!(prompt.contains("POST /logout"));
+    }
+}
@@ -2,25 +2,68 @@
 //!
 //! Three-phase workflow: Plan → Build → Validate
 
+pub mod abort;
+pub mod adherence;
 pub mod approval;
+pub mod artifacts;
+pub mod beads;
+pub mod bootstrap;
+pub mod comments;
 pub mod config;
+pub mod coverage;
+pub mod docs;
+pub mod estimate;
+pub mod executor;
+pub mod followup;
+pub mod memory;
+pub mod multi_repo;
+pub mod plan_approval;
 pub mod planner;
+pub mod pool;
 pub mod prompts;
 pub mod result;
+pub mod status;
 pub mod task;
+pub mod test_first;
 
 pub use config::{
-    ApprovalConfig, BuildingConfig, CruiseConfig, PlanningConfig, PrStrategy, RepoLifecycle,
-    TestConfig, TestLevel, ValidationConfig,
+    AbortConfig, ApprovalConfig, ApprovalMode, BootstrapConfig, BranchProtection, BuildingConfig,
+    CruiseConfig, PlanningConfig, PoolConfig, PrStrategy, RepoLifecycle, TestConfig, TestLevel,
+    ValidationConfig,
 };
 pub use result::{
-    AdherenceCheck, AdherenceStatus, AuditFinding, BuildResult, CruiseResult, FindingSeverity,
-    FunctionalTestResult, PlanResult, TaskResult, ValidationResult,
+    AdherenceCheck, AdherenceStatus, AuditFinding, BuildResult, CombinedObservability, CruisePhase,
+    CruiseResult, FindingSeverity, FunctionalTestResult, PhaseObservability, PlanResult,
+    TaskResult, ValidationResult,
+};
+pub use coverage::{parse_coverage, CoverageConfig, CoverageDelta, COVERAGE_CATEGORY};
+pub use estimate::{
+    PlanEstimate, PlanEstimator, TaskEstimate, TaskHistory, TaskSample, TASK_HISTORY_FILE,
+};
+pub use task::{CruisePlan, CruiseTask, SpawnInstance, TaskComplexity, TaskStatus};
+pub use abort::{AbortReport, RunAborter};
+pub use adherence::{
+    adherence_prompt, attributed_checks, close_beads_issues, parse_adherence_json,
+    AdherenceChecker, BeadsClosure,
 };
-pub use task::{CruisePlan, CruiseTask, TaskComplexity, TaskStatus};
 pub use approval::{ApprovalPoller, PrStatus};
+pub use artifacts::ArtifactStore;
+pub use beads::BeadsSync;
+pub use bootstrap::RepoBootstrapper;
+pub use comments::{classify_comment, CommentKind, CommentPoller, CommentTriage, PrComment};
+pub use docs::{docs_prompt, DocsConfig, DocsOutcome, DocsUpdater};
+pub use executor::{create_runner, create_runner_with, InstanceExecution, TaskExecutor};
+pub use followup::{FollowUp, FollowUpConfig, FollowUpFiler};
+pub use memory::{parse_decisions, CruiseMemory, MEMORY_FILE};
+pub use multi_repo::{CrossRepoDependency, MultiRepoCruiseRunner, RepoTarget};
 pub use planner::{
     generate_plan_markdown, generate_pr_body, parse_plan_json, plan_to_beads, validate_plan,
-    Planner, ReviewPhase,
+    PlanDiff, PlanRevision, Planner, ReviewPhase,
 };
+pub use plan_approval::{render_plan, PlanDecision, PlanReview, TerminalApproval};
+pub use pool::{Lease, PooledRepo, RepoPool, POOL_FILE};
 pub use prompts::{PlanPromptBuilder, PlanReviewPromptBuilder};
+pub use status::{CruiseStatus, TaskCounts};
+pub use test_first::{
+    is_test_path, TestBaseline, TestFirstOutcome, TestFirstRunner, TEST_INTEGRITY_CATEGORY,
+};
@@ -0,0 +1,898 @@
+//! Cruise orchestration across several repositories.
+//!
+//! A feature may need changes in more than one repository (e.g. a service
+//! and its client library). A multi-repo plan tags each task with the
+//! repository it runs in; [`MultiRepoCruiseRunner`] resolves tasks against
+//! their repository, tracks dependencies that cross repositories, and gives
+//! every repository the same coordinated branch so the resulting PRs can be
+//! found and reviewed together.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::artifacts::ArtifactStore;
+use super::beads::BeadsSync;
+use super::executor::{InstanceExecution, TaskExecutor};
+use super::status::CruiseStatus;
+use super::task::{CruisePlan, CruiseTask, TaskStatus};
+use crate::branch::BranchNamer;
+use crate::commit::{CommitContext, CommitMessages, GitHistoryPolicy};
+use crate::error::{Error, PlanError, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{PRManager, PullRequest};
+use crate::sandbox::{Sandbox, SandboxManifest, WorktreeSandbox, WorktreeSandboxInstance};
+use crate::spawn::CommitInfo;
+
+/// A repository taking part in a multi-repo plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoTarget {
+    /// Name tasks use to refer to the repository.
+    pub name: String,
+    /// Path to the local clone.
+    pub path: PathBuf,
+    /// Branch PRs target.
+    #[serde(default = "default_base_branch")]
+    pub base_branch: String,
+    /// Context prepended to the prompt of every task in this repository.
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+fn default_base_branch() -> String {
+    "main".to_string()
+}
+
+impl RepoTarget {
+    /// Creates a target for the clone at `path`.
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            base_branch: default_base_branch(),
+            prompt: None,
+        }
+    }
+
+    /// Sets the branch PRs target.
+    pub fn with_base_branch(mut self, branch: impl Into<String>) -> Self {
+        self.base_branch = branch.into();
+        self
+    }
+
+    /// Sets the per-repository prompt context.
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+}
+
+/// A task that waits on a task in another repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossRepoDependency {
+    /// The waiting task.
+    pub task_id: String,
+    /// Repository of the waiting task.
+    pub repo: String,
+    /// The task it waits on.
+    pub depends_on: String,
+    /// Repository of the task it waits on.
+    pub depends_on_repo: String,
+}
+
+/// Runs one cruise plan across several repositories.
+pub struct MultiRepoCruiseRunner {
+    plan: CruisePlan,
+    repos: Vec<RepoTarget>,
+    executor: TaskExecutor,
+    branches: BranchNamer,
+    sandbox_dir: Option<PathBuf>,
+    stores: HashMap<String, ArtifactStore>,
+    prs: HashMap<String, PullRequest>,
+    feature_branch: String,
+    beads: Option<BeadsSync>,
+    commit_messages: CommitMessages,
+    status: Option<CruiseStatus>,
+}
+
+impl MultiRepoCruiseRunner {
+    /// Creates a runner for `plan` over `repos`.
+    ///
+    /// Tasks without a `repo` run in the first repository. Fails if a task
+    /// names an unknown repository, repository names repeat, or the plan
+    /// has a dependency cycle.
+    pub fn new(plan: CruisePlan, repos: Vec<RepoTarget>, executor: TaskExecutor) -> Result<Self> {
+        if repos.is_empty() {
+            return Err(Error::Config(
+                "multi-repo plan has no repositories".to_string(),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for repo in &repos {
+            if !names.insert(repo.name.as_str()) {
+                return Err(Error::Config(format!(
+                    "repository '{}' is listed twice",
+                    repo.name
+                )));
+            }
+        }
+        for task in &plan.tasks {
+            if let Some(repo) = &task.repo {
+                if !names.contains(repo.as_str()) {
+                    return Err(Error::InvalidPlan(PlanError::UnknownRepository {
+                        task_id: task.id.clone(),
+                        repo: repo.clone(),
+                    }));
+                }
+            }
+        }
+        if let Some(cycle) = plan.has_cycle() {
+            return Err(Error::DependencyCycle(cycle));
+        }
+
+        let branches = BranchNamer::new();
+        let stores = repos
+            .iter()
+            .map(|r| (r.name.clone(), ArtifactStore::new(&r.path)))
+            .collect();
+        let feature_branch = branches.sandbox_branch_for(&feature_hint(&plan));
+
+        Ok(Self {
+            plan,
+            repos,
+            executor,
+            branches,
+            sandbox_dir: None,
+            stores,
+            prs: HashMap::new(),
+            feature_branch,
+            beads: None,
+            commit_messages: CommitMessages::default(),
+            status: None,
+        })
+    }
+
+    /// Sets the namer for the coordinated, sandbox and artifact branches.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.feature_branch = namer.sandbox_branch_for(&feature_hint(&self.plan));
+        self.stores = self
+            .repos
+            .iter()
+            .map(|r| {
+                let store = ArtifactStore::new(&r.path).with_branch_namer(namer.clone());
+                (r.name.clone(), store)
+            })
+            .collect();
+        self.branches = namer;
+        self
+    }
+
+    /// Sets the directory sandboxes are created in.
+    pub fn with_sandbox_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.sandbox_dir = Some(dir.into());
+        self
+    }
+
+    /// Keeps the plan's beads issues in step with task execution.
+    pub fn with_beads(mut self, sync: BeadsSync) -> Self {
+        self.beads = Some(sync);
+        self
+    }
+
+    /// Keeps `status` current as tasks run and sandboxes, branches and PRs
+    /// are created, saving it in the first repository so `cruise status`
+    /// and `cruise abort` find the run.
+    pub fn with_status(mut self, mut status: CruiseStatus) -> Self {
+        for repo in &self.repos[1..] {
+            status.add_repo(&repo.path);
+        }
+        self.status = Some(status);
+        self.update_status(|_| {});
+        self
+    }
+
+    /// Sets how the feature branches' history is rewritten before their
+    /// PRs are opened.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.commit_messages = messages;
+        self
+    }
+
+    /// Returns the plan with current task statuses.
+    pub fn plan(&self) -> &CruisePlan {
+        &self.plan
+    }
+
+    /// Returns the branch every repository's PR is opened from.
+    pub fn feature_branch(&self) -> &str {
+        &self.feature_branch
+    }
+
+    /// Returns the repository a task runs in.
+    pub fn repo_for(&self, task: &CruiseTask) -> &RepoTarget {
+        task.repo
+            .as_deref()
+            .and_then(|name| self.repos.iter().find(|r| r.name == name))
+            .unwrap_or(&self.repos[0])
+    }
+
+    /// Returns every dependency between tasks in different repositories.
+    pub fn cross_repo_dependencies(&self) -> Vec<CrossRepoDependency> {
+        let mut deps = Vec::new();
+        for task in &self.plan.tasks {
+            let repo = &self.repo_for(task).name;
+            for dep_id in &task.blocked_by {
+                let Some(dep) = self.task(dep_id) else {
+                    continue;
+                };
+                let dep_repo = &self.repo_for(dep).name;
+                if dep_repo != repo {
+                    deps.push(CrossRepoDependency {
+                        task_id: task.id.clone(),
+                        repo: repo.clone(),
+                        depends_on: dep_id.clone(),
+                        depends_on_repo: dep_repo.clone(),
+                    });
+                }
+            }
+        }
+        deps
+    }
+
+    /// Returns tasks whose dependencies, in any repository, are complete.
+    pub fn ready_tasks(&self) -> Vec<&CruiseTask> {
+        self.plan.ready_tasks()
+    }
+
+    /// Resolves the instances that execute a task, with the repository's
+    /// prompt context and notes on upstream work in other repositories.
+    pub fn plan_task(&self, task_id: &str) -> Result<Vec<InstanceExecution>> {
+        let task = self.require_task(task_id)?;
+        let repo = self.repo_for(task);
+
+        let mut context = String::new();
+        if let Some(prompt) = &repo.prompt {
+            context.push_str(prompt.trim());
+            context.push_str("\n\n");
+        }
+        let upstream: Vec<_> = self
+            .cross_repo_dependencies()
+            .into_iter()
+            .filter(|d| d.task_id == task.id)
+            .collect();
+        if !upstream.is_empty() {
+            context.push_str("### Changes in other repositories\n\n");
+            for dep in upstream {
+                let subject = self
+                    .task(&dep.depends_on)
+                    .map_or("", |t| t.subject.as_str());
+                context.push_str(&format!(
+                    "- {} ({}): {} — on branch `{}`\n",
+                    dep.depends_on, dep.depends_on_repo, subject, self.feature_branch
+                ));
+            }
+            context.push('\n');
+        }
+
+        let mut instances = self.executor.plan_task(task);
+        for instance in &mut instances {
+            instance.prompt = format!("{}{}", context, instance.prompt);
+        }
+        Ok(instances)
+    }
+
+    /// Creates a sandbox for a task in its repository, starting from the
+    /// merged output of its same-repository dependencies.
+    pub fn create_sandbox(
+        &mut self,
+        task_id: &str,
+        manifest: SandboxManifest,
+    ) -> Result<WorktreeSandboxInstance> {
+        let task = self.require_task(task_id)?;
+        let repo = self.repo_for(task).clone();
+
+        // Cross-repo dependencies gate readiness but cannot be merged in
+        let local = task.clone().with_blocked_by(
+            task.blocked_by
+                .iter()
+                .filter(|dep| {
+                    self.task(dep)
+                        .is_some_and(|d| self.repo_for(d).name == repo.name)
+                })
+                .cloned()
+                .collect(),
+        );
+        let base = self.store(&repo.name).base_for(&local)?;
+
+        let provider = WorktreeSandbox::new(repo.path.clone(), self.sandbox_dir.clone())
+            .with_branch_namer(self.branches.clone());
+        let sandbox = provider.create_from(manifest, &base)?;
+        self.update_status(|status| {
+            status.add_sandbox(sandbox.path());
+            status.add_branch(sandbox.branch_name());
+        });
+        Ok(sandbox)
+    }
+
+    /// Marks a task in progress as its spawn starts.
+    pub fn start(&mut self, task_id: &str) {
+        self.set_status(task_id, TaskStatus::InProgress, None);
+        self.sync_beads(task_id, |beads| beads.start(task_id));
+        self.update_status(|status| status.set_phase("building"));
+    }
+
+    /// Notes the commits a task's spawn made on its beads issue.
+    pub fn record_commits(&self, task_id: &str, commits: &[CommitInfo]) {
+        self.sync_beads(task_id, |beads| beads.record_commits(task_id, commits));
+    }
+
+    /// Records a completed task's output and marks it complete.
+    pub fn complete(&mut self, task_id: &str, source_ref: &str) -> Result<String> {
+        let repo = self.repo_for(self.require_task(task_id)?).name.clone();
+        let branch = self.store(&repo).record(task_id, source_ref)?;
+        self.set_status(task_id, TaskStatus::Completed, None);
+        self.update_status(|status| status.add_branch(&branch));
+        Ok(branch)
+    }
+
+    /// Marks a task blocked, and skips the tasks in every repository that
+    /// depend on it.
+    pub fn fail(&mut self, task_id: &str, error: impl Into<String>) {
+        let error = error.into();
+        self.sync_beads(task_id, |beads| beads.block(task_id, &error));
+        self.set_status(task_id, TaskStatus::Blocked, Some(error));
+
+        let mut failed = vec![task_id.to_string()];
+        while let Some(id) = failed.pop() {
+            let dependents: Vec<String> = self
+                .plan
+                .tasks
+                .iter()
+                .filter(|t| t.status == TaskStatus::Pending && t.blocked_by.contains(&id))
+                .map(|t| t.id.clone())
+                .collect();
+            for dependent in dependents {
+                let reason = format!("dependency {} failed", id);
+                self.sync_beads(&dependent, |beads| beads.block(&dependent, &reason));
+                self.set_status(&dependent, TaskStatus::Skipped, Some(reason));
+                failed.push(dependent);
+            }
+        }
+    }
+
+    /// Points each repository's feature branch at the merge of its completed
+    /// tasks, rewritten following the configured
+    /// [`GitHistoryPolicy`]. Returns the repositories that have changes.
+    pub fn integrate(&mut self) -> Result<Vec<String>> {
+        let mut changed = Vec::new();
+        for repo in self.repos.clone() {
+            let completed: Vec<String> = self
+                .plan
+                .tasks
+                .iter()
+                .filter(|t| t.status == TaskStatus::Completed && self.repo_for(t).name == repo.name)
+                .map(|t| t.id.clone())
+                .collect();
+            if completed.is_empty() {
+                continue;
+            }
+
+            let integration = CruiseTask::new(self.feature_branch.clone(), "integration")
+                .with_blocked_by(completed);
+            let head = self.store(&repo.name).base_for(&integration)?;
+            let head = self.rewrite_history(&repo, &head)?;
+            SystemGit.run(&repo.path, &["branch", "-f", &self.feature_branch, &head])?;
+            let branch = self.feature_branch.clone();
+            self.update_status(|status| status.add_branch(branch));
+            changed.push(repo.name.clone());
+        }
+        Ok(changed)
+    }
+
+    /// Pushes each integrated repository and opens linked PRs.
+    ///
+    /// PRs are created first and then edited so every description links to
+    /// the PRs in the other repositories.
+    pub fn open_prs(&mut self, title: &str, draft: bool) -> Result<Vec<PullRequest>> {
+        for name in self.integrate()? {
+            let repo = self.repo(&name).clone();
+            SystemGit.run(&repo.path, &["push", "-u", "origin", &self.feature_branch])?;
+            let manager = PRManager::new(repo.path.clone()).with_draft(draft);
+            let pr = manager.create_pr(
+                title,
+                &self.pr_body(&name),
+                &self.feature_branch,
+                &repo.base_branch,
+            )?;
+            self.update_status(|status| status.add_pr(&pr.url));
+            self.prs.insert(name, pr);
+        }
+
+        if self.prs.len() > 1 {
+            for (name, pr) in &self.prs {
+                PRManager::new(self.repo(name).path.clone()).edit_body(pr, &self.pr_body(name))?;
+            }
+        }
+
+        let mut prs: Vec<_> = self.prs.values().cloned().collect();
+        prs.sort_by(|a, b| a.url.cmp(&b.url));
+        Ok(prs)
+    }
+
+    /// Records a PR opened outside [`open_prs`](Self::open_prs).
+    pub fn record_pr(&mut self, repo: &str, pr: PullRequest) {
+        self.update_status(|status| status.add_pr(&pr.url));
+        self.prs.insert(repo.to_string(), pr);
+    }
+
+    /// Builds the PR description for a repository: its tasks, and links to
+    /// the coordinated PRs in the other repositories.
+    pub fn pr_body(&self, repo: &str) -> String {
+        let mut body = format!("## {}\n\n", self.plan.title);
+        if !self.plan.overview.is_empty() {
+            body.push_str(&self.plan.overview);
+            body.push_str("\n\n");
+        }
+
+        body.push_str("### Tasks\n\n");
+        for task in self
+            .plan
+            .tasks
+            .iter()
+            .filter(|t| self.repo_for(t).name == repo)
+        {
+            let mark = if task.status == TaskStatus::Completed {
+                "x"
+            } else {
+                " "
+            };
+            body.push_str(&format!("- [{}] {}: {}\n", mark, task.id, task.subject));
+        }
+
+        let others: Vec<_> = self.repos.iter().filter(|r| r.name != repo).collect();
+        if !others.is_empty() {
+            body.push_str("\n### Related pull requests\n\n");
+            for other in others {
+                let link = self
+                    .prs
+                    .get(&other.name)
+                    .map_or("pending", |pr| pr.url.as_str());
+                body.push_str(&format!("- {}: {}\n", other.name, link));
+            }
+        }
+
+        body.push_str(&format!(
+            "\nBranch `{}` in every repository.\n",
+            self.feature_branch
+        ));
+        body
+    }
+
+    /// Deletes artifact and base branches in every repository.
+    pub fn cleanup(&mut self) -> Result<()> {
+        for store in self.stores.values_mut() {
+            store.cleanup()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the commits of `head` since `repo`'s base branch in a
+    /// temporary worktree, leaving the clone's checkout alone. Returns the
+    /// new head.
+    fn rewrite_history(&self, repo: &RepoTarget, head: &str) -> Result<String> {
+        if self.commit_messages.history == GitHistoryPolicy::KeepAll {
+            return Ok(head.to_string());
+        }
+
+        let worktree = std::env::temp_dir().join(format!(
+            "improbability-drive-{}-{}",
+            BranchNamer::dir_name(&self.feature_branch),
+            repo.name
+        ));
+        let dir = worktree.to_string_lossy().into_owned();
+        SystemGit.run(&repo.path, &["worktree", "add", "--detach", &dir, head])?;
+
+        let context = CommitContext::new(feature_hint(&self.plan));
+        let result = PRManager::new(repo.path.clone())
+            .with_commit_messages(self.commit_messages.clone())
+            .prepare_for_pr(&worktree, &repo.base_branch, &context);
+        if let Err(e) = SystemGit.run(&repo.path, &["worktree", "remove", "--force", &dir]) {
+            tracing::warn!(error = %e, "could not remove the history worktree");
+        }
+        Ok(result?.unwrap_or_else(|| head.to_string()))
+    }
+
+    fn task(&self, id: &str) -> Option<&CruiseTask> {
+        self.plan.tasks.iter().find(|t| t.id == id)
+    }
+
+    fn require_task(&self, id: &str) -> Result<&CruiseTask> {
+        self.task(id)
+            .ok_or_else(|| Error::UnknownTask(id.to_string()))
+    }
+
+    fn repo(&self, name: &str) -> &RepoTarget {
+        self.repos
+            .iter()
+            .find(|r| r.name == name)
+            .expect("repository names are validated in new()")
+    }
+
+    fn store(&mut self, repo: &str) -> &mut ArtifactStore {
+        self.stores
+            .get_mut(repo)
+            .expect("every repository has an artifact store")
+    }
+
+    /// Applies `update` to a task's beads issue, if beads are synced. The
+    /// tracker only mirrors the run, so failures are logged, not returned.
+    fn sync_beads(&self, task_id: &str, update: impl FnOnce(&BeadsSync) -> Result<bool>) {
+        if let Some(beads) = &self.beads {
+            if let Err(e) = update(beads) {
+                tracing::warn!(task = %task_id, error = %e, "failed to update beads issue");
+            }
+        }
+    }
+
+    fn set_status(&mut self, task_id: &str, status: TaskStatus, error: Option<String>) {
+        if let Some(task) = self.plan.tasks.iter_mut().find(|t| t.id == task_id) {
+            task.status = status;
+            task.error = error;
+        }
+        self.update_status(|_| {});
+    }
+
+    /// Applies `update` to the run's status, if it is kept, and saves it
+    /// with the current task counts. Like beads, the status only mirrors
+    /// the run, so failures are logged, not returned.
+    fn update_status(&mut self, update: impl FnOnce(&mut CruiseStatus)) {
+        if let Some(status) = &mut self.status {
+            update(status);
+            status.record_plan(&self.plan);
+            if let Err(e) = status.save(&self.repos[0].path) {
+                tracing::warn!(run = %status.run_id, error = %e, "failed to save run status");
+            }
+        }
+    }
+}
+
+/// Slug hint for the coordinated branch.
+fn feature_hint(plan: &CruisePlan) -> String {
+    if plan.title.is_empty() {
+        plan.prompt.clone()
+    } else {
+        plan.title.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::planner::{plan_to_beads, BEADS_DIR};
+    use crate::cruise::{BuildingConfig, RunAborter};
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Helper to create a temp git repo for testing.
+    fn create_temp_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let path = temp_dir.path();
+
+        SystemGit.run(path, &["init", "-q"]).unwrap();
+        SystemGit
+            .run(path, &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(path, &["config", "user.name", "Test User"])
+            .unwrap();
+        fs::write(path.join("README.md"), "# Test Repo\n").unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", "Initial commit"])
+            .unwrap();
+
+        temp_dir
+    }
+
+    fn plan() -> CruisePlan {
+        let mut plan = CruisePlan::new("add a billing endpoint and client");
+        plan.title = "Billing endpoint".to_string();
+        plan.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Add endpoint").with_repo("api"),
+            CruiseTask::new("CRUISE-002", "Add endpoint tests")
+                .with_repo("api")
+                .with_blocked_by(vec!["CRUISE-001".to_string()]),
+            CruiseTask::new("CRUISE-003", "Add client method")
+                .with_repo("client")
+                .with_blocked_by(vec!["CRUISE-001".to_string()]),
+        ];
+        plan
+    }
+
+    fn runner(api: &TempDir, client: &TempDir) -> MultiRepoCruiseRunner {
+        let repos = vec![
+            RepoTarget::new("api", api.path()).with_prompt("You are working in the API service."),
+            RepoTarget::new("client", client.path()),
+        ];
+        MultiRepoCruiseRunner::new(plan(), repos, TaskExecutor::new(BuildingConfig::default()))
+            .unwrap()
+    }
+
+    #[test]
+    fn rejects_unknown_repositories() {
+        let mut plan = plan();
+        plan.tasks[0].repo = Some("web".to_string());
+
+        let result = MultiRepoCruiseRunner::new(
+            plan,
+            vec![RepoTarget::new("api", "/tmp/api")],
+            TaskExecutor::new(BuildingConfig::default()),
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidPlan(PlanError::UnknownRepository { repo, .. })) if repo == "web"
+        ));
+    }
+
+    #[test]
+    fn tracks_cross_repo_dependencies() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let runner = runner(&api, &client);
+
+        assert_eq!(
+            runner.cross_repo_dependencies(),
+            vec![CrossRepoDependency {
+                task_id: "CRUISE-003".to_string(),
+                repo: "client".to_string(),
+                depends_on: "CRUISE-001".to_string(),
+                depends_on_repo: "api".to_string(),
+            }]
+        );
+        assert!(runner.feature_branch().starts_with("spawn-sandbox-"));
+    }
+
+    #[test]
+    fn prompts_include_repo_context_and_upstream_work() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let runner = runner(&api, &client);
+
+        let api_prompt = &runner.plan_task("CRUISE-001").unwrap()[0].prompt;
+        assert!(api_prompt.starts_with("You are working in the API service."));
+
+        let client_prompt = &runner.plan_task("CRUISE-003").unwrap()[0].prompt;
+        assert!(client_prompt.contains("- CRUISE-001 (api): Add endpoint"));
+        assert!(client_prompt.contains("## Task CRUISE-003"));
+    }
+
+    #[test]
+    fn cross_repo_task_waits_then_starts_from_head() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let sandboxes = TempDir::new().unwrap();
+        let mut runner = runner(&api, &client).with_sandbox_dir(sandboxes.path());
+
+        let ready: Vec<_> = runner.ready_tasks().iter().map(|t| t.id.clone()).collect();
+        assert_eq!(ready, vec!["CRUISE-001"]);
+
+        let sandbox = runner
+            .create_sandbox("CRUISE-001", SandboxManifest::default())
+            .unwrap();
+        fs::write(sandbox.path().join("endpoint.rs"), "fn billing() {}\n").unwrap();
+        SystemGit.run(sandbox.path(), &["add", "."]).unwrap();
+        SystemGit
+            .run(sandbox.path(), &["commit", "-q", "-m", "Add endpoint"])
+            .unwrap();
+        runner
+            .complete("CRUISE-001", sandbox.branch_name())
+            .unwrap();
+        drop(sandbox);
+
+        let ready: Vec<_> = runner.ready_tasks().iter().map(|t| t.id.clone()).collect();
+        assert_eq!(ready, vec!["CRUISE-002", "CRUISE-003"]);
+
+        // Same-repo dependency: starts from the recorded artifact
+        let api_sandbox = runner
+            .create_sandbox("CRUISE-002", SandboxManifest::default())
+            .unwrap();
+        assert!(api_sandbox.path().join("endpoint.rs").exists());
+
+        // Cross-repo dependency: starts from the client's HEAD
+        let client_sandbox = runner
+            .create_sandbox("CRUISE-003", SandboxManifest::default())
+            .unwrap();
+        assert!(client_sandbox.path().join("README.md").exists());
+
+        drop((api_sandbox, client_sandbox));
+        runner.cleanup().unwrap();
+    }
+
+    #[test]
+    fn integrate_points_feature_branch_at_completed_work() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let mut runner = runner(&api, &client);
+        SystemGit
+            .run(api.path(), &["checkout", "-q", "-b", "work"])
+            .unwrap();
+        fs::write(api.path().join("endpoint.rs"), "fn billing() {}\n").unwrap();
+        SystemGit.run(api.path(), &["add", "."]).unwrap();
+        SystemGit
+            .run(api.path(), &["commit", "-q", "-m", "Add endpoint"])
+            .unwrap();
+        runner.complete("CRUISE-001", "work").unwrap();
+
+        let changed = runner.integrate().unwrap();
+
+        assert_eq!(changed, vec!["api"]);
+        let branch = runner.feature_branch().to_string();
+        let subject = SystemGit
+            .run(api.path(), &["log", "-1", "--format=%s", &branch])
+            .unwrap();
+        assert_eq!(subject.trim(), "Add endpoint");
+        assert!(SystemGit
+            .run(client.path(), &["branch", "--list", &branch])
+            .unwrap()
+            .trim()
+            .is_empty());
+    }
+
+    #[test]
+    fn integrate_applies_history_policy() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let base = SystemGit
+            .run(api.path(), &["rev-parse", "--abbrev-ref", "HEAD"])
+            .unwrap();
+        let base = base.trim();
+        let repos = vec![
+            RepoTarget::new("api", api.path()).with_base_branch(base),
+            RepoTarget::new("client", client.path()),
+        ];
+        let messages = CommitMessages::default().with_history(GitHistoryPolicy::SquashAll);
+        let mut runner =
+            MultiRepoCruiseRunner::new(plan(), repos, TaskExecutor::new(BuildingConfig::default()))
+                .unwrap()
+                .with_commit_messages(messages);
+        SystemGit
+            .run(api.path(), &["checkout", "-q", "-b", "work"])
+            .unwrap();
+        for file in ["endpoint.rs", "routes.rs"] {
+            fs::write(api.path().join(file), "fn billing() {}\n").unwrap();
+            SystemGit.run(api.path(), &["add", "."]).unwrap();
+            SystemGit
+                .run(api.path(), &["commit", "-q", "-m", file])
+                .unwrap();
+        }
+        runner.complete("CRUISE-001", "work").unwrap();
+
+        runner.integrate().unwrap();
+
+        let range = format!("{}..{}", base, runner.feature_branch());
+        let count = SystemGit
+            .run(api.path(), &["rev-list", "--count", &range])
+            .unwrap();
+        assert_eq!(count.trim(), "1");
+        let files = SystemGit
+            .run(
+                api.path(),
+                &["ls-tree", "--name-only", runner.feature_branch()],
+            )
+            .unwrap();
+        assert!(files.contains("routes.rs"));
+        let checkout = SystemGit
+            .run(api.path(), &["rev-parse", "--abbrev-ref", "HEAD"])
+            .unwrap();
+        assert_eq!(checkout.trim(), "work");
+    }
+
+    #[test]
+    fn status_records_what_the_run_creates() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let sandboxes = TempDir::new().unwrap();
+        let mut runner = runner(&api, &client)
+            .with_sandbox_dir(sandboxes.path())
+            .with_status(CruiseStatus::new("run-1", "add billing"));
+
+        runner.start("CRUISE-001");
+        let sandbox = runner
+            .create_sandbox("CRUISE-001", SandboxManifest::default())
+            .unwrap();
+        fs::write(sandbox.path().join("endpoint.rs"), "fn billing() {}\n").unwrap();
+        SystemGit.run(sandbox.path(), &["add", "."]).unwrap();
+        SystemGit
+            .run(sandbox.path(), &["commit", "-q", "-m", "Add endpoint"])
+            .unwrap();
+        let artifact = runner
+            .complete("CRUISE-001", sandbox.branch_name())
+            .unwrap();
+        runner.start("CRUISE-003");
+        let client_sandbox = runner
+            .create_sandbox("CRUISE-003", SandboxManifest::default())
+            .unwrap();
+        runner.integrate().unwrap();
+
+        let mut status = CruiseStatus::load(api.path(), "run-1").unwrap();
+        assert_eq!(status.phase, "building");
+        assert_eq!((status.tasks.completed, status.tasks.in_progress), (1, 1));
+        assert_eq!(status.repos, vec![client.path().to_path_buf()]);
+        assert_eq!(
+            status.sandboxes,
+            vec![sandbox.path().clone(), client_sandbox.path().clone()]
+        );
+        for branch in [
+            sandbox.branch_name(),
+            artifact.as_str(),
+            runner.feature_branch(),
+        ] {
+            assert!(status.branches.iter().any(|b| b == branch), "{}", branch);
+        }
+
+        // Abort finds and removes the sandbox in the other repository too
+        let report = RunAborter::new(api.path()).abort(&mut status).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(!client_sandbox.path().exists());
+    }
+
+    #[test]
+    fn failure_skips_dependents_in_other_repos() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let mut runner = runner(&api, &client);
+
+        runner.fail("CRUISE-001", "tests failed");
+
+        let statuses: Vec<_> = runner.plan().tasks.iter().map(|t| t.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                TaskStatus::Blocked,
+                TaskStatus::Skipped,
+                TaskStatus::Skipped
+            ]
+        );
+        assert!(runner.ready_tasks().is_empty());
+    }
+
+    #[test]
+    fn beads_issues_follow_task_lifecycle() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let beads = api.path().join(BEADS_DIR);
+        plan_to_beads(&plan(), &beads).unwrap();
+        let mut runner = runner(&api, &client).with_beads(BeadsSync::new(api.path()));
+        let read = |id: &str| fs::read_to_string(beads.join(format!("{}.md", id))).unwrap();
+
+        runner.start("CRUISE-001");
+        assert_eq!(runner.plan().tasks[0].status, TaskStatus::InProgress);
+        assert!(read("CRUISE-001").contains("status: in_progress\n"));
+
+        runner.fail("CRUISE-001", "tests failed");
+
+        assert!(read("CRUISE-001").contains("- Blocked: tests failed\n"));
+        let dependent = read("CRUISE-003");
+        assert!(dependent.contains("status: blocked\n"));
+        assert!(dependent.contains("- Blocked: dependency CRUISE-001 failed\n"));
+    }
+
+    #[test]
+    fn pr_body_links_related_pull_requests() {
+        let (api, client) = (create_temp_git_repo(), create_temp_git_repo());
+        let mut runner = runner(&api, &client);
+        runner.record_pr(
+            "client",
+            PullRequest {
+                number: 7,
+                url: "https://github.com/acme/client/pull/7".to_string(),
+                title: "Billing endpoint".to_string(),
+                base_branch: "main".to_string(),
+                head_branch: runner.feature_branch().to_string(),
+                is_draft: false,
+            },
+        );
+
+        let body = runner.pr_body("api");
+
+        assert!(body.contains("- [ ] CRUISE-001: Add endpoint"));
+        assert!(!body.contains("CRUISE-003"));
+        assert!(body.contains("- client: https://github.com/acme/client/pull/7"));
+        assert!(runner.pr_body("client").contains("- api: pending"));
+    }
+}
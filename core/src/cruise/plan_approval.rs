@@ -0,0 +1,310 @@
+//! Plan approval in the terminal.
+//!
+//! With `approval.mode = "terminal"`, a plan is shown in the terminal
+//! instead of being opened as a plan PR. The user approves or rejects it,
+//! or rewrites task descriptions first; edits are made to the plan itself,
+//! so the build phase runs what was approved.
+
+use std::io::{BufRead, Write};
+
+use super::estimate::PlanEstimate;
+use super::status::format_secs;
+use super::task::CruisePlan;
+use crate::error::Result;
+
+/// Ends a multi-line description typed at the prompt.
+const END_OF_DESCRIPTION: &str = ".";
+
+/// Commands accepted at the approval prompt.
+const HELP: &str = "Commands:\n  \
+    a, approve           run the plan\n  \
+    r, reject [reason]   stop without running the plan\n  \
+    e, edit <task-id>    rewrite a task's description\n  \
+    p, plan              show the plan again\n";
+
+/// Outcome of reviewing a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanDecision {
+    /// Run the plan.
+    Approved,
+    /// Stop without running the plan.
+    Rejected {
+        /// Why the plan was rejected, if the user said.
+        reason: Option<String>,
+    },
+}
+
+/// What the user decided and which tasks they edited on the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanReview {
+    /// The decision.
+    pub decision: PlanDecision,
+    /// IDs of the tasks whose descriptions were rewritten, in edit order.
+    pub edited: Vec<String>,
+}
+
+impl PlanReview {
+    /// Returns whether the plan was approved.
+    pub fn is_approved(&self) -> bool {
+        self.decision == PlanDecision::Approved
+    }
+}
+
+/// Formats `plan` for the terminal: its tasks with their dependencies and,
+/// if given, their estimates.
+pub fn render_plan(plan: &CruisePlan, estimate: Option<&PlanEstimate>) -> String {
+    let title = if plan.title.is_empty() {
+        &plan.prompt
+    } else {
+        &plan.title
+    };
+    let mut out = format!("Plan: {}\n", title.trim());
+    if !plan.overview.trim().is_empty() {
+        out.push_str(&format!("\n{}\n", plan.overview.trim()));
+    }
+
+    for task in &plan.tasks {
+        out.push_str(&format!("\n{}  {}\n", task.id, task.subject));
+        let mut details = vec![format!("{:?}", task.complexity).to_lowercase()];
+        if !task.blocked_by.is_empty() {
+            details.push(format!("after {}", task.blocked_by.join(", ")));
+        }
+        if let Some(e) = estimate.and_then(|e| e.tasks.iter().find(|t| t.task_id == task.id)) {
+            details.push(format!(
+                "~{}, ${:.2}",
+                format_secs(e.duration.as_secs()),
+                e.cost_usd
+            ));
+        }
+        out.push_str(&format!("    {}\n", details.join(" | ")));
+        for line in task.description.trim().lines() {
+            out.push_str(&format!("    {}\n", line));
+        }
+    }
+
+    if !plan.risks.is_empty() {
+        out.push_str("\nRisks:\n");
+        for risk in &plan.risks {
+            out.push_str(&format!("  - {}\n", risk));
+        }
+    }
+    if let Some(estimate) = estimate {
+        out.push_str(&format!(
+            "\nEstimate: ~{} build time, ~${:.2}\n",
+            format_secs(estimate.wall_clock.as_secs()),
+            estimate.total_cost()
+        ));
+    }
+    out
+}
+
+/// Asks for a plan decision on a terminal.
+pub struct TerminalApproval<R, W> {
+    input: R,
+    output: W,
+}
+
+impl TerminalApproval<std::io::StdinLock<'static>, std::io::Stderr> {
+    /// Creates an approval reading stdin and writing to stderr.
+    pub fn stdio() -> Self {
+        Self::new(std::io::stdin().lock(), std::io::stderr())
+    }
+}
+
+impl<R: BufRead, W: Write> TerminalApproval<R, W> {
+    /// Creates an approval reading commands from `input` and writing the
+    /// plan and prompts to `output`.
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// Shows `plan` and waits for a decision, applying any description
+    /// edits to `plan`. Running out of input rejects the plan.
+    pub fn review(
+        &mut self,
+        plan: &mut CruisePlan,
+        estimate: Option<&PlanEstimate>,
+    ) -> Result<PlanReview> {
+        let mut edited: Vec<String> = Vec::new();
+        write!(self.output, "{}\n{}", render_plan(plan, estimate), HELP)?;
+
+        loop {
+            write!(self.output, "\nApprove this plan? [a/r/e/p] ")?;
+            self.output.flush()?;
+            let Some(line) = self.read_line()? else {
+                return Ok(PlanReview {
+                    decision: PlanDecision::Rejected {
+                        reason: Some("no answer".to_string()),
+                    },
+                    edited,
+                });
+            };
+            let (command, argument) = match line.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (line.as_str(), ""),
+            };
+
+            match command.to_lowercase().as_str() {
+                "a" | "approve" => {
+                    return Ok(PlanReview {
+                        decision: PlanDecision::Approved,
+                        edited,
+                    })
+                }
+                "r" | "reject" => {
+                    return Ok(PlanReview {
+                        decision: PlanDecision::Rejected {
+                            reason: (!argument.is_empty()).then(|| argument.to_string()),
+                        },
+                        edited,
+                    })
+                }
+                "e" | "edit" => {
+                    if self.edit(plan, argument)? && !edited.iter().any(|id| id == argument) {
+                        edited.push(argument.to_string());
+                    }
+                }
+                "p" | "plan" => write!(self.output, "{}", render_plan(plan, estimate))?,
+                _ => write!(self.output, "{}", HELP)?,
+            }
+        }
+    }
+
+    /// Rewrites the description of task `task_id`, returning whether it
+    /// changed.
+    fn edit(&mut self, plan: &mut CruisePlan, task_id: &str) -> Result<bool> {
+        let Some(index) = plan.tasks.iter().position(|t| t.id == task_id) else {
+            let ids: Vec<&str> = plan.tasks.iter().map(|t| t.id.as_str()).collect();
+            writeln!(
+                self.output,
+                "No task {:?}; tasks are {}.",
+                task_id,
+                ids.join(", ")
+            )?;
+            return Ok(false);
+        };
+
+        writeln!(
+            self.output,
+            "Current description of {}:\n{}\n\nNew description, ending with a line \
+             holding only \"{}\" (an empty first line keeps it):",
+            task_id,
+            plan.tasks[index].description.trim(),
+            END_OF_DESCRIPTION
+        )?;
+        let mut lines = Vec::new();
+        while let Some(line) = self.read_line()? {
+            if line == END_OF_DESCRIPTION || (lines.is_empty() && line.is_empty()) {
+                break;
+            }
+            lines.push(line);
+        }
+        let description = lines.join("\n").trim().to_string();
+        if description.is_empty() || description == plan.tasks[index].description.trim() {
+            writeln!(self.output, "{} unchanged.", task_id)?;
+            return Ok(false);
+        }
+
+        plan.tasks[index].description = description;
+        writeln!(self.output, "Updated {}.", task_id)?;
+        Ok(true)
+    }
+
+    /// Reads a line without its line ending, or `None` at end of input.
+    fn read_line(&mut self) -> Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::estimate::TaskEstimate;
+    use crate::cruise::task::{CruiseTask, TaskComplexity};
+    use std::time::Duration;
+
+    fn plan() -> CruisePlan {
+        let mut plan = CruisePlan::new("Build an API");
+        plan.title = "Todo API".to_string();
+        plan.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Setup").with_description("Scaffold the crate"),
+            CruiseTask::new("CRUISE-002", "Auth").with_description("Add login"),
+        ];
+        plan.tasks[1].blocked_by = vec!["CRUISE-001".to_string()];
+        plan.tasks[1].complexity = TaskComplexity::High;
+        plan
+    }
+
+    fn answer(plan: &mut CruisePlan, input: &str) -> (PlanReview, String) {
+        let mut output = Vec::new();
+        let review = TerminalApproval::new(input.as_bytes(), &mut output)
+            .review(plan, None)
+            .unwrap();
+        (review, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn plan_shows_tasks_dependencies_and_estimates() {
+        let estimate = PlanEstimate {
+            tasks: vec![TaskEstimate {
+                task_id: "CRUISE-002".to_string(),
+                complexity: TaskComplexity::High,
+                duration: Duration::from_secs(1800),
+                cost_usd: 1.5,
+                from_history: false,
+            }],
+            wall_clock: Duration::from_secs(1800),
+        };
+
+        let rendered = render_plan(&plan(), Some(&estimate));
+
+        assert!(rendered.starts_with("Plan: Todo API\n"));
+        assert!(rendered.contains("\nCRUISE-001  Setup\n    medium\n    Scaffold the crate\n"));
+        assert!(rendered.contains(
+            "\nCRUISE-002  Auth\n    high | after CRUISE-001 | ~30m 00s, $1.50\n    Add login\n"
+        ));
+        assert!(rendered.ends_with("\nEstimate: ~30m 00s build time, ~$1.50\n"));
+    }
+
+    #[test]
+    fn edits_are_applied_before_approval() {
+        let mut plan = plan();
+
+        let (review, output) = answer(
+            &mut plan,
+            "e CRUISE-404\nedit CRUISE-002\nAdd login\nwith refresh tokens\n.\na\n",
+        );
+
+        assert!(review.is_approved());
+        assert_eq!(review.edited, ["CRUISE-002"]);
+        assert_eq!(plan.tasks[1].description, "Add login\nwith refresh tokens");
+        assert_eq!(plan.tasks[0].description, "Scaffold the crate");
+        assert!(output.contains("No task \"CRUISE-404\"; tasks are CRUISE-001, CRUISE-002."));
+        assert!(output.contains("Updated CRUISE-002."));
+    }
+
+    #[test]
+    fn plans_are_rejected_with_a_reason_or_without_input() {
+        let mut plan = plan();
+
+        let (review, _) = answer(&mut plan, "e CRUISE-001\n\nreject too broad\n");
+
+        assert_eq!(
+            review,
+            PlanReview {
+                decision: PlanDecision::Rejected {
+                    reason: Some("too broad".to_string())
+                },
+                edited: Vec::new(),
+            }
+        );
+        assert_eq!(plan.tasks[0].description, "Scaffold the crate");
+
+        let (review, _) = answer(&mut plan, "");
+        assert!(!review.is_approved());
+    }
+}
@@ -1,19 +1,33 @@
 //! Planner for cruise-control plan generation.
 //!
 //! Uses spawn-team ping-pong with phased reviews to generate
-//! dependency-aware plans as beads issues.
+//! dependency-aware plans as beads issues. Humans can request changes on
+//! the plan PR; [`Planner::revise_plan`] feeds their comments into another
+//! planning iteration and updates the beads issues to match.
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
+use super::comments::{CommentKind, PrComment};
 use super::config::PlanningConfig;
+use super::estimate::PlanEstimate;
+use super::prompts::PlanPromptBuilder;
 use super::result::PlanResult;
-use super::task::{CruisePlan, CruiseTask, TaskComplexity, TaskStatus};
-use crate::error::{Error, Result};
+use super::task::{CruisePlan, CruiseTask, SpawnInstance, TaskComplexity, TaskStatus};
+use crate::config::KNOWN_LLMS;
+use crate::error::{Error, PlanError, Result};
+use crate::prompt::PromptTemplates;
+use crate::runner::{LLMOutput, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::SandboxManifest;
+
+/// Directory beads issues are written to, relative to the repository root.
+pub const BEADS_DIR: &str = ".beads";
 
 /// Review phase for plan iteration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -43,6 +57,17 @@ impl ReviewPhase {
         }
     }
 
+    /// Returns the review domain name used for gating findings.
+    pub fn domain(&self) -> &'static str {
+        match self {
+            ReviewPhase::Security => "security",
+            ReviewPhase::TechnicalFeasibility => "technical_feasibility",
+            ReviewPhase::TaskGranularity => "task_granularity",
+            ReviewPhase::DependencyCompleteness => "dependency_completeness",
+            ReviewPhase::GeneralPolish => "general_polish",
+        }
+    }
+
     /// Returns the focus description for this phase.
     pub fn focus_description(&self) -> &'static str {
         match self {
@@ -73,12 +98,101 @@ impl ReviewPhase {
 /// Planner for cruise-control plan generation.
 pub struct Planner {
     config: PlanningConfig,
+    /// LLM that writes and revises plans.
+    runner: Option<Arc<dyn LLMRunner>>,
+}
+
+/// Task-level differences between two versions of a plan.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanDiff {
+    /// IDs of tasks only in the new plan.
+    pub added: Vec<String>,
+    /// IDs of tasks whose definition changed.
+    pub changed: Vec<String>,
+    /// IDs of tasks dropped from the new plan.
+    pub removed: Vec<String>,
+}
+
+impl PlanDiff {
+    /// Compares the tasks of `old` and `new` by ID.
+    pub fn between(old: &CruisePlan, new: &CruisePlan) -> Self {
+        let mut diff = Self::default();
+        for task in &new.tasks {
+            match old.tasks.iter().find(|t| t.id == task.id) {
+                None => diff.added.push(task.id.clone()),
+                Some(previous) if !same_definition(previous, task) => {
+                    diff.changed.push(task.id.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .tasks
+            .iter()
+            .filter(|t| !new.tasks.iter().any(|n| n.id == t.id))
+            .map(|t| t.id.clone())
+            .collect();
+        diff
+    }
+
+    /// Returns whether the plans have the same tasks.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Formats the diff as a markdown list for a PR comment.
+    pub fn to_markdown(&self) -> String {
+        if self.is_empty() {
+            return "No task changes.\n".to_string();
+        }
+        let mut md = String::new();
+        for (label, ids) in [
+            ("Added", &self.added),
+            ("Changed", &self.changed),
+            ("Closed", &self.removed),
+        ] {
+            if !ids.is_empty() {
+                md.push_str(&format!("- **{}:** {}\n", label, ids.join(", ")));
+            }
+        }
+        md
+    }
+}
+
+/// Compares the parts of a task the planner writes.
+fn same_definition(a: &CruiseTask, b: &CruiseTask) -> bool {
+    a.subject == b.subject
+        && a.description == b.description
+        && a.blocked_by == b.blocked_by
+        && a.component == b.component
+        && a.complexity == b.complexity
+        && a.acceptance_criteria == b.acceptance_criteria
+}
+
+/// Outcome of revising a plan from PR comments.
+#[derive(Debug, Clone)]
+pub struct PlanRevision {
+    /// The revised plan.
+    pub plan: CruisePlan,
+    /// How its tasks differ from the previous plan.
+    pub diff: PlanDiff,
+    /// Beads issues written, created, or closed.
+    pub beads_files: Vec<PathBuf>,
 }
 
 impl Planner {
     /// Creates a new planner with the given configuration.
     pub fn new(config: PlanningConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            runner: None,
+        }
+    }
+
+    /// Sets the LLM that writes and revises plans.
+    pub fn with_runner(mut self, runner: Arc<dyn LLMRunner>) -> Self {
+        self.runner = Some(runner);
+        self
     }
 
     /// Creates a planner with default configuration.
@@ -99,7 +213,7 @@ impl Planner {
         // For now, just validate that we can parse a mock response
         // Full implementation will integrate with spawn-team
         let _ = prompt;
-        Err(Error::Cruise(
+        Err(Error::Config(
             "Planner not yet integrated with spawn-team".to_string(),
         ))
     }
@@ -125,10 +239,132 @@ impl Planner {
             duration: start.elapsed(),
             plan_file: None,
             error: Some("Planner not yet integrated with spawn-team".to_string()),
+            observability: Vec::new(),
+        })
+    }
+
+    /// Revises `plan` from human comments on the plan PR.
+    ///
+    /// Comments other than acknowledgements are fed back as review feedback
+    /// in one more planning iteration. The regenerated plan is validated
+    /// and diffed against `plan` by task ID, and the beads issues in
+    /// `work_dir` are updated: new and changed tasks are (re)written and
+    /// dropped tasks are closed as skipped. Task progress carries over to
+    /// tasks that remain. If no comment asks for anything, `plan` is
+    /// returned unchanged without running the LLM.
+    pub async fn revise_plan(
+        &self,
+        plan: &CruisePlan,
+        comments: &[PrComment],
+        work_dir: &Path,
+    ) -> Result<PlanRevision> {
+        let feedback = format_plan_feedback(comments);
+        if feedback.is_empty() {
+            return Ok(PlanRevision {
+                plan: plan.clone(),
+                diff: PlanDiff::default(),
+                beads_files: Vec::new(),
+            });
+        }
+
+        let runner = self
+            .runner
+            .as_ref()
+            .ok_or_else(|| Error::Config("no LLM runner configured for planning".to_string()))?;
+        let previous = serde_json::to_string_pretty(plan)
+            .map_err(|e| Error::Config(format!("failed to serialize plan: {}", e)))?;
+        let config = LLMSpawnConfig {
+            prompt: PlanPromptBuilder::new(&plan.prompt)
+                .with_previous_plan(previous)
+                .with_review_feedback(feedback)
+                .build_with(&PromptTemplates::for_repo(work_dir)?),
+            working_dir: work_dir.to_path_buf(),
+            manifest: SandboxManifest::default(),
+            model: None,
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
+        let (result, output) = tokio::join!(runner.spawn(config, tx), async {
+            let mut lines = Vec::new();
+            while let Some(output) = rx.recv().await {
+                if let LLMOutput::Stdout(line) = output {
+                    lines.push(line);
+                }
+            }
+            lines.join("\n")
+        });
+        if !result?.success {
+            tracing::warn!(runner = %runner.name(), "plan revision exited unsuccessfully");
+        }
+
+        let mut revised = parse_plan_json(&output)?;
+        revised.prompt = plan.prompt.clone();
+        revised.planning_iterations = plan.planning_iterations + 1;
+        for task in &mut revised.tasks {
+            if let Some(previous) = plan.tasks.iter().find(|t| t.id == task.id) {
+                task.status = previous.status;
+            }
+        }
+        validate_plan(&revised)?;
+
+        let diff = PlanDiff::between(plan, &revised);
+        let beads_files = update_beads(plan, &revised, &diff, &work_dir.join(BEADS_DIR))?;
+        tracing::info!(
+            added = diff.added.len(),
+            changed = diff.changed.len(),
+            removed = diff.removed.len(),
+            "revised plan from PR comments"
+        );
+
+        Ok(PlanRevision {
+            plan: revised,
+            diff,
+            beads_files,
         })
     }
 }
 
+/// Formats PR comments as review feedback for a planning iteration,
+/// skipping those that need no action.
+fn format_plan_feedback(comments: &[PrComment]) -> String {
+    comments
+        .iter()
+        .filter(|c| c.kind != CommentKind::Other || c.changes_requested)
+        .map(|c| format!("- @{}: {}", c.author, c.body.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Writes beads issues for the tasks `diff` touches: new and changed tasks
+/// from `new`, and dropped tasks from `old` closed as skipped.
+fn update_beads(
+    old: &CruisePlan,
+    new: &CruisePlan,
+    diff: &PlanDiff,
+    beads_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut touched = CruisePlan::new(&new.prompt);
+    touched.tasks = new
+        .tasks
+        .iter()
+        .filter(|t| diff.added.contains(&t.id) || diff.changed.contains(&t.id))
+        .cloned()
+        .collect();
+    touched.tasks.extend(
+        old.tasks
+            .iter()
+            .filter(|t| diff.removed.contains(&t.id))
+            .map(|t| {
+                let mut closed = t.clone();
+                closed.status = TaskStatus::Skipped;
+                closed
+            }),
+    );
+    plan_to_beads(&touched, beads_dir)
+}
+
 /// Intermediate struct for parsing plan JSON.
 #[derive(Debug, Deserialize)]
 struct PlanJson {
@@ -153,6 +389,8 @@ struct TaskJson {
     complexity: String,
     #[serde(default)]
     acceptance_criteria: Vec<String>,
+    #[serde(default)]
+    spawn_instances: Vec<SpawnInstance>,
 }
 
 fn default_complexity() -> String {
@@ -165,12 +403,11 @@ fn default_complexity() -> String {
 /// and parses it into a CruisePlan.
 pub fn parse_plan_json(output: &str) -> Result<CruisePlan> {
     // Try to find JSON in the output
-    let json_str =
-        extract_json(output).ok_or_else(|| Error::Cruise("No JSON found in output".to_string()))?;
+    let json_str = extract_json(output).ok_or_else(|| Error::NoJson("plan output".to_string()))?;
 
     // Parse the JSON
-    let parsed: PlanJson = serde_json::from_str(json_str)
-        .map_err(|e| Error::Cruise(format!("Failed to parse plan JSON: {}", e)))?;
+    let parsed: PlanJson =
+        serde_json::from_str(json_str).map_err(|e| Error::json("plan output", e))?;
 
     // Convert to CruisePlan
     let mut plan = CruisePlan::new("");
@@ -192,6 +429,7 @@ pub fn parse_plan_json(output: &str) -> Result<CruisePlan> {
 
         task.component = task_json.component;
         task.acceptance_criteria = task_json.acceptance_criteria;
+        task.spawn_instances = task_json.spawn_instances;
 
         plan.tasks.push(task);
     }
@@ -200,7 +438,7 @@ pub fn parse_plan_json(output: &str) -> Result<CruisePlan> {
 }
 
 /// Extracts JSON from output that may contain markdown code blocks.
-fn extract_json(output: &str) -> Option<&str> {
+pub(crate) fn extract_json(output: &str) -> Option<&str> {
     // Try to find JSON in code block
     if let Some(start) = output.find("```json") {
         let json_start = start + 7;
@@ -223,12 +461,12 @@ fn extract_json(output: &str) -> Option<&str> {
 pub fn validate_plan(plan: &CruisePlan) -> Result<()> {
     // Check for empty plan
     if plan.tasks.is_empty() {
-        return Err(Error::Cruise("Plan produced no tasks".to_string()));
+        return Err(Error::InvalidPlan(PlanError::NoTasks));
     }
 
     // Check for empty title
     if plan.title.trim().is_empty() {
-        return Err(Error::Cruise("Plan has no title".to_string()));
+        return Err(Error::InvalidPlan(PlanError::NoTitle));
     }
 
     // Check for dependency cycles
@@ -240,24 +478,31 @@ pub fn validate_plan(plan: &CruisePlan) -> Result<()> {
     for task in &plan.tasks {
         // Check ID format
         if !task.id.starts_with("CRUISE-") {
-            return Err(Error::Cruise(format!(
-                "Task ID '{}' must use CRUISE-XXX format",
-                task.id
-            )));
+            return Err(Error::InvalidPlan(PlanError::TaskId(task.id.clone())));
         }
 
         // Check for empty subject
         if task.subject.trim().is_empty() {
-            return Err(Error::Cruise(format!("Task {} has no subject", task.id)));
+            return Err(Error::InvalidPlan(PlanError::NoSubject(task.id.clone())));
         }
 
         // Check for unknown dependencies
         for dep in &task.blocked_by {
             if !plan.tasks.iter().any(|t| &t.id == dep) {
-                return Err(Error::Cruise(format!(
-                    "Task {} depends on unknown task {}",
-                    task.id, dep
-                )));
+                return Err(Error::InvalidPlan(PlanError::UnknownDependency {
+                    task_id: task.id.clone(),
+                    dependency: dep.clone(),
+                }));
+            }
+        }
+
+        // Check spawn instance runners
+        for instance in &task.spawn_instances {
+            if !KNOWN_LLMS.contains(&instance.llm.as_str()) {
+                return Err(Error::InvalidPlan(PlanError::UnknownLlm {
+                    task_id: task.id.clone(),
+                    llm: instance.llm.clone(),
+                }));
             }
         }
     }
@@ -269,7 +514,7 @@ pub fn validate_plan(plan: &CruisePlan) -> Result<()> {
 pub fn plan_to_beads(plan: &CruisePlan, beads_dir: &Path) -> Result<Vec<std::path::PathBuf>> {
     // Create .beads directory if needed
     fs::create_dir_all(beads_dir)
-        .map_err(|e| Error::Cruise(format!("Failed to create beads directory: {}", e)))?;
+        .map_err(|e| Error::from(e).context("failed to create beads directory"))?;
 
     let mut written_files = Vec::new();
 
@@ -280,7 +525,7 @@ pub fn plan_to_beads(plan: &CruisePlan, beads_dir: &Path) -> Result<Vec<std::pat
         let content = format_beads_issue(task);
 
         fs::write(&filepath, content)
-            .map_err(|e| Error::Cruise(format!("Failed to write {}: {}", filename, e)))?;
+            .map_err(|e| Error::from(e).context(format!("failed to write {}", filename)))?;
 
         written_files.push(filepath);
     }
@@ -426,7 +671,7 @@ pub fn generate_plan_markdown(plan: &CruisePlan) -> String {
 }
 
 /// Computes execution waves (groups of tasks that can run in parallel).
-fn compute_execution_waves(plan: &CruisePlan) -> Vec<Vec<String>> {
+pub(crate) fn compute_execution_waves(plan: &CruisePlan) -> Vec<Vec<String>> {
     let mut waves: Vec<Vec<String>> = Vec::new();
     let mut completed: HashSet<String> = HashSet::new();
     let mut remaining: Vec<&CruiseTask> = plan.tasks.iter().collect();
@@ -458,8 +703,14 @@ fn compute_execution_waves(plan: &CruisePlan) -> Vec<Vec<String>> {
     waves
 }
 
-/// Generates the PR body for a plan PR.
-pub fn generate_pr_body(plan: &CruisePlan, user_prompt: &str, iterations: u32) -> String {
+/// Generates the PR body for a plan PR, including the build's estimated
+/// duration and cost.
+pub fn generate_pr_body(
+    plan: &CruisePlan,
+    user_prompt: &str,
+    iterations: u32,
+    estimate: &PlanEstimate,
+) -> String {
     let mut body = String::new();
 
     // Summary
@@ -514,6 +765,8 @@ pub fn generate_pr_body(plan: &CruisePlan, user_prompt: &str, iterations: u32) -
     }
     body.push('\n');
 
+    body.push_str(&estimate.to_markdown());
+
     // Planning stats
     body.push_str("## Planning Stats\n\n");
     body.push_str(&format!("- **Iterations**: {}\n", iterations));
@@ -597,6 +850,8 @@ fn render_tree_node(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cruise::config::BuildingConfig;
+    use crate::cruise::estimate::PlanEstimator;
 
     #[test]
     fn review_phase_for_iteration_maps_correctly() {
@@ -627,6 +882,16 @@ mod tests {
         assert!(!ReviewPhase::GeneralPolish.focus_description().is_empty());
     }
 
+    #[test]
+    fn review_phase_domain_matches_serialized_name() {
+        for phase in (1..=5).map(ReviewPhase::for_iteration) {
+            assert_eq!(
+                serde_json::to_string(&phase).unwrap(),
+                format!("\"{}\"", phase.domain())
+            );
+        }
+    }
+
     #[test]
     fn review_phase_serializes_correctly() {
         assert_eq!(
@@ -700,6 +965,51 @@ Here's my plan:
         assert!(plan.risks.is_empty());
     }
 
+    #[test]
+    fn parse_plan_json_parses_spawn_instances() {
+        let output = r#"{
+            "title": "Instances",
+            "overview": "Plan with instances",
+            "tasks": [
+                {
+                    "id": "CRUISE-001",
+                    "subject": "Task",
+                    "description": "Do something",
+                    "spawn_instances": [
+                        {
+                            "llm": "gemini-cli",
+                            "cli_params": ["--yolo"],
+                            "use_spawn_team": true,
+                            "allowed_tools": ["Read", "Write"]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let plan = parse_plan_json(output).unwrap();
+        let instances = &plan.tasks[0].spawn_instances;
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].llm, "gemini-cli");
+        assert_eq!(instances[0].cli_params, vec!["--yolo"]);
+        assert!(instances[0].use_spawn_team);
+    }
+
+    #[test]
+    fn validate_plan_rejects_unknown_instance_llm() {
+        let mut plan = CruisePlan::new("Test");
+        plan.title = "Test".to_string();
+        plan.tasks = vec![CruiseTask::new("CRUISE-001", "Task").with_spawn_instances(vec![
+            SpawnInstance {
+                llm: "gpt".to_string(),
+                ..Default::default()
+            },
+        ])];
+
+        let err = validate_plan(&plan).unwrap_err();
+        assert!(err.to_string().contains("unknown LLM 'gpt'"));
+    }
+
     #[test]
     fn parse_plan_json_returns_error_for_invalid_json() {
         let output = "not json at all";
@@ -909,7 +1219,8 @@ Here's my plan:
             CruiseTask::new("CRUISE-002", "Build").with_blocked_by(vec!["CRUISE-001".to_string()]),
         ];
 
-        let body = generate_pr_body(&plan, "Original request here", 5);
+        let estimate = PlanEstimator::new(BuildingConfig::default()).estimate(&plan);
+        let body = generate_pr_body(&plan, "Original request here", 5, &estimate);
 
         assert!(body.contains("## Summary"));
         assert!(body.contains("Build something cool."));
@@ -920,6 +1231,8 @@ Here's my plan:
         assert!(body.contains("## Dependency Graph"));
         assert!(body.contains("## Parallel Execution"));
         assert!(body.contains("**Wave 1**"));
+        assert!(body.contains("## Estimate"));
+        assert!(body.contains("- **Model cost**: ~$"));
         assert!(body.contains("## Planning Stats"));
         assert!(body.contains("Iterations**: 5"));
     }
@@ -954,4 +1267,138 @@ Here's my plan:
         let result = planner.plan_dry_run("test prompt");
         assert!(result.is_err());
     }
+
+    /// Runner that prints a fixed plan.
+    struct PlanWriter {
+        plan_json: String,
+        prompt: std::sync::Mutex<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl LLMRunner for PlanWriter {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            *self.prompt.lock().unwrap() = config.prompt;
+            let _ = output_tx
+                .send(LLMOutput::Stdout(self.plan_json.clone()))
+                .await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "plan-writer"
+        }
+    }
+
+    fn comment(body: &str, kind: CommentKind) -> PrComment {
+        PrComment {
+            id: "c1".to_string(),
+            author: "alice".to_string(),
+            body: body.to_string(),
+            changes_requested: false,
+            kind,
+        }
+    }
+
+    #[test]
+    fn plan_diff_classifies_tasks() {
+        let mut old = CruisePlan::new("test");
+        old.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Setup"),
+            CruiseTask::new("CRUISE-002", "Auth"),
+            CruiseTask::new("CRUISE-003", "Docs"),
+        ];
+        let mut new = old.clone();
+        new.tasks.remove(2);
+        new.tasks[1].description = "Use OAuth".to_string();
+        new.tasks[0].status = TaskStatus::Completed;
+        new.tasks.push(CruiseTask::new("CRUISE-004", "Metrics"));
+
+        let diff = PlanDiff::between(&old, &new);
+
+        assert_eq!(diff.added, vec!["CRUISE-004"]);
+        assert_eq!(diff.changed, vec!["CRUISE-002"]);
+        assert_eq!(diff.removed, vec!["CRUISE-003"]);
+        assert!(diff.to_markdown().contains("- **Closed:** CRUISE-003"));
+    }
+
+    #[tokio::test]
+    async fn revise_plan_updates_beads_from_comments() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut plan = CruisePlan::new("Build an API");
+        plan.title = "API".to_string();
+        plan.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Setup").with_description("Scaffold"),
+            CruiseTask::new("CRUISE-002", "Docs").with_description("Write docs"),
+        ];
+        plan.tasks[0].status = TaskStatus::Completed;
+        let revised = r#"{"title": "API", "overview": "", "tasks": [
+            {"id": "CRUISE-001", "subject": "Setup", "description": "Scaffold"},
+            {"id": "CRUISE-003", "subject": "Rate limiting", "description": "Add limits",
+             "blocked_by": ["CRUISE-001"]}
+        ]}"#;
+        let prompts = dir.path().join(crate::prompt::templates::PROMPTS_DIR);
+        fs::create_dir_all(&prompts).unwrap();
+        fs::write(
+            prompts.join("plan.md"),
+            "House style. Revise {{previous_plan}} for: {{review_feedback}}",
+        )
+        .unwrap();
+        let writer = Arc::new(PlanWriter {
+            plan_json: revised.to_string(),
+            prompt: Default::default(),
+        });
+        let planner = Planner::with_defaults().with_runner(writer.clone());
+        let comments = vec![
+            comment(
+                "Please drop the docs task and add rate limiting",
+                CommentKind::ChangeRequest,
+            ),
+            comment("LGTM otherwise", CommentKind::Other),
+        ];
+
+        let revision = planner
+            .revise_plan(&plan, &comments, dir.path())
+            .await
+            .unwrap();
+
+        let prompt = writer.prompt.lock().unwrap().clone();
+        assert!(prompt.starts_with("House style. Revise {"));
+        assert!(prompt.contains("drop the docs task"));
+        assert_eq!(revision.diff.added, vec!["CRUISE-003"]);
+        assert!(revision.diff.changed.is_empty());
+        assert_eq!(revision.diff.removed, vec!["CRUISE-002"]);
+        assert_eq!(revision.plan.prompt, "Build an API");
+        assert_eq!(revision.plan.planning_iterations, 1);
+        assert_eq!(revision.plan.tasks[0].status, TaskStatus::Completed);
+        assert_eq!(revision.beads_files.len(), 2);
+        let closed = fs::read_to_string(dir.path().join(".beads/CRUISE-002.md")).unwrap();
+        assert!(closed.contains("status: skipped"));
+        assert!(dir.path().join(".beads/CRUISE-003.md").exists());
+    }
+
+    #[tokio::test]
+    async fn revise_plan_without_requests_keeps_plan() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut plan = CruisePlan::new("test");
+        plan.tasks = vec![CruiseTask::new("CRUISE-001", "Setup")];
+
+        // No runner: the LLM must not be needed
+        let revision = Planner::with_defaults()
+            .revise_plan(&plan, &[comment("Thanks!", CommentKind::Other)], dir.path())
+            .await
+            .unwrap();
+
+        assert!(revision.diff.is_empty());
+        assert!(revision.beads_files.is_empty());
+    }
 }
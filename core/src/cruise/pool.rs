@@ -0,0 +1,402 @@
+//! Pool of test repositories reused between E2E fixtures.
+//!
+//! Creating and deleting a GitHub repository per fixture is slow and
+//! rate-limited. A [`RepoPool`] instead leases repositories out and records
+//! the commit each one started from. Before a repository is leased again it
+//! is force-reset to that commit: other branches are deleted, locally and
+//! on the remote, and untracked files are removed. A new repository is only
+//! created when none is free, and never beyond [`PoolConfig::max_size`].
+//! Leases that are not released expire after [`PoolConfig::lease_secs`], so
+//! a crashed fixture cannot hold a repository forever. The pool is saved as
+//! `repo-pool.json` in its directory, next to the clones.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::bootstrap::RepoBootstrapper;
+use super::config::{BootstrapConfig, PoolConfig};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+
+/// Name of the saved pool in the pool directory.
+pub const POOL_FILE: &str = "repo-pool.json";
+
+/// Goal written to the README of repositories the pool creates.
+const POOL_PROMPT: &str = "Test repository reused between E2E fixtures.";
+
+/// A repository in the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PooledRepo {
+    /// Repository name, without the owner.
+    pub name: String,
+    /// Path of the local clone.
+    pub path: PathBuf,
+    /// Default branch.
+    pub branch: String,
+    /// Commit the repository is reset to between leases.
+    pub initial_commit: String,
+    /// Current lease, if the repository is in use.
+    #[serde(default)]
+    pub lease: Option<Lease>,
+}
+
+/// A repository's current holder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    /// Who holds the lease, e.g. a fixture or run ID.
+    pub holder: String,
+    /// Unix time, in seconds, after which the lease has expired.
+    pub expires_at: u64,
+}
+
+/// Leases test repositories, creating them up to a cap.
+pub struct RepoPool {
+    dir: PathBuf,
+    owner: String,
+    config: PoolConfig,
+    repos: Vec<PooledRepo>,
+}
+
+impl RepoPool {
+    /// Opens the pool of `owner`'s repositories kept in `dir`, empty if
+    /// none was saved.
+    pub fn open(
+        dir: impl Into<PathBuf>,
+        owner: impl Into<String>,
+        config: PoolConfig,
+    ) -> Result<Self> {
+        let dir = dir.into();
+        let path = dir.join(POOL_FILE);
+        let repos = if path.exists() {
+            let json = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&json).map_err(|e| {
+                Error::Config(format!("invalid repo pool {}: {}", path.display(), e))
+            })?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            dir,
+            owner: owner.into(),
+            config,
+            repos,
+        })
+    }
+
+    /// Returns the repositories in the pool.
+    pub fn repos(&self) -> &[PooledRepo] {
+        &self.repos
+    }
+
+    /// Returns `owner/name` for a pooled repository.
+    pub fn full_name(&self, repo: &PooledRepo) -> String {
+        format!("{}/{}", self.owner, repo.name)
+    }
+
+    /// Leases a repository to `holder`, creating one from `bootstrap` (with
+    /// the pool's owner and a pool name) if none is free.
+    pub fn lease(&mut self, holder: &str, bootstrap: &BootstrapConfig) -> Result<PooledRepo> {
+        let dir = self.dir.clone();
+        let owner = self.owner.clone();
+        self.lease_with(holder, |name| {
+            let config = BootstrapConfig {
+                owner,
+                name: name.to_string(),
+                ..bootstrap.clone()
+            };
+            RepoBootstrapper::new(config, dir).bootstrap(POOL_PROMPT)
+        })
+    }
+
+    /// Leases a repository to `holder`. A free repository is reset to its
+    /// initial commit first. If none is free and the pool is not full,
+    /// `create` is called with a new repository name and must return the
+    /// path of its pushed clone.
+    pub fn lease_with(
+        &mut self,
+        holder: &str,
+        create: impl FnOnce(&str) -> Result<PathBuf>,
+    ) -> Result<PooledRepo> {
+        let now = now();
+        for repo in &mut self.repos {
+            if let Some(lease) = repo.lease.take_if(|lease| lease.expires_at <= now) {
+                tracing::warn!(repo = %repo.name, holder = %lease.holder, "repo pool lease expired");
+            }
+        }
+        let lease = Lease {
+            holder: holder.to_string(),
+            expires_at: now + self.config.lease_secs,
+        };
+
+        let index = match self.repos.iter().position(|repo| repo.lease.is_none()) {
+            Some(index) => {
+                reset(&self.repos[index])?;
+                index
+            }
+            None if self.repos.len() < self.config.max_size => {
+                let name = format!(
+                    "{}-{}",
+                    self.config.prefix,
+                    &uuid::Uuid::new_v4().simple().to_string()[..8]
+                );
+                let path = create(&name)?;
+                let branch = SystemGit
+                    .run(&path, &["rev-parse", "--abbrev-ref", "HEAD"])?
+                    .trim()
+                    .to_string();
+                let initial_commit = SystemGit
+                    .run(&path, &["rev-parse", "HEAD"])?
+                    .trim()
+                    .to_string();
+                tracing::info!(repo = %name, "added repository to pool");
+                self.repos.push(PooledRepo {
+                    name,
+                    path,
+                    branch,
+                    initial_commit,
+                    lease: None,
+                });
+                self.repos.len() - 1
+            }
+            None => return Err(Error::PoolExhausted(self.repos.len())),
+        };
+
+        self.repos[index].lease = Some(lease);
+        self.save()?;
+        tracing::debug!(repo = %self.repos[index].name, holder = %holder, "leased pooled repository");
+        Ok(self.repos[index].clone())
+    }
+
+    /// Returns the repository named `name` to the pool.
+    pub fn release(&mut self, name: &str) -> Result<()> {
+        let repo = self
+            .repos
+            .iter_mut()
+            .find(|repo| repo.name == name)
+            .ok_or_else(|| Error::UnknownRepository(name.to_string()))?;
+        repo.lease = None;
+        self.save()
+    }
+
+    /// Deletes every free repository, on GitHub and locally, and drops it
+    /// from the pool. Leased repositories are kept. Returns the names of
+    /// the deleted repositories; if one cannot be deleted, those deleted
+    /// before it are still dropped.
+    pub fn cleanup(&mut self) -> Result<Vec<String>> {
+        let mut deleted = Vec::new();
+        let mut failed = None;
+        for repo in self.repos.iter().filter(|repo| repo.lease.is_none()) {
+            if let Err(e) = delete(&self.full_name(repo), &repo.path) {
+                failed = Some(e);
+                break;
+            }
+            deleted.push(repo.name.clone());
+        }
+        self.repos.retain(|repo| !deleted.contains(&repo.name));
+        self.save()?;
+        match failed {
+            Some(e) => Err(e),
+            None => Ok(deleted),
+        }
+    }
+
+    /// Writes the pool to its directory.
+    fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(&self.repos)
+            .map_err(|e| Error::Config(format!("failed to serialize repo pool: {}", e)))?;
+        std::fs::write(self.dir.join(POOL_FILE), json)?;
+        Ok(())
+    }
+}
+
+/// Deletes the GitHub repository `full_name` and its clone at `path`.
+fn delete(full_name: &str, path: &Path) -> Result<()> {
+    let args = ["repo", "delete", full_name, "--yes"];
+    let output = Command::new("gh").args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::gh_command(&args, &output)
+            .context(format!("failed to delete pooled repository {}", full_name)));
+    }
+    if path.exists() {
+        std::fs::remove_dir_all(path)?;
+    }
+    tracing::info!(repo = %full_name, "deleted pooled repository");
+    Ok(())
+}
+
+/// Force-resets `repo` to its initial commit, locally and on the remote,
+/// and deletes every other branch and all untracked files.
+fn reset(repo: &PooledRepo) -> Result<()> {
+    let dir = &repo.path;
+    SystemGit.run(dir, &["fetch", "-q", "--prune", "origin"])?;
+    SystemGit.run(
+        dir,
+        &[
+            "checkout",
+            "-q",
+            "-f",
+            "-B",
+            &repo.branch,
+            &repo.initial_commit,
+        ],
+    )?;
+    SystemGit.run(dir, &["clean", "-qfdx"])?;
+    SystemGit.run(
+        dir,
+        &[
+            "push",
+            "-q",
+            "--force",
+            "origin",
+            &format!("HEAD:{}", repo.branch),
+        ],
+    )?;
+
+    let remote = SystemGit.run(
+        dir,
+        &[
+            "for-each-ref",
+            "--format=%(refname:strip=3)",
+            "refs/remotes/origin",
+        ],
+    )?;
+    for branch in remote.lines().filter(|b| *b != repo.branch && *b != "HEAD") {
+        SystemGit.run(dir, &["push", "-q", "origin", "--delete", branch])?;
+    }
+    let local = SystemGit.run(
+        dir,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )?;
+    for branch in local.lines().filter(|b| *b != repo.branch) {
+        SystemGit.run(dir, &["branch", "-q", "-D", branch])?;
+    }
+    tracing::debug!(repo = %repo.name, commit = %repo.initial_commit, "reset pooled repository");
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const IDENTITY: [&str; 4] = ["-c", "user.email=test@test.com", "-c", "user.name=Test"];
+
+    /// Creates a bare remote under `root` and a pushed clone of it in the
+    /// pool directory, like `gh repo create --clone` would.
+    fn create(root: &Path, pool: &Path, name: &str) -> Result<PathBuf> {
+        let remote = root.join("remotes").join(name);
+        std::fs::create_dir_all(&remote)?;
+        SystemGit.run(&remote, &["init", "-q", "--bare", "-b", "main"])?;
+        let clone = pool.join(name);
+        SystemGit.run(
+            root,
+            &[
+                "clone",
+                "-q",
+                &remote.to_string_lossy(),
+                &clone.to_string_lossy(),
+            ],
+        )?;
+        SystemGit.run(&clone, &["checkout", "-q", "-b", "main"])?;
+        std::fs::write(clone.join("README.md"), "# Fixture\n")?;
+        SystemGit.run(&clone, &["add", "-A"])?;
+        SystemGit.run(
+            &clone,
+            &[&IDENTITY[..], &["commit", "-q", "-m", "Initial"]].concat(),
+        )?;
+        SystemGit.run(&clone, &["push", "-q", "-u", "origin", "main"])?;
+        Ok(clone)
+    }
+
+    fn config(max_size: usize, lease_secs: u64) -> PoolConfig {
+        PoolConfig {
+            max_size,
+            lease_secs,
+            ..PoolConfig::default()
+        }
+    }
+
+    #[test]
+    fn released_repos_are_reset_and_reused() {
+        let root = TempDir::new().unwrap();
+        let dir = root.path().join("pool");
+        let mut pool = RepoPool::open(&dir, "acme", config(2, 3600)).unwrap();
+
+        let repo = pool
+            .lease_with("fixture-1", |name| create(root.path(), &dir, name))
+            .unwrap();
+        assert!(repo.name.starts_with("e2e-pool-"));
+        SystemGit
+            .run(&repo.path, &["checkout", "-q", "-b", "feature"])
+            .unwrap();
+        std::fs::write(repo.path.join("lib.rs"), "fn main() {}\n").unwrap();
+        SystemGit.run(&repo.path, &["add", "-A"]).unwrap();
+        SystemGit
+            .run(
+                &repo.path,
+                &[&IDENTITY[..], &["commit", "-q", "-m", "Work"]].concat(),
+            )
+            .unwrap();
+        SystemGit
+            .run(&repo.path, &["push", "-q", "origin", "feature"])
+            .unwrap();
+        std::fs::write(repo.path.join("scratch.txt"), "untracked\n").unwrap();
+        pool.release(&repo.name).unwrap();
+
+        let mut pool = RepoPool::open(&dir, "acme", config(2, 3600)).unwrap();
+        let again = pool
+            .lease_with("fixture-2", |_| {
+                panic!("a free repository should be reused")
+            })
+            .unwrap();
+        assert_eq!(again.name, repo.name);
+        assert_eq!(again.lease.as_ref().unwrap().holder, "fixture-2");
+        assert_eq!(
+            SystemGit
+                .run(&again.path, &["rev-parse", "HEAD"])
+                .unwrap()
+                .trim(),
+            repo.initial_commit
+        );
+        assert!(!again.path.join("lib.rs").exists());
+        assert!(!again.path.join("scratch.txt").exists());
+        let remote_branches = SystemGit
+            .run(&again.path, &["ls-remote", "--heads", "origin"])
+            .unwrap();
+        assert!(!remote_branches.contains("feature"));
+        assert_eq!(pool.full_name(&again), format!("acme/{}", repo.name));
+    }
+
+    #[test]
+    fn pool_is_capped_until_leases_expire() {
+        let root = TempDir::new().unwrap();
+        let dir = root.path().join("pool");
+        let mut pool = RepoPool::open(&dir, "acme", config(1, 3600)).unwrap();
+        pool.lease_with("a", |name| create(root.path(), &dir, name))
+            .unwrap();
+
+        let err = pool
+            .lease_with("b", |name| create(root.path(), &dir, name))
+            .unwrap_err();
+        assert!(err.to_string().contains("repo pool is full"));
+
+        let mut expiring = RepoPool::open(&dir, "acme", config(1, 0)).unwrap();
+        expiring.repos[0].lease.as_mut().unwrap().expires_at = 0;
+        let repo = expiring
+            .lease_with("b", |_| panic!("the expired lease should be reclaimed"))
+            .unwrap();
+        assert_eq!(repo.lease.unwrap().holder, "b");
+        assert!(expiring.release("missing").is_err());
+    }
+}
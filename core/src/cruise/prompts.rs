@@ -1,6 +1,7 @@
 //! Prompt builders for cruise-control planning.
 
 use super::planner::ReviewPhase;
+use crate::prompt::{PromptTemplates, TemplateVars};
 
 /// Builder for creating primary LLM plan generation prompts.
 pub struct PlanPromptBuilder {
@@ -31,57 +32,22 @@ impl PlanPromptBuilder {
         self
     }
 
-    /// Builds the prompt.
+    /// Builds the prompt using the built-in template.
     pub fn build(&self) -> String {
-        let mut prompt = String::new();
-
-        if self.previous_plan.is_some() {
-            prompt.push_str("## Plan Refinement Request\n\n");
-            prompt.push_str("Refine the plan based on the review feedback.\n\n");
-        } else {
-            prompt.push_str("## Plan Generation Request\n\n");
-            prompt.push_str("Create a dependency-aware implementation plan.\n\n");
-        }
-
-        prompt.push_str("### Original Request\n\n");
-        prompt.push_str(&self.user_prompt);
-        prompt.push_str("\n\n");
+        self.build_with(&PromptTemplates::builtin())
+    }
 
+    /// Builds the prompt using the given template registry.
+    pub fn build_with(&self, templates: &PromptTemplates) -> String {
+        let mut vars = TemplateVars::new();
+        vars.insert("user_prompt", self.user_prompt.clone());
         if let Some(plan) = &self.previous_plan {
-            prompt.push_str("### Current Plan\n\n");
-            prompt.push_str("```json\n");
-            prompt.push_str(plan);
-            prompt.push_str("\n```\n\n");
+            vars.insert("previous_plan", plan.clone());
         }
-
         if let Some(feedback) = &self.review_feedback {
-            prompt.push_str("### Review Feedback to Address\n\n");
-            prompt.push_str(feedback);
-            prompt.push_str("\n\n");
+            vars.insert("review_feedback", feedback.clone());
         }
-
-        prompt.push_str("### Output Format\n\n");
-        prompt.push_str("Respond with a JSON object:\n");
-        prompt.push_str("```json\n");
-        prompt.push_str("{\n");
-        prompt.push_str("  \"title\": \"Plan title\",\n");
-        prompt.push_str("  \"overview\": \"2-3 sentence overview\",\n");
-        prompt.push_str("  \"tasks\": [\n");
-        prompt.push_str("    {\n");
-        prompt.push_str("      \"id\": \"CRUISE-001\",\n");
-        prompt.push_str("      \"subject\": \"Task title\",\n");
-        prompt.push_str("      \"description\": \"Detailed description\",\n");
-        prompt.push_str("      \"blocked_by\": [],\n");
-        prompt.push_str("      \"component\": \"component-name\",\n");
-        prompt.push_str("      \"complexity\": \"low|medium|high\",\n");
-        prompt.push_str("      \"acceptance_criteria\": [\"criterion 1\", \"criterion 2\"]\n");
-        prompt.push_str("    }\n");
-        prompt.push_str("  ],\n");
-        prompt.push_str("  \"risks\": [\"risk 1\", \"risk 2\"]\n");
-        prompt.push_str("}\n");
-        prompt.push_str("```\n");
-
-        prompt
+        templates.render(PromptTemplates::PLAN, &vars)
     }
 }
 
@@ -100,40 +66,17 @@ impl PlanReviewPromptBuilder {
         }
     }
 
-    /// Builds the review prompt.
+    /// Builds the review prompt using the built-in template.
     pub fn build(&self) -> String {
-        let mut prompt = String::new();
-
-        prompt.push_str("## Plan Review Request\n\n");
-        prompt.push_str("Review the following implementation plan.\n\n");
-
-        prompt.push_str("### Review Focus\n\n");
-        prompt.push_str(self.phase.focus_description());
-        prompt.push_str("\n\n");
-
-        prompt.push_str("### Plan to Review\n\n");
-        prompt.push_str("```json\n");
-        prompt.push_str(&self.plan_json);
-        prompt.push_str("\n```\n\n");
-
-        prompt.push_str("### Response Format\n\n");
-        prompt.push_str("Respond with a JSON object:\n");
-        prompt.push_str("```json\n");
-        prompt.push_str("{\n");
-        prompt.push_str("  \"verdict\": \"approved\" | \"needs_changes\",\n");
-        prompt.push_str("  \"suggestions\": [\n");
-        prompt.push_str("    {\n");
-        prompt.push_str("      \"category\": \"security|feasibility|granularity|dependency\",\n");
-        prompt.push_str("      \"task_id\": \"CRUISE-001 or null for general\",\n");
-        prompt.push_str("      \"issue\": \"Description of the issue\",\n");
-        prompt.push_str("      \"suggestion\": \"How to address it\"\n");
-        prompt.push_str("    }\n");
-        prompt.push_str("  ],\n");
-        prompt.push_str("  \"summary\": \"Brief summary of review\"\n");
-        prompt.push_str("}\n");
-        prompt.push_str("```\n");
-
-        prompt
+        self.build_with(&PromptTemplates::builtin())
+    }
+
+    /// Builds the review prompt using the given template registry.
+    pub fn build_with(&self, templates: &PromptTemplates) -> String {
+        let mut vars = TemplateVars::new();
+        vars.insert("focus", self.phase.focus_description().to_string());
+        vars.insert("plan_json", self.plan_json.clone());
+        templates.render(PromptTemplates::PLAN_REVIEW, &vars)
     }
 }
 
@@ -164,6 +107,16 @@ mod tests {
         assert!(prompt.contains("Add error handling task"));
     }
 
+    #[test]
+    fn plan_prompt_builder_refinement_omits_generation_header() {
+        let prompt = PlanPromptBuilder::new("Build a REST API")
+            .with_previous_plan("{}")
+            .build();
+
+        assert!(!prompt.contains("Plan Generation Request"));
+        assert!(!prompt.contains("Review Feedback to Address"));
+    }
+
     #[test]
     fn plan_review_prompt_builder_includes_phase_focus() {
         let prompt =
@@ -3,7 +3,12 @@
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+use super::config::TestLevel;
+use super::coverage::{CoverageConfig, CoverageDelta, COVERAGE_CATEGORY};
+use super::docs::DocsOutcome;
 use super::task::TaskStatus;
+use super::test_first::TEST_INTEGRITY_CATEGORY;
+use crate::audit::SpawnObservability;
 
 /// Result of the planning phase.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +27,9 @@ pub struct PlanResult {
     pub plan_file: Option<String>,
     /// Error message if failed.
     pub error: Option<String>,
+    /// What each planning spawn did.
+    #[serde(default)]
+    pub observability: Vec<SpawnObservability>,
 }
 
 /// Result of a single task execution.
@@ -54,6 +62,9 @@ pub struct BuildResult {
     pub completed_count: usize,
     /// Count of blocked tasks.
     pub blocked_count: usize,
+    /// What each task's spawns did.
+    #[serde(default)]
+    pub observability: Vec<SpawnObservability>,
 }
 
 impl BuildResult {
@@ -130,6 +141,18 @@ pub enum AdherenceStatus {
     Deviated,
 }
 
+impl AdherenceStatus {
+    /// Describes the status for a sentence such as "the task was ...".
+    pub fn description(self) -> &'static str {
+        match self {
+            AdherenceStatus::Implemented => "implemented",
+            AdherenceStatus::Partial => "partially implemented",
+            AdherenceStatus::Missing => "not implemented",
+            AdherenceStatus::Deviated => "implemented differently than planned",
+        }
+    }
+}
+
 /// Plan adherence check for a task.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdherenceCheck {
@@ -160,6 +183,16 @@ pub struct ValidationResult {
     pub duration: Duration,
     /// Path to the audit report.
     pub report_file: Option<String>,
+    /// Line coverage before and after each task.
+    #[serde(default)]
+    pub coverage: Vec<CoverageDelta>,
+    /// What each validation spawn did.
+    #[serde(default)]
+    pub observability: Vec<SpawnObservability>,
+    /// Where unfinished tasks were filed as follow-ups: beads issue paths
+    /// and GitHub issue URLs.
+    #[serde(default)]
+    pub follow_ups: Vec<String>,
 }
 
 impl ValidationResult {
@@ -183,6 +216,37 @@ impl ValidationResult {
             .filter(|c| c.status == AdherenceStatus::Implemented)
             .count()
     }
+
+    /// Records a task's coverage, failing validation when it breaks the
+    /// thresholds in `config`.
+    pub fn record_coverage(&mut self, delta: CoverageDelta, config: &CoverageConfig) {
+        if let Some(finding) = config.check(&delta) {
+            self.findings.push(finding);
+            self.success = false;
+        }
+        self.coverage.push(delta);
+    }
+
+    /// Returns whether the results meet `level`. Coverage below the
+    /// configured threshold fails every level; phase completion is checked
+    /// by the caller.
+    pub fn meets(&self, level: TestLevel) -> bool {
+        if self.has_findings_in(COVERAGE_CATEGORY) {
+            return false;
+        }
+
+        let tests_pass = self.functional_tests.iter().all(|t| t.passed);
+        match level {
+            TestLevel::Basic => true,
+            TestLevel::Functional => tests_pass,
+            TestLevel::Strict => tests_pass && self.critical_count() == 0,
+            TestLevel::TestFirst => tests_pass && !self.has_findings_in(TEST_INTEGRITY_CATEGORY),
+        }
+    }
+
+    fn has_findings_in(&self, category: &str) -> bool {
+        self.findings.iter().any(|f| f.category == category)
+    }
 }
 
 /// Overall result of a cruise-control run.
@@ -198,12 +262,137 @@ pub struct CruiseResult {
     pub build_result: Option<BuildResult>,
     /// Validation phase result.
     pub validation_result: Option<ValidationResult>,
+    /// Documentation pass result, if the pass ran.
+    #[serde(default)]
+    pub docs_result: Option<DocsOutcome>,
     /// Total duration.
     pub total_duration: Duration,
     /// Summary message.
     pub summary: String,
 }
 
+impl CruiseResult {
+    /// Returns what every spawn of the run did, tagged with its phase.
+    pub fn combined_observability(&self) -> CombinedObservability {
+        let phases = [
+            (
+                CruisePhase::Plan,
+                self.plan_result
+                    .as_ref()
+                    .map(|r| r.observability.as_slice()),
+            ),
+            (
+                CruisePhase::Build,
+                self.build_result
+                    .as_ref()
+                    .map(|r| r.observability.as_slice()),
+            ),
+            (
+                CruisePhase::Validate,
+                self.validation_result
+                    .as_ref()
+                    .map(|r| r.observability.as_slice()),
+            ),
+            (
+                CruisePhase::Docs,
+                self.docs_result
+                    .as_ref()
+                    .map(|r| std::slice::from_ref(&r.observability)),
+            ),
+        ];
+        let mut combined = CombinedObservability::default();
+        for (phase, records) in phases {
+            for observability in records.into_iter().flatten() {
+                combined.push(phase, observability.clone());
+            }
+        }
+        combined
+    }
+}
+
+/// Phase of a cruise run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CruisePhase {
+    /// Planning and plan review.
+    Plan,
+    /// Task execution.
+    Build,
+    /// Validation of the built result.
+    Validate,
+    /// Documentation pass after approval.
+    Docs,
+}
+
+impl CruisePhase {
+    /// Every phase, in run order.
+    pub const ALL: [CruisePhase; 4] = [
+        CruisePhase::Plan,
+        CruisePhase::Build,
+        CruisePhase::Validate,
+        CruisePhase::Docs,
+    ];
+
+    /// Returns the phase's name as used in reports.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CruisePhase::Plan => "plan",
+            CruisePhase::Build => "build",
+            CruisePhase::Validate => "validate",
+            CruisePhase::Docs => "docs",
+        }
+    }
+}
+
+/// A spawn's observability record and the phase it ran in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseObservability {
+    /// Phase the spawn ran in.
+    pub phase: CruisePhase,
+    /// What the spawn did.
+    pub observability: SpawnObservability,
+}
+
+/// Observability of a whole cruise run, each record tagged with its phase.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CombinedObservability {
+    /// Records in run order.
+    pub records: Vec<PhaseObservability>,
+}
+
+impl CombinedObservability {
+    /// Adds a spawn's record for `phase`.
+    pub fn push(&mut self, phase: CruisePhase, observability: SpawnObservability) {
+        self.records.push(PhaseObservability {
+            phase,
+            observability,
+        });
+    }
+
+    /// Returns whether nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns everything recorded in `phase`, merged into one record.
+    pub fn phase(&self, phase: CruisePhase) -> SpawnObservability {
+        self.merge(|p| p == phase)
+    }
+
+    /// Returns everything recorded, merged into one record.
+    pub fn merged(&self) -> SpawnObservability {
+        self.merge(|_| true)
+    }
+
+    fn merge(&self, include: impl Fn(CruisePhase) -> bool) -> SpawnObservability {
+        let mut merged = SpawnObservability::default();
+        for record in self.records.iter().filter(|r| include(r.phase)) {
+            merged.merge(record.observability.clone());
+        }
+        merged
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +421,7 @@ mod tests {
             duration: Duration::from_secs(90),
             completed_count: 1,
             blocked_count: 1,
+            observability: Vec::new(),
         };
 
         assert_eq!(result.success_rate(), 50.0);
@@ -246,6 +436,7 @@ mod tests {
             duration: Duration::from_secs(0),
             completed_count: 0,
             blocked_count: 0,
+            observability: Vec::new(),
         };
 
         assert_eq!(result.success_rate(), 100.0);
@@ -278,6 +469,9 @@ mod tests {
             quality_score: 5.0,
             duration: Duration::from_secs(300),
             report_file: None,
+            coverage: vec![],
+            observability: Vec::new(),
+            follow_ups: vec![],
         };
 
         assert_eq!(result.critical_count(), 1);
@@ -308,11 +502,82 @@ mod tests {
             quality_score: 8.0,
             duration: Duration::from_secs(60),
             report_file: None,
+            coverage: vec![],
+            observability: Vec::new(),
+            follow_ups: vec![],
         };
 
         assert_eq!(result.tests_passed(), 1);
     }
 
+    #[test]
+    fn validation_result_meets_test_levels() {
+        let finding = |severity, category: &str| AuditFinding {
+            severity,
+            category: category.to_string(),
+            description: "finding".to_string(),
+            file: None,
+            line: None,
+            suggestion: None,
+        };
+        let mut result = ValidationResult {
+            success: true,
+            functional_tests: vec![],
+            adherence_checks: vec![],
+            findings: vec![finding(FindingSeverity::Critical, "security")],
+            quality_score: 6.0,
+            duration: Duration::from_secs(60),
+            report_file: None,
+            coverage: vec![],
+            observability: Vec::new(),
+            follow_ups: vec![],
+        };
+
+        assert!(result.meets(TestLevel::Functional));
+        assert!(!result.meets(TestLevel::Strict));
+        assert!(result.meets(TestLevel::TestFirst));
+
+        result
+            .findings
+            .push(finding(FindingSeverity::Critical, TEST_INTEGRITY_CATEGORY));
+        assert!(!result.meets(TestLevel::TestFirst));
+    }
+
+    #[test]
+    fn validation_result_records_coverage() {
+        let mut result = ValidationResult {
+            success: true,
+            functional_tests: vec![],
+            adherence_checks: vec![],
+            findings: vec![],
+            quality_score: 9.0,
+            duration: Duration::from_secs(60),
+            report_file: None,
+            coverage: vec![],
+            observability: Vec::new(),
+            follow_ups: vec![],
+        };
+        let config = CoverageConfig {
+            min_percent: Some(70.0),
+            ..Default::default()
+        };
+        let delta = |task_id: &str, after| CoverageDelta {
+            task_id: task_id.to_string(),
+            before: Some(75.0),
+            after,
+        };
+
+        result.record_coverage(delta("CRUISE-001", 78.0), &config);
+        assert!(result.success);
+        assert!(result.meets(TestLevel::Basic));
+
+        result.record_coverage(delta("CRUISE-002", 65.0), &config);
+        assert_eq!(result.coverage.len(), 2);
+        assert!(!result.success);
+        assert_eq!(result.findings[0].category, COVERAGE_CATEGORY);
+        assert!(!result.meets(TestLevel::Basic));
+    }
+
     #[test]
     fn finding_severity_serializes() {
         assert_eq!(
@@ -332,4 +597,60 @@ mod tests {
             "\"deviated\""
         );
     }
+
+    #[test]
+    fn combined_observability_keeps_phases_apart() {
+        let record = |tool: &str| SpawnObservability {
+            tool_calls: vec![crate::audit::ToolCallRecord::now(tool, "{}")],
+            ..Default::default()
+        };
+        let result = CruiseResult {
+            success: true,
+            prompt: "Build an API".to_string(),
+            plan_result: Some(PlanResult {
+                success: true,
+                iterations: 1,
+                task_count: 2,
+                pr_url: None,
+                duration: Duration::from_secs(10),
+                plan_file: None,
+                error: None,
+                observability: vec![record("Read")],
+            }),
+            build_result: Some(BuildResult {
+                success: true,
+                task_results: vec![],
+                max_parallelism: 2,
+                duration: Duration::from_secs(60),
+                completed_count: 2,
+                blocked_count: 0,
+                observability: vec![record("Edit"), record("Bash")],
+            }),
+            validation_result: None,
+            docs_result: Some(DocsOutcome {
+                commit: Some("abc".to_string()),
+                files: Vec::new(),
+                observability: record("Write"),
+            }),
+            total_duration: Duration::from_secs(70),
+            summary: String::new(),
+        };
+
+        let combined = result.combined_observability();
+        let tools = |o: SpawnObservability| -> Vec<String> {
+            o.tool_calls.into_iter().map(|c| c.tool).collect()
+        };
+        assert_eq!(tools(combined.phase(CruisePhase::Plan)), vec!["Read"]);
+        assert_eq!(
+            tools(combined.phase(CruisePhase::Build)),
+            vec!["Edit", "Bash"]
+        );
+        assert!(tools(combined.phase(CruisePhase::Validate)).is_empty());
+        assert_eq!(tools(combined.phase(CruisePhase::Docs)), vec!["Write"]);
+        assert_eq!(combined.merged().tool_calls.len(), 4);
+        assert_eq!(
+            serde_json::to_value(&combined.records[0]).unwrap()["phase"],
+            "plan"
+        );
+    }
 }
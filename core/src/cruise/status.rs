@@ -0,0 +1,435 @@
+//! Live status of cruise-control runs.
+//!
+//! A running cruise writes a [`CruiseStatus`] snapshot as it moves through
+//! phases, completes tasks, opens PRs, and hears from the LLM. `cruise
+//! status` reads the latest snapshot back, together with the run's
+//! cancellation [`Checkpoint`] if it was stopped.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::task::CruisePlan;
+use crate::cancel::Checkpoint;
+use crate::error::{Error, Result};
+use crate::team::ReviewSuggestion;
+
+/// Directory, relative to the repository, run status is written to.
+pub const STATUS_DIR: &str = ".improbability-drive/runs";
+
+/// Task completion counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskCounts {
+    /// All tasks in the plan.
+    pub total: usize,
+    /// Tasks not started.
+    pub pending: usize,
+    /// Tasks being worked on.
+    pub in_progress: usize,
+    /// Tasks finished.
+    pub completed: usize,
+    /// Tasks that failed or are waiting on a failed task.
+    pub blocked: usize,
+}
+
+/// Snapshot of a cruise run's progress.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CruiseStatus {
+    /// Identifier of the run.
+    pub run_id: String,
+    /// The run's original prompt.
+    pub prompt: String,
+    /// Current phase (e.g., "planning", "building", "review:security").
+    pub phase: String,
+    /// When the run started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    /// When the snapshot was last updated, in seconds since the Unix epoch.
+    pub updated_at: u64,
+    /// Time budget for the whole run, in seconds.
+    #[serde(default)]
+    pub budget_secs: Option<u64>,
+    /// Task completion counts.
+    #[serde(default)]
+    pub tasks: TaskCounts,
+    /// Review findings not yet addressed.
+    #[serde(default)]
+    pub open_findings: Vec<ReviewSuggestion>,
+    /// PRs opened by the run.
+    #[serde(default)]
+    pub pr_urls: Vec<String>,
+    /// When the LLM last produced output, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub last_activity_at: Option<u64>,
+    /// Why the run was cancelled, if it was.
+    #[serde(default)]
+    pub cancelled: Option<String>,
+    /// Process running the cruise.
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Start time and command line of `pid`, so a reused PID is not
+    /// mistaken for the run (see [`process_identity`]).
+    #[serde(default)]
+    pub process: Option<String>,
+    /// Work branches created by the run.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Sandboxes created by the run.
+    #[serde(default)]
+    pub sandboxes: Vec<PathBuf>,
+    /// Other repositories the run created branches in.
+    #[serde(default)]
+    pub repos: Vec<PathBuf>,
+    /// Beads issue IDs of the run's plan.
+    #[serde(default)]
+    pub task_ids: Vec<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl CruiseStatus {
+    /// Creates the status of a run starting now.
+    pub fn new(run_id: impl Into<String>, prompt: impl Into<String>) -> Self {
+        let now = now();
+        let pid = std::process::id();
+        Self {
+            run_id: run_id.into(),
+            prompt: prompt.into(),
+            phase: "planning".to_string(),
+            started_at: now,
+            updated_at: now,
+            budget_secs: None,
+            tasks: TaskCounts::default(),
+            open_findings: Vec::new(),
+            pr_urls: Vec::new(),
+            last_activity_at: None,
+            cancelled: None,
+            pid: Some(pid),
+            process: process_identity(pid),
+            branches: Vec::new(),
+            sandboxes: Vec::new(),
+            repos: Vec::new(),
+            task_ids: Vec::new(),
+        }
+    }
+
+    /// Sets the time budget for the run.
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget_secs = Some(budget.as_secs());
+        self
+    }
+
+    /// Moves the run to `phase`.
+    pub fn set_phase(&mut self, phase: impl Into<String>) {
+        self.phase = phase.into();
+        self.updated_at = now();
+    }
+
+    /// Updates the task counts from `plan` and records its task IDs.
+    pub fn record_plan(&mut self, plan: &CruisePlan) {
+        for task in &plan.tasks {
+            if !self.task_ids.contains(&task.id) {
+                self.task_ids.push(task.id.clone());
+            }
+        }
+        let (pending, in_progress, completed, blocked) = plan.task_counts();
+        self.tasks = TaskCounts {
+            total: plan.tasks.len(),
+            pending,
+            in_progress,
+            completed,
+            blocked,
+        };
+        self.updated_at = now();
+    }
+
+    /// Replaces the open review findings.
+    pub fn set_open_findings(&mut self, findings: Vec<ReviewSuggestion>) {
+        self.open_findings = findings;
+        self.updated_at = now();
+    }
+
+    /// Records a PR opened by the run.
+    pub fn add_pr(&mut self, url: impl Into<String>) {
+        let url = url.into();
+        if !self.pr_urls.contains(&url) {
+            self.pr_urls.push(url);
+        }
+        self.updated_at = now();
+    }
+
+    /// Records a work branch created by the run.
+    pub fn add_branch(&mut self, branch: impl Into<String>) {
+        let branch = branch.into();
+        if !self.branches.contains(&branch) {
+            self.branches.push(branch);
+        }
+        self.updated_at = now();
+    }
+
+    /// Records a sandbox created by the run.
+    pub fn add_sandbox(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        if !self.sandboxes.contains(&path) {
+            self.sandboxes.push(path);
+        }
+        self.updated_at = now();
+    }
+
+    /// Records another repository the run creates branches in.
+    pub fn add_repo(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        if !self.repos.contains(&path) {
+            self.repos.push(path);
+        }
+        self.updated_at = now();
+    }
+
+    /// Records LLM activity now.
+    pub fn touch(&mut self) {
+        let now = now();
+        self.last_activity_at = Some(now);
+        self.updated_at = now;
+    }
+
+    /// Returns where this status is stored under `repo`.
+    pub fn path(&self, repo: &Path) -> PathBuf {
+        repo.join(STATUS_DIR).join(format!("{}.json", self.run_id))
+    }
+
+    /// Writes the status under `repo`, returning its path.
+    pub fn save(&self, repo: &Path) -> Result<PathBuf> {
+        let path = self.path(repo);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("failed to serialize run status: {}", e)))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// Loads the status of `run_id` from `repo`, marking it cancelled if a
+    /// cancellation checkpoint exists for it.
+    pub fn load(repo: &Path, run_id: &str) -> Result<Self> {
+        let path = repo.join(STATUS_DIR).join(format!("{}.json", run_id));
+        let json = std::fs::read_to_string(&path)?;
+        let mut status: Self = serde_json::from_str(&json)
+            .map_err(|e| Error::Config(format!("invalid run status {}: {}", path.display(), e)))?;
+
+        if let Ok(checkpoint) = Checkpoint::load(repo, run_id) {
+            status.phase = checkpoint.phase;
+            status.cancelled = Some(checkpoint.reason);
+            if let Some(pr) = checkpoint.pr_url {
+                if !status.pr_urls.contains(&pr) {
+                    status.pr_urls.push(pr);
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    /// Loads every run recorded under `repo`, in no particular order.
+    pub fn all(repo: &Path) -> Result<Vec<Self>> {
+        let dir = repo.join(STATUS_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut runs = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(run_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            runs.push(Self::load(repo, run_id)?);
+        }
+        Ok(runs)
+    }
+
+    /// Loads the most recently updated run under `repo`, if any.
+    pub fn latest(repo: &Path) -> Result<Option<Self>> {
+        let mut latest: Option<Self> = None;
+        for status in Self::all(repo)? {
+            if latest
+                .as_ref()
+                .is_none_or(|l| status.updated_at > l.updated_at)
+            {
+                latest = Some(status);
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Formats the status for a terminal, as of `now` (seconds since the
+    /// Unix epoch).
+    pub fn render(&self, now: u64) -> String {
+        let elapsed = now.saturating_sub(self.started_at);
+        let mut out = format!("Run:      {}\n", self.run_id);
+        match &self.cancelled {
+            Some(reason) => out.push_str(&format!(
+                "Phase:    {} (cancelled: {})\n",
+                self.phase, reason
+            )),
+            None => out.push_str(&format!("Phase:    {}\n", self.phase)),
+        }
+        out.push_str(&format!(
+            "Tasks:    {}/{} completed, {} in progress, {} blocked\n",
+            self.tasks.completed, self.tasks.total, self.tasks.in_progress, self.tasks.blocked
+        ));
+        match self.budget_secs {
+            Some(budget) => out.push_str(&format!(
+                "Elapsed:  {} of {} budgeted\n",
+                format_secs(elapsed),
+                format_secs(budget)
+            )),
+            None => out.push_str(&format!("Elapsed:  {}\n", format_secs(elapsed))),
+        }
+        match self.last_activity_at {
+            Some(at) => out.push_str(&format!(
+                "Activity: {} ago\n",
+                format_secs(now.saturating_sub(at))
+            )),
+            None => out.push_str("Activity: none yet\n"),
+        }
+
+        if !self.pr_urls.is_empty() {
+            out.push_str("\nPRs:\n");
+            for url in &self.pr_urls {
+                out.push_str(&format!("  {}\n", url));
+            }
+        }
+        if !self.open_findings.is_empty() {
+            out.push_str(&format!(
+                "\nOpen findings ({}):\n",
+                self.open_findings.len()
+            ));
+            for finding in &self.open_findings {
+                let location = match finding.line {
+                    Some(line) => format!("{}:{}", finding.file, line),
+                    None => finding.file.clone(),
+                };
+                out.push_str(&format!(
+                    "  [{:?}] {} {}\n",
+                    finding.severity, location, finding.issue
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Returns the start time and command line of process `pid` as `ps`
+/// reports them, or `None` if it is not running.
+pub(crate) fn process_identity(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "lstart=", "-o", "command=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let identity = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !identity.is_empty()).then_some(identity)
+}
+
+/// Formats seconds as e.g. `1h 02m`, `4m 05s`, or `12s`.
+pub(crate) fn format_secs(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h {:02}m", s / 3600, (s % 3600) / 60),
+        s if s >= 60 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::{CruiseTask, TaskStatus};
+    use crate::team::ReviewSeverity;
+    use tempfile::TempDir;
+
+    fn status() -> CruiseStatus {
+        let mut status =
+            CruiseStatus::new("run-1", "Build an API").with_budget(Duration::from_secs(3600));
+        let mut plan = CruisePlan::new("Build an API");
+        plan.tasks = vec![
+            CruiseTask::new("CRUISE-001", "Setup"),
+            CruiseTask::new("CRUISE-002", "Auth"),
+            CruiseTask::new("CRUISE-003", "Docs"),
+        ];
+        plan.tasks[0].status = TaskStatus::Completed;
+        plan.tasks[1].status = TaskStatus::InProgress;
+        status.set_phase("building");
+        status.record_plan(&plan);
+        status.add_pr("https://github.com/o/r/pull/7");
+        status.set_open_findings(vec![ReviewSuggestion {
+            file: "src/auth.rs".to_string(),
+            line: Some(12),
+            issue: "Token is logged".to_string(),
+            suggestion: "Redact it".to_string(),
+            severity: ReviewSeverity::High,
+        }]);
+        status
+    }
+
+    #[test]
+    fn status_round_trips_and_picks_latest() {
+        let repo = TempDir::new().unwrap();
+        let mut older = CruiseStatus::new("run-0", "old");
+        older.updated_at = 1;
+        older.save(repo.path()).unwrap();
+        status().save(repo.path()).unwrap();
+
+        let latest = CruiseStatus::latest(repo.path()).unwrap().unwrap();
+
+        assert_eq!(latest.run_id, "run-1");
+        assert_eq!(latest.phase, "building");
+        assert_eq!(latest.tasks.completed, 1);
+        assert_eq!(latest.tasks.total, 3);
+        assert_eq!(latest.open_findings.len(), 1);
+        assert_eq!(latest.task_ids, ["CRUISE-001", "CRUISE-002", "CRUISE-003"]);
+    }
+
+    #[test]
+    fn latest_is_none_without_runs() {
+        let repo = TempDir::new().unwrap();
+        assert!(CruiseStatus::latest(repo.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn load_merges_cancellation_checkpoint() {
+        let repo = TempDir::new().unwrap();
+        status().save(repo.path()).unwrap();
+        let mut checkpoint = Checkpoint::new("run-1", "review:security", "Build an API", "ctrl-c");
+        checkpoint.pr_url = Some("https://github.com/o/r/pull/8".to_string());
+        checkpoint.save(repo.path()).unwrap();
+
+        let status = CruiseStatus::load(repo.path(), "run-1").unwrap();
+
+        assert_eq!(status.phase, "review:security");
+        assert_eq!(status.cancelled.as_deref(), Some("ctrl-c"));
+        assert_eq!(status.pr_urls.len(), 2);
+    }
+
+    #[test]
+    fn render_shows_progress_against_budget() {
+        let mut status = status();
+        status.last_activity_at = Some(status.started_at + 125);
+
+        let text = status.render(status.started_at + 130);
+
+        assert!(text.contains("Phase:    building"));
+        assert!(text.contains("Tasks:    1/3 completed, 1 in progress, 0 blocked"));
+        assert!(text.contains("Elapsed:  2m 10s of 1h 00m budgeted"));
+        assert!(text.contains("Activity: 5s ago"));
+        assert!(text.contains("https://github.com/o/r/pull/7"));
+        assert!(text.contains("[High] src/auth.rs:12 Token is logged"));
+    }
+}
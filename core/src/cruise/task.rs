@@ -33,6 +33,56 @@ pub enum TaskComplexity {
     High,
 }
 
+/// Runner configuration for one spawned instance working on a task.
+///
+/// Plans may request several instances per task (e.g., one implementing and
+/// one writing tests); tasks without instances use the build defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpawnInstance {
+    /// LLM runner identifier (e.g., "claude-code").
+    #[serde(default = "default_instance_llm")]
+    pub llm: String,
+    /// Model passed to the runner (e.g., "sonnet").
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Additional CLI arguments for the runner. Only flags listed in
+    /// `building.allowed_cli_params` are passed on.
+    #[serde(default)]
+    pub cli_params: Vec<String>,
+    /// Whether to run the instance through spawn-team review.
+    #[serde(default)]
+    pub use_spawn_team: bool,
+    /// Tools the instance may use, out of the default manifest's (empty
+    /// uses the default manifest).
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Paths the instance may write, within the default manifest's (empty
+    /// uses the default manifest).
+    #[serde(default)]
+    pub writable_paths: Vec<String>,
+    /// Instruction appended to the task prompt for this instance.
+    #[serde(default)]
+    pub focus: Option<String>,
+}
+
+fn default_instance_llm() -> String {
+    "claude-code".to_string()
+}
+
+impl Default for SpawnInstance {
+    fn default() -> Self {
+        Self {
+            llm: default_instance_llm(),
+            model: None,
+            cli_params: Vec::new(),
+            use_spawn_team: false,
+            allowed_tools: Vec::new(),
+            writable_paths: Vec::new(),
+            focus: None,
+        }
+    }
+}
+
 /// A single task in a cruise-control plan.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CruiseTask {
@@ -51,6 +101,9 @@ pub struct CruiseTask {
     /// Component this task belongs to.
     #[serde(default)]
     pub component: Option<String>,
+    /// Repository this task runs in, for multi-repo plans.
+    #[serde(default)]
+    pub repo: Option<String>,
     /// Estimated complexity.
     #[serde(default)]
     pub complexity: TaskComplexity,
@@ -60,6 +113,9 @@ pub struct CruiseTask {
     /// Acceptance criteria.
     #[serde(default)]
     pub acceptance_criteria: Vec<String>,
+    /// Spawned instances that execute this task.
+    #[serde(default)]
+    pub spawn_instances: Vec<SpawnInstance>,
     /// Start timestamp (when moved to InProgress).
     #[serde(default)]
     pub started_at: Option<u64>,
@@ -81,9 +137,11 @@ impl CruiseTask {
             status: TaskStatus::Pending,
             blocked_by: Vec::new(),
             component: None,
+            repo: None,
             complexity: TaskComplexity::Medium,
             parallel_group: None,
             acceptance_criteria: Vec::new(),
+            spawn_instances: Vec::new(),
             started_at: None,
             finished_at: None,
             error: None,
@@ -108,12 +166,24 @@ impl CruiseTask {
         self
     }
 
+    /// Sets the repository the task runs in.
+    pub fn with_repo(mut self, repo: impl Into<String>) -> Self {
+        self.repo = Some(repo.into());
+        self
+    }
+
     /// Sets the complexity.
     pub fn with_complexity(mut self, complexity: TaskComplexity) -> Self {
         self.complexity = complexity;
         self
     }
 
+    /// Sets the spawn instances.
+    pub fn with_spawn_instances(mut self, instances: Vec<SpawnInstance>) -> Self {
+        self.spawn_instances = instances;
+        self
+    }
+
     /// Checks if this task is ready to execute (all dependencies completed).
     pub fn is_ready(&self, completed_tasks: &HashSet<String>) -> bool {
         self.status == TaskStatus::Pending
@@ -369,6 +439,17 @@ mod tests {
         assert!(plan.has_cycle().is_none());
     }
 
+    #[test]
+    fn spawn_instance_deserializes_with_defaults() {
+        let instance: SpawnInstance =
+            serde_json::from_str(r#"{"cli_params": ["--max-turns", "5"]}"#).unwrap();
+
+        assert_eq!(instance.llm, "claude-code");
+        assert_eq!(instance.cli_params, vec!["--max-turns", "5"]);
+        assert!(!instance.use_spawn_team);
+        assert!(instance.allowed_tools.is_empty());
+    }
+
     #[test]
     fn task_status_serializes_correctly() {
         assert_eq!(
@@ -0,0 +1,445 @@
+//! Test-first task execution.
+//!
+//! With [`TestLevel::TestFirst`](super::config::TestLevel::TestFirst) a task
+//! runs as two spawns. The first writes failing tests from the task's
+//! acceptance criteria, which are committed on their own. The second
+//! implements the task and has to make those tests pass. Validation then
+//! flags every committed test file the implementation changed.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use super::executor::InstanceExecution;
+use super::result::{AuditFinding, FindingSeverity};
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::PRManager;
+use crate::prompt::{PromptTemplates, TemplateVars};
+use crate::runner::{LLMOutput, LLMRunner, LLMSpawnConfig};
+use crate::verification::{VerificationConfig, VerificationFailure};
+
+/// Category of the audit findings for test-first tests changed by the
+/// implementation.
+pub const TEST_INTEGRITY_CATEGORY: &str = "test-integrity";
+
+/// Directory names that hold tests.
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "specs"];
+
+/// Returns whether `path` is a test file rather than source code.
+pub fn is_test_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let (dirs, file) = path.rsplit_once('/').unwrap_or(("", &path));
+    let stem = file.split('.').next().unwrap_or_default();
+
+    dirs.split('/').any(|dir| TEST_DIRS.contains(&dir))
+        || stem.starts_with("test_")
+        || ["_test", "_tests", "_spec", "Test", "Tests"]
+            .iter()
+            .any(|suffix| stem.ends_with(suffix))
+        || file.contains(".test.")
+        || file.contains(".spec.")
+}
+
+/// Tests written for a task before it was implemented.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestBaseline {
+    /// Task the tests were written for.
+    pub task_id: String,
+    /// Commit holding the tests.
+    pub commit: String,
+    /// Test files the commit added or changed.
+    pub files: Vec<String>,
+}
+
+impl TestBaseline {
+    /// Records the test files of `commit` in `worktree`.
+    pub fn from_commit(task_id: impl Into<String>, worktree: &Path, commit: &str) -> Result<Self> {
+        let files = SystemGit
+            .run(
+                worktree,
+                &["diff-tree", "--no-commit-id", "--name-only", "-r", commit],
+            )?
+            .lines()
+            .filter(|path| is_test_path(path))
+            .map(str::to_string)
+            .collect();
+
+        Ok(Self {
+            task_id: task_id.into(),
+            commit: commit.to_string(),
+            files,
+        })
+    }
+
+    /// Adds the tests to the implementation prompt `prompt`.
+    pub fn implementation_prompt(&self, prompt: &str) -> String {
+        let mut prompt = format!(
+            "{}\n### Tests\n\nThese tests were written before the implementation. \
+             Make them pass without changing them:\n\n",
+            prompt.trim_end()
+        );
+        for file in &self.files {
+            prompt.push_str(&format!("- `{}`\n", file));
+        }
+        prompt
+    }
+
+    /// Returns the test files changed or deleted in `worktree` since the
+    /// tests were committed, including uncommitted changes.
+    pub fn modified_files(&self, worktree: &Path) -> Result<Vec<String>> {
+        if self.files.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut args = vec!["diff", "--name-only", self.commit.as_str(), "--"];
+        args.extend(self.files.iter().map(String::as_str));
+        Ok(SystemGit
+            .run(worktree, &args)?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Returns a critical finding for each test file changed since the
+    /// tests were committed.
+    pub fn findings(&self, worktree: &Path) -> Result<Vec<AuditFinding>> {
+        Ok(self
+            .modified_files(worktree)?
+            .into_iter()
+            .map(|file| AuditFinding {
+                severity: FindingSeverity::Critical,
+                category: TEST_INTEGRITY_CATEGORY.to_string(),
+                description: format!(
+                    "Task {} changed a test written before its implementation",
+                    self.task_id
+                ),
+                file: Some(file),
+                line: None,
+                suggestion: Some(
+                    "Restore the original test and change the implementation instead".to_string(),
+                ),
+            })
+            .collect())
+    }
+}
+
+/// Outcome of a test-first task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFirstOutcome {
+    /// The tests written first.
+    pub baseline: TestBaseline,
+    /// Whether the implementation spawn exited successfully.
+    pub implemented: bool,
+    /// The test command's failure after implementation, if it failed.
+    pub test_failure: Option<VerificationFailure>,
+    /// Test files the implementation changed.
+    pub modified_tests: Vec<String>,
+}
+
+impl TestFirstOutcome {
+    /// Returns whether the task was implemented, its tests pass, and they
+    /// were left untouched.
+    pub fn passed(&self) -> bool {
+        self.implemented && self.test_failure.is_none() && self.modified_tests.is_empty()
+    }
+}
+
+/// Runs cruise tasks test-first.
+pub struct TestFirstRunner {
+    test_writer: Arc<dyn LLMRunner>,
+    implementer: Arc<dyn LLMRunner>,
+    templates: PromptTemplates,
+    test_command: Option<String>,
+}
+
+impl TestFirstRunner {
+    /// Creates a runner where `test_writer` writes the tests and
+    /// `implementer` implements the task.
+    pub fn new(test_writer: Arc<dyn LLMRunner>, implementer: Arc<dyn LLMRunner>) -> Self {
+        Self {
+            test_writer,
+            implementer,
+            templates: PromptTemplates::builtin(),
+            test_command: None,
+        }
+    }
+
+    /// Sets the prompt templates.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Sets the command that runs the tests. New tests must fail it, and
+    /// the implementation must pass it.
+    pub fn with_test_command(mut self, command: impl Into<String>) -> Self {
+        self.test_command = Some(command.into());
+        self
+    }
+
+    /// Builds the test-writing prompt for an instance of a task.
+    pub fn test_prompt(&self, execution: &InstanceExecution) -> String {
+        let mut vars = TemplateVars::new();
+        vars.insert("task", execution.prompt.clone());
+        self.templates.render(PromptTemplates::WRITE_TESTS, &vars)
+    }
+
+    /// Writes the tests and then the implementation for `execution` in
+    /// `worktree`.
+    pub async fn run(
+        &self,
+        execution: &InstanceExecution,
+        worktree: &Path,
+    ) -> Result<TestFirstOutcome> {
+        let baseline = self.write_tests(execution, worktree).await?;
+        self.implement(execution, worktree, baseline).await
+    }
+
+    /// Has the test writer write failing tests for `execution` and commits
+    /// them.
+    ///
+    /// Fails if no tests were written, if they are not in test files of
+    /// their own, or if they already pass.
+    pub async fn write_tests(
+        &self,
+        execution: &InstanceExecution,
+        worktree: &Path,
+    ) -> Result<TestBaseline> {
+        let mut config = execution.spawn_config(worktree.to_path_buf());
+        config.prompt = self.test_prompt(execution);
+        if !spawn(self.test_writer.as_ref(), config).await? {
+            tracing::warn!(task = %execution.task_id, "test writer exited unsuccessfully");
+        }
+
+        if self.test_command.is_some() && self.run_tests(execution, worktree).await.is_none() {
+            return Err(Error::TestFirst {
+                task_id: execution.task_id.clone(),
+                reason: "the tests pass before the task is implemented",
+            });
+        }
+
+        let message = format!("Add failing tests for {}", execution.task_id);
+        let Some(commit) =
+            PRManager::new(worktree.to_path_buf()).commit_changes(worktree, &message)?
+        else {
+            return Err(Error::TestFirst {
+                task_id: execution.task_id.clone(),
+                reason: "no tests were written",
+            });
+        };
+
+        let baseline = TestBaseline::from_commit(&execution.task_id, worktree, &commit)?;
+        if baseline.files.is_empty() {
+            return Err(Error::TestFirst {
+                task_id: execution.task_id.clone(),
+                reason: "the tests are not in test files of their own",
+            });
+        }
+        Ok(baseline)
+    }
+
+    /// Has the implementer make the tests in `baseline` pass, then checks
+    /// the tests pass and were left untouched.
+    pub async fn implement(
+        &self,
+        execution: &InstanceExecution,
+        worktree: &Path,
+        baseline: TestBaseline,
+    ) -> Result<TestFirstOutcome> {
+        let mut config = execution.spawn_config(worktree.to_path_buf());
+        config.prompt = baseline.implementation_prompt(&execution.prompt);
+        let implemented = spawn(self.implementer.as_ref(), config).await?;
+
+        let test_failure = self.run_tests(execution, worktree).await;
+        let modified_tests = baseline.modified_files(worktree)?;
+        if !modified_tests.is_empty() {
+            tracing::warn!(
+                task = %execution.task_id,
+                files = ?modified_tests,
+                "implementation changed test-first tests"
+            );
+        }
+
+        Ok(TestFirstOutcome {
+            baseline,
+            implemented,
+            test_failure,
+            modified_tests,
+        })
+    }
+
+    /// Runs the test command, returning its failure.
+    async fn run_tests(
+        &self,
+        execution: &InstanceExecution,
+        worktree: &Path,
+    ) -> Option<VerificationFailure> {
+        let command = self.test_command.clone()?;
+        VerificationConfig {
+            commands: vec![command],
+            ..Default::default()
+        }
+        .run(worktree, &execution.manifest)
+        .await
+    }
+}
+
+/// Runs `runner` to completion, returning whether it succeeded.
+async fn spawn(runner: &dyn LLMRunner, config: LLMSpawnConfig) -> Result<bool> {
+    let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
+    let (result, _) = tokio::join!(runner.spawn(config, tx), async {
+        while rx.recv().await.is_some() {}
+    });
+    Ok(result?.success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::config::BuildingConfig;
+    use crate::cruise::executor::TaskExecutor;
+    use crate::cruise::task::CruiseTask;
+    use crate::runner::LLMResult;
+    use async_trait::async_trait;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    /// Runner that writes fixed files into its working directory.
+    struct Writes(&'static [(&'static str, &'static str)]);
+
+    #[async_trait]
+    impl LLMRunner for Writes {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            for (path, content) in self.0 {
+                let path = config.working_dir.join(path);
+                std::fs::create_dir_all(path.parent().unwrap())?;
+                std::fs::write(path, content)?;
+            }
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "writer"
+        }
+    }
+
+    const TESTS: &[(&str, &str)] = &[
+        ("tests/login.rs", "#[test] fn returns_token() {}"),
+        ("src/login.rs", "// stub"),
+    ];
+
+    fn create_temp_git_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .current_dir(dir.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        run(&["init"]);
+        run(&["config", "user.email", "test@test.com"]);
+        run(&["config", "user.name", "Test"]);
+        std::fs::write(dir.path().join("README.md"), "# Test").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-m", "Initial commit"]);
+        dir
+    }
+
+    fn execution() -> InstanceExecution {
+        let mut task = CruiseTask::new("CRUISE-001", "Add login");
+        task.acceptance_criteria = vec!["Returns a token".to_string()];
+        TaskExecutor::new(BuildingConfig::default()).plan_task(&task)[0].clone()
+    }
+
+    fn runner(implementation: &'static [(&'static str, &'static str)]) -> TestFirstRunner {
+        TestFirstRunner::new(Arc::new(Writes(TESTS)), Arc::new(Writes(implementation)))
+            .with_test_command("grep -q token src/login.rs")
+    }
+
+    #[test]
+    fn recognizes_test_files() {
+        for path in [
+            "tests/login.rs",
+            "pkg/auth/login_test.go",
+            "src/__tests__/login.tsx",
+            "web/login.spec.ts",
+            "app/test_login.py",
+            "src/test/java/LoginTest.java",
+        ] {
+            assert!(is_test_path(path), "{}", path);
+        }
+        for path in ["src/login.rs", "src/testing.rs", "latest/notes.md"] {
+            assert!(!is_test_path(path), "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_are_committed_before_the_implementation() {
+        let repo = create_temp_git_repo();
+        let worktree = repo.path().to_path_buf();
+        let runner = runner(&[("src/login.rs", "fn token() {}")]);
+        let execution = execution();
+
+        assert!(runner.test_prompt(&execution).contains("- Returns a token"));
+        let outcome = runner.run(&execution, &worktree).await.unwrap();
+
+        assert!(outcome.passed(), "{:?}", outcome);
+        assert_eq!(outcome.baseline.files, vec!["tests/login.rs"]);
+        let subject = SystemGit
+            .run(repo.path(), &["log", "-1", "--format=%s"])
+            .unwrap();
+        assert_eq!(subject.trim(), "Add failing tests for CRUISE-001");
+    }
+
+    #[tokio::test]
+    async fn changed_tests_fail_validation() {
+        let repo = create_temp_git_repo();
+        let worktree = repo.path().to_path_buf();
+        let runner = runner(&[
+            ("src/login.rs", "fn token() {}"),
+            ("tests/login.rs", "// removed"),
+        ]);
+
+        let outcome = runner.run(&execution(), &worktree).await.unwrap();
+
+        assert!(!outcome.passed());
+        assert_eq!(outcome.modified_tests, vec!["tests/login.rs"]);
+        let findings = outcome.baseline.findings(repo.path()).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].category, TEST_INTEGRITY_CATEGORY);
+        assert_eq!(findings[0].severity, FindingSeverity::Critical);
+    }
+
+    #[tokio::test]
+    async fn tests_that_already_pass_are_rejected() {
+        let repo = create_temp_git_repo();
+        let worktree = repo.path().to_path_buf();
+        let runner = TestFirstRunner::new(Arc::new(Writes(TESTS)), Arc::new(Writes(&[])))
+            .with_test_command("true");
+
+        let err = runner
+            .write_tests(&execution(), &worktree)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::TestFirst { task_id, reason }
+                if task_id == "CRUISE-001" && reason.contains("pass before")
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+//! Diff scoping for reviews.
+//!
+//! Reviewers see the changes a [`DiffProvider`] produces. The default
+//! [`GitDiffProvider`] diffs the worktree against the point where the branch
+//! forked from its base, so every commit of a multi-commit iteration is
+//! reviewed, and leaves out lockfiles and vendored code. [`DiffOptions`] can
+//! be overridden per review domain, like gating policies.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+
+/// Paths left out of review diffs by default (git glob pathspecs).
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "**/Cargo.lock",
+    "**/package-lock.json",
+    "**/yarn.lock",
+    "**/pnpm-lock.yaml",
+    "**/poetry.lock",
+    "**/go.sum",
+    "vendor/**",
+    "third_party/**",
+    "**/node_modules/**",
+];
+
+/// Which changes a review diff covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffScope {
+    /// Everything since the branch forked from the base ref, committed or not.
+    #[default]
+    Branch,
+    /// Only changes not yet committed.
+    Uncommitted,
+}
+
+/// How to build the diff for one review domain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffOptions {
+    /// Which changes to include.
+    #[serde(default)]
+    pub scope: DiffScope,
+    /// Paths to review (git glob pathspecs). Empty means every path.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Paths to leave out (git glob pathspecs).
+    #[serde(default = "default_excludes")]
+    pub exclude: Vec<String>,
+    /// Diffs larger than this many tokens are split into chunks of whole
+    /// files, each reviewed separately.
+    #[serde(default)]
+    pub max_chunk_tokens: Option<usize>,
+    /// Re-reviews only see changes since the domain's last verdict.
+    #[serde(default)]
+    pub incremental: bool,
+    /// Commit to diff against instead of the scope's base. Set for
+    /// incremental re-reviews.
+    #[serde(skip)]
+    pub since: Option<String>,
+}
+
+fn default_excludes() -> Vec<String> {
+    DEFAULT_EXCLUDES.iter().map(|p| p.to_string()).collect()
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            scope: DiffScope::default(),
+            include: Vec::new(),
+            exclude: default_excludes(),
+            max_chunk_tokens: None,
+            incremental: false,
+            since: None,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Returns the pathspecs passed to `git diff` after `--`.
+    pub fn pathspecs(&self) -> Vec<String> {
+        let mut specs: Vec<String> = if self.include.is_empty() {
+            vec![".".to_string()]
+        } else {
+            self.include
+                .iter()
+                .map(|p| format!(":(glob){}", p))
+                .collect()
+        };
+        specs.extend(self.exclude.iter().map(|p| format!(":(glob,exclude){}", p)));
+        specs
+    }
+}
+
+/// Diff options, with optional overrides per review domain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffConfig {
+    /// Options for domains without an override.
+    #[serde(default)]
+    pub default: DiffOptions,
+    /// Per-domain overrides.
+    #[serde(default)]
+    pub domains: HashMap<String, DiffOptions>,
+}
+
+impl DiffConfig {
+    /// Returns the options for a review domain.
+    pub fn options(&self, domain: &str) -> &DiffOptions {
+        self.domains.get(domain).unwrap_or(&self.default)
+    }
+}
+
+/// Produces the diff a reviewer sees.
+pub trait DiffProvider: Send + Sync {
+    /// Returns the changes in `worktree` to review under `options`.
+    fn diff(&self, worktree: &Path, options: &DiffOptions) -> Result<String>;
+}
+
+/// Diffs a git worktree against a base ref.
+#[derive(Debug, Clone)]
+pub struct GitDiffProvider {
+    base_ref: String,
+}
+
+impl GitDiffProvider {
+    /// Creates a provider diffing against `base_ref` (e.g. `main`).
+    pub fn new(base_ref: impl Into<String>) -> Self {
+        Self {
+            base_ref: base_ref.into(),
+        }
+    }
+
+    /// Returns the commit the diff is taken against for `scope`.
+    fn base(&self, worktree: &Path, scope: DiffScope) -> Result<String> {
+        match scope {
+            DiffScope::Uncommitted => Ok("HEAD".to_string()),
+            DiffScope::Branch => {
+                let base = SystemGit.run(worktree, &["merge-base", &self.base_ref, "HEAD"])?;
+                Ok(base.trim().to_string())
+            }
+        }
+    }
+}
+
+impl DiffProvider for GitDiffProvider {
+    fn diff(&self, worktree: &Path, options: &DiffOptions) -> Result<String> {
+        // Intent-to-add makes untracked files show up in the diff
+        SystemGit.run(worktree, &["add", "--intent-to-add", "--all"])?;
+
+        let base = match &options.since {
+            Some(since) => since.clone(),
+            None => self.base(worktree, options.scope)?,
+        };
+        let pathspecs = options.pathspecs();
+        let mut args = vec!["diff", base.as_str(), "--"];
+        args.extend(pathspecs.iter().map(String::as_str));
+        SystemGit.run(worktree, &args)
+    }
+}
+
+/// Records the worktree's current state, untracked files included, as a
+/// commit later diffs can be taken against.
+///
+/// The commit is on no branch, and the worktree's index is left untouched.
+pub fn snapshot(worktree: &Path) -> Result<String> {
+    let index = worktree.join(
+        SystemGit
+            .run(
+                worktree,
+                &[
+                    "rev-parse",
+                    "--git-path",
+                    "improbability-drive-snapshot-index",
+                ],
+            )?
+            .trim(),
+    );
+    let with_index = |args: &[&str]| git_with_env(worktree, args, &[("GIT_INDEX_FILE", &index)]);
+
+    let tree = with_index(&["read-tree", "HEAD"])
+        .and_then(|_| with_index(&["add", "--all"]))
+        .and_then(|_| with_index(&["write-tree"]));
+    let _ = std::fs::remove_file(&index);
+
+    let tree = tree?;
+    let commit = SystemGit.run(
+        worktree,
+        &[
+            "commit-tree",
+            tree.trim(),
+            "-p",
+            "HEAD",
+            "-m",
+            "review snapshot",
+        ],
+    )?;
+    Ok(commit.trim().to_string())
+}
+
+/// Returns the paths a unified diff touches, in diff order.
+pub fn changed_files(diff: &str) -> Vec<String> {
+    diff.lines()
+        .filter_map(|line| line.strip_prefix("diff --git a/"))
+        .filter_map(|line| line.rsplit_once(" b/").map(|(_, path)| path.to_string()))
+        .collect()
+}
+
+/// Runs git in `dir` with extra environment variables.
+fn git_with_env(dir: &Path, args: &[&str], env: &[(&str, &Path)]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .envs(env.iter().copied())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(Error::git_command(args, &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Creates a repo with a `main` commit and a feature branch checked out.
+    fn create_branched_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        SystemGit.run(path, &["init", "-q", "-b", "main"]).unwrap();
+        SystemGit
+            .run(path, &["config", "user.email", "test@test.com"])
+            .unwrap();
+        SystemGit
+            .run(path, &["config", "user.name", "Test User"])
+            .unwrap();
+        fs::write(path.join("README.md"), "# Test Repo\n").unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", "Initial commit"])
+            .unwrap();
+        SystemGit
+            .run(path, &["checkout", "-q", "-b", "feature"])
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn branch_scope_covers_every_commit_and_skips_lockfiles() {
+        let repo = create_branched_repo();
+        let path = repo.path();
+        fs::create_dir(path.join("src")).unwrap();
+        fs::write(path.join("src/first.rs"), "fn first() {}\n").unwrap();
+        fs::write(path.join("Cargo.lock"), "# generated\n").unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", "First"])
+            .unwrap();
+        fs::write(path.join("src/second.rs"), "fn second() {}\n").unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", "Second"])
+            .unwrap();
+        fs::write(path.join("src/third.rs"), "fn third() {}\n").unwrap();
+
+        let diff = GitDiffProvider::new("main")
+            .diff(path, &DiffOptions::default())
+            .unwrap();
+
+        assert!(diff.contains("src/first.rs"));
+        assert!(diff.contains("src/second.rs"));
+        assert!(diff.contains("src/third.rs"));
+        assert!(!diff.contains("Cargo.lock"));
+    }
+
+    #[test]
+    fn uncommitted_scope_and_include_filters() {
+        let repo = create_branched_repo();
+        let path = repo.path();
+        fs::write(path.join("committed.rs"), "fn a() {}\n").unwrap();
+        SystemGit.run(path, &["add", "."]).unwrap();
+        SystemGit
+            .run(path, &["commit", "-q", "-m", "Committed"])
+            .unwrap();
+        fs::create_dir(path.join("docs")).unwrap();
+        fs::write(path.join("docs/guide.md"), "# Guide\n").unwrap();
+        fs::write(path.join("new.rs"), "fn b() {}\n").unwrap();
+
+        let options = DiffOptions {
+            scope: DiffScope::Uncommitted,
+            include: vec!["**/*.rs".to_string()],
+            ..Default::default()
+        };
+        let diff = GitDiffProvider::new("main").diff(path, &options).unwrap();
+
+        assert!(diff.contains("new.rs"));
+        assert!(!diff.contains("committed.rs"));
+        assert!(!diff.contains("guide.md"));
+    }
+
+    #[test]
+    fn config_deserializes_domain_overrides() {
+        let config: DiffConfig = toml::from_str(
+            r#"
+            [default]
+            max_chunk_tokens = 20000
+
+            [domains.security]
+            include = ["src/**"]
+            exclude = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default.max_chunk_tokens, Some(20_000));
+        assert_eq!(config.default.exclude.len(), DEFAULT_EXCLUDES.len());
+        assert_eq!(
+            config.options("security").pathspecs(),
+            vec![":(glob)src/**"]
+        );
+        assert_eq!(config.options("general_polish"), &config.default);
+    }
+
+    #[test]
+    fn since_snapshot_shows_only_later_changes() {
+        let repo = create_branched_repo();
+        let path = repo.path();
+        fs::write(path.join("reviewed.rs"), "fn a() {}\n").unwrap();
+        let snapshot = snapshot(path).unwrap();
+        fs::write(path.join("later.rs"), "fn b() {}\n").unwrap();
+
+        let options = DiffOptions {
+            since: Some(snapshot),
+            ..Default::default()
+        };
+        let diff = GitDiffProvider::new("main").diff(path, &options).unwrap();
+
+        assert_eq!(changed_files(&diff), vec!["later.rs"]);
+        // The snapshot left the real index alone
+        let staged = SystemGit
+            .run(path, &["diff", "--cached", "--name-only"])
+            .unwrap();
+        assert!(!staged.contains("reviewed.rs"));
+    }
+}
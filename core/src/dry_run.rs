@@ -0,0 +1,617 @@
+//! Dry-run execution plans.
+//!
+//! A dry run resolves settings and builds every prompt a run would send,
+//! then lists the LLM invocations, git commands, branches, and PRs it would
+//! create with an estimated cost. Nothing is spawned and no remote is
+//! touched.
+
+use std::fmt;
+
+use crate::branch::BranchNamer;
+use crate::cruise::{
+    ApprovalMode, PlanPromptBuilder, PlanReviewPromptBuilder, RepoBootstrapper, RepoLifecycle,
+    ReviewPhase,
+};
+use crate::pr::pr_title;
+use crate::prompt::{budget, PromptTemplates};
+use crate::settings::Settings;
+use crate::team::{FixPromptBuilder, ReviewPromptBuilder, TESTER_ROLE};
+
+/// Output tokens assumed per invocation when estimating cost.
+pub const ESTIMATED_OUTPUT_TOKENS: usize = 4_000;
+
+/// Placeholder for a diff that only exists once the primary has run.
+const DIFF_PLACEHOLDER: &str = "(diff of the primary LLM's changes)";
+
+/// Placeholder for a plan that only exists once the planner has run.
+const PLAN_PLACEHOLDER: &str = "(plan JSON from the planner)";
+
+/// Something a run would do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlannedAction {
+    /// An LLM invocation.
+    Invoke {
+        /// What the invocation is for (e.g., "primary", "review: security").
+        label: String,
+        /// Runner identifier (e.g., "claude-code").
+        runner: String,
+        /// Model passed to the runner, if any.
+        model: Option<String>,
+        /// The prompt that would be sent.
+        prompt: String,
+    },
+    /// A git or gh command.
+    Command(String),
+    /// A branch that would be created.
+    Branch(String),
+    /// A pull request that would be opened.
+    PullRequest {
+        title: String,
+        head: String,
+        base: String,
+        draft: bool,
+    },
+}
+
+impl PlannedAction {
+    /// Returns the estimated input tokens for an invocation.
+    pub fn input_tokens(&self) -> usize {
+        match self {
+            PlannedAction::Invoke {
+                runner,
+                model,
+                prompt,
+                ..
+            } => budget::estimate_tokens(prompt, model.as_deref().unwrap_or(runner)),
+            _ => 0,
+        }
+    }
+
+    /// Returns the estimated cost in USD of an invocation.
+    pub fn estimated_cost(&self) -> f64 {
+        match self {
+            PlannedAction::Invoke { runner, model, .. } => budget::estimate_cost(
+                model.as_deref().unwrap_or(runner),
+                self.input_tokens(),
+                ESTIMATED_OUTPUT_TOKENS,
+            ),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Everything a run would do, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionPlan {
+    /// Operation being planned ("spawn", "spawn-team", "cruise").
+    pub operation: String,
+    /// Actions in execution order.
+    pub actions: Vec<PlannedAction>,
+    /// Caveats about what could not be planned ahead.
+    pub notes: Vec<String>,
+}
+
+impl ExecutionPlan {
+    fn new(operation: &str) -> Self {
+        Self {
+            operation: operation.to_string(),
+            actions: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Returns the number of LLM invocations.
+    pub fn invocation_count(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|a| matches!(a, PlannedAction::Invoke { .. }))
+            .count()
+    }
+
+    /// Returns the estimated input tokens across all invocations.
+    pub fn estimated_input_tokens(&self) -> usize {
+        self.actions.iter().map(PlannedAction::input_tokens).sum()
+    }
+
+    /// Returns the estimated cost in USD across all invocations.
+    pub fn estimated_cost(&self) -> f64 {
+        self.actions.iter().map(PlannedAction::estimated_cost).sum()
+    }
+}
+
+impl fmt::Display for ExecutionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Dry run: {}", self.operation)?;
+        writeln!(f)?;
+
+        for (index, action) in self.actions.iter().enumerate() {
+            let n = index + 1;
+            match action {
+                PlannedAction::Invoke {
+                    label,
+                    runner,
+                    model,
+                    prompt,
+                } => {
+                    let model = model.as_deref().unwrap_or("default model");
+                    writeln!(
+                        f,
+                        "{}. invoke {} ({}) for {}: ~{} input tokens, ~${:.2}",
+                        n,
+                        runner,
+                        model,
+                        label,
+                        action.input_tokens(),
+                        action.estimated_cost()
+                    )?;
+                    for line in prompt.lines() {
+                        writeln!(f, "     | {}", line)?;
+                    }
+                }
+                PlannedAction::Command(command) => writeln!(f, "{}. run `{}`", n, command)?,
+                PlannedAction::Branch(name) => writeln!(f, "{}. create branch {}", n, name)?,
+                PlannedAction::PullRequest {
+                    title,
+                    head,
+                    base,
+                    draft,
+                } => writeln!(
+                    f,
+                    "{}. open {}PR \"{}\" ({} -> {})",
+                    n,
+                    if *draft { "draft " } else { "" },
+                    title,
+                    head,
+                    base
+                )?,
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Estimated: {} invocations, ~{} input tokens, ~${:.2} (assuming ~{} output tokens each)",
+            self.invocation_count(),
+            self.estimated_input_tokens(),
+            self.estimated_cost(),
+            ESTIMATED_OUTPUT_TOKENS
+        )?;
+        for note in &self.notes {
+            writeln!(f, "Note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds execution plans from resolved settings.
+pub struct DryRun<'a> {
+    settings: &'a Settings,
+    templates: PromptTemplates,
+    base_branch: String,
+}
+
+impl<'a> DryRun<'a> {
+    /// Creates a dry run for the given settings.
+    pub fn new(settings: &'a Settings) -> Self {
+        Self {
+            settings,
+            templates: PromptTemplates::builtin(),
+            base_branch: "main".to_string(),
+        }
+    }
+
+    /// Sets the template registry prompts are rendered from.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Sets the branch PRs would target.
+    pub fn with_base_branch(mut self, branch: impl Into<String>) -> Self {
+        self.base_branch = branch.into();
+        self
+    }
+
+    /// Plans a single sandboxed spawn.
+    pub fn spawn(&self, prompt: &str) -> ExecutionPlan {
+        let mut plan = ExecutionPlan::new("spawn");
+        self.push_sandbox(&mut plan);
+        plan.actions.push(PlannedAction::Invoke {
+            label: "primary".to_string(),
+            runner: self.settings.spawn.default_llm.clone(),
+            model: self.settings.spawn.model.clone(),
+            prompt: prompt.to_string(),
+        });
+        self.push_pr(&mut plan, prompt, false);
+        self.push_timeout_note(&mut plan);
+        plan
+    }
+
+    /// Plans a spawn-team run, assuming every review round requests changes.
+    pub fn spawn_team(&self, prompt: &str) -> ExecutionPlan {
+        let team = &self.settings.team;
+        let mut plan = ExecutionPlan::new("spawn-team");
+        self.push_sandbox(&mut plan);
+        if !team.uses_role_pipeline() {
+            plan.actions.push(PlannedAction::Invoke {
+                label: "primary".to_string(),
+                runner: team.primary_llm.clone(),
+                model: team
+                    .primary_model
+                    .clone()
+                    .or_else(|| self.settings.spawn.model.clone()),
+                prompt: prompt.to_string(),
+            });
+        } else {
+            for role in team.role_pipeline() {
+                let gated = role.name == TESTER_ROLE;
+                plan.actions.push(PlannedAction::Invoke {
+                    label: format!("role: {}", role.name),
+                    runner: role.llm,
+                    model: role.model,
+                    prompt: prompt.to_string(),
+                });
+                if gated {
+                    for command in &team.tester.gate.commands {
+                        plan.actions.push(PlannedAction::Command(command.clone()));
+                    }
+                }
+            }
+        }
+
+        let mut domains: Vec<&str> = team.gating.domains.keys().map(String::as_str).collect();
+        domains.sort_unstable();
+        if domains.is_empty() {
+            domains.push("general");
+        }
+
+        let iterations = team.max_iterations.max(1);
+        for domain in domains {
+            for iteration in 1..=iterations {
+                let review = ReviewPromptBuilder::new(prompt)
+                    .with_diff(DIFF_PLACEHOLDER)
+                    .build_with(&self.templates);
+                plan.actions.push(PlannedAction::Invoke {
+                    label: format!("review: {} (pass {})", domain, iteration),
+                    runner: team.reviewer_llm.clone(),
+                    model: team.review_model().map(String::from),
+                    prompt: review,
+                });
+                if iteration < iterations {
+                    plan.actions.push(PlannedAction::Invoke {
+                        label: format!("fix: {} (round {})", domain, iteration),
+                        runner: team.primary_llm.clone(),
+                        model: team
+                            .fix_model()
+                            .map(String::from)
+                            .or_else(|| self.settings.spawn.model.clone()),
+                        prompt: FixPromptBuilder::new(prompt).build_with(&self.templates),
+                    });
+                }
+            }
+        }
+
+        self.push_pr(&mut plan, prompt, team.draft_prs);
+        plan.notes.push(format!(
+            "review and fix counts are worst case; each domain stops once approved (max {} passes)",
+            iterations
+        ));
+        self.push_timeout_note(&mut plan);
+        plan
+    }
+
+    /// Plans the cruise-control planning phase.
+    pub fn cruise(&self, prompt: &str) -> ExecutionPlan {
+        let planning = &self.settings.cruise.planning;
+        let mut plan = ExecutionPlan::new("cruise");
+        if self.settings.cruise.test.repo_lifecycle == RepoLifecycle::CreateNew {
+            self.push_bootstrap(&mut plan, prompt);
+        }
+        self.push_sandbox(&mut plan);
+
+        let iterations = planning.ping_pong_iterations.max(1);
+        for iteration in 1..=iterations {
+            let mut builder = PlanPromptBuilder::new(prompt);
+            if iteration > 1 {
+                builder = builder
+                    .with_previous_plan(PLAN_PLACEHOLDER)
+                    .with_review_feedback("(review feedback from the previous pass)");
+            }
+            plan.actions.push(PlannedAction::Invoke {
+                label: format!("plan (iteration {})", iteration),
+                runner: self.settings.spawn.default_llm.clone(),
+                model: self.settings.spawn.model.clone(),
+                prompt: builder.build_with(&self.templates),
+            });
+
+            let phase = ReviewPhase::for_iteration(iteration);
+            plan.actions.push(PlannedAction::Invoke {
+                label: format!("plan review: {}", phase.domain()),
+                runner: planning.reviewer_llm.clone(),
+                model: None,
+                prompt: PlanReviewPromptBuilder::new(PLAN_PLACEHOLDER, phase)
+                    .build_with(&self.templates),
+            });
+        }
+
+        match self.settings.cruise.approval.mode {
+            ApprovalMode::Pr => self.push_pr(&mut plan, prompt, false),
+            ApprovalMode::Terminal => plan.notes.push(
+                "the plan is shown in the terminal to approve, reject, or edit; \
+                 no plan PR is opened"
+                    .to_string(),
+            ),
+        }
+        plan.notes.push(
+            "build tasks depend on the approved plan and are not included in the estimate"
+                .to_string(),
+        );
+        self.push_timeout_note(&mut plan);
+        plan
+    }
+
+    fn push_bootstrap(&self, plan: &mut ExecutionPlan, prompt: &str) {
+        let bootstrap = &self.settings.cruise.bootstrap;
+        let bootstrapper =
+            RepoBootstrapper::new(bootstrap.clone(), self.settings.sandbox_dir());
+        let args: Vec<String> = bootstrapper
+            .create_args()
+            .into_iter()
+            .map(|a| if a.contains(' ') { format!("'{}'", a) } else { a })
+            .collect();
+        plan.actions
+            .push(PlannedAction::Command(format!("gh {}", args.join(" "))));
+        plan.actions
+            .push(PlannedAction::Command("git push -u origin HEAD".to_string()));
+        let scaffold = match bootstrapper.scaffold_for(prompt) {
+            Ok(Some(scaffold)) => format!(", the {} scaffold", scaffold.name()),
+            _ => String::new(),
+        };
+        let fixture = match &bootstrap.fixture {
+            Some(fixture) => format!(" the files of {},", fixture.display()),
+            None => String::new(),
+        };
+        plan.notes.push(format!(
+            "the new repository {} is seeded with{} a README{}{} before planning, \
+             and plan and build run against it",
+            bootstrapper.full_name(),
+            fixture,
+            scaffold,
+            if bootstrap.ci.is_some() { " and CI workflow" } else { "" }
+        ));
+        if bootstrap.branch_protection.is_some() {
+            plan.notes.push(format!(
+                "the default branch of {} is protected once pushed",
+                bootstrapper.full_name()
+            ));
+        }
+    }
+
+    fn push_sandbox(&self, plan: &mut ExecutionPlan) {
+        let dir = self.settings.sandbox_dir();
+        let branch = self.settings.branches.sandbox_placeholder();
+        plan.actions.push(PlannedAction::Branch(branch.clone()));
+        plan.actions.push(PlannedAction::Command(format!(
+            "git worktree add -b {} {}/{}",
+            branch,
+            dir.display(),
+            BranchNamer::dir_name(&branch)
+        )));
+    }
+
+    fn push_pr(&self, plan: &mut ExecutionPlan, prompt: &str, draft: bool) {
+        let branch = self.settings.branches.sandbox_placeholder();
+        plan.actions.push(PlannedAction::Command(format!(
+            "git push -u origin {}",
+            branch
+        )));
+        plan.actions.push(PlannedAction::PullRequest {
+            title: pr_title(prompt),
+            head: branch,
+            base: self.base_branch.clone(),
+            draft,
+        });
+    }
+
+    fn push_timeout_note(&self, plan: &mut ExecutionPlan) {
+        plan.notes.push(format!(
+            "runs stop after {}s total or {}s without activity",
+            self.settings.spawn.total_timeout, self.settings.spawn.idle_timeout
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::team::{ReviewGatePolicy, TeamRole};
+
+    fn invocations(plan: &ExecutionPlan) -> Vec<&str> {
+        plan.actions
+            .iter()
+            .filter_map(|a| match a {
+                PlannedAction::Invoke { label, .. } => Some(label.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spawn_plan_lists_sandbox_invocation_and_pr() {
+        let settings = Settings::default();
+
+        let plan = DryRun::new(&settings).spawn("Fix the login bug");
+
+        assert_eq!(invocations(&plan), vec!["primary"]);
+        assert!(plan
+            .actions
+            .iter()
+            .any(|a| matches!(a, PlannedAction::Command(c) if c.starts_with("git worktree add"))));
+        assert!(plan.actions.iter().any(|a| matches!(
+            a,
+            PlannedAction::PullRequest { title, base, .. }
+                if title == "Fix the login bug" && base == "main"
+        )));
+        assert!(plan.estimated_cost() > 0.0);
+    }
+
+    #[test]
+    fn spawn_team_plan_runs_configured_roles() {
+        let mut settings = Settings::default();
+        settings.team.max_iterations = 1;
+        let mut tester = TeamRole::new("tester", "gemini-cli");
+        tester.order = 1;
+        settings.team.roles = vec![tester, TeamRole::new("architect", "claude-code")];
+
+        let plan = DryRun::new(&settings).spawn_team("Add caching");
+
+        assert_eq!(
+            invocations(&plan),
+            vec![
+                "role: architect",
+                "role: tester",
+                "review: general (pass 1)"
+            ]
+        );
+    }
+
+    #[test]
+    fn spawn_team_plan_gates_tester_before_reviews() {
+        let mut settings = Settings::default();
+        settings.team.max_iterations = 1;
+        settings.team.tester.enabled = true;
+        settings.team.tester.gate.commands = vec!["cargo test".to_string()];
+
+        let plan = DryRun::new(&settings).spawn_team("Add caching");
+
+        assert_eq!(
+            invocations(&plan),
+            vec![
+                "role: implementer",
+                "role: tester",
+                "role: reviewer",
+                "review: general (pass 1)"
+            ]
+        );
+        let tester = plan
+            .actions
+            .iter()
+            .position(
+                |a| matches!(a, PlannedAction::Invoke { label, .. } if label == "role: tester"),
+            )
+            .unwrap();
+        assert!(matches!(
+            &plan.actions[tester + 1],
+            PlannedAction::Command(command) if command == "cargo test"
+        ));
+    }
+
+    #[test]
+    fn spawn_team_plan_covers_each_domain() {
+        let mut settings = Settings::default();
+        settings.team.max_iterations = 2;
+        settings.team.draft_prs = true;
+        for domain in ["security", "performance"] {
+            settings
+                .team
+                .gating
+                .domains
+                .insert(domain.to_string(), ReviewGatePolicy::default());
+        }
+
+        let plan = DryRun::new(&settings).spawn_team("Add caching");
+
+        assert_eq!(
+            invocations(&plan),
+            vec![
+                "primary",
+                "review: performance (pass 1)",
+                "fix: performance (round 1)",
+                "review: performance (pass 2)",
+                "review: security (pass 1)",
+                "fix: security (round 1)",
+                "review: security (pass 2)",
+            ]
+        );
+        assert!(plan
+            .actions
+            .iter()
+            .any(|a| matches!(a, PlannedAction::PullRequest { draft: true, .. })));
+    }
+
+    #[test]
+    fn cruise_plan_builds_plan_and_review_prompts() {
+        let mut settings = Settings::default();
+        settings.cruise.planning.ping_pong_iterations = 2;
+
+        let plan = DryRun::new(&settings).cruise("Build a todo app");
+
+        assert_eq!(
+            invocations(&plan),
+            vec![
+                "plan (iteration 1)",
+                "plan review: security",
+                "plan (iteration 2)",
+                "plan review: technical_feasibility",
+            ]
+        );
+        match &plan.actions[2] {
+            PlannedAction::Invoke { prompt, .. } => assert!(prompt.contains("Build a todo app")),
+            other => panic!("expected invocation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cruise_plan_skips_plan_pr_with_terminal_approval() {
+        let mut settings = Settings::default();
+        settings.cruise.approval.mode = ApprovalMode::Terminal;
+
+        let plan = DryRun::new(&settings).cruise("Build a todo app");
+
+        assert!(!plan
+            .actions
+            .iter()
+            .any(|a| matches!(a, PlannedAction::PullRequest { .. })));
+        assert!(plan.notes.iter().any(|n| n.contains("in the terminal")));
+    }
+
+    #[test]
+    fn cruise_plan_creates_repository_first_when_bootstrapping() {
+        let mut settings = Settings::default();
+        settings.cruise.test.repo_lifecycle = RepoLifecycle::CreateNew;
+        settings.cruise.bootstrap.owner = "acme".to_string();
+        settings.cruise.bootstrap.name = "todo".to_string();
+
+        let plan = DryRun::new(&settings).cruise("Build a todo app");
+
+        match &plan.actions[0] {
+            PlannedAction::Command(cmd) => {
+                assert!(cmd.starts_with("gh repo create acme/todo --clone --private"))
+            }
+            other => panic!("expected command, got {:?}", other),
+        }
+        assert!(plan.notes.iter().any(|n| n.contains("acme/todo")));
+    }
+
+    #[test]
+    fn rendered_plan_includes_prompts_and_estimate() {
+        let settings = Settings::default();
+
+        let rendered = DryRun::new(&settings)
+            .with_base_branch("develop")
+            .spawn("Update the README")
+            .to_string();
+
+        assert!(rendered.starts_with("Dry run: spawn"));
+        assert!(rendered.contains("     | Update the README"));
+        assert!(rendered.contains("-> develop"));
+        assert!(rendered.contains("Estimated: 1 invocations"));
+    }
+
+    #[test]
+    fn long_prompts_are_shortened_for_pr_titles() {
+        let title = pr_title(&"x".repeat(100));
+        assert_eq!(title.chars().count(), 72);
+        assert!(title.ends_with("..."));
+    }
+}
@@ -1,8 +1,52 @@
 //! Error types for the infinite-improbability-drive plugin.
+//!
+//! Failures of external commands keep the command, exit status, and stderr
+//! ([`Error::GitCommand`], [`Error::GhCommand`], [`Error::Command`]);
+//! subsystems report what went wrong with their own types ([`PlanError`],
+//! [`PatchError`]); wrapped errors keep their source so the whole chain can
+//! be reported. [`Error::is_retryable`] tells
+//! callers whether trying again may help, and [`Error::exit_code`] maps an
+//! error to the CLI's exit status.
 
 use std::path::PathBuf;
+use std::process::Output;
+
 use thiserror::Error;
 
+/// Exit codes used by the CLI.
+pub mod exit_code {
+    /// Any failure without a more specific code.
+    pub const FAILURE: i32 = 1;
+    /// Invalid configuration or arguments.
+    pub const CONFIG: i32 = 2;
+    /// A sandbox could not be created, provisioned, or cleaned up.
+    pub const SANDBOX: i32 = 3;
+    /// A git or GitHub operation failed.
+    pub const VCS: i32 = 4;
+    /// Every LLM invocation failed.
+    pub const LLM: i32 = 5;
+    /// The run timed out.
+    pub const TIMEOUT: i32 = 124;
+    /// The run was cancelled.
+    pub const CANCELLED: i32 = 130;
+}
+
+/// Phrases in command output that indicate a transient failure.
+const TRANSIENT_PATTERNS: &[&str] = &[
+    "rate limit",
+    "secondary rate limit",
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "could not resolve host",
+    "temporary failure",
+    "http 502",
+    "http 503",
+    "http 504",
+    "index.lock",
+];
+
 /// Top-level error type for spawn operations.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -14,9 +58,16 @@ pub enum Error {
     #[error("failed to clean up sandbox at {path}: {reason}")]
     SandboxCleanup { path: PathBuf, reason: String },
 
-    /// Git operation failed.
-    #[error("git operation failed: {0}")]
-    Git(String),
+    /// A git command exited unsuccessfully.
+    #[error("git {command} failed{}: {stderr}", status_suffix(*.status))]
+    GitCommand {
+        /// The git subcommand, e.g. `push`.
+        command: String,
+        /// Exit status, if the command was not killed by a signal.
+        status: Option<i32>,
+        /// Trimmed standard error.
+        stderr: String,
+    },
 
     /// IO error during sandbox operations.
     #[error("IO error: {0}")]
@@ -30,22 +81,471 @@ pub enum Error {
     #[error("configuration error: {0}")]
     Config(String),
 
-    /// Cruise-control operation failed.
-    #[error("cruise-control error: {0}")]
-    Cruise(String),
+    /// A rebase stopped on conflicts the configured strategy does not resolve.
+    #[error("rebasing {branch} onto {onto} conflicts in: {}", join_paths(paths))]
+    RebaseConflict {
+        /// The branch being rebased.
+        branch: String,
+        /// The ref it was rebased onto.
+        onto: String,
+        /// Files left with conflicts.
+        paths: Vec<PathBuf>,
+    },
+
+    /// A command other than git or gh exited unsuccessfully.
+    #[error("{command} failed{}: {stderr}", status_suffix(*.status))]
+    Command {
+        /// What was run, e.g. `sqlite3`.
+        command: String,
+        /// Exit status, if the command was not killed by a signal.
+        status: Option<i32>,
+        /// Trimmed standard error.
+        stderr: String,
+    },
+
+    /// A program the operation needs is not installed.
+    #[error("the {program} command is not installed")]
+    MissingProgram {
+        /// The program that could not be started.
+        program: String,
+        /// The error from starting it.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A cruise plan is incomplete or inconsistent.
+    #[error("invalid plan: {0}")]
+    InvalidPlan(PlanError),
+
+    /// A task ID is not in the plan.
+    #[error("unknown task {0}")]
+    UnknownTask(String),
+
+    /// A task's dependency finished without recording its artifacts.
+    #[error("task {task_id} depends on {dependency}, which has no recorded artifacts")]
+    MissingArtifacts {
+        /// The task being started.
+        task_id: String,
+        /// The dependency without artifacts.
+        dependency: String,
+    },
+
+    /// Tests written ahead of a task break the test-first rules.
+    #[error("test-first check failed for {task_id}: {reason}")]
+    TestFirst {
+        /// The task the tests were written for.
+        task_id: String,
+        /// Which rule was broken.
+        reason: &'static str,
+    },
+
+    /// A coverage command printed no coverage total.
+    #[error("no coverage total in the output of `{0}`")]
+    NoCoverageTotal(String),
+
+    /// Every repository in the repo pool is leased.
+    #[error("repo pool is full: all {0} repositories are leased")]
+    PoolExhausted(usize),
+
+    /// A repository is not in the repo pool.
+    #[error("repository {0} is not in the pool")]
+    UnknownRepository(String),
+
+    /// A PID on record now belongs to a process other than the cruise run.
+    #[error("process {pid} is not the recorded cruise run ({identity}); not signalling it")]
+    ForeignProcess {
+        /// The recorded PID.
+        pid: u32,
+        /// Start time and command line of the process holding it now.
+        identity: String,
+    },
+
+    /// A patch from an LLM in apply-patch mode was rejected.
+    #[error("patch rejected: {0}")]
+    Patch(PatchError),
 
     /// GitHub API operation failed.
     #[error("GitHub operation failed: {0}")]
     GitHub(String),
 
+    /// A `gh` command exited unsuccessfully.
+    #[error("gh {command} failed{}: {stderr}", status_suffix(*.status))]
+    GhCommand {
+        /// The gh subcommand, e.g. `pr view`.
+        command: String,
+        /// Exit status, if the command was not killed by a signal.
+        status: Option<i32>,
+        /// Trimmed standard error.
+        stderr: String,
+    },
+
+    /// Output that should have contained JSON had none.
+    #[error("no JSON found in {0}")]
+    NoJson(String),
+
+    /// Output that should have been JSON could not be parsed.
+    #[error("failed to parse {what}")]
+    Json {
+        /// What was being parsed.
+        what: String,
+        /// The parse error.
+        #[source]
+        source: serde_json::Error,
+    },
+
     /// Plan approval timeout.
     #[error("plan approval timed out after {0} seconds")]
     ApprovalTimeout(u64),
 
+    /// An LLM invocation failed on every available model.
+    #[error("LLM invocation failed: {0}")]
+    Invocation(String),
+
+    /// The run was cancelled before it finished.
+    #[error("run cancelled: {0}")]
+    Cancelled(String),
+
     /// Dependency cycle detected in plan.
     #[error("dependency cycle detected: {0}")]
     DependencyCycle(String),
+
+    /// An error with a description of what was being done.
+    #[error("{context}")]
+    Context {
+        /// What was being done.
+        context: String,
+        /// The underlying error.
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+/// Why a cruise plan was rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// The plan has no tasks.
+    #[error("the plan has no tasks")]
+    NoTasks,
+    /// The plan has no title.
+    #[error("the plan has no title")]
+    NoTitle,
+    /// A task ID does not use the `CRUISE-XXX` format.
+    #[error("task ID '{0}' must use CRUISE-XXX format")]
+    TaskId(String),
+    /// A task has no subject.
+    #[error("task {0} has no subject")]
+    NoSubject(String),
+    /// A task depends on a task the plan does not have.
+    #[error("task {task_id} depends on unknown task {dependency}")]
+    UnknownDependency {
+        /// The dependent task.
+        task_id: String,
+        /// The missing dependency.
+        dependency: String,
+    },
+    /// A task's spawn instance names an LLM that is not supported.
+    #[error("task {task_id} spawn instance uses unknown LLM '{llm}'")]
+    UnknownLlm {
+        /// The task.
+        task_id: String,
+        /// The unsupported LLM.
+        llm: String,
+    },
+    /// A task targets a repository the multi-repo run does not have.
+    #[error("task {task_id} targets unknown repository '{repo}'")]
+    UnknownRepository {
+        /// The task.
+        task_id: String,
+        /// The missing repository.
+        repo: String,
+    },
+}
+
+/// Why a patch from an LLM was rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The LLM's output has no diff block.
+    #[error("the output contains no ```diff block")]
+    NoDiff,
+    /// The diff names no files.
+    #[error("the diff names no files")]
+    NoFiles,
+    /// A path leaves the repository or points into `.git`.
+    #[error("{0} is outside the repository")]
+    OutsideRepository(String),
+    /// A path is not covered by the manifest's writable paths.
+    #[error("{0} is not in the writable paths")]
+    NotWritable(String),
+    /// The files a line of the diff changes cannot be determined.
+    #[error("cannot tell which files `{0}` changes")]
+    UnknownPaths(String),
+}
+
+fn status_suffix(status: Option<i32>) -> String {
+    match status {
+        Some(code) => format!(" (exit {})", code),
+        None => String::new(),
+    }
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Error {
+    /// Builds an [`Error::GitCommand`] from a finished `git` invocation.
+    pub fn git_command(args: &[&str], output: &Output) -> Self {
+        Error::GitCommand {
+            command: args.first().unwrap_or(&"").to_string(),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+
+    /// Builds an [`Error::GhCommand`] from a finished `gh` invocation.
+    ///
+    /// The command is the leading arguments up to the first flag or value,
+    /// e.g. `pr view`.
+    pub fn gh_command(args: &[&str], output: &Output) -> Self {
+        let command = args
+            .iter()
+            .take(2)
+            .take_while(|a| !a.starts_with('-') && !a.contains(['/', ' ']))
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        Error::GhCommand {
+            command,
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+
+    /// Builds an [`Error::Command`] from a finished invocation of `command`.
+    pub fn command(command: impl Into<String>, output: &Output) -> Self {
+        Error::Command {
+            command: command.into(),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+
+    /// Builds an [`Error::MissingProgram`] if `source` says `program` was
+    /// not found, or an [`Error::Io`] otherwise.
+    pub fn spawn_failed(program: &str, source: std::io::Error) -> Self {
+        match source.kind() {
+            std::io::ErrorKind::NotFound => Error::MissingProgram {
+                program: program.to_string(),
+                source,
+            },
+            _ => Error::Io(source),
+        }
+    }
+
+    /// Builds an [`Error::Json`] for unparseable `what`.
+    pub fn json(what: impl Into<String>, source: serde_json::Error) -> Self {
+        Error::Json {
+            what: what.into(),
+            source,
+        }
+    }
+
+    /// Wraps this error with a description of what was being done.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Error::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the innermost error, skipping [`Error::Context`] wrappers.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// Returns whether trying the operation again may succeed: network
+    /// hiccups, rate limits, lock contention, and overloaded models.
+    pub fn is_retryable(&self) -> bool {
+        match self.root() {
+            Error::GitCommand { stderr, .. } | Error::GhCommand { stderr, .. } => {
+                is_transient(stderr)
+            }
+            Error::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ),
+            Error::Invocation(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the CLI exit code for this error. See [`exit_code`].
+    pub fn exit_code(&self) -> i32 {
+        match self.root() {
+            Error::Config(_) | Error::DependencyCycle(_) | Error::InvalidPlan(_) => {
+                exit_code::CONFIG
+            }
+            Error::SandboxCreation(_) | Error::SandboxCleanup { .. } | Error::InvalidPath(_) => {
+                exit_code::SANDBOX
+            }
+            Error::GitCommand { .. }
+            | Error::RebaseConflict { .. }
+            | Error::GitHub(_)
+            | Error::GhCommand { .. } => exit_code::VCS,
+            Error::Invocation(_) => exit_code::LLM,
+            Error::ApprovalTimeout(_) => exit_code::TIMEOUT,
+            Error::Cancelled(_) => exit_code::CANCELLED,
+            _ => exit_code::FAILURE,
+        }
+    }
+
+    /// Formats the error followed by each of its sources.
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            report.push_str(&format!("\n  caused by: {}", error));
+            source = error.source();
+        }
+        report
+    }
+}
+
+/// Returns whether command output describes a transient failure.
+fn is_transient(message: &str) -> bool {
+    let message = message.to_lowercase();
+    TRANSIENT_PATTERNS.iter().any(|p| message.contains(p))
+}
+
+/// Adds context to the error of a [`Result`].
+pub trait ResultExt<T> {
+    /// Wraps the error with a description of what was being done.
+    fn context(self, context: impl Into<String>) -> Result<T>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.context(context))
+    }
 }
 
 /// Result type alias for spawn operations.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn failed_git(args: &[&str]) -> Output {
+        let dir = tempfile::TempDir::new().unwrap();
+        Command::new("git")
+            .current_dir(dir.path())
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    #[test]
+    fn git_command_keeps_status_and_stderr() {
+        let args = ["rev-parse", "HEAD"];
+        let error = Error::git_command(&args, &failed_git(&args));
+
+        let Error::GitCommand {
+            command, status, ..
+        } = &error
+        else {
+            panic!("unexpected error: {:?}", error);
+        };
+        assert_eq!(command, "rev-parse");
+        assert!(status.is_some_and(|code| code != 0));
+        assert!(error.to_string().starts_with("git rev-parse failed (exit "));
+        assert_eq!(error.exit_code(), exit_code::VCS);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn context_keeps_source_chain() {
+        let error = Error::Cancelled("interrupted".to_string()).context("pushing partial work");
+
+        assert_eq!(error.to_string(), "pushing partial work");
+        assert_eq!(
+            error.report(),
+            "pushing partial work\n  caused by: run cancelled: interrupted"
+        );
+        assert_eq!(error.exit_code(), exit_code::CANCELLED);
+    }
+
+    #[test]
+    fn transient_failures_are_retryable() {
+        let rate_limited = Error::GhCommand {
+            command: "pr view".to_string(),
+            status: Some(1),
+            stderr: "API rate limit exceeded for user".to_string(),
+        };
+        let not_found = Error::GhCommand {
+            command: "pr view".to_string(),
+            status: Some(1),
+            stderr: "no pull requests found".to_string(),
+        };
+
+        assert!(rate_limited.is_retryable());
+        assert!(rate_limited.context("polling approval").is_retryable());
+        assert!(!not_found.is_retryable());
+        assert!(!Error::Config("bad".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn missing_programs_keep_the_io_error() {
+        let missing = Error::spawn_failed("sqlite3", std::io::ErrorKind::NotFound.into());
+        let denied = Error::spawn_failed("sqlite3", std::io::ErrorKind::PermissionDenied.into());
+
+        assert_eq!(missing.to_string(), "the sqlite3 command is not installed");
+        assert!(missing.report().contains("\n  caused by: "));
+        assert!(matches!(denied, Error::Io(_)));
+    }
+
+    #[test]
+    fn subsystem_errors_are_typed() {
+        let plan = Error::InvalidPlan(PlanError::UnknownDependency {
+            task_id: "CRUISE-002".to_string(),
+            dependency: "CRUISE-009".to_string(),
+        });
+        let rebase = Error::RebaseConflict {
+            branch: "feature".to_string(),
+            onto: "origin/main".to_string(),
+            paths: vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")],
+        };
+
+        assert_eq!(
+            plan.to_string(),
+            "invalid plan: task CRUISE-002 depends on unknown task CRUISE-009"
+        );
+        assert_eq!(plan.exit_code(), exit_code::CONFIG);
+        assert_eq!(
+            rebase.to_string(),
+            "rebasing feature onto origin/main conflicts in: a.rs, b.rs"
+        );
+        assert_eq!(rebase.exit_code(), exit_code::VCS);
+        assert!(!rebase.is_retryable());
+    }
+
+    #[test]
+    fn gh_command_names_subcommand() {
+        let output = failed_git(&["rev-parse", "HEAD"]);
+        let error = Error::gh_command(&["pr", "view", "https://github.com/o/r/pull/1"], &output);
+
+        assert!(error.to_string().starts_with("gh pr view failed"));
+    }
+}
@@ -0,0 +1,709 @@
+//! A/B model experiments.
+//!
+//! An experiment runs one prompt against the same fixture with two
+//! configurations, for example a sonnet primary against an opus primary.
+//! Each variant works in its own ephemeral copy of the fixture, and both
+//! run at once. Afterwards every variant's changes are verified and
+//! reviewed the same way, and an [`ExperimentReport`] compares cost,
+//! duration, validation, and review findings side by side.
+//!
+//! Experiments are described in TOML:
+//!
+//! ```toml
+//! name = "parser-sonnet-vs-opus"
+//! prompt = "Implement the widget parser in src/parser.rs"
+//! fixture = "fixtures/widgets"
+//!
+//! [verify]
+//! commands = ["cargo test"]
+//!
+//! [a]
+//! name = "sonnet"
+//! model = "sonnet"
+//!
+//! [b]
+//! name = "opus"
+//! model = "opus"
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::cruise::create_runner_with;
+use crate::cruise::status::format_secs;
+use crate::error::{Error, Result, ResultExt};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr_review::{domain_focus, DEFAULT_REVIEW_DOMAIN};
+use crate::prompt::{budget, PromptTemplates};
+use crate::review_loop::{merge_reviews, DomainReviewLoop};
+use crate::runner::{GeminiConfig, LLMOutput, LLMRunner, LLMSpawnConfig, TokenUsage};
+use crate::sandbox::SandboxManifest;
+use crate::team::{DomainSelection, ReviewResult, ReviewSeverity, SpawnTeamConfig};
+use crate::verification::VerificationConfig;
+
+/// Committer for the ephemeral repositories, which have no git config.
+const IDENTITY: &[&str] = &[
+    "-c",
+    "user.name=improbability-drive",
+    "-c",
+    "user.email=improbability-drive@localhost",
+];
+
+/// One configuration under test.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentVariant {
+    /// Label used in the report.
+    pub name: String,
+    /// LLM runner for the primary (e.g., "claude-code").
+    #[serde(default = "default_llm")]
+    pub llm: String,
+    /// Model passed to the runner, if any.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_llm() -> String {
+    "claude-code".to_string()
+}
+
+/// An experiment definition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experiment {
+    /// Name of the experiment.
+    pub name: String,
+    /// Prompt given to both variants.
+    pub prompt: String,
+    /// Directory or git repository each variant starts from.
+    pub fixture: PathBuf,
+    /// Commands that decide whether a variant's changes pass validation.
+    #[serde(default)]
+    pub verify: VerificationConfig,
+    /// Review domains to skip or force, replacing the team configuration's
+    /// when set.
+    #[serde(default)]
+    pub review_domains: DomainSelection,
+    /// First variant.
+    pub a: ExperimentVariant,
+    /// Second variant.
+    pub b: ExperimentVariant,
+}
+
+impl Experiment {
+    /// Loads an experiment from a TOML file. A relative fixture path is
+    /// resolved against the file's directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(Error::from)
+            .context(format!("failed to read experiment {}", path.display()))?;
+        let mut experiment: Experiment = toml::from_str(&content)
+            .map_err(|e| Error::Config(format!("invalid experiment {}: {}", path.display(), e)))?;
+        if experiment.fixture.is_relative() {
+            if let Some(dir) = path.parent() {
+                experiment.fixture = dir.join(&experiment.fixture);
+            }
+        }
+        if experiment.a.name == experiment.b.name {
+            return Err(Error::Config(format!(
+                "experiment variants must have different names, both are '{}'",
+                experiment.a.name
+            )));
+        }
+        Ok(experiment)
+    }
+}
+
+/// What one variant produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantOutcome {
+    /// The variant.
+    pub variant: ExperimentVariant,
+    /// Whether the primary exited successfully.
+    pub success: bool,
+    /// Time the primary ran for.
+    pub duration: Duration,
+    /// Tokens the primary used.
+    pub usage: TokenUsage,
+    /// Estimated cost in USD, when the model is known.
+    pub cost_usd: Option<f64>,
+    /// Files the primary changed.
+    pub files_changed: usize,
+    /// Whether the verify commands passed; `None` when none are configured.
+    pub validation_passed: Option<bool>,
+    /// The verify command that failed, if any.
+    pub failed_command: Option<String>,
+    /// The merged review of the variant's changes.
+    pub review: Option<ReviewResult>,
+    /// Why the variant could not be run or reviewed.
+    pub error: Option<String>,
+    /// The variant's ephemeral repository, when kept.
+    pub repo_path: Option<PathBuf>,
+}
+
+impl VariantOutcome {
+    fn new(variant: &ExperimentVariant) -> Self {
+        Self {
+            variant: variant.clone(),
+            success: false,
+            duration: Duration::ZERO,
+            usage: TokenUsage::default(),
+            cost_usd: None,
+            files_changed: 0,
+            validation_passed: None,
+            failed_command: None,
+            review: None,
+            error: None,
+            repo_path: None,
+        }
+    }
+
+    /// Returns the number of review findings at `severity` or above.
+    pub fn findings_at_least(&self, severity: ReviewSeverity) -> usize {
+        self.review.as_ref().map_or(0, |review| {
+            review
+                .suggestions
+                .iter()
+                .filter(|s| s.severity >= severity)
+                .count()
+        })
+    }
+}
+
+/// Side-by-side results of an experiment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    /// Name of the experiment.
+    pub name: String,
+    /// Prompt both variants were given.
+    pub prompt: String,
+    /// First variant's outcome.
+    pub a: VariantOutcome,
+    /// Second variant's outcome.
+    pub b: VariantOutcome,
+}
+
+impl ExperimentReport {
+    /// Formats the comparison as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let (a, b) = (&self.a, &self.b);
+        let mut out = format!("# Experiment: {}\n\n", self.name);
+        out.push_str(&format!(
+            "- **Prompt:** {}\n\n",
+            self.prompt.lines().next().unwrap_or_default()
+        ));
+        out.push_str(&format!(
+            "| | {} | {} |\n|---|---|---|\n",
+            a.variant.name, b.variant.name
+        ));
+
+        let mut row = |label: &str, value: &dyn Fn(&VariantOutcome) -> String| {
+            out.push_str(&format!("| {} | {} | {} |\n", label, value(a), value(b)));
+        };
+        row("Runner", &|o| match &o.variant.model {
+            Some(model) => format!("{} ({})", o.variant.llm, model),
+            None => o.variant.llm.clone(),
+        });
+        row("Outcome", &|o| match &o.error {
+            Some(_) => "error".to_string(),
+            None if o.success => "finished".to_string(),
+            None => "failed".to_string(),
+        });
+        row("Duration", &|o| format_secs(o.duration.as_secs()));
+        row("Tokens", &|o| o.usage.total().to_string());
+        row("Estimated cost", &|o| {
+            o.cost_usd
+                .map_or_else(|| "unknown".to_string(), |c| format!("${:.2}", c))
+        });
+        row("Files changed", &|o| o.files_changed.to_string());
+        row(
+            "Validation",
+            &|o| match (o.validation_passed, &o.failed_command) {
+                (Some(true), _) => "passed".to_string(),
+                (Some(false), Some(command)) => format!("failed (`{}`)", command),
+                (Some(false), None) => "failed".to_string(),
+                (None, _) => "not run".to_string(),
+            },
+        );
+        row("Review verdict", &|o| match &o.review {
+            Some(review) => format!("{:?}", review.verdict),
+            None => "not reviewed".to_string(),
+        });
+        row("Findings", &|o| {
+            o.review
+                .as_ref()
+                .map_or(0, |r| r.suggestions.len())
+                .to_string()
+        });
+        row("High or critical", &|o| {
+            o.findings_at_least(ReviewSeverity::High).to_string()
+        });
+
+        for outcome in [a, b] {
+            if let Some(error) = &outcome.error {
+                out.push_str(&format!("\n**{}:** {}\n", outcome.variant.name, error));
+            }
+        }
+        for outcome in [a, b] {
+            if let Some(path) = &outcome.repo_path {
+                out.push_str(&format!(
+                    "\n{} kept at `{}`\n",
+                    outcome.variant.name,
+                    path.display()
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Runs experiments.
+pub struct ExperimentRunner {
+    experiment: Experiment,
+    reviewer: Arc<dyn LLMRunner>,
+    config: SpawnTeamConfig,
+    templates: PromptTemplates,
+    manifest: SandboxManifest,
+    keep_repos: bool,
+    gemini: GeminiConfig,
+}
+
+impl ExperimentRunner {
+    /// Creates a runner that reviews both variants with `reviewer` in the
+    /// review domains of `config`.
+    pub fn new(
+        experiment: Experiment,
+        reviewer: Arc<dyn LLMRunner>,
+        config: &SpawnTeamConfig,
+    ) -> Self {
+        Self {
+            experiment,
+            reviewer,
+            config: config.clone(),
+            templates: PromptTemplates::builtin(),
+            manifest: SandboxManifest::default(),
+            keep_repos: false,
+            gemini: GeminiConfig::default(),
+        }
+    }
+
+    /// Sets the template registry used for review prompts.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Sets the manifest the primaries and verify commands run under.
+    pub fn with_manifest(mut self, manifest: SandboxManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Sets how Gemini CLI primaries are invoked.
+    pub fn with_gemini(mut self, config: GeminiConfig) -> Self {
+        self.gemini = config;
+        self
+    }
+
+    /// Keeps the ephemeral repositories instead of deleting them.
+    pub fn with_keep_repos(mut self, keep: bool) -> Self {
+        self.keep_repos = keep;
+        self
+    }
+
+    /// Runs both variants in parallel and compares them.
+    pub async fn run(&self) -> Result<ExperimentReport> {
+        let a = create_runner_with(&self.experiment.a.llm, &self.gemini)?;
+        let b = create_runner_with(&self.experiment.b.llm, &self.gemini)?;
+        Ok(self.run_with(a, b).await)
+    }
+
+    /// Runs both variants with the given primaries.
+    async fn run_with(&self, a: Arc<dyn LLMRunner>, b: Arc<dyn LLMRunner>) -> ExperimentReport {
+        let (a, b) = tokio::join!(
+            self.run_variant(&self.experiment.a, a),
+            self.run_variant(&self.experiment.b, b)
+        );
+        ExperimentReport {
+            name: self.experiment.name.clone(),
+            prompt: self.experiment.prompt.clone(),
+            a,
+            b,
+        }
+    }
+
+    /// Runs one variant in a fresh copy of the fixture.
+    async fn run_variant(
+        &self,
+        variant: &ExperimentVariant,
+        primary: Arc<dyn LLMRunner>,
+    ) -> VariantOutcome {
+        let mut outcome = VariantOutcome::new(variant);
+        let repo = match ephemeral_repo(&self.experiment.fixture) {
+            Ok(repo) => repo,
+            Err(e) => {
+                outcome.error = Some(format!("failed to copy the fixture: {}", e));
+                return outcome;
+            }
+        };
+
+        if let Err(e) = self.try_run_variant(&repo, primary, &mut outcome).await {
+            tracing::warn!(variant = %variant.name, error = %e, "experiment variant failed");
+            outcome.error = Some(e.to_string());
+        }
+
+        if self.keep_repos {
+            outcome.repo_path = Some(repo);
+        } else if let Err(e) = std::fs::remove_dir_all(&repo) {
+            tracing::warn!(path = %repo.display(), error = %e, "failed to remove experiment repo");
+        }
+        outcome
+    }
+
+    async fn try_run_variant(
+        &self,
+        repo: &Path,
+        primary: Arc<dyn LLMRunner>,
+        outcome: &mut VariantOutcome,
+    ) -> Result<()> {
+        let base = SystemGit
+            .run(repo, &["rev-parse", "HEAD"])?
+            .trim()
+            .to_string();
+        let config = LLMSpawnConfig {
+            prompt: self.experiment.prompt.clone(),
+            working_dir: repo.to_path_buf(),
+            manifest: self.manifest.clone(),
+            model: outcome.variant.model.clone(),
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let started = Instant::now();
+        let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
+        let (result, usage) = tokio::join!(primary.spawn(config, tx), async {
+            let mut usage = TokenUsage::default();
+            while let Some(output) = rx.recv().await {
+                if let LLMOutput::Usage(tokens) = output {
+                    usage.add(&tokens);
+                }
+            }
+            usage
+        });
+        let result = result?;
+        outcome.duration = started.elapsed();
+        outcome.success = result.success;
+        outcome.cost_usd = result
+            .model
+            .as_deref()
+            .or(outcome.variant.model.as_deref())
+            .map(|model| {
+                budget::estimate_cost(
+                    model,
+                    (usage.input_tokens + usage.cache_read_tokens) as usize,
+                    usage.output_tokens as usize,
+                )
+            });
+        outcome.usage = usage;
+
+        // Commit the changes so the review sees new files too
+        SystemGit.run(repo, &["add", "-A"])?;
+        outcome.files_changed = SystemGit
+            .run(repo, &["diff", "--cached", "--name-only"])?
+            .lines()
+            .count();
+        if outcome.files_changed > 0 {
+            let message = format!("Experiment variant {}", outcome.variant.name);
+            SystemGit.run(
+                repo,
+                &[IDENTITY, &["commit", "-q", "-m", &message]].concat(),
+            )?;
+        }
+
+        if self.experiment.verify.is_enabled() {
+            let failure = self.experiment.verify.run(repo, &self.manifest).await;
+            outcome.validation_passed = Some(failure.is_none());
+            outcome.failed_command = failure.map(|f| f.command);
+        }
+
+        outcome.review = Some(self.review(repo, &base).await?);
+        Ok(())
+    }
+
+    /// Reviews the changes since `base` once in every configured domain.
+    async fn review(&self, repo: &Path, base: &str) -> Result<ReviewResult> {
+        let mut config = SpawnTeamConfig {
+            max_iterations: 1,
+            ..self.config.clone()
+        };
+        if self.experiment.review_domains != DomainSelection::default() {
+            config.review_domains = self.experiment.review_domains.clone();
+        }
+        // The single pass never fixes, so the reviewer doubles as primary
+        let review_loop =
+            DomainReviewLoop::new(self.reviewer.clone(), self.reviewer.clone(), &config)
+                .with_templates(self.templates.clone())
+                .with_manifest(self.manifest.clone())
+                .with_base_ref(base);
+
+        let mut domains: Vec<String> = self.config.gating.domains.keys().cloned().collect();
+        domains.sort_unstable();
+        if domains.is_empty() {
+            domains.push(DEFAULT_REVIEW_DOMAIN.to_string());
+        }
+
+        let mut reviews = Vec::new();
+        for domain in review_loop.route(repo, &domains)? {
+            let focus = domain_focus(&domain);
+            let outcome = review_loop
+                .run(repo, &self.experiment.prompt, &domain, focus.as_deref())
+                .await?;
+            reviews.extend(outcome.reviews());
+        }
+        Ok(merge_reviews(reviews))
+    }
+}
+
+/// Copies `fixture` into a new temporary git repository and returns its
+/// path. A fixture that is a git repository is cloned; any other
+/// directory is copied and committed.
+fn ephemeral_repo(fixture: &Path) -> Result<PathBuf> {
+    if !fixture.is_dir() {
+        return Err(Error::InvalidPath(fixture.to_path_buf()));
+    }
+    let repo = std::env::temp_dir().join(format!(
+        "improbability-drive-experiment-{}",
+        uuid::Uuid::new_v4()
+    ));
+    let repo_str = repo
+        .to_str()
+        .ok_or_else(|| Error::InvalidPath(repo.clone()))?;
+
+    if fixture.join(".git").exists() {
+        let fixture_str = fixture
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(fixture.to_path_buf()))?;
+        SystemGit.run(fixture, &["clone", "--quiet", fixture_str, repo_str])?;
+    } else {
+        copy_dir(fixture, &repo)?;
+        SystemGit.run(&repo, &["init", "-q"])?;
+        SystemGit.run(&repo, &["add", "-A"])?;
+        SystemGit.run(
+            &repo,
+            &[
+                IDENTITY,
+                &["commit", "-q", "--allow-empty", "-m", "Experiment fixture"],
+            ]
+            .concat(),
+        )?;
+    }
+    Ok(repo)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::LLMResult;
+    use crate::team::ReviewVerdict;
+    use async_trait::async_trait;
+    use std::process::Command;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Primary that writes `files` and reports token usage.
+    struct WritingPrimary {
+        files: Vec<(&'static str, &'static str)>,
+        output_tokens: u64,
+    }
+
+    #[async_trait]
+    impl LLMRunner for WritingPrimary {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            for (file, content) in &self.files {
+                std::fs::write(config.working_dir.join(file), content)?;
+            }
+            let _ = output_tx
+                .send(LLMOutput::Usage(TokenUsage {
+                    input_tokens: 10_000,
+                    output_tokens: self.output_tokens,
+                    cache_read_tokens: 0,
+                }))
+                .await;
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: config.model,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "primary"
+        }
+    }
+
+    /// Reviewer that flags diffs containing "todo!" and approves the rest.
+    struct TodoReviewer {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LLMRunner for TodoReviewer {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            let response = if config.prompt.contains("todo!") {
+                r#"{"verdict": "needs_changes", "suggestions": [{"file": "lib.rs", "line": 1, "issue": "unimplemented", "suggestion": "implement it", "severity": "high"}]}"#
+            } else {
+                r#"{"verdict": "approved", "suggestions": []}"#
+            };
+            self.prompts.lock().unwrap().push(config.prompt);
+            let _ = output_tx
+                .send(LLMOutput::Stdout(response.to_string()))
+                .await;
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "reviewer"
+        }
+    }
+
+    fn variant(name: &str) -> ExperimentVariant {
+        ExperimentVariant {
+            name: name.to_string(),
+            llm: default_llm(),
+            model: Some(name.to_string()),
+        }
+    }
+
+    #[test]
+    fn loads_experiment_toml() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("experiment.toml");
+        std::fs::write(
+            &path,
+            r#"
+name = "parser"
+prompt = "Implement the parser"
+fixture = "fixtures/widgets"
+
+[verify]
+commands = ["cargo test"]
+
+[a]
+name = "sonnet"
+model = "sonnet"
+
+[b]
+name = "opus"
+llm = "gemini-cli"
+"#,
+        )
+        .unwrap();
+
+        let experiment = Experiment::load(&path).unwrap();
+        assert_eq!(experiment.fixture, dir.path().join("fixtures/widgets"));
+        assert_eq!(experiment.verify.commands, vec!["cargo test"]);
+        assert_eq!(experiment.a.llm, "claude-code");
+        assert_eq!(experiment.b.llm, "gemini-cli");
+        assert_eq!(experiment.b.model, None);
+
+        std::fs::write(
+            &path,
+            "name = \"x\"\nprompt = \"p\"\nfixture = \".\"\n[a]\nname = \"same\"\n[b]\nname = \"same\"\n",
+        )
+        .unwrap();
+        assert!(Experiment::load(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn compares_variants_in_separate_repos() {
+        let fixture = TempDir::new().unwrap();
+        std::fs::write(fixture.path().join("lib.rs"), "// widgets\n").unwrap();
+
+        let experiment = Experiment {
+            name: "parser".to_string(),
+            prompt: "Implement the parser".to_string(),
+            fixture: fixture.path().to_path_buf(),
+            verify: VerificationConfig {
+                commands: vec!["! grep -q todo lib.rs".to_string()],
+                ..Default::default()
+            },
+            review_domains: Default::default(),
+            a: variant("sonnet"),
+            b: variant("opus"),
+        };
+        let reviewer = Arc::new(TodoReviewer {
+            prompts: Mutex::new(Vec::new()),
+        });
+        let runner =
+            ExperimentRunner::new(experiment, reviewer.clone(), &SpawnTeamConfig::default());
+        let report = runner
+            .run_with(
+                Arc::new(WritingPrimary {
+                    files: vec![("lib.rs", "fn parse() { todo!() }\n")],
+                    output_tokens: 1_000,
+                }),
+                Arc::new(WritingPrimary {
+                    files: vec![
+                        ("lib.rs", "fn parse() -> u32 { 1 }\n"),
+                        ("test.rs", "#[test] fn parses() {}\n"),
+                    ],
+                    output_tokens: 4_000,
+                }),
+            )
+            .await;
+
+        let (a, b) = (&report.a, &report.b);
+        assert!(a.error.is_none() && b.error.is_none());
+        assert_eq!((a.files_changed, b.files_changed), (1, 2));
+        assert_eq!(a.validation_passed, Some(false));
+        assert_eq!(b.validation_passed, Some(true));
+        assert_eq!(a.findings_at_least(ReviewSeverity::High), 1);
+        assert_eq!(b.review.as_ref().unwrap().verdict, ReviewVerdict::Approved);
+        assert!(a.cost_usd.unwrap() < b.cost_usd.unwrap());
+        assert_eq!(reviewer.prompts.lock().unwrap().len(), 2);
+        // The fixture itself is untouched and the copies are gone
+        assert_eq!(
+            std::fs::read_to_string(fixture.path().join("lib.rs")).unwrap(),
+            "// widgets\n"
+        );
+        assert!(a.repo_path.is_none());
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| | sonnet | opus |"));
+        assert!(markdown.contains("| Files changed | 1 | 2 |"));
+        assert!(markdown.contains("| Validation | failed (`! grep -q todo lib.rs`) | passed |"));
+        assert!(markdown.contains("| High or critical | 1 | 0 |"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Git invocations behind a trait.
+//!
+//! Code that decides what to do from git's answers (does this branch have
+//! commits of its own, would these branches merge cleanly) takes a
+//! [`GitExecutor`] rather than running `git` itself. [`SystemGit`] runs the
+//! real binary; [`MockGit`] answers from a script and records every
+//! invocation, so that logic can be unit tested without a repository.
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::error::{Error, Result};
+
+/// Result of a finished git invocation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitOutput {
+    /// Exit status, if git was not killed by a signal.
+    pub status: Option<i32>,
+    /// Standard output.
+    pub stdout: String,
+    /// Standard error.
+    pub stderr: String,
+}
+
+impl GitOutput {
+    /// A successful invocation printing `stdout`.
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        Self {
+            status: Some(0),
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// A failed invocation exiting with `status` and printing `stderr`.
+    pub fn failed(status: i32, stderr: impl Into<String>) -> Self {
+        Self {
+            status: Some(status),
+            stdout: String::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    /// Returns whether git exited successfully.
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    /// Returns stdout, or an [`Error::GitCommand`] if the invocation with
+    /// `args` failed.
+    pub fn into_result(self, args: &[&str]) -> Result<String> {
+        if !self.success() {
+            return Err(Error::GitCommand {
+                command: args.first().unwrap_or(&"").to_string(),
+                status: self.status,
+                stderr: self.stderr.trim().to_string(),
+            });
+        }
+        Ok(self.stdout)
+    }
+}
+
+/// Runs git commands.
+pub trait GitExecutor: Send + Sync {
+    /// Runs git with `args` in `dir`. Fails only if git could not be run;
+    /// an unsuccessful exit is reported in the output.
+    fn output(&self, dir: &Path, args: &[&str]) -> Result<GitOutput>;
+
+    /// Runs git with `args` in `dir`, returning stdout or an error if git
+    /// exited unsuccessfully.
+    fn run(&self, dir: &Path, args: &[&str]) -> Result<String> {
+        self.output(dir, args)?.into_result(args)
+    }
+}
+
+/// Runs the `git` binary.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemGit;
+
+impl GitExecutor for SystemGit {
+    fn output(&self, dir: &Path, args: &[&str]) -> Result<GitOutput> {
+        let output = Command::new("git").current_dir(dir).args(args).output()?;
+        Ok(GitOutput {
+            status: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// Git that answers from a script, for tests.
+///
+/// Each invocation gets the output of the first scripted entry whose
+/// arguments it starts with; an invocation nothing matches fails. Every
+/// invocation is recorded.
+#[derive(Debug, Default)]
+pub struct MockGit {
+    script: Vec<(Vec<String>, GitOutput)>,
+    calls: Mutex<Vec<Vec<String>>>,
+}
+
+impl MockGit {
+    /// Creates a mock with an empty script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers invocations starting with `args` with `output`.
+    pub fn on(mut self, args: &[&str], output: GitOutput) -> Self {
+        self.script
+            .push((args.iter().map(|a| a.to_string()).collect(), output));
+        self
+    }
+
+    /// Returns the arguments of every invocation so far, in order.
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl GitExecutor for MockGit {
+    fn output(&self, _dir: &Path, args: &[&str]) -> Result<GitOutput> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(args.iter().map(|a| a.to_string()).collect());
+        self.script
+            .iter()
+            .find(|(prefix, _)| {
+                args.starts_with(&prefix.iter().map(String::as_str).collect::<Vec<_>>())
+            })
+            .map(|(_, output)| output.clone())
+            .ok_or_else(|| Error::GitCommand {
+                command: args.first().unwrap_or(&"").to_string(),
+                status: None,
+                stderr: format!("unexpected invocation: git {}", args.join(" ")),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn system_git_reports_failures_in_output() {
+        let dir = TempDir::new().unwrap();
+
+        let output = SystemGit
+            .output(dir.path(), &["rev-parse", "HEAD"])
+            .unwrap();
+        assert!(!output.success());
+        let err = SystemGit
+            .run(dir.path(), &["rev-parse", "HEAD"])
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("git rev-parse failed (exit 128)"));
+
+        SystemGit.run(dir.path(), &["init", "-q"]).unwrap();
+        assert!(SystemGit
+            .run(dir.path(), &["status", "--porcelain"])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn mock_git_answers_by_prefix_and_records_calls() {
+        let git = MockGit::new()
+            .on(&["rev-parse", "--abbrev-ref"], GitOutput::ok("main\n"))
+            .on(&["push"], GitOutput::failed(1, "rejected\n"));
+
+        assert_eq!(
+            git.run(Path::new("."), &["rev-parse", "--abbrev-ref", "HEAD"])
+                .unwrap(),
+            "main\n"
+        );
+        let err = git
+            .run(Path::new("."), &["push", "origin", "main"])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "git push failed (exit 1): rejected");
+        assert!(git.run(Path::new("."), &["fetch"]).is_err());
+        assert_eq!(git.calls().len(), 3);
+        assert_eq!(git.calls()[1], ["push", "origin", "main"]);
+    }
+}
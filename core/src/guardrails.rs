@@ -0,0 +1,395 @@
+//! Large-file and binary guardrails on commits.
+//!
+//! Before the drive commits, staged files are checked against a size limit
+//! and for unexpected binary content. Offending files are unstaged — or, with
+//! `lfs` enabled, tracked with Git LFS — and each one is reported as a
+//! [`FileViolation`], so bloat is flagged instead of silently pushed.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Validate, ValidationResult};
+use crate::cruise::{AuditFinding, FindingSeverity};
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+
+/// Default largest file committed, in megabytes.
+pub const DEFAULT_MAX_FILE_MB: u64 = 10;
+
+/// Binaries committed by default (git glob pathspecs).
+pub const DEFAULT_ALLOWED_BINARIES: &[&str] = &[
+    "**/*.png",
+    "**/*.jpg",
+    "**/*.jpeg",
+    "**/*.gif",
+    "**/*.ico",
+    "**/*.woff",
+    "**/*.woff2",
+];
+
+/// Size and binary checks applied to staged files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileGuardrails {
+    /// Largest file committed, in megabytes. `0` disables the check.
+    #[serde(default = "default_max_file_mb")]
+    pub max_file_mb: u64,
+
+    /// Refuses binary files that do not match `allowed_binaries`.
+    #[serde(default = "default_true")]
+    pub block_binaries: bool,
+
+    /// Binaries committed anyway (git glob pathspecs).
+    #[serde(default = "default_allowed_binaries")]
+    pub allowed_binaries: Vec<String>,
+
+    /// Tracks offending files with Git LFS instead of refusing them.
+    #[serde(default)]
+    pub lfs: bool,
+}
+
+fn default_max_file_mb() -> u64 {
+    DEFAULT_MAX_FILE_MB
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_allowed_binaries() -> Vec<String> {
+    DEFAULT_ALLOWED_BINARIES
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+impl Default for FileGuardrails {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Why a file was stopped at commit time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ViolationKind {
+    /// The file exceeds the size limit.
+    TooLarge {
+        /// File size in bytes.
+        bytes: u64,
+    },
+    /// The file is an unexpected binary.
+    Binary,
+}
+
+/// A staged file that broke a guardrail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileViolation {
+    /// Path relative to the worktree root.
+    pub path: PathBuf,
+    /// Which guardrail it broke.
+    #[serde(flatten)]
+    pub kind: ViolationKind,
+    /// Whether the file was committed through Git LFS rather than refused.
+    #[serde(default)]
+    pub lfs: bool,
+}
+
+impl FileViolation {
+    /// Converts the violation into an audit finding.
+    pub fn to_finding(&self) -> AuditFinding {
+        let problem = match &self.kind {
+            ViolationKind::TooLarge { bytes } => {
+                format!("file is {:.1} MB", *bytes as f64 / (1024.0 * 1024.0))
+            }
+            ViolationKind::Binary => "unexpected binary file".to_string(),
+        };
+        let (severity, outcome) = if self.lfs {
+            (FindingSeverity::Info, "committed through Git LFS")
+        } else {
+            (FindingSeverity::Warning, "left out of the commit")
+        };
+        AuditFinding {
+            severity,
+            category: "repository".to_string(),
+            description: format!("{}; {}", problem, outcome),
+            file: Some(self.path.display().to_string()),
+            line: None,
+            suggestion: (!self.lfs).then(|| {
+                "Generate the file at build time, or enable commits.guardrails.lfs".to_string()
+            }),
+        }
+    }
+}
+
+impl FileGuardrails {
+    /// Creates guardrails with the default size limit and binary allowlist.
+    pub fn new() -> Self {
+        Self {
+            max_file_mb: default_max_file_mb(),
+            block_binaries: true,
+            allowed_binaries: default_allowed_binaries(),
+            lfs: false,
+        }
+    }
+
+    /// Sets the size limit in megabytes (`0` disables it).
+    pub fn with_max_file_mb(mut self, max_file_mb: u64) -> Self {
+        self.max_file_mb = max_file_mb;
+        self
+    }
+
+    /// Sets whether offending files are tracked with Git LFS.
+    pub fn with_lfs(mut self, lfs: bool) -> Self {
+        self.lfs = lfs;
+        self
+    }
+
+    /// Returns the staged files in `worktree` that break a guardrail.
+    pub fn check(&self, worktree: &Path) -> Result<Vec<FileViolation>> {
+        let mut violations = Vec::new();
+
+        if self.max_file_mb > 0 {
+            let limit = self.max_file_mb * 1024 * 1024;
+            let staged = SystemGit.run(
+                worktree,
+                &["diff", "--cached", "--name-only", "--diff-filter=AM", "-z"],
+            )?;
+            for path in staged.split('\0').filter(|p| !p.is_empty()) {
+                let bytes = std::fs::metadata(worktree.join(path))
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                if bytes > limit {
+                    violations.push(FileViolation {
+                        path: PathBuf::from(path),
+                        kind: ViolationKind::TooLarge { bytes },
+                        lfs: false,
+                    });
+                }
+            }
+        }
+
+        if self.block_binaries {
+            let mut args: Vec<String> = [
+                "diff",
+                "--cached",
+                "--numstat",
+                "--no-renames",
+                "--diff-filter=AM",
+                "-z",
+                "--",
+                ".",
+            ]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+            args.extend(
+                self.allowed_binaries
+                    .iter()
+                    .map(|g| format!(":(exclude,glob){}", g)),
+            );
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            // Binary files are listed as "-\t-\t<path>"
+            for entry in SystemGit.run(worktree, &args)?.split('\0') {
+                let Some(path) = entry.strip_prefix("-\t-\t") else {
+                    continue;
+                };
+                let path = PathBuf::from(path);
+                if !violations.iter().any(|v| v.path == path) {
+                    violations.push(FileViolation {
+                        path,
+                        kind: ViolationKind::Binary,
+                        lfs: false,
+                    });
+                }
+            }
+        }
+
+        violations.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(violations)
+    }
+
+    /// Checks the staged files in `worktree` and keeps offending ones out
+    /// of the next commit, returning them.
+    ///
+    /// With `lfs` enabled, offending files are tracked with Git LFS and
+    /// restaged as pointers; if LFS is unavailable they are unstaged like
+    /// any other violation.
+    pub fn enforce(&self, worktree: &Path) -> Result<Vec<FileViolation>> {
+        let mut violations = self.check(worktree)?;
+        if violations.is_empty() {
+            return Ok(violations);
+        }
+
+        if self.lfs {
+            match track_with_lfs(worktree, &violations) {
+                Ok(()) => {
+                    for violation in &mut violations {
+                        violation.lfs = true;
+                    }
+                    return Ok(violations);
+                }
+                Err(e) => tracing::warn!(error = %e, "Git LFS unavailable, refusing files"),
+            }
+        }
+
+        let mut args = vec!["rm", "--cached", "-q", "--"];
+        let paths: Vec<String> = violations
+            .iter()
+            .map(|v| v.path.display().to_string())
+            .collect();
+        args.extend(paths.iter().map(String::as_str));
+        SystemGit.run(worktree, &args)?;
+
+        Ok(violations)
+    }
+}
+
+impl Validate for FileGuardrails {
+    fn validate(&self) -> ValidationResult {
+        let mut result = ValidationResult::default();
+
+        if self.max_file_mb == 0 && !self.block_binaries {
+            result.add_warning("commits.guardrails disables both size and binary checks");
+        }
+        if self.allowed_binaries.iter().any(|g| g.trim().is_empty()) {
+            result.add_error("commits.guardrails.allowed_binaries must not contain empty globs");
+        }
+
+        result
+    }
+}
+
+/// Tracks `violations` with Git LFS and restages them as pointers.
+fn track_with_lfs(worktree: &Path, violations: &[FileViolation]) -> Result<()> {
+    let paths: Vec<String> = violations
+        .iter()
+        .map(|v| v.path.display().to_string())
+        .collect();
+
+    let mut args = vec!["lfs", "track", "--filename", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    SystemGit.run(worktree, &args)?;
+
+    // Renormalizing applies the LFS filter to the already-staged files
+    SystemGit.run(worktree, &["add", "--", ".gitattributes"])?;
+    let mut args = vec!["add", "--renormalize", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    SystemGit.run(worktree, &args)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for args in [
+            vec!["init", "-q"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+        ] {
+            SystemGit.run(dir.path(), &args).unwrap();
+        }
+        dir
+    }
+
+    fn stage(dir: &Path, path: &str, content: &[u8]) {
+        let file = dir.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+        SystemGit.run(dir, &["add", "--", path]).unwrap();
+    }
+
+    fn staged(dir: &Path) -> String {
+        SystemGit
+            .run(dir, &["diff", "--cached", "--name-only"])
+            .unwrap()
+    }
+
+    #[test]
+    fn oversized_and_binary_files_are_unstaged() {
+        let repo = repo();
+        let dir = repo.path();
+        stage(dir, "src/lib.rs", b"fn main() {}\n");
+        stage(dir, "data/dump.bin", &[0, 159, 146, 150, 0, 1]);
+        stage(dir, "assets/logo.png", &[0x89, b'P', b'N', b'G', 0, 0]);
+        stage(dir, "big.txt", &vec![b'a'; 1024 * 1024 + 1]);
+        let guardrails = FileGuardrails::new().with_max_file_mb(1);
+
+        let violations = guardrails.enforce(dir).unwrap();
+
+        assert_eq!(
+            violations,
+            vec![
+                FileViolation {
+                    path: PathBuf::from("big.txt"),
+                    kind: ViolationKind::TooLarge {
+                        bytes: 1024 * 1024 + 1
+                    },
+                    lfs: false,
+                },
+                FileViolation {
+                    path: PathBuf::from("data/dump.bin"),
+                    kind: ViolationKind::Binary,
+                    lfs: false,
+                },
+            ]
+        );
+        assert_eq!(staged(dir), "assets/logo.png\nsrc/lib.rs\n");
+    }
+
+    #[test]
+    fn disabled_checks_allow_everything() {
+        let repo = repo();
+        let dir = repo.path();
+        stage(dir, "data/dump.bin", &[0, 1, 2, 0]);
+        let guardrails = FileGuardrails {
+            block_binaries: false,
+            ..FileGuardrails::new()
+        };
+
+        assert!(guardrails.enforce(dir).unwrap().is_empty());
+        assert_eq!(staged(dir), "data/dump.bin\n");
+    }
+
+    #[test]
+    fn violations_become_findings() {
+        let refused = FileViolation {
+            path: PathBuf::from("big.txt"),
+            kind: ViolationKind::TooLarge {
+                bytes: 15 * 1024 * 1024,
+            },
+            lfs: false,
+        };
+        let finding = refused.to_finding();
+        assert_eq!(finding.severity, FindingSeverity::Warning);
+        assert_eq!(finding.file.as_deref(), Some("big.txt"));
+        assert_eq!(
+            finding.description,
+            "file is 15.0 MB; left out of the commit"
+        );
+
+        let routed = FileViolation {
+            lfs: true,
+            ..refused
+        };
+        assert_eq!(routed.to_finding().severity, FindingSeverity::Info);
+        assert!(routed.to_finding().suggestion.is_none());
+    }
+
+    #[test]
+    fn guardrails_parse_from_toml() {
+        let guardrails: FileGuardrails = toml::from_str("max_file_mb = 50\nlfs = true").unwrap();
+        assert_eq!(guardrails.max_file_mb, 50);
+        assert!(guardrails.lfs);
+        assert!(guardrails.block_binaries);
+        assert_eq!(
+            guardrails.allowed_binaries.len(),
+            DEFAULT_ALLOWED_BINARIES.len()
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Project instruction files for agents.
+//!
+//! Many repositories carry instructions for coding agents in files such as
+//! `CLAUDE.md`, `AGENTS.md`, or `.cursorrules`. Spawned LLMs pick them up
+//! from their working directory, so sandboxes receive copies of any the
+//! checkout lacks, and reviewers get a summary so they judge changes
+//! against the same conventions.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::Result;
+
+/// Instruction files recognized at the repository root, in priority order.
+pub const INSTRUCTION_FILES: &[&str] = &["CLAUDE.md", "AGENTS.md", ".cursorrules"];
+
+/// Most summary lines kept per instruction file.
+const MAX_SUMMARY_LINES: usize = 30;
+
+/// Longest summary line kept, in characters.
+const MAX_LINE_CHARS: usize = 160;
+
+/// An instruction file found in a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionFile {
+    /// File name relative to the repository root.
+    pub path: String,
+    /// File contents.
+    pub text: String,
+}
+
+/// Returns the instruction files present at the root of `dir`.
+///
+/// Empty and unreadable files are skipped.
+pub fn detect(dir: &Path) -> Vec<InstructionFile> {
+    INSTRUCTION_FILES
+        .iter()
+        .filter_map(|name| {
+            let text = std::fs::read_to_string(dir.join(name)).ok()?;
+            (!text.trim().is_empty()).then(|| InstructionFile {
+                path: name.to_string(),
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Copies instruction files from `source` that are missing in `sandbox`.
+///
+/// A sandbox checked out from a commit lacks ignored instruction files and
+/// those outside its sparse patterns. Untracked files that are not ignored
+/// are left out, since the spawn's commit would pick them up. Returns the
+/// copied file names.
+pub fn carry_into(source: &Path, sandbox: &Path) -> Result<Vec<String>> {
+    let mut copied = Vec::new();
+    for file in detect(source) {
+        let target = sandbox.join(&file.path);
+        if target.exists() {
+            continue;
+        }
+        if is_untracked(source, &file.path) {
+            tracing::warn!(
+                file = %file.path,
+                "instruction file is untracked; commit it or add it to .gitignore to share it with sandboxes"
+            );
+            continue;
+        }
+        std::fs::write(&target, &file.text)?;
+        copied.push(file.path);
+    }
+    if !copied.is_empty() {
+        tracing::debug!(files = ?copied, "copied instruction files into sandbox");
+    }
+    Ok(copied)
+}
+
+/// Summarizes instruction files for a reviewer prompt.
+///
+/// Keeps headings and list items, which carry most rules, falling back to
+/// the first lines for files without that structure. Returns an empty
+/// string when there is nothing to summarize.
+pub fn summarize(files: &[InstructionFile]) -> String {
+    let mut summary = String::new();
+    for file in files {
+        let lines = summary_lines(&file.text);
+        if lines.is_empty() {
+            continue;
+        }
+        if !summary.is_empty() {
+            summary.push('\n');
+        }
+        summary.push_str(&format!("From `{}`:\n\n", file.path));
+        for line in lines {
+            summary.push_str(&line);
+            summary.push('\n');
+        }
+    }
+    summary
+}
+
+/// Picks the lines of one file that go into its summary.
+fn summary_lines(text: &str) -> Vec<String> {
+    let mut in_code = false;
+    let mut structured = Vec::new();
+    let mut plain = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || trimmed.is_empty() {
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            structured.push(format!("**{}**", heading.trim_start_matches('#').trim()));
+        } else if is_list_item(trimmed) {
+            structured.push(line.trim_end().to_string());
+        } else {
+            plain.push(format!("- {}", trimmed));
+        }
+    }
+
+    let lines = if structured.iter().any(|l| !l.starts_with("**")) {
+        structured
+    } else {
+        plain
+    };
+    let total = lines.len();
+    let mut lines: Vec<String> = lines
+        .into_iter()
+        .take(MAX_SUMMARY_LINES)
+        .map(|line| truncate_line(&line))
+        .collect();
+    if total > MAX_SUMMARY_LINES {
+        lines.push(format!("- ... ({} more lines)", total - MAX_SUMMARY_LINES));
+    }
+    lines
+}
+
+fn is_list_item(line: &str) -> bool {
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return true;
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && line[digits..].starts_with(". ")
+}
+
+fn truncate_line(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_CHARS {
+        return line.to_string();
+    }
+    let kept: String = line.chars().take(MAX_LINE_CHARS).collect();
+    format!("{}...", kept.trim_end())
+}
+
+/// Returns whether `path` is untracked and not ignored in `repo`.
+fn is_untracked(repo: &Path, path: &str) -> bool {
+    let output = Command::new("git")
+        .current_dir(repo)
+        .args(["ls-files", "--others", "--exclude-standard", "--", path])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => !output.stdout.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn carries_ignored_instruction_files_only() {
+        let source = TempDir::new().unwrap();
+        let sandbox = TempDir::new().unwrap();
+        git(source.path(), &["init", "-q"]);
+        std::fs::write(source.path().join(".gitignore"), "CLAUDE.md\n").unwrap();
+        std::fs::write(source.path().join("CLAUDE.md"), "- Use tabs\n").unwrap();
+        std::fs::write(source.path().join("AGENTS.md"), "- Draft rules\n").unwrap();
+        std::fs::write(source.path().join(".cursorrules"), "Be terse.\n").unwrap();
+        std::fs::write(sandbox.path().join(".cursorrules"), "Be terse.\n").unwrap();
+
+        let copied = carry_into(source.path(), sandbox.path()).unwrap();
+
+        assert_eq!(copied, vec!["CLAUDE.md"]);
+        assert_eq!(
+            std::fs::read_to_string(sandbox.path().join("CLAUDE.md")).unwrap(),
+            "- Use tabs\n"
+        );
+        assert!(!sandbox.path().join("AGENTS.md").exists());
+    }
+
+    #[test]
+    fn summary_keeps_headings_and_rules() {
+        let files = vec![
+            InstructionFile {
+                path: "CLAUDE.md".to_string(),
+                text: "# Project\n\nSome intro prose.\n\n## Style\n\n- Prefer `Result` over panics\n1. Run `cargo test`\n\n```sh\n- not a rule\n```\n".to_string(),
+            },
+            InstructionFile {
+                path: ".cursorrules".to_string(),
+                text: "Always write doc comments.\n".to_string(),
+            },
+        ];
+
+        let summary = summarize(&files);
+
+        assert_eq!(
+            summary,
+            "From `CLAUDE.md`:\n\n**Project**\n**Style**\n- Prefer `Result` over panics\n1. Run `cargo test`\n\nFrom `.cursorrules`:\n\n- Always write doc comments.\n"
+        );
+        assert!(summarize(&[]).is_empty());
+    }
+
+    #[test]
+    fn summary_caps_long_files() {
+        let text: String = (0..40).map(|i| format!("- rule {}\n", i)).collect();
+        let files = vec![InstructionFile {
+            path: "AGENTS.md".to_string(),
+            text,
+        }];
+
+        let summary = summarize(&files);
+
+        assert!(summary.contains("- rule 29\n"));
+        assert!(!summary.contains("- rule 30\n"));
+        assert!(summary.ends_with("- ... (10 more lines)\n"));
+    }
+}
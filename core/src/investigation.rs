@@ -0,0 +1,76 @@
+//! Read-only investigation spawns.
+//!
+//! Some tasks ("audit this module", "explain this failure") need an answer
+//! rather than a change. A spawn created with [`SpawnConfig::read_only`]
+//! runs with read-only tools, never commits or opens a PR, and returns its
+//! findings as a Markdown report.
+//!
+//! [`SpawnConfig::read_only`]: crate::spawn::SpawnConfig::read_only
+
+/// Heading that starts the report in the LLM's answer.
+pub const REPORT_HEADING: &str = "## Report";
+
+/// Appended to the prompt of a read-only spawn.
+pub const REPORT_INSTRUCTIONS: &str = "\n\n### Output Format\n\n\
+This is an investigation: you cannot edit files or run commands. Read what you need, \
+then end your final message with your findings in Markdown, starting with the line \
+`## Report`. Cite files as `path:line`.";
+
+/// Extracts the report from the LLM's messages.
+///
+/// Takes everything from the last [`REPORT_HEADING`] line. Without one, the
+/// last message is the report. Returns `None` if the LLM said nothing.
+pub fn extract_report(messages: &[String]) -> Option<String> {
+    let transcript = messages.join("\n");
+    let start = transcript
+        .match_indices(REPORT_HEADING)
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || transcript[..i].ends_with('\n'))
+        .last();
+    let report = match start {
+        Some(start) => &transcript[start..],
+        None => messages.iter().rev().find(|m| !m.trim().is_empty())?,
+    };
+    Some(format!("{}\n", report.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_starts_at_last_heading() {
+        let messages = vec![
+            "I will write a ## Report at the end.".to_string(),
+            "## Report\n\nDraft".to_string(),
+            "Looking further.".to_string(),
+            "## Report\n\n- `src/auth.rs:12` skips expiry checks".to_string(),
+            "## Report".to_string(),
+            "More detail.".to_string(),
+        ];
+
+        assert_eq!(
+            extract_report(&messages[..4]).unwrap(),
+            "## Report\n\n- `src/auth.rs:12` skips expiry checks\n"
+        );
+        assert_eq!(
+            extract_report(&messages).unwrap(),
+            "## Report\nMore detail.\n"
+        );
+    }
+
+    #[test]
+    fn report_falls_back_to_last_message() {
+        let messages = vec![
+            "Reading files".to_string(),
+            "The cache is never evicted.".to_string(),
+            " ".to_string(),
+        ];
+
+        assert_eq!(
+            extract_report(&messages).unwrap(),
+            "The cache is never evicted.\n"
+        );
+        assert!(extract_report(&[]).is_none());
+    }
+}
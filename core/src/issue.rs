@@ -0,0 +1,237 @@
+//! GitHub issues as run inputs.
+//!
+//! `from-issue` turns an issue into a spawn prompt, or for issues labelled
+//! for cruise, a cruise prompt that can only be dry-run so far. The resulting
+//! PR closes the issue through a closing keyword in its description, and
+//! progress is mirrored to a single comment on the issue that is edited as
+//! the run advances. Work a cruise run leaves unfinished can be filed as
+//! new issues.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result, ResultExt};
+
+/// Labels that route an issue to a cruise run instead of a single spawn.
+pub const CRUISE_LABELS: &[&str] = &["cruise", "epic"];
+
+/// A GitHub issue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Issue {
+    /// Issue number.
+    pub number: u64,
+    /// Issue URL.
+    pub url: String,
+    /// Issue title.
+    pub title: String,
+    /// Issue description.
+    pub body: String,
+    /// Label names.
+    pub labels: Vec<String>,
+}
+
+impl Issue {
+    /// Returns the issue as `owner/repo#N`, or `#N` if the URL does not
+    /// name a repository.
+    pub fn reference(&self) -> String {
+        let parts: Vec<&str> = self.url.trim_end_matches('/').rsplit('/').collect();
+        match parts.as_slice() {
+            [_, "issues", repo, owner, ..] => format!("{}/{}#{}", owner, repo, self.number),
+            _ => format!("#{}", self.number),
+        }
+    }
+
+    /// Returns whether the issue's labels ask for a cruise run.
+    pub fn is_cruise(&self) -> bool {
+        self.labels
+            .iter()
+            .any(|label| CRUISE_LABELS.contains(&label.to_lowercase().as_str()))
+    }
+
+    /// Builds the run prompt from the title, description, and labels.
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!(
+            "{}\n\nResolve GitHub issue {}.",
+            self.title,
+            self.reference()
+        );
+        if !self.body.trim().is_empty() {
+            prompt.push_str(&format!("\n\n## Issue description\n\n{}", self.body.trim()));
+        }
+        if !self.labels.is_empty() {
+            prompt.push_str(&format!("\n\nLabels: {}", self.labels.join(", ")));
+        }
+        prompt
+    }
+}
+
+/// Parses `gh issue view --json number,url,title,body,labels` output.
+pub fn parse_issue_json(json: &str) -> Result<Issue> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("gh output", e))?;
+    let number = json["number"]
+        .as_u64()
+        .ok_or_else(|| Error::GitHub("issue has no number".to_string()))?;
+    let text = |key: &str| json[key].as_str().unwrap_or_default().to_string();
+    let labels = json["labels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|label| label["name"].as_str().map(str::to_string))
+        .collect();
+
+    Ok(Issue {
+        number,
+        url: text("url"),
+        title: text("title"),
+        body: text("body"),
+        labels,
+    })
+}
+
+/// Reads issues and keeps a progress comment on them up to date.
+pub struct IssueTracker {
+    /// Repository the `gh` commands run in.
+    repo_path: PathBuf,
+    /// Whether the progress comment has been posted.
+    commented: bool,
+}
+
+impl IssueTracker {
+    /// Creates a tracker for issues of the repository at `repo_path`.
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            commented: false,
+        }
+    }
+
+    /// Fetches an issue by URL or number.
+    pub fn fetch(&self, issue: &str) -> Result<Issue> {
+        let args = [
+            "issue",
+            "view",
+            issue,
+            "--json",
+            "number,url,title,body,labels",
+        ];
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(args)
+            .output()
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+
+        parse_issue_json(&String::from_utf8_lossy(&output.stdout))
+            .context(format!("failed to read issue {}", issue))
+    }
+
+    /// Posts `body` as the run's progress comment on `issue`, editing the
+    /// comment posted earlier instead of adding another.
+    pub fn post_progress(&mut self, issue: &Issue, body: &str) -> Result<()> {
+        let mut args = vec!["issue", "comment", issue.url.as_str(), "--body", body];
+        if self.commented {
+            args.push("--edit-last");
+        }
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(&args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output)
+                .context(format!("failed to comment on issue #{}", issue.number)));
+        }
+
+        self.commented = true;
+        Ok(())
+    }
+
+    /// Opens an issue with `title`, `body` and `labels`, returning its URL.
+    pub fn create(&self, title: &str, body: &str, labels: &[String]) -> Result<String> {
+        let mut args = vec!["issue", "create", "--title", title, "--body", body];
+        for label in labels {
+            args.extend(["--label", label.as_str()]);
+        }
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(&args)
+            .output()
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output)
+                .context(format!("failed to open issue \"{}\"", title)));
+        }
+
+        // gh prints the new issue's URL last
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .last()
+            .unwrap_or_default()
+            .trim()
+            .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(labels: &[&str]) -> Issue {
+        Issue {
+            number: 42,
+            url: "https://github.com/acme/widgets/issues/42".to_string(),
+            title: "Crash on empty config".to_string(),
+            body: "Steps to reproduce...".to_string(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_gh_issue_json() {
+        let json = r#"{
+            "number": 42,
+            "url": "https://github.com/acme/widgets/issues/42",
+            "title": "Crash on empty config",
+            "body": "Steps to reproduce...",
+            "labels": [{"id": "x", "name": "bug", "color": "d73a4a"}]
+        }"#;
+
+        assert_eq!(parse_issue_json(json).unwrap(), issue(&["bug"]));
+        assert!(parse_issue_json("{}").is_err());
+    }
+
+    #[test]
+    fn reference_names_repository() {
+        assert_eq!(issue(&[]).reference(), "acme/widgets#42");
+
+        let local = Issue {
+            url: String::new(),
+            ..issue(&[])
+        };
+        assert_eq!(local.reference(), "#42");
+    }
+
+    #[test]
+    fn labels_route_to_cruise() {
+        assert!(!issue(&["bug"]).is_cruise());
+        assert!(issue(&["Epic"]).is_cruise());
+    }
+
+    #[test]
+    fn prompt_includes_title_body_and_reference() {
+        let prompt = issue(&["bug"]).prompt();
+
+        assert!(
+            prompt.starts_with("Crash on empty config\n\nResolve GitHub issue acme/widgets#42.")
+        );
+        assert!(prompt.contains("## Issue description\n\nSteps to reproduce..."));
+        assert!(prompt.ends_with("Labels: bug"));
+    }
+}
@@ -0,0 +1,266 @@
+//! JUnit XML results for CI test reporting.
+//!
+//! A [`TestSuite`] turns finished runs, for example one cruise run per E2E
+//! fixture, into test cases: a failed run becomes a failed case whose
+//! message is the failure reason, and each case carries its duration and
+//! the PRs it opened. [`TestSuite::write`] saves the suite as `junit.xml`,
+//! which CI systems render as test results, plus a Markdown summary for
+//! job summaries and PR comments.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cruise::status::format_secs;
+use crate::error::Result;
+use crate::report::RunReport;
+
+/// Name of the JUnit XML file.
+pub const JUNIT_FILE: &str = "junit.xml";
+
+/// Name of the Markdown summary written next to it.
+pub const JUNIT_SUMMARY_FILE: &str = "summary.md";
+
+/// One run, as a test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    /// Case name, e.g. the fixture or run ID.
+    pub name: String,
+    /// Group the case belongs to, e.g. the kind of run.
+    pub classname: String,
+    /// Wall-clock duration.
+    pub duration: Duration,
+    /// Why the run failed; `None` if it passed.
+    pub failure: Option<String>,
+    /// Failed cruise tasks and their errors, shown with the failure.
+    pub details: Vec<String>,
+    /// PRs the run opened.
+    pub pr_urls: Vec<String>,
+}
+
+impl TestCase {
+    /// Creates the case for a finished run.
+    pub fn from_report(report: &RunReport) -> Self {
+        let failure = (!report.success).then(|| {
+            let reason = report.summary.lines().next().unwrap_or_default().trim();
+            if reason.is_empty() {
+                format!("{} run failed", report.kind)
+            } else {
+                reason.to_string()
+            }
+        });
+        let details = report
+            .tasks
+            .iter()
+            .filter_map(|task| {
+                let error = task.error.as_ref()?;
+                Some(format!("{}: {}", task.task_id, error))
+            })
+            .collect();
+        Self {
+            name: report.run_id.clone(),
+            classname: report.kind.clone(),
+            duration: report.duration,
+            failure,
+            details,
+            pr_urls: report.pr_urls.clone(),
+        }
+    }
+
+    /// Sets the case name, e.g. to the fixture the run was for.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+/// A set of runs, as a JUnit test suite.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestSuite {
+    /// Suite name.
+    pub name: String,
+    /// Cases, in the order they ran.
+    pub cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    /// Creates an empty suite.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            cases: Vec::new(),
+        }
+    }
+
+    /// Adds a case.
+    pub fn push(&mut self, case: TestCase) {
+        self.cases.push(case);
+    }
+
+    /// Returns the number of failed cases.
+    pub fn failures(&self) -> usize {
+        self.cases.iter().filter(|c| c.failure.is_some()).count()
+    }
+
+    /// Returns the total duration of all cases.
+    pub fn duration(&self) -> Duration {
+        self.cases.iter().map(|c| c.duration).sum()
+    }
+
+    /// Formats the suite as JUnit XML.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            self.cases.len(),
+            self.failures(),
+            self.duration().as_secs_f64()
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\">\n",
+            escape_xml(&self.name),
+            self.cases.len(),
+            self.failures(),
+            self.duration().as_secs_f64()
+        ));
+        for case in &self.cases {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape_xml(&case.name),
+                escape_xml(&case.classname),
+                case.duration.as_secs_f64()
+            ));
+            if case.failure.is_none() && case.pr_urls.is_empty() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            if let Some(failure) = &case.failure {
+                xml.push_str(&format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    escape_xml(failure),
+                    escape_xml(&case.details.join("\n"))
+                ));
+            }
+            if !case.pr_urls.is_empty() {
+                xml.push_str(&format!(
+                    "      <system-out>{}</system-out>\n",
+                    escape_xml(&case.pr_urls.join("\n"))
+                ));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    /// Formats the suite as a Markdown summary table.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "## {}: {} of {} passed\n\n",
+            self.name,
+            self.cases.len() - self.failures(),
+            self.cases.len()
+        );
+        out.push_str("| Case | Result | Duration | PRs | Failure |\n");
+        out.push_str("|------|--------|----------|-----|---------|\n");
+        for case in &self.cases {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                cell(&case.name),
+                if case.failure.is_some() {
+                    "failed"
+                } else {
+                    "passed"
+                },
+                format_secs(case.duration.as_secs()),
+                case.pr_urls.join(" "),
+                cell(case.failure.as_deref().unwrap_or_default())
+            ));
+        }
+        out
+    }
+
+    /// Writes `junit.xml` and `summary.md` to `dir`, returning their paths.
+    pub fn write(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir)?;
+        let xml = dir.join(JUNIT_FILE);
+        std::fs::write(&xml, self.to_xml())?;
+        let summary = dir.join(JUNIT_SUMMARY_FILE);
+        std::fs::write(&summary, self.to_markdown())?;
+        Ok(vec![xml, summary])
+    }
+}
+
+/// Makes `text` safe to show in a markdown table cell.
+fn cell(text: &str) -> String {
+    text.lines().next().unwrap_or_default().replace('|', "\\|")
+}
+
+/// Escapes `text` for XML attributes and element content.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::result::TaskResult;
+    use crate::cruise::task::TaskStatus;
+    use tempfile::TempDir;
+
+    fn suite() -> TestSuite {
+        let mut passed = RunReport::new("cruise", "run-1", "Add auth")
+            .with_duration(Duration::from_secs(90))
+            .with_pr_url("https://github.com/acme/app/pull/1");
+        passed.success = true;
+        let mut failed = RunReport::new("cruise", "run-2", "Add billing")
+            .with_duration(Duration::from_millis(1500));
+        failed.summary = "Build failed: 1 task blocked <CRUISE-002>".to_string();
+        failed.tasks = vec![TaskResult {
+            task_id: "CRUISE-002".to_string(),
+            status: TaskStatus::Blocked,
+            pr_url: None,
+            duration: Duration::from_secs(1),
+            error: Some("tests & lints failed".to_string()),
+        }];
+
+        let mut suite = TestSuite::new("e2e");
+        suite.push(TestCase::from_report(&passed).with_name("auth-fixture"));
+        suite.push(TestCase::from_report(&failed));
+        suite
+    }
+
+    #[test]
+    fn xml_reports_cases_with_failures_and_prs() {
+        let xml = suite().to_xml();
+
+        assert!(xml.contains("<testsuites tests=\"2\" failures=\"1\" time=\"91.500\">"));
+        assert!(xml.contains(
+            "<testcase name=\"auth-fixture\" classname=\"cruise\" time=\"90.000\">\n      \
+             <system-out>https://github.com/acme/app/pull/1</system-out>"
+        ));
+        assert!(xml.contains(
+            "<failure message=\"Build failed: 1 task blocked &lt;CRUISE-002&gt;\">\
+             CRUISE-002: tests &amp; lints failed</failure>"
+        ));
+    }
+
+    #[test]
+    fn write_saves_xml_and_summary() {
+        let dir = TempDir::new().unwrap();
+
+        let written = suite().write(dir.path()).unwrap();
+
+        assert_eq!(written.len(), 2);
+        let summary = std::fs::read_to_string(dir.path().join(JUNIT_SUMMARY_FILE)).unwrap();
+        assert!(summary.starts_with("## e2e: 1 of 2 passed\n"));
+        assert!(summary.contains(
+            "| auth-fixture | passed | 1m 30s | https://github.com/acme/app/pull/1 |  |\n"
+        ));
+        assert!(summary.contains("| run-2 | failed |"));
+    }
+}
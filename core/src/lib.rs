@@ -3,31 +3,133 @@
 //! This library provides the core functionality for launching isolated LLM instances
 //! in git worktree sandboxes with intelligent resource provisioning and lifecycle management.
 
+pub mod audit;
+pub mod benchmark;
+pub mod branch;
+pub mod cancel;
+pub mod checks;
+pub mod commit;
 pub mod config;
+pub mod conflict;
+pub mod context;
 pub mod cruise;
+pub mod diff;
+pub mod dry_run;
 pub mod error;
+pub mod experiment;
+pub mod git;
+pub mod github_actions;
+pub mod guardrails;
+pub mod instructions;
+pub mod investigation;
+pub mod issue;
+pub mod junit;
+pub mod logs;
+pub mod mcp;
 pub mod monitor;
+pub mod patch;
 pub mod permissions;
 pub mod pr;
+pub mod pr_fix;
+pub mod pr_review;
+pub mod preflight;
+pub mod prompt;
+pub mod question;
+pub mod queue;
+pub mod release_notes;
+pub mod report;
+pub mod review_ledger;
+pub mod review_loop;
+pub mod review_threads;
+pub mod role_pipeline;
 pub mod runner;
 pub mod sandbox;
+pub mod scaffold;
 pub mod secrets;
+pub mod security;
+pub mod settings;
 pub mod spawn;
+pub mod stats;
 pub mod team;
+pub mod timeline;
+pub mod verification;
 pub mod watcher;
 
-pub use error::Error;
-pub use monitor::{ProgressMonitor, ProgressSummary, TimeoutConfig, TimeoutReason};
+pub use audit::{
+    format_observability_markdown, AuditLog, ModelUse, PermissionGrant, PermissionRequest,
+    PostedComment, SpawnObservability, ToolCallRecord, OBSERVABILITY_FILE,
+};
+pub use benchmark::{
+    parse_benchmarks, BenchmarkComparison, BenchmarkConfig, BenchmarkReport, Measurement,
+    PERFORMANCE_DOMAIN,
+};
+pub use branch::BranchNamer;
+pub use cancel::{open_partial_pr, wind_down, CancellationToken, Checkpoint};
+pub use checks::{
+    summarize_log, CheckAnnotation, CheckRun, CheckSource, ChecksClient, CiConfig, CiFailure,
+    CiStatus, CiWatcher,
+};
+pub use commit::{
+    CommitContext, CommitMessages, CommitSigning, CommitTrailers, CommitType, GitHistoryPolicy,
+    SigningFormat,
+};
+pub use conflict::{ConflictResolutionConfig, ConflictResolver, ResolutionOutcome};
+pub use context::{ContextConfig, ContextPack};
+pub use diff::{DiffConfig, DiffOptions, DiffProvider, DiffScope, GitDiffProvider};
+pub use dry_run::{DryRun, ExecutionPlan, PlannedAction};
+pub use error::{Error, PatchError, PlanError};
+pub use experiment::{Experiment, ExperimentReport, ExperimentRunner, ExperimentVariant};
+pub use git::{GitExecutor, GitOutput, MockGit, SystemGit};
+pub use github_actions::{ActionsConfig, ActionsContext, ActionsEvent, FixRequest};
+pub use guardrails::{FileGuardrails, FileViolation, ViolationKind};
+pub use instructions::InstructionFile;
+pub use issue::{Issue, IssueTracker};
+pub use junit::{TestCase, TestSuite, JUNIT_FILE, JUNIT_SUMMARY_FILE};
+pub use logs::{find_spawn_dir, LogFollower, LogRenderer, LogSource};
+pub use mcp::McpServer;
+pub use monitor::{
+    BudgetPhase, BudgetShares, LoopConfig, LoopDetector, ProgressMonitor, ProgressSummary,
+    TimeoutBudget, TimeoutConfig, TimeoutReason,
+};
+pub use patch::WriteMode;
 pub use permissions::{PermissionDetector, PermissionError, PermissionErrorType, PermissionFix};
-pub use pr::{ConflictFile, ConflictStrategy, MergeStatus, PRManager, PullRequest};
-pub use runner::{ClaudeRunner, GeminiRunner, LLMOutput, LLMResult, LLMRunner, LLMSpawnConfig};
+pub use pr::{
+    all_reviews_approved, format_review_comment, latest_review, parse_review_comment,
+    parse_review_thread_id, parse_review_threads, review_section, truncate_body,
+    upsert_review_section, CommitOutcome, ConflictFile, ConflictStrategy, MergeStatus, PRManager,
+    PullRequest, ReviewThread, MAX_BODY_BYTES,
+};
+pub use pr_fix::{PrFeedback, PrFixOutcome, PrFixer};
+pub use pr_review::{fetch_pr, PrDetails, PrReviewOutcome, PrReviewer};
+pub use preflight::{CheckStatus, Preflight, PreflightReport, Tool, ToolCheck, ToolOutput};
+pub use prompt::{PromptTemplate, PromptTemplates};
+pub use question::{AnswerChannel, CliChannel, PrCommentChannel};
+pub use queue::{QueueConfig, QueueEntry, QueueSnapshot, SpawnPriority, SpawnQueue};
+pub use release_notes::{MergedPr, ReleaseNotes, ReleaseNotesGenerator};
+pub use report::{ReportConfig, RunReport};
+pub use review_ledger::{DomainLedger, ReviewLedger};
+pub use review_loop::{DomainReviewLoop, DomainReviewOutcome, PhaseState, ReviewPass};
+pub use review_threads::{FixCheck, FixVerifier, ReviewThreads, ThreadResolution};
+pub use role_pipeline::{PipelineOutcome, RoleOutcome, RolePipeline};
+pub use runner::{
+    ClaudeRunner, FallbackRunner, GeminiApprovalMode, GeminiConfig, GeminiRunner, LLMOutput,
+    LLMResult, LLMRunner, LLMSpawnConfig,
+};
 pub use sandbox::{Sandbox, SandboxManifest, SandboxProvider};
+pub use scaffold::Scaffold;
 pub use secrets::{SecretError, SecretRef, SecretSource, SecretsManager};
-pub use spawn::{SpawnConfig, SpawnResult, SpawnStatus};
+pub use security::{parse_security_findings, SecurityFinding, SecurityReviewPromptBuilder};
+pub use settings::{AuxModelConfig, Settings, SettingsLoader, SettingsOverrides};
+pub use spawn::{ChangeKind, ChangeSummary, SpawnConfig, SpawnResult, SpawnStatus};
+pub use stats::{RunMetrics, StatsConfig, StatsReport, StatsStore};
 pub use team::{
-    CoordinationMode, FixPromptBuilder, ReviewPromptBuilder, ReviewResult, ReviewSuggestion,
-    ReviewVerdict, SpawnTeamConfig, SpawnTeamResult,
+    CoordinationMode, DomainRoute, DomainSelection, FixPromptBuilder, GateAction, GatedReview,
+    GitHubReviewPromptBuilder, PhaseModels, ReviewGatePolicy, ReviewGating, ReviewPromptBuilder,
+    ReviewResult, ReviewSeverity, ReviewSuggestion, ReviewVerdict, SpawnTeamConfig,
+    SpawnTeamResult, TeamRole,
 };
+pub use timeline::{EventKind, EventLog, Timeline, TimelineEvent};
+pub use verification::{VerificationConfig, VerificationFailure};
 pub use watcher::{RecoveryStrategy, TerminationReason, WatcherAgent, WatcherConfig, WatcherResult};
 
 pub use config::{
@@ -37,9 +139,9 @@ pub use config::{
 pub use cruise::{
     generate_plan_markdown, generate_pr_body, parse_plan_json, plan_to_beads,
     validate_plan as validate_cruise_plan, AdherenceCheck, AdherenceStatus, ApprovalConfig,
-    AuditFinding, BuildResult, BuildingConfig, CruiseConfig, CruisePlan, CruiseResult, CruiseTask,
-    FindingSeverity, FunctionalTestResult, PlanPromptBuilder, PlanResult, Planner, PlanningConfig,
-    PlanReviewPromptBuilder, PrStrategy, RepoLifecycle, ReviewPhase, TaskComplexity, TaskResult,
-    TaskStatus, TestConfig, TestLevel, ValidationConfig as CruiseValidationConfig,
-    ValidationResult as CruiseValidationResult,
+    AuditFinding, BuildResult, BuildingConfig, CruiseConfig, CruisePlan, CruiseResult,
+    CruiseStatus, CruiseTask, FindingSeverity, FunctionalTestResult, PlanDiff, PlanPromptBuilder,
+    PlanResult, PlanReviewPromptBuilder, PlanRevision, Planner, PlanningConfig, PrStrategy,
+    RepoLifecycle, ReviewPhase, TaskComplexity, TaskResult, TaskStatus, TestConfig, TestLevel,
+    ValidationConfig as CruiseValidationConfig, ValidationResult as CruiseValidationResult,
 };
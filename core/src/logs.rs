@@ -0,0 +1,468 @@
+//! Streaming a spawn's logs to the terminal.
+//!
+//! `logs <spawn-id>` prints a spawn's stdout, stderr and events, and with
+//! `--follow` keeps printing as they grow. Stream-json output is rendered
+//! event by event: tool calls are highlighted and file writes summarized.
+//! The spawn's log directory is looked up in every logs directory the
+//! [`LocationRegistry`] knows, so logs written before `logs_dir` changed
+//! are still found.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::audit::ToolCallRecord;
+use crate::error::{Error, Result};
+use crate::runner::{LLMOutput, StreamEvent, StreamParser};
+use crate::sandbox::LocationRegistry;
+
+/// Name of the LLM's stdout log in a spawn's log directory.
+pub const STDOUT_FILE: &str = "stdout.log";
+
+/// Name of the LLM's stderr log in a spawn's log directory.
+pub const STDERR_FILE: &str = "stderr.log";
+
+/// Name of the event log in a spawn's log directory.
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+/// Longest tool target shown, in characters.
+const MAX_TARGET_CHARS: usize = 120;
+
+const RESET: &str = "\x1b[0m";
+const BOLD_CYAN: &str = "\x1b[1;36m";
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const DIM: &str = "\x1b[2m";
+
+/// A log file of a spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    /// The LLM's standard output.
+    Stdout,
+    /// The LLM's standard error.
+    Stderr,
+    /// Structured spawn events.
+    Events,
+}
+
+impl LogSource {
+    /// Every log file, in the order they are read.
+    pub const ALL: [LogSource; 3] = [LogSource::Stdout, LogSource::Stderr, LogSource::Events];
+
+    /// Returns the file name of this log.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            LogSource::Stdout => STDOUT_FILE,
+            LogSource::Stderr => STDERR_FILE,
+            LogSource::Events => EVENTS_FILE,
+        }
+    }
+}
+
+/// Finds the log directory of `spawn_id`.
+///
+/// Looks in `logs_dir` and then in every logs directory recorded for
+/// `repo_path`. A unique prefix of the spawn id is enough.
+pub fn find_spawn_dir(repo_path: &Path, logs_dir: &Path, spawn_id: &str) -> Result<PathBuf> {
+    let registry = LocationRegistry::load(repo_path)?;
+    let mut dirs = vec![logs_dir];
+    for dir in registry.logs_dirs() {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    if let Some(dir) = dirs
+        .iter()
+        .map(|d| d.join(spawn_id))
+        .find(|d| !spawn_id.is_empty() && d.is_dir())
+    {
+        return Ok(dir);
+    }
+
+    let mut matches = Vec::new();
+    for dir in &dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(spawn_id) && entry.path().is_dir() {
+                matches.push(entry.path());
+            }
+        }
+    }
+
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 => Err(Error::Config(format!(
+            "no logs found for spawn '{}' in {}",
+            spawn_id,
+            dirs.iter()
+                .map(|d| d.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+        _ => Err(Error::Config(format!(
+            "spawn id '{}' is ambiguous: {}",
+            spawn_id,
+            matches
+                .iter()
+                .filter_map(|m| m.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Reads the lines added to a spawn's logs since the last poll.
+#[derive(Debug)]
+pub struct LogFollower {
+    dir: PathBuf,
+    offsets: [u64; 3],
+    partial: [String; 3],
+}
+
+impl LogFollower {
+    /// Creates a follower that starts at the beginning of the logs in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            offsets: [0; 3],
+            partial: Default::default(),
+        }
+    }
+
+    /// Returns the complete lines written since the last call.
+    ///
+    /// A line still being written is returned once its newline arrives.
+    /// Logs that do not exist yet are skipped.
+    pub fn poll(&mut self) -> Result<Vec<(LogSource, String)>> {
+        let mut lines = Vec::new();
+        for (i, source) in LogSource::ALL.into_iter().enumerate() {
+            let path = self.dir.join(source.file_name());
+            let Ok(mut file) = std::fs::File::open(&path) else {
+                continue;
+            };
+            // A truncated log starts over
+            if file.metadata()?.len() < self.offsets[i] {
+                self.offsets[i] = 0;
+                self.partial[i].clear();
+            }
+            file.seek(SeekFrom::Start(self.offsets[i]))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            self.offsets[i] += bytes.len() as u64;
+
+            self.partial[i].push_str(&String::from_utf8_lossy(&bytes));
+            if let Some(end) = self.partial[i].rfind('\n') {
+                let rest = self.partial[i].split_off(end + 1);
+                let complete = std::mem::replace(&mut self.partial[i], rest);
+                lines.extend(complete.lines().map(|l| (source, l.to_string())));
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// Which CLI wrote the stream-json output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Claude,
+    Gemini,
+}
+
+/// Renders log lines for the terminal.
+#[derive(Debug)]
+pub struct LogRenderer {
+    color: bool,
+    claude: StreamParser,
+    gemini: StreamParser,
+    dialect: Option<Dialect>,
+}
+
+impl LogRenderer {
+    /// Creates a renderer, with ANSI colors if `color` is set.
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            claude: StreamParser::claude(),
+            gemini: StreamParser::gemini(),
+            dialect: None,
+        }
+    }
+
+    /// Renders one line of `source`, returning the lines to print.
+    pub fn render(&mut self, source: LogSource, line: &str) -> Vec<String> {
+        match source {
+            LogSource::Stdout => match self.parse(line) {
+                Some(events) => events
+                    .into_iter()
+                    .filter_map(|e| self.render_event(e))
+                    .collect(),
+                None if line.trim().is_empty() => Vec::new(),
+                None => vec![line.to_string()],
+            },
+            LogSource::Stderr => vec![self.paint(RED, &format!("stderr: {}", line))],
+            LogSource::Events => self.render_spawn_event(line).into_iter().collect(),
+        }
+    }
+
+    /// Renders text still buffered at the end of the output.
+    pub fn finish(&mut self) -> Vec<String> {
+        let events = match self.dialect {
+            Some(Dialect::Claude) => self.claude.finish(),
+            _ => self.gemini.finish(),
+        };
+        events
+            .into_iter()
+            .filter_map(|e| self.render_event(e))
+            .collect()
+    }
+
+    /// Parses a stream-json line, settling on the CLI that understood the
+    /// first event.
+    fn parse(&mut self, line: &str) -> Option<Vec<StreamEvent>> {
+        match self.dialect {
+            Some(Dialect::Claude) => self.claude.feed(line),
+            Some(Dialect::Gemini) => self.gemini.feed(line),
+            None => {
+                let claude = self.claude.feed(line);
+                let gemini = self.gemini.feed(line);
+                match (claude, gemini) {
+                    (Some(events), _) if !events.is_empty() => {
+                        self.dialect = Some(Dialect::Claude);
+                        Some(events)
+                    }
+                    (_, Some(events)) if !events.is_empty() => {
+                        self.dialect = Some(Dialect::Gemini);
+                        Some(events)
+                    }
+                    (None, None) => None,
+                    _ => Some(Vec::new()),
+                }
+            }
+        }
+    }
+
+    fn render_event(&self, event: StreamEvent) -> Option<String> {
+        match event {
+            StreamEvent::Init { session_id, model } => Some(self.paint(
+                DIM,
+                &format!(
+                    "session started (model {}, session {})",
+                    model.as_deref().unwrap_or("unknown"),
+                    session_id.as_deref().unwrap_or("unknown")
+                ),
+            )),
+            StreamEvent::Text(text) => {
+                let text = text.trim_end();
+                (!text.is_empty()).then(|| text.to_string())
+            }
+            StreamEvent::ToolUse { id, name, input } => {
+                let summary = write_summary(&input);
+                let outputs = StreamEvent::ToolUse {
+                    id,
+                    name: name.clone(),
+                    input: input.clone(),
+                }
+                .into_outputs();
+                let written = outputs.into_iter().find_map(|o| match o {
+                    LLMOutput::FileWrite(path) => Some(path),
+                    _ => None,
+                });
+                Some(match written {
+                    Some(path) => {
+                        self.paint(GREEN, &format!("✎ {} {}{}", name, path.display(), summary))
+                    }
+                    None => self.paint(BOLD_CYAN, &format!("▶ {} {}", name, target(&name, &input))),
+                })
+            }
+            StreamEvent::ToolResult {
+                output, is_error, ..
+            } => {
+                if is_error {
+                    let first = output.lines().next().unwrap_or("").trim();
+                    Some(self.paint(RED, &format!("✗ tool failed: {}", first)))
+                } else {
+                    let count = output.lines().count();
+                    (count > 0).then(|| {
+                        self.paint(
+                            DIM,
+                            &format!("  ↳ {} line{}", count, if count == 1 { "" } else { "s" }),
+                        )
+                    })
+                }
+            }
+            StreamEvent::Usage(usage) => Some(self.paint(
+                DIM,
+                &format!(
+                    "tokens: {} in, {} out, {} cached",
+                    usage.input_tokens, usage.output_tokens, usage.cache_read_tokens
+                ),
+            )),
+            StreamEvent::PermissionDenied { name, input } => Some(self.paint(
+                YELLOW,
+                &format!("⚠ permission denied: {} {}", name, target(&name, &input)),
+            )),
+        }
+    }
+
+    fn render_spawn_event(&self, line: &str) -> Option<String> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let Ok(Value::Object(mut fields)) = serde_json::from_str::<Value>(line) else {
+            return Some(self.paint(DIM, line));
+        };
+        let kind = ["type", "event"]
+            .iter()
+            .find_map(|key| fields.remove(*key))
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_else(|| "event".to_string());
+        let details = fields
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(s) => format!("{}={}", key, s),
+                other => format!("{}={}", key, other),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(format!(
+            "{} {}",
+            self.paint(YELLOW, &format!("[{}]", kind)),
+            details
+        ))
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Returns what a tool call acted on, shortened for one line.
+fn target(name: &str, input: &Value) -> String {
+    let target = ToolCallRecord::now(name, input.to_string()).target();
+    let target = target.lines().next().unwrap_or("");
+    if target.chars().count() > MAX_TARGET_CHARS {
+        let cut: String = target.chars().take(MAX_TARGET_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        target.to_string()
+    }
+}
+
+/// Summarizes the lines a file write adds and removes, e.g. ` (+3 -1)`.
+fn write_summary(input: &Value) -> String {
+    let lines = |key: &str, value: &Value| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map_or(0, |s| s.lines().count())
+    };
+    let (added, removed) = match input.get("edits").and_then(Value::as_array) {
+        Some(edits) => edits.iter().fold((0, 0), |(a, r), edit| {
+            (a + lines("new_string", edit), r + lines("old_string", edit))
+        }),
+        None => (
+            lines("content", input) + lines("new_string", input),
+            lines("old_string", input),
+        ),
+    };
+    if added == 0 && removed == 0 {
+        String::new()
+    } else {
+        format!(" (+{} -{})", added, removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::Locations;
+    use tempfile::TempDir;
+
+    #[test]
+    fn spawn_dir_is_found_in_recorded_logs_dirs() {
+        let repo = TempDir::new().unwrap();
+        let current = TempDir::new().unwrap();
+        let earlier = TempDir::new().unwrap();
+        std::fs::create_dir(earlier.path().join("3f2a-old")).unwrap();
+        std::fs::create_dir(current.path().join("9c1d-new")).unwrap();
+        std::fs::create_dir(current.path().join("9c2e-new")).unwrap();
+        LocationRegistry::record(repo.path(), Locations::new("/tmp/sb", earlier.path())).unwrap();
+
+        let found = find_spawn_dir(repo.path(), current.path(), "3f2a").unwrap();
+        assert_eq!(found, earlier.path().join("3f2a-old"));
+        let found = find_spawn_dir(repo.path(), current.path(), "9c1d-new").unwrap();
+        assert_eq!(found, current.path().join("9c1d-new"));
+
+        let err = find_spawn_dir(repo.path(), current.path(), "9c").unwrap_err();
+        assert!(err.to_string().contains("ambiguous"));
+        assert!(find_spawn_dir(repo.path(), current.path(), "missing").is_err());
+    }
+
+    #[test]
+    fn follower_returns_complete_new_lines() {
+        let dir = TempDir::new().unwrap();
+        let stdout = dir.path().join(STDOUT_FILE);
+        let mut follower = LogFollower::new(dir.path());
+        assert!(follower.poll().unwrap().is_empty());
+
+        std::fs::write(&stdout, "one\ntw").unwrap();
+        assert_eq!(
+            follower.poll().unwrap(),
+            vec![(LogSource::Stdout, "one".to_string())]
+        );
+
+        std::fs::write(&stdout, "one\ntwo\n").unwrap();
+        std::fs::write(dir.path().join(STDERR_FILE), "boom\n").unwrap();
+        assert_eq!(
+            follower.poll().unwrap(),
+            vec![
+                (LogSource::Stdout, "two".to_string()),
+                (LogSource::Stderr, "boom".to_string()),
+            ]
+        );
+        assert!(follower.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn renderer_highlights_tools_and_summarizes_writes() {
+        let mut renderer = LogRenderer::new(false);
+        let lines = [
+            r#"{"type":"system","subtype":"init","session_id":"s-1","model":"sonnet"}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Fixing it"},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test"}},{"type":"tool_use","id":"t2","name":"Edit","input":{"file_path":"src/lib.rs","old_string":"a","new_string":"b\nc"}}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"error: failed\nmore","is_error":true}]}}"#,
+            "plain text",
+        ];
+        let rendered: Vec<String> = lines
+            .iter()
+            .flat_map(|l| renderer.render(LogSource::Stdout, l))
+            .collect();
+
+        assert_eq!(
+            rendered,
+            vec![
+                "session started (model sonnet, session s-1)",
+                "Fixing it",
+                "▶ Bash cargo test",
+                "✎ Edit src/lib.rs (+2 -1)",
+                "✗ tool failed: error: failed",
+                "plain text",
+            ]
+        );
+        assert_eq!(
+            renderer.render(LogSource::Events, r#"{"type":"phase","name":"review"}"#),
+            vec!["[phase] name=review"]
+        );
+        assert!(LogRenderer::new(true).render(LogSource::Stderr, "x")[0].starts_with(RED));
+    }
+}
@@ -2,51 +2,354 @@
 //!
 //! CLI tool for spawning sandboxed LLM instances.
 
-use std::path::PathBuf;
+use std::io::IsTerminal;
 
-use improbability_drive::sandbox::WorktreeSandbox;
+use improbability_drive::cruise::{CruiseStatus, RunAborter};
+use improbability_drive::error::exit_code;
+use improbability_drive::github_actions::format_job_summary;
+use improbability_drive::sandbox::{LocationRegistry, WorkspaceSandbox, WorktreeSandbox};
 use improbability_drive::spawn::Spawner;
-use improbability_drive::{SandboxManifest, SpawnConfig, SpawnStatus};
+use improbability_drive::{
+    fetch_pr, find_spawn_dir, ActionsContext, ChecksClient, CiStatus, CiWatcher, DryRun,
+    Experiment, ExperimentRunner, FixPromptBuilder, FixRequest, FixVerifier, Issue, IssueTracker,
+    LogFollower, LogRenderer, McpServer, PrFixer, PrReviewer, Preflight, PromptTemplates,
+    ReleaseNotesGenerator, ReviewThreads, RunMetrics, SandboxManifest, Settings, SettingsLoader,
+    SettingsOverrides, StatsStore, Timeline, Validate, WriteMode,
+};
 
 fn main() {
+    // Get current repo path
+    let repo_path = std::env::current_dir().expect("failed to get current directory");
+
+    // Location flags apply to every command, so they are taken out first
+    let mut args: Vec<String> = std::env::args().collect();
+    let overrides = take_location_flags(&mut args);
+
+    // Load settings (user config, project config, profile, env, flags)
+    let settings = match SettingsLoader::new()
+        .with_repo(&repo_path)
+        .with_overrides(overrides)
+        .load()
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Failed to load settings: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    // In serve mode stdout carries the protocol, so logs go to stderr
+    let serve = args.get(1).map(String::as_str) == Some("serve");
+
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
+    let level = if settings.logging.debug {
+        "debug"
+    } else {
+        settings.logging.level.as_str()
+    };
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(level));
+    if serve {
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(filter).init();
+    }
+
+    match settings.validate().into_result() {
+        Ok(warnings) => {
+            for warning in warnings {
+                tracing::warn!("{}", warning);
+            }
+        }
+        Err(e) => {
+            eprintln!("Invalid settings: {}", e);
+            std::process::exit(exit_code::CONFIG);
+        }
+    }
+
+    if serve {
+        let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+        let server = McpServer::new(settings, repo_path);
+        if let Err(e) = runtime.block_on(server.serve_stdio()) {
+            eprintln!("MCP server failed: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+        return;
+    }
 
     // Parse args (basic for now - will add clap in later phase)
-    let args: Vec<String> = std::env::args().collect();
+    let program = args.remove(0);
+
+    if args.first().map(String::as_str) == Some("stats") {
+        show_stats(&repo_path, &args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("logs") {
+        show_logs(&repo_path, &settings, &args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("replay") {
+        replay(&repo_path, &settings, &args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("release-notes") {
+        release_notes(&repo_path, &settings, &args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("doctor") {
+        doctor(&settings, args.get(1));
+        return;
+    }
+
+    let mut command = match args.first().map(String::as_str) {
+        Some(
+            c @ ("spawn" | "spawn-team" | "cruise" | "from-issue" | "review" | "fix-pr"
+            | "experiment"),
+        ) if args.len() > 1 => {
+            let c = c.to_string();
+            args.remove(0);
+            c
+        }
+        _ => "spawn".to_string(),
+    };
+    if command == "cruise" && args.first().map(String::as_str) == Some("status") {
+        cruise_status(&repo_path, &args[1..]);
+        return;
+    }
+    if command == "cruise" && args.first().map(String::as_str) == Some("abort") {
+        cruise_abort(&repo_path, &settings, args.get(1));
+        return;
+    }
 
-    if args.len() < 2 {
-        eprintln!("Usage: {} <prompt>", args[0]);
+    if command == "review" {
+        review_pr(&repo_path, &settings, &args);
+        return;
+    }
+    if command == "fix-pr" {
+        fix_pr(&repo_path, &settings, &args[0]);
+        return;
+    }
+    if command == "experiment" {
+        run_experiment(&repo_path, &settings, &args);
+        return;
+    }
+
+    let mut dry_run = false;
+    let mut write_mode = None;
+    while let Some(flag) = args.first().map(String::as_str) {
+        match flag {
+            "--dry-run" => dry_run = true,
+            "--patch" => write_mode = Some(WriteMode::Patch),
+            "--read-only" => write_mode = Some(WriteMode::ReadOnly),
+            _ => break,
+        }
+        args.remove(0);
+    }
+
+    // In a workflow, PR comments with the trigger start a fixer round
+    let actions = ActionsContext::detect().filter(|_| settings.github_actions.enabled);
+    let fix = match &actions {
+        Some(actions) if actions.event_name == "issue_comment" => {
+            let event = match actions.event() {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("Failed to read workflow event: {}", e.report());
+                    std::process::exit(e.exit_code());
+                }
+            };
+            match event.fix_request(&settings.github_actions) {
+                Some(fix) => Some(fix),
+                None => {
+                    println!("Comment does not request a fix; nothing to do");
+                    return;
+                }
+            }
+        }
+        _ => None,
+    };
+
+    if args.is_empty() && fix.is_none() {
+        eprintln!("Usage: {} [spawn|spawn-team|cruise] [--dry-run] [--patch|--read-only] <prompt>", program);
+        eprintln!("       {} from-issue [--dry-run] <issue-url>", program);
+        eprintln!("       {} review [--no-post] [--in-description] <pr-url>", program);
+        eprintln!("       {} fix-pr <pr-url>", program);
+        eprintln!("       {} cruise status [--json] [<run-id>]", program);
+        eprintln!("       {} cruise abort [<run-id>]", program);
+        eprintln!("       {} experiment [--keep] <experiment.toml>", program);
+        eprintln!("       {} stats [--json]", program);
+        eprintln!("       {} release-notes [--json] [--pr <version>] <tag>", program);
+        eprintln!("       {} logs [--follow] <spawn-id>", program);
+        eprintln!("       {} replay [--timing] <spawn-id>", program);
+        eprintln!("       {} doctor [<command>]", program);
+        eprintln!("       {} serve", program);
         eprintln!("\nSpawns a sandboxed LLM instance with the given prompt.");
-        std::process::exit(1);
+        eprintln!("With --dry-run, prints the execution plan without running anything.");
+        eprintln!("With --patch, the LLM returns a diff to apply instead of editing files.");
+        eprintln!("With --read-only, the LLM investigates and reports without changing files.");
+        eprintln!("--sandbox-dir <dir> and --logs-dir <dir> set where sandboxes and logs go.");
+        eprintln!("from-issue runs a spawn for an issue; cruise/epic issues need --dry-run.");
+        eprintln!("review reviews any PR in every review domain and posts the findings.");
+        eprintln!("fix-pr fixes a PR's open review threads and failing checks on its branch.");
+        eprintln!("cruise status prints the progress of the latest (or given) cruise run.");
+        eprintln!("cruise abort stops a cruise run and rolls back its PRs, sandboxes, and issues.");
+        eprintln!("experiment runs a prompt with two configurations and compares them.");
+        eprintln!("stats prints trends across the runs recorded in this repository.");
+        eprintln!("release-notes lists the drive PRs merged since a tag; --pr opens a release PR.");
+        eprintln!("logs prints a spawn's output; --follow keeps printing as it runs.");
+        eprintln!("replay prints a spawn's timeline; --timing adds time spent per phase.");
+        eprintln!("doctor checks that the tools a command needs are installed and logged in.");
+        eprintln!("serve runs an MCP server on stdio.");
+        std::process::exit(exit_code::CONFIG);
+    }
+
+    // Issues labelled for cruise get a cruise run, everything else a spawn
+    let issue = if command == "from-issue" {
+        match IssueTracker::new(&repo_path).fetch(&args[0]) {
+            Ok(issue) => Some(issue),
+            Err(e) => {
+                eprintln!("Failed to fetch issue: {}", e.report());
+                std::process::exit(e.exit_code());
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(issue) = &issue {
+        command = if issue.is_cruise() { "cruise" } else { "spawn" }.to_string();
+        if issue.is_cruise() && !dry_run {
+            eprintln!(
+                "{} is labelled for cruise, and cruise runs are only available with --dry-run",
+                issue.reference()
+            );
+            eprintln!("Usage: {} from-issue --dry-run <issue-url>", program);
+            eprintln!("Remove its cruise or epic label to resolve it with a single spawn.");
+            std::process::exit(exit_code::CONFIG);
+        }
     }
 
-    let prompt = args[1..].join(" ");
+    let prompt = match (&fix, &issue) {
+        (Some(fix), _) => fix_prompt(fix),
+        (None, Some(issue)) => issue.prompt(),
+        (None, None) => args.join(" "),
+    };
 
-    // Get current repo path
-    let repo_path = std::env::current_dir().expect("failed to get current directory");
+    if dry_run {
+        let templates = match PromptTemplates::for_repo(&repo_path) {
+            Ok(templates) => templates,
+            Err(e) => {
+                eprintln!("Failed to load prompt templates: {}", e.report());
+                std::process::exit(e.exit_code());
+            }
+        };
+        let dry_run = DryRun::new(&settings)
+            .with_templates(templates)
+            .with_base_branch(current_branch(&repo_path));
+        let plan = match command.as_str() {
+            "spawn-team" => dry_run.spawn_team(&prompt),
+            "cruise" => dry_run.cruise(&prompt),
+            _ => dry_run.spawn(&prompt),
+        };
+        print!("{}", plan);
+        return;
+    }
 
-    // Setup directories
-    let logs_dir = PathBuf::from(".improbability-drive/spawns");
-    let sandbox_dir = std::env::temp_dir().join("improbability-drive-sandboxes");
+    if command != "spawn" {
+        eprintln!("{} is only available with --dry-run from the CLI", command);
+        std::process::exit(exit_code::CONFIG);
+    }
 
-    // Create spawner
-    let provider = WorktreeSandbox::new(repo_path, Some(sandbox_dir));
-    let spawner = Spawner::new(provider, logs_dir);
+    preflight(&settings, "spawn");
+
+    // Setup directories
+    let logs_dir = settings.logging.logs_dir.clone();
+    let sandbox_dir = settings.sandbox_dir();
+    record_locations(&repo_path, &settings);
 
     // Create config
-    let config = SpawnConfig::new(&prompt);
-    let manifest = SandboxManifest::default();
+    let mut config = settings.spawn_config(&prompt);
+    if let Some(mode) = write_mode {
+        config = config.with_write_mode(mode);
+    }
+    let manifest = SandboxManifest {
+        isolate_home: settings.sandbox.isolate_home,
+        ..Default::default()
+    };
+    let mut tracker = IssueTracker::new(&repo_path);
+    if let Some(issue) = &issue {
+        config = config.with_closing_issue(issue.reference());
+        post_issue_progress(&mut tracker, issue, "Started a spawn for this issue.");
+    }
 
     // Run spawn
     tracing::info!(prompt = %prompt, "starting spawn");
 
-    match spawner.spawn(config, manifest) {
+    // A workflow's checkout is already disposable, so it is the sandbox
+    let workspace = actions
+        .as_ref()
+        .map(|a| a.workspace.clone())
+        .or_else(|| (settings.sandbox.provider == "workspace").then(|| repo_path.clone()));
+    let result = match workspace {
+        Some(workspace) => {
+            if let Some(fix) = &fix {
+                if let Err(e) = fix.checkout_head(&workspace) {
+                    eprintln!(
+                        "Failed to check out PR #{}: {}",
+                        fix.pr_number,
+                        e.report()
+                    );
+                    std::process::exit(e.exit_code());
+                }
+            }
+            let mut provider =
+                WorkspaceSandbox::new(workspace).with_branch_namer(settings.branches.clone());
+            if fix.is_some() {
+                provider = provider.on_current_branch();
+            }
+            Spawner::new(provider, logs_dir)
+                .with_branch_namer(settings.branches.clone())
+                .with_commit_messages(settings.commits.clone())
+                .spawn(config, manifest)
+        }
+        None => {
+            let provider = WorktreeSandbox::new(repo_path.clone(), Some(sandbox_dir))
+                .with_branch_namer(settings.branches.clone());
+            Spawner::new(provider, logs_dir)
+                .with_branch_namer(settings.branches.clone())
+                .with_commit_messages(settings.commits.clone())
+                .spawn(config, manifest)
+        }
+    };
+
+    if let (Some(actions), Ok(result)) = (&actions, &result) {
+        if settings.github_actions.job_summary {
+            if let Err(e) = actions.write_job_summary(&format_job_summary(result)) {
+                tracing::warn!(error = %e, "failed to write job summary");
+            }
+        }
+    }
+
+    if let Some(issue) = &issue {
+        let progress = match &result {
+            Ok(result) => {
+                let mut progress = format!("Spawn finished: `{:?}`.", result.status);
+                if let Some(url) = &result.pr_url {
+                    progress.push_str(&format!(" PR: {}", url));
+                }
+                progress
+            }
+            Err(e) => format!("Spawn failed: {}", e),
+        };
+        post_issue_progress(&mut tracker, issue, &progress);
+    }
+
+    if let Ok(result) = &result {
+        record_stats(&repo_path, &settings, &RunMetrics::from_spawn(result));
+    }
+
+    match result {
         Ok(result) => {
             println!("\n{}", "=".repeat(60));
             println!("Spawn Complete: {}", result.spawn_id);
@@ -54,19 +357,604 @@ fn main() {
             println!();
             println!("Status: {:?}", result.status);
             println!("Duration: {:?}", result.duration);
+            if !result.files_changed.is_empty() {
+                println!("Changes: {}", result.change_summary());
+            }
+            if !result.commits.is_empty() {
+                println!("Commits: {}", result.commits.len());
+            }
             println!();
             println!("Summary:");
             println!("  {}", result.summary);
             println!();
             println!("Logs: {}", result.logs.stdout.parent().unwrap().display());
 
-            if result.status != SpawnStatus::Success {
+            if !result.status.is_success() {
+                std::process::exit(result.status.exit_code());
+            }
+        }
+        Err(e) => {
+            eprintln!("Spawn failed: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Removes `--sandbox-dir <dir>` and `--logs-dir <dir>` from `args`.
+fn take_location_flags(args: &mut Vec<String>) -> SettingsOverrides {
+    let mut overrides = SettingsOverrides::default();
+    let mut i = 0;
+    while i < args.len() {
+        let slot = match args[i].as_str() {
+            "--sandbox-dir" => &mut overrides.sandbox_dir,
+            "--logs-dir" => &mut overrides.logs_dir,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let Some(dir) = args.get(i + 1).cloned() else {
+            eprintln!("{} requires a directory", args[i]);
+            std::process::exit(exit_code::CONFIG);
+        };
+        *slot = Some(dir.into());
+        args.drain(i..i + 2);
+    }
+    overrides
+}
+
+/// Records where this run puts sandboxes and logs so cleanup can find them.
+fn record_locations(repo_path: &std::path::Path, settings: &Settings) {
+    if let Err(e) = LocationRegistry::record(repo_path, settings.locations()) {
+        tracing::warn!(error = %e, "failed to record sandbox and log locations");
+    }
+}
+
+/// Builds the prompt for a fixer round requested in a PR comment.
+fn fix_prompt(fix: &FixRequest) -> String {
+    FixPromptBuilder::new(format!("Changes on PR #{}", fix.pr_number))
+        .with_suggestions(vec![fix.suggestion()])
+        .build()
+}
+
+/// Checks the tools `command` needs, exiting with how to fix any that are
+/// missing, outdated, or logged out.
+fn preflight(settings: &Settings, command: &str) {
+    match Preflight::new(settings).check(command).into_result() {
+        Ok(warnings) => {
+            for warning in warnings {
+                tracing::warn!("{}", warning);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e.report());
+            eprintln!("Run `doctor {}` for details.", command);
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Prints the state of every tool `command` needs, or every tool any
+/// command needs.
+fn doctor(settings: &Settings, command: Option<&String>) {
+    let command = command.map(String::as_str).unwrap_or("all");
+    let report = Preflight::new(settings).check(command);
+    print!("{}", report);
+    if !report.is_ok() {
+        std::process::exit(exit_code::CONFIG);
+    }
+}
+
+/// Updates the run's progress comment on an issue, logging failures.
+fn post_issue_progress(tracker: &mut IssueTracker, issue: &Issue, progress: &str) {
+    if let Err(e) = tracker.post_progress(issue, progress) {
+        tracing::warn!(issue = issue.number, error = %e, "failed to update issue progress");
+    }
+}
+
+/// Reviews the PR named in `args` with the spawn-team reviewer and posts
+/// the findings, unless `--no-post` is given. With `--in-description`, each
+/// domain's review is also kept in its own section of the PR description.
+fn review_pr(repo_path: &std::path::Path, settings: &Settings, args: &[String]) {
+    let post = !args.iter().any(|a| a == "--no-post");
+    let in_description = args.iter().any(|a| a == "--in-description");
+    let Some(pr) = args.iter().find(|a| !a.starts_with("--")) else {
+        eprintln!("review needs a PR URL or number");
+        std::process::exit(exit_code::CONFIG);
+    };
+    preflight(settings, "review");
+
+    let runner = match settings.create_runner(&settings.team.reviewer_llm) {
+        Ok(runner) => runner,
+        Err(e) => {
+            eprintln!("Failed to create reviewer: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let templates = match PromptTemplates::for_repo(repo_path) {
+        Ok(templates) => templates,
+        Err(e) => {
+            eprintln!("Failed to load prompt templates: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let reviewer = PrReviewer::new(repo_path, runner, &settings.team).with_templates(templates);
+    let details = match reviewer.fetch(pr) {
+        Ok(details) => details,
+        Err(e) => {
+            eprintln!("Failed to fetch pull request: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    let started = std::time::Instant::now();
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+    let outcome = match runtime.block_on(reviewer.review(&details)) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("Review failed: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let run_id = format!("review-{}-{}", details.number, uuid::Uuid::new_v4());
+    let metrics = RunMetrics::new("review", run_id, started.elapsed())
+        .with_verdict(&outcome.review.verdict)
+        .with_domains(&outcome.domains);
+    record_stats(repo_path, settings, &metrics);
+
+    println!("PR #{}: {:?}", details.number, outcome.review.verdict);
+    for suggestion in &outcome.review.suggestions {
+        let location = match suggestion.line {
+            Some(line) => format!("{}:{}", suggestion.file, line),
+            None => suggestion.file.clone(),
+        };
+        println!(
+            "  [{:?}] {} {}",
+            suggestion.severity, location, suggestion.issue
+        );
+    }
+
+    if post {
+        let mut threads = ReviewThreads::new(Vec::new());
+        match reviewer.post(&details, &outcome.review, &mut threads) {
+            Ok(posted) => println!("Posted review with {} finding(s)", posted),
+            Err(e) => {
+                eprintln!("Failed to post review: {}", e.report());
+                std::process::exit(e.exit_code());
+            }
+        }
+        if in_description {
+            if let Err(e) = reviewer.update_description(&details, &outcome) {
+                eprintln!("Failed to update PR description: {}", e.report());
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+}
+
+/// Fixes the open review threads and failing checks of `pr`, pushing the
+/// fixes to the PR's branch.
+fn fix_pr(repo_path: &std::path::Path, settings: &Settings, pr: &str) {
+    preflight(settings, "fix-pr");
+    let runners = settings
+        .create_runner(&settings.team.primary_llm)
+        .and_then(|primary| {
+            Ok((
+                primary,
+                settings.create_runner(&settings.team.reviewer_llm)?,
+            ))
+        });
+    let (primary, reviewer) = match runners {
+        Ok(runners) => runners,
+        Err(e) => {
+            eprintln!("Failed to create runners: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let templates = match PromptTemplates::for_repo(repo_path) {
+        Ok(templates) => templates,
+        Err(e) => {
+            eprintln!("Failed to load prompt templates: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let details = match fetch_pr(repo_path, pr) {
+        Ok(details) => details,
+        Err(e) => {
+            eprintln!("Failed to fetch pull request: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    record_locations(repo_path, settings);
+    let mut verifier = FixVerifier::new(reviewer).with_templates(templates.clone());
+    if let Some(model) = settings.verify_model() {
+        verifier = verifier.with_model(model);
+    }
+    let mut fixer = PrFixer::new(repo_path, primary, verifier)
+        .with_sandbox_dir(settings.sandbox_dir())
+        .with_branch_namer(settings.branches.clone())
+        .with_commit_messages(settings.commits.clone())
+        .with_verification(settings.team.verify.clone())
+        .with_templates(templates);
+    if let Some(model) = settings
+        .team
+        .fix_model()
+        .or(settings.spawn.model.as_deref())
+    {
+        fixer = fixer.with_model(model);
+    }
+    if settings.team.ci.max_fix_rounds > 0 {
+        let checks = std::sync::Arc::new(ChecksClient::new(repo_path));
+        fixer = fixer.with_ci(CiWatcher::new(checks, settings.team.ci.clone()));
+    }
+
+    let feedback = match fixer.collect(&details) {
+        Ok(feedback) => feedback,
+        Err(e) => {
+            eprintln!("Failed to collect PR feedback: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    if feedback.is_empty() {
+        println!(
+            "PR #{} has no open review threads or failing checks",
+            details.number
+        );
+        return;
+    }
+    println!(
+        "PR #{}: {} open thread(s), {} check failure(s)",
+        details.number,
+        feedback.threads.len(),
+        feedback.annotations.len()
+    );
+
+    let started = std::time::Instant::now();
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+    let outcome = match runtime.block_on(fixer.fix(&details, &feedback)) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("Fixing PR failed: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let run_id = format!("fix-pr-{}-{}", details.number, uuid::Uuid::new_v4());
+    let mut metrics = RunMetrics::new("fix-pr", run_id, started.elapsed());
+    metrics.iterations = outcome.rounds + outcome.ci_rounds;
+    let failure = match (&outcome.unverified, &outcome.ci) {
+        (Some(failure), _) => Some(format!("verification failed: `{}`", failure.command)),
+        (None, Some(CiStatus::Failed(_))) => Some("CI still failing".to_string()),
+        (None, Some(CiStatus::TimedOut)) => Some("timed out waiting for CI".to_string()),
+        _ if !outcome.remaining.is_empty() => Some("review findings left open".to_string()),
+        _ => None,
+    };
+    if let Some(failure) = failure {
+        metrics = metrics.with_failure(failure);
+    }
+    record_stats(repo_path, settings, &metrics);
+
+    println!(
+        "Pushed {} commit(s) to {}: {} resolved, {} escalated, {} remaining",
+        outcome.commits.len(),
+        details.head_branch,
+        outcome.resolved,
+        outcome.escalated,
+        outcome.remaining.len()
+    );
+    for suggestion in &outcome.remaining {
+        println!(
+            "  [{:?}] {} {}",
+            suggestion.severity, suggestion.file, suggestion.issue
+        );
+    }
+    if let Some(failure) = &outcome.unverified {
+        println!(
+            "Stopped: `{}` still fails; the last round was not pushed",
+            failure.command
+        );
+        println!("{}", failure.output);
+    }
+    match &outcome.ci {
+        Some(CiStatus::Passed) => println!("CI passed after {} fix round(s)", outcome.ci_rounds),
+        Some(CiStatus::Failed(failures)) => {
+            println!("CI still failing after {} fix round(s):", outcome.ci_rounds);
+            for failure in failures {
+                println!("  {} {}", failure.run.name, failure.run.url);
+            }
+        }
+        Some(CiStatus::NoChecks) => println!("No CI checks ran on the fixes"),
+        Some(CiStatus::TimedOut) => println!("Timed out waiting for CI"),
+        None => {}
+    }
+}
+
+/// Runs the experiment file named in `args` and prints the comparison.
+/// `--keep` keeps each variant's ephemeral repository.
+fn run_experiment(repo_path: &std::path::Path, settings: &Settings, args: &[String]) {
+    let keep = args.iter().any(|a| a == "--keep");
+    let Some(path) = args.iter().find(|a| !a.starts_with("--")) else {
+        eprintln!("experiment needs an experiment file");
+        std::process::exit(exit_code::CONFIG);
+    };
+
+    let experiment = match Experiment::load(std::path::Path::new(path)) {
+        Ok(experiment) => experiment,
+        Err(e) => {
+            eprintln!("Failed to load experiment: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let reviewer = match settings.create_runner(&settings.team.reviewer_llm) {
+        Ok(runner) => runner,
+        Err(e) => {
+            eprintln!("Failed to create reviewer: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let templates = match PromptTemplates::for_repo(repo_path) {
+        Ok(templates) => templates,
+        Err(e) => {
+            eprintln!("Failed to load prompt templates: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+    let runner = ExperimentRunner::new(experiment, reviewer, &settings.team)
+        .with_templates(templates)
+        .with_gemini(settings.spawn.gemini.clone())
+        .with_keep_repos(keep);
+
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+    match runtime.block_on(runner.run()) {
+        Ok(report) => print!("{}", report.to_markdown()),
+        Err(e) => {
+            eprintln!("Experiment failed: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Records a finished run's metrics, logging failures.
+fn record_stats(repo_path: &std::path::Path, settings: &Settings, metrics: &RunMetrics) {
+    if !settings.stats.enabled {
+        return;
+    }
+    if let Err(e) = StatsStore::open(repo_path).and_then(|store| store.record(metrics)) {
+        tracing::warn!(run_id = %metrics.run_id, error = %e, "failed to record run stats");
+    }
+}
+
+/// Prints trends across the recorded runs. `--json` prints the raw report.
+fn show_stats(repo_path: &std::path::Path, args: &[String]) {
+    let json = args.iter().any(|a| a == "--json");
+
+    let report = match StatsStore::open(repo_path).and_then(|store| store.report()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Failed to read run stats: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize run stats: {}", e);
                 std::process::exit(1);
             }
         }
+    } else {
+        print!("{}", report.render());
+    }
+}
+
+/// Prints release notes for the drive PRs merged since a tag. With
+/// `--pr <version>`, also opens a release PR adding them to the changelog;
+/// `--json` prints the collected PRs instead.
+fn release_notes(repo_path: &std::path::Path, settings: &Settings, args: &[String]) {
+    let json = args.iter().any(|a| a == "--json");
+    let version = args
+        .iter()
+        .position(|a| a == "--pr")
+        .and_then(|i| args.get(i + 1));
+    let Some(tag) = args
+        .iter()
+        .enumerate()
+        .find(|(i, a)| !a.starts_with("--") && (*i == 0 || args[i - 1] != "--pr"))
+        .map(|(_, a)| a)
+    else {
+        eprintln!("Usage: release-notes [--json] [--pr <version>] <tag>");
+        std::process::exit(exit_code::CONFIG);
+    };
+
+    let generator = ReleaseNotesGenerator::new(repo_path)
+        .with_branches(settings.branches.clone())
+        .with_commit_messages(settings.commits.clone());
+    let notes = match generator.generate(tag) {
+        Ok(notes) => notes,
+        Err(e) => {
+            eprintln!("Failed to collect release notes: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&notes) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize release notes: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print!("{}", notes.render(version.map(String::as_str)));
+    }
+
+    let Some(version) = version else {
+        return;
+    };
+    if notes.is_empty() {
+        eprintln!(
+            "No drive PRs merged since {}; not opening a release PR",
+            tag
+        );
+        return;
+    }
+    match generator.open_release_pr(&notes, version, &current_branch(repo_path)) {
+        Ok(pr) => println!("Opened release PR: {}", pr.url),
+        Err(e) => {
+            eprintln!("Failed to open release PR: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Prints a spawn's stdout, stderr and events. With `--follow`, keeps
+/// printing new lines until interrupted.
+fn show_logs(repo_path: &std::path::Path, settings: &Settings, args: &[String]) {
+    let follow = args.iter().any(|a| a == "--follow" || a == "-f");
+    let Some(spawn_id) = args.iter().find(|a| !a.starts_with('-')) else {
+        eprintln!("Usage: logs [--follow] <spawn-id>");
+        std::process::exit(exit_code::CONFIG);
+    };
+
+    let dir = match find_spawn_dir(repo_path, &settings.logging.logs_dir, spawn_id) {
+        Ok(dir) => dir,
         Err(e) => {
-            eprintln!("Spawn failed: {}", e);
+            eprintln!("Failed to find spawn logs: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let mut renderer = LogRenderer::new(color);
+    let mut follower = LogFollower::new(&dir);
+    loop {
+        let lines = match follower.poll() {
+            Ok(lines) => lines,
+            Err(e) => {
+                eprintln!("Failed to read spawn logs: {}", e.report());
+                std::process::exit(e.exit_code());
+            }
+        };
+        for (source, line) in lines {
+            for rendered in renderer.render(source, &line) {
+                println!("{}", rendered);
+            }
+        }
+        if !follow {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+    for rendered in renderer.finish() {
+        println!("{}", rendered);
+    }
+}
+
+/// Prints a spawn's timeline, with a chart of time per phase if `--timing`
+/// is given.
+fn replay(repo_path: &std::path::Path, settings: &Settings, args: &[String]) {
+    let timing = args.iter().any(|a| a == "--timing");
+    let Some(spawn_id) = args.iter().find(|a| !a.starts_with('-')) else {
+        eprintln!("Usage: replay [--timing] <spawn-id>");
+        std::process::exit(exit_code::CONFIG);
+    };
+
+    let timeline = find_spawn_dir(repo_path, &settings.logging.logs_dir, spawn_id)
+        .and_then(|dir| Timeline::load(&dir));
+    match timeline {
+        Ok(timeline) => print!("{}", timeline.render(timing)),
+        Err(e) => {
+            eprintln!("Failed to load spawn timeline: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Prints the status of a cruise run: the one named in `args`, or the most
+/// recently updated. `--json` prints the raw snapshot.
+fn cruise_status(repo_path: &std::path::Path, args: &[String]) {
+    let json = args.iter().any(|a| a == "--json");
+    let run_id = args.iter().find(|a| !a.starts_with("--"));
+
+    let status = match run_id {
+        Some(run_id) => CruiseStatus::load(repo_path, run_id).map(Some),
+        None => CruiseStatus::latest(repo_path),
+    };
+    let status = match status {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            eprintln!("No cruise runs found");
             std::process::exit(1);
         }
+        Err(e) => {
+            eprintln!("Failed to read cruise status: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&status) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize cruise status: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        print!("{}", status.render(now));
     }
 }
+
+/// Aborts a cruise run: the one named, or the most recently updated.
+fn cruise_abort(repo_path: &std::path::Path, settings: &Settings, run_id: Option<&String>) {
+    let status = match run_id {
+        Some(run_id) => CruiseStatus::load(repo_path, run_id).map(Some),
+        None => CruiseStatus::latest(repo_path),
+    };
+    let mut status = match status {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            eprintln!("No cruise runs found");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to read cruise status: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    let aborter = RunAborter::new(repo_path).with_config(settings.cruise.abort.clone());
+    match aborter.abort(&mut status) {
+        Ok(report) => {
+            println!("Aborted run {}", status.run_id);
+            print!("{}", report.render());
+            if !report.errors.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Abort failed: {}", e.report());
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Returns the checked-out branch, falling back to "main".
+fn current_branch(repo_path: &std::path::Path) -> String {
+    std::process::Command::new("git")
+        .current_dir(repo_path)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|b| !b.is_empty() && b != "HEAD")
+        .unwrap_or_else(|| "main".to_string())
+}
@@ -0,0 +1,580 @@
+//! MCP server mode.
+//!
+//! Exposes the drive as Model Context Protocol tools so Claude Code can call
+//! it directly instead of shelling out. Requests are JSON-RPC 2.0 messages,
+//! one per line, read from stdin with responses written to stdout.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::cancel::{CancellationToken, Checkpoint, CHECKPOINT_DIR};
+use crate::cruise::Planner;
+use crate::dry_run::DryRun;
+use crate::error::Result;
+use crate::prompt::PromptTemplates;
+use crate::queue::{SpawnPriority, SpawnQueue};
+use crate::sandbox::{LocationRegistry, SandboxManifest, WorktreeSandbox};
+use crate::settings::Settings;
+use crate::spawn::Spawner;
+
+/// MCP protocol revision implemented by the server.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for malformed parameters.
+const INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error code for unparseable messages.
+const PARSE_ERROR: i64 = -32700;
+
+/// Serves MCP tools for one repository.
+#[derive(Clone)]
+pub struct McpServer {
+    settings: Settings,
+    repo_path: PathBuf,
+    queue: SpawnQueue,
+}
+
+impl McpServer {
+    /// Creates a server for `repo_path` using resolved `settings`.
+    pub fn new(settings: Settings, repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            queue: SpawnQueue::new(settings.queue.clone()),
+            settings,
+            repo_path: repo_path.into(),
+        }
+    }
+
+    /// Serves requests on stdin and stdout until stdin closes.
+    pub async fn serve_stdio(&self) -> Result<()> {
+        let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+        self.serve(stdin, tokio::io::stdout()).await
+    }
+
+    /// Serves newline-delimited JSON-RPC requests until `reader` closes and
+    /// every request has been answered.
+    ///
+    /// Each request is handled on its own task, so a long tool call such as
+    /// a spawn does not hold up the requests after it. Responses are
+    /// written as they finish, which may not be the order they were asked.
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let server = Arc::new(self.clone());
+        let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+        let mut requests = Some(tx);
+        let mut lines = reader.lines();
+
+        loop {
+            tokio::select! {
+                line = lines.next_line(), if requests.is_some() => {
+                    let Some(line) = line? else {
+                        // Answers still running keep their own senders
+                        requests = None;
+                        continue;
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let Some(tx) = &requests else {
+                        continue;
+                    };
+                    match serde_json::from_str::<Value>(&line) {
+                        Ok(request) => {
+                            let server = server.clone();
+                            let tx = tx.clone();
+                            tokio::spawn(async move {
+                                if let Some(response) = server.handle(request).await {
+                                    let _ = tx.send(response);
+                                }
+                            });
+                        }
+                        Err(e) => {
+                            let _ = tx.send(error_response(
+                                Value::Null,
+                                PARSE_ERROR,
+                                &format!("parse error: {}", e),
+                            ));
+                        }
+                    }
+                }
+                response = rx.recv() => {
+                    let Some(response) = response else {
+                        break;
+                    };
+                    writer.write_all(response.to_string().as_bytes()).await?;
+                    writer.write_all(b"\n").await?;
+                    writer.flush().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one JSON-RPC message. Returns `None` for notifications.
+    pub async fn handle(&self, request: Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        tracing::debug!(method = %method, "mcp request");
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params).await,
+            _ if id.is_none() => return None,
+            other => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", other))),
+        };
+
+        // Notifications get no response, even on error
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    /// Runs a tool, reporting tool failures as error content.
+    async fn call_tool(&self, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params["name"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+        let args = &params["arguments"];
+        let prompt = args["prompt"].as_str();
+        let dry_run = args["dry_run"].as_bool().unwrap_or(false);
+
+        let needs_prompt = name != "cruise_status";
+        if needs_prompt && prompt.is_none_or(|p| p.trim().is_empty()) {
+            return Err((INVALID_PARAMS, format!("{} requires a prompt", name)));
+        }
+        let prompt = prompt.unwrap_or_default();
+
+        let outcome = match name {
+            "spawn_sandboxed" if dry_run => Ok(self.dry_run().spawn(prompt).to_string()),
+            "spawn_sandboxed" => {
+                let priority = match args.get("priority") {
+                    Some(priority) => serde_json::from_value(priority.clone())
+                        .map_err(|e| (INVALID_PARAMS, format!("invalid priority: {}", e)))?,
+                    None => SpawnPriority::default(),
+                };
+                self.spawn(prompt, priority).await
+            }
+            "cruise_plan" if dry_run => Ok(self.dry_run().cruise(prompt).to_string()),
+            "cruise_plan" => self.cruise_plan(prompt).await,
+            "cruise_status" => self.cruise_status(args["run_id"].as_str()),
+            other => return Err((INVALID_PARAMS, format!("unknown tool '{}'", other))),
+        };
+
+        let (text, is_error) = match outcome {
+            Ok(text) => (text, false),
+            Err(text) => (text, true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+
+    fn dry_run(&self) -> DryRun<'_> {
+        let templates = PromptTemplates::for_repo(&self.repo_path).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to load repo templates, using built-ins");
+            PromptTemplates::builtin()
+        });
+        DryRun::new(&self.settings).with_templates(templates)
+    }
+
+    /// Runs a sandboxed spawn on the blocking pool, off the runtime's
+    /// worker threads, once the server's queue gives it a slot.
+    ///
+    /// A spawn preempted before it starts is cancelled; one already running
+    /// finishes, but its result is dropped and it runs again later.
+    async fn spawn(
+        &self,
+        prompt: &str,
+        priority: SpawnPriority,
+    ) -> std::result::Result<String, String> {
+        if let Err(e) = LocationRegistry::record(&self.repo_path, self.settings.locations()) {
+            tracing::warn!(error = %e, "failed to record sandbox and log locations");
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        let result = self
+            .queue
+            .run(id, priority, || {
+                let cancel = CancelOnDrop(CancellationToken::new());
+                let provider =
+                    WorktreeSandbox::new(self.repo_path.clone(), Some(self.settings.sandbox_dir()))
+                        .with_branch_namer(self.settings.branches.clone());
+                let spawner = Spawner::new(provider, self.settings.logging.logs_dir.clone())
+                    .with_branch_namer(self.settings.branches.clone())
+                    .with_commit_messages(self.settings.commits.clone())
+                    .with_cancellation(cancel.0.clone());
+                let config = self.settings.spawn_config(prompt);
+                async move {
+                    let _cancel = cancel;
+                    tokio::task::spawn_blocking(move || {
+                        spawner.spawn(config, SandboxManifest::default())
+                    })
+                    .await
+                    .map_err(std::io::Error::other)?
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        to_json_text(&result)
+    }
+
+    async fn cruise_plan(&self, prompt: &str) -> std::result::Result<String, String> {
+        let planner = Planner::new(self.settings.cruise.planning.clone());
+        let result = planner
+            .plan(prompt, &self.repo_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        to_json_text(&result)
+    }
+
+    /// Reports saved checkpoints, or one checkpoint by run ID.
+    fn cruise_status(&self, run_id: Option<&str>) -> std::result::Result<String, String> {
+        if let Some(run_id) = run_id {
+            let checkpoint =
+                Checkpoint::load(&self.repo_path, run_id).map_err(|e| e.to_string())?;
+            return to_json_text(&checkpoint);
+        }
+
+        let checkpoints = list_checkpoints(&self.repo_path);
+        if checkpoints.is_empty() {
+            return Ok("No interrupted runs.".to_string());
+        }
+        to_json_text(&checkpoints)
+    }
+}
+
+/// Loads every readable checkpoint under `repo`, oldest first.
+fn list_checkpoints(repo: &Path) -> Vec<Checkpoint> {
+    let Ok(entries) = std::fs::read_dir(repo.join(CHECKPOINT_DIR)) else {
+        return Vec::new();
+    };
+    let mut checkpoints: Vec<Checkpoint> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let run_id = path.file_stem()?.to_str()?.to_string();
+            Checkpoint::load(repo, &run_id).ok()
+        })
+        .collect();
+    checkpoints.sort_by_key(|c| c.cancelled_at);
+    checkpoints
+}
+
+fn to_json_text(value: &impl serde::Serialize) -> std::result::Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Cancels its token when dropped, e.g. when the queue preempts a spawn.
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel("preempted by a higher-priority spawn");
+    }
+}
+
+/// Returns the MCP definitions of the served tools.
+fn tool_definitions() -> Value {
+    let prompt_schema = |description: &str| {
+        json!({
+            "type": "object",
+            "properties": {
+                "prompt": { "type": "string", "description": description },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Print the execution plan and estimated cost without running anything",
+                },
+            },
+            "required": ["prompt"],
+        })
+    };
+
+    let mut spawn_schema = prompt_schema("Task for the sandboxed LLM");
+    spawn_schema["properties"]["priority"] = json!({
+        "type": "string",
+        "enum": ["low", "normal", "high", "critical"],
+        "description": "Order in the spawn queue when more spawns are requested than may run at once",
+    });
+
+    json!([
+        {
+            "name": "spawn_sandboxed",
+            "description": "Run a task in a sandboxed LLM instance on its own git worktree",
+            "inputSchema": spawn_schema,
+        },
+        {
+            "name": "cruise_plan",
+            "description": "Generate a cruise-control plan for a feature",
+            "inputSchema": prompt_schema("Feature to plan"),
+        },
+        {
+            "name": "cruise_status",
+            "description": "Show interrupted runs that can be resumed",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "run_id": { "type": "string", "description": "Show only this run" },
+                },
+            },
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn server(repo: &TempDir) -> McpServer {
+        McpServer::new(Settings::default(), repo.path())
+    }
+
+    fn call(name: &str, arguments: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments },
+        })
+    }
+
+    #[tokio::test]
+    async fn initialize_advertises_tools() {
+        let repo = TempDir::new().unwrap();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}});
+
+        let response = server(&repo).handle(request).await.unwrap();
+
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert!(response["result"]["capabilities"]["tools"].is_object());
+    }
+
+    #[tokio::test]
+    async fn lists_all_tools() {
+        let repo = TempDir::new().unwrap();
+        let request = json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"});
+
+        let response = server(&repo).handle(request).await.unwrap();
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec!["spawn_sandboxed", "cruise_plan", "cruise_status"]
+        );
+    }
+
+    #[tokio::test]
+    async fn notifications_get_no_response() {
+        let repo = TempDir::new().unwrap();
+        let request = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+
+        assert!(server(&repo).handle(request).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn unknown_method_is_an_error() {
+        let repo = TempDir::new().unwrap();
+        let request = json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list"});
+
+        let response = server(&repo).handle(request).await.unwrap();
+
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn spawn_dry_run_returns_plan() {
+        let repo = TempDir::new().unwrap();
+
+        let response = server(&repo)
+            .handle(call(
+                "spawn_sandboxed",
+                json!({"prompt": "Add caching", "dry_run": true}),
+            ))
+            .await
+            .unwrap();
+
+        let result = &response["result"];
+        assert_eq!(result["isError"], false);
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Dry run: spawn"));
+    }
+
+    #[tokio::test]
+    async fn spawn_team_is_not_served() {
+        let repo = TempDir::new().unwrap();
+
+        let response = server(&repo)
+            .handle(call(
+                "spawn_team",
+                json!({"prompt": "Add caching", "dry_run": true}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn missing_prompt_is_invalid_params() {
+        let repo = TempDir::new().unwrap();
+
+        let response = server(&repo)
+            .handle(call("spawn_sandboxed", json!({})))
+            .await
+            .unwrap();
+
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn spawns_wait_for_a_queue_slot() {
+        let repo = TempDir::new().unwrap();
+        let mut settings = Settings::default();
+        settings.queue.max_concurrent = 1;
+        settings.logging.logs_dir = repo.path().join("logs");
+        let server = McpServer::new(settings, repo.path());
+        let release = Arc::new(tokio::sync::Notify::new());
+        let holder = {
+            let (queue, release) = (server.queue.clone(), release.clone());
+            tokio::spawn(async move {
+                queue
+                    .run("held", SpawnPriority::Normal, || {
+                        let release = release.clone();
+                        async move {
+                            release.notified().await;
+                            Ok(())
+                        }
+                    })
+                    .await
+            })
+        };
+        while server.queue.snapshot().running.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let spawn = server.handle(call(
+            "spawn_sandboxed",
+            json!({"prompt": "Add caching", "priority": "high"}),
+        ));
+        tokio::pin!(spawn);
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), &mut spawn)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            server.queue.snapshot().waiting[0].priority,
+            SpawnPriority::High
+        );
+
+        release.notify_one();
+        holder.await.unwrap().unwrap();
+        let response = spawn.await.unwrap();
+        assert!(response["result"]["content"][0]["text"].is_string());
+        assert!(server.queue.snapshot().running.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_priority_is_invalid_params() {
+        let repo = TempDir::new().unwrap();
+
+        let response = server(&repo)
+            .handle(call(
+                "spawn_sandboxed",
+                json!({"prompt": "Add caching", "priority": "urgent"}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn cruise_status_reports_checkpoints() {
+        let repo = TempDir::new().unwrap();
+        let server = server(&repo);
+
+        let response = server
+            .handle(call("cruise_status", json!({})))
+            .await
+            .unwrap();
+        assert_eq!(
+            response["result"]["content"][0]["text"],
+            "No interrupted runs."
+        );
+
+        Checkpoint::new("run-9", "building", "Build it", "interrupted")
+            .save(repo.path())
+            .unwrap();
+        let response = server
+            .handle(call("cruise_status", json!({"run_id": "run-9"})))
+            .await
+            .unwrap();
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("\"phase\": \"building\""));
+    }
+
+    #[tokio::test]
+    async fn serves_line_delimited_requests() {
+        let repo = TempDir::new().unwrap();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+            "\n\nnot json\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+
+        server(&repo)
+            .serve(input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        let ping = responses.iter().find(|r| r["id"] == 1).unwrap();
+        assert_eq!(ping["result"], json!({}));
+        let parse_error = responses.iter().find(|r| r["id"].is_null()).unwrap();
+        assert_eq!(parse_error["error"]["code"], PARSE_ERROR);
+    }
+}
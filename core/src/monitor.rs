@@ -3,12 +3,14 @@
 //! Tracks file changes, commits, output lines, and detects timeouts
 //! based on activity or wall-clock time.
 
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
+use crate::runner::TokenUsage;
+
 /// Information about a commit made during spawn.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitInfo {
@@ -25,6 +27,9 @@ pub struct TimeoutConfig {
     pub idle_timeout: Duration,
     /// Maximum total wall-clock time before termination.
     pub total_timeout: Duration,
+    /// Maximum time without tool calls or file writes, even if the LLM
+    /// keeps producing output. `None` disables stall detection.
+    pub stall_timeout: Option<Duration>,
 }
 
 impl Default for TimeoutConfig {
@@ -32,6 +37,123 @@ impl Default for TimeoutConfig {
         Self {
             idle_timeout: Duration::from_secs(120),
             total_timeout: Duration::from_secs(1800),
+            stall_timeout: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+/// Phase of a run that draws on a [`TimeoutBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetPhase {
+    /// The primary LLM's initial work.
+    Primary,
+    /// One review pass for one domain.
+    Review,
+    /// One fix round.
+    Fix,
+}
+
+/// Fractions of the total budget given to each phase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetShares {
+    /// Share for the primary invocation.
+    pub primary: f64,
+    /// Share for all review passes, split across domains.
+    pub review: f64,
+    /// Share for all fix rounds, split across rounds.
+    pub fix: f64,
+}
+
+impl Default for BudgetShares {
+    fn default() -> Self {
+        Self {
+            primary: 0.5,
+            review: 0.25,
+            fix: 0.25,
+        }
+    }
+}
+
+/// Splits a run's total time across the primary, review, and fix phases.
+///
+/// Each invocation gets a deadline of its phase's allocation, capped by
+/// whatever is left of the total, so a slow early phase cannot push later
+/// invocations past the overall limit.
+#[derive(Debug, Clone)]
+pub struct TimeoutBudget {
+    total: Duration,
+    started: Instant,
+    shares: BudgetShares,
+    review_domains: u32,
+    fix_rounds: u32,
+}
+
+impl TimeoutBudget {
+    /// Starts a budget of `total` with the default shares.
+    pub fn new(total: Duration) -> Self {
+        Self {
+            total,
+            started: Instant::now(),
+            shares: BudgetShares::default(),
+            review_domains: 1,
+            fix_rounds: 1,
+        }
+    }
+
+    /// Sets the fraction of the budget each phase receives.
+    pub fn with_shares(mut self, shares: BudgetShares) -> Self {
+        self.shares = shares;
+        self
+    }
+
+    /// Sets the number of review domains the review share is split across.
+    pub fn with_review_domains(mut self, domains: u32) -> Self {
+        self.review_domains = domains.max(1);
+        self
+    }
+
+    /// Sets the number of fix rounds the fix share is split across.
+    pub fn with_fix_rounds(mut self, rounds: u32) -> Self {
+        self.fix_rounds = rounds.max(1);
+        self
+    }
+
+    /// Returns the total budget.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Returns the time left before the total budget is spent.
+    pub fn remaining(&self) -> Duration {
+        self.total.saturating_sub(self.started.elapsed())
+    }
+
+    /// Returns whether the total budget is spent.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Returns the time allocated to one invocation in `phase`.
+    pub fn allocation(&self, phase: BudgetPhase) -> Duration {
+        let (share, parts) = match phase {
+            BudgetPhase::Primary => (self.shares.primary, 1),
+            BudgetPhase::Review => (self.shares.review, self.review_domains),
+            BudgetPhase::Fix => (self.shares.fix, self.fix_rounds),
+        };
+        self.total.mul_f64(share.clamp(0.0, 1.0)) / parts
+    }
+
+    /// Returns how long an invocation in `phase` may run before it is killed.
+    pub fn invocation_deadline(&self, phase: BudgetPhase) -> Duration {
+        self.allocation(phase).min(self.remaining())
+    }
+
+    /// Returns a timeout configuration for an invocation in `phase`.
+    pub fn timeout_config(&self, phase: BudgetPhase, idle_timeout: Duration) -> TimeoutConfig {
+        TimeoutConfig {
+            idle_timeout,
+            total_timeout: self.invocation_deadline(phase),
+            ..TimeoutConfig::default()
         }
     }
 }
@@ -41,6 +163,8 @@ impl Default for TimeoutConfig {
 pub enum TimeoutReason {
     /// No activity for too long.
     Idle,
+    /// Output, but no tool calls or file writes, for too long.
+    Stalled,
     /// Total time exceeded.
     Total,
 }
@@ -55,12 +179,20 @@ pub struct ProgressMonitor {
     commits: Vec<CommitInfo>,
     /// Number of output lines captured.
     output_lines: usize,
+    /// Number of tool calls made.
+    tool_calls: usize,
+    /// Tokens reported by the LLM.
+    usage: TokenUsage,
     /// Time of last activity.
     last_activity: Instant,
+    /// Time of the last tool call, file access, or commit.
+    last_progress: Instant,
     /// Time when monitoring started.
     start_time: Instant,
     /// Timeout configuration.
     timeout_config: TimeoutConfig,
+    /// Budget for the whole run, if this invocation is part of one.
+    budget: Option<TimeoutBudget>,
 }
 
 impl ProgressMonitor {
@@ -72,28 +204,40 @@ impl ProgressMonitor {
             files_written: HashSet::new(),
             commits: Vec::new(),
             output_lines: 0,
+            tool_calls: 0,
+            usage: TokenUsage::default(),
             last_activity: now,
+            last_progress: now,
             start_time: now,
             timeout_config,
+            budget: None,
         }
     }
 
+    /// Attaches the budget of the run this invocation belongs to.
+    ///
+    /// The remaining budget is reported in [`ProgressSummary`].
+    pub fn with_budget(mut self, budget: TimeoutBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Records that a file was read.
     pub fn record_file_read(&mut self, path: PathBuf) {
         self.files_read.insert(path);
-        self.last_activity = Instant::now();
+        self.record_progress();
     }
 
     /// Records that a file was written.
     pub fn record_file_write(&mut self, path: PathBuf) {
         self.files_written.insert(path);
-        self.last_activity = Instant::now();
+        self.record_progress();
     }
 
     /// Records a commit.
     pub fn record_commit(&mut self, info: CommitInfo) {
         self.commits.push(info);
-        self.last_activity = Instant::now();
+        self.record_progress();
     }
 
     /// Records output lines.
@@ -102,6 +246,34 @@ impl ProgressMonitor {
         self.last_activity = Instant::now();
     }
 
+    /// Records a tool call.
+    pub fn record_tool_call(&mut self) {
+        self.tool_calls += 1;
+        self.record_progress();
+    }
+
+    /// Resets both the activity and stall timers.
+    fn record_progress(&mut self) {
+        let now = Instant::now();
+        self.last_activity = now;
+        self.last_progress = now;
+    }
+
+    /// Adds reported token usage.
+    pub fn record_usage(&mut self, usage: &TokenUsage) {
+        self.usage.add(usage);
+    }
+
+    /// Returns the number of tool calls made.
+    pub fn tool_calls(&self) -> usize {
+        self.tool_calls
+    }
+
+    /// Returns the tokens reported so far.
+    pub fn usage(&self) -> TokenUsage {
+        self.usage
+    }
+
     /// Touches the activity timer without recording any specific event.
     pub fn touch(&mut self) {
         self.last_activity = Instant::now();
@@ -132,6 +304,11 @@ impl ProgressMonitor {
         self.last_activity.elapsed()
     }
 
+    /// Returns time since the last tool call, file access, or commit.
+    pub fn stall_duration(&self) -> Duration {
+        self.last_progress.elapsed()
+    }
+
     /// Returns total elapsed time.
     pub fn total_duration(&self) -> Duration {
         self.start_time.elapsed()
@@ -143,6 +320,12 @@ impl ProgressMonitor {
     pub fn check_timeout(&self) -> Option<TimeoutReason> {
         if self.idle_duration() >= self.timeout_config.idle_timeout {
             Some(TimeoutReason::Idle)
+        } else if self
+            .timeout_config
+            .stall_timeout
+            .is_some_and(|stall| self.stall_duration() >= stall)
+        {
+            Some(TimeoutReason::Stalled)
         } else if self.total_duration() >= self.timeout_config.total_timeout {
             Some(TimeoutReason::Total)
         } else {
@@ -150,6 +333,25 @@ impl ProgressMonitor {
         }
     }
 
+    /// Returns how long until the next timeout would trigger.
+    pub fn time_until_timeout(&self) -> Duration {
+        let idle = self
+            .timeout_config
+            .idle_timeout
+            .saturating_sub(self.idle_duration());
+        let total = self
+            .timeout_config
+            .total_timeout
+            .saturating_sub(self.total_duration());
+        let stall = self
+            .timeout_config
+            .stall_timeout
+            .map_or(Duration::MAX, |stall| {
+                stall.saturating_sub(self.stall_duration())
+            });
+        idle.min(total).min(stall)
+    }
+
     /// Returns whether there has been any activity.
     pub fn has_activity(&self) -> bool {
         !self.files_read.is_empty()
@@ -159,6 +361,57 @@ impl ProgressMonitor {
     }
 }
 
+/// Settings for detecting an LLM that repeats the same tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopConfig {
+    /// Number of recent tool calls remembered.
+    pub window: usize,
+    /// Occurrences of one call within the window that count as a loop.
+    pub threshold: usize,
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        Self {
+            window: 10,
+            threshold: 4,
+        }
+    }
+}
+
+/// Detects repeated identical tool calls.
+///
+/// Calls are fingerprinted by tool name and arguments, so re-running the
+/// same failing command counts but running a different test does not.
+#[derive(Debug, Clone)]
+pub struct LoopDetector {
+    config: LoopConfig,
+    recent: VecDeque<(String, String)>,
+}
+
+impl LoopDetector {
+    /// Creates a detector with the given settings.
+    pub fn new(config: LoopConfig) -> Self {
+        Self {
+            config,
+            recent: VecDeque::with_capacity(config.window),
+        }
+    }
+
+    /// Records a tool call, returning the call as `tool args` if it has now
+    /// been repeated `threshold` times within the window.
+    pub fn observe(&mut self, tool: &str, args: &str) -> Option<String> {
+        let call = (tool.to_string(), args.trim().to_string());
+        if self.recent.len() == self.config.window.max(1) {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(call.clone());
+
+        let repeats = self.recent.iter().filter(|c| **c == call).count();
+        (repeats >= self.config.threshold.max(2)).then(|| format!("{} {}", call.0, call.1))
+    }
+}
+
 /// Summary of progress state for serialization.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProgressSummary {
@@ -167,6 +420,15 @@ pub struct ProgressSummary {
     pub commits: Vec<CommitInfo>,
     pub output_lines: usize,
     pub total_duration_secs: f64,
+    /// Seconds left in the run's timeout budget, if it has one.
+    #[serde(default)]
+    pub budget_remaining_secs: Option<f64>,
+    /// Number of tool calls made.
+    #[serde(default)]
+    pub tool_calls: usize,
+    /// Tokens reported by the LLM.
+    #[serde(default)]
+    pub usage: TokenUsage,
 }
 
 impl From<&ProgressMonitor> for ProgressSummary {
@@ -177,6 +439,12 @@ impl From<&ProgressMonitor> for ProgressSummary {
             commits: monitor.commits.clone(),
             output_lines: monitor.output_lines,
             total_duration_secs: monitor.total_duration().as_secs_f64(),
+            budget_remaining_secs: monitor
+                .budget
+                .as_ref()
+                .map(|b| b.remaining().as_secs_f64()),
+            tool_calls: monitor.tool_calls,
+            usage: monitor.usage,
         }
     }
 }
@@ -249,11 +517,34 @@ mod tests {
         assert!(monitor.has_activity());
     }
 
+    #[test]
+    fn progress_monitor_tracks_tool_calls_and_usage() {
+        let mut monitor = ProgressMonitor::new(TimeoutConfig::default());
+
+        monitor.record_tool_call();
+        monitor.record_usage(&TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_read_tokens: 0,
+        });
+        monitor.record_usage(&TokenUsage {
+            input_tokens: 3,
+            output_tokens: 2,
+            cache_read_tokens: 20,
+        });
+
+        let summary = ProgressSummary::from(&monitor);
+        assert_eq!(summary.tool_calls, 1);
+        assert_eq!(summary.usage.input_tokens, 13);
+        assert_eq!(summary.usage.total(), 40);
+    }
+
     #[test]
     fn progress_monitor_detects_idle_timeout() {
         let config = TimeoutConfig {
             idle_timeout: Duration::from_millis(50),
             total_timeout: Duration::from_secs(3600),
+            stall_timeout: None,
         };
         let monitor = ProgressMonitor::new(config);
 
@@ -271,6 +562,7 @@ mod tests {
         let config = TimeoutConfig {
             idle_timeout: Duration::from_secs(3600),
             total_timeout: Duration::from_millis(50),
+            stall_timeout: None,
         };
         let monitor = ProgressMonitor::new(config);
 
@@ -288,6 +580,7 @@ mod tests {
         let config = TimeoutConfig {
             idle_timeout: Duration::from_millis(100),
             total_timeout: Duration::from_secs(3600),
+            stall_timeout: None,
         };
         let mut monitor = ProgressMonitor::new(config);
 
@@ -304,6 +597,61 @@ mod tests {
         assert_eq!(monitor.check_timeout(), None);
     }
 
+    #[test]
+    fn progress_monitor_detects_stall_despite_output() {
+        let config = TimeoutConfig {
+            idle_timeout: Duration::from_secs(3600),
+            total_timeout: Duration::from_secs(3600),
+            stall_timeout: Some(Duration::from_millis(100)),
+        };
+        let mut monitor = ProgressMonitor::new(config);
+
+        thread::sleep(Duration::from_millis(60));
+        monitor.record_tool_call();
+        thread::sleep(Duration::from_millis(60));
+
+        // The tool call reset the stall timer
+        assert_eq!(monitor.check_timeout(), None);
+
+        // Output keeps the LLM from going idle, but is not progress
+        monitor.record_output(5);
+        thread::sleep(Duration::from_millis(60));
+        monitor.record_output(5);
+
+        assert_eq!(monitor.check_timeout(), Some(TimeoutReason::Stalled));
+    }
+
+    #[test]
+    fn loop_detector_flags_repeated_calls() {
+        let mut detector = LoopDetector::new(LoopConfig {
+            window: 5,
+            threshold: 3,
+        });
+
+        assert_eq!(detector.observe("Bash", "cargo test"), None);
+        assert_eq!(detector.observe("Read", "src/lib.rs"), None);
+        assert_eq!(detector.observe("Bash", "cargo test "), None);
+        assert_eq!(detector.observe("Bash", "cargo test -p core"), None);
+        assert_eq!(
+            detector.observe("Bash", "cargo test"),
+            Some("Bash cargo test".to_string())
+        );
+    }
+
+    #[test]
+    fn loop_detector_forgets_calls_outside_window() {
+        let mut detector = LoopDetector::new(LoopConfig {
+            window: 3,
+            threshold: 2,
+        });
+
+        detector.observe("Bash", "make");
+        detector.observe("Read", "a.rs");
+        detector.observe("Read", "b.rs");
+
+        assert_eq!(detector.observe("Bash", "make"), None);
+    }
+
     #[test]
     fn progress_summary_captures_state() {
         let mut monitor = ProgressMonitor::new(TimeoutConfig::default());
@@ -324,4 +672,67 @@ mod tests {
         assert_eq!(summary.output_lines, 42);
         assert!(summary.total_duration_secs >= 0.0);
     }
+
+    #[test]
+    fn budget_splits_across_phases() {
+        let budget = TimeoutBudget::new(Duration::from_secs(1200))
+            .with_review_domains(3)
+            .with_fix_rounds(2);
+
+        assert_eq!(budget.allocation(BudgetPhase::Primary), Duration::from_secs(600));
+        assert_eq!(budget.allocation(BudgetPhase::Review), Duration::from_secs(100));
+        assert_eq!(budget.allocation(BudgetPhase::Fix), Duration::from_secs(150));
+    }
+
+    #[test]
+    fn budget_deadline_is_capped_by_remaining_time() {
+        let budget = TimeoutBudget::new(Duration::from_millis(40)).with_shares(BudgetShares {
+            primary: 1.0,
+            review: 0.0,
+            fix: 0.0,
+        });
+
+        thread::sleep(Duration::from_millis(20));
+
+        assert!(budget.invocation_deadline(BudgetPhase::Primary) <= Duration::from_millis(20));
+        assert_eq!(budget.invocation_deadline(BudgetPhase::Review), Duration::ZERO);
+
+        thread::sleep(Duration::from_millis(30));
+        assert!(budget.is_exhausted());
+    }
+
+    #[test]
+    fn budget_timeout_config_uses_phase_deadline() {
+        let budget = TimeoutBudget::new(Duration::from_secs(100));
+
+        let config = budget.timeout_config(BudgetPhase::Fix, Duration::from_secs(30));
+
+        assert_eq!(config.idle_timeout, Duration::from_secs(30));
+        assert!(config.total_timeout <= Duration::from_secs(25));
+        assert!(config.total_timeout > Duration::from_secs(24));
+    }
+
+    #[test]
+    fn progress_summary_reports_remaining_budget() {
+        let monitor = ProgressMonitor::new(TimeoutConfig::default());
+        assert!(ProgressSummary::from(&monitor).budget_remaining_secs.is_none());
+
+        let budget = TimeoutBudget::new(Duration::from_secs(60));
+        let monitor = ProgressMonitor::new(TimeoutConfig::default()).with_budget(budget);
+        let remaining = ProgressSummary::from(&monitor).budget_remaining_secs.unwrap();
+        assert!(remaining > 59.0 && remaining <= 60.0);
+    }
+
+    #[test]
+    fn time_until_timeout_tracks_nearest_deadline() {
+        let config = TimeoutConfig {
+            idle_timeout: Duration::from_secs(10),
+            total_timeout: Duration::from_secs(3),
+            stall_timeout: None,
+        };
+        let monitor = ProgressMonitor::new(config);
+
+        assert!(monitor.time_until_timeout() <= Duration::from_secs(3));
+        assert!(monitor.time_until_timeout() > Duration::from_secs(2));
+    }
 }
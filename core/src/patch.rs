@@ -0,0 +1,505 @@
+//! Apply-patch execution mode.
+//!
+//! In [`WriteMode::Patch`] the spawned LLM gets read-only tools and answers
+//! with a unified diff. The orchestrator checks every path the diff touches
+//! against the manifest's writable paths and applies it with `git apply`,
+//! so an untrusted prompt can never write outside them or run commands
+//! that do.
+
+use std::io::Write;
+use std::path::{Component, Path};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, PatchError, Result};
+use crate::permissions::PermissionFix;
+use crate::sandbox::SandboxManifest;
+
+/// How a spawned LLM changes files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteMode {
+    /// The LLM edits files with its own tools.
+    #[default]
+    Direct,
+    /// The LLM outputs a unified diff that the orchestrator applies.
+    Patch,
+    /// The LLM changes nothing and reports its findings in Markdown. See
+    /// [`crate::investigation`].
+    #[serde(rename = "read-only")]
+    ReadOnly,
+}
+
+impl WriteMode {
+    /// Returns whether the LLM gets its own write tools.
+    pub fn grants_write_tools(self) -> bool {
+        self == WriteMode::Direct
+    }
+}
+
+/// Tools granted in patch and read-only mode.
+pub const PATCH_MODE_TOOLS: &[&str] = &["Read", "Glob", "Grep"];
+
+/// Appended to the prompt in patch mode.
+pub const PATCH_INSTRUCTIONS: &str = "\n\n### Output Format\n\n\
+You cannot edit files or run commands. Read the files you need, then end your final \
+message with the complete change as a unified diff, as `git diff` prints it, in a \
+```diff fenced block. Use paths relative to the repository root and `/dev/null` as the \
+old path of new files. The diff is applied with `git apply`; changes outside the \
+allowed paths are rejected.";
+
+/// Returns the manifest the LLM runs with in patch and read-only mode: the
+/// same permissions with only read-only tools.
+pub fn read_only(manifest: &SandboxManifest) -> SandboxManifest {
+    SandboxManifest {
+        allowed_tools: PATCH_MODE_TOOLS.iter().map(|t| t.to_string()).collect(),
+        ..manifest.clone()
+    }
+}
+
+/// Returns the fix to apply for a permission error when the LLM runs with
+/// read-only tools.
+///
+/// Only read access can be granted; tools, commands, and write paths
+/// would let the LLM change files directly.
+pub fn restrict_fix(fix: &PermissionFix) -> PermissionFix {
+    match fix {
+        PermissionFix::EnableTool(tool) if !PATCH_MODE_TOOLS.contains(&tool.as_str()) => {
+            PermissionFix::CannotFix(format!(
+                "the {} tool is not granted without direct writes",
+                tool
+            ))
+        }
+        PermissionFix::AllowCommand(command) => PermissionFix::CannotFix(format!(
+            "`{}` is not allowed without direct writes",
+            command
+        )),
+        PermissionFix::AddWritePath(path) => {
+            PermissionFix::CannotFix(format!("{} is not writable without direct writes", path))
+        }
+        fix => fix.clone(),
+    }
+}
+
+/// Extracts the patch from the LLM's messages.
+///
+/// Uses the diff blocks of the last message that has any, so drafts in
+/// earlier messages are ignored. Runners that report output line by line
+/// have no single message with a whole block; their output is searched as
+/// one text instead.
+pub fn extract_patch(messages: &[String]) -> Option<String> {
+    messages
+        .iter()
+        .rev()
+        .find_map(|message| diff_blocks(message))
+        .or_else(|| diff_blocks(&messages.join("\n")))
+}
+
+/// Joins the ```diff and ```patch fenced blocks of `text`.
+fn diff_blocks(text: &str) -> Option<String> {
+    let mut patch = String::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if !in_block {
+            in_block = trimmed == "```diff" || trimmed == "```patch";
+        } else if trimmed == "```" {
+            in_block = false;
+        } else {
+            patch.push_str(line);
+            patch.push('\n');
+        }
+    }
+    (!patch.trim().is_empty()).then_some(patch)
+}
+
+/// Returns every path a unified diff names: the files it creates, changes,
+/// renames, copies, or deletes, and the sources of copies.
+///
+/// `diff --git` headers count too, so mode changes and binary patches
+/// without `---`/`+++` lines are covered. Fails if a header's paths are
+/// quoted or cannot be told apart.
+pub fn patch_paths(patch: &str) -> Result<Vec<String>> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in patch.lines() {
+        let named = if let Some(header) = line.strip_prefix("diff --git ") {
+            let (old, new) = git_header_paths(header)
+                .ok_or_else(|| Error::Patch(PatchError::UnknownPaths(line.to_string())))?;
+            vec![old, new]
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            vec![strip_side(line, path, "a/")?]
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            vec![strip_side(line, path, "b/")?]
+        } else if let Some(path) = ["rename from ", "rename to ", "copy from ", "copy to "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+        {
+            vec![path]
+        } else {
+            continue;
+        };
+
+        for path in named {
+            // Headers may carry a tab-separated timestamp
+            let path = path.split('\t').next().unwrap_or(path).trim();
+            if path.starts_with('"') {
+                return Err(Error::Patch(PatchError::UnknownPaths(line.to_string())));
+            }
+            if path != "/dev/null" && !paths.iter().any(|p| p == path) {
+                paths.push(path.to_string());
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Strips the `a/` or `b/` prefix that `git apply -p1` removes from a
+/// `---` or `+++` path.
+///
+/// git drops the first component whatever it is, so a path without the
+/// prefix would be checked as written but applied one directory up; those
+/// are rejected.
+fn strip_side<'a>(line: &str, path: &'a str, prefix: &str) -> Result<&'a str> {
+    let name = path.split('\t').next().unwrap_or(path).trim();
+    if name == "/dev/null" {
+        return Ok(path);
+    }
+    path.strip_prefix(prefix)
+        .ok_or_else(|| Error::Patch(PatchError::UnknownPaths(line.to_string())))
+}
+
+/// Splits the `a/<old> b/<new>` part of a `diff --git` header.
+///
+/// Unchanged names are found by length, so they may contain " b/"; renamed
+/// or copied ones may not. Quoted names are not unquoted.
+fn git_header_paths(header: &str) -> Option<(&str, &str)> {
+    let names = header.strip_prefix("a/")?;
+    if names.len() >= 3 && (names.len() - 3) % 2 == 0 {
+        let half = (names.len() - 3) / 2;
+        if let (Some(old), Some(" b/"), Some(new)) = (
+            names.get(..half),
+            names.get(half..half + 3),
+            names.get(half + 3..),
+        ) {
+            if old == new {
+                return Some((old, new));
+            }
+        }
+    }
+
+    let mut split = names.split(" b/");
+    match (split.next(), split.next(), split.next()) {
+        (Some(old), Some(new), None) if !old.is_empty() && !new.is_empty() => Some((old, new)),
+        _ => None,
+    }
+}
+
+/// Checks that every path in `patch` stays inside the repository and
+/// matches the manifest's writable paths, when it lists any.
+pub fn validate(patch: &str, manifest: &SandboxManifest) -> Result<Vec<String>> {
+    let paths = patch_paths(patch)?;
+    if paths.is_empty() {
+        return Err(Error::Patch(PatchError::NoFiles));
+    }
+
+    for path in &paths {
+        let relative = Path::new(path);
+        let escapes = relative.is_absolute()
+            || relative
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        if escapes || relative.starts_with(".git") {
+            return Err(Error::Patch(PatchError::OutsideRepository(path.clone())));
+        }
+        if !manifest.writable_paths.is_empty()
+            && !manifest
+                .writable_paths
+                .iter()
+                .any(|pattern| glob_matches(pattern, path))
+        {
+            return Err(Error::Patch(PatchError::NotWritable(path.clone())));
+        }
+    }
+    Ok(paths)
+}
+
+/// Validates `patch` and applies it to `worktree`. Returns the paths it
+/// touched.
+///
+/// Nothing is changed unless the whole patch applies.
+pub fn apply(worktree: &Path, patch: &str, manifest: &SandboxManifest) -> Result<Vec<String>> {
+    let paths = validate(patch, manifest)?;
+    git_apply(worktree, patch, &["apply", "-p1", "--check"])?;
+    git_apply(worktree, patch, &["apply", "-p1"])?;
+
+    tracing::info!(files = paths.len(), "applied patch from LLM");
+    Ok(paths)
+}
+
+/// Applies the patch in an LLM's messages to `worktree`. Returns the
+/// paths it touched.
+pub fn apply_output(
+    worktree: &Path,
+    messages: &[String],
+    manifest: &SandboxManifest,
+) -> Result<Vec<String>> {
+    let patch = extract_patch(messages).ok_or(Error::Patch(PatchError::NoDiff))?;
+    apply(worktree, &patch, manifest)
+}
+
+/// Runs `git apply` with the patch on stdin.
+fn git_apply(worktree: &Path, patch: &str, args: &[&str]) -> Result<()> {
+    let mut child = Command::new("git")
+        .current_dir(worktree)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(patch.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::git_command(args, &output));
+    }
+    Ok(())
+}
+
+/// Matches `path` against a manifest path pattern.
+///
+/// `**` matches any number of directories, `*` anything within one path
+/// segment, and a trailing `/` the whole directory.
+pub(crate) fn glob_matches(pattern: &str, path: &str) -> bool {
+    if let Some(dir) = pattern.strip_suffix('/') {
+        return path.starts_with(&format!("{}/", dir));
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                match_segment(segment.as_bytes(), name.as_bytes())
+                    && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| match_segment(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PATCH: &str = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                         --- a/src/lib.rs\n\
+                         +++ b/src/lib.rs\n\
+                         @@ -1 +1 @@\n\
+                         -pub fn old() {}\n\
+                         +pub fn new() {}\n\
+                         diff --git a/src/util.rs b/src/util.rs\n\
+                         new file mode 100644\n\
+                         --- /dev/null\n\
+                         +++ b/src/util.rs\n\
+                         @@ -0,0 +1 @@\n\
+                         +pub fn util() {}\n";
+
+    fn repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .current_dir(dir.path())
+                .args(["-c", "user.name=Test", "-c", "user.email=test@test.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "pub fn old() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-qm", "init"]);
+        dir
+    }
+
+    #[test]
+    fn extract_patch_prefers_last_message() {
+        let messages = vec![
+            "Draft:\n```diff\n--- a/draft.rs\n```".to_string(),
+            "Reading files".to_string(),
+            format!("Final change:\n\n```diff\n{}```\n\nDone.", PATCH),
+            "All set.".to_string(),
+        ];
+
+        assert_eq!(extract_patch(&messages).as_deref(), Some(PATCH));
+
+        let lines: Vec<String> = ["```patch", "--- a/x", "+++ b/x", "```"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(extract_patch(&lines).as_deref(), Some("--- a/x\n+++ b/x\n"));
+        assert!(extract_patch(&["No diff".to_string()]).is_none());
+    }
+
+    #[test]
+    fn validate_checks_writable_paths() {
+        assert_eq!(
+            patch_paths(PATCH).unwrap(),
+            vec!["src/lib.rs", "src/util.rs"]
+        );
+
+        let open = SandboxManifest::default();
+        let src_only = SandboxManifest {
+            writable_paths: vec!["src/**".to_string()],
+            ..Default::default()
+        };
+        let tests_only = SandboxManifest {
+            writable_paths: vec!["tests/".to_string()],
+            ..Default::default()
+        };
+
+        assert!(validate(PATCH, &open).is_ok());
+        assert!(validate(PATCH, &src_only).is_ok());
+        assert!(validate(PATCH, &tests_only).is_err());
+        assert!(validate("--- a/../etc/passwd\n+++ b/../etc/passwd\n", &open).is_err());
+        assert!(validate("--- a/.git/config\n+++ b/.git/config\n", &open).is_err());
+        assert!(validate("just text\n", &open).is_err());
+    }
+
+    #[test]
+    fn header_only_and_copied_paths_are_checked() {
+        let src_only = SandboxManifest {
+            writable_paths: vec!["src/**".to_string()],
+            ..Default::default()
+        };
+        let mode_change = "diff --git a/scripts/run.sh b/scripts/run.sh\n\
+                           old mode 100644\n\
+                           new mode 100755\n";
+        let copy = "diff --git a/src/lib.rs b/.github/workflows/ci.yml\n\
+                    similarity index 100%\n\
+                    copy from src/lib.rs\n\
+                    copy to .github/workflows/ci.yml\n";
+        let binary = "diff --git a/src/logo b/src/logo.png\n\
+                      GIT binary patch\n\
+                      literal 0\n";
+
+        assert_eq!(patch_paths(mode_change).unwrap(), vec!["scripts/run.sh"]);
+        assert_eq!(
+            patch_paths(copy).unwrap(),
+            vec!["src/lib.rs", ".github/workflows/ci.yml"]
+        );
+        assert_eq!(
+            patch_paths(binary).unwrap(),
+            vec!["src/logo", "src/logo.png"]
+        );
+        assert!(validate(mode_change, &src_only).is_err());
+        assert!(validate(copy, &src_only).is_err());
+        assert!(validate(binary, &src_only).is_ok());
+        // An unchanged name may itself contain " b/"
+        assert_eq!(
+            patch_paths("diff --git a/x b/y b/x b/y\n").unwrap(),
+            vec!["x b/y"]
+        );
+    }
+
+    #[test]
+    fn patches_with_unknown_targets_are_rejected() {
+        for patch in [
+            "diff --git a/x b/y b/z\n",
+            "diff --git \"a/tab\\there\" \"b/tab\\there\"\n",
+            "diff --git x y\n",
+            "--- \"a/quoted\"\n+++ \"b/quoted\"\n",
+            "--- src/lib.rs\n+++ b/src/lib.rs\n",
+        ] {
+            let err = patch_paths(patch).unwrap_err();
+            assert!(
+                matches!(err, Error::Patch(PatchError::UnknownPaths(_))),
+                "{}: {:?}",
+                patch,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn apply_changes_worktree_only_when_whole_patch_fits() {
+        let repo = repo();
+        let manifest = SandboxManifest::default();
+        let broken = PATCH.replace("-pub fn old() {}", "-pub fn other() {}");
+
+        assert!(apply(repo.path(), &broken, &manifest).is_err());
+        assert!(!repo.path().join("src/util.rs").exists());
+
+        let paths = apply(repo.path(), PATCH, &manifest).unwrap();
+
+        assert_eq!(paths, vec!["src/lib.rs", "src/util.rs"]);
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("src/lib.rs")).unwrap(),
+            "pub fn new() {}\n"
+        );
+        assert!(repo.path().join("src/util.rs").exists());
+    }
+
+    #[test]
+    fn paths_without_side_prefixes_are_not_applied_elsewhere() {
+        let repo = repo();
+        let docs_only = SandboxManifest {
+            writable_paths: vec!["docs/**".to_string()],
+            ..Default::default()
+        };
+        // git apply -p1 would write this to src/lib.rs
+        let patch = "--- docs/src/lib.rs\n\
+                     +++ docs/src/lib.rs\n\
+                     @@ -1 +1 @@\n\
+                     -pub fn old() {}\n\
+                     +pub fn new() {}\n";
+
+        let err = apply(repo.path(), patch, &docs_only).unwrap_err();
+
+        assert!(matches!(err, Error::Patch(PatchError::UnknownPaths(_))));
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("src/lib.rs")).unwrap(),
+            "pub fn old() {}\n"
+        );
+    }
+
+    #[test]
+    fn patch_mode_refuses_write_permissions() {
+        let manifest = SandboxManifest {
+            allowed_tools: vec!["Read".to_string(), "Edit".to_string(), "Bash".to_string()],
+            writable_paths: vec!["src/**".to_string()],
+            ..Default::default()
+        };
+
+        let restricted = read_only(&manifest);
+
+        assert_eq!(restricted.allowed_tools, PATCH_MODE_TOOLS);
+        assert_eq!(restricted.writable_paths, manifest.writable_paths);
+        assert!(matches!(
+            restrict_fix(&PermissionFix::EnableTool("Write".to_string())),
+            PermissionFix::CannotFix(_)
+        ));
+        assert_eq!(
+            restrict_fix(&PermissionFix::AddReadPath("docs/**".to_string())),
+            PermissionFix::AddReadPath("docs/**".to_string())
+        );
+    }
+}
@@ -3,7 +3,7 @@
 //! Pattern-matches common permission errors and computes appropriate fixes
 //! for the recovery system.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
@@ -56,6 +56,33 @@ pub struct PermissionError {
     pub original_message: String,
 }
 
+impl PermissionError {
+    /// Builds the error for a tool call the CLI refused to run.
+    ///
+    /// Shell calls are reported as the blocked command, other tools as
+    /// disabled. `args` is the call's JSON input.
+    pub fn tool_denied(tool: &str, args: &str) -> Self {
+        let command = serde_json::from_str::<serde_json::Value>(args)
+            .ok()
+            .and_then(|v| v.get("command")?.as_str().map(str::to_string));
+        let (error_type, fix) = match command {
+            Some(cmd) if tool == "Bash" || tool == "run_shell_command" => (
+                PermissionErrorType::CommandBlocked(cmd.clone()),
+                PermissionFix::AllowCommand(cmd),
+            ),
+            _ => (
+                PermissionErrorType::ToolDisabled(tool.to_string()),
+                PermissionFix::EnableTool(tool.to_string()),
+            ),
+        };
+        Self {
+            error_type,
+            fix,
+            original_message: format!("{} denied: {}", tool, args),
+        }
+    }
+}
+
 /// Detects and classifies permission errors from output.
 pub struct PermissionDetector {
     /// Known patterns for file read denials.
@@ -269,7 +296,7 @@ impl PermissionDetector {
     }
 
     /// Converts a path to a glob pattern for the parent directory.
-    fn path_to_pattern(&self, path: &PathBuf) -> String {
+    fn path_to_pattern(&self, path: &Path) -> String {
         if let Some(parent) = path.parent() {
             format!("{}/**", parent.display())
         } else {
@@ -459,6 +486,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tool_denied_reports_shell_commands_and_tools() {
+        let error = PermissionError::tool_denied("Bash", r#"{"command":"npm test"}"#);
+        assert_eq!(
+            error.error_type,
+            PermissionErrorType::CommandBlocked("npm test".to_string())
+        );
+        assert_eq!(
+            error.fix,
+            PermissionFix::AllowCommand("npm test".to_string())
+        );
+
+        let error = PermissionError::tool_denied("WebFetch", r#"{"url":"https://x.dev"}"#);
+        assert_eq!(error.fix, PermissionFix::EnableTool("WebFetch".to_string()));
+    }
+
     #[test]
     fn detector_detects_env_var_missing() {
         let detector = PermissionDetector::new();
@@ -2,12 +2,96 @@
 //!
 //! Handles creating PRs from worktree branches and resolving merge conflicts.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, Result};
+use crate::cancel::Checkpoint;
+use crate::commit::{CommitContext, CommitMessages};
+use crate::cruise::AuditFinding;
+use crate::error::{Error, Result, ResultExt};
+use crate::git::{GitExecutor, SystemGit};
+use crate::guardrails::FileViolation;
+use crate::team::{review_from_json, ReviewResult, ReviewSuggestion, ReviewVerdict};
+
+/// Marker opening the hidden review artifact in a PR comment.
+pub const REVIEW_ARTIFACT_MARKER: &str = "<!-- improbability-drive:review";
+
+/// Footer ending the description of every PR the drive opens.
+pub const CREATED_BY_FOOTER: &str = "*Created by infinite-improbability-drive*";
+
+/// Opens the section of a PR description holding one domain's review,
+/// followed by the domain and ` -->`.
+const REVIEW_SECTION_START: &str = "<!-- improbability-drive:review-section ";
+
+/// Closes a domain's review section, followed by the domain and ` -->`.
+const REVIEW_SECTION_END: &str = "<!-- /improbability-drive:review-section ";
+
+/// Attempts at writing a review section before giving up when another
+/// writer keeps replacing the PR description.
+const BODY_EDIT_ATTEMPTS: u32 = 3;
+
+/// Serializes PR description edits made by this process.
+static BODY_EDIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Longest PR description or comment sent to GitHub, in bytes. GitHub
+/// rejects bodies over 65536 characters.
+pub const MAX_BODY_BYTES: usize = 60_000;
+
+/// New files never committed, even without a `.gitignore` entry (git glob
+/// pathspecs).
+pub const DEFAULT_COMMIT_DENY: &[&str] = &[
+    "**/node_modules/**",
+    "**/__pycache__/**",
+    "**/*.pyc",
+    "**/.DS_Store",
+];
+
+/// GraphQL query listing a PR's review threads with their first comment.
+const REVIEW_THREADS_QUERY: &str = "query($owner: String!, $repo: String!, $number: Int!) {
+  repository(owner: $owner, name: $repo) {
+    pullRequest(number: $number) {
+      reviewThreads(first: 100) {
+        nodes {
+          id isResolved path line
+          comments(first: 1) { nodes { databaseId body author { login } } }
+        }
+      }
+    }
+  }
+}";
+
+/// GraphQL mutation resolving a review thread.
+const RESOLVE_THREAD_MUTATION: &str = "mutation($id: ID!) {
+  resolveReviewThread(input: { threadId: $id }) { thread { isResolved } }
+}";
+
+/// Headline of a review comment that approves the changes.
+const APPROVED_HEADLINE: &str = "REVIEW - APPROVED";
+
+/// Headline of a review comment that requests changes.
+const NEEDS_CHANGES_HEADLINE: &str = "REVIEW - NEEDS CHANGES";
+
+/// A review thread on a PR, as started by its first comment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewThread {
+    /// GraphQL node ID of the thread.
+    pub id: String,
+    /// Database ID of the first comment, which replies are threaded under.
+    pub comment_id: u64,
+    /// Login of the comment author.
+    pub author: String,
+    /// File the thread is on.
+    pub path: String,
+    /// Line the thread is anchored to, if it is still on the diff.
+    pub line: Option<u32>,
+    /// Body of the first comment.
+    pub body: String,
+    /// Whether the thread is resolved.
+    pub is_resolved: bool,
+}
 
 /// Information about a created pull request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +106,9 @@ pub struct PullRequest {
     pub base_branch: String,
     /// Source branch.
     pub head_branch: String,
+    /// Whether the PR is a draft.
+    #[serde(default)]
+    pub is_draft: bool,
 }
 
 /// Strategy for handling merge conflicts.
@@ -64,6 +151,39 @@ pub struct PRManager {
     repo_path: PathBuf,
     /// Conflict handling strategy.
     conflict_strategy: ConflictStrategy,
+    /// Whether new PRs are opened as drafts.
+    draft: bool,
+    /// Labels added to new PRs.
+    labels: Vec<String>,
+    /// Issues new PRs close when merged, e.g. `owner/repo#12`.
+    closes: Vec<String>,
+    /// How commits are described.
+    commit_messages: CommitMessages,
+    /// Globs of new files that are never committed.
+    commit_deny: Vec<String>,
+    /// Runs git for commits, pushes and branch inspection.
+    git: Arc<dyn GitExecutor>,
+}
+
+/// Result of committing a worktree's changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitOutcome {
+    /// The new commit, or `None` if nothing was left to commit.
+    pub hash: Option<String>,
+    /// New files left out because they are ignored or denied.
+    pub skipped: Vec<PathBuf>,
+    /// Files that broke a size or binary guardrail.
+    pub violations: Vec<FileViolation>,
+}
+
+impl CommitOutcome {
+    /// Returns the guardrail violations as audit findings.
+    pub fn findings(&self) -> Vec<AuditFinding> {
+        self.violations
+            .iter()
+            .map(FileViolation::to_finding)
+            .collect()
+    }
 }
 
 impl PRManager {
@@ -72,6 +192,12 @@ impl PRManager {
         Self {
             repo_path,
             conflict_strategy: ConflictStrategy::default(),
+            draft: false,
+            labels: Vec::new(),
+            closes: Vec::new(),
+            commit_messages: CommitMessages::default(),
+            commit_deny: DEFAULT_COMMIT_DENY.iter().map(|p| p.to_string()).collect(),
+            git: Arc::new(SystemGit),
         }
     }
 
@@ -81,71 +207,230 @@ impl PRManager {
         self
     }
 
+    /// Opens new PRs as drafts.
+    ///
+    /// Draft PRs don't notify reviewers; use [`Self::mark_ready_if_approved`]
+    /// once internal review passes.
+    pub fn with_draft(mut self, draft: bool) -> Self {
+        self.draft = draft;
+        self
+    }
+
+    /// Adds labels to new PRs.
+    pub fn with_labels(mut self, labels: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.labels.extend(labels.into_iter().map(Into::into));
+        self
+    }
+
+    /// Links new PRs to issues they close when merged.
+    pub fn with_closing_issues(
+        mut self,
+        issues: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.closes.extend(issues.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets how commits are described.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.commit_messages = messages;
+        self
+    }
+
+    /// Adds globs of new files that are never committed, such as a
+    /// manifest's `commit_deny`.
+    pub fn with_commit_deny(mut self, globs: impl IntoIterator<Item = String>) -> Self {
+        self.commit_deny.extend(globs);
+        self
+    }
+
+    /// Runs git through `git` instead of the system binary.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Returns the repository path.
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
     /// Commits any uncommitted changes in the worktree.
-    pub fn commit_changes(&self, worktree_path: &PathBuf, message: &str) -> Result<Option<String>> {
-        // Check for changes
-        let status = Command::new("git")
-            .current_dir(worktree_path)
-            .args(["status", "--porcelain"])
-            .output()?;
+    pub fn commit_changes(&self, worktree_path: &Path, message: &str) -> Result<Option<String>> {
+        Ok(self.commit_filtered(worktree_path, message)?.hash)
+    }
 
-        let status_output = String::from_utf8_lossy(&status.stdout);
-        if status_output.trim().is_empty() {
-            return Ok(None); // No changes to commit
+    /// Commits any uncommitted changes in the worktree, leaving out new
+    /// files that `.gitignore` or the deny globs exclude.
+    ///
+    /// Ignored files can still reach the index, e.g. through `git add -f`
+    /// or a missing `.gitignore`; they are unstaged and reported in
+    /// [`CommitOutcome::skipped`] rather than committed. Files that break
+    /// the configured [`FileGuardrails`](crate::guardrails::FileGuardrails)
+    /// are reported in [`CommitOutcome::violations`].
+    pub fn commit_filtered(&self, worktree_path: &Path, message: &str) -> Result<CommitOutcome> {
+        // Check for changes
+        let status = self.git.run(worktree_path, &["status", "--porcelain"])?;
+        if status.trim().is_empty() {
+            return Ok(CommitOutcome::default()); // No changes to commit
         }
 
         // Stage all changes
-        let add = Command::new("git")
-            .current_dir(worktree_path)
-            .args(["add", "-A"])
-            .output()?;
+        self.git
+            .run(worktree_path, &["add", "-A"])
+            .context("failed to stage changes")?;
 
-        if !add.status.success() {
-            return Err(Error::Git(format!(
-                "failed to stage changes: {}",
-                String::from_utf8_lossy(&add.stderr)
-            )));
+        let skipped = self.unstage_unwanted(worktree_path)?;
+        if !skipped.is_empty() {
+            tracing::warn!(skipped = ?skipped, "left ignored files out of the commit");
+        }
+        let violations = self.commit_messages.guardrails.enforce(worktree_path)?;
+        if !violations.is_empty() {
+            tracing::warn!(violations = ?violations, "files broke commit guardrails");
         }
 
-        // Commit
-        let commit = Command::new("git")
-            .current_dir(worktree_path)
-            .args(["commit", "-m", message])
-            .output()?;
+        let staged = self
+            .git
+            .output(worktree_path, &["diff", "--cached", "--quiet"])?;
+        if staged.success() {
+            return Ok(CommitOutcome {
+                hash: None,
+                skipped,
+                violations,
+            });
+        }
 
-        if !commit.status.success() {
-            let stderr = String::from_utf8_lossy(&commit.stderr);
-            // Check if it's just "nothing to commit"
-            if stderr.contains("nothing to commit") {
-                return Ok(None);
-            }
-            return Err(Error::Git(format!("failed to commit: {}", stderr)));
+        // Commit, signing if configured
+        let args = self.commit_messages.commit_args(message);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let commit = self.git.output(worktree_path, &args)?;
+        // Check if it's just "nothing to commit"
+        if !commit.success() && commit.stderr.contains("nothing to commit") {
+            return Ok(CommitOutcome {
+                hash: None,
+                skipped,
+                violations,
+            });
         }
+        commit.into_result(&args).context("failed to commit")?;
 
         // Get commit hash
-        let rev = Command::new("git")
-            .current_dir(worktree_path)
-            .args(["rev-parse", "HEAD"])
-            .output()?;
-
-        let hash = String::from_utf8_lossy(&rev.stdout).trim().to_string();
-        Ok(Some(hash))
+        let hash = self
+            .git
+            .run(worktree_path, &["rev-parse", "HEAD"])?
+            .trim()
+            .to_string();
+        Ok(CommitOutcome {
+            hash: Some(hash),
+            skipped,
+            violations,
+        })
     }
 
-    /// Pushes a branch to the remote.
-    pub fn push_branch(&self, worktree_path: &PathBuf, branch_name: &str) -> Result<()> {
-        let output = Command::new("git")
-            .current_dir(worktree_path)
-            .args(["push", "-u", "origin", branch_name])
-            .output()?;
+    /// Unstages newly added files that are gitignored or match a deny glob,
+    /// returning them.
+    fn unstage_unwanted(&self, worktree_path: &Path) -> Result<Vec<PathBuf>> {
+        let added = self.git.run(
+            worktree_path,
+            &["diff", "--cached", "--name-only", "--diff-filter=A", "-z"],
+        )?;
+        let added: Vec<&str> = added.split('\0').filter(|p| !p.is_empty()).collect();
+        if added.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        if !output.status.success() {
-            return Err(Error::Git(format!(
-                "failed to push branch: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
+        // --no-index also matches files that are already staged; exit
+        // status 1 means none of them is ignored
+        let mut args = vec!["check-ignore", "--no-index", "--"];
+        args.extend(added.iter().copied());
+        let ignored = self.git.output(worktree_path, &args)?;
+        let ignored = match ignored.status {
+            Some(1) => String::new(),
+            _ => ignored.into_result(&args)?,
+        };
+        let mut unwanted: Vec<String> = ignored
+            .lines()
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if !self.commit_deny.is_empty() {
+            let mut args = vec![
+                "diff".to_string(),
+                "--cached".to_string(),
+                "--name-only".to_string(),
+                "--diff-filter=A".to_string(),
+                "-z".to_string(),
+                "--".to_string(),
+            ];
+            args.extend(self.commit_deny.iter().map(|g| format!(":(glob){}", g)));
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            let denied = self.git.run(worktree_path, &args)?;
+            for path in denied.split('\0') {
+                if !path.is_empty() && !unwanted.iter().any(|p| p == path) {
+                    unwanted.push(path.to_string());
+                }
+            }
+        }
+
+        if unwanted.is_empty() {
+            return Ok(Vec::new());
         }
+        unwanted.sort();
+
+        let mut args = vec!["rm", "--cached", "-q", "--"];
+        args.extend(unwanted.iter().map(String::as_str));
+        self.git
+            .run(worktree_path, &args)
+            .context("failed to unstage ignored files")?;
+
+        Ok(unwanted.into_iter().map(PathBuf::from).collect())
+    }
 
+    /// Commits any uncommitted changes with a message rendered from the
+    /// commit template.
+    pub fn commit_with_context(
+        &self,
+        worktree_path: &Path,
+        context: &CommitContext,
+    ) -> Result<Option<String>> {
+        self.commit_changes(worktree_path, &self.commit_messages.message(context))
+    }
+
+    /// Rewrites the worktree branch's history for a PR against
+    /// `base_branch`, following the configured
+    /// [`GitHistoryPolicy`](crate::commit::GitHistoryPolicy).
+    ///
+    /// A branch squashed into one commit is described by `context`. Returns
+    /// the new head, or `None` if the history was left alone.
+    pub fn prepare_for_pr(
+        &self,
+        worktree_path: &Path,
+        base_branch: &str,
+        context: &CommitContext,
+    ) -> Result<Option<String>> {
+        self.commit_messages.rewrite_history(
+            worktree_path,
+            base_branch,
+            &self.commit_messages.message(context),
+        )
+    }
+
+    /// Pushes the worktree's `HEAD` to `branch` on the remote, e.g. to
+    /// add fixes to an existing PR from a sandbox branch.
+    pub fn push_head_to(&self, worktree_path: &Path, branch: &str) -> Result<()> {
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        self.git
+            .run(worktree_path, &["push", "origin", &refspec])
+            .context(format!("failed to push to branch {}", branch))?;
+        Ok(())
+    }
+
+    /// Pushes a branch to the remote.
+    pub fn push_branch(&self, worktree_path: &Path, branch_name: &str) -> Result<()> {
+        self.git
+            .run(worktree_path, &["push", "-u", "origin", branch_name])
+            .context(format!("failed to push branch {}", branch_name))?;
         Ok(())
     }
 
@@ -157,27 +442,16 @@ impl PRManager {
         head_branch: &str,
         base_branch: &str,
     ) -> Result<PullRequest> {
+        let body = self.closing_body(&truncate_body(body, "Description truncated."));
         let output = Command::new("gh")
             .current_dir(&self.repo_path)
-            .args([
-                "pr",
-                "create",
-                "--title",
-                title,
-                "--body",
-                body,
-                "--head",
-                head_branch,
-                "--base",
-                base_branch,
-            ])
+            .args(self.create_pr_args(title, &body, head_branch, base_branch))
             .output()?;
 
         if !output.status.success() {
-            return Err(Error::Git(format!(
-                "failed to create PR: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
+            return Err(
+                Error::gh_command(&["pr", "create"], &output).context("failed to create PR")
+            );
         }
 
         // Parse PR URL from output
@@ -186,7 +460,7 @@ impl PRManager {
         // Extract PR number from URL
         let number = url
             .split('/')
-            .last()
+            .next_back()
             .and_then(|s| s.parse().ok())
             .unwrap_or(0);
 
@@ -196,54 +470,422 @@ impl PRManager {
             title: title.to_string(),
             base_branch: base_branch.to_string(),
             head_branch: head_branch.to_string(),
+            is_draft: self.draft,
         })
     }
 
-    /// Checks for merge conflicts between the head and base branches.
-    pub fn check_conflicts(&self, head_branch: &str, base_branch: &str) -> Result<MergeStatus> {
-        // Fetch latest
-        let _ = Command::new("git")
+    /// Appends a closing keyword for each linked issue to `body`.
+    fn closing_body(&self, body: &str) -> String {
+        let mut body = body.to_string();
+        if !self.closes.is_empty() {
+            body.push('\n');
+            for issue in &self.closes {
+                body.push_str(&format!("\nCloses {}", issue));
+            }
+            body.push('\n');
+        }
+        body
+    }
+
+    /// Builds the `gh pr create` arguments.
+    fn create_pr_args<'a>(
+        &'a self,
+        title: &'a str,
+        body: &'a str,
+        head_branch: &'a str,
+        base_branch: &'a str,
+    ) -> Vec<&'a str> {
+        let mut args = vec![
+            "pr",
+            "create",
+            "--title",
+            title,
+            "--body",
+            body,
+            "--head",
+            head_branch,
+            "--base",
+            base_branch,
+        ];
+        if self.draft {
+            args.push("--draft");
+        }
+        for label in &self.labels {
+            args.extend(["--label", label.as_str()]);
+        }
+        args
+    }
+
+    /// Marks a draft PR as ready for review.
+    pub fn mark_ready(&self, pr: &mut PullRequest) -> Result<()> {
+        let output = Command::new("gh")
             .current_dir(&self.repo_path)
-            .args(["fetch", "origin", base_branch])
+            .args(["pr", "ready", &pr.number.to_string()])
             .output()?;
 
-        // Try a dry-run merge
-        let output = Command::new("git")
+        if !output.status.success() {
+            return Err(Error::gh_command(&["pr", "ready"], &output)
+                .context(format!("failed to mark PR #{} ready", pr.number)));
+        }
+
+        pr.is_draft = false;
+        Ok(())
+    }
+
+    /// Marks a draft PR ready once every review domain has approved.
+    ///
+    /// Returns true if the PR was marked ready.
+    pub fn mark_ready_if_approved(
+        &self,
+        pr: &mut PullRequest,
+        verdicts: &[ReviewVerdict],
+    ) -> Result<bool> {
+        if !pr.is_draft || !all_reviews_approved(verdicts) {
+            return Ok(false);
+        }
+
+        self.mark_ready(pr)?;
+        tracing::info!(pr = pr.number, "all reviews approved, marked PR ready");
+        Ok(true)
+    }
+
+    /// Posts a review as a PR comment with a machine-readable artifact.
+    pub fn post_review(&self, pr: &PullRequest, review: &ReviewResult) -> Result<()> {
+        self.comment(pr, &format_review_comment(review))
+    }
+
+    /// Posts a comment on a PR, truncated to fit if it is too long.
+    pub fn comment(&self, pr: &PullRequest, body: &str) -> Result<()> {
+        let body = truncate_body(body, "Comment truncated.");
+        let output = Command::new("gh")
             .current_dir(&self.repo_path)
-            .args([
-                "merge-tree",
-                &format!("origin/{}", base_branch),
-                head_branch,
+            .args(["pr", "comment", &pr.number.to_string(), "--body", &body])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&["pr", "comment"], &output)
+                .context(format!("failed to comment on PR #{}", pr.number)));
+        }
+
+        Ok(())
+    }
+
+    /// Posts a review comment anchored to `line` of `path` at `commit`.
+    ///
+    /// Returns the ID of the new comment, which replies are threaded under.
+    pub fn post_line_comment(
+        &self,
+        pr: &PullRequest,
+        commit: &str,
+        path: &str,
+        line: u32,
+        body: &str,
+    ) -> Result<u64> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/pulls/{}/comments", pr.number);
+        let fields = [
+            format!("body={}", body),
+            format!("commit_id={}", commit),
+            format!("path={}", path),
+            "side=RIGHT".to_string(),
+        ];
+        let mut args = vec!["api", endpoint.as_str()];
+        for field in &fields {
+            args.extend(["-f", field.as_str()]);
+        }
+        let line = format!("line={}", line);
+        args.extend(["-F", line.as_str()]);
+
+        self.create_comment(&args).context(format!(
+            "failed to comment on {} in PR #{}",
+            path, pr.number
+        ))
+    }
+
+    /// Replies to a review comment, keeping the reply in its thread.
+    ///
+    /// Returns the ID of the reply.
+    pub fn reply_to_comment(&self, pr: &PullRequest, comment_id: u64, body: &str) -> Result<u64> {
+        let endpoint = format!(
+            "repos/{{owner}}/{{repo}}/pulls/{}/comments/{}/replies",
+            pr.number, comment_id
+        );
+        let body = format!("body={}", body);
+
+        self.create_comment(&["api", &endpoint, "-f", &body])
+            .context(format!("failed to reply to comment {}", comment_id))
+    }
+
+    /// Marks the review thread started by comment `comment_id` resolved.
+    ///
+    /// Returns false if the comment does not start a thread on the PR. Only
+    /// the PR's first 100 threads are searched.
+    pub fn resolve_thread(&self, pr: &PullRequest, comment_id: u64) -> Result<bool> {
+        let threads = self.review_threads(pr)?;
+        let Some(thread) = threads
+            .iter()
+            .find(|t| !t.is_resolved && t.comment_id == comment_id)
+        else {
+            return Ok(false);
+        };
+
+        let id = format!("id={}", thread.id);
+        let mutation = format!("query={}", RESOLVE_THREAD_MUTATION);
+        self.graphql(&["-f", &id, "-f", &mutation])
+            .context(format!(
+                "failed to resolve thread of comment {}",
+                comment_id
+            ))?;
+        Ok(true)
+    }
+
+    /// Lists the PR's review threads. Only the first 100 are returned.
+    pub fn review_threads(&self, pr: &PullRequest) -> Result<Vec<ReviewThread>> {
+        let number = format!("number={}", pr.number);
+        let query = format!("query={}", REVIEW_THREADS_QUERY);
+        let response = self
+            .graphql(&[
+                "-F",
+                "owner={owner}",
+                "-F",
+                "repo={repo}",
+                "-F",
+                &number,
+                "-f",
+                &query,
             ])
+            .context(format!(
+                "failed to list review threads of PR #{}",
+                pr.number
+            ))?;
+        Ok(parse_review_threads(&response))
+    }
+
+    /// Runs a `gh api graphql` call with `fields`, returning the response.
+    fn graphql(&self, fields: &[&str]) -> Result<serde_json::Value> {
+        let mut args = vec!["api", "graphql"];
+        args.extend(fields);
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(&args)
             .output()?;
 
-        let merge_output = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+
+        serde_json::from_slice(&output.stdout).map_err(|e| Error::json("gh output", e))
+    }
 
-        // Check for conflict markers
-        if merge_output.contains("<<<<<<<") || merge_output.contains(">>>>>>>") {
-            let conflicts = self.parse_conflicts(&merge_output);
-            return Ok(MergeStatus::Conflicts(conflicts));
+    /// Runs a `gh api` call that creates a comment, returning its ID.
+    fn create_comment(&self, args: &[&str]) -> Result<u64> {
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(args, &output));
         }
 
-        // Check if already up to date
-        let merge_base = Command::new("git")
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).map_err(|e| Error::json("gh output", e))?;
+        json["id"]
+            .as_u64()
+            .ok_or_else(|| Error::GitHub("created comment has no id".to_string()))
+    }
+
+    /// Replaces the description of an existing PR, truncated to fit if it
+    /// is too long.
+    pub fn edit_body(&self, pr: &PullRequest, body: &str) -> Result<()> {
+        let body = truncate_body(body, "Description truncated.");
+        let output = Command::new("gh")
             .current_dir(&self.repo_path)
-            .args([
-                "merge-base",
-                head_branch,
-                &format!("origin/{}", base_branch),
-            ])
+            .args(["pr", "edit", &pr.number.to_string(), "--body", &body])
             .output()?;
 
-        let head_rev = Command::new("git")
+        if !output.status.success() {
+            return Err(Error::gh_command(&["pr", "edit"], &output)
+                .context(format!("failed to edit PR #{}", pr.number)));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the description of a PR.
+    pub fn fetch_body(&self, pr: &PullRequest) -> Result<String> {
+        let output = Command::new("gh")
             .current_dir(&self.repo_path)
-            .args(["rev-parse", head_branch])
+            .args(["pr", "view", &pr.number.to_string(), "--json", "body"])
             .output()?;
 
-        let base_output = String::from_utf8_lossy(&merge_base.stdout)
-            .trim()
-            .to_string();
-        let head_output = String::from_utf8_lossy(&head_rev.stdout).trim().to_string();
+        if !output.status.success() {
+            return Err(Error::gh_command(&["pr", "view"], &output)
+                .context(format!("failed to read PR #{}", pr.number)));
+        }
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).map_err(|e| Error::json("gh output", e))?;
+        Ok(json["body"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// Writes `review` to the `domain` section of the PR description,
+    /// replacing the section an earlier review wrote instead of adding a
+    /// second one.
+    ///
+    /// Edits made by this process are serialized. The description is read
+    /// back after each edit, and the edit is retried if another writer
+    /// replaced it in between and dropped the section.
+    pub fn append_review_to_pr(
+        &self,
+        pr: &PullRequest,
+        domain: &str,
+        review: &ReviewResult,
+    ) -> Result<()> {
+        let _guard = BODY_EDIT_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for attempt in 1..=BODY_EDIT_ATTEMPTS {
+            let body = upsert_review_section(&self.fetch_body(pr)?, domain, review);
+            self.edit_body(pr, &body)?;
+            let saved = self.fetch_body(pr)?.replace("\r\n", "\n");
+            if review_section(&saved, domain) == review_section(&body, domain) {
+                return Ok(());
+            }
+            tracing::debug!(
+                pr = pr.number,
+                domain,
+                attempt,
+                "PR description was replaced"
+            );
+        }
+
+        Err(Error::GitHub(format!(
+            "the description of PR #{} kept changing; {} review not saved",
+            pr.number, domain
+        )))
+    }
+
+    /// Uploads `path` to a secret gist, returning its URL.
+    pub fn create_gist(&self, path: &Path, description: &str) -> Result<String> {
+        let path = path.to_string_lossy();
+        let args = ["gist", "create", path.as_ref(), "--desc", description];
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output)
+                .context(format!("failed to upload {} to a gist", path)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Comments on a PR that its run was cancelled and can be resumed.
+    pub fn mark_cancelled(&self, pr: &PullRequest, checkpoint: &Checkpoint) -> Result<()> {
+        let mut body = format!(
+            "Run cancelled during {} ({}). Partial work has been pushed to this branch.\n\n\
+             Resume with checkpoint `{}`.",
+            checkpoint.phase, checkpoint.reason, checkpoint.run_id
+        );
+        if !checkpoint.completed.is_empty() {
+            body.push_str(&format!("\n\nCompleted: {}", checkpoint.completed.join(", ")));
+        }
+
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(["pr", "comment", &pr.number.to_string(), "--body", &body])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&["pr", "comment"], &output)
+                .context(format!("failed to comment on PR #{}", pr.number)));
+        }
+
+        Ok(())
+    }
+
+    /// Comments on a PR that its run was aborted, closing it if `close` is
+    /// set and deleting its branch if `delete_branch` is also set.
+    ///
+    /// `pr` is a PR number, URL, or branch, as accepted by `gh`.
+    pub fn mark_abandoned(&self, pr: &str, close: bool, delete_branch: bool) -> Result<()> {
+        let comment = "Run aborted; this PR is abandoned.";
+        let mut args = if close {
+            vec!["pr", "close", pr, "--comment", comment]
+        } else {
+            vec!["pr", "comment", pr, "--body", comment]
+        };
+        if close && delete_branch {
+            args.push("--delete-branch");
+        }
+
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(&args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(
+                Error::gh_command(&args, &output).context(format!("failed to abandon PR {}", pr))
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the most recent review posted as a comment on a PR.
+    pub fn fetch_review(&self, pr: &PullRequest) -> Result<Option<ReviewResult>> {
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(["pr", "view", &pr.number.to_string(), "--json", "comments"])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&["pr", "view"], &output)
+                .context(format!("failed to read comments on PR #{}", pr.number)));
+        }
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).map_err(|e| Error::json("gh output", e))?;
+        let bodies = json["comments"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c["body"].as_str());
+
+        Ok(latest_review(bodies))
+    }
+
+    /// Checks for merge conflicts between the head and base branches.
+    pub fn check_conflicts(&self, head_branch: &str, base_branch: &str) -> Result<MergeStatus> {
+        let repo = self.repo_path.as_path();
+        let remote_base = format!("origin/{}", base_branch);
+
+        // Fetch latest
+        let _ = self.git.output(repo, &["fetch", "origin", base_branch])?;
+
+        // Try a dry-run merge
+        let merge_output = self
+            .git
+            .output(repo, &["merge-tree", &remote_base, head_branch])?
+            .stdout;
+
+        // Check for conflict markers
+        if merge_output.contains("<<<<<<<") || merge_output.contains(">>>>>>>") {
+            let conflicts = self.parse_conflicts(&merge_output);
+            return Ok(MergeStatus::Conflicts(conflicts));
+        }
+
+        // Check if already up to date
+        let merge_base = self
+            .git
+            .output(repo, &["merge-base", head_branch, &remote_base])?;
+        let head_rev = self.git.output(repo, &["rev-parse", head_branch])?;
+
+        let base_output = merge_base.stdout.trim();
+        let head_output = head_rev.stdout.trim();
 
         if base_output == head_output {
             return Ok(MergeStatus::UpToDate);
@@ -292,17 +934,14 @@ impl PRManager {
     }
 
     /// Attempts to auto-resolve simple conflicts.
-    pub fn auto_resolve_conflicts(&self, worktree_path: &PathBuf) -> Result<bool> {
+    pub fn auto_resolve_conflicts(&self, worktree_path: &Path) -> Result<bool> {
         // This is a simplified implementation
         // In practice, this would use more sophisticated conflict resolution
 
-        let output = Command::new("git")
-            .current_dir(worktree_path)
-            .args(["diff", "--name-only", "--diff-filter=U"])
-            .output()?;
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let conflicted_files: Vec<&str> = output_str.lines().filter(|s| !s.is_empty()).collect();
+        let output = self
+            .git
+            .run(worktree_path, &["diff", "--name-only", "--diff-filter=U"])?;
+        let conflicted_files: Vec<&str> = output.lines().filter(|s| !s.is_empty()).collect();
 
         if conflicted_files.is_empty() {
             return Ok(true); // No conflicts
@@ -311,21 +950,14 @@ impl PRManager {
         // For now, we only handle simple cases where we can use "theirs"
         // In a full implementation, this would be more sophisticated
         for file in conflicted_files {
-            let checkout = Command::new("git")
-                .current_dir(worktree_path)
-                .args(["checkout", "--theirs", file])
-                .output()?;
-
-            if !checkout.status.success() {
+            let checkout = self
+                .git
+                .output(worktree_path, &["checkout", "--theirs", file])?;
+            if !checkout.success() {
                 return Ok(false); // Cannot auto-resolve
             }
 
-            let add = Command::new("git")
-                .current_dir(worktree_path)
-                .args(["add", file])
-                .output()?;
-
-            if !add.status.success() {
+            if !self.git.output(worktree_path, &["add", file])?.success() {
                 return Ok(false);
             }
         }
@@ -367,15 +999,221 @@ impl PRManager {
         }
 
         body.push_str("---\n");
-        body.push_str("*Created by infinite-improbability-drive*\n");
+        body.push_str(&format!("{}\n", CREATED_BY_FOOTER));
 
         body
     }
 }
 
+/// Cuts `body` down to [`MAX_BODY_BYTES`], ending at a line break where
+/// possible so tables and lists stay intact, and appends `note` in italics.
+/// Bodies that already fit are returned unchanged.
+pub fn truncate_body(body: &str, note: &str) -> String {
+    if body.len() <= MAX_BODY_BYTES {
+        return body.to_string();
+    }
+    let mut end = MAX_BODY_BYTES - note.len() - 8;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = &body[..end];
+    let cut = cut.rfind('\n').map_or(cut, |i| &cut[..i]);
+    format!("{}\n\n_{}_\n", cut, note)
+}
+
+/// Returns the first sentence of a prompt, shortened for use as a PR title.
+pub(crate) fn pr_title(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or_default().trim();
+    let line = first_sentence(line);
+    if line.chars().count() > 72 {
+        format!("{}...", line.chars().take(69).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Returns `text` up to the end of its first sentence, without a trailing
+/// period. A sentence ends at `.`, `!` or `?` followed by a space and a
+/// capital letter, so abbreviations such as "e.g. tokio" do not end it.
+fn first_sentence(text: &str) -> &str {
+    let end = text.char_indices().find_map(|(i, c)| {
+        let rest = text[i + c.len_utf8()..].strip_prefix(' ')?;
+        let ends = matches!(c, '.' | '!' | '?') && rest.starts_with(char::is_uppercase);
+        ends.then(|| if c == '.' { i } else { i + 1 })
+    });
+    end.map_or(text, |end| &text[..end]).trim_end_matches('.')
+}
+
+/// Returns true if there is at least one verdict and all are approvals.
+pub fn all_reviews_approved(verdicts: &[ReviewVerdict]) -> bool {
+    !verdicts.is_empty() && verdicts.iter().all(|v| *v == ReviewVerdict::Approved)
+}
+
+/// Formats a review as a PR comment.
+///
+/// The comment starts with a `REVIEW - ...` headline for humans and ends
+/// with a hidden HTML comment holding the review as JSON.
+pub fn format_review_comment(review: &ReviewResult) -> String {
+    let headline = match review.verdict {
+        ReviewVerdict::Approved => APPROVED_HEADLINE,
+        _ => NEEDS_CHANGES_HEADLINE,
+    };
+
+    let mut body = format!("## {}\n\n", headline);
+    if !review.summary.is_empty() {
+        body.push_str(&review.summary);
+        body.push_str("\n\n");
+    }
+    push_suggestions(&mut body, &review.suggestions);
+
+    let artifact = serde_json::json!({
+        "verdict": review.verdict,
+        "summary": review.summary,
+        "findings": review.suggestions,
+    });
+    // "--" may not appear inside an HTML comment
+    let artifact = artifact.to_string().replace("--", "-\\u002d");
+    body.push_str(&format!("\n{}\n{}\n-->\n", REVIEW_ARTIFACT_MARKER, artifact));
+    body
+}
+
+/// Returns `body` with its `domain` review section set to `review`. An
+/// existing section is replaced in place; otherwise the section is added
+/// at the end, so writing the same review twice changes nothing.
+pub fn upsert_review_section(body: &str, domain: &str, review: &ReviewResult) -> String {
+    let verdict = match review.verdict {
+        ReviewVerdict::Approved => "approved",
+        _ => "needs changes",
+    };
+    let mut section = format!(
+        "{}{} -->\n### {} review: {}\n\n",
+        REVIEW_SECTION_START, domain, domain, verdict
+    );
+    if !review.summary.is_empty() {
+        section.push_str(&review.summary);
+        section.push_str("\n\n");
+    }
+    push_suggestions(&mut section, &review.suggestions);
+    section.push_str(&format!("{}{} -->", REVIEW_SECTION_END, domain));
+
+    match section_range(body, domain) {
+        Some((start, end)) => format!("{}{}{}", &body[..start], section, &body[end..]),
+        None if body.trim().is_empty() => format!("{}\n", section),
+        None => format!("{}\n\n{}\n", body.trim_end(), section),
+    }
+}
+
+/// Returns the `domain` review section of a PR description, markers
+/// included.
+pub fn review_section<'a>(body: &'a str, domain: &str) -> Option<&'a str> {
+    section_range(body, domain).map(|(start, end)| &body[start..end])
+}
+
+/// Returns the byte range of the `domain` review section of `body`.
+fn section_range(body: &str, domain: &str) -> Option<(usize, usize)> {
+    let start_marker = format!("{}{} -->", REVIEW_SECTION_START, domain);
+    let end_marker = format!("{}{} -->", REVIEW_SECTION_END, domain);
+    let start = body.find(&start_marker)?;
+    let end = start + body[start..].find(&end_marker)? + end_marker.len();
+    Some((start, end))
+}
+
+/// Lists `suggestions` as Markdown bullets.
+fn push_suggestions(body: &mut String, suggestions: &[ReviewSuggestion]) {
+    for suggestion in suggestions {
+        body.push_str(&format!("- `{}`", suggestion.file));
+        if let Some(line) = suggestion.line {
+            body.push_str(&format!(" (line {})", line));
+        }
+        body.push_str(&format!(": {} {}\n", suggestion.issue, suggestion.suggestion));
+    }
+}
+
+/// Parses the response to the review threads query. Threads without a
+/// first comment are skipped.
+pub fn parse_review_threads(response: &serde_json::Value) -> Vec<ReviewThread> {
+    response["data"]["repository"]["pullRequest"]["reviewThreads"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|thread| {
+            let comment = &thread["comments"]["nodes"][0];
+            let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+            Some(ReviewThread {
+                id: text(&thread["id"]),
+                comment_id: comment["databaseId"].as_u64()?,
+                author: text(&comment["author"]["login"]),
+                path: text(&thread["path"]),
+                line: thread["line"].as_u64().map(|line| line as u32),
+                body: text(&comment["body"]),
+                is_resolved: thread["isResolved"].as_bool().unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// Returns the node ID of the unresolved review thread whose first comment
+/// is `comment_id`, from the response to the review threads query.
+pub fn parse_review_thread_id(response: &serde_json::Value, comment_id: u64) -> Option<String> {
+    parse_review_threads(response)
+        .into_iter()
+        .find(|thread| !thread.is_resolved && thread.comment_id == comment_id)
+        .map(|thread| thread.id)
+}
+
+/// Parses a review from a PR comment.
+///
+/// Tries, in order: the hidden review artifact, a fenced `json` block with
+/// a `verdict`, and finally a `REVIEW - APPROVED` / `REVIEW - NEEDS CHANGES`
+/// headline (which yields a review without findings). Returns `None` if the
+/// comment is not a review.
+pub fn parse_review_comment(body: &str) -> Option<ReviewResult> {
+    if let Some(start) = body.rfind(REVIEW_ARTIFACT_MARKER) {
+        let rest = &body[start + REVIEW_ARTIFACT_MARKER.len()..];
+        let json = rest.find("-->").map_or(rest, |end| &rest[..end]);
+        match serde_json::from_str::<serde_json::Value>(json.trim()) {
+            Ok(value) => {
+                if let Some(review) = review_from_json(&value) {
+                    return Some(review);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "malformed review artifact"),
+        }
+    }
+
+    for block in body.split("```json").skip(1) {
+        let json = block.split("```").next().unwrap_or_default();
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(json.trim()) {
+            if let Some(review) = review_from_json(&value) {
+                return Some(review);
+            }
+        }
+    }
+
+    let verdict = if body.contains(NEEDS_CHANGES_HEADLINE) {
+        ReviewVerdict::NeedsChanges
+    } else if body.contains(APPROVED_HEADLINE) {
+        ReviewVerdict::Approved
+    } else {
+        return None;
+    };
+
+    Some(ReviewResult {
+        verdict,
+        suggestions: Vec::new(),
+        summary: body.trim().to_string(),
+    })
+}
+
+/// Returns the last review among PR comment bodies, oldest first.
+pub fn latest_review<'a>(bodies: impl IntoIterator<Item = &'a str>) -> Option<ReviewResult> {
+    bodies.into_iter().filter_map(parse_review_comment).last()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::{GitOutput, MockGit};
     use tempfile::TempDir;
 
     fn create_test_repo() -> TempDir {
@@ -430,6 +1268,236 @@ mod tests {
         assert_eq!(manager.conflict_strategy, ConflictStrategy::Fail);
     }
 
+    #[test]
+    fn create_pr_args_include_draft_flag() {
+        let manager = PRManager::new(PathBuf::from("/tmp/test"));
+        let args = manager.create_pr_args("Title", "Body", "feature", "main");
+        assert!(!args.contains(&"--draft"));
+
+        let manager = manager.with_draft(true);
+        let args = manager.create_pr_args("Title", "Body", "feature", "main");
+        assert!(args.contains(&"--draft"));
+    }
+
+    #[test]
+    fn create_pr_args_include_labels() {
+        let manager = PRManager::new(PathBuf::from("/tmp/test")).with_labels(["incomplete"]);
+        let args = manager.create_pr_args("Title", "Body", "feature", "main");
+        assert!(args.ends_with(&["--label", "incomplete"]));
+    }
+
+    #[test]
+    fn closing_body_links_issues() {
+        let manager = PRManager::new(PathBuf::from("/tmp/test"));
+        assert_eq!(manager.closing_body("Body"), "Body");
+
+        let manager = manager.with_closing_issues(["owner/repo#12"]);
+        assert_eq!(
+            manager.closing_body("Body"),
+            "Body\n\nCloses owner/repo#12\n"
+        );
+    }
+
+    fn review(verdict: ReviewVerdict) -> ReviewResult {
+        ReviewResult {
+            verdict,
+            suggestions: vec![crate::team::ReviewSuggestion {
+                file: "src/auth.rs".to_string(),
+                line: Some(7),
+                issue: "Token is logged -- remove it".to_string(),
+                suggestion: "Drop the log line".to_string(),
+                severity: crate::team::ReviewSeverity::Critical,
+            }],
+            summary: "One problem".to_string(),
+        }
+    }
+
+    #[test]
+    fn review_sections_are_updated_in_place() {
+        let body = "Adds login.";
+        let needs_changes = review(ReviewVerdict::NeedsChanges);
+        let approved = review(ReviewVerdict::Approved);
+        let once = upsert_review_section(body, "security", &needs_changes);
+        assert!(
+            once.starts_with("Adds login.\n\n<!-- improbability-drive:review-section security -->")
+        );
+        assert!(once.contains("### security review: needs changes"));
+        assert_eq!(
+            upsert_review_section(&once, "security", &needs_changes),
+            once
+        );
+
+        let both = upsert_review_section(&once, "style", &approved);
+        let updated = upsert_review_section(&both, "security", &approved);
+        assert_eq!(updated.matches("review-section security -->").count(), 2);
+        assert!(review_section(&updated, "security")
+            .unwrap()
+            .contains("security review: approved"));
+        let position = |domain: &str| updated.find(&format!("review-section {}", domain));
+        assert!(position("security") < position("style"));
+        assert_eq!(review_section(body, "security"), None);
+    }
+
+    #[test]
+    fn review_comment_round_trips() {
+        let comment = format_review_comment(&review(ReviewVerdict::NeedsChanges));
+
+        assert!(comment.starts_with("## REVIEW - NEEDS CHANGES"));
+        assert!(comment.contains(REVIEW_ARTIFACT_MARKER));
+
+        let parsed = parse_review_comment(&comment).unwrap();
+        assert_eq!(parsed.verdict, ReviewVerdict::NeedsChanges);
+        assert_eq!(parsed.summary, "One problem");
+        assert_eq!(parsed.suggestions.len(), 1);
+        assert_eq!(parsed.suggestions[0].line, Some(7));
+        assert_eq!(parsed.suggestions[0].issue, "Token is logged -- remove it");
+    }
+
+    #[test]
+    fn parse_review_comment_reads_fenced_json() {
+        let comment = "Looks good.\n\n```json\n{\"verdict\": \"approved\", \"findings\": []}\n```\n";
+
+        let parsed = parse_review_comment(comment).unwrap();
+        assert_eq!(parsed.verdict, ReviewVerdict::Approved);
+    }
+
+    #[test]
+    fn parse_review_comment_falls_back_to_headline() {
+        let parsed = parse_review_comment("REVIEW - NEEDS CHANGES\nFix the tests").unwrap();
+        assert_eq!(parsed.verdict, ReviewVerdict::NeedsChanges);
+        assert!(parsed.suggestions.is_empty());
+
+        // A malformed artifact still falls back to the headline
+        let parsed =
+            parse_review_comment("REVIEW - APPROVED\n<!-- improbability-drive:review {oops -->")
+                .unwrap();
+        assert_eq!(parsed.verdict, ReviewVerdict::Approved);
+
+        assert!(parse_review_comment("Thanks for the PR!").is_none());
+    }
+
+    #[test]
+    fn parse_review_thread_id_finds_open_thread_of_comment() {
+        let response = serde_json::json!({
+            "data": {"repository": {"pullRequest": {"reviewThreads": {"nodes": [
+                {"id": "PRRT_a", "isResolved": true, "comments": {"nodes": [{"databaseId": 7}]}},
+                {"id": "PRRT_b", "isResolved": false, "comments": {"nodes": [{"databaseId": 7}]}},
+                {"id": "PRRT_c", "isResolved": false, "comments": {"nodes": [{"databaseId": 8}]}}
+            ]}}}}
+        });
+
+        assert_eq!(
+            parse_review_thread_id(&response, 7).as_deref(),
+            Some("PRRT_b")
+        );
+        assert_eq!(parse_review_thread_id(&response, 9), None);
+        assert_eq!(parse_review_thread_id(&serde_json::json!({}), 7), None);
+    }
+
+    #[test]
+    fn parse_review_threads_reads_location_and_first_comment() {
+        let response = serde_json::json!({
+            "data": {"repository": {"pullRequest": {"reviewThreads": {"nodes": [
+                {"id": "PRRT_a", "isResolved": false, "path": "src/lib.rs", "line": 12,
+                 "comments": {"nodes": [{"databaseId": 7, "body": "Handle the error",
+                                         "author": {"login": "octocat"}}]}},
+                {"id": "PRRT_b", "isResolved": false, "path": "src/old.rs", "line": null,
+                 "comments": {"nodes": []}}
+            ]}}}}
+        });
+
+        assert_eq!(
+            parse_review_threads(&response),
+            vec![ReviewThread {
+                id: "PRRT_a".to_string(),
+                comment_id: 7,
+                author: "octocat".to_string(),
+                path: "src/lib.rs".to_string(),
+                line: Some(12),
+                body: "Handle the error".to_string(),
+                is_resolved: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn latest_review_uses_last_review_comment() {
+        let approved = format_review_comment(&review(ReviewVerdict::Approved));
+        let bodies = [
+            "REVIEW - NEEDS CHANGES",
+            approved.as_str(),
+            "Thanks, merging soon",
+        ];
+
+        let latest = latest_review(bodies).unwrap();
+        assert_eq!(latest.verdict, ReviewVerdict::Approved);
+        assert!(latest_review(["hello"]).is_none());
+    }
+
+    #[test]
+    fn all_reviews_approved_requires_every_domain() {
+        assert!(!all_reviews_approved(&[]));
+        assert!(all_reviews_approved(&[
+            ReviewVerdict::Approved,
+            ReviewVerdict::Approved
+        ]));
+        assert!(!all_reviews_approved(&[
+            ReviewVerdict::Approved,
+            ReviewVerdict::NeedsChanges
+        ]));
+        assert!(!all_reviews_approved(&[ReviewVerdict::Failed]));
+    }
+
+    #[test]
+    fn truncate_body_cuts_long_bodies_at_a_line() {
+        assert_eq!(truncate_body("short", "Truncated."), "short");
+
+        let row = "| Bash | 1 | `cargo test` |\n";
+        let body = row.repeat(MAX_BODY_BYTES / row.len() + 10);
+        let cut = truncate_body(&body, "Truncated.");
+        assert!(cut.len() <= MAX_BODY_BYTES);
+        assert!(cut.ends_with("|\n\n_Truncated._\n"));
+    }
+
+    #[test]
+    fn pr_title_is_the_first_sentence() {
+        assert_eq!(
+            pr_title("Add a login page. It should use OAuth.\nMore details"),
+            "Add a login page"
+        );
+        assert_eq!(pr_title("Why does CI fail? Fix it."), "Why does CI fail?");
+        assert_eq!(
+            pr_title("Use a runtime, e.g. tokio. Keep it small."),
+            "Use a runtime, e.g. tokio"
+        );
+        assert_eq!(pr_title("Fix the build."), "Fix the build");
+    }
+
+    #[test]
+    fn mark_ready_if_approved_skips_non_draft_and_pending() {
+        let manager = PRManager::new(PathBuf::from("/tmp/test"));
+        let mut pr = PullRequest {
+            number: 1,
+            url: "https://github.com/o/r/pull/1".to_string(),
+            title: "Title".to_string(),
+            base_branch: "main".to_string(),
+            head_branch: "feature".to_string(),
+            is_draft: false,
+        };
+
+        // Not a draft: nothing to do (and gh is never invoked)
+        assert!(!manager
+            .mark_ready_if_approved(&mut pr, &[ReviewVerdict::Approved])
+            .unwrap());
+
+        // Draft with outstanding changes stays a draft
+        pr.is_draft = true;
+        assert!(!manager
+            .mark_ready_if_approved(&mut pr, &[ReviewVerdict::NeedsChanges])
+            .unwrap());
+        assert!(pr.is_draft);
+    }
+
     #[test]
     fn pr_manager_commits_changes() {
         let repo = create_test_repo();
@@ -438,7 +1506,7 @@ mod tests {
         // Add a new file
         std::fs::write(repo.path().join("new_file.txt"), "content").unwrap();
 
-        let result = manager.commit_changes(&repo.path().to_path_buf(), "Add new file");
+        let result = manager.commit_changes(repo.path(), "Add new file");
 
         assert!(result.is_ok());
         let hash = result.unwrap();
@@ -451,12 +1519,102 @@ mod tests {
         let repo = create_test_repo();
         let manager = PRManager::new(repo.path().to_path_buf());
 
-        let result = manager.commit_changes(&repo.path().to_path_buf(), "No changes");
+        let result = manager.commit_changes(repo.path(), "No changes");
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
     }
 
+    #[test]
+    fn prepare_for_pr_squashes_branch_history() {
+        use crate::commit::GitHistoryPolicy;
+
+        let repo = create_test_repo();
+        let path = repo.path().to_path_buf();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .current_dir(&path)
+                .args(args)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        git(&["branch", "base"]);
+        let manager = PRManager::new(path.clone())
+            .with_commit_messages(CommitMessages::new().with_history(GitHistoryPolicy::SquashAll));
+        for (round, file) in ["a.rs", "b.rs"].iter().enumerate() {
+            std::fs::write(path.join(file), "fn a() {}\n").unwrap();
+            let context = CommitContext::new("Add parser").with_iteration(round as u32);
+            manager.commit_with_context(&path, &context).unwrap();
+        }
+
+        let context = CommitContext::new("Add parser").with_scope("core");
+        let head = manager.prepare_for_pr(&path, "base", &context).unwrap();
+
+        assert!(head.is_some());
+        assert_eq!(git(&["rev-list", "--count", "base..HEAD"]), "1");
+        assert_eq!(git(&["log", "-1", "--format=%s"]), "feat(core): Add parser");
+        assert!(git(&["ls-files"]).contains("b.rs"));
+    }
+
+    #[test]
+    fn commit_leaves_out_ignored_and_denied_files() {
+        let repo = create_test_repo();
+        let path = repo.path().to_path_buf();
+        std::fs::write(path.join(".gitignore"), "build/\n").unwrap();
+        for file in [
+            "build/out.bin",
+            "node_modules/x/index.js",
+            "dist/app.js",
+            "src/lib.rs",
+        ] {
+            let file = path.join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, "content").unwrap();
+        }
+        // Force-added despite .gitignore
+        Command::new("git")
+            .current_dir(&path)
+            .args(["add", "-f", "build/out.bin"])
+            .output()
+            .unwrap();
+        let manager = PRManager::new(path.clone()).with_commit_deny(vec!["dist/**".to_string()]);
+
+        let outcome = manager.commit_filtered(&path, "Add lib").unwrap();
+
+        assert!(outcome.hash.is_some());
+        assert_eq!(
+            outcome.skipped,
+            vec![
+                PathBuf::from("build/out.bin"),
+                PathBuf::from("dist/app.js"),
+                PathBuf::from("node_modules/x/index.js"),
+            ]
+        );
+        let committed = Command::new("git")
+            .current_dir(&path)
+            .args(["show", "--name-only", "--format="])
+            .output()
+            .unwrap();
+        let committed = String::from_utf8_lossy(&committed.stdout);
+        assert_eq!(committed.trim(), ".gitignore\nsrc/lib.rs");
+    }
+
+    #[test]
+    fn commit_refuses_unexpected_binaries() {
+        let repo = create_test_repo();
+        let path = repo.path().to_path_buf();
+        std::fs::write(path.join("model.bin"), [0u8, 1, 2, 0]).unwrap();
+        std::fs::write(path.join("notes.txt"), "notes").unwrap();
+        let manager = PRManager::new(path.clone());
+
+        let outcome = manager.commit_filtered(&path, "Add notes").unwrap();
+
+        assert!(outcome.hash.is_some());
+        assert_eq!(outcome.violations.len(), 1);
+        assert_eq!(outcome.findings()[0].file.as_deref(), Some("model.bin"));
+    }
+
     #[test]
     fn conflict_file_simple_detection() {
         let simple = ConflictFile {
@@ -481,6 +1639,73 @@ mod tests {
         assert_ne!(MergeStatus::Clean, MergeStatus::UpToDate);
     }
 
+    #[test]
+    fn check_conflicts_reads_merge_tree_and_revisions() {
+        let manager = |head_rev: &str| {
+            let git = MockGit::new()
+                .on(&["fetch"], GitOutput::failed(128, "no network"))
+                .on(&["merge-tree"], GitOutput::ok("merged\n"))
+                .on(&["merge-base"], GitOutput::ok("abc123\n"))
+                .on(&["rev-parse"], GitOutput::ok(head_rev));
+            PRManager::new(PathBuf::from("/repo")).with_git(Arc::new(git))
+        };
+
+        assert_eq!(
+            manager("abc123\n")
+                .check_conflicts("feature", "main")
+                .unwrap(),
+            MergeStatus::UpToDate
+        );
+        assert_eq!(
+            manager("def456\n")
+                .check_conflicts("feature", "main")
+                .unwrap(),
+            MergeStatus::Clean
+        );
+
+        let conflicted = MockGit::new().on(&["fetch"], GitOutput::ok("")).on(
+            &["merge-tree"],
+            GitOutput::ok("diff --git a/src/lib.rs b/src/lib.rs\n<<<<<<< ours\n"),
+        );
+        let status = PRManager::new(PathBuf::from("/repo"))
+            .with_git(Arc::new(conflicted))
+            .check_conflicts("feature", "main")
+            .unwrap();
+        let MergeStatus::Conflicts(files) = status else {
+            panic!("expected conflicts, got {:?}", status);
+        };
+        assert_eq!(files[0].path, PathBuf::from("src/lib.rs"));
+        assert_eq!(files[0].conflict_count, 1);
+    }
+
+    #[test]
+    fn pushes_run_through_the_git_executor() {
+        let git = Arc::new(
+            MockGit::new()
+                .on(&["push", "-u"], GitOutput::failed(1, "rejected"))
+                .on(&["push"], GitOutput::ok("")),
+        );
+        let manager = PRManager::new(PathBuf::from("/repo")).with_git(git.clone());
+
+        manager
+            .push_head_to(Path::new("/repo"), "cruise/task-1")
+            .unwrap();
+        let err = manager
+            .push_branch(&PathBuf::from("/repo"), "cruise/task-2")
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("failed to push branch cruise/task-2"));
+        assert_eq!(
+            git.calls(),
+            [
+                vec!["push", "origin", "HEAD:refs/heads/cruise/task-1"],
+                vec!["push", "-u", "origin", "cruise/task-2"],
+            ]
+        );
+    }
+
     #[test]
     fn pr_body_generation() {
         let manager = PRManager::new(PathBuf::from("/tmp"));
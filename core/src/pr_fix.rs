@@ -0,0 +1,781 @@
+//! Fixing any pull request from its outstanding feedback.
+//!
+//! `fix-pr <pr>` works on the PR's own branch. It collects the PR's
+//! unresolved review threads and the failure annotations of its checks,
+//! has the primary LLM address them in a sandbox started from the PR's
+//! head, and pushes each fix round back to the PR branch. Every finding is
+//! checked by a [`FixVerifier`]: review threads are answered and resolved
+//! through [`ReviewThreads`], and findings the fix did not resolve go into
+//! the next round. With a [`CiWatcher`], the fixer then waits for the checks
+//! on its last push and runs further rounds on whatever CI reports failing.
+//! Nothing is pushed until the configured local verification commands pass.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::audit::PostedComment;
+use crate::branch::BranchNamer;
+use crate::checks::{CheckAnnotation, ChecksClient, CiFailure, CiStatus, CiWatcher};
+use crate::commit::CommitMessages;
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{PRManager, ReviewThread};
+use crate::pr_review::PrDetails;
+use crate::prompt::PromptTemplates;
+use crate::review_threads::{FixVerifier, ReviewThreads, ThreadResolution};
+use crate::runner::{run_in_sandbox, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::{Sandbox, SandboxManifest, WorktreeSandbox};
+use crate::team::{FixPromptBuilder, ReviewSeverity, ReviewSuggestion};
+use crate::verification::{VerificationConfig, VerificationFailure};
+
+/// Fix rounds run before giving up on the remaining feedback.
+pub const DEFAULT_MAX_FIX_ROUNDS: u32 = 3;
+
+/// Feedback on a PR that asks for changes.
+#[derive(Debug, Clone, Default)]
+pub struct PrFeedback {
+    /// Unresolved review threads.
+    pub threads: Vec<ReviewThread>,
+    /// Failure annotations of failed checks on the PR's head.
+    pub annotations: Vec<CheckAnnotation>,
+}
+
+impl PrFeedback {
+    /// Returns whether there is nothing to fix.
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty() && self.annotations.is_empty()
+    }
+
+    /// Returns the feedback as findings for a fix round.
+    pub fn suggestions(&self) -> Vec<ReviewSuggestion> {
+        let threads = self.threads.iter().map(|thread| ReviewSuggestion {
+            file: thread.path.clone(),
+            line: thread.line,
+            issue: thread.body.clone(),
+            suggestion: String::new(),
+            severity: ReviewSeverity::High,
+        });
+        threads
+            .chain(self.annotations.iter().map(CheckAnnotation::to_suggestion))
+            .collect()
+    }
+
+    /// Returns the review threads as comments fixes can be threaded under.
+    pub fn posted_comments(&self) -> Vec<PostedComment> {
+        self.threads
+            .iter()
+            .map(|thread| PostedComment {
+                id: Some(thread.comment_id),
+                file: thread.path.clone(),
+                line: thread.line,
+                issue: thread.body.clone(),
+                resolved_by: None,
+                failed_fixes: 0,
+                escalated: false,
+            })
+            .collect()
+    }
+}
+
+/// Outcome of fixing a PR.
+#[derive(Debug, Clone, Default)]
+pub struct PrFixOutcome {
+    /// Fix rounds run.
+    pub rounds: u32,
+    /// Commits pushed to the PR branch, in order.
+    pub commits: Vec<String>,
+    /// Findings a verified fix resolved.
+    pub resolved: usize,
+    /// Findings handed to a human after too many failed fixes.
+    pub escalated: usize,
+    /// Findings still open when the rounds ran out.
+    pub remaining: Vec<ReviewSuggestion>,
+    /// Fix rounds run for CI failures.
+    pub ci_rounds: u32,
+    /// Checks on the last push, if CI was watched.
+    pub ci: Option<CiStatus>,
+    /// Verification failure that stopped the fixer; that round's changes
+    /// were not pushed.
+    pub unverified: Option<VerificationFailure>,
+    /// The PR's review threads and what became of them.
+    pub comments: Vec<PostedComment>,
+}
+
+/// Fixes PRs from their review threads and failing checks.
+pub struct PrFixer {
+    /// Repository the PR belongs to.
+    repo_path: PathBuf,
+    primary: Arc<dyn LLMRunner>,
+    verifier: FixVerifier,
+    model: Option<String>,
+    sandbox_dir: Option<PathBuf>,
+    branches: BranchNamer,
+    templates: PromptTemplates,
+    manifest: SandboxManifest,
+    commit_messages: CommitMessages,
+    max_rounds: u32,
+    ci: Option<CiWatcher>,
+    verification: VerificationConfig,
+}
+
+impl PrFixer {
+    /// Creates a fixer for PRs of the repository at `repo_path`. `primary`
+    /// writes the fixes and `verifier` checks them.
+    pub fn new(
+        repo_path: impl Into<PathBuf>,
+        primary: Arc<dyn LLMRunner>,
+        verifier: FixVerifier,
+    ) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            primary,
+            verifier,
+            model: None,
+            sandbox_dir: None,
+            branches: BranchNamer::new(),
+            templates: PromptTemplates::builtin(),
+            manifest: SandboxManifest::default(),
+            commit_messages: CommitMessages::default(),
+            max_rounds: DEFAULT_MAX_FIX_ROUNDS,
+            ci: None,
+            verification: VerificationConfig::default(),
+        }
+    }
+
+    /// Sets the model passed to the primary runner.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sets the directory sandboxes are created in.
+    pub fn with_sandbox_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.sandbox_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the namer for sandbox branches.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    /// Sets the prompt templates.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Sets the sandbox manifest for fix rounds.
+    pub fn with_manifest(mut self, manifest: SandboxManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Sets how fix commits are described.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.commit_messages = messages;
+        self
+    }
+
+    /// Sets the number of fix rounds.
+    pub fn with_max_rounds(mut self, rounds: u32) -> Self {
+        self.max_rounds = rounds.max(1);
+        self
+    }
+
+    /// Waits for CI after pushing and fixes failing checks, up to the
+    /// watcher's `max_fix_rounds`.
+    pub fn with_ci(mut self, watcher: CiWatcher) -> Self {
+        self.ci = Some(watcher);
+        self
+    }
+
+    /// Sets the commands that must pass before anything is pushed.
+    pub fn with_verification(mut self, verification: VerificationConfig) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Collects the unresolved review threads of `pr` and the failure
+    /// annotations of its head commit's checks.
+    pub fn collect(&self, pr: &PrDetails) -> Result<PrFeedback> {
+        let threads = PRManager::new(self.repo_path.clone())
+            .review_threads(&pr.pull_request())?
+            .into_iter()
+            .filter(|thread| !thread.is_resolved)
+            .collect();
+        let annotations = ChecksClient::new(&self.repo_path).failure_annotations(&pr.head_sha)?;
+        Ok(PrFeedback {
+            threads,
+            annotations,
+        })
+    }
+
+    /// Fixes `feedback` on `pr` in a sandbox started from the PR's head,
+    /// pushing every fix round to the PR branch.
+    pub async fn fix(&self, pr: &PrDetails, feedback: &PrFeedback) -> Result<PrFixOutcome> {
+        SystemGit.run(&self.repo_path, &["fetch", "origin", &pr.head_branch])?;
+        let provider = WorktreeSandbox::new(self.repo_path.clone(), self.sandbox_dir.clone())
+            .with_branch_namer(self.branches.clone());
+        let mut sandbox = provider.create_from(self.manifest.clone(), "FETCH_HEAD")?;
+
+        let outcome = self.fix_in(sandbox.path(), pr, feedback).await;
+        if let Err(e) = sandbox.cleanup() {
+            tracing::warn!(error = %e, "failed to clean up fix sandbox");
+        }
+        outcome
+    }
+
+    /// Runs fix rounds in `worktree` until every finding is resolved or
+    /// escalated, or the rounds run out, then fixes CI failures on the
+    /// last push.
+    async fn fix_in(
+        &self,
+        worktree: &Path,
+        pr: &PrDetails,
+        feedback: &PrFeedback,
+    ) -> Result<PrFixOutcome> {
+        let manager = PRManager::new(self.repo_path.clone())
+            .with_commit_messages(self.commit_messages.clone())
+            .with_commit_deny(self.manifest.commit_deny.clone());
+        let pull_request = pr.pull_request();
+        let mut threads = ReviewThreads::new(feedback.posted_comments());
+        let mut pending = feedback.suggestions();
+        let mut outcome = PrFixOutcome::default();
+
+        while !pending.is_empty() && outcome.rounds < self.max_rounds {
+            outcome.rounds += 1;
+            self.run_primary(worktree, pr, self.fix_prompt(pr, &pending))
+                .await?;
+
+            let message = format!("Address feedback on PR #{}", pr.number);
+            let Some(commit) = self
+                .commit_verified(&manager, worktree, pr, &message, &mut outcome)
+                .await?
+            else {
+                break;
+            };
+            manager.push_head_to(worktree, &pr.head_branch)?;
+            outcome.commits.push(commit.clone());
+
+            let mut open = Vec::new();
+            for mut suggestion in pending {
+                let check = self
+                    .verifier
+                    .verify_commit(worktree, &suggestion, &commit)
+                    .await?;
+                let resolution =
+                    threads.resolve(&manager, &pull_request, &suggestion, &commit, &check)?;
+                match resolution {
+                    ThreadResolution::Resolved => outcome.resolved += 1,
+                    ThreadResolution::NoThread if check.resolved => outcome.resolved += 1,
+                    ThreadResolution::Escalated(_) => outcome.escalated += 1,
+                    ThreadResolution::Retry(_) | ThreadResolution::NoThread => {
+                        suggestion.suggestion =
+                            format!("A previous fix did not resolve this: {}", check.reason);
+                        open.push(suggestion);
+                    }
+                }
+            }
+            pending = open;
+        }
+
+        outcome.remaining = pending;
+        outcome.comments = threads.into_posted();
+
+        let last_push = outcome.commits.last().cloned();
+        if let (Some(ci), Some(commit), None) = (&self.ci, last_push, &outcome.unverified) {
+            self.fix_ci(ci, &manager, worktree, pr, commit, &mut outcome)
+                .await?;
+        }
+        Ok(outcome)
+    }
+
+    /// Waits for the checks on `commit` and, while they fail, runs fix
+    /// rounds on their failures and pushes the result.
+    async fn fix_ci(
+        &self,
+        ci: &CiWatcher,
+        manager: &PRManager,
+        worktree: &Path,
+        pr: &PrDetails,
+        mut commit: String,
+        outcome: &mut PrFixOutcome,
+    ) -> Result<()> {
+        loop {
+            let status = ci.wait(&commit).await?;
+            let failures = match &status {
+                CiStatus::Failed(failures) if outcome.ci_rounds < ci.config().max_fix_rounds => {
+                    failures
+                }
+                _ => {
+                    outcome.ci = Some(status);
+                    return Ok(());
+                }
+            };
+
+            outcome.ci_rounds += 1;
+            let pending: Vec<_> = failures.iter().flat_map(CiFailure::suggestions).collect();
+            self.run_primary(worktree, pr, self.fix_prompt(pr, &pending))
+                .await?;
+
+            let message = format!("Fix CI failures on PR #{}", pr.number);
+            let Some(next) = self
+                .commit_verified(manager, worktree, pr, &message, outcome)
+                .await?
+            else {
+                outcome.ci = Some(status);
+                return Ok(());
+            };
+            manager.push_head_to(worktree, &pr.head_branch)?;
+            outcome.commits.push(next.clone());
+            commit = next;
+        }
+    }
+
+    /// Commits the round's changes once they pass verification. Returns
+    /// `None`, committing nothing, if the round changed nothing or
+    /// verification still fails after the fix attempts.
+    async fn commit_verified(
+        &self,
+        manager: &PRManager,
+        worktree: &Path,
+        pr: &PrDetails,
+        message: &str,
+        outcome: &mut PrFixOutcome,
+    ) -> Result<Option<String>> {
+        if let Some(failure) = self.verify(worktree, pr).await? {
+            tracing::warn!(
+                pr = pr.number,
+                command = %failure.command,
+                "verification still failing; not pushing"
+            );
+            outcome.unverified = Some(failure);
+            return Ok(None);
+        }
+
+        let commit = manager.commit_changes(worktree, message)?;
+        if commit.is_none() {
+            tracing::warn!(pr = pr.number, "fix round changed nothing");
+        }
+        Ok(commit)
+    }
+
+    /// Runs the verification commands, asking the primary LLM to fix the
+    /// build while they fail. Returns the failure left once the fix
+    /// attempts run out.
+    async fn verify(&self, worktree: &Path, pr: &PrDetails) -> Result<Option<VerificationFailure>> {
+        let mut attempts = 0;
+        loop {
+            let Some(failure) = self.verification.run(worktree, &self.manifest).await else {
+                return Ok(None);
+            };
+            if attempts >= self.verification.max_fix_attempts {
+                return Ok(Some(failure));
+            }
+
+            attempts += 1;
+            tracing::info!(
+                pr = pr.number,
+                command = %failure.command,
+                attempt = attempts,
+                "verification failed; fixing the build"
+            );
+            let prompt = failure.prompt(&pr.prompt(), &self.templates);
+            self.run_primary(worktree, pr, prompt).await?;
+        }
+    }
+
+    /// Returns the prompt for fixing the open findings.
+    fn fix_prompt(&self, pr: &PrDetails, pending: &[ReviewSuggestion]) -> String {
+        FixPromptBuilder::new(pr.prompt())
+            .with_suggestions(pending.to_vec())
+            .build_with(&self.templates)
+    }
+
+    /// Runs the primary LLM on `prompt`.
+    async fn run_primary(&self, worktree: &Path, pr: &PrDetails, prompt: String) -> Result<()> {
+        let config = LLMSpawnConfig {
+            prompt,
+            working_dir: worktree.to_path_buf(),
+            manifest: self.manifest.clone(),
+            model: self.model.clone(),
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let run = run_in_sandbox(self.primary.as_ref(), config).await?;
+        if !run.result.success {
+            tracing::warn!(pr = pr.number, "PR fixer exited unsuccessfully");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{CheckRun, CheckSource, CiConfig};
+    use crate::runner::{LLMOutput, LLMResult};
+    use async_trait::async_trait;
+    use std::collections::VecDeque;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    /// Runner that writes a new file on every fix round.
+    struct FileWriter {
+        calls: AtomicU32,
+    }
+
+    /// Runner that answers every fix check the same way.
+    struct Verdict(&'static str);
+
+    fn result() -> Result<LLMResult> {
+        Ok(LLMResult {
+            exit_status: Command::new("true").status()?,
+            output_lines: 0,
+            success: true,
+            model: None,
+            session_id: None,
+        })
+    }
+
+    #[async_trait]
+    impl LLMRunner for FileWriter {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            let round = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            std::fs::write(
+                config.working_dir.join(format!("fix-{}.txt", round)),
+                "fixed",
+            )?;
+            result()
+        }
+
+        fn name(&self) -> &str {
+            "fixer"
+        }
+    }
+
+    #[async_trait]
+    impl LLMRunner for Verdict {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            let _ = output_tx.send(LLMOutput::Stdout(self.0.to_string())).await;
+            result()
+        }
+
+        fn name(&self) -> &str {
+            "verifier"
+        }
+    }
+
+    /// Check source whose runs conclude with the scripted conclusions, one
+    /// per poll, repeating the last.
+    struct ScriptedChecks(Mutex<VecDeque<&'static str>>);
+
+    impl CheckSource for ScriptedChecks {
+        fn check_runs(&self, _commit: &str) -> Result<Vec<CheckRun>> {
+            let mut conclusions = self.0.lock().unwrap();
+            let conclusion = if conclusions.len() > 1 {
+                conclusions.pop_front().unwrap()
+            } else {
+                conclusions[0]
+            };
+            Ok(vec![CheckRun {
+                id: 3,
+                name: "test".to_string(),
+                status: "completed".to_string(),
+                conclusion: Some(conclusion.to_string()),
+                url: "https://github.com/acme/widgets/runs/3".to_string(),
+            }])
+        }
+
+        fn annotations(&self, _run: &CheckRun) -> Result<Vec<CheckAnnotation>> {
+            Ok(Vec::new())
+        }
+
+        fn job_log(&self, _run: &CheckRun) -> Result<String> {
+            Ok("thread 'parses' panicked at src/lib.rs:4:5".to_string())
+        }
+    }
+
+    fn thread(resolved: bool) -> ReviewThread {
+        ReviewThread {
+            id: "PRRT_1".to_string(),
+            comment_id: 41,
+            author: "octocat".to_string(),
+            path: "src/lib.rs".to_string(),
+            line: Some(7),
+            body: "This unwrap panics on empty input".to_string(),
+            is_resolved: resolved,
+        }
+    }
+
+    fn annotation() -> CheckAnnotation {
+        CheckAnnotation {
+            check: "test".to_string(),
+            path: "src/lib.rs".to_string(),
+            line: Some(20),
+            level: "failure".to_string(),
+            title: String::new(),
+            message: "assertion failed".to_string(),
+        }
+    }
+
+    /// Creates a clone whose origin has a `feature` branch one commit
+    /// ahead of `main`.
+    fn create_repo_with_origin() -> (TempDir, TempDir) {
+        let origin = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .current_dir(repo.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        Command::new("git")
+            .args(["init", "--bare", "-q"])
+            .arg(origin.path())
+            .output()
+            .unwrap();
+        run(&["init", "-q", "-b", "main"]);
+        run(&["config", "user.email", "test@test.com"]);
+        run(&["config", "user.name", "Test"]);
+        run(&["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        std::fs::write(repo.path().join("README.md"), "# Test").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-m", "Initial commit"]);
+        run(&["checkout", "-q", "-b", "feature"]);
+        std::fs::write(repo.path().join("feature.rs"), "fn feature() {}").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-m", "Add feature"]);
+        run(&["push", "-q", "origin", "main", "feature"]);
+        run(&["checkout", "-q", "main"]);
+        (repo, origin)
+    }
+
+    fn details() -> PrDetails {
+        PrDetails {
+            number: 7,
+            url: "https://github.com/acme/widgets/pull/7".to_string(),
+            title: "Add feature".to_string(),
+            body: String::new(),
+            head_branch: "feature".to_string(),
+            base_branch: "main".to_string(),
+            head_sha: "abc123".to_string(),
+            is_draft: false,
+        }
+    }
+
+    fn fixer(repo: &Path, sandboxes: &Path, verdict: &'static str) -> PrFixer {
+        let verifier = FixVerifier::new(Arc::new(Verdict(verdict)));
+        PrFixer::new(
+            repo,
+            Arc::new(FileWriter {
+                calls: AtomicU32::new(0),
+            }),
+            verifier,
+        )
+        .with_sandbox_dir(sandboxes)
+    }
+
+    fn origin_log(origin: &Path) -> String {
+        let log = Command::new("git")
+            .current_dir(origin)
+            .args(["log", "--format=%s", "feature"])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&log.stdout).into_owned()
+    }
+
+    #[test]
+    fn feedback_becomes_findings_and_threads() {
+        let feedback = PrFeedback {
+            threads: vec![thread(false)],
+            annotations: vec![annotation()],
+        };
+
+        let suggestions = feedback.suggestions();
+        let comments = feedback.posted_comments();
+
+        assert!(!feedback.is_empty());
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].issue, "This unwrap panics on empty input");
+        assert_eq!(suggestions[0].line, Some(7));
+        assert_eq!(suggestions[1].issue, "test failed: assertion failed");
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].id, Some(41));
+        assert_eq!(comments[0].issue, suggestions[0].issue);
+    }
+
+    #[tokio::test]
+    async fn fix_pushes_to_the_pr_branch() {
+        let (repo, origin) = create_repo_with_origin();
+        let sandboxes = TempDir::new().unwrap();
+        let feedback = PrFeedback {
+            threads: Vec::new(),
+            annotations: vec![annotation()],
+        };
+
+        let outcome = fixer(
+            repo.path(),
+            sandboxes.path(),
+            r#"{"resolved": true, "reason": "assertion holds"}"#,
+        )
+        .fix(&details(), &feedback)
+        .await
+        .unwrap();
+
+        assert_eq!(outcome.rounds, 1);
+        assert_eq!(outcome.commits.len(), 1);
+        assert_eq!(outcome.resolved, 1);
+        assert!(outcome.remaining.is_empty());
+        let log = origin_log(origin.path());
+        assert!(log.starts_with("Address feedback on PR #7"), "{}", log);
+        assert!(log.contains("Add feature"));
+    }
+
+    #[tokio::test]
+    async fn unresolved_findings_carry_into_the_next_round() {
+        let (repo, origin) = create_repo_with_origin();
+        let sandboxes = TempDir::new().unwrap();
+        let feedback = PrFeedback {
+            threads: Vec::new(),
+            annotations: vec![annotation()],
+        };
+
+        let outcome = fixer(
+            repo.path(),
+            sandboxes.path(),
+            r#"{"resolved": false, "reason": "still fails"}"#,
+        )
+        .with_max_rounds(2)
+        .fix(&details(), &feedback)
+        .await
+        .unwrap();
+
+        assert_eq!(outcome.rounds, 2);
+        assert_eq!(outcome.commits.len(), 2);
+        assert_eq!(outcome.resolved, 0);
+        assert_eq!(outcome.remaining.len(), 1);
+        assert!(outcome.remaining[0].suggestion.contains("still fails"));
+        assert_eq!(origin_log(origin.path()).lines().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn ci_failures_get_fix_rounds_until_checks_pass() {
+        let (repo, origin) = create_repo_with_origin();
+        let sandboxes = TempDir::new().unwrap();
+        let feedback = PrFeedback {
+            threads: Vec::new(),
+            annotations: vec![annotation()],
+        };
+        let checks = ScriptedChecks(Mutex::new(VecDeque::from(["failure", "success"])));
+        let ci = CiWatcher::new(
+            Arc::new(checks),
+            CiConfig {
+                poll_interval: 0,
+                ..Default::default()
+            },
+        );
+
+        let outcome = fixer(
+            repo.path(),
+            sandboxes.path(),
+            r#"{"resolved": true, "reason": "assertion holds"}"#,
+        )
+        .with_ci(ci)
+        .fix(&details(), &feedback)
+        .await
+        .unwrap();
+
+        assert_eq!(outcome.rounds, 1);
+        assert_eq!(outcome.ci_rounds, 1);
+        assert_eq!(outcome.commits.len(), 2);
+        assert_eq!(outcome.ci, Some(CiStatus::Passed));
+        let log = origin_log(origin.path());
+        assert!(log.starts_with("Fix CI failures on PR #7"), "{}", log);
+    }
+
+    #[tokio::test]
+    async fn failed_verification_gets_a_build_fix_before_pushing() {
+        let (repo, origin) = create_repo_with_origin();
+        let sandboxes = TempDir::new().unwrap();
+        let feedback = PrFeedback {
+            threads: Vec::new(),
+            annotations: vec![annotation()],
+        };
+        // Passes only once the build fix after the first round has run
+        let verification = VerificationConfig {
+            commands: vec!["test -f fix-2.txt".to_string()],
+            ..Default::default()
+        };
+
+        let outcome = fixer(
+            repo.path(),
+            sandboxes.path(),
+            r#"{"resolved": true, "reason": "assertion holds"}"#,
+        )
+        .with_verification(verification)
+        .fix(&details(), &feedback)
+        .await
+        .unwrap();
+
+        assert_eq!(outcome.commits.len(), 1);
+        assert_eq!(outcome.unverified, None);
+        let files = Command::new("git")
+            .current_dir(origin.path())
+            .args(["show", "--name-only", "--format=", "feature"])
+            .output()
+            .unwrap();
+        let files = String::from_utf8_lossy(&files.stdout);
+        assert!(
+            files.contains("fix-1.txt") && files.contains("fix-2.txt"),
+            "{}",
+            files
+        );
+    }
+
+    #[tokio::test]
+    async fn unverified_changes_are_not_pushed() {
+        let (repo, origin) = create_repo_with_origin();
+        let sandboxes = TempDir::new().unwrap();
+        let feedback = PrFeedback {
+            threads: Vec::new(),
+            annotations: vec![annotation()],
+        };
+        let verification = VerificationConfig {
+            commands: vec!["echo 'error: does not compile'; exit 1".to_string()],
+            max_fix_attempts: 1,
+            ..Default::default()
+        };
+
+        let outcome = fixer(
+            repo.path(),
+            sandboxes.path(),
+            r#"{"resolved": true, "reason": "assertion holds"}"#,
+        )
+        .with_verification(verification)
+        .fix(&details(), &feedback)
+        .await
+        .unwrap();
+
+        assert!(outcome.commits.is_empty());
+        assert_eq!(outcome.remaining.len(), 1);
+        let failure = outcome.unverified.unwrap();
+        assert_eq!(failure.output, "error: does not compile");
+        assert_eq!(origin_log(origin.path()).lines().count(), 2);
+    }
+}
@@ -0,0 +1,543 @@
+//! Standalone review of any pull request.
+//!
+//! `review <pr>` points the spawn-team review machinery at a PR the drive
+//! did not open, e.g. one written by a human. Each configured review domain
+//! gets one pass over the PR's diff as reported by `gh pr diff`, with no
+//! fix rounds, and the findings are posted through [`ReviewThreads`], so a
+//! problem several domains flag is only commented on once.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::PERFORMANCE_DOMAIN;
+use crate::diff::{DiffOptions, DiffProvider};
+use crate::error::{Error, Result, ResultExt};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{PRManager, PullRequest};
+use crate::prompt::PromptTemplates;
+use crate::review_loop::{merge_reviews, DomainReviewLoop, DomainReviewOutcome};
+use crate::review_threads::ReviewThreads;
+use crate::runner::LLMRunner;
+use crate::team::{ReviewResult, SpawnTeamConfig};
+
+/// Domain reviewed when the gating configures none.
+pub const DEFAULT_REVIEW_DOMAIN: &str = "general";
+
+/// Domain reviewing infrastructure: CI workflows, containers, deployment.
+pub const INFRA_DOMAIN: &str = "infra";
+
+/// Returns the reviewer focus for `domain`, or `None` for the general
+/// domain, which reviews everything.
+pub fn domain_focus(domain: &str) -> Option<String> {
+    match domain {
+        DEFAULT_REVIEW_DOMAIN => None,
+        INFRA_DOMAIN => Some(
+            "Review for infrastructure concerns only: CI workflows, container images, \
+             deployment and provisioning config. Check for leaked secrets, unpinned \
+             versions, overly broad permissions, and changes that break builds or \
+             deploys."
+                .to_string(),
+        ),
+        domain => Some(format!(
+            "Review for {} concerns only.",
+            domain.replace('_', " ")
+        )),
+    }
+}
+
+/// A pull request to review.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrDetails {
+    /// PR number.
+    pub number: u64,
+    /// PR URL.
+    pub url: String,
+    /// PR title.
+    pub title: String,
+    /// PR description.
+    pub body: String,
+    /// Branch the PR merges from.
+    pub head_branch: String,
+    /// Branch the PR merges into.
+    pub base_branch: String,
+    /// Commit at the head of the PR, which line comments are anchored to.
+    pub head_sha: String,
+    /// Whether the PR is a draft.
+    pub is_draft: bool,
+}
+
+impl PrDetails {
+    /// Returns the PR in the form [`PRManager`] works with.
+    pub fn pull_request(&self) -> PullRequest {
+        PullRequest {
+            number: self.number,
+            url: self.url.clone(),
+            title: self.title.clone(),
+            base_branch: self.base_branch.clone(),
+            head_branch: self.head_branch.clone(),
+            is_draft: self.is_draft,
+        }
+    }
+
+    /// Returns the task the reviewer judges the diff against: the PR's
+    /// title and description.
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!("{}\n\nPull request: {}", self.title, self.url);
+        if !self.body.trim().is_empty() {
+            prompt.push_str(&format!("\n\n{}", self.body.trim()));
+        }
+        prompt
+    }
+}
+
+/// Parses the output of `gh pr view --json` with the fields [`fetch_pr`]
+/// asks for.
+pub fn parse_pr_json(json: &str) -> Result<PrDetails> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("gh output", e))?;
+    let number = json["number"]
+        .as_u64()
+        .ok_or_else(|| Error::GitHub("pull request has no number".to_string()))?;
+    let text = |key: &str| json[key].as_str().unwrap_or_default().to_string();
+
+    Ok(PrDetails {
+        number,
+        url: text("url"),
+        title: text("title"),
+        body: text("body"),
+        head_branch: text("headRefName"),
+        base_branch: text("baseRefName"),
+        head_sha: text("headRefOid"),
+        is_draft: json["isDraft"].as_bool().unwrap_or(false),
+    })
+}
+
+/// Fetches a PR of the repository at `repo_path` by URL or number.
+pub fn fetch_pr(repo_path: &Path, pr: &str) -> Result<PrDetails> {
+    let args = [
+        "pr",
+        "view",
+        pr,
+        "--json",
+        "number,url,title,body,headRefName,baseRefName,headRefOid,isDraft",
+    ];
+    let output = Command::new("gh")
+        .current_dir(repo_path)
+        .args(args)
+        .output()
+        .map_err(|e| Error::from(e).context("failed to run gh"))?;
+
+    if !output.status.success() {
+        return Err(Error::gh_command(&args, &output));
+    }
+
+    parse_pr_json(&String::from_utf8_lossy(&output.stdout))
+        .context(format!("failed to read pull request {}", pr))
+}
+
+/// Diff provider that reads a PR's diff from GitHub, so the PR does not
+/// need to be checked out.
+///
+/// Domain path filters are not applied; the reviewer sees the whole PR.
+#[derive(Debug, Clone)]
+pub struct GhPrDiffProvider {
+    /// PR URL or number.
+    pr: String,
+}
+
+impl GhPrDiffProvider {
+    /// Creates a provider for the PR at `pr` (URL or number).
+    pub fn new(pr: impl Into<String>) -> Self {
+        Self { pr: pr.into() }
+    }
+}
+
+impl DiffProvider for GhPrDiffProvider {
+    fn diff(&self, worktree: &Path, _options: &DiffOptions) -> Result<String> {
+        let args = ["pr", "diff", self.pr.as_str()];
+        let output = Command::new("gh")
+            .current_dir(worktree)
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Outcome of reviewing a PR in every domain.
+#[derive(Debug, Clone)]
+pub struct PrReviewOutcome {
+    /// One outcome per domain, in review order.
+    pub domains: Vec<DomainReviewOutcome>,
+    /// All domains' findings merged into one review.
+    pub review: ReviewResult,
+}
+
+/// Reviews PRs with the configured reviewer and review domains.
+pub struct PrReviewer {
+    /// Repository the `gh` commands and reviewer run in.
+    repo_path: PathBuf,
+    reviewer: Arc<dyn LLMRunner>,
+    config: SpawnTeamConfig,
+    templates: PromptTemplates,
+}
+
+impl PrReviewer {
+    /// Creates a reviewer for PRs of the repository at `repo_path`, using
+    /// the gating and diff settings of `config`.
+    ///
+    /// Every domain gets a single pass: findings are reported, never fixed.
+    pub fn new(
+        repo_path: impl Into<PathBuf>,
+        reviewer: Arc<dyn LLMRunner>,
+        config: &SpawnTeamConfig,
+    ) -> Self {
+        let mut config = config.clone();
+        config.max_iterations = 1;
+        config.reuse_sessions = false;
+        // There is no earlier verdict to diff against
+        config.diff.default.incremental = false;
+        for options in config.diff.domains.values_mut() {
+            options.incremental = false;
+        }
+
+        Self {
+            repo_path: repo_path.into(),
+            reviewer,
+            config,
+            templates: PromptTemplates::builtin(),
+        }
+    }
+
+    /// Sets the prompt templates.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Returns the review domains, sorted, or the general domain if the
+    /// gating configures none. Configured benchmarks add the performance
+    /// domain.
+    pub fn domains(&self) -> Vec<String> {
+        let mut domains: Vec<String> = self.config.gating.domains.keys().cloned().collect();
+        if self.config.benchmarks.is_enabled() && !domains.iter().any(|d| d == PERFORMANCE_DOMAIN) {
+            domains.push(PERFORMANCE_DOMAIN.to_string());
+        }
+        domains.sort_unstable();
+        if domains.is_empty() {
+            domains.push(DEFAULT_REVIEW_DOMAIN.to_string());
+        }
+        domains
+    }
+
+    /// Fetches a PR by URL or number.
+    pub fn fetch(&self, pr: &str) -> Result<PrDetails> {
+        fetch_pr(&self.repo_path, pr)
+    }
+
+    /// Reviews `pr` in every domain.
+    ///
+    /// With benchmarks configured, the PR's head is checked out in a
+    /// temporary worktree so the performance domain can benchmark it
+    /// against the merge base.
+    pub async fn review(&self, pr: &PrDetails) -> Result<PrReviewOutcome> {
+        let provider = Arc::new(GhPrDiffProvider::new(&pr.url));
+        if !self.config.benchmarks.is_enabled() {
+            return self.review_with(pr, provider, None).await;
+        }
+
+        let (checkout, base) = self.checkout(pr)?;
+        let outcome = self
+            .review_with(pr, provider, Some((checkout.as_path(), base.as_str())))
+            .await;
+        if let Err(e) = SystemGit.run(
+            &self.repo_path,
+            &["worktree", "remove", "--force", &checkout.to_string_lossy()],
+        ) {
+            tracing::warn!(error = %e.report(), "failed to remove benchmark worktree");
+        }
+        outcome
+    }
+
+    /// Checks out the head of `pr` in a temporary worktree and returns it
+    /// with the merge base of the PR and its base branch.
+    fn checkout(&self, pr: &PrDetails) -> Result<(PathBuf, String)> {
+        let head_ref = format!("pull/{}/head", pr.number);
+        SystemGit.run(&self.repo_path, &["fetch", "origin", &head_ref])?;
+        SystemGit.run(&self.repo_path, &["fetch", "origin", &pr.base_branch])?;
+        let base = SystemGit
+            .run(&self.repo_path, &["merge-base", &pr.head_sha, "FETCH_HEAD"])?
+            .trim()
+            .to_string();
+
+        let worktree = std::env::temp_dir().join(format!(
+            "improbability-drive-review-{}",
+            uuid::Uuid::new_v4()
+        ));
+        SystemGit.run(
+            &self.repo_path,
+            &[
+                "worktree",
+                "add",
+                "--detach",
+                &worktree.to_string_lossy(),
+                &pr.head_sha,
+            ],
+        )?;
+        Ok((worktree, base))
+    }
+
+    /// Reviews `pr` in every domain, reading its diff from `provider`.
+    /// `checkout` is the PR's head and merge base the performance domain
+    /// benchmarks.
+    async fn review_with(
+        &self,
+        pr: &PrDetails,
+        provider: Arc<dyn DiffProvider>,
+        checkout: Option<(&Path, &str)>,
+    ) -> Result<PrReviewOutcome> {
+        // The single pass never fixes, so the reviewer doubles as primary
+        let mut review_loop =
+            DomainReviewLoop::new(self.reviewer.clone(), self.reviewer.clone(), &self.config)
+                .with_templates(self.templates.clone())
+                .with_diff_provider(provider);
+        if let Some((_, base)) = checkout {
+            review_loop = review_loop.with_base_ref(base);
+        }
+        let prompt = pr.prompt();
+
+        let mut domains = Vec::new();
+        for domain in review_loop.route(&self.repo_path, &self.domains())? {
+            let focus = domain_focus(&domain);
+            let worktree = match checkout {
+                Some((checkout, _)) if domain == PERFORMANCE_DOMAIN => checkout,
+                _ => self.repo_path.as_path(),
+            };
+            let outcome = review_loop
+                .run(worktree, &prompt, &domain, focus.as_deref())
+                .await?;
+            domains.push(outcome);
+        }
+
+        let review = merge_reviews(
+            domains
+                .iter()
+                .flat_map(|outcome| outcome.reviews())
+                .collect(),
+        );
+        Ok(PrReviewOutcome { domains, review })
+    }
+
+    /// Posts the findings of `review` on `pr`, skipping those `threads`
+    /// has already posted. Returns the number of new findings.
+    pub fn post(
+        &self,
+        pr: &PrDetails,
+        review: &ReviewResult,
+        threads: &mut ReviewThreads,
+    ) -> Result<usize> {
+        let manager = PRManager::new(self.repo_path.clone());
+        threads.post(&manager, &pr.pull_request(), &pr.head_sha, review)
+    }
+
+    /// Writes the last review of each reviewed domain in `outcome` to its
+    /// own section of the description of `pr`, replacing the section an
+    /// earlier review wrote. Skipped domains are left alone.
+    pub fn update_description(&self, pr: &PrDetails, outcome: &PrReviewOutcome) -> Result<()> {
+        let manager = PRManager::new(self.repo_path.clone());
+        let pull_request = pr.pull_request();
+        for domain in &outcome.domains {
+            if let Some(pass) = domain.passes.last() {
+                manager.append_review_to_pr(&pull_request, &domain.domain, &pass.review)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{LLMOutput, LLMResult, LLMSpawnConfig};
+    use crate::team::{ReviewGatePolicy, ReviewVerdict};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    /// Diff provider returning a fixed diff.
+    struct FixedDiff;
+
+    impl DiffProvider for FixedDiff {
+        fn diff(&self, _worktree: &Path, _options: &DiffOptions) -> Result<String> {
+            Ok("+fn parse() { unimplemented!() }".to_string())
+        }
+    }
+
+    /// Reviewer that flags every diff and records its prompts.
+    #[derive(Default)]
+    struct FlaggingReviewer {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LLMRunner for FlaggingReviewer {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            self.prompts.lock().unwrap().push(config.prompt);
+            let response = r#"{"verdict": "needs_changes", "suggestions": [{"file": "src/lib.rs", "line": 1, "issue": "parse is unimplemented", "suggestion": "implement it", "severity": "high"}]}"#;
+            let _ = output_tx
+                .send(LLMOutput::Stdout(response.to_string()))
+                .await;
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "reviewer"
+        }
+    }
+
+    fn pr() -> PrDetails {
+        PrDetails {
+            number: 7,
+            url: "https://github.com/acme/widgets/pull/7".to_string(),
+            title: "Add parser".to_string(),
+            body: "Parses widget files.".to_string(),
+            head_branch: "parser".to_string(),
+            base_branch: "main".to_string(),
+            head_sha: "abc123".to_string(),
+            is_draft: false,
+        }
+    }
+
+    #[test]
+    fn parses_gh_pr_json() {
+        let json = r#"{
+            "number": 7,
+            "url": "https://github.com/acme/widgets/pull/7",
+            "title": "Add parser",
+            "body": "Parses widget files.",
+            "headRefName": "parser",
+            "baseRefName": "main",
+            "headRefOid": "abc123",
+            "isDraft": false
+        }"#;
+
+        assert_eq!(parse_pr_json(json).unwrap(), pr());
+        assert!(parse_pr_json("{}").is_err());
+    }
+
+    #[test]
+    fn domains_default_to_general() {
+        let reviewer = Arc::new(FlaggingReviewer::default());
+        let mut config = SpawnTeamConfig::default();
+        assert_eq!(
+            PrReviewer::new("/tmp", reviewer.clone(), &config).domains(),
+            vec![DEFAULT_REVIEW_DOMAIN]
+        );
+
+        for domain in ["tests", "security"] {
+            config
+                .gating
+                .domains
+                .insert(domain.to_string(), ReviewGatePolicy::default());
+        }
+        assert_eq!(
+            PrReviewer::new("/tmp", reviewer.clone(), &config).domains(),
+            vec!["security", "tests"]
+        );
+
+        config.benchmarks.commands = vec!["cargo bench".to_string()];
+        assert_eq!(
+            PrReviewer::new("/tmp", reviewer, &config).domains(),
+            vec!["performance", "security", "tests"]
+        );
+    }
+
+    #[tokio::test]
+    async fn reviews_each_domain_once_without_fixing() {
+        let dir = TempDir::new().unwrap();
+        let reviewer = Arc::new(FlaggingReviewer::default());
+        let mut config = SpawnTeamConfig {
+            max_iterations: 3,
+            ..Default::default()
+        };
+        config
+            .gating
+            .domains
+            .insert("security".to_string(), ReviewGatePolicy::default());
+
+        let outcome = PrReviewer::new(dir.path(), reviewer.clone(), &config)
+            .review_with(&pr(), Arc::new(FixedDiff), None)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.domains.len(), 1);
+        assert_eq!(outcome.domains[0].passes.len(), 1);
+        assert!(!outcome.domains[0].passes[0].fixed);
+        assert_eq!(outcome.review.verdict, ReviewVerdict::NeedsChanges);
+        assert_eq!(outcome.review.suggestions.len(), 1);
+
+        let prompts = reviewer.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("Add parser"));
+        assert!(prompts[0].contains("Review for security concerns only."));
+        assert!(prompts[0].contains("unimplemented!()"));
+    }
+
+    #[tokio::test]
+    async fn performance_domain_gets_benchmark_numbers() {
+        let dir = TempDir::new().unwrap();
+        let checkout = dir.path();
+        let run = |args: &[&str]| SystemGit.run(checkout, args).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "test@example.com"]);
+        run(&["config", "user.name", "Test"]);
+        std::fs::write(
+            checkout.join("bench.txt"),
+            "test parse ... bench: 1,000 ns/iter\n",
+        )
+        .unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "base"]);
+        let base = run(&["rev-parse", "HEAD"]).trim().to_string();
+        std::fs::write(
+            checkout.join("bench.txt"),
+            "test parse ... bench: 1,500 ns/iter\n",
+        )
+        .unwrap();
+        run(&["commit", "-q", "-am", "slower"]);
+
+        let reviewer = Arc::new(FlaggingReviewer::default());
+        let mut config = SpawnTeamConfig::default();
+        config.benchmarks.commands = vec!["cat bench.txt".to_string()];
+
+        let outcome = PrReviewer::new(checkout, reviewer.clone(), &config)
+            .review_with(&pr(), Arc::new(FixedDiff), Some((checkout, &base)))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.domains[0].domain, PERFORMANCE_DOMAIN);
+        assert_eq!(outcome.review.suggestions.len(), 2);
+        assert_eq!(outcome.review.suggestions[1].file, "(benchmark: parse)");
+        let prompts = reviewer.prompts.lock().unwrap();
+        assert!(prompts[0].contains("Review for performance concerns only."));
+        assert!(prompts[0].contains("| parse | 1.00 µs | 1.50 µs | +50.0% |"));
+    }
+}
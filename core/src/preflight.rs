@@ -0,0 +1,554 @@
+//! Preflight checks for the command-line tools a run depends on.
+//!
+//! Runs shell out to `git`, `gh`, and the CLI of every LLM they invoke; a
+//! missing or logged-out tool otherwise only shows up once the phase that
+//! needs it starts. [`Preflight`] works out from the settings which tools a
+//! command needs (the runners it would invoke, whether it talks to GitHub)
+//! and checks that each is on `PATH`, new enough, and logged in. The
+//! `doctor` command prints the report; runs check it before they start.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::cruise::RepoLifecycle;
+use crate::error::{Error, Result};
+use crate::runner::{CliVersion, CLAUDE_MIN_VERSION, GEMINI_MIN_VERSION};
+use crate::settings::Settings;
+
+/// Commands whose requirements [`Preflight::requirements`] knows.
+pub const PREFLIGHT_COMMANDS: &[&str] = &["spawn", "spawn-team", "cruise", "review", "fix-pr"];
+
+/// Environment variables that log Claude Code in without a saved login.
+const CLAUDE_AUTH_ENV: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "CLAUDE_CODE_OAUTH_TOKEN",
+    "CLAUDE_CODE_USE_BEDROCK",
+    "CLAUDE_CODE_USE_VERTEX",
+];
+
+/// Environment variables that log Gemini CLI in without a saved login.
+const GEMINI_AUTH_ENV: &[&str] = &[
+    "GEMINI_API_KEY",
+    "GOOGLE_API_KEY",
+    "GOOGLE_GENAI_USE_VERTEXAI",
+];
+
+/// A command-line tool runs depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tool {
+    /// Git, for sandboxes, commits, and pushes.
+    Git,
+    /// The GitHub CLI, for PRs, issues, and repositories.
+    Gh,
+    /// Claude Code, the `claude-code` runner.
+    Claude,
+    /// Gemini CLI, the `gemini-cli` runner.
+    Gemini,
+}
+
+impl Tool {
+    /// Returns the tool behind an LLM runner identifier, if known.
+    pub fn for_runner(llm: &str) -> Option<Self> {
+        match llm {
+            "claude-code" => Some(Tool::Claude),
+            "gemini-cli" => Some(Tool::Gemini),
+            _ => None,
+        }
+    }
+
+    /// Returns the binary looked up on `PATH`.
+    pub fn binary(self) -> &'static str {
+        match self {
+            Tool::Git => "git",
+            Tool::Gh => "gh",
+            Tool::Claude => "claude",
+            Tool::Gemini => "gemini",
+        }
+    }
+
+    /// Returns the oldest supported version, if there is one.
+    pub fn min_version(self) -> Option<CliVersion> {
+        match self {
+            // Sandboxes are git worktrees
+            Tool::Git => Some(CliVersion::new(2, 5, 0)),
+            // PR and issue commands read `--json` output
+            Tool::Gh => Some(CliVersion::new(2, 0, 0)),
+            Tool::Claude => Some(CLAUDE_MIN_VERSION),
+            Tool::Gemini => Some(GEMINI_MIN_VERSION),
+        }
+    }
+
+    /// Tells the user how to install the tool.
+    fn install_hint(self) -> &'static str {
+        match self {
+            Tool::Git => "install git from https://git-scm.com/downloads",
+            Tool::Gh => "install the GitHub CLI from https://cli.github.com",
+            Tool::Claude => "install Claude Code with `npm install -g @anthropic-ai/claude-code`",
+            Tool::Gemini => "install Gemini CLI with `npm install -g @google/gemini-cli`",
+        }
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.binary())
+    }
+}
+
+/// Output of a finished tool invocation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolOutput {
+    /// Whether the tool exited successfully.
+    pub success: bool,
+    /// Standard output.
+    pub stdout: String,
+    /// Standard error.
+    pub stderr: String,
+}
+
+/// Result of checking one tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Installed, new enough, and logged in.
+    Ok,
+    /// Usable, but something could not be confirmed.
+    Warning(String),
+    /// Not usable; the message says how to fix it.
+    Failed(String),
+}
+
+/// One tool's check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCheck {
+    /// The tool checked.
+    pub tool: Tool,
+    /// What the command needs it for.
+    pub purposes: Vec<String>,
+    /// Version reported by the tool, if it ran.
+    pub version: Option<String>,
+    /// Outcome.
+    pub status: CheckStatus,
+}
+
+/// Checks of every tool a command needs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightReport {
+    /// Checks, one per tool.
+    pub checks: Vec<ToolCheck>,
+}
+
+impl PreflightReport {
+    /// Returns whether every tool is usable.
+    pub fn is_ok(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|c| matches!(c.status, CheckStatus::Failed(_)))
+    }
+
+    /// Returns the warnings, or an [`Error::Config`] listing every failed
+    /// tool and how to fix it.
+    pub fn into_result(self) -> Result<Vec<String>> {
+        let mut warnings = Vec::new();
+        let mut failures = Vec::new();
+        for check in self.checks {
+            match check.status {
+                CheckStatus::Ok => {}
+                CheckStatus::Warning(message) => {
+                    warnings.push(format!("{}: {}", check.tool, message))
+                }
+                CheckStatus::Failed(message) => {
+                    failures.push(format!("{}: {}", check.tool, message))
+                }
+            }
+        }
+        if failures.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(Error::Config(format!(
+                "preflight failed: {}",
+                failures.join("; ")
+            )))
+        }
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let label = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warning(_) => "warning",
+                CheckStatus::Failed(_) => "FAILED",
+            };
+            write!(f, "{:<8}{}", label, check.tool)?;
+            if let Some(version) = &check.version {
+                write!(f, " {}", version)?;
+            }
+            writeln!(f, " ({})", check.purposes.join(", "))?;
+            if let CheckStatus::Warning(message) | CheckStatus::Failed(message) = &check.status {
+                writeln!(f, "        {}", message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs a tool with arguments, returning `None` if it could not be started.
+type ToolRunner = dyn Fn(&str, &[&str]) -> Option<ToolOutput> + Send + Sync;
+
+/// Checks the tools a command needs before it runs.
+pub struct Preflight<'a> {
+    /// Settings deciding which runners are invoked.
+    settings: &'a Settings,
+    /// Runs the tools.
+    runner: Box<ToolRunner>,
+    /// Environment consulted for credentials.
+    env: HashMap<String, String>,
+    /// Home directory holding saved logins.
+    home: Option<PathBuf>,
+}
+
+impl<'a> Preflight<'a> {
+    /// Creates a preflight that runs the real tools with the process
+    /// environment.
+    pub fn new(settings: &'a Settings) -> Self {
+        Self {
+            settings,
+            runner: Box::new(run_tool),
+            env: std::env::vars().collect(),
+            home: std::env::var_os("HOME").map(PathBuf::from),
+        }
+    }
+
+    /// Sets how tools are run.
+    pub fn with_runner(
+        mut self,
+        runner: impl Fn(&str, &[&str]) -> Option<ToolOutput> + Send + Sync + 'static,
+    ) -> Self {
+        self.runner = Box::new(runner);
+        self
+    }
+
+    /// Sets the environment consulted for credentials.
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Sets the home directory holding saved logins.
+    pub fn with_home(mut self, home: impl Into<PathBuf>) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    /// Returns the tools `command` needs and what for.
+    ///
+    /// Any command not in [`PREFLIGHT_COMMANDS`] needs everything those do.
+    pub fn requirements(&self, command: &str) -> BTreeMap<Tool, Vec<String>> {
+        let team = &self.settings.team;
+        let mut needs: Vec<(Option<Tool>, &str)> = vec![(Some(Tool::Git), "sandboxes and commits")];
+        match command {
+            "spawn" => {
+                needs.push((
+                    Tool::for_runner(&self.settings.spawn.default_llm),
+                    "primary",
+                ));
+                needs.push((Some(Tool::Gh), "pull requests"));
+            }
+            "spawn-team" => {
+                for role in &team.roles {
+                    needs.push((Tool::for_runner(&role.llm), "team roles"));
+                }
+                if team.tester.enabled {
+                    let llm = team.tester.llm.as_deref().unwrap_or(&team.primary_llm);
+                    needs.push((Tool::for_runner(llm), "tests"));
+                }
+                needs.push((Tool::for_runner(&team.primary_llm), "primary and fixes"));
+                needs.push((Tool::for_runner(&team.reviewer_llm), "reviews"));
+                needs.push((Some(Tool::Gh), "pull requests"));
+            }
+            "cruise" => {
+                let cruise = &self.settings.cruise;
+                needs.push((
+                    Tool::for_runner(&self.settings.spawn.default_llm),
+                    "planning",
+                ));
+                needs.push((
+                    Tool::for_runner(&cruise.planning.reviewer_llm),
+                    "plan reviews",
+                ));
+                needs.push((Some(Tool::Gh), "pull requests"));
+                if cruise.test.repo_lifecycle == RepoLifecycle::CreateNew {
+                    needs.push((Some(Tool::Gh), "test repositories"));
+                }
+            }
+            "review" => {
+                needs.push((Tool::for_runner(&team.reviewer_llm), "reviews"));
+                needs.push((Some(Tool::Gh), "reading and commenting on PRs"));
+            }
+            "fix-pr" => {
+                needs.push((Tool::for_runner(&team.primary_llm), "fixes"));
+                needs.push((Tool::for_runner(&team.reviewer_llm), "fix verification"));
+                needs.push((Some(Tool::Gh), "reading and pushing to PRs"));
+            }
+            _ => {
+                let mut all = BTreeMap::new();
+                for command in PREFLIGHT_COMMANDS {
+                    merge(&mut all, self.requirements(command));
+                }
+                return all;
+            }
+        }
+
+        let mut requirements = BTreeMap::new();
+        for (tool, purpose) in needs {
+            if let Some(tool) = tool {
+                merge(&mut requirements, [(tool, vec![purpose.to_string()])]);
+            }
+        }
+        requirements
+    }
+
+    /// Checks every tool `command` needs.
+    pub fn check(&self, command: &str) -> PreflightReport {
+        let checks = self
+            .requirements(command)
+            .into_iter()
+            .map(|(tool, purposes)| {
+                let (version, status) = self.check_tool(tool);
+                ToolCheck {
+                    tool,
+                    purposes,
+                    version,
+                    status,
+                }
+            })
+            .collect();
+        PreflightReport { checks }
+    }
+
+    /// Checks that `tool` runs, is new enough, and is logged in.
+    fn check_tool(&self, tool: Tool) -> (Option<String>, CheckStatus) {
+        let Some(output) = (self.runner)(tool.binary(), &["--version"]) else {
+            return (
+                None,
+                CheckStatus::Failed(format!("not found on PATH; {}", tool.install_hint())),
+            );
+        };
+        if !output.success {
+            return (
+                None,
+                CheckStatus::Failed(format!(
+                    "`{} --version` failed: {}",
+                    tool,
+                    first_line(&output.stderr)
+                )),
+            );
+        }
+
+        let version = CliVersion::parse(&output.stdout);
+        if let (Some(min), Some(found)) = (tool.min_version(), version) {
+            if found < min {
+                return (
+                    Some(found.to_string()),
+                    CheckStatus::Failed(format!(
+                        "version {} or newer is required; {}",
+                        min,
+                        tool.install_hint()
+                    )),
+                );
+            }
+        }
+        (version.map(|v| v.to_string()), self.check_auth(tool))
+    }
+
+    /// Checks that `tool` is logged in.
+    ///
+    /// Only `gh` can be asked; for the LLM CLIs a saved login or credential
+    /// variable is looked for, and finding none is a warning since the
+    /// login may live in the system keychain.
+    fn check_auth(&self, tool: Tool) -> CheckStatus {
+        match tool {
+            Tool::Git => CheckStatus::Ok,
+            Tool::Gh => match (self.runner)("gh", &["auth", "status"]) {
+                Some(output) if output.success => CheckStatus::Ok,
+                _ => CheckStatus::Failed(
+                    "not logged in to GitHub; run `gh auth login` or set GH_TOKEN".to_string(),
+                ),
+            },
+            Tool::Claude => self.saved_login(
+                CLAUDE_AUTH_ENV,
+                ".claude/.credentials.json",
+                "no saved login or API key found; run `claude` once to log in or set ANTHROPIC_API_KEY",
+            ),
+            Tool::Gemini => self.saved_login(
+                GEMINI_AUTH_ENV,
+                ".gemini/oauth_creds.json",
+                "no saved login or API key found; run `gemini` once to log in or set GEMINI_API_KEY",
+            ),
+        }
+    }
+
+    /// Returns OK if a variable in `vars` is set or `file` exists under
+    /// the home directory, otherwise a warning with `hint`.
+    fn saved_login(&self, vars: &[&str], file: &str, hint: &str) -> CheckStatus {
+        let from_env = vars
+            .iter()
+            .any(|var| self.env.get(*var).is_some_and(|v| !v.is_empty()));
+        let from_file = self
+            .home
+            .as_ref()
+            .is_some_and(|home| home.join(file).exists());
+        if from_env || from_file {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Warning(hint.to_string())
+        }
+    }
+}
+
+/// Adds `other`'s purposes to `requirements`, skipping duplicates.
+fn merge(
+    requirements: &mut BTreeMap<Tool, Vec<String>>,
+    other: impl IntoIterator<Item = (Tool, Vec<String>)>,
+) {
+    for (tool, purposes) in other {
+        let entry = requirements.entry(tool).or_default();
+        for purpose in purposes {
+            if !entry.contains(&purpose) {
+                entry.push(purpose);
+            }
+        }
+    }
+}
+
+/// Runs `program` with `args`.
+fn run_tool(program: &str, args: &[&str]) -> Option<ToolOutput> {
+    let output = Command::new(program).args(args).output().ok()?;
+    Some(ToolOutput {
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Answers `--version` for the given tools and `gh auth status` with
+    /// `gh_logged_in`.
+    fn tools(
+        installed: &'static [(&'static str, &'static str)],
+        gh_logged_in: bool,
+    ) -> impl Fn(&str, &[&str]) -> Option<ToolOutput> + Send + Sync {
+        move |program, args| {
+            if args == ["auth", "status"] {
+                return Some(ToolOutput {
+                    success: gh_logged_in,
+                    ..Default::default()
+                });
+            }
+            let (_, version) = installed.iter().find(|(name, _)| *name == program)?;
+            Some(ToolOutput {
+                success: true,
+                stdout: version.to_string(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn requirements_follow_the_configured_runners() {
+        let mut settings = Settings::default();
+        settings.team.primary_llm = "gemini-cli".to_string();
+        settings.team.reviewer_llm = "gemini-cli".to_string();
+        let preflight = Preflight::new(&settings);
+
+        let review = preflight.requirements("review");
+        assert_eq!(
+            review.keys().copied().collect::<Vec<_>>(),
+            [Tool::Git, Tool::Gh, Tool::Gemini]
+        );
+        assert_eq!(
+            preflight.requirements("spawn-team")[&Tool::Gemini],
+            ["primary and fixes", "reviews"]
+        );
+        assert!(preflight.requirements("spawn").contains_key(&Tool::Claude));
+        assert!(preflight.requirements("doctor").contains_key(&Tool::Claude));
+    }
+
+    #[test]
+    fn check_reports_missing_outdated_and_logged_out_tools() {
+        let settings = Settings::default();
+        let home = TempDir::new().unwrap();
+
+        let report = Preflight::new(&settings)
+            .with_runner(tools(
+                &[
+                    ("git", "git version 2.43.0\n"),
+                    ("gh", "gh version 1.14.0 (2021-08-04)\n"),
+                ],
+                true,
+            ))
+            .with_env(HashMap::new())
+            .with_home(home.path())
+            .check("spawn");
+
+        assert!(!report.is_ok());
+        assert_eq!(report.checks[0].version.as_deref(), Some("2.43.0"));
+        assert_eq!(report.checks[0].status, CheckStatus::Ok);
+        assert!(
+            matches!(&report.checks[1].status, CheckStatus::Failed(m) if m.starts_with("version 2.0.0 or newer"))
+        );
+        assert!(
+            matches!(&report.checks[2].status, CheckStatus::Failed(m) if m.starts_with("not found on PATH"))
+        );
+        let err = report.into_result().unwrap_err().to_string();
+        assert!(err.contains("gh: version 2.0.0 or newer is required"));
+        assert!(err.contains("claude: not found on PATH; install Claude Code"));
+
+        let logged_out = Preflight::new(&settings)
+            .with_runner(tools(
+                &[
+                    ("git", "git version 2.43.0"),
+                    ("gh", "gh version 2.40.1"),
+                    ("claude", "1.0.30 (Claude Code)"),
+                ],
+                false,
+            ))
+            .with_env(HashMap::new())
+            .with_home(home.path())
+            .check("spawn");
+        assert!(logged_out
+            .to_string()
+            .contains("FAILED  gh 2.40.1 (pull requests)\n        not logged in"));
+        assert!(matches!(
+            logged_out.checks[2].status,
+            CheckStatus::Warning(_)
+        ));
+
+        let env = [("ANTHROPIC_API_KEY".to_string(), "sk-test".to_string())].into();
+        let ready = Preflight::new(&settings)
+            .with_runner(tools(
+                &[
+                    ("git", "git version 2.43.0"),
+                    ("gh", "gh version 2.40.1"),
+                    ("claude", "1.0.30 (Claude Code)"),
+                ],
+                true,
+            ))
+            .with_env(env)
+            .with_home(home.path())
+            .check("spawn");
+        assert_eq!(ready.into_result().unwrap(), Vec::<String>::new());
+    }
+}
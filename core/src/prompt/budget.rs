@@ -0,0 +1,408 @@
+//! Token budget estimation and prompt truncation.
+//!
+//! Token counts are estimated from character counts using a per-model ratio.
+//! The estimates are deliberately conservative: they are used to keep prompts
+//! inside a model's context window, not for billing.
+
+/// Default context window for unknown models, in tokens.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+
+/// Marker inserted where text was removed by truncation.
+pub const TRUNCATION_MARKER: &str = "[... truncated ...]";
+
+/// Returns the context window (in tokens) for a model identifier.
+///
+/// Accepts both runner names ("claude-code", "gemini-cli") and model names
+/// ("sonnet", "gemini-2.5-pro").
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_lowercase();
+    if model.contains("gemini") {
+        1_000_000
+    } else if model.contains("claude")
+        || model.contains("opus")
+        || model.contains("sonnet")
+        || model.contains("haiku")
+    {
+        200_000
+    } else {
+        DEFAULT_CONTEXT_WINDOW
+    }
+}
+
+/// Returns the average number of characters per token for a model.
+fn chars_per_token(model: &str) -> f64 {
+    let model = model.to_lowercase();
+    if model.contains("gemini") {
+        4.0
+    } else {
+        // Claude tokenizers average slightly fewer characters per token
+        3.5
+    }
+}
+
+/// Estimates the number of tokens in `text` for the given model.
+pub fn estimate_tokens(text: &str, model: &str) -> usize {
+    (text.chars().count() as f64 / chars_per_token(model)).ceil() as usize
+}
+
+/// Returns the approximate number of characters that fit in `tokens`.
+fn chars_for_tokens(tokens: usize, model: &str) -> usize {
+    (tokens as f64 * chars_per_token(model)).floor() as usize
+}
+
+/// Returns true if `text` likely exceeds the model's context window.
+pub fn exceeds_context_window(text: &str, model: &str) -> bool {
+    estimate_tokens(text, model) > context_window(model)
+}
+
+/// Logs a warning if `text` likely exceeds the model's context window.
+///
+/// Returns true if a warning was emitted.
+pub fn warn_if_over_window(text: &str, model: &str) -> bool {
+    let tokens = estimate_tokens(text, model);
+    let window = context_window(model);
+    if tokens > window {
+        tracing::warn!(
+            model = %model,
+            estimated_tokens = tokens,
+            context_window = window,
+            "prompt likely exceeds model context window"
+        );
+        true
+    } else {
+        false
+    }
+}
+
+/// Returns approximate list prices (input, output) in USD per million tokens.
+///
+/// Runner names resolve to the model the CLI uses by default. Unknown models
+/// are priced like a mid-tier model so estimates err on the high side.
+fn price_per_million(model: &str) -> (f64, f64) {
+    let model = model.to_lowercase();
+    if model.contains("opus") {
+        (15.0, 75.0)
+    } else if model.contains("haiku") {
+        (0.8, 4.0)
+    } else if model.contains("flash") {
+        (0.3, 2.5)
+    } else if model.contains("gemini") {
+        (1.25, 10.0)
+    } else {
+        (3.0, 15.0)
+    }
+}
+
+/// Estimates the cost in USD of an invocation with the given token counts.
+///
+/// Like the token estimates, this is for planning, not billing.
+pub fn estimate_cost(model: &str, input_tokens: usize, output_tokens: usize) -> f64 {
+    let (input, output) = price_per_million(model);
+    (input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0
+}
+
+/// Truncates plain text to fit within `max_tokens`.
+///
+/// Keeps the beginning and end of the text and replaces the middle with
+/// [`TRUNCATION_MARKER`].
+pub fn truncate_text(text: &str, max_tokens: usize, model: &str) -> String {
+    if estimate_tokens(text, model) <= max_tokens {
+        return text.to_string();
+    }
+
+    let budget = chars_for_tokens(max_tokens, model).saturating_sub(TRUNCATION_MARKER.len() + 2);
+    let chars: Vec<char> = text.chars().collect();
+    let head_len = budget * 2 / 3;
+    let tail_len = budget - head_len;
+
+    let head: String = chars[..head_len].iter().collect();
+    let tail: String = chars[chars.len() - tail_len..].iter().collect();
+    format!("{}\n{}\n{}", head, TRUNCATION_MARKER, tail)
+}
+
+/// A file section of a unified diff.
+struct DiffFile<'a> {
+    /// Lines before the first hunk (`diff --git`, `index`, `---`, `+++`).
+    header: &'a str,
+    /// Hunks, each starting with an `@@` line.
+    hunks: Vec<&'a str>,
+}
+
+/// Splits a unified diff into files and hunks.
+fn parse_diff(diff: &str) -> Vec<DiffFile<'_>> {
+    let mut files = Vec::new();
+
+    // Each `diff --git` line starts a new file; any preamble joins the first
+    let mut file_starts = vec![0];
+    let mut offset = 0;
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git") && offset > 0 {
+            file_starts.push(offset);
+        }
+        offset += line.len();
+    }
+
+    for (i, &start) in file_starts.iter().enumerate() {
+        let end = file_starts.get(i + 1).copied().unwrap_or(diff.len());
+        let section = &diff[start..end];
+
+        let mut hunk_starts = Vec::new();
+        let mut pos = 0;
+        for line in section.split_inclusive('\n') {
+            if line.starts_with("@@") {
+                hunk_starts.push(pos);
+            }
+            pos += line.len();
+        }
+
+        let header_end = hunk_starts.first().copied().unwrap_or(section.len());
+        let hunks = hunk_starts
+            .iter()
+            .enumerate()
+            .map(|(j, &h)| &section[h..hunk_starts.get(j + 1).copied().unwrap_or(section.len())])
+            .collect();
+
+        files.push(DiffFile {
+            header: &section[..header_end],
+            hunks,
+        });
+    }
+
+    files
+}
+
+/// Truncates a unified diff to fit within `max_tokens`.
+///
+/// File headers are always kept so the reviewer knows which files changed.
+/// Hunks are then added in priority order — the first hunk of each file, the
+/// last hunk of each file, then the remaining middle hunks — while they fit.
+/// Omitted hunks are replaced with a marker noting how many were dropped.
+pub fn truncate_diff(diff: &str, max_tokens: usize, model: &str) -> String {
+    if estimate_tokens(diff, model) <= max_tokens {
+        return diff.to_string();
+    }
+
+    let files = parse_diff(diff);
+    let budget = chars_for_tokens(max_tokens, model);
+
+    let mut used: usize = files.iter().map(|f| f.header.len()).sum();
+    if used > budget {
+        // Even the headers don't fit - fall back to plain truncation
+        return truncate_text(diff, max_tokens, model);
+    }
+
+    // Reserve room for one omission marker per file
+    used += files.len() * (TRUNCATION_MARKER.len() + 16);
+
+    let mut included: Vec<Vec<bool>> = files.iter().map(|f| vec![false; f.hunks.len()]).collect();
+
+    let mut order: Vec<(usize, usize)> = Vec::new();
+    for (fi, file) in files.iter().enumerate() {
+        if !file.hunks.is_empty() {
+            order.push((fi, 0));
+        }
+    }
+    for (fi, file) in files.iter().enumerate() {
+        if file.hunks.len() > 1 {
+            order.push((fi, file.hunks.len() - 1));
+        }
+    }
+    for (fi, file) in files.iter().enumerate() {
+        for hi in 1..file.hunks.len().saturating_sub(1) {
+            order.push((fi, hi));
+        }
+    }
+
+    for (fi, hi) in order {
+        let len = files[fi].hunks[hi].len();
+        if used + len <= budget {
+            included[fi][hi] = true;
+            used += len;
+        }
+    }
+
+    let mut out = String::with_capacity(budget);
+    for (fi, file) in files.iter().enumerate() {
+        out.push_str(file.header);
+        let mut omitted = 0;
+        for (hi, hunk) in file.hunks.iter().enumerate() {
+            if included[fi][hi] {
+                if omitted > 0 {
+                    out.push_str(&omission_marker(omitted));
+                    omitted = 0;
+                }
+                out.push_str(hunk);
+            } else {
+                omitted += 1;
+            }
+        }
+        if omitted > 0 {
+            out.push_str(&omission_marker(omitted));
+        }
+    }
+
+    out
+}
+
+/// Splits a unified diff into chunks of whole files, each within `max_tokens`.
+///
+/// A file too large for a chunk of its own is truncated with
+/// [`truncate_diff`]. A diff that already fits is returned as one chunk.
+pub fn chunk_diff(diff: &str, max_tokens: usize, model: &str) -> Vec<String> {
+    if estimate_tokens(diff, model) <= max_tokens {
+        return vec![diff.to_string()];
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for file in parse_diff(diff) {
+        let section = format!("{}{}", file.header, file.hunks.concat());
+        if estimate_tokens(&section, model) > max_tokens {
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            chunks.push(truncate_diff(&section, max_tokens, model));
+            continue;
+        }
+        if !current.is_empty()
+            && estimate_tokens(&current, model) + estimate_tokens(&section, model) > max_tokens
+        {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(&section);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+fn omission_marker(count: usize) -> String {
+    format!(
+        "{} ({} hunk{} omitted)\n",
+        TRUNCATION_MARKER,
+        count,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_diff(files: usize, hunks_per_file: usize, lines_per_hunk: usize) -> String {
+        let mut diff = String::new();
+        for f in 0..files {
+            diff.push_str(&format!("diff --git a/file{f}.rs b/file{f}.rs\n"));
+            diff.push_str(&format!("--- a/file{f}.rs\n+++ b/file{f}.rs\n"));
+            for h in 0..hunks_per_file {
+                diff.push_str(&format!("@@ -{h},3 +{h},3 @@\n"));
+                for l in 0..lines_per_hunk {
+                    diff.push_str(&format!("+file {f} hunk {h} line {l}\n"));
+                }
+            }
+        }
+        diff
+    }
+
+    #[test]
+    fn context_window_by_model() {
+        assert_eq!(context_window("claude-code"), 200_000);
+        assert_eq!(context_window("sonnet"), 200_000);
+        assert_eq!(context_window("gemini-2.5-pro"), 1_000_000);
+        assert_eq!(context_window("gpt-4"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn estimate_tokens_scales_with_length() {
+        assert_eq!(estimate_tokens("", "sonnet"), 0);
+        assert_eq!(estimate_tokens("abcd", "gemini-cli"), 1);
+        assert!(estimate_tokens(&"x".repeat(7000), "sonnet") >= 2000);
+    }
+
+    #[test]
+    fn exceeds_context_window_detects_large_prompts() {
+        assert!(!exceeds_context_window("small", "haiku"));
+        assert!(exceeds_context_window(&"x".repeat(800_000), "haiku"));
+        assert!(!exceeds_context_window(&"x".repeat(800_000), "gemini-cli"));
+    }
+
+    #[test]
+    fn truncate_text_keeps_head_and_tail() {
+        let text = format!("START{}END", "m".repeat(10_000));
+        let truncated = truncate_text(&text, 100, "sonnet");
+
+        assert!(truncated.starts_with("START"));
+        assert!(truncated.ends_with("END"));
+        assert!(truncated.contains(TRUNCATION_MARKER));
+        assert!(estimate_tokens(&truncated, "sonnet") <= 100);
+    }
+
+    #[test]
+    fn truncate_text_returns_short_text_unchanged() {
+        assert_eq!(truncate_text("short", 100, "sonnet"), "short");
+    }
+
+    #[test]
+    fn truncate_diff_returns_small_diff_unchanged() {
+        let diff = sample_diff(1, 2, 2);
+        assert_eq!(truncate_diff(&diff, 10_000, "sonnet"), diff);
+    }
+
+    #[test]
+    fn truncate_diff_keeps_all_file_headers() {
+        let diff = sample_diff(3, 5, 20);
+        let truncated = truncate_diff(&diff, 600, "sonnet");
+
+        for f in 0..3 {
+            assert!(truncated.contains(&format!("diff --git a/file{f}.rs b/file{f}.rs")));
+        }
+        assert!(truncated.contains("hunks omitted"));
+        assert!(truncated.len() < diff.len());
+    }
+
+    #[test]
+    fn chunk_diff_keeps_files_whole() {
+        let diff = sample_diff(4, 2, 20);
+        let chunks = chunk_diff(&diff, 450, "sonnet");
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), diff);
+        for chunk in &chunks {
+            assert!(chunk.starts_with("diff --git"));
+            assert!(estimate_tokens(chunk, "sonnet") <= 450);
+        }
+        assert_eq!(chunk_diff(&diff, 100_000, "sonnet"), vec![diff]);
+    }
+
+    #[test]
+    fn truncate_diff_prefers_first_and_last_hunks() {
+        let diff = sample_diff(1, 5, 20);
+        let truncated = truncate_diff(&diff, 300, "sonnet");
+
+        assert!(truncated.contains("hunk 0 line 0"));
+        assert!(truncated.contains("hunk 4 line 0"));
+        assert!(!truncated.contains("hunk 2 line 0"));
+    }
+
+    #[test]
+    fn truncate_diff_falls_back_when_headers_too_large() {
+        let diff = sample_diff(50, 1, 1);
+        let truncated = truncate_diff(&diff, 50, "sonnet");
+        assert!(truncated.contains(TRUNCATION_MARKER));
+        assert!(estimate_tokens(&truncated, "sonnet") <= 50);
+    }
+
+    #[test]
+    fn estimates_cost_by_model_tier() {
+        let opus = estimate_cost("opus", 1_000_000, 0);
+        let sonnet = estimate_cost("claude-code", 1_000_000, 0);
+        let flash = estimate_cost("gemini-2.5-flash", 1_000_000, 0);
+
+        assert!((opus - 15.0).abs() < 1e-9);
+        assert!((sonnet - 3.0).abs() < 1e-9);
+        assert!(flash < sonnet);
+        assert!((estimate_cost("gemini-cli", 0, 100_000) - 1.0).abs() < 1e-9);
+    }
+}
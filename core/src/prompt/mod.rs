@@ -0,0 +1,11 @@
+//! Prompt construction utilities.
+//!
+//! This module provides the [`PromptTemplates`] registry used by the prompt
+//! builders, so the wording of planning, review, and fix prompts can be
+//! tuned per repository without forking the crate, and the [`budget`]
+//! utilities for keeping prompts inside a model's context window.
+
+pub mod budget;
+pub mod templates;
+
+pub use templates::{PromptTemplate, PromptTemplates, TemplateVars};
@@ -0,0 +1,637 @@
+//! Prompt template registry.
+//!
+//! Templates use a small Handlebars-compatible subset:
+//!
+//! - `{{name}}` substitutes a variable (missing variables render empty).
+//! - `{{#if name}}...{{else}}...{{/if}}` includes a block when the variable
+//!   is set and non-empty. Blocks cannot be nested.
+//!
+//! Built-in templates are always available. Files named `<template>.md` in
+//! `.improbability-drive/prompts/` override (or add to) the built-ins.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Result;
+
+/// Variables available to a template during rendering.
+pub type TemplateVars<'a> = HashMap<&'a str, String>;
+
+/// Directory (relative to the repository root) holding prompt overrides.
+pub const PROMPTS_DIR: &str = ".improbability-drive/prompts";
+
+/// A named prompt template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    /// Template name (e.g., "review").
+    name: String,
+    /// Raw template source.
+    source: String,
+}
+
+impl PromptTemplate {
+    /// Creates a new template from its source.
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Returns the template name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the raw template source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Renders the template with the given variables.
+    pub fn render(&self, vars: &TemplateVars) -> String {
+        render_source(&self.source, vars)
+    }
+}
+
+/// Registry of prompt templates, keyed by name.
+#[derive(Debug, Clone)]
+pub struct PromptTemplates {
+    templates: HashMap<String, PromptTemplate>,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PromptTemplates {
+    /// Plan generation and refinement prompt.
+    pub const PLAN: &'static str = "plan";
+    /// Plan review prompt.
+    pub const PLAN_REVIEW: &'static str = "plan-review";
+    /// Code review prompt.
+    pub const REVIEW: &'static str = "review";
+    /// Fix prompt built from review suggestions.
+    pub const FIX: &'static str = "fix";
+    /// Merge conflict resolution prompt.
+    pub const CONFLICT: &'static str = "conflict";
+    /// GitHub-mode review prompt, answered with a PR comment.
+    pub const GITHUB_REVIEW: &'static str = "github-review";
+    /// Security review prompt answered with structured findings.
+    pub const SECURITY_REVIEW: &'static str = "security-review";
+    /// Check that a fix commit addressed a review comment.
+    pub const VERIFY_FIX: &'static str = "verify-fix";
+    /// Fix prompt for a failed local verification command.
+    pub const FIX_BUILD: &'static str = "fix-build";
+    /// Test-first prompt asking for failing tests before implementation.
+    pub const WRITE_TESTS: &'static str = "write-tests";
+
+    /// Creates a registry containing only the built-in templates.
+    pub fn builtin() -> Self {
+        let templates = [
+            (Self::PLAN, BUILTIN_PLAN),
+            (Self::PLAN_REVIEW, BUILTIN_PLAN_REVIEW),
+            (Self::REVIEW, BUILTIN_REVIEW),
+            (Self::FIX, BUILTIN_FIX),
+            (Self::CONFLICT, BUILTIN_CONFLICT),
+            (Self::GITHUB_REVIEW, BUILTIN_GITHUB_REVIEW),
+            (Self::SECURITY_REVIEW, BUILTIN_SECURITY_REVIEW),
+            (Self::VERIFY_FIX, BUILTIN_VERIFY_FIX),
+            (Self::FIX_BUILD, BUILTIN_FIX_BUILD),
+            (Self::WRITE_TESTS, BUILTIN_WRITE_TESTS),
+        ]
+        .into_iter()
+        .map(|(name, source)| (name.to_string(), PromptTemplate::new(name, source)))
+        .collect();
+
+        Self { templates }
+    }
+
+    /// Creates a registry for a repository.
+    ///
+    /// Starts from the built-ins and applies any overrides found in
+    /// `<repo>/.improbability-drive/prompts/`.
+    pub fn for_repo(repo_path: &Path) -> Result<Self> {
+        let mut templates = Self::builtin();
+        let dir = repo_path.join(PROMPTS_DIR);
+        if dir.is_dir() {
+            let loaded = templates.load_dir(&dir)?;
+            tracing::debug!(dir = ?dir, loaded, "loaded prompt template overrides");
+        }
+        Ok(templates)
+    }
+
+    /// Loads every `*.md` file in `dir` as a template named after its file stem.
+    ///
+    /// Returns the number of templates loaded.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize> {
+        let mut loaded = 0;
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            let source = fs::read_to_string(&path)?;
+            self.insert(PromptTemplate::new(name, source));
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Adds or replaces a template.
+    pub fn insert(&mut self, template: PromptTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    /// Returns the template with the given name.
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Renders the named template.
+    ///
+    /// Unknown template names render as an empty string.
+    pub fn render(&self, name: &str, vars: &TemplateVars) -> String {
+        match self.get(name) {
+            Some(template) => template.render(vars),
+            None => {
+                tracing::warn!(template = %name, "unknown prompt template");
+                String::new()
+            }
+        }
+    }
+}
+
+/// Renders template source, resolving variables and `#if` blocks.
+fn render_source(source: &str, vars: &TemplateVars) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+
+        let Some(end) = after_open.find("}}") else {
+            // Unterminated tag - emit verbatim
+            out.push_str(&rest[start..]);
+            return out;
+        };
+
+        let tag = after_open[..end].trim();
+        let after_tag = &after_open[end + 2..];
+
+        if let Some(name) = tag.strip_prefix("#if ") {
+            let Some(close) = after_tag.find("{{/if}}") else {
+                out.push_str(&rest[start..]);
+                return out;
+            };
+
+            let block = &after_tag[..close];
+            let (then_part, else_part) = match block.find("{{else}}") {
+                Some(idx) => (&block[..idx], &block[idx + 8..]),
+                None => (block, ""),
+            };
+
+            let enabled = vars
+                .get(name.trim())
+                .map(|v| !v.trim().is_empty())
+                .unwrap_or(false);
+            let chosen = if enabled { then_part } else { else_part };
+            out.push_str(&render_source(chosen, vars));
+
+            rest = &after_tag[close + 7..];
+        } else {
+            if let Some(value) = vars.get(tag) {
+                out.push_str(value);
+            }
+            rest = after_tag;
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+const BUILTIN_PLAN: &str = r#"{{#if previous_plan}}## Plan Refinement Request
+
+Refine the plan based on the review feedback.
+{{else}}## Plan Generation Request
+
+Create a dependency-aware implementation plan.
+{{/if}}
+### Original Request
+
+{{user_prompt}}
+
+{{#if previous_plan}}### Current Plan
+
+```json
+{{previous_plan}}
+```
+
+{{/if}}{{#if review_feedback}}### Review Feedback to Address
+
+{{review_feedback}}
+
+{{/if}}### Output Format
+
+Respond with a JSON object:
+```json
+{
+  "title": "Plan title",
+  "overview": "2-3 sentence overview",
+  "tasks": [
+    {
+      "id": "CRUISE-001",
+      "subject": "Task title",
+      "description": "Detailed description",
+      "blocked_by": [],
+      "component": "component-name",
+      "complexity": "low|medium|high",
+      "acceptance_criteria": ["criterion 1", "criterion 2"],
+      "spawn_instances": [
+        {
+          "llm": "claude-code|gemini-cli",
+          "model": null,
+          "cli_params": [],
+          "use_spawn_team": false,
+          "allowed_tools": [],
+          "writable_paths": [],
+          "focus": null
+        }
+      ]
+    }
+  ],
+  "risks": ["risk 1", "risk 2"]
+}
+```
+
+`spawn_instances` is optional; omit it to use the default runner. List
+several instances when a task benefits from parallel work (e.g., one
+implementing and one writing tests), and set `use_spawn_team` for tasks
+that need a second reviewer.
+"#;
+
+const BUILTIN_PLAN_REVIEW: &str = r#"## Plan Review Request
+
+Review the following implementation plan.
+
+### Review Focus
+
+{{focus}}
+
+### Plan to Review
+
+```json
+{{plan_json}}
+```
+
+### Response Format
+
+Respond with a JSON object:
+```json
+{
+  "verdict": "approved" | "needs_changes",
+  "suggestions": [
+    {
+      "category": "security|feasibility|granularity|dependency",
+      "task_id": "CRUISE-001 or null for general",
+      "issue": "Description of the issue",
+      "suggestion": "How to address it",
+      "severity": "nit|low|medium|high|critical"
+    }
+  ],
+  "summary": "Brief summary of review"
+}
+```
+"#;
+
+const BUILTIN_REVIEW: &str = r#"## Code Review Request
+
+Please review the following changes and provide feedback.
+
+### Original Task
+
+{{original_prompt}}
+
+{{#if focus}}### Review Focus
+
+{{focus}}
+
+{{/if}}{{#if conventions}}### Project Conventions
+
+The repository gives these instructions to agents working in it. Flag changes that break them.
+
+{{conventions}}
+
+{{/if}}### Changes Made
+
+```diff
+{{diff}}
+```
+
+### Response Format
+
+Respond with a JSON object:
+```json
+{
+  "verdict": "approved" | "needs_changes",
+  "suggestions": [
+    {
+      "file": "path/to/file",
+      "line": 42,
+      "issue": "description of issue",
+      "suggestion": "how to fix it",
+      "severity": "nit|low|medium|high|critical"
+    }
+  ]
+}
+```
+"#;
+
+const BUILTIN_FIX: &str = r#"## Fix Request
+
+Address the following review feedback:
+
+### Original Task
+
+{{original_prompt}}
+
+### Issues to Fix
+
+{{issues}}"#;
+
+const BUILTIN_CONFLICT: &str = r#"## Merge Conflict Resolution Request
+
+Rebasing `{{branch}}` onto `{{base_ref}}` stopped with merge conflicts.
+Resolve every conflict so the result keeps the intent of both sides.
+
+{{#if context}}### Context
+
+{{context}}
+
+{{/if}}### Conflicted Files
+
+{{conflicts}}
+
+### Instructions
+
+- Edit only the conflicted files listed above.
+- Remove every conflict marker (`<<<<<<<`, `=======`, `>>>>>>>`).
+- Do not stage, commit, or continue the rebase; that is done for you.
+"#;
+
+const BUILTIN_GITHUB_REVIEW: &str = r#"## Pull Request Review Request
+
+Review the pull request {{pr_url}}. Read the changes with `gh pr diff {{pr_url}}`.
+
+### Original Task
+
+{{original_prompt}}
+
+### Posting Your Review
+
+Post exactly one comment with `gh pr comment {{pr_url}} --body-file <file>`.
+Start it with `REVIEW - APPROVED` or `REVIEW - NEEDS CHANGES`, followed by
+your findings for human readers. End the comment with this hidden block,
+filled in with your verdict and findings:
+
+```
+<!-- improbability-drive:review
+{
+  "verdict": "approved" | "needs_changes",
+  "summary": "one-sentence summary",
+  "findings": [
+    {
+      "file": "path/to/file",
+      "line": 42,
+      "issue": "description of issue",
+      "suggestion": "how to fix it",
+      "severity": "nit|low|medium|high|critical"
+    }
+  ]
+}
+-->
+```
+
+The block must be valid JSON. Do not approve or request changes through
+GitHub's review UI.
+"#;
+
+const BUILTIN_SECURITY_REVIEW: &str = r#"## Security Review Request
+
+Review the following changes for security issues only: authentication and
+authorization, secrets handling, injection, input validation, unsafe
+deserialization, and path traversal.
+
+### Original Task
+
+{{original_prompt}}
+
+### Changes Made
+
+```diff
+{{diff}}
+```
+
+### Response Format
+
+Respond with a JSON object. Use an empty `findings` array if there are no
+issues:
+```json
+{
+  "findings": [
+    {
+      "severity": "nit|low|medium|high|critical",
+      "file": "path/to/file",
+      "line": 42,
+      "cwe": "CWE-89",
+      "issue": "description of the vulnerability",
+      "recommendation": "how to fix it"
+    }
+  ]
+}
+```
+"#;
+
+const BUILTIN_VERIFY_FIX: &str = r#"## Fix Verification Request
+
+A reviewer left the comment below, and the diff after it was committed to
+address it. Decide whether the diff actually resolves the comment.
+
+### Review Comment
+
+`{{location}}`: {{issue}}
+
+{{#if suggestion}}Suggested fix: {{suggestion}}
+
+{{/if}}### Resolving Diff
+
+```diff
+{{diff}}
+```
+
+### Response Format
+
+Respond with a JSON object. Only answer `true` if the diff fixes what the
+comment describes, not merely code near it:
+```json
+{
+  "resolved": true | false,
+  "reason": "one sentence explaining the decision"
+}
+```
+"#;
+
+const BUILTIN_FIX_BUILD: &str = r#"## Fix the Build
+
+Your changes for the task below fail local verification, so they have not
+been pushed.
+
+### Task
+
+{{original_prompt}}
+
+### Failed Command
+
+```
+$ {{command}}
+{{output}}
+```
+
+### Instructions
+
+- Fix the cause of the failure; do not skip, silence, or delete checks or tests.
+- Keep the changes made for the task.
+- Do not commit or push; that is done for you once verification passes.
+"#;
+
+const BUILTIN_WRITE_TESTS: &str = r#"## Write Failing Tests
+
+The task below has not been implemented yet. Write the tests that will
+show it is done, before anyone implements it.
+
+{{task}}
+
+### Instructions
+
+- Cover every acceptance criterion with at least one test.
+- Put the tests in test files of their own (such as `tests/` or `*_test.*`),
+  not inside source files, and follow the project's test style.
+- The tests must compile where the language requires it, but fail until the
+  task is implemented.
+- Do not implement the task; add only the stubs the tests need to compile.
+- Do not commit; that is done for you. The implementation must make these
+  tests pass without changing them.
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn vars(pairs: &[(&'static str, &str)]) -> TemplateVars<'static> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn render_substitutes_variables() {
+        let template = PromptTemplate::new("t", "Hello {{name}}, welcome to {{ place }}.");
+        let out = template.render(&vars(&[("name", "Arthur"), ("place", "Magrathea")]));
+        assert_eq!(out, "Hello Arthur, welcome to Magrathea.");
+    }
+
+    #[test]
+    fn render_missing_variable_is_empty() {
+        let template = PromptTemplate::new("t", "[{{missing}}]");
+        assert_eq!(template.render(&TemplateVars::new()), "[]");
+    }
+
+    #[test]
+    fn render_if_else_blocks() {
+        let template =
+            PromptTemplate::new("t", "{{#if plan}}refine {{plan}}{{else}}create{{/if}}!");
+
+        assert_eq!(template.render(&vars(&[("plan", "v1")])), "refine v1!");
+        assert_eq!(template.render(&TemplateVars::new()), "create!");
+        assert_eq!(template.render(&vars(&[("plan", "  ")])), "create!");
+    }
+
+    #[test]
+    fn render_does_not_expand_tags_inside_values() {
+        let template = PromptTemplate::new("t", "{{diff}}");
+        let out = template.render(&vars(&[("diff", "+ let x = \"{{name}}\";")]));
+        assert_eq!(out, "+ let x = \"{{name}}\";");
+    }
+
+    #[test]
+    fn render_leaves_unterminated_tags_verbatim() {
+        let template = PromptTemplate::new("t", "before {{oops");
+        assert_eq!(template.render(&TemplateVars::new()), "before {{oops");
+    }
+
+    #[test]
+    fn builtin_registry_has_all_templates() {
+        let templates = PromptTemplates::builtin();
+
+        for name in [
+            PromptTemplates::PLAN,
+            PromptTemplates::PLAN_REVIEW,
+            PromptTemplates::REVIEW,
+            PromptTemplates::FIX,
+            PromptTemplates::CONFLICT,
+            PromptTemplates::GITHUB_REVIEW,
+            PromptTemplates::SECURITY_REVIEW,
+            PromptTemplates::VERIFY_FIX,
+            PromptTemplates::FIX_BUILD,
+            PromptTemplates::WRITE_TESTS,
+        ] {
+            assert!(templates.get(name).is_some(), "missing template {}", name);
+        }
+    }
+
+    #[test]
+    fn unknown_template_renders_empty() {
+        let templates = PromptTemplates::builtin();
+        assert_eq!(templates.render("nope", &TemplateVars::new()), "");
+    }
+
+    #[test]
+    fn for_repo_applies_overrides() {
+        let repo = TempDir::new().unwrap();
+        let dir = repo.path().join(PROMPTS_DIR);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("review.md"), "Custom review of {{diff}}").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let templates = PromptTemplates::for_repo(repo.path()).unwrap();
+
+        let out = templates.render(PromptTemplates::REVIEW, &vars(&[("diff", "+x")]));
+        assert_eq!(out, "Custom review of +x");
+        assert!(templates.get("notes").is_none());
+        // Non-overridden templates keep their built-in source
+        assert_eq!(
+            templates.get(PromptTemplates::FIX).unwrap().source(),
+            BUILTIN_FIX
+        );
+    }
+
+    #[test]
+    fn for_repo_without_prompts_dir_uses_builtins() {
+        let repo = TempDir::new().unwrap();
+        let templates = PromptTemplates::for_repo(repo.path()).unwrap();
+        assert_eq!(
+            templates.get(PromptTemplates::REVIEW).unwrap().source(),
+            BUILTIN_REVIEW
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Questions from a spawned LLM to a human.
+//!
+//! An LLM that cannot continue without a decision ends its message with a
+//! `QUESTION:` line and stops. The watcher posts the question through an
+//! [`AnswerChannel`], waits for the reply, and resumes the LLM with it in
+//! the same sandbox, continuing the conversation when the runner reports
+//! one.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::cruise::CommentPoller;
+use crate::error::{Error, Result};
+
+/// Marker that starts a question in the LLM's output.
+pub const QUESTION_MARKER: &str = "QUESTION:";
+
+/// Prefix of the PR comment that answers a question.
+pub const ANSWER_PREFIX: &str = "/answer";
+
+/// Default time to wait for an answer.
+const DEFAULT_ANSWER_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Default interval between PR comment polls.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Appended to the prompt when questions can be answered.
+pub const QUESTION_INSTRUCTIONS: &str = "\n\n### Questions\n\n\
+If you cannot continue without a decision only a human can make, end your message with \
+a single line starting with `QUESTION:` followed by the question, and stop. You will be \
+resumed with the answer. Ask only when guessing would likely be wrong.";
+
+/// Returns the question the LLM stopped on, if any.
+///
+/// Only the last message counts: a question the LLM moved past is not
+/// waiting for an answer.
+pub fn parse_question(messages: &[String]) -> Option<String> {
+    let last = messages.iter().rev().find(|m| !m.trim().is_empty())?;
+    let start = last
+        .match_indices(QUESTION_MARKER)
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || last[..i].ends_with('\n'))
+        .last()?;
+    let question = last[start + QUESTION_MARKER.len()..].trim();
+    (!question.is_empty()).then(|| question.to_string())
+}
+
+/// Formats a human's answer for the LLM.
+pub fn answer_prompt(question: &str, answer: &str) -> String {
+    format!(
+        "\n\n### Clarification\n\nYou asked: {}\n\nA human answered: {}\n\n\
+         Continue the task with this answer.",
+        question.trim(),
+        answer.trim()
+    )
+}
+
+/// Where questions are posted and answers come from.
+#[async_trait]
+pub trait AnswerChannel: Send + Sync {
+    /// Posts `question` and waits for the answer. Returns `None` if nobody
+    /// answered in time.
+    async fn ask(&self, question: &str) -> Result<Option<String>>;
+
+    /// Returns the name of this channel.
+    fn name(&self) -> &str;
+}
+
+/// Asks on the terminal and reads the answer from stdin.
+#[derive(Debug, Clone)]
+pub struct CliChannel {
+    timeout: Duration,
+}
+
+impl CliChannel {
+    /// Creates a channel that waits up to 30 minutes for an answer.
+    pub fn new() -> Self {
+        Self {
+            timeout: DEFAULT_ANSWER_TIMEOUT,
+        }
+    }
+
+    /// Sets how long to wait for an answer.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for CliChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AnswerChannel for CliChannel {
+    async fn ask(&self, question: &str) -> Result<Option<String>> {
+        eprintln!("\nThe agent has a question:\n\n  {}\n", question);
+        eprintln!(
+            "Answer (one line, within {} minutes):",
+            self.timeout.as_secs().div_ceil(60)
+        );
+
+        let mut line = String::new();
+        let mut stdin = BufReader::new(tokio::io::stdin());
+        match tokio::time::timeout(self.timeout, stdin.read_line(&mut line)).await {
+            Ok(read) => {
+                read?;
+                let answer = line.trim();
+                Ok((!answer.is_empty()).then(|| answer.to_string()))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "cli"
+    }
+}
+
+/// Posts the question as a PR comment and waits for a reply starting with
+/// [`ANSWER_PREFIX`].
+#[derive(Debug, Clone)]
+pub struct PrCommentChannel {
+    pr_url: String,
+    timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl PrCommentChannel {
+    /// Creates a channel for the PR at `pr_url` that waits up to 30 minutes
+    /// for an answer.
+    pub fn new(pr_url: impl Into<String>) -> Self {
+        Self {
+            pr_url: pr_url.into(),
+            timeout: DEFAULT_ANSWER_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Sets how long to wait for an answer.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how often the PR is polled for the answer.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    fn post(&self, question: &str) -> Result<()> {
+        let body = format!(
+            "**The agent has a question:**\n\n{}\n\n\
+             Reply with a comment starting with `{}` to answer.",
+            question, ANSWER_PREFIX
+        );
+        let args = [
+            "pr",
+            "comment",
+            self.pr_url.as_str(),
+            "--body",
+            body.as_str(),
+        ];
+        let output = Command::new("gh")
+            .args(args)
+            .output()
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AnswerChannel for PrCommentChannel {
+    async fn ask(&self, question: &str) -> Result<Option<String>> {
+        let mut poller = CommentPoller::new();
+        let existing = poller.fetch_comments(&self.pr_url)?;
+        poller.mark_seen(&existing);
+        self.post(question)?;
+        tracing::info!(pr_url = %self.pr_url, "posted agent question, waiting for an answer");
+
+        let deadline = Instant::now() + self.timeout;
+        while Instant::now() < deadline {
+            tokio::time::sleep(
+                self.poll_interval
+                    .min(deadline.saturating_duration_since(Instant::now())),
+            )
+            .await;
+
+            let comments = match poller.fetch_comments(&self.pr_url) {
+                Ok(comments) => comments,
+                Err(e) => {
+                    tracing::warn!(pr_url = %self.pr_url, error = %e, "failed to poll for an answer");
+                    continue;
+                }
+            };
+            let triage = poller.take_new(comments);
+            let answer = [
+                triage.questions,
+                triage.change_requests,
+                triage.nits,
+                triage.other,
+            ]
+            .into_iter()
+            .flatten()
+            .find_map(|comment| parse_answer(&comment.body));
+            if answer.is_some() {
+                return Ok(answer);
+            }
+        }
+        Ok(None)
+    }
+
+    fn name(&self) -> &str {
+        "pr-comment"
+    }
+}
+
+/// Returns the answer in a comment starting with [`ANSWER_PREFIX`].
+fn parse_answer(body: &str) -> Option<String> {
+    let answer = body.trim().strip_prefix(ANSWER_PREFIX)?;
+    if answer.starts_with(|c: char| !c.is_whitespace() && c != ':') {
+        return None;
+    }
+    let answer = answer.trim_start_matches(':').trim();
+    (!answer.is_empty()).then(|| answer.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn question_must_end_the_output() {
+        let asked = vec![
+            "Reading the schema.".to_string(),
+            "Two tables could hold this.\nQUESTION: Should sessions go in `users` or a new table?\n"
+                .to_string(),
+        ];
+        assert_eq!(
+            parse_question(&asked).as_deref(),
+            Some("Should sessions go in `users` or a new table?")
+        );
+
+        let moved_on = vec![
+            "QUESTION: Which table?".to_string(),
+            "I'll use a new table.".to_string(),
+        ];
+        assert!(parse_question(&moved_on).is_none());
+        assert!(parse_question(&["The QUESTION: marker mid-line".to_string()]).is_none());
+        assert!(parse_question(&["QUESTION:  ".to_string()]).is_none());
+    }
+
+    #[test]
+    fn answers_need_the_prefix() {
+        assert_eq!(
+            parse_answer("/answer: use a new table").as_deref(),
+            Some("use a new table")
+        );
+        assert_eq!(
+            parse_answer("  /answer\nUse `sessions`.").as_deref(),
+            Some("Use `sessions`.")
+        );
+        assert!(parse_answer("/answered already").is_none());
+        assert!(parse_answer("/answer").is_none());
+        assert!(parse_answer("Looks good").is_none());
+        assert!(answer_prompt("Which table?", "A new one").contains("A human answered: A new one"));
+    }
+}
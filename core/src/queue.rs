@@ -0,0 +1,489 @@
+//! Spawn queue with a global concurrency cap and priorities.
+//!
+//! Callers that run several spawns, such as the MCP server, share one queue
+//! to keep the number of concurrent LLM processes under a cap (and the
+//! model's rate limits).
+//! Waiting requests start in priority order, first-come first-served within
+//! a priority. With preemption enabled, a request may stop a lower-priority
+//! running spawn, which is restarted once a slot frees up.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::config::{Validate, ValidationResult};
+use crate::error::Result;
+
+/// Priority of a queued spawn.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SpawnPriority {
+    /// Background work.
+    Low,
+    /// Regular spawns.
+    #[default]
+    Normal,
+    /// Interactive or blocking work.
+    High,
+    /// Work that may preempt everything else.
+    Critical,
+}
+
+/// Configuration for the spawn queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueConfig {
+    /// Maximum spawns running at once.
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Whether higher-priority spawns may stop lower-priority running ones.
+    #[serde(default)]
+    pub preemption: bool,
+}
+
+fn default_max_concurrent() -> usize {
+    2
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            preemption: false,
+        }
+    }
+}
+
+impl Validate for QueueConfig {
+    fn validate(&self) -> ValidationResult {
+        let mut result = ValidationResult::default();
+        if self.max_concurrent == 0 {
+            result.add_error("queue.max_concurrent must be at least 1");
+        }
+        result
+    }
+}
+
+/// A spawn in the queue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueEntry {
+    /// Spawn identifier.
+    pub id: String,
+    /// Spawn priority.
+    pub priority: SpawnPriority,
+}
+
+/// Point-in-time view of the queue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    /// Concurrency cap.
+    pub max_concurrent: usize,
+    /// Running spawns, oldest first.
+    pub running: Vec<QueueEntry>,
+    /// Waiting spawns, in the order they will start.
+    pub waiting: Vec<QueueEntry>,
+}
+
+struct Waiter {
+    id: String,
+    priority: SpawnPriority,
+    seq: u64,
+    grant: oneshot::Sender<oneshot::Receiver<()>>,
+}
+
+struct Running {
+    id: String,
+    priority: SpawnPriority,
+    seq: u64,
+    /// Signals the spawn to stop; taken once preemption is requested.
+    preempt: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_seq: u64,
+    waiting: Vec<Waiter>,
+    running: Vec<Running>,
+}
+
+impl QueueState {
+    /// Starts the best waiters while slots are free.
+    fn schedule(&mut self, max_concurrent: usize) {
+        while self.running.len() < max_concurrent {
+            let Some(index) = self.next_waiter() else {
+                break;
+            };
+            let waiter = self.waiting.remove(index);
+            let (preempt_tx, preempt_rx) = oneshot::channel();
+            if waiter.grant.send(preempt_rx).is_ok() {
+                self.running.push(Running {
+                    id: waiter.id,
+                    priority: waiter.priority,
+                    seq: waiter.seq,
+                    preempt: Some(preempt_tx),
+                });
+            }
+        }
+    }
+
+    /// Index of the highest-priority, oldest waiter.
+    fn next_waiter(&self) -> Option<usize> {
+        self.waiting
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, w)| (w.priority, std::cmp::Reverse(w.seq)))
+            .map(|(i, _)| i)
+    }
+
+    /// Asks the lowest-priority, newest running spawn below `priority` to stop.
+    fn preempt_below(&mut self, priority: SpawnPriority) -> Option<String> {
+        let victim = self
+            .running
+            .iter_mut()
+            .filter(|r| r.priority < priority && r.preempt.is_some())
+            .min_by_key(|r| (r.priority, std::cmp::Reverse(r.seq)))?;
+        let _ = victim.preempt.take()?.send(());
+        Some(victim.id.clone())
+    }
+}
+
+/// Queue limiting how many spawns run at once.
+///
+/// Cloning the queue shares its state.
+#[derive(Clone)]
+pub struct SpawnQueue {
+    config: QueueConfig,
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl SpawnQueue {
+    /// Creates a queue with the given configuration.
+    pub fn new(config: QueueConfig) -> Self {
+        Self {
+            config: QueueConfig {
+                max_concurrent: config.max_concurrent.max(1),
+                ..config
+            },
+            state: Arc::new(Mutex::new(QueueState::default())),
+        }
+    }
+
+    /// Runs a spawn once a slot is free.
+    ///
+    /// `make` creates the spawn future. If the spawn is preempted, its future
+    /// is dropped (killing any child process started with `kill_on_drop`)
+    /// and `make` is called again when the spawn gets a new slot. Preempted
+    /// spawns keep their place in the queue.
+    pub async fn run<F, Fut, T>(
+        &self,
+        id: impl Into<String>,
+        priority: SpawnPriority,
+        mut make: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let id = id.into();
+        let seq = {
+            let mut state = self.lock();
+            state.next_seq += 1;
+            state.next_seq
+        };
+
+        loop {
+            let slot = Slot {
+                queue: self.clone(),
+                seq,
+            };
+            let mut preempted = self.acquire(&id, priority, seq).await;
+
+            tokio::select! {
+                result = make() => {
+                    drop(slot);
+                    return result;
+                }
+                Ok(()) = &mut preempted => {
+                    tracing::info!(id = %id, priority = ?priority, "spawn preempted, requeueing");
+                    drop(slot);
+                }
+            }
+        }
+    }
+
+    /// Returns the current queue state.
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let state = self.lock();
+        let entry = |id: &String, priority: SpawnPriority| QueueEntry {
+            id: id.clone(),
+            priority,
+        };
+
+        let mut waiting: Vec<&Waiter> = state.waiting.iter().collect();
+        waiting.sort_by_key(|w| (std::cmp::Reverse(w.priority), w.seq));
+
+        QueueSnapshot {
+            max_concurrent: self.config.max_concurrent,
+            running: state
+                .running
+                .iter()
+                .map(|r| entry(&r.id, r.priority))
+                .collect(),
+            waiting: waiting.iter().map(|w| entry(&w.id, w.priority)).collect(),
+        }
+    }
+
+    /// Waits for a slot. Returns the receiver that fires on preemption.
+    async fn acquire(&self, id: &str, priority: SpawnPriority, seq: u64) -> oneshot::Receiver<()> {
+        let (grant_tx, grant_rx) = oneshot::channel();
+        {
+            let mut state = self.lock();
+            state.waiting.push(Waiter {
+                id: id.to_string(),
+                priority,
+                seq,
+                grant: grant_tx,
+            });
+            state.schedule(self.config.max_concurrent);
+
+            let waiting = state.waiting.iter().any(|w| w.seq == seq);
+            if waiting && self.config.preemption {
+                if let Some(victim) = state.preempt_below(priority) {
+                    tracing::info!(id = %id, victim = %victim, "preempting lower-priority spawn");
+                }
+            }
+            if waiting {
+                tracing::debug!(id = %id, priority = ?priority, "spawn queued");
+            }
+        }
+
+        // The sender lives until this spawn's slot is released
+        grant_rx.await.unwrap_or_else(|_| oneshot::channel().1)
+    }
+
+    /// Removes a spawn from the queue and starts waiters in its place.
+    fn release(&self, seq: u64) {
+        let mut state = self.lock();
+        state.waiting.retain(|w| w.seq != seq);
+        state.running.retain(|r| r.seq != seq);
+        state.schedule(self.config.max_concurrent);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Releases a spawn's place in the queue when dropped, including when the
+/// waiting or running future is cancelled.
+struct Slot {
+    queue: SpawnQueue,
+    seq: u64,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.queue.release(self.seq);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    fn queue(max_concurrent: usize, preemption: bool) -> SpawnQueue {
+        SpawnQueue::new(QueueConfig {
+            max_concurrent,
+            preemption,
+        })
+    }
+
+    /// Waits until the queue has `running` running and `waiting` waiting spawns.
+    async fn wait_for(queue: &SpawnQueue, running: usize, waiting: usize) {
+        for _ in 0..200 {
+            let snapshot = queue.snapshot();
+            if snapshot.running.len() == running && snapshot.waiting.len() == waiting {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("queue never reached {running} running, {waiting} waiting");
+    }
+
+    #[tokio::test]
+    async fn respects_concurrency_cap() {
+        let queue = queue(2, false);
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..6)
+            .map(|i| {
+                let queue = queue.clone();
+                let active = active.clone();
+                let peak = peak.clone();
+                tokio::spawn(async move {
+                    queue
+                        .run(format!("spawn-{i}"), SpawnPriority::Normal, || {
+                            let active = active.clone();
+                            let peak = peak.clone();
+                            async move {
+                                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                                peak.fetch_max(now, Ordering::SeqCst);
+                                tokio::time::sleep(Duration::from_millis(10)).await;
+                                active.fetch_sub(1, Ordering::SeqCst);
+                                Ok(i)
+                            }
+                        })
+                        .await
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert!(queue.snapshot().running.is_empty());
+    }
+
+    #[tokio::test]
+    async fn higher_priority_starts_first() {
+        let queue = queue(1, false);
+        let gate = Arc::new(Notify::new());
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let blocker = {
+            let (queue, gate) = (queue.clone(), gate.clone());
+            tokio::spawn(async move {
+                queue
+                    .run("blocker", SpawnPriority::Normal, || {
+                        let gate = gate.clone();
+                        async move {
+                            gate.notified().await;
+                            Ok(())
+                        }
+                    })
+                    .await
+            })
+        };
+        wait_for(&queue, 1, 0).await;
+
+        let mut handles = Vec::new();
+        for (id, priority) in [("low", SpawnPriority::Low), ("high", SpawnPriority::High)] {
+            let (task_queue, order) = (queue.clone(), order.clone());
+            handles.push(tokio::spawn(async move {
+                task_queue
+                    .run(id, priority, || {
+                        let order = order.clone();
+                        async move {
+                            order.lock().unwrap().push(id);
+                            Ok(())
+                        }
+                    })
+                    .await
+            }));
+            wait_for(&queue, 1, handles.len()).await;
+        }
+
+        let snapshot = queue.snapshot();
+        assert_eq!(snapshot.running[0].id, "blocker");
+        assert_eq!(snapshot.waiting[0].id, "high");
+        assert_eq!(snapshot.waiting[1].id, "low");
+
+        gate.notify_one();
+        blocker.await.unwrap().unwrap();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["high", "low"]);
+    }
+
+    #[tokio::test]
+    async fn preemption_restarts_lower_priority_spawn() {
+        let queue = queue(1, true);
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let low = {
+            let (queue, attempts, order) = (queue.clone(), attempts.clone(), order.clone());
+            tokio::spawn(async move {
+                queue
+                    .run("low", SpawnPriority::Low, || {
+                        let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                        let order = order.clone();
+                        async move {
+                            if attempt == 1 {
+                                // Runs until preempted
+                                std::future::pending::<()>().await;
+                            }
+                            order.lock().unwrap().push("low");
+                            Ok(attempt)
+                        }
+                    })
+                    .await
+            })
+        };
+        wait_for(&queue, 1, 0).await;
+
+        let high = {
+            let (queue, order) = (queue.clone(), order.clone());
+            tokio::spawn(async move {
+                queue
+                    .run("critical", SpawnPriority::Critical, || {
+                        let order = order.clone();
+                        async move {
+                            order.lock().unwrap().push("critical");
+                            Ok(0)
+                        }
+                    })
+                    .await
+            })
+        };
+
+        high.await.unwrap().unwrap();
+        assert_eq!(low.await.unwrap().unwrap(), 2);
+        assert_eq!(*order.lock().unwrap(), vec!["critical", "low"]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_spawn_frees_its_slot() {
+        let queue = queue(1, false);
+
+        let stuck = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                queue
+                    .run("stuck", SpawnPriority::Normal, || {
+                        std::future::pending::<Result<()>>()
+                    })
+                    .await
+            })
+        };
+        wait_for(&queue, 1, 0).await;
+
+        stuck.abort();
+        let _ = stuck.await;
+
+        let result = queue
+            .run("next", SpawnPriority::Normal, || async { Ok(7) })
+            .await
+            .unwrap();
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn zero_concurrency_is_invalid() {
+        let config = QueueConfig {
+            max_concurrent: 0,
+            preemption: false,
+        };
+        assert!(!config.validate().is_valid());
+        assert!(QueueConfig::default().validate().is_valid());
+    }
+}
@@ -0,0 +1,504 @@
+//! Release notes from merged drive PRs.
+//!
+//! `release-notes <tag>` collects the PRs merged since `tag` that the drive
+//! opened: cruise PRs recorded in the run registry (`CruiseStatus` files
+//! under `.improbability-drive/runs`), and spawn or spawn-team PRs
+//! recognised by their branch name or the drive's description footer. Each
+//! PR is filed under a category taken from its labels, its conventional
+//! commit title, or the first word of the title, and the notes are rendered
+//! as a Markdown changelog section. With `--pr <version>` the section is
+//! added to `CHANGELOG.md` on a `release/<version>` branch and opened as a
+//! release PR.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::branch::BranchNamer;
+use crate::commit::{CommitContext, CommitMessages, CommitType};
+use crate::cruise::CruiseStatus;
+use crate::error::{Error, Result, ResultExt};
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{PRManager, PullRequest, CREATED_BY_FOOTER, REVIEW_ARTIFACT_MARKER};
+
+/// Changelog the release PR adds the notes to.
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// Most merged PRs read from GitHub.
+const MAX_MERGED_PRS: &str = "500";
+
+/// Sections of the release notes, in order, with the types filed under them.
+/// Types not listed go under "Other changes".
+const SECTIONS: &[(&str, &[CommitType])] = &[
+    ("Features", &[CommitType::Feat]),
+    ("Bug fixes", &[CommitType::Fix]),
+    ("Performance", &[CommitType::Perf]),
+    ("Documentation", &[CommitType::Docs]),
+];
+
+/// A merged pull request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergedPr {
+    /// PR number.
+    pub number: u64,
+    /// PR URL.
+    pub url: String,
+    /// PR title.
+    pub title: String,
+    /// PR description.
+    pub body: String,
+    /// Branch the PR merged from.
+    pub head_branch: String,
+    /// Label names.
+    pub labels: Vec<String>,
+    /// When the PR was merged, as reported by GitHub.
+    pub merged_at: String,
+}
+
+impl MergedPr {
+    /// Returns the change type: from a `bug`, `enhancement`, `documentation`
+    /// or `performance` label, else the title's conventional commit type,
+    /// else inferred from the title's first word.
+    pub fn kind(&self) -> CommitType {
+        for label in &self.labels {
+            match label.to_lowercase().as_str() {
+                "bug" | "fix" => return CommitType::Fix,
+                "enhancement" | "feature" => return CommitType::Feat,
+                "documentation" | "docs" => return CommitType::Docs,
+                "performance" | "perf" => return CommitType::Perf,
+                _ => {}
+            }
+        }
+        match conventional_title(&self.title) {
+            Some((kind, _, _)) => kind,
+            None => CommitType::infer(&self.title),
+        }
+    }
+
+    /// Returns the release notes entry for the PR.
+    pub fn entry(&self) -> String {
+        let (scope, summary) = match conventional_title(&self.title) {
+            Some((_, scope, summary)) => (scope, summary),
+            None => (None, self.title.trim()),
+        };
+        let mut chars = summary.chars();
+        let summary = chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default();
+        match scope {
+            Some(scope) => format!(
+                "**{}:** {} ([#{}]({}))",
+                scope, summary, self.number, self.url
+            ),
+            None => format!("{} ([#{}]({}))", summary, self.number, self.url),
+        }
+    }
+
+    /// Returns true if the drive opened the PR: it is in `registry`, its
+    /// branch is one `branches` names, or its description carries the
+    /// drive's footer or review sections.
+    pub fn is_from_drive(&self, registry: &HashSet<String>, branches: &BranchNamer) -> bool {
+        registry.contains(&self.url)
+            || branches.is_drive_branch(&self.head_branch)
+            || self.body.contains(CREATED_BY_FOOTER)
+            || self.body.contains(REVIEW_ARTIFACT_MARKER)
+    }
+}
+
+/// Splits a conventional commit title (`type(scope)!: summary`) into its
+/// type, scope and summary. Returns `None` for other titles.
+fn conventional_title(title: &str) -> Option<(CommitType, Option<&str>, &str)> {
+    let (prefix, summary) = title.split_once(':')?;
+    let prefix = prefix.trim_end_matches('!');
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+    let kind = match kind {
+        "feat" => CommitType::Feat,
+        "fix" => CommitType::Fix,
+        "docs" => CommitType::Docs,
+        "test" => CommitType::Test,
+        "refactor" => CommitType::Refactor,
+        "perf" => CommitType::Perf,
+        "build" => CommitType::Build,
+        "ci" => CommitType::Ci,
+        "chore" => CommitType::Chore,
+        _ => return None,
+    };
+    Some((kind, scope.filter(|s| !s.is_empty()), summary.trim()))
+}
+
+/// Parses the output of `gh pr list --json` with the fields
+/// [`ReleaseNotesGenerator`] asks for.
+pub fn parse_merged_prs(json: &str) -> Result<Vec<MergedPr>> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::json("gh output", e))?;
+    let prs = json
+        .as_array()
+        .ok_or_else(|| Error::GitHub("expected a list of pull requests".to_string()))?;
+
+    Ok(prs
+        .iter()
+        .filter_map(|pr| {
+            let text = |key: &str| pr[key].as_str().unwrap_or_default().to_string();
+            Some(MergedPr {
+                number: pr["number"].as_u64()?,
+                url: text("url"),
+                title: text("title"),
+                body: text("body"),
+                head_branch: text("headRefName"),
+                labels: pr["labels"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|label| label["name"].as_str())
+                    .map(String::from)
+                    .collect(),
+                merged_at: text("mergedAt"),
+            })
+        })
+        .collect())
+}
+
+/// Categorized notes for the drive PRs merged since a tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    /// Tag the notes start from.
+    pub since: String,
+    /// Merged PRs, oldest first.
+    pub prs: Vec<MergedPr>,
+}
+
+impl ReleaseNotes {
+    /// Creates notes for `prs` merged since `since`, ordering them by when
+    /// they were merged.
+    pub fn new(since: impl Into<String>, mut prs: Vec<MergedPr>) -> Self {
+        prs.sort_by(|a, b| a.merged_at.cmp(&b.merged_at));
+        Self {
+            since: since.into(),
+            prs,
+        }
+    }
+
+    /// Returns whether no PRs were merged.
+    pub fn is_empty(&self) -> bool {
+        self.prs.is_empty()
+    }
+
+    /// Formats the notes as a Markdown changelog section headed `version`,
+    /// or "Unreleased" without one.
+    pub fn render(&self, version: Option<&str>) -> String {
+        let mut out = format!("## {}\n\n", version.unwrap_or("Unreleased"));
+        if self.prs.is_empty() {
+            out.push_str(&format!("No changes merged since `{}`.\n", self.since));
+            return out;
+        }
+        out.push_str(&format!("Changes merged since `{}`.\n", self.since));
+
+        let listed: Vec<CommitType> = SECTIONS
+            .iter()
+            .flat_map(|(_, kinds)| kinds.iter().copied())
+            .collect();
+        let mut sections: Vec<(&str, Vec<&MergedPr>)> = SECTIONS
+            .iter()
+            .map(|(heading, kinds)| {
+                let prs = self
+                    .prs
+                    .iter()
+                    .filter(|pr| kinds.contains(&pr.kind()))
+                    .collect();
+                (*heading, prs)
+            })
+            .collect();
+        sections.push((
+            "Other changes",
+            self.prs
+                .iter()
+                .filter(|pr| !listed.contains(&pr.kind()))
+                .collect(),
+        ));
+
+        for (heading, prs) in sections.iter().filter(|(_, prs)| !prs.is_empty()) {
+            out.push_str(&format!("\n### {}\n\n", heading));
+            for pr in prs {
+                out.push_str(&format!("- {}\n", pr.entry()));
+            }
+        }
+        out
+    }
+}
+
+/// Adds `section` to a changelog, above its newest release section. An
+/// empty changelog gets a title first.
+pub fn add_to_changelog(changelog: &str, section: &str) -> String {
+    if changelog.trim().is_empty() {
+        return format!("# Changelog\n\n{}", section);
+    }
+    let start = if changelog.starts_with("## ") {
+        Some(0)
+    } else {
+        changelog.find("\n## ").map(|i| i + 1)
+    };
+    match start {
+        Some(i) => format!("{}{}\n{}", &changelog[..i], section, &changelog[i..]),
+        None => format!("{}\n\n{}", changelog.trim_end(), section),
+    }
+}
+
+/// Collects release notes and opens release PRs.
+pub struct ReleaseNotesGenerator {
+    repo_path: PathBuf,
+    branches: BranchNamer,
+    messages: CommitMessages,
+    git: Arc<dyn GitExecutor>,
+}
+
+impl ReleaseNotesGenerator {
+    /// Creates a generator for the repository at `repo_path`.
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            branches: BranchNamer::new(),
+            messages: CommitMessages::default(),
+            git: Arc::new(SystemGit),
+        }
+    }
+
+    /// Recognises drive PRs by the branches `branches` names.
+    pub fn with_branches(mut self, branches: BranchNamer) -> Self {
+        self.branches = branches;
+        self
+    }
+
+    /// Sets how the release commit is written and signed.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    /// Runs git through `git` instead of the system binary.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Collects the drive PRs merged since `tag`.
+    pub fn generate(&self, tag: &str) -> Result<ReleaseNotes> {
+        let since = self
+            .git
+            .run(&self.repo_path, &["log", "-1", "--format=%cI", tag])
+            .context(format!("failed to read the date of {}", tag))?;
+        let prs = self.merged_since(since.trim())?;
+        Ok(self.select(tag, prs))
+    }
+
+    /// Keeps the PRs in `prs` the drive opened.
+    pub fn select(&self, tag: &str, prs: Vec<MergedPr>) -> ReleaseNotes {
+        let registry: HashSet<String> = match CruiseStatus::all(&self.repo_path) {
+            Ok(runs) => runs.into_iter().flat_map(|run| run.pr_urls).collect(),
+            Err(e) => {
+                tracing::warn!(error = %e, "could not read the cruise run registry");
+                HashSet::new()
+            }
+        };
+        let prs = prs
+            .into_iter()
+            .filter(|pr| pr.is_from_drive(&registry, &self.branches))
+            .collect();
+        ReleaseNotes::new(tag, prs)
+    }
+
+    /// Lists the PRs merged after `date` (ISO 8601).
+    fn merged_since(&self, date: &str) -> Result<Vec<MergedPr>> {
+        let search = format!("merged:>{}", date);
+        let args = [
+            "pr",
+            "list",
+            "--state",
+            "merged",
+            "--search",
+            &search,
+            "--limit",
+            MAX_MERGED_PRS,
+            "--json",
+            "number,url,title,body,headRefName,labels,mergedAt",
+        ];
+        let output = Command::new("gh")
+            .current_dir(&self.repo_path)
+            .args(args)
+            .output()
+            .map_err(|e| Error::from(e).context("failed to run gh"))?;
+
+        if !output.status.success() {
+            return Err(Error::gh_command(&args, &output));
+        }
+
+        parse_merged_prs(&String::from_utf8_lossy(&output.stdout))
+            .context("failed to read merged pull requests")
+    }
+
+    /// Adds `notes` to the changelog on a `release/<version>` branch off
+    /// `base` and opens a PR for it.
+    pub fn open_release_pr(
+        &self,
+        notes: &ReleaseNotes,
+        version: &str,
+        base: &str,
+    ) -> Result<PullRequest> {
+        let branch = format!("release/{}", version);
+        let worktree = std::env::temp_dir().join(format!(
+            "improbability-drive-{}",
+            BranchNamer::dir_name(&branch)
+        ));
+        let dir = worktree.to_string_lossy().into_owned();
+        self.git.run(
+            &self.repo_path,
+            &["worktree", "add", "-b", &branch, &dir, base],
+        )?;
+
+        let manager = PRManager::new(self.repo_path.clone())
+            .with_commit_messages(self.messages.clone())
+            .with_git(self.git.clone());
+        let result = self.commit_notes(&worktree, notes, version).and_then(|()| {
+            manager.prepare_for_pr(&worktree, base, &release_context(version))?;
+            self.git
+                .run(&worktree, &["push", "-u", "origin", &branch])?;
+            manager.create_pr(
+                &format!("Release {}", version),
+                &notes.render(Some(version)),
+                &branch,
+                base,
+            )
+        });
+        if let Err(e) = self
+            .git
+            .run(&self.repo_path, &["worktree", "remove", "--force", &dir])
+        {
+            tracing::warn!(error = %e, "could not remove the release worktree");
+        }
+        result
+    }
+
+    /// Writes `notes` into the changelog of `worktree` and commits it.
+    fn commit_notes(&self, worktree: &Path, notes: &ReleaseNotes, version: &str) -> Result<()> {
+        let path = worktree.join(CHANGELOG_FILE);
+        let changelog = std::fs::read_to_string(&path).unwrap_or_default();
+        std::fs::write(
+            &path,
+            add_to_changelog(&changelog, &notes.render(Some(version))),
+        )?;
+
+        let message = self.messages.message(&release_context(version));
+        let args = self.messages.commit_args(&message);
+        self.git.run(worktree, &["add", CHANGELOG_FILE])?;
+        self.git.run(
+            worktree,
+            &args.iter().map(String::as_str).collect::<Vec<_>>(),
+        )?;
+        Ok(())
+    }
+}
+
+/// Describes the release commit for `version`.
+fn release_context(version: &str) -> CommitContext {
+    CommitContext::new(format!("release {}", version)).with_kind(CommitType::Chore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pr(number: u64, title: &str, head: &str, merged_at: &str) -> MergedPr {
+        MergedPr {
+            number,
+            url: format!("https://github.com/acme/widgets/pull/{}", number),
+            title: title.to_string(),
+            body: String::new(),
+            head_branch: head.to_string(),
+            labels: Vec::new(),
+            merged_at: merged_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_gh_pr_list_output() {
+        let json = r#"[{"number": 7, "url": "https://github.com/acme/widgets/pull/7",
+            "title": "fix: crash", "body": "", "headRefName": "wip/crash-1",
+            "labels": [{"name": "bug"}], "mergedAt": "2026-03-01T10:00:00Z"}]"#;
+
+        let prs = parse_merged_prs(json).unwrap();
+
+        assert_eq!(prs[0].number, 7);
+        assert_eq!(prs[0].labels, ["bug"]);
+        assert_eq!(prs[0].head_branch, "wip/crash-1");
+        assert!(parse_merged_prs("{}").is_err());
+    }
+
+    #[test]
+    fn drive_prs_are_categorized_into_notes() {
+        let repo = TempDir::new().unwrap();
+        let mut status = CruiseStatus::new("run-1", "Build an API");
+        status.add_pr("https://github.com/acme/widgets/pull/3");
+        status.save(repo.path()).unwrap();
+        let generator = ReleaseNotesGenerator::new(repo.path());
+
+        let mut labelled = pr(4, "Handle empty input", "spawn-sandbox-1-0", "2026-02-02");
+        labelled.labels = vec!["bug".to_string()];
+        let mut footer = pr(5, "Document the API", "docs-api", "2026-02-03");
+        footer.body = format!("Adds docs.\n\n---\n{}\n", CREATED_BY_FOOTER);
+        let prs = vec![
+            pr(3, "feat(api)!: add login", "main-login", "2026-02-01"),
+            labelled,
+            footer,
+            pr(6, "Bump serde", "cruise/artifacts/CRUISE-002", "2026-02-04"),
+            pr(9, "feat: hand-written", "feature/manual", "2026-01-15"),
+        ];
+
+        let notes = generator.select("v1.0.0", prs);
+
+        assert_eq!(
+            notes.prs.iter().map(|p| p.number).collect::<Vec<_>>(),
+            [3, 4, 5, 6]
+        );
+        assert_eq!(
+            notes.render(Some("v1.1.0")),
+            "## v1.1.0\n\n\
+             Changes merged since `v1.0.0`.\n\n\
+             ### Features\n\n\
+             - **api:** Add login ([#3](https://github.com/acme/widgets/pull/3))\n\n\
+             ### Bug fixes\n\n\
+             - Handle empty input ([#4](https://github.com/acme/widgets/pull/4))\n\n\
+             ### Documentation\n\n\
+             - Document the API ([#5](https://github.com/acme/widgets/pull/5))\n\n\
+             ### Other changes\n\n\
+             - Bump serde ([#6](https://github.com/acme/widgets/pull/6))\n"
+        );
+    }
+
+    #[test]
+    fn notes_go_above_the_newest_release() {
+        let section = "## v1.1.0\n\n- New\n";
+
+        assert_eq!(
+            add_to_changelog(
+                "# Changelog\n\nAll changes.\n\n## v1.0.0\n\n- Old\n",
+                section
+            ),
+            "# Changelog\n\nAll changes.\n\n## v1.1.0\n\n- New\n\n## v1.0.0\n\n- Old\n"
+        );
+        assert_eq!(
+            add_to_changelog("", section),
+            "# Changelog\n\n## v1.1.0\n\n- New\n"
+        );
+        assert_eq!(
+            add_to_changelog("# Changelog\n", section),
+            "# Changelog\n\n## v1.1.0\n\n- New\n"
+        );
+    }
+}
@@ -0,0 +1,869 @@
+//! Run reports.
+//!
+//! At the end of a cruise or spawn-team run, a [`RunReport`] gathers the
+//! outcome, token usage and estimated cost, LLM activity, review history,
+//! commits, and validation results into one Markdown document. It is
+//! written to `report.md` in the run's log directory, optionally alongside
+//! a self-contained `report.html`, and can be posted as a PR comment.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{format_observability_markdown, SpawnObservability};
+use crate::cruise::result::{
+    AdherenceStatus, CombinedObservability, CruisePhase, CruiseResult, FindingSeverity, TaskResult,
+    ValidationResult,
+};
+use crate::cruise::status::format_secs;
+use crate::cruise::task::TaskStatus;
+use crate::error::Result;
+use crate::junit::{TestCase, TestSuite};
+use crate::monitor::{CommitInfo, ProgressSummary};
+use crate::pr::{truncate_body, PRManager, PullRequest, MAX_BODY_BYTES};
+use crate::prompt::budget;
+use crate::runner::TokenUsage;
+use crate::team::{ReviewResult, ReviewVerdict, SpawnTeamResult};
+
+/// Name of the Markdown report in a run's log directory.
+pub const REPORT_FILE: &str = "report.md";
+
+/// Name of the HTML report in a run's log directory.
+pub const REPORT_HTML_FILE: &str = "report.html";
+
+/// What to produce at the end of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportConfig {
+    /// Writes `report.md` to the run's log directory.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Also writes a self-contained `report.html`.
+    #[serde(default)]
+    pub html: bool,
+    /// Posts the report as a comment on the run's PR.
+    #[serde(default)]
+    pub post_to_pr: bool,
+    /// Uploads a report too long for a PR comment to a secret gist and
+    /// links it from the truncated comment.
+    #[serde(default)]
+    pub gist: bool,
+    /// Also writes the outcome as JUnit XML (`junit.xml`, with a
+    /// `summary.md`) for CI test reporting.
+    #[serde(default)]
+    pub junit: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            html: false,
+            post_to_pr: false,
+            gist: false,
+            junit: false,
+        }
+    }
+}
+
+/// Everything known about a finished run, for the report.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Kind of run, e.g. `cruise` or `spawn-team`.
+    pub kind: String,
+    /// Run or spawn ID; names the log directory.
+    pub run_id: String,
+    /// The prompt the run was given.
+    pub prompt: String,
+    /// Whether the run succeeded.
+    pub success: bool,
+    /// Wall-clock duration.
+    pub duration: Duration,
+    /// Summary message.
+    pub summary: String,
+    /// Model used, for the cost estimate.
+    pub model: Option<String>,
+    /// Tokens used across the run.
+    pub usage: TokenUsage,
+    /// Progress of the LLM, if recorded.
+    pub progress: Option<ProgressSummary>,
+    /// Tool calls and permissions, if recorded.
+    pub observability: Option<SpawnObservability>,
+    /// Tool calls and permissions of a cruise run, by phase.
+    pub phases: CombinedObservability,
+    /// Reviews, in the order they were made.
+    pub reviews: Vec<ReviewResult>,
+    /// Commits made by the run.
+    pub commits: Vec<CommitInfo>,
+    /// Cruise task results.
+    pub tasks: Vec<TaskResult>,
+    /// Cruise validation results.
+    pub validation: Option<ValidationResult>,
+    /// PRs opened by the run.
+    pub pr_urls: Vec<String>,
+}
+
+impl RunReport {
+    /// Creates an empty report for a run.
+    pub fn new(
+        kind: impl Into<String>,
+        run_id: impl Into<String>,
+        prompt: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind: kind.into(),
+            run_id: run_id.into(),
+            prompt: prompt.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a report from the result of a spawn-team run.
+    pub fn from_team(
+        run_id: impl Into<String>,
+        prompt: impl Into<String>,
+        result: &SpawnTeamResult,
+    ) -> Self {
+        Self {
+            success: result.success,
+            summary: result.summary.clone(),
+            reviews: result.reviews.clone(),
+            ..Self::new("spawn-team", run_id, prompt)
+        }
+    }
+
+    /// Creates a report from the result of a cruise run.
+    pub fn from_cruise(run_id: impl Into<String>, result: &CruiseResult) -> Self {
+        let mut report = Self {
+            success: result.success,
+            duration: result.total_duration,
+            summary: result.summary.clone(),
+            validation: result.validation_result.clone(),
+            phases: result.combined_observability(),
+            ..Self::new("cruise", run_id, result.prompt.clone())
+        };
+        if let Some(plan) = &result.plan_result {
+            report.pr_urls.extend(plan.pr_url.clone());
+        }
+        if let Some(build) = &result.build_result {
+            report.tasks = build.task_results.clone();
+            report
+                .pr_urls
+                .extend(build.task_results.iter().filter_map(|t| t.pr_url.clone()));
+        }
+        report
+    }
+
+    /// Sets the wall-clock duration.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets the model and the tokens it used.
+    pub fn with_usage(mut self, model: impl Into<String>, usage: TokenUsage) -> Self {
+        self.model = Some(model.into());
+        self.usage = usage;
+        self
+    }
+
+    /// Adds the LLM's activity. Its commits and token usage are added to
+    /// the report's.
+    pub fn with_activity(
+        mut self,
+        progress: ProgressSummary,
+        observability: SpawnObservability,
+    ) -> Self {
+        self.usage.add(&progress.usage);
+        self.commits.extend(progress.commits.iter().cloned());
+        self.progress = Some(progress);
+        self.observability = Some(observability);
+        self
+    }
+
+    /// Adds commits made by the run.
+    pub fn with_commits(mut self, commits: impl IntoIterator<Item = CommitInfo>) -> Self {
+        self.commits.extend(commits);
+        self
+    }
+
+    /// Adds a PR opened by the run.
+    pub fn with_pr_url(mut self, url: impl Into<String>) -> Self {
+        let url = url.into();
+        if !self.pr_urls.contains(&url) {
+            self.pr_urls.push(url);
+        }
+        self
+    }
+
+    /// Returns the estimated cost in USD of the tokens used, if the model
+    /// is known.
+    pub fn estimated_cost(&self) -> Option<f64> {
+        let model = self.model.as_deref()?;
+        Some(budget::estimate_cost(
+            model,
+            (self.usage.input_tokens + self.usage.cache_read_tokens) as usize,
+            self.usage.output_tokens as usize,
+        ))
+    }
+
+    /// Formats the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {} report: {}\n\n", self.kind, self.run_id);
+        out.push_str(&format!(
+            "- **Outcome:** {}\n- **Duration:** {}\n",
+            if self.success { "succeeded" } else { "failed" },
+            format_secs(self.duration.as_secs())
+        ));
+        if !self.prompt.is_empty() {
+            out.push_str(&format!(
+                "- **Prompt:** {}\n",
+                self.prompt.lines().next().unwrap_or_default()
+            ));
+        }
+        out.push('\n');
+        if !self.summary.is_empty() {
+            out.push_str(&format!("{}\n\n", self.summary));
+        }
+
+        self.push_cost(&mut out);
+        if let (Some(progress), Some(observability)) = (&self.progress, &self.observability) {
+            out.push_str("## Activity\n\n");
+            out.push_str(&format_observability_markdown(progress, observability));
+        }
+        self.push_phases(&mut out);
+        self.push_tasks(&mut out);
+        self.push_reviews(&mut out);
+        self.push_commits(&mut out);
+        self.push_validation(&mut out);
+
+        if !self.pr_urls.is_empty() {
+            out.push_str("## Pull requests\n\n");
+            for url in &self.pr_urls {
+                out.push_str(&format!("- {}\n", url));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Formats the report as a self-contained HTML page.
+    pub fn to_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{} report: {}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape_html(&self.kind),
+            escape_html(&self.run_id),
+            HTML_STYLE,
+            markdown_to_html(&self.to_markdown())
+        )
+    }
+
+    /// Writes the report to `<logs_dir>/<run_id>/` as configured, returning
+    /// the files written. If configured and `pr` is given, also posts the
+    /// report as a comment on it; a failed comment is logged, not returned.
+    pub fn publish(
+        &self,
+        logs_dir: &Path,
+        config: &ReportConfig,
+        pr: Option<(&PRManager, &PullRequest)>,
+    ) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+        if !config.enabled {
+            return Ok(written);
+        }
+
+        let dir = logs_dir.join(&self.run_id);
+        std::fs::create_dir_all(&dir)?;
+        let markdown = self.to_markdown();
+        let path = dir.join(REPORT_FILE);
+        std::fs::write(&path, &markdown)?;
+        written.push(path);
+        if config.html {
+            let path = dir.join(REPORT_HTML_FILE);
+            std::fs::write(&path, self.to_html())?;
+            written.push(path);
+        }
+        if config.junit {
+            let mut suite = TestSuite::new(&self.kind);
+            suite.push(TestCase::from_report(self));
+            written.extend(suite.write(&dir)?);
+        }
+
+        if let (true, Some((manager, pr))) = (config.post_to_pr, pr) {
+            let full_report = if config.gist && markdown.len() > MAX_BODY_BYTES {
+                let description = format!("{} report: {}", self.kind, self.run_id);
+                match manager.create_gist(&written[0], &description) {
+                    Ok(url) => Some(url),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "failed to upload run report to a gist");
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            let comment = truncate_comment(&markdown, full_report.as_deref());
+            if let Err(e) = manager.comment(pr, &comment) {
+                tracing::warn!(pr = pr.number, error = %e, "failed to post run report");
+            }
+        }
+
+        tracing::info!(run_id = %self.run_id, dir = %dir.display(), "wrote run report");
+        Ok(written)
+    }
+
+    fn push_cost(&self, out: &mut String) {
+        if self.usage.total() == 0 {
+            return;
+        }
+        out.push_str("## Cost\n\n");
+        if let Some(model) = &self.model {
+            out.push_str(&format!("- **Model:** {}\n", model));
+        }
+        out.push_str(&format!(
+            "- **Tokens:** {} in, {} out, {} cached\n",
+            self.usage.input_tokens, self.usage.output_tokens, self.usage.cache_read_tokens
+        ));
+        if let Some(cost) = self.estimated_cost() {
+            out.push_str(&format!("- **Estimated cost:** ${:.2}\n", cost));
+        }
+        out.push('\n');
+    }
+
+    fn push_phases(&self, out: &mut String) {
+        if self.phases.is_empty() {
+            return;
+        }
+        out.push_str("## Activity by phase\n\n");
+        out.push_str("| Phase | Tool calls | Permissions requested | Granted |\n");
+        out.push_str("|-------|------------|-----------------------|---------|\n");
+        for phase in CruisePhase::ALL {
+            if !self.phases.records.iter().any(|r| r.phase == phase) {
+                continue;
+            }
+            let observability = self.phases.phase(phase);
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                phase.as_str(),
+                observability.tool_calls.len(),
+                observability.permissions_requested.len(),
+                observability.permissions_granted.len()
+            ));
+        }
+        out.push('\n');
+    }
+
+    fn push_tasks(&self, out: &mut String) {
+        if self.tasks.is_empty() {
+            return;
+        }
+        out.push_str(
+            "## Tasks\n\n| Task | Status | Duration | PR |\n|------|--------|----------|----|\n",
+        );
+        for task in &self.tasks {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                table_cell(&task.task_id),
+                task_status(task.status),
+                format_secs(task.duration.as_secs()),
+                task.pr_url.as_deref().unwrap_or("-")
+            ));
+        }
+        out.push('\n');
+        for task in self.tasks.iter().filter(|t| t.error.is_some()) {
+            out.push_str(&format!(
+                "- **{}:** {}\n",
+                task.task_id,
+                task.error.as_deref().unwrap_or_default()
+            ));
+        }
+        if self.tasks.iter().any(|t| t.error.is_some()) {
+            out.push('\n');
+        }
+    }
+
+    fn push_reviews(&self, out: &mut String) {
+        if self.reviews.is_empty() {
+            return;
+        }
+        out.push_str("## Reviews\n\n");
+        for (round, review) in self.reviews.iter().enumerate() {
+            let verdict = match review.verdict {
+                ReviewVerdict::Approved => "approved",
+                ReviewVerdict::NeedsChanges => "needs changes",
+                ReviewVerdict::Failed => "failed",
+            };
+            out.push_str(&format!("### Round {}: {}\n\n", round + 1, verdict));
+            if !review.summary.is_empty() {
+                out.push_str(&format!("{}\n\n", review.summary));
+            }
+            for suggestion in &review.suggestions {
+                let location = match suggestion.line {
+                    Some(line) => format!("{}:{}", suggestion.file, line),
+                    None => suggestion.file.clone(),
+                };
+                out.push_str(&format!(
+                    "- **{:?}** `{}`: {}\n",
+                    suggestion.severity, location, suggestion.issue
+                ));
+            }
+            if !review.suggestions.is_empty() {
+                out.push('\n');
+            }
+        }
+    }
+
+    fn push_commits(&self, out: &mut String) {
+        if self.commits.is_empty() {
+            return;
+        }
+        out.push_str("## Commits\n\n");
+        for commit in &self.commits {
+            let hash = commit.hash.get(..7).unwrap_or(&commit.hash);
+            out.push_str(&format!(
+                "- `{}` {}\n",
+                hash,
+                commit.message.lines().next().unwrap_or_default()
+            ));
+        }
+        out.push('\n');
+    }
+
+    fn push_validation(&self, out: &mut String) {
+        let Some(validation) = &self.validation else {
+            return;
+        };
+        out.push_str("## Validation\n\n");
+        out.push_str(&format!(
+            "- **Result:** {}\n- **Quality score:** {:.1}/10\n",
+            if validation.success {
+                "passed"
+            } else {
+                "failed"
+            },
+            validation.quality_score
+        ));
+        if !validation.functional_tests.is_empty() {
+            out.push_str(&format!(
+                "- **Functional tests:** {}/{} passed\n",
+                validation.tests_passed(),
+                validation.functional_tests.len()
+            ));
+        }
+        if !validation.adherence_checks.is_empty() {
+            out.push_str(&format!(
+                "- **Plan adherence:** {}/{} tasks fully implemented\n",
+                validation.fully_implemented(),
+                validation.adherence_checks.len()
+            ));
+        }
+        out.push('\n');
+
+        if !validation.findings.is_empty() {
+            out.push_str("| Severity | Category | Finding |\n|----------|----------|---------|\n");
+            for finding in &validation.findings {
+                let severity = match finding.severity {
+                    FindingSeverity::Critical => "critical",
+                    FindingSeverity::Warning => "warning",
+                    FindingSeverity::Info => "info",
+                };
+                out.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    severity,
+                    table_cell(&finding.category),
+                    table_cell(&finding.description)
+                ));
+            }
+            out.push('\n');
+        }
+
+        if !validation.adherence_checks.is_empty() {
+            out.push_str("| Task | Adherence | Notes |\n|------|-----------|-------|\n");
+            for check in &validation.adherence_checks {
+                let status = match check.status {
+                    AdherenceStatus::Implemented => "implemented",
+                    AdherenceStatus::Partial => "partial",
+                    AdherenceStatus::Missing => "missing",
+                    AdherenceStatus::Deviated => "deviated",
+                };
+                out.push_str(&format!(
+                    "| {}: {} | {} | {} |\n",
+                    table_cell(&check.task_id),
+                    table_cell(&check.subject),
+                    status,
+                    table_cell(check.notes.as_deref().unwrap_or_default())
+                ));
+            }
+            out.push('\n');
+        }
+
+        if !validation.follow_ups.is_empty() {
+            out.push_str("Unfinished work was filed as follow-ups:\n\n");
+            for follow_up in &validation.follow_ups {
+                out.push_str(&format!("- {}\n", follow_up));
+            }
+            out.push('\n');
+        }
+
+        if !validation.coverage.is_empty() {
+            out.push_str("| Task | Coverage before | Coverage after | Change |\n|------|-----------------|----------------|--------|\n");
+            for delta in &validation.coverage {
+                out.push_str(&format!(
+                    "| {} | {} | {:.1}% | {} |\n",
+                    table_cell(&delta.task_id),
+                    delta
+                        .before
+                        .map_or_else(|| "-".to_string(), |before| format!("{:.1}%", before)),
+                    delta.after,
+                    delta
+                        .change()
+                        .map_or_else(|| "-".to_string(), |change| format!("{:+.1}", change))
+                ));
+            }
+            out.push('\n');
+        }
+    }
+}
+
+fn task_status(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "pending",
+        TaskStatus::InProgress => "in progress",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Blocked => "blocked",
+        TaskStatus::Skipped => "skipped",
+    }
+}
+
+/// Makes `text` safe to show in a markdown table cell.
+fn table_cell(text: &str) -> String {
+    text.lines().next().unwrap_or_default().replace('|', "\\|")
+}
+
+/// Cuts a report down to fit in a PR comment, pointing to the full report
+/// at `full_report` if it was uploaded, or to the run's logs otherwise.
+fn truncate_comment(markdown: &str, full_report: Option<&str>) -> String {
+    let note = match full_report {
+        Some(url) => format!("Report truncated; see the [full report]({}).", url),
+        None => format!("Report truncated; see `{}` in the run's logs.", REPORT_FILE),
+    };
+    truncate_body(markdown, &note)
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:60em;margin:2em auto;\
+padding:0 1em;line-height:1.5}table{border-collapse:collapse}th,td{border:1px solid #ccc;\
+padding:.25em .5em;text-align:left}code{background:#f4f4f4;padding:0 .2em}";
+
+/// Converts the Markdown subset used by reports to HTML: headings, list
+/// items, tables, paragraphs, code spans, and bold text.
+fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut in_list = false;
+    let mut table_row = None;
+    for line in markdown.lines() {
+        if in_list && !line.starts_with("- ") {
+            html.push_str("</ul>\n");
+            in_list = false;
+        }
+        if table_row.is_some() && !line.starts_with('|') {
+            html.push_str("</table>\n");
+            table_row = None;
+        }
+
+        if let Some(item) = line.strip_prefix("- ") {
+            if !in_list {
+                html.push_str("<ul>\n");
+                in_list = true;
+            }
+            html.push_str(&format!("<li>{}</li>\n", inline_html(item)));
+        } else if line.starts_with('|') {
+            let row = table_row.get_or_insert(0);
+            *row += 1;
+            // The second row separates the header from the body
+            if *row == 2 {
+                continue;
+            }
+            if *row == 1 {
+                html.push_str("<table>\n");
+            }
+            let tag = if *row == 1 { "th" } else { "td" };
+            html.push_str("<tr>");
+            for cell in split_row(line) {
+                html.push_str(&format!("<{}>{}</{}>", tag, inline_html(&cell), tag));
+            }
+            html.push_str("</tr>\n");
+        } else if let Some((level, heading)) = heading(line) {
+            html.push_str(&format!(
+                "<h{}>{}</h{}>\n",
+                level,
+                inline_html(heading),
+                level
+            ));
+        } else if !line.trim().is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", inline_html(line)));
+        }
+    }
+    if in_list {
+        html.push_str("</ul>\n");
+    }
+    if table_row.is_some() {
+        html.push_str("</table>\n");
+    }
+    html
+}
+
+/// Returns the level and text of a `#` heading.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line.get(level..)?.strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text))
+}
+
+/// Splits a table row into cells, honouring `\|` escapes.
+fn split_row(line: &str) -> Vec<String> {
+    let inner = line.trim().trim_start_matches('|');
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+    let mut cells = vec![String::new()];
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                chars.next();
+                cells.last_mut().unwrap().push('|');
+            }
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells.iter().map(|c| c.trim().to_string()).collect()
+}
+
+/// Escapes text and converts `code` spans and `**bold**` text.
+fn inline_html(text: &str) -> String {
+    let mut html = String::new();
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            html.push_str(&format!("<code>{}</code>", escape_html(part)));
+            continue;
+        }
+        for (j, part) in escape_html(part).split("**").enumerate() {
+            if j % 2 == 1 {
+                html.push_str(&format!("<strong>{}</strong>", part));
+            } else {
+                html.push_str(part);
+            }
+        }
+    }
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::ToolCallRecord;
+    use crate::cruise::coverage::CoverageDelta;
+    use crate::cruise::result::{AdherenceCheck, AuditFinding, BuildResult};
+    use crate::team::{ReviewSeverity, ReviewSuggestion};
+    use tempfile::TempDir;
+
+    fn team_report() -> RunReport {
+        let result = SpawnTeamResult {
+            success: true,
+            iterations: 2,
+            final_verdict: Some(ReviewVerdict::Approved),
+            reviews: vec![
+                ReviewResult {
+                    verdict: ReviewVerdict::NeedsChanges,
+                    suggestions: vec![ReviewSuggestion {
+                        file: "src/lib.rs".to_string(),
+                        line: Some(12),
+                        issue: "unwrap on user input".to_string(),
+                        suggestion: "return an error".to_string(),
+                        severity: ReviewSeverity::High,
+                    }],
+                    summary: "One bug".to_string(),
+                },
+                ReviewResult {
+                    verdict: ReviewVerdict::Approved,
+                    suggestions: vec![],
+                    summary: "Looks good".to_string(),
+                },
+            ],
+            summary: "Approved after 2 iterations".to_string(),
+        };
+        RunReport::from_team("team-1", "Add a parser", &result)
+            .with_duration(Duration::from_secs(125))
+            .with_usage(
+                "claude-code",
+                TokenUsage {
+                    input_tokens: 1000,
+                    output_tokens: 200,
+                    cache_read_tokens: 0,
+                },
+            )
+            .with_commits([CommitInfo {
+                hash: "0123456789abcdef".to_string(),
+                message: "Add parser\n\nDetails".to_string(),
+            }])
+    }
+
+    #[test]
+    fn markdown_includes_reviews_commits_and_cost() {
+        let markdown = team_report().to_markdown();
+
+        assert!(markdown.starts_with("# spawn-team report: team-1\n"));
+        assert!(markdown.contains("- **Outcome:** succeeded\n- **Duration:** 2m 05s\n"));
+        assert!(markdown.contains("### Round 1: needs changes"));
+        assert!(markdown.contains("- **High** `src/lib.rs:12`: unwrap on user input"));
+        assert!(markdown.contains("### Round 2: approved"));
+        assert!(markdown.contains("- `0123456` Add parser\n"));
+        assert!(markdown.contains("- **Estimated cost:** $"));
+    }
+
+    #[test]
+    fn cruise_report_lists_tasks_prs_and_validation() {
+        let result = CruiseResult {
+            success: false,
+            prompt: "Build an API".to_string(),
+            plan_result: None,
+            build_result: Some(BuildResult {
+                success: false,
+                task_results: vec![TaskResult {
+                    task_id: "CRUISE-001".to_string(),
+                    status: TaskStatus::Blocked,
+                    pr_url: Some("https://github.com/o/r/pull/3".to_string()),
+                    duration: Duration::from_secs(30),
+                    error: Some("tests failed".to_string()),
+                }],
+                max_parallelism: 1,
+                duration: Duration::from_secs(30),
+                completed_count: 0,
+                blocked_count: 1,
+                observability: vec![SpawnObservability {
+                    tool_calls: vec![ToolCallRecord::now("Bash", "{}")],
+                    ..Default::default()
+                }],
+            }),
+            validation_result: Some(ValidationResult {
+                success: false,
+                functional_tests: vec![],
+                adherence_checks: vec![AdherenceCheck {
+                    task_id: "CRUISE-001".to_string(),
+                    subject: "Auth".to_string(),
+                    status: AdherenceStatus::Partial,
+                    notes: Some("1 commit, but the task did not complete".to_string()),
+                }],
+                findings: vec![AuditFinding {
+                    severity: FindingSeverity::Critical,
+                    category: "security".to_string(),
+                    description: "SQL built with a | in it".to_string(),
+                    file: None,
+                    line: None,
+                    suggestion: None,
+                }],
+                quality_score: 4.0,
+                duration: Duration::from_secs(10),
+                report_file: None,
+                coverage: vec![CoverageDelta {
+                    task_id: "CRUISE-001".to_string(),
+                    before: Some(82.0),
+                    after: 79.5,
+                }],
+                observability: Vec::new(),
+                follow_ups: vec![".beads/CRUISE-001-followup.md".to_string()],
+            }),
+            docs_result: None,
+            total_duration: Duration::from_secs(60),
+            summary: String::new(),
+        };
+
+        let markdown = RunReport::from_cruise("run-1", &result).to_markdown();
+
+        assert!(markdown.contains("| CRUISE-001 | blocked | 30s | https://github.com/o/r/pull/3 |"));
+        assert!(markdown.contains("- **CRUISE-001:** tests failed"));
+        assert!(markdown.contains("| critical | security | SQL built with a \\| in it |"));
+        assert!(markdown.contains("| CRUISE-001 | 82.0% | 79.5% | -2.5 |"));
+        assert!(markdown.contains("- **Plan adherence:** 0/1 tasks fully implemented"));
+        assert!(markdown.contains("follow-ups:\n\n- .beads/CRUISE-001-followup.md\n"));
+        assert!(markdown
+            .contains("| CRUISE-001: Auth | partial | 1 commit, but the task did not complete |"));
+        assert!(markdown.contains("| build | 1 | 0 | 0 |\n"));
+        assert!(!markdown.contains("| plan |"));
+        assert!(markdown.contains("## Pull requests\n\n- https://github.com/o/r/pull/3\n"));
+        assert!(!markdown.contains("## Cost"));
+    }
+
+    #[test]
+    fn html_is_self_contained() {
+        let html = team_report().to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>spawn-team report: team-1</h1>"));
+        assert!(html.contains("<li><strong>High</strong> <code>src/lib.rs:12</code>"));
+        assert!(!html.contains("<script"));
+        assert_eq!(
+            markdown_to_html("| A | B |\n|---|---|\n| a \\| b | <c> |\n"),
+            "<table>\n<tr><th>A</th><th>B</th></tr>\n<tr><td>a | b</td><td>&lt;c&gt;</td></tr>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn publish_writes_configured_files() {
+        let logs = TempDir::new().unwrap();
+        let report = team_report();
+
+        let written = report
+            .publish(logs.path(), &ReportConfig::default(), None)
+            .unwrap();
+        assert_eq!(written, vec![logs.path().join("team-1").join(REPORT_FILE)]);
+
+        let config = ReportConfig {
+            html: true,
+            ..ReportConfig::default()
+        };
+        let written = report.publish(logs.path(), &config, None).unwrap();
+        assert_eq!(written.len(), 2);
+        assert!(logs.path().join("team-1").join(REPORT_HTML_FILE).exists());
+
+        let config = ReportConfig {
+            junit: true,
+            ..ReportConfig::default()
+        };
+        let written = report.publish(logs.path(), &config, None).unwrap();
+        assert_eq!(written.len(), 3);
+        assert!(logs.path().join("team-1").join(crate::junit::JUNIT_FILE).exists());
+
+        let disabled = ReportConfig {
+            enabled: false,
+            ..ReportConfig::default()
+        };
+        assert!(report
+            .publish(logs.path(), &disabled, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn truncated_comment_links_full_report() {
+        let report = team_report().to_markdown();
+        assert_eq!(truncate_comment(&report, None), report);
+
+        let long = report.repeat(MAX_BODY_BYTES / report.len() + 1);
+        let comment = truncate_comment(&long, Some("https://gist.github.com/u/abc"));
+        assert!(comment.len() <= MAX_BODY_BYTES);
+        assert!(comment.ends_with("see the [full report](https://gist.github.com/u/abc)._\n"));
+        assert!(truncate_comment(&long, None).contains("`report.md` in the run's logs"));
+    }
+}
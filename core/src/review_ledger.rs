@@ -0,0 +1,168 @@
+//! Per-domain record of what reviewers have already seen.
+//!
+//! With incremental review, a domain that has given a verdict is only sent
+//! the changes made since then. The ledger remembers, per domain, the
+//! snapshot the last verdict was given on and the findings still open
+//! against each reviewed file, so a verdict on the delta can be stitched
+//! together with the earlier one.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::team::{ReviewResult, ReviewSuggestion, ReviewVerdict};
+
+/// What one review domain has seen.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DomainLedger {
+    /// Snapshot commit the last verdict was given on.
+    pub snapshot: Option<String>,
+    /// Reviewed files and the findings still open against them.
+    pub files: BTreeMap<String, Vec<ReviewSuggestion>>,
+}
+
+impl DomainLedger {
+    /// Returns the reviewed files with no open findings.
+    pub fn approved_files(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(_, findings)| findings.is_empty())
+            .map(|(file, _)| file.as_str())
+            .collect()
+    }
+
+    /// Combines a review of the files changed since the last verdict with
+    /// the findings still open on files that did not change.
+    ///
+    /// Open findings on unchanged files were not addressed, so they carry
+    /// over and keep the stitched verdict from being an approval.
+    pub fn stitch(&self, changed: &[String], review: ReviewResult) -> ReviewResult {
+        let carried: Vec<ReviewSuggestion> = self
+            .files
+            .iter()
+            .filter(|(file, _)| !changed.contains(file))
+            .flat_map(|(_, findings)| findings.iter().cloned())
+            .collect();
+        if carried.is_empty() {
+            return review;
+        }
+
+        let verdict = match review.verdict {
+            ReviewVerdict::Approved => ReviewVerdict::NeedsChanges,
+            verdict => verdict,
+        };
+        let mut suggestions = review.suggestions;
+        suggestions.extend(carried);
+        ReviewResult {
+            verdict,
+            suggestions,
+            summary: review.summary,
+        }
+    }
+
+    /// Records a stitched verdict on the `changed` files, given on
+    /// `snapshot`.
+    pub fn record(&mut self, snapshot: String, changed: &[String], review: &ReviewResult) {
+        for file in changed {
+            self.files.insert(file.clone(), Vec::new());
+        }
+        for findings in self.files.values_mut() {
+            findings.clear();
+        }
+        for suggestion in &review.suggestions {
+            self.files
+                .entry(suggestion.file.clone())
+                .or_default()
+                .push(suggestion.clone());
+        }
+        self.snapshot = Some(snapshot);
+    }
+}
+
+/// Ledgers for every review domain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReviewLedger {
+    /// Ledger per review domain.
+    #[serde(default)]
+    pub domains: HashMap<String, DomainLedger>,
+}
+
+impl ReviewLedger {
+    /// Creates an empty ledger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the ledger for `domain`, if it has given a verdict.
+    pub fn domain(&self, domain: &str) -> Option<&DomainLedger> {
+        self.domains.get(domain)
+    }
+
+    /// Returns the ledger for `domain`, creating it if needed.
+    pub fn domain_mut(&mut self, domain: &str) -> &mut DomainLedger {
+        self.domains.entry(domain.to_string()).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::team::ReviewSeverity;
+
+    fn finding(file: &str) -> ReviewSuggestion {
+        ReviewSuggestion {
+            file: file.to_string(),
+            line: None,
+            issue: "Missing check".to_string(),
+            suggestion: "Add it".to_string(),
+            severity: ReviewSeverity::High,
+        }
+    }
+
+    fn review(verdict: ReviewVerdict, suggestions: Vec<ReviewSuggestion>) -> ReviewResult {
+        ReviewResult {
+            verdict,
+            suggestions,
+            summary: String::new(),
+        }
+    }
+
+    #[test]
+    fn stitch_carries_findings_on_unchanged_files() {
+        let mut ledger = DomainLedger::default();
+        let files = vec!["a.rs".to_string(), "b.rs".to_string()];
+        ledger.record(
+            "abc".to_string(),
+            &files,
+            &review(ReviewVerdict::NeedsChanges, vec![finding("b.rs")]),
+        );
+        assert_eq!(ledger.approved_files(), vec!["a.rs"]);
+
+        // The delta only touched a.rs, so b.rs's finding is still open
+        let stitched = ledger.stitch(
+            &["a.rs".to_string()],
+            review(ReviewVerdict::Approved, Vec::new()),
+        );
+
+        assert_eq!(stitched.verdict, ReviewVerdict::NeedsChanges);
+        assert_eq!(stitched.suggestions[0].file, "b.rs");
+    }
+
+    #[test]
+    fn record_clears_findings_on_fixed_files() {
+        let mut ledger = DomainLedger::default();
+        let files = vec!["a.rs".to_string()];
+        ledger.record(
+            "abc".to_string(),
+            &files,
+            &review(ReviewVerdict::NeedsChanges, vec![finding("a.rs")]),
+        );
+
+        let stitched = ledger.stitch(&files, review(ReviewVerdict::Approved, Vec::new()));
+        ledger.record("def".to_string(), &files, &stitched);
+
+        assert_eq!(stitched.verdict, ReviewVerdict::Approved);
+        assert_eq!(ledger.approved_files(), vec!["a.rs"]);
+        assert_eq!(ledger.snapshot.as_deref(), Some("def"));
+    }
+}
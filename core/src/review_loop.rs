@@ -0,0 +1,1033 @@
+//! Per-domain review loop for spawn-team.
+//!
+//! A fix round does not guarantee the reviewer is satisfied. Within one
+//! review domain, the reviewer re-reviews the new diff after every fix
+//! until it approves, its remaining findings are non-blocking, or
+//! `max_iterations` passes have run. Only then does the caller move on to
+//! the next domain.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::{BenchmarkConfig, BenchmarkReport, Measurement, PERFORMANCE_DOMAIN};
+use crate::cancel::CancellationToken;
+use crate::diff::{self, DiffConfig, DiffOptions, DiffProvider, GitDiffProvider};
+use crate::error::Result;
+use crate::instructions;
+use crate::monitor::{BudgetPhase, TimeoutBudget};
+use crate::prompt::{budget, PromptTemplates};
+use crate::review_ledger::ReviewLedger;
+use crate::runner::{run_in_sandbox, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::SandboxManifest;
+use crate::team::{
+    parse_review_response, DomainSelection, FixPromptBuilder, ReviewGating, ReviewPromptBuilder,
+    ReviewResult, ReviewSuggestion, ReviewVerdict, SpawnTeamConfig,
+};
+use crate::timeline::{EventKind, EventLog};
+
+/// One review pass within a domain.
+#[derive(Debug, Clone)]
+pub struct ReviewPass {
+    /// Pass number within the domain (1-indexed).
+    pub iteration: u32,
+    /// The reviewer's result.
+    pub review: ReviewResult,
+    /// Number of findings that blocked approval.
+    pub blocking: usize,
+    /// Whether a fix round ran after this pass.
+    pub fixed: bool,
+}
+
+/// Outcome of reviewing one domain.
+#[derive(Debug, Clone)]
+pub struct DomainReviewOutcome {
+    /// Review domain (e.g., "security").
+    pub domain: String,
+    /// Every review pass, in order.
+    pub passes: Vec<ReviewPass>,
+    /// Whether the domain ended without blocking findings.
+    pub approved: bool,
+    /// Why the domain was not reviewed, if it was skipped.
+    pub skipped: Option<String>,
+}
+
+impl DomainReviewOutcome {
+    /// Returns the review results of all passes.
+    pub fn reviews(&self) -> Vec<ReviewResult> {
+        self.passes.iter().map(|p| p.review.clone()).collect()
+    }
+
+    /// Returns the verdict of the last pass.
+    pub fn final_verdict(&self) -> Option<ReviewVerdict> {
+        self.passes.last().map(|p| p.review.verdict.clone())
+    }
+}
+
+/// Conversations carried across the rounds of review loops.
+///
+/// With session reuse, fix rounds continue the primary LLM's conversation
+/// and re-reviews continue the reviewer's conversation for that domain, so
+/// neither has to re-read the repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseState {
+    /// Primary LLM conversation, continued by fix rounds.
+    #[serde(default)]
+    pub primary_session: Option<String>,
+    /// Reviewer conversation per review domain.
+    #[serde(default)]
+    pub reviewer_sessions: HashMap<String, String>,
+}
+
+/// Runs review and fix rounds for one domain until the reviewer is satisfied.
+pub struct DomainReviewLoop {
+    primary: Arc<dyn LLMRunner>,
+    reviewer: Arc<dyn LLMRunner>,
+    max_iterations: u32,
+    fix_model: Option<String>,
+    review_model: Option<String>,
+    gating: ReviewGating,
+    templates: PromptTemplates,
+    manifest: SandboxManifest,
+    base_ref: String,
+    diff: DiffConfig,
+    domains: DomainSelection,
+    diff_provider: Option<Arc<dyn DiffProvider>>,
+    ledger: Mutex<ReviewLedger>,
+    reuse_sessions: bool,
+    phase: Mutex<PhaseState>,
+    budget: Option<TimeoutBudget>,
+    cancel: CancellationToken,
+    benchmarks: BenchmarkConfig,
+    baseline: Mutex<Option<Vec<Measurement>>>,
+    events: Mutex<EventLog>,
+}
+
+impl DomainReviewLoop {
+    /// Creates a loop using the iteration limit and gating from `config`.
+    pub fn new(
+        primary: Arc<dyn LLMRunner>,
+        reviewer: Arc<dyn LLMRunner>,
+        config: &SpawnTeamConfig,
+    ) -> Self {
+        Self {
+            primary,
+            reviewer,
+            max_iterations: config.max_iterations.max(1),
+            fix_model: config.fix_model().map(String::from),
+            review_model: config.review_model().map(String::from),
+            gating: config.gating.clone(),
+            templates: PromptTemplates::builtin(),
+            manifest: SandboxManifest::default(),
+            base_ref: "HEAD".to_string(),
+            diff: config.diff.clone(),
+            domains: config.review_domains.clone(),
+            diff_provider: None,
+            ledger: Mutex::new(ReviewLedger::new()),
+            reuse_sessions: config.reuse_sessions,
+            phase: Mutex::new(PhaseState::default()),
+            budget: None,
+            cancel: CancellationToken::new(),
+            benchmarks: config.benchmarks.clone(),
+            baseline: Mutex::new(None),
+            events: Mutex::new(EventLog::new()),
+        }
+    }
+
+    /// Appends each review pass and its verdict to `path`, e.g. the spawn's
+    /// [`EVENTS_FILE`](crate::logs::EVENTS_FILE).
+    pub fn with_event_log(self, path: impl Into<PathBuf>) -> Self {
+        *self.events.lock().unwrap() = EventLog::to_file(path);
+        self
+    }
+
+    /// Sets the template registry used for review and fix prompts.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Sets the permissions for fix rounds.
+    pub fn with_manifest(mut self, manifest: SandboxManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Sets the ref the reviewed diff is taken against.
+    pub fn with_base_ref(mut self, base_ref: impl Into<String>) -> Self {
+        self.base_ref = base_ref.into();
+        self
+    }
+
+    /// Replaces the git diff against the base ref with a custom provider.
+    pub fn with_diff_provider(mut self, provider: Arc<dyn DiffProvider>) -> Self {
+        self.diff_provider = Some(provider);
+        self
+    }
+
+    /// Starts from an existing ledger, so incremental domains that already
+    /// gave a verdict only review later changes.
+    pub fn with_ledger(self, ledger: ReviewLedger) -> Self {
+        *self.ledger.lock().unwrap() = ledger;
+        self
+    }
+
+    /// Returns the ledger of what each domain has reviewed.
+    pub fn ledger(&self) -> ReviewLedger {
+        self.ledger.lock().unwrap().clone()
+    }
+
+    /// Starts from existing conversations, e.g. the primary session of the
+    /// run that produced the changes under review.
+    pub fn with_phase_state(self, state: PhaseState) -> Self {
+        *self.phase.lock().unwrap() = state;
+        self
+    }
+
+    /// Returns the conversations the loop has run in so far.
+    pub fn phase_state(&self) -> PhaseState {
+        self.phase.lock().unwrap().clone()
+    }
+
+    /// Bounds each review and fix invocation by its share of `budget`.
+    ///
+    /// An invocation that runs past its deadline is killed. A timed-out
+    /// review ends the loop without approval; a timed-out fix round keeps
+    /// whatever it wrote and is reviewed again.
+    pub fn with_budget(mut self, budget: TimeoutBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Stops the loop with [`Error::Cancelled`](crate::error::Error::Cancelled) when `token` is cancelled.
+    ///
+    /// The running invocation is killed; changes already in the worktree
+    /// are left for the caller to preserve.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Returns the domains that review the changes in `worktree`, routing
+    /// changed files by the configured [`DomainSelection`]. `defaults`
+    /// review the files no route matches.
+    pub fn route(&self, worktree: &Path, defaults: &[String]) -> Result<Vec<String>> {
+        if self.domains.routes.is_empty() {
+            return Ok(self.domains.route(defaults, &[]));
+        }
+        let changed = diff::changed_files(&self.diff(worktree, &self.diff.default)?);
+        let domains = self.domains.route(defaults, &changed);
+        tracing::debug!(files = changed.len(), domains = ?domains, "routed review domains");
+        Ok(domains)
+    }
+
+    /// Returns the kill deadline for an invocation in `phase`, if budgeted.
+    fn deadline(&self, phase: BudgetPhase) -> Option<Duration> {
+        self.budget.as_ref().map(|b| b.invocation_deadline(phase))
+    }
+
+    /// Runs the benchmarks for the performance domain, measuring the base
+    /// ref once and the worktree on every pass.
+    fn benchmark(&self, worktree: &Path, domain: &str) -> Result<Option<BenchmarkReport>> {
+        if domain != PERFORMANCE_DOMAIN || !self.benchmarks.is_enabled() {
+            return Ok(None);
+        }
+
+        let mut baseline = self.baseline.lock().unwrap();
+        if baseline.is_none() {
+            *baseline = Some(
+                self.benchmarks
+                    .run_at(worktree, &self.base_ref, &self.manifest)?,
+            );
+        }
+        let before = baseline.as_deref().unwrap_or_default();
+        Ok(Some(BenchmarkReport::new(
+            before,
+            self.benchmarks.run(worktree, &self.manifest),
+        )))
+    }
+
+    /// Returns the diff to review under `options`.
+    fn diff(&self, worktree: &Path, options: &DiffOptions) -> Result<String> {
+        match &self.diff_provider {
+            Some(provider) => provider.diff(worktree, options),
+            None => GitDiffProvider::new(&self.base_ref).diff(worktree, options),
+        }
+    }
+
+    /// Reviews the diff in chunks, if configured, and merges the results.
+    async fn review_diff(
+        &self,
+        worktree: &Path,
+        original_prompt: &str,
+        domain: &str,
+        focus: Option<&str>,
+        diff: String,
+        options: &DiffOptions,
+    ) -> Result<ReviewResult> {
+        let chunks = match options.max_chunk_tokens {
+            Some(max_tokens) => budget::chunk_diff(&diff, max_tokens, self.reviewer.name()),
+            None => vec![diff],
+        };
+
+        let mut reviews = Vec::new();
+        for chunk in chunks {
+            let review = self
+                .review(worktree, original_prompt, domain, focus, chunk)
+                .await?;
+            let failed = review.verdict == ReviewVerdict::Failed;
+            reviews.push(review);
+            if failed {
+                break;
+            }
+        }
+        Ok(merge_reviews(reviews))
+    }
+
+    /// Reviews the domain's diff, or with incremental review, only the
+    /// changes since the domain's last verdict, stitched together with the
+    /// findings still open from earlier verdicts.
+    async fn review_pass(
+        &self,
+        worktree: &Path,
+        original_prompt: &str,
+        domain: &str,
+        focus: Option<&str>,
+    ) -> Result<ReviewResult> {
+        let mut options = self.diff.options(domain).clone();
+        if !options.incremental {
+            let diff = self.diff(worktree, &options)?;
+            return self
+                .review_diff(worktree, original_prompt, domain, focus, diff, &options)
+                .await;
+        }
+
+        options.since = self
+            .ledger
+            .lock()
+            .unwrap()
+            .domain(domain)
+            .and_then(|d| d.snapshot.clone());
+        let snapshot = diff::snapshot(worktree)?;
+        let delta = self.diff(worktree, &options)?;
+        let changed = diff::changed_files(&delta);
+
+        let review = if options.since.is_some() && changed.is_empty() {
+            ReviewResult {
+                verdict: ReviewVerdict::Approved,
+                suggestions: Vec::new(),
+                summary: "no changes since the last verdict".to_string(),
+            }
+        } else {
+            self.review_diff(worktree, original_prompt, domain, focus, delta, &options)
+                .await?
+        };
+        if review.verdict == ReviewVerdict::Failed {
+            return Ok(review);
+        }
+
+        tracing::debug!(domain = %domain, files = changed.len(), "incremental review");
+        let mut ledger = self.ledger.lock().unwrap();
+        let domain_ledger = ledger.domain_mut(domain);
+        let review = domain_ledger.stitch(&changed, review);
+        domain_ledger.record(snapshot, &changed, &review);
+        Ok(review)
+    }
+
+    /// Reviews one diff chunk.
+    async fn review(
+        &self,
+        worktree: &Path,
+        original_prompt: &str,
+        domain: &str,
+        focus: Option<&str>,
+        diff: String,
+    ) -> Result<ReviewResult> {
+        let mut builder = ReviewPromptBuilder::new(original_prompt)
+            .with_diff(diff)
+            .with_conventions(instructions::summarize(&instructions::detect(worktree)));
+        if let Some(focus) = focus {
+            builder = builder.with_focus(focus);
+        }
+        if let Some(model) = &self.review_model {
+            builder = builder.with_model(model);
+        }
+        let session = self.reuse_sessions.then(|| {
+            let phase = self.phase.lock().unwrap();
+            phase.reviewer_sessions.get(domain).cloned()
+        });
+        let config = LLMSpawnConfig {
+            prompt: builder.build_with(&self.templates),
+            working_dir: worktree.to_path_buf(),
+            manifest: read_only_manifest(),
+            model: self.review_model.clone(),
+            extra_args: Vec::new(),
+            session: session.flatten(),
+        };
+        let run = run_llm(
+            &self.reviewer,
+            config,
+            self.deadline(BudgetPhase::Review),
+            &self.cancel,
+        )
+        .await?;
+
+        if let Some(id) = run.as_ref().and_then(|r| r.session_id.clone()) {
+            if self.reuse_sessions {
+                let mut phase = self.phase.lock().unwrap();
+                phase.reviewer_sessions.insert(domain.to_string(), id);
+            }
+        }
+
+        Ok(match run {
+            Some(run) => parse_review_response(&run.output).unwrap_or_else(|| ReviewResult {
+                verdict: ReviewVerdict::Failed,
+                suggestions: Vec::new(),
+                summary: "reviewer output could not be parsed".to_string(),
+            }),
+            None => ReviewResult {
+                verdict: ReviewVerdict::Failed,
+                suggestions: Vec::new(),
+                summary: "review exceeded its time budget".to_string(),
+            },
+        })
+    }
+
+    /// Reviews the changes in `worktree` for one domain.
+    ///
+    /// `focus` narrows the reviewer to the domain's concerns. A diff split
+    /// into chunks is reviewed chunk by chunk and the results merged into
+    /// one pass. A review that cannot be parsed ends the loop without
+    /// approval. In the performance domain the reviewer is also shown the
+    /// configured benchmarks' numbers, and regressions are added as
+    /// findings. A domain the [`DomainSelection`] skips for the changed
+    /// files is approved without a review.
+    pub async fn run(
+        &self,
+        worktree: &Path,
+        original_prompt: &str,
+        domain: &str,
+        focus: Option<&str>,
+    ) -> Result<DomainReviewOutcome> {
+        let mut outcome = DomainReviewOutcome {
+            domain: domain.to_string(),
+            passes: Vec::new(),
+            approved: false,
+            skipped: None,
+        };
+
+        let changed = if self.domains.checks_paths(domain) {
+            let mut options = self.diff.options(domain).clone();
+            options.since = None;
+            diff::changed_files(&self.diff(worktree, &options)?)
+        } else {
+            Vec::new()
+        };
+        if let Some(reason) = self.domains.skip_reason(domain, &changed) {
+            tracing::info!(domain = %domain, reason = %reason, "skipping review domain");
+            outcome.approved = true;
+            outcome.skipped = Some(reason);
+            return Ok(outcome);
+        }
+
+        for iteration in 1..=self.max_iterations {
+            let benchmarks = self.benchmark(worktree, domain)?;
+            let pass_focus = benchmarks.as_ref().map(|report| {
+                format!(
+                    "{}\n\n### Benchmarks\n\nBase `{}` compared with the changes:\n\n{}",
+                    focus.unwrap_or("Review for performance concerns only."),
+                    self.base_ref,
+                    report.to_markdown()
+                )
+            });
+            self.events.lock().unwrap().record(EventKind::Review {
+                domain: domain.to_string(),
+                iteration,
+                model: self.review_model.clone(),
+            });
+            let mut review = self
+                .review_pass(
+                    worktree,
+                    original_prompt,
+                    domain,
+                    pass_focus.as_deref().or(focus),
+                )
+                .await?;
+            if let Some(report) = &benchmarks {
+                add_findings(&mut review, self.benchmarks.regressions(report));
+            }
+            let gated = self.gating.gate(domain, &review);
+            let last = iteration == self.max_iterations;
+            let fix = gated.needs_fix_round && review.verdict != ReviewVerdict::Failed && !last;
+            self.events.lock().unwrap().record(EventKind::Verdict {
+                domain: domain.to_string(),
+                verdict: review.verdict.clone(),
+                blocking: gated.blocking.len(),
+            });
+
+            tracing::info!(
+                domain = %domain,
+                iteration,
+                verdict = ?review.verdict,
+                blocking = gated.blocking.len(),
+                logged = gated.logged.len(),
+                ignored = gated.ignored,
+                "review pass complete"
+            );
+
+            if review.verdict == ReviewVerdict::Failed {
+                outcome.passes.push(ReviewPass {
+                    iteration,
+                    review,
+                    blocking: 0,
+                    fixed: false,
+                });
+                break;
+            }
+
+            if !gated.needs_fix_round {
+                outcome.approved = true;
+            }
+            outcome.passes.push(ReviewPass {
+                iteration,
+                review,
+                blocking: gated.blocking.len(),
+                fixed: fix,
+            });
+            if !fix {
+                break;
+            }
+
+            let config = LLMSpawnConfig {
+                prompt: FixPromptBuilder::new(original_prompt)
+                    .with_suggestions(gated.blocking)
+                    .build_with(&self.templates),
+                working_dir: worktree.to_path_buf(),
+                manifest: self.manifest.clone(),
+                model: self.fix_model.clone(),
+                extra_args: Vec::new(),
+                session: self
+                    .reuse_sessions
+                    .then(|| self.phase.lock().unwrap().primary_session.clone())
+                    .flatten(),
+            };
+            let deadline = self.deadline(BudgetPhase::Fix);
+            match run_llm(&self.primary, config, deadline, &self.cancel).await? {
+                Some(run) => {
+                    if self.reuse_sessions && run.session_id.is_some() {
+                        self.phase.lock().unwrap().primary_session = run.session_id;
+                    }
+                }
+                None => {
+                    tracing::warn!(domain = %domain, iteration, "fix round exceeded its time budget");
+                }
+            }
+        }
+
+        if !outcome.approved {
+            tracing::warn!(
+                domain = %domain,
+                passes = outcome.passes.len(),
+                "review domain ended without approval"
+            );
+        }
+        Ok(outcome)
+    }
+}
+
+/// Merges the reviews of a chunked diff into one result.
+///
+/// The worst verdict wins and every finding is kept.
+pub(crate) fn merge_reviews(mut reviews: Vec<ReviewResult>) -> ReviewResult {
+    if reviews.len() == 1 {
+        return reviews.remove(0);
+    }
+
+    let verdict = if reviews.iter().any(|r| r.verdict == ReviewVerdict::Failed) {
+        ReviewVerdict::Failed
+    } else if reviews
+        .iter()
+        .any(|r| r.verdict == ReviewVerdict::NeedsChanges)
+    {
+        ReviewVerdict::NeedsChanges
+    } else {
+        ReviewVerdict::Approved
+    };
+    let summary = reviews
+        .iter()
+        .map(|r| r.summary.as_str())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ReviewResult {
+        verdict,
+        suggestions: reviews.into_iter().flat_map(|r| r.suggestions).collect(),
+        summary,
+    }
+}
+
+/// Adds findings the reviewer did not produce itself, such as benchmark
+/// regressions. A failed review is left alone.
+fn add_findings(review: &mut ReviewResult, findings: Vec<ReviewSuggestion>) {
+    if findings.is_empty() || review.verdict == ReviewVerdict::Failed {
+        return;
+    }
+    review.verdict = ReviewVerdict::NeedsChanges;
+    review.suggestions.extend(findings);
+}
+
+/// Manifest for reviewer runs, which only read the worktree.
+fn read_only_manifest() -> SandboxManifest {
+    SandboxManifest {
+        allowed_tools: vec!["Read".to_string(), "Glob".to_string(), "Grep".to_string()],
+        writable_paths: Vec::new(),
+        ..Default::default()
+    }
+}
+
+/// Stdout and conversation of a finished invocation.
+struct LLMRun {
+    output: String,
+    session_id: Option<String>,
+}
+
+/// Runs an LLM with `config` and returns its stdout.
+///
+/// Returns `None` if the LLM is still running at `deadline`, and
+/// [`Error::Cancelled`](crate::error::Error::Cancelled) if `cancel` fires
+/// first. Either way, dropping the invocation kills the child process.
+async fn run_llm(
+    runner: &Arc<dyn LLMRunner>,
+    config: LLMSpawnConfig,
+    deadline: Option<Duration>,
+    cancel: &CancellationToken,
+) -> Result<Option<LLMRun>> {
+    let run = run_in_sandbox(runner.as_ref(), config);
+    let bounded = async {
+        match deadline {
+            Some(deadline) => tokio::time::timeout(deadline, run).await.ok(),
+            None => Some(run.await),
+        }
+    };
+    let run = tokio::select! {
+        finished = bounded => match finished {
+            Some(finished) => finished,
+            None => {
+                tracing::warn!(runner = %runner.name(), ?deadline, "LLM killed at deadline");
+                return Ok(None);
+            }
+        },
+        _ = cancel.cancelled() => return Err(cancel.error()),
+    };
+
+    let run = run?;
+    if !run.result.success {
+        tracing::warn!(runner = %runner.name(), "LLM exited unsuccessfully");
+    }
+    Ok(Some(LLMRun {
+        output: run.output,
+        session_id: run.result.session_id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::runner::{LLMOutput, LLMResult};
+    use async_trait::async_trait;
+    use std::fs;
+    use std::process::Command;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    /// Reviewer that replies with scripted responses, one per call.
+    struct ScriptedReviewer {
+        responses: Mutex<Vec<String>>,
+        prompts: Mutex<Vec<String>>,
+        sessions: Mutex<Vec<Option<String>>>,
+    }
+
+    impl ScriptedReviewer {
+        fn new(responses: &[&str]) -> Arc<Self> {
+            Arc::new(Self {
+                responses: Mutex::new(responses.iter().rev().map(|r| r.to_string()).collect()),
+                prompts: Mutex::new(Vec::new()),
+                sessions: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LLMRunner for ScriptedReviewer {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            self.prompts.lock().unwrap().push(config.prompt);
+            self.sessions.lock().unwrap().push(config.session);
+            let response = self.responses.lock().unwrap().pop().unwrap_or_default();
+            for line in response.lines() {
+                let _ = output_tx.send(LLMOutput::Stdout(line.to_string())).await;
+            }
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: Some("review-session".to_string()),
+            })
+        }
+
+        fn name(&self) -> &str {
+            "reviewer"
+        }
+    }
+
+    /// Fixer that appends a line to `fix.txt` on every call.
+    struct CountingFixer;
+
+    #[async_trait]
+    impl LLMRunner for CountingFixer {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            let path = config.working_dir.join("fix.txt");
+            let mut content = fs::read_to_string(&path).unwrap_or_default();
+            content.push_str("fixed\n");
+            fs::write(path, content)?;
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                // Report a new id so tests can see which session was resumed
+                session_id: Some(format!("{}+fix", config.session.unwrap_or_default())),
+            })
+        }
+
+        fn name(&self) -> &str {
+            "fixer"
+        }
+    }
+
+    /// Runs git in `dir`, returning stdout.
+    fn git(dir: &Path, args: &[&str]) -> Result<String> {
+        let output = Command::new("git").current_dir(dir).args(args).output()?;
+        assert!(output.status.success(), "git {:?} failed", args);
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Helper to create a temp git repo for testing.
+    fn create_temp_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let path = temp_dir.path();
+
+        git(path, &["init"]).unwrap();
+        git(path, &["config", "user.email", "test@test.com"]).unwrap();
+        git(path, &["config", "user.name", "Test User"]).unwrap();
+        fs::write(path.join("README.md"), "# Test Repo\n").unwrap();
+        git(path, &["add", "."]).unwrap();
+        git(path, &["commit", "-m", "Initial commit"]).unwrap();
+
+        temp_dir
+    }
+
+    const NEEDS_CHANGES: &str = r#"{"verdict": "needs_changes", "suggestions": [
+        {"file": "fix.txt", "issue": "Missing fix", "suggestion": "Add it", "severity": "high"}
+    ]}"#;
+    const APPROVED: &str = r#"{"verdict": "approved", "suggestions": []}"#;
+
+    fn review_loop(reviewer: Arc<ScriptedReviewer>, max_iterations: u32) -> DomainReviewLoop {
+        let config = SpawnTeamConfig {
+            max_iterations,
+            ..Default::default()
+        };
+        DomainReviewLoop::new(Arc::new(CountingFixer), reviewer, &config)
+    }
+
+    #[tokio::test]
+    async fn domains_are_skipped_or_forced_by_changed_paths() {
+        let repo = create_temp_git_repo();
+        fs::write(repo.path().join("README.md"), "# Docs only\n").unwrap();
+        let reviewer = ScriptedReviewer::new(&[APPROVED]);
+        let mut config = SpawnTeamConfig::default();
+        config.review_domains.skip = vec!["task_granularity".to_string()];
+        config
+            .review_domains
+            .skip_when_only
+            .insert("security".to_string(), vec!["**/*.md".to_string()]);
+        config
+            .review_domains
+            .relevant_paths
+            .insert("performance".to_string(), vec!["src/**".to_string()]);
+
+        let review_loop = DomainReviewLoop::new(Arc::new(CountingFixer), reviewer.clone(), &config);
+        for domain in ["task_granularity", "security", "performance"] {
+            let outcome = review_loop
+                .run(repo.path(), "Update docs", domain, None)
+                .await
+                .unwrap();
+            assert!(outcome.approved);
+            assert!(outcome.passes.is_empty());
+            assert!(outcome.skipped.is_some(), "{} was reviewed", domain);
+        }
+        assert!(reviewer.prompts.lock().unwrap().is_empty());
+
+        config.review_domains.force = vec!["security".to_string()];
+        let outcome = DomainReviewLoop::new(Arc::new(CountingFixer), reviewer.clone(), &config)
+            .run(repo.path(), "Update docs", "security", None)
+            .await
+            .unwrap();
+        assert_eq!(outcome.passes.len(), 1);
+        assert!(outcome.skipped.is_none());
+    }
+
+    #[tokio::test]
+    async fn re_reviews_until_approved() {
+        let repo = create_temp_git_repo();
+        let reviewer = ScriptedReviewer::new(&[NEEDS_CHANGES, APPROVED]);
+
+        let outcome = review_loop(reviewer.clone(), 3)
+            .run(repo.path(), "Add a fix", "security", Some("Check auth"))
+            .await
+            .unwrap();
+
+        assert!(outcome.approved);
+        assert_eq!(outcome.passes.len(), 2);
+        assert!(outcome.passes[0].fixed);
+        assert_eq!(outcome.passes[0].blocking, 1);
+        assert!(!outcome.passes[1].fixed);
+        assert_eq!(outcome.final_verdict(), Some(ReviewVerdict::Approved));
+
+        // The second review sees the fixer's new file
+        let prompts = reviewer.prompts.lock().unwrap();
+        assert!(prompts[0].contains("Check auth"));
+        assert!(prompts[1].contains("+fixed"));
+    }
+
+    #[tokio::test]
+    async fn stops_at_max_iterations_without_approval() {
+        let repo = create_temp_git_repo();
+        let reviewer = ScriptedReviewer::new(&[NEEDS_CHANGES, NEEDS_CHANGES, NEEDS_CHANGES]);
+
+        let outcome = review_loop(reviewer, 2)
+            .run(repo.path(), "Add a fix", "security", None)
+            .await
+            .unwrap();
+
+        assert!(!outcome.approved);
+        assert_eq!(outcome.passes.len(), 2);
+        assert!(outcome.passes[0].fixed);
+        assert!(!outcome.passes[1].fixed);
+        // Only one fix round ran; the final pass does not trigger another
+        let fixes = fs::read_to_string(repo.path().join("fix.txt")).unwrap();
+        assert_eq!(fixes.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn non_blocking_findings_approve_the_domain() {
+        let repo = create_temp_git_repo();
+        let reviewer = ScriptedReviewer::new(&[r#"{"verdict": "needs_changes", "suggestions": [
+            {"file": "a.rs", "issue": "Spacing", "suggestion": "Reformat", "severity": "nit"}
+        ]}"#]);
+
+        let outcome = review_loop(reviewer, 3)
+            .run(repo.path(), "Add a fix", "general_polish", None)
+            .await
+            .unwrap();
+
+        assert!(outcome.approved);
+        assert_eq!(outcome.passes.len(), 1);
+        assert!(!repo.path().join("fix.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn chunked_diff_is_reviewed_per_chunk_and_merged() {
+        let repo = create_temp_git_repo();
+        fs::write(repo.path().join("a.rs"), "fn a() {}\n".repeat(40)).unwrap();
+        fs::write(repo.path().join("b.rs"), "fn b() {}\n".repeat(40)).unwrap();
+        let reviewer = ScriptedReviewer::new(&[APPROVED, NEEDS_CHANGES, APPROVED]);
+        let mut config = SpawnTeamConfig {
+            max_iterations: 2,
+            ..Default::default()
+        };
+        config.diff.default.max_chunk_tokens = Some(200);
+
+        let outcome = DomainReviewLoop::new(Arc::new(CountingFixer), reviewer.clone(), &config)
+            .run(repo.path(), "Add files", "security", None)
+            .await
+            .unwrap();
+
+        // One chunk needed changes, so the merged first pass blocks
+        assert_eq!(
+            outcome.passes[0].review.verdict,
+            ReviewVerdict::NeedsChanges
+        );
+        assert_eq!(outcome.passes[0].blocking, 1);
+        let prompts = reviewer.prompts.lock().unwrap();
+        assert!(prompts[0].contains("a.rs") && !prompts[0].contains("b.rs"));
+        assert!(prompts[1].contains("b.rs") && !prompts[1].contains("a.rs"));
+    }
+
+    #[tokio::test]
+    async fn incremental_re_review_sees_only_the_delta() {
+        let repo = create_temp_git_repo();
+        fs::write(repo.path().join("a.rs"), "fn a() {}\n").unwrap();
+        let reviewer = ScriptedReviewer::new(&[NEEDS_CHANGES, APPROVED]);
+        let mut config = SpawnTeamConfig {
+            max_iterations: 3,
+            ..Default::default()
+        };
+        config.diff.default.incremental = true;
+        let review_loop = DomainReviewLoop::new(Arc::new(CountingFixer), reviewer.clone(), &config);
+
+        let outcome = review_loop
+            .run(repo.path(), "Add a", "security", None)
+            .await
+            .unwrap();
+
+        assert!(outcome.approved);
+        {
+            let prompts = reviewer.prompts.lock().unwrap();
+            assert!(prompts[0].contains("a.rs"));
+            assert!(prompts[1].contains("fix.txt") && !prompts[1].contains("a.rs"));
+        }
+        let ledger = review_loop.ledger();
+        let files = ledger.domain("security").unwrap().approved_files();
+        assert_eq!(files, vec!["a.rs", "fix.txt"]);
+
+        // Nothing changed since the last verdict, so the reviewer is not asked
+        let outcome = review_loop
+            .run(repo.path(), "Add a", "security", None)
+            .await
+            .unwrap();
+        assert!(outcome.approved);
+        assert_eq!(reviewer.prompts.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reused_sessions_continue_across_rounds() {
+        let repo = create_temp_git_repo();
+        let reviewer = ScriptedReviewer::new(&[NEEDS_CHANGES, APPROVED]);
+        let config = SpawnTeamConfig {
+            reuse_sessions: true,
+            ..Default::default()
+        };
+        let review_loop = DomainReviewLoop::new(Arc::new(CountingFixer), reviewer.clone(), &config)
+            .with_phase_state(PhaseState {
+                primary_session: Some("primary".to_string()),
+                ..Default::default()
+            });
+
+        let outcome = review_loop
+            .run(repo.path(), "Add a fix", "security", None)
+            .await
+            .unwrap();
+
+        assert!(outcome.approved);
+        assert_eq!(
+            *reviewer.sessions.lock().unwrap(),
+            vec![None, Some("review-session".to_string())]
+        );
+        let state = review_loop.phase_state();
+        assert_eq!(state.primary_session.as_deref(), Some("primary+fix"));
+        assert_eq!(state.reviewer_sessions["security"], "review-session");
+    }
+
+    #[tokio::test]
+    async fn sessions_are_not_reused_by_default() {
+        let repo = create_temp_git_repo();
+        let reviewer = ScriptedReviewer::new(&[NEEDS_CHANGES, APPROVED]);
+
+        review_loop(reviewer.clone(), 3)
+            .run(repo.path(), "Add a fix", "security", None)
+            .await
+            .unwrap();
+
+        assert_eq!(*reviewer.sessions.lock().unwrap(), vec![None, None]);
+    }
+
+    /// Runner that never exits.
+    struct HangingRunner;
+
+    #[async_trait]
+    impl LLMRunner for HangingRunner {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            std::future::pending().await
+        }
+
+        fn name(&self) -> &str {
+            "hanging"
+        }
+    }
+
+    #[tokio::test]
+    async fn review_past_deadline_ends_loop() {
+        let repo = create_temp_git_repo();
+        let budget = TimeoutBudget::new(Duration::from_millis(200)).with_review_domains(2);
+
+        let outcome = DomainReviewLoop::new(
+            Arc::new(CountingFixer),
+            Arc::new(HangingRunner),
+            &SpawnTeamConfig::default(),
+        )
+        .with_budget(budget)
+        .run(repo.path(), "Add a fix", "security", None)
+        .await
+        .unwrap();
+
+        assert!(!outcome.approved);
+        assert_eq!(outcome.final_verdict(), Some(ReviewVerdict::Failed));
+        assert!(outcome.passes[0].review.summary.contains("time budget"));
+    }
+
+    #[tokio::test]
+    async fn cancellation_stops_the_loop() {
+        let repo = create_temp_git_repo();
+        let token = CancellationToken::new();
+        let review_loop = DomainReviewLoop::new(
+            Arc::new(CountingFixer),
+            Arc::new(HangingRunner),
+            &SpawnTeamConfig::default(),
+        )
+        .with_cancellation(token.clone());
+
+        let canceller = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            token.cancel("interrupted");
+        };
+        let (result, _) = tokio::join!(
+            review_loop.run(repo.path(), "Add a fix", "security", None),
+            canceller
+        );
+
+        assert!(matches!(result, Err(Error::Cancelled(reason)) if reason == "interrupted"));
+    }
+
+    #[tokio::test]
+    async fn unparseable_review_ends_loop() {
+        let repo = create_temp_git_repo();
+        let reviewer = ScriptedReviewer::new(&["I could not review this."]);
+
+        let outcome = review_loop(reviewer, 3)
+            .run(repo.path(), "Add a fix", "security", None)
+            .await
+            .unwrap();
+
+        assert!(!outcome.approved);
+        assert_eq!(outcome.final_verdict(), Some(ReviewVerdict::Failed));
+    }
+}
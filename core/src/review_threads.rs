@@ -0,0 +1,636 @@
+//! Review comments that stay readable across fix rounds.
+//!
+//! Reviewers restate open findings every round, often in slightly
+//! different words, and each fix would otherwise be announced in a new
+//! top-level comment. [`ReviewThreads`] remembers what has been posted on a
+//! PR, drops findings that repeat an earlier one, and replies to the
+//! original comment when a fix lands, marking its thread resolved, so the
+//! conversation stays in one place.
+//!
+//! A fix commit is not taken at its word: [`FixVerifier`] shows a model the
+//! comment and the resolving diff, and only a confirmed fix resolves the
+//! thread. A finding whose fixes keep failing is escalated to a human.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::PostedComment;
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+use crate::pr::{PRManager, PullRequest};
+use crate::prompt::{PromptTemplates, TemplateVars};
+use crate::runner::{run_in_sandbox, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::SandboxManifest;
+use crate::team::{ReviewResult, ReviewSuggestion, ReviewVerdict};
+
+/// Word overlap above which two findings on the same spot are the same.
+pub const SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// Lines two findings may be apart and still be about the same code.
+const LINE_TOLERANCE: u32 = 3;
+
+/// Failed fixes after which a finding is escalated to a human.
+pub const DEFAULT_MAX_FAILED_FIXES: u32 = 2;
+
+/// Returns the word overlap (Jaccard index) of two texts, from 0 to 1.
+///
+/// Case and punctuation are ignored.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+/// Returns the lowercase words of `text`.
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Returns whether two findings are about the same problem: same file,
+/// nearby lines, and similar wording.
+///
+/// A finding without a line matches any line of its file.
+fn same_finding(a: &PostedComment, b: &PostedComment) -> bool {
+    let near = match (a.line, b.line) {
+        (Some(x), Some(y)) => x.abs_diff(y) <= LINE_TOLERANCE,
+        _ => true,
+    };
+    a.file == b.file && near && similarity(&a.issue, &b.issue) >= SIMILARITY_THRESHOLD
+}
+
+/// Collapses findings that describe the same problem, keeping the most
+/// severe wording of each.
+pub fn dedup_findings(suggestions: &[ReviewSuggestion]) -> Vec<ReviewSuggestion> {
+    let mut kept: Vec<ReviewSuggestion> = Vec::new();
+    for suggestion in suggestions {
+        let candidate = posted(None, suggestion);
+        let existing = kept
+            .iter_mut()
+            .find(|k| same_finding(&candidate, &posted(None, k)));
+        match existing {
+            Some(k) if suggestion.severity > k.severity => *k = suggestion.clone(),
+            Some(_) => {}
+            None => kept.push(suggestion.clone()),
+        }
+    }
+    kept
+}
+
+/// Builds the record of `suggestion` posted as comment `id`.
+fn posted(id: Option<u64>, suggestion: &ReviewSuggestion) -> PostedComment {
+    PostedComment {
+        id,
+        file: suggestion.file.clone(),
+        line: suggestion.line,
+        issue: suggestion.issue.clone(),
+        resolved_by: None,
+        failed_fixes: 0,
+        escalated: false,
+    }
+}
+
+/// Returns `path:line`, or just the path for findings without a line.
+fn location(suggestion: &ReviewSuggestion) -> String {
+    match suggestion.line {
+        Some(line) => format!("{}:{}", suggestion.file, line),
+        None => suggestion.file.clone(),
+    }
+}
+
+/// Formats a finding as a line comment.
+fn format_line_comment(suggestion: &ReviewSuggestion) -> String {
+    let mut body = format!("**{:?}:** {}", suggestion.severity, suggestion.issue);
+    if !suggestion.suggestion.is_empty() {
+        body.push_str(&format!("\n\n{}", suggestion.suggestion));
+    }
+    body
+}
+
+/// Whether a fix addressed a review comment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixCheck {
+    /// Whether the comment is resolved.
+    pub resolved: bool,
+    /// Why the verifier decided so.
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl FixCheck {
+    /// Creates a check that confirms the fix.
+    pub fn resolved(reason: impl Into<String>) -> Self {
+        Self {
+            resolved: true,
+            reason: reason.into(),
+        }
+    }
+
+    /// Creates a check that rejects the fix.
+    pub fn unresolved(reason: impl Into<String>) -> Self {
+        Self {
+            resolved: false,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Parses a verifier response, or `None` if it holds no check JSON.
+pub fn parse_fix_check(output: &str) -> Option<FixCheck> {
+    let start = output.find('{')?;
+    let end = output.rfind('}')?;
+    if start >= end {
+        return None;
+    }
+    serde_json::from_str(&output[start..=end]).ok()
+}
+
+/// Asks a model whether a diff resolves a review comment.
+pub struct FixVerifier {
+    runner: Arc<dyn LLMRunner>,
+    model: Option<String>,
+    templates: PromptTemplates,
+}
+
+impl FixVerifier {
+    /// Creates a verifier using the given runner, e.g. the reviewer's.
+    pub fn new(runner: Arc<dyn LLMRunner>) -> Self {
+        Self {
+            runner,
+            model: None,
+            templates: PromptTemplates::builtin(),
+        }
+    }
+
+    /// Sets the model, e.g. a cheaper one than the reviewer's.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sets the prompt templates.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Builds the verification prompt for `suggestion` and its fix.
+    pub fn build_prompt(&self, suggestion: &ReviewSuggestion, diff: &str) -> String {
+        let mut vars = TemplateVars::new();
+        vars.insert("location", location(suggestion));
+        vars.insert("issue", suggestion.issue.clone());
+        vars.insert("suggestion", suggestion.suggestion.clone());
+        vars.insert("diff", diff.to_string());
+        self.templates.render(PromptTemplates::VERIFY_FIX, &vars)
+    }
+
+    /// Checks whether `diff`, run against `worktree`, resolves
+    /// `suggestion`.
+    ///
+    /// An empty diff never resolves anything, and a response without a
+    /// verdict counts as unresolved.
+    pub async fn verify(
+        &self,
+        worktree: &Path,
+        suggestion: &ReviewSuggestion,
+        diff: &str,
+    ) -> Result<FixCheck> {
+        if diff.trim().is_empty() {
+            return Ok(FixCheck::unresolved("the fix changed nothing"));
+        }
+
+        let config = LLMSpawnConfig {
+            prompt: self.build_prompt(suggestion, diff),
+            working_dir: worktree.to_path_buf(),
+            manifest: SandboxManifest {
+                allowed_tools: vec!["Read".to_string(), "Glob".to_string(), "Grep".to_string()],
+                writable_paths: Vec::new(),
+                ..Default::default()
+            },
+            model: self.model.clone(),
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let run = run_in_sandbox(self.runner.as_ref(), config).await?;
+        if !run.result.success {
+            tracing::warn!(runner = %self.runner.name(), "fix verifier exited unsuccessfully");
+        }
+        Ok(parse_fix_check(&run.output).unwrap_or_else(|| {
+            tracing::warn!(file = %suggestion.file, "fix verifier gave no verdict");
+            FixCheck::unresolved("the verifier gave no verdict")
+        }))
+    }
+
+    /// Checks whether `commit` resolves `suggestion`.
+    pub async fn verify_commit(
+        &self,
+        worktree: &Path,
+        suggestion: &ReviewSuggestion,
+        commit: &str,
+    ) -> Result<FixCheck> {
+        let diff = SystemGit.run(worktree, &["show", "--format=", commit])?;
+        self.verify(worktree, suggestion, &diff).await
+    }
+}
+
+/// What became of a comment's thread after its fix was checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThreadResolution {
+    /// The fix was confirmed and the thread says so.
+    Resolved,
+    /// The fix fell short; another fix round should try again.
+    Retry(String),
+    /// Too many fixes fell short; the thread asks a human to take over.
+    Escalated(String),
+    /// No open comment matches the finding.
+    NoThread,
+}
+
+/// Review comments posted on one PR.
+#[derive(Debug, Clone)]
+pub struct ReviewThreads {
+    /// Comments posted so far, oldest first.
+    posted: Vec<PostedComment>,
+    /// Failed fixes after which a finding is escalated.
+    max_failed_fixes: u32,
+}
+
+impl ReviewThreads {
+    /// Creates a tracker that knows about comments already posted, e.g.
+    /// from an earlier run's [`SpawnObservability`](crate::SpawnObservability).
+    pub fn new(posted: Vec<PostedComment>) -> Self {
+        Self {
+            posted,
+            max_failed_fixes: DEFAULT_MAX_FAILED_FIXES,
+        }
+    }
+
+    /// Sets how many fixes may fail verification before a finding is
+    /// escalated.
+    pub fn with_max_failed_fixes(mut self, max: u32) -> Self {
+        self.max_failed_fixes = max.max(1);
+        self
+    }
+
+    /// Returns the comments posted so far.
+    pub fn posted(&self) -> &[PostedComment] {
+        &self.posted
+    }
+
+    /// Consumes the tracker, returning the comments posted, for
+    /// [`SpawnObservability::posted_comments`](crate::SpawnObservability::posted_comments).
+    pub fn into_posted(self) -> Vec<PostedComment> {
+        self.posted
+    }
+
+    /// Returns the findings that have not been posted yet, with duplicates
+    /// among them collapsed.
+    ///
+    /// A finding repeating a resolved comment counts as new: the fix did
+    /// not hold.
+    pub fn unposted(&self, suggestions: &[ReviewSuggestion]) -> Vec<ReviewSuggestion> {
+        dedup_findings(suggestions)
+            .into_iter()
+            .filter(|s| self.find_thread(s).is_none())
+            .collect()
+    }
+
+    /// Returns the open comment `suggestion` repeats, if any.
+    pub fn find_thread(&self, suggestion: &ReviewSuggestion) -> Option<&PostedComment> {
+        self.open_index(suggestion).map(|index| &self.posted[index])
+    }
+
+    /// Posts the new findings of `review` given on `commit`.
+    ///
+    /// The review summary lists only findings not posted before, and each
+    /// new finding with a line also gets a line comment to thread fixes
+    /// under. A review that needs changes but adds nothing new is not
+    /// posted at all. Returns the number of new findings.
+    pub fn post(
+        &mut self,
+        manager: &PRManager,
+        pr: &PullRequest,
+        commit: &str,
+        review: &ReviewResult,
+    ) -> Result<usize> {
+        let new = self.unposted(&review.suggestions);
+        if new.is_empty() && review.verdict != ReviewVerdict::Approved {
+            tracing::debug!(pr = pr.number, "review repeats posted findings only");
+            return Ok(0);
+        }
+
+        manager.post_review(
+            pr,
+            &ReviewResult {
+                suggestions: new.clone(),
+                ..review.clone()
+            },
+        )?;
+
+        for suggestion in &new {
+            let id = match suggestion.line {
+                Some(line) => Some(manager.post_line_comment(
+                    pr,
+                    commit,
+                    &suggestion.file,
+                    line,
+                    &format_line_comment(suggestion),
+                )?),
+                None => None,
+            };
+            self.posted.push(posted(id, suggestion));
+        }
+        Ok(new.len())
+    }
+
+    /// Records the verified outcome of `commit`'s fix for `suggestion`,
+    /// replying in the thread of the comment that raised it.
+    ///
+    /// A confirmed fix resolves the thread, on GitHub too. A rejected one
+    /// counts against the finding; once [`Self::with_max_failed_fixes`]
+    /// fixes have failed, the thread is handed to a human.
+    pub fn resolve(
+        &mut self,
+        manager: &PRManager,
+        pr: &PullRequest,
+        suggestion: &ReviewSuggestion,
+        commit: &str,
+        check: &FixCheck,
+    ) -> Result<ThreadResolution> {
+        let Some(index) = self.open_index(suggestion) else {
+            return Ok(ThreadResolution::NoThread);
+        };
+
+        let was_escalated = self.posted[index].escalated;
+        let outcome = self.record_check(index, commit, check);
+        let reply = match &outcome {
+            ThreadResolution::Resolved => Some(format!("Fixed in commit {}.", commit)),
+            ThreadResolution::Escalated(reason) if !was_escalated => Some(format!(
+                "Commit {} did not resolve this: {}\n\n\
+                 {} fixes have failed; leaving this for a human.",
+                commit, reason, self.posted[index].failed_fixes
+            )),
+            _ => None,
+        };
+        if let (Some(id), Some(body)) = (self.posted[index].id, reply) {
+            manager.reply_to_comment(pr, id, &body)?;
+        }
+        if let (ThreadResolution::Resolved, Some(id)) = (&outcome, self.posted[index].id) {
+            // The reply already records the fix; a thread left open only
+            // clutters the review UI, so failing to close it is not fatal
+            if let Err(e) = manager.resolve_thread(pr, id) {
+                tracing::warn!(
+                    pr = pr.number,
+                    comment = id,
+                    error = %e.report(),
+                    "failed to resolve review thread"
+                );
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Applies `check` to the comment at `index`.
+    fn record_check(&mut self, index: usize, commit: &str, check: &FixCheck) -> ThreadResolution {
+        let max_failed_fixes = self.max_failed_fixes;
+        let comment = &mut self.posted[index];
+        if check.resolved {
+            comment.resolved_by = Some(commit.to_string());
+            return ThreadResolution::Resolved;
+        }
+
+        comment.failed_fixes += 1;
+        if comment.failed_fixes >= max_failed_fixes {
+            comment.escalated = true;
+            ThreadResolution::Escalated(check.reason.clone())
+        } else {
+            ThreadResolution::Retry(check.reason.clone())
+        }
+    }
+
+    /// Returns the index of the open comment `suggestion` repeats.
+    fn open_index(&self, suggestion: &ReviewSuggestion) -> Option<usize> {
+        let candidate = posted(None, suggestion);
+        self.posted
+            .iter()
+            .position(|c| c.resolved_by.is_none() && same_finding(&candidate, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{LLMOutput, LLMResult};
+    use crate::team::ReviewSeverity;
+    use async_trait::async_trait;
+    use std::process::Command;
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    /// Runner that answers with a fixed response and records prompts.
+    struct ScriptedVerifier {
+        response: String,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedVerifier {
+        fn new(response: &str) -> Arc<Self> {
+            Arc::new(Self {
+                response: response.to_string(),
+                prompts: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LLMRunner for ScriptedVerifier {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            self.prompts.lock().unwrap().push(config.prompt);
+            for line in self.response.lines() {
+                let _ = output_tx.send(LLMOutput::Stdout(line.to_string())).await;
+            }
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "verifier"
+        }
+    }
+
+    fn finding(line: u32, issue: &str, severity: ReviewSeverity) -> ReviewSuggestion {
+        ReviewSuggestion {
+            file: "src/lib.rs".to_string(),
+            line: Some(line),
+            issue: issue.to_string(),
+            suggestion: String::new(),
+            severity,
+        }
+    }
+
+    #[test]
+    fn similarity_ignores_case_and_punctuation() {
+        assert_eq!(
+            similarity("Missing error check.", "missing ERROR check"),
+            1.0
+        );
+        assert_eq!(similarity("unused import", "off by one"), 0.0);
+        assert!(similarity("result is not checked", "the result is not checked") > 0.6);
+    }
+
+    #[test]
+    fn dedup_keeps_most_severe_wording() {
+        let findings = vec![
+            finding(10, "Return value of write is ignored", ReviewSeverity::Low),
+            finding(
+                11,
+                "return value of write is ignored here",
+                ReviewSeverity::High,
+            ),
+            finding(40, "Return value of write is ignored", ReviewSeverity::Low),
+        ];
+
+        let kept = dedup_findings(&findings);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].severity, ReviewSeverity::High);
+        assert_eq!(kept[1].line, Some(40));
+    }
+
+    #[test]
+    fn unposted_skips_open_comments_only() {
+        let mut threads = ReviewThreads::new(vec![
+            posted(
+                Some(1),
+                &finding(10, "Panics on empty input", ReviewSeverity::High),
+            ),
+            posted(
+                Some(2),
+                &finding(30, "Unused variable", ReviewSeverity::Nit),
+            ),
+        ]);
+        threads.posted[1].resolved_by = Some("abc123".to_string());
+
+        let new = threads.unposted(&[
+            finding(12, "panics on empty input!", ReviewSeverity::High),
+            finding(30, "Unused variable", ReviewSeverity::Nit),
+        ]);
+
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].line, Some(30));
+    }
+
+    #[test]
+    fn find_thread_matches_same_file_and_nearby_line() {
+        let threads = ReviewThreads::new(vec![posted(
+            Some(7),
+            &finding(10, "Panics on empty input", ReviewSeverity::High),
+        )]);
+
+        let nearby = finding(13, "Panics on empty input", ReviewSeverity::High);
+        let far = finding(50, "Panics on empty input", ReviewSeverity::High);
+        let other_file = ReviewSuggestion {
+            file: "src/main.rs".to_string(),
+            ..nearby.clone()
+        };
+
+        assert_eq!(threads.find_thread(&nearby).and_then(|c| c.id), Some(7));
+        assert!(threads.find_thread(&far).is_none());
+        assert!(threads.find_thread(&other_file).is_none());
+    }
+
+    #[test]
+    fn parses_fix_check_json() {
+        let output = "Looks good.\n```json\n{\"resolved\": true, \"reason\": \"checked\"}\n```";
+
+        assert_eq!(parse_fix_check(output), Some(FixCheck::resolved("checked")));
+        assert_eq!(
+            parse_fix_check(r#"{"resolved": false}"#),
+            Some(FixCheck::unresolved(""))
+        );
+        assert_eq!(parse_fix_check("no verdict"), None);
+    }
+
+    #[test]
+    fn failed_fixes_retry_then_escalate() {
+        let mut threads = ReviewThreads::new(vec![posted(
+            Some(7),
+            &finding(10, "Panics on empty input", ReviewSeverity::High),
+        )])
+        .with_max_failed_fixes(2);
+        let rejected = FixCheck::unresolved("still panics");
+
+        assert_eq!(
+            threads.record_check(0, "abc123", &rejected),
+            ThreadResolution::Retry("still panics".to_string())
+        );
+        assert_eq!(
+            threads.record_check(0, "def456", &rejected),
+            ThreadResolution::Escalated("still panics".to_string())
+        );
+        assert!(threads.posted()[0].escalated);
+        assert!(threads.posted()[0].resolved_by.is_none());
+
+        assert_eq!(
+            threads.record_check(0, "0a1b2c", &FixCheck::resolved("fixed")),
+            ThreadResolution::Resolved
+        );
+        assert_eq!(threads.posted()[0].resolved_by.as_deref(), Some("0a1b2c"));
+    }
+
+    #[tokio::test]
+    async fn verifier_reads_verdict_from_runner() {
+        let runner = ScriptedVerifier::new(r#"{"resolved": false, "reason": "wrong function"}"#);
+        let verifier = FixVerifier::new(runner.clone());
+        let suggestion = ReviewSuggestion {
+            suggestion: "Return an error instead".to_string(),
+            ..finding(10, "Panics on empty input", ReviewSeverity::High)
+        };
+
+        let check = verifier
+            .verify(Path::new("."), &suggestion, "+fn other() {}")
+            .await
+            .unwrap();
+
+        assert_eq!(check, FixCheck::unresolved("wrong function"));
+        let prompts = runner.prompts.lock().unwrap();
+        assert!(prompts[0].contains("`src/lib.rs:10`: Panics on empty input"));
+        assert!(prompts[0].contains("Suggested fix: Return an error instead"));
+        assert!(prompts[0].contains("+fn other() {}"));
+    }
+
+    #[tokio::test]
+    async fn empty_diff_is_never_a_fix() {
+        let runner = ScriptedVerifier::new(r#"{"resolved": true}"#);
+        let verifier = FixVerifier::new(runner.clone());
+
+        let check = verifier
+            .verify(
+                Path::new("."),
+                &finding(10, "Panics", ReviewSeverity::High),
+                "\n",
+            )
+            .await
+            .unwrap();
+
+        assert!(!check.resolved);
+        assert!(runner.prompts.lock().unwrap().is_empty());
+    }
+}
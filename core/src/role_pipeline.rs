@@ -0,0 +1,395 @@
+//! Role pipelines for spawn-team.
+//!
+//! A team configured with [`TeamRole`]s runs each role in turn on the same
+//! worktree, e.g. an architect designing the change, an implementer making
+//! it, a tester covering it and a reviewer checking it. Every role is told
+//! the task and handed the answers of the roles before it, so later roles
+//! build on earlier ones. The pipeline stops at the first role that fails.
+//!
+//! The tester's work is gated: after it runs, the gate commands of
+//! [`TesterConfig`](crate::team::TesterConfig) must pass before the roles
+//! after it and the review phases start. A failing command goes back to
+//! the tester as a "fix the build" prompt until the attempts run out.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::SpawnObservability;
+use crate::cancel::CancellationToken;
+use crate::error::Result;
+use crate::prompt::PromptTemplates;
+use crate::runner::{run_in_sandbox, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::{sandbox_environment_names, SandboxManifest};
+use crate::team::{SpawnTeamConfig, TeamRole, TESTER_ROLE};
+use crate::verification::{VerificationConfig, VerificationFailure};
+
+/// Longest answer, in characters, handed on to later roles. Longer answers
+/// keep their end, where CLIs put their summary.
+const MAX_HANDOFF_CHARS: usize = 8000;
+
+/// What one run of a role did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleOutcome {
+    /// Role name.
+    pub role: String,
+    /// LLM that played the role.
+    pub llm: String,
+    /// Whether the LLM completed successfully.
+    pub success: bool,
+    /// The role's answer.
+    pub output: String,
+    /// Tool calls and environment of the run.
+    #[serde(default)]
+    pub observability: SpawnObservability,
+}
+
+/// Outcome of a role pipeline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineOutcome {
+    /// Role runs in order, including the tester's fix attempts.
+    pub roles: Vec<RoleOutcome>,
+    /// Whether every role ran and succeeded, and the test gate passed.
+    pub success: bool,
+    /// Gate command still failing after the tester's last attempt.
+    #[serde(default)]
+    pub test_gate: Option<VerificationFailure>,
+}
+
+/// Runs a team's roles in order on one worktree.
+pub struct RolePipeline {
+    roles: Vec<TeamRole>,
+    runners: HashMap<String, Arc<dyn LLMRunner>>,
+    manifest: SandboxManifest,
+    gate: VerificationConfig,
+    templates: PromptTemplates,
+    cancel: CancellationToken,
+}
+
+impl RolePipeline {
+    /// Creates a pipeline for the roles of `config`, creating a runner for
+    /// each LLM they use with `create_runner`.
+    pub fn new(
+        config: &SpawnTeamConfig,
+        create_runner: impl Fn(&str) -> Result<Arc<dyn LLMRunner>>,
+    ) -> Result<Self> {
+        let roles = config.role_pipeline();
+        let mut runners = HashMap::new();
+        for role in &roles {
+            if !runners.contains_key(&role.llm) {
+                runners.insert(role.llm.clone(), create_runner(&role.llm)?);
+            }
+        }
+        Ok(Self {
+            roles,
+            runners,
+            manifest: SandboxManifest::default(),
+            gate: config.tester.gate.clone(),
+            templates: PromptTemplates::builtin(),
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Sets the permissions for roles that may write.
+    pub fn with_manifest(mut self, manifest: SandboxManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Uses `templates` for the tester's fix prompts.
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Stops the pipeline with [`Error::Cancelled`](crate::error::Error::Cancelled) when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Returns the roles in the order they run.
+    pub fn roles(&self) -> &[TeamRole] {
+        &self.roles
+    }
+
+    /// Runs every role on `worktree` for the task in `prompt`.
+    ///
+    /// An unsuccessful outcome means the review phases should not start.
+    pub async fn run(&self, worktree: &Path, prompt: &str) -> Result<PipelineOutcome> {
+        let mut outcome = PipelineOutcome::default();
+        for role in &self.roles {
+            let role_prompt = role_prompt(role, prompt, &outcome.roles);
+            let run = self.run_role(role, worktree, role_prompt).await?;
+            let success = run.success;
+            outcome.roles.push(run);
+            if !success {
+                tracing::warn!(role = %role.name, "team role failed, stopping the pipeline");
+                return Ok(outcome);
+            }
+            if role.name == TESTER_ROLE && self.gate.is_enabled() {
+                if let Some(failure) = self.pass_gate(role, worktree, prompt, &mut outcome).await? {
+                    tracing::warn!(command = %failure.command, "tests still fail, skipping reviews");
+                    outcome.test_gate = Some(failure);
+                    return Ok(outcome);
+                }
+            }
+        }
+        outcome.success = true;
+        Ok(outcome)
+    }
+
+    /// Runs the gate commands, giving `tester` up to `max_fix_attempts`
+    /// runs to fix what fails. Returns the failure if one remains.
+    async fn pass_gate(
+        &self,
+        tester: &TeamRole,
+        worktree: &Path,
+        task: &str,
+        outcome: &mut PipelineOutcome,
+    ) -> Result<Option<VerificationFailure>> {
+        let mut attempts = 0;
+        while let Some(failure) = self.gate.run(worktree, &self.manifest).await {
+            if attempts == self.gate.max_fix_attempts {
+                return Ok(Some(failure));
+            }
+            attempts += 1;
+            tracing::info!(attempt = attempts, command = %failure.command, "test gate failed");
+            let prompt = failure.prompt(task, &self.templates);
+            outcome
+                .roles
+                .push(self.run_role(tester, worktree, prompt).await?);
+        }
+        Ok(None)
+    }
+
+    /// Runs `role` once with `prompt`.
+    async fn run_role(
+        &self,
+        role: &TeamRole,
+        worktree: &Path,
+        prompt: String,
+    ) -> Result<RoleOutcome> {
+        let runner = &self.runners[&role.llm];
+        let manifest = if role.is_read_only() {
+            read_only(&self.manifest)
+        } else {
+            self.manifest.clone()
+        };
+        let environment = sandbox_environment_names(&manifest);
+        let config = LLMSpawnConfig {
+            prompt,
+            working_dir: worktree.to_path_buf(),
+            manifest,
+            model: role.model.clone(),
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        tracing::info!(role = %role.name, runner = %runner.name(), "running team role");
+        let run = tokio::select! {
+            run = run_in_sandbox(runner.as_ref(), config) => run?,
+            _ = self.cancel.cancelled() => return Err(self.cancel.error()),
+        };
+        Ok(RoleOutcome {
+            role: role.name.clone(),
+            llm: role.llm.clone(),
+            success: run.result.success,
+            output: run.output,
+            observability: SpawnObservability {
+                tool_calls: run.tool_calls,
+                environment,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Returns `manifest` limited to reading the repository.
+fn read_only(manifest: &SandboxManifest) -> SandboxManifest {
+    SandboxManifest {
+        allowed_tools: vec!["Read".to_string(), "Glob".to_string(), "Grep".to_string()],
+        writable_paths: Vec::new(),
+        ..manifest.clone()
+    }
+}
+
+/// Builds the prompt for `role`: its instructions, the task, and the
+/// answers of the roles that ran before it.
+fn role_prompt(role: &TeamRole, task: &str, earlier: &[RoleOutcome]) -> String {
+    let mut prompt = String::new();
+    if let Some(instructions) = role.instructions() {
+        prompt.push_str(instructions.trim());
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str("## Task\n\n");
+    prompt.push_str(task.trim());
+    prompt.push('\n');
+    if !earlier.is_empty() {
+        prompt.push_str("\n## Work by earlier roles\n");
+        for outcome in earlier {
+            let output = outcome.output.trim();
+            let start = output
+                .char_indices()
+                .rev()
+                .nth(MAX_HANDOFF_CHARS - 1)
+                .map_or(0, |(i, _)| i);
+            prompt.push_str(&format!("\n### {}\n\n{}\n", outcome.role, &output[start..]));
+        }
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{LLMOutput, LLMResult};
+    use async_trait::async_trait;
+    use std::process::Command;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    /// Runner that answers with its name and records what it was asked.
+    struct EchoRunner {
+        name: String,
+        calls: Mutex<Vec<LLMSpawnConfig>>,
+    }
+
+    #[async_trait]
+    impl LLMRunner for EchoRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            let answer = format!("{} answered", self.name);
+            let _ = output_tx.send(LLMOutput::Stdout(answer)).await;
+            self.calls.lock().unwrap().push(config);
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+    }
+
+    /// Runner that reads the repository once and writes `tests.txt` when
+    /// asked to fix the build.
+    struct TestWriter;
+
+    #[async_trait]
+    impl LLMRunner for TestWriter {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            let read = LLMOutput::ToolCall {
+                tool: "Read".to_string(),
+                args: "src/lib.rs".to_string(),
+            };
+            let _ = output_tx.send(read).await;
+            if config.prompt.starts_with("## Fix the Build") {
+                std::fs::write(config.working_dir.join("tests.txt"), "covered\n")?;
+            }
+            Ok(LLMResult {
+                exit_status: Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "test-writer"
+        }
+    }
+
+    fn gated_pipeline(command: &str) -> RolePipeline {
+        let mut config = SpawnTeamConfig::default();
+        config.tester.enabled = true;
+        config.tester.gate.commands = vec![command.to_string()];
+        config.tester.gate.max_fix_attempts = 1;
+        RolePipeline::new(&config, |_| Ok(Arc::new(TestWriter) as Arc<dyn LLMRunner>)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn tester_fixes_failing_tests_before_review() {
+        let worktree = TempDir::new().unwrap();
+        let pipeline = gated_pipeline("test -f tests.txt");
+
+        let outcome = pipeline.run(worktree.path(), "Add login").await.unwrap();
+
+        assert!(outcome.success);
+        assert!(outcome.test_gate.is_none());
+        let order: Vec<_> = outcome.roles.iter().map(|r| r.role.as_str()).collect();
+        assert_eq!(order, ["implementer", "tester", "tester", "reviewer"]);
+        assert_eq!(outcome.roles[1].observability.tool_calls[0].tool, "Read");
+    }
+
+    #[tokio::test]
+    async fn failing_test_gate_stops_before_review() {
+        let worktree = TempDir::new().unwrap();
+        let pipeline = gated_pipeline("exit 1");
+
+        let outcome = pipeline.run(worktree.path(), "Add login").await.unwrap();
+
+        assert!(!outcome.success);
+        assert_eq!(outcome.test_gate.unwrap().command, "exit 1");
+        let order: Vec<_> = outcome.roles.iter().map(|r| r.role.as_str()).collect();
+        assert_eq!(order, ["implementer", "tester", "tester"]);
+    }
+
+    #[tokio::test]
+    async fn roles_run_in_order_and_see_earlier_work() {
+        let mut config = SpawnTeamConfig::default();
+        let mut tester = TeamRole::new("tester", "gemini-cli");
+        tester.order = 2;
+        let mut architect = TeamRole::new("architect", "claude-code");
+        architect.model = Some("opus".to_string());
+        let mut implementer = TeamRole::new("implementer", "claude-code");
+        implementer.order = 1;
+        config.roles = vec![tester, architect, implementer];
+        let runners = Mutex::new(Vec::new());
+        let pipeline = RolePipeline::new(&config, |llm| {
+            let runner = Arc::new(EchoRunner {
+                name: llm.to_string(),
+                calls: Mutex::new(Vec::new()),
+            });
+            runners.lock().unwrap().push(runner.clone());
+            Ok(runner as Arc<dyn LLMRunner>)
+        })
+        .unwrap();
+        let worktree = TempDir::new().unwrap();
+
+        let outcome = pipeline.run(worktree.path(), "Add login").await.unwrap();
+
+        assert!(outcome.success);
+        let order: Vec<_> = outcome.roles.iter().map(|r| r.role.as_str()).collect();
+        assert_eq!(order, ["architect", "implementer", "tester"]);
+
+        let runners = runners.into_inner().unwrap();
+        assert_eq!(runners.len(), 2);
+        let claude = runners[0].calls.lock().unwrap();
+        assert_eq!(claude[0].model.as_deref(), Some("opus"));
+        assert!(claude[0].prompt.starts_with("You are the architect."));
+        assert!(claude[0].manifest.writable_paths.is_empty());
+        assert!(!claude[0].prompt.contains("## Work by earlier roles"));
+        assert!(claude[1]
+            .prompt
+            .contains("## Work by earlier roles\n\n### architect\n\nclaude-code answered\n"));
+        let gemini = runners[1].calls.lock().unwrap();
+        assert!(gemini[0].prompt.contains("## Task\n\nAdd login\n"));
+        assert!(gemini[0].prompt.contains("### implementer\n"));
+    }
+}
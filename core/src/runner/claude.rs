@@ -1,6 +1,7 @@
 //! Claude Code CLI runner.
 
 use std::process::Stdio;
+use std::sync::OnceLock;
 
 use async_trait::async_trait;
 use tokio::io::{AsyncBufReadExt, BufReader};
@@ -8,13 +9,18 @@ use tokio::process::Command;
 use tokio::sync::mpsc;
 
 use crate::error::{Error, Result};
+use crate::sandbox::sandbox_environment;
 
-use super::{LLMOutput, LLMResult, LLMRunner, LLMSpawnConfig};
+use super::stream::{StreamEvent, StreamParser};
+use super::version::{detected, Capabilities, CliVersion};
+use super::{line_outputs, send_all, LLMOutput, LLMResult, LLMRunner, LLMSpawnConfig};
 
 /// Runner for Claude Code CLI.
 pub struct ClaudeRunner {
     /// Path to the claude CLI binary.
     cli_path: String,
+    /// Installed CLI version, once detected.
+    version: OnceLock<Option<CliVersion>>,
 }
 
 impl Default for ClaudeRunner {
@@ -28,6 +34,7 @@ impl ClaudeRunner {
     pub fn new() -> Self {
         Self {
             cli_path: "claude".to_string(),
+            version: OnceLock::new(),
         }
     }
 
@@ -35,6 +42,15 @@ impl ClaudeRunner {
     pub fn with_cli_path(cli_path: impl Into<String>) -> Self {
         Self {
             cli_path: cli_path.into(),
+            version: OnceLock::new(),
+        }
+    }
+
+    /// Uses `version` instead of detecting the installed CLI's version.
+    pub fn with_version(self, version: CliVersion) -> Self {
+        Self {
+            version: OnceLock::from(Some(version)),
+            ..self
         }
     }
 
@@ -42,6 +58,9 @@ impl ClaudeRunner {
     fn build_args(&self, config: &LLMSpawnConfig) -> Vec<String> {
         let mut args = vec![
             "--print".to_string(), // Non-interactive mode
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--verbose".to_string(), // Required for stream-json with --print
         ];
 
         // Add model if specified
@@ -50,12 +69,21 @@ impl ClaudeRunner {
             args.push(model.clone());
         }
 
+        // Continue an earlier conversation
+        if let Some(session) = &config.session {
+            args.push("--resume".to_string());
+            args.push(session.clone());
+        }
+
         // Add allowed tools from manifest
         if !config.manifest.allowed_tools.is_empty() {
             args.push("--allowedTools".to_string());
             args.push(config.manifest.allowed_tools.join(","));
         }
 
+        // Add caller-supplied arguments
+        args.extend(config.extra_args.iter().cloned());
+
         // Add the prompt
         args.push(config.prompt.clone());
 
@@ -70,7 +98,15 @@ impl LLMRunner for ClaudeRunner {
         config: LLMSpawnConfig,
         output_tx: mpsc::Sender<LLMOutput>,
     ) -> Result<LLMResult> {
-        let args = self.build_args(&config);
+        let capabilities = Capabilities::claude(detected(&self.version, &self.cli_path).await)?;
+        let mut args = self.build_args(&config);
+        let mut session_id = config.session.clone();
+        if session_id.is_none() && capabilities.session_id {
+            // Name new conversations so later rounds can resume them
+            let id = uuid::Uuid::new_v4().to_string();
+            args.splice(1..1, ["--session-id".to_string(), id.clone()]);
+            session_id = Some(id);
+        }
 
         tracing::info!(
             cli = %self.cli_path,
@@ -81,9 +117,12 @@ impl LLMRunner for ClaudeRunner {
         let mut child = Command::new(&self.cli_path)
             .args(&args)
             .current_dir(&config.working_dir)
+            .env_clear()
+            .envs(sandbox_environment(&config.manifest))
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| Error::SandboxCreation(format!("failed to spawn claude: {}", e)))?;
 
@@ -94,6 +133,7 @@ impl LLMRunner for ClaudeRunner {
         let mut stderr_reader = BufReader::new(stderr).lines();
 
         let mut output_lines = 0;
+        let mut parser = StreamParser::claude();
 
         // Process stdout and stderr concurrently
         loop {
@@ -103,9 +143,13 @@ impl LLMRunner for ClaudeRunner {
                         Ok(Some(line)) => {
                             output_lines += 1;
 
-                            // Check for tool calls and file operations
-                            let output = self.parse_output_line(&line);
-                            if output_tx.send(output).await.is_err() {
+                            // Typed stream-json events, or tool calls and file
+                            // operations detected in plain text
+                            let outputs =
+                                line_outputs(&mut parser, &line, &mut session_id, |line| {
+                                    self.parse_output_line(line)
+                                });
+                            if !send_all(&output_tx, outputs).await {
                                 tracing::warn!("output receiver dropped");
                                 break;
                             }
@@ -135,6 +179,12 @@ impl LLMRunner for ClaudeRunner {
             }
         }
 
+        let remaining = parser
+            .finish()
+            .into_iter()
+            .flat_map(StreamEvent::into_outputs);
+        send_all(&output_tx, remaining).await;
+
         let status = child
             .wait()
             .await
@@ -144,6 +194,8 @@ impl LLMRunner for ClaudeRunner {
             exit_status: status,
             output_lines,
             success: status.success(),
+            model: config.model.clone(),
+            session_id,
         })
     }
 
@@ -237,6 +289,8 @@ mod tests {
             working_dir: "/tmp/test".into(),
             manifest: Default::default(),
             model: None,
+            extra_args: Vec::new(),
+            session: None,
         };
 
         let args = runner.build_args(&config);
@@ -253,6 +307,8 @@ mod tests {
             working_dir: "/tmp".into(),
             manifest: Default::default(),
             model: Some("haiku".to_string()),
+            extra_args: Vec::new(),
+            session: None,
         };
 
         let args = runner.build_args(&config);
@@ -264,14 +320,18 @@ mod tests {
     #[test]
     fn claude_runner_includes_allowed_tools() {
         let runner = ClaudeRunner::new();
-        let mut manifest = crate::sandbox::SandboxManifest::default();
-        manifest.allowed_tools = vec!["Read".to_string(), "Write".to_string()];
+        let manifest = crate::sandbox::SandboxManifest {
+            allowed_tools: vec!["Read".to_string(), "Write".to_string()],
+            ..Default::default()
+        };
 
         let config = LLMSpawnConfig {
             prompt: "test".to_string(),
             working_dir: "/tmp".into(),
             manifest,
             model: None,
+            extra_args: Vec::new(),
+            session: None,
         };
 
         let args = runner.build_args(&config);
@@ -280,6 +340,25 @@ mod tests {
         assert!(args.contains(&"Read,Write".to_string()));
     }
 
+    #[test]
+    fn claude_runner_passes_extra_args_before_prompt() {
+        let runner = ClaudeRunner::new();
+        let config = LLMSpawnConfig {
+            prompt: "test".to_string(),
+            working_dir: "/tmp".into(),
+            manifest: Default::default(),
+            model: None,
+            extra_args: vec!["--max-turns".to_string(), "5".to_string()],
+            session: None,
+        };
+
+        let args = runner.build_args(&config);
+
+        let pos = args.iter().position(|a| a == "--max-turns").unwrap();
+        assert_eq!(args[pos + 1], "5");
+        assert_eq!(args.last().unwrap(), "test");
+    }
+
     #[test]
     fn claude_runner_parses_stdout_line() {
         let runner = ClaudeRunner::new();
@@ -328,4 +407,22 @@ mod tests {
         let runner = ClaudeRunner::with_cli_path("/usr/local/bin/claude");
         assert_eq!(runner.cli_path, "/usr/local/bin/claude");
     }
+
+    #[test]
+    fn claude_runner_resumes_session() {
+        let runner = ClaudeRunner::new();
+        let config = LLMSpawnConfig {
+            prompt: "fix it".to_string(),
+            working_dir: "/tmp/test".into(),
+            manifest: Default::default(),
+            model: None,
+            extra_args: Vec::new(),
+            session: Some("abc-123".to_string()),
+        };
+
+        let args = runner.build_args(&config);
+
+        let pos = args.iter().position(|a| a == "--resume").unwrap();
+        assert_eq!(args[pos + 1], "abc-123");
+    }
 }
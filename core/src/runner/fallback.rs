@@ -0,0 +1,409 @@
+//! Model fallback chains.
+//!
+//! Wraps a runner so an invocation that fails because the model is
+//! overloaded (HTTP 429/529, rate limits) or that runs past a deadline is
+//! retried on the next model in the chain, e.g. `opus -> sonnet -> haiku`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::error::{Error, Result};
+
+use super::{LLMOutput, LLMResult, LLMRunner, LLMSpawnConfig};
+
+/// Phrases in CLI errors that indicate the model is overloaded.
+const OVERLOAD_MARKERS: &[&str] = &[
+    "rate limit",
+    "rate limited",
+    "rate limits",
+    "rate_limit",
+    "too many requests",
+    "overloaded",
+    "resource_exhausted",
+    "quota exceeded",
+];
+
+/// HTTP statuses of a rate-limited or overloaded API.
+const OVERLOAD_STATUSES: &[&str] = &["429", "529"];
+
+/// Words that mark a number in an error as an HTTP status.
+const STATUS_CONTEXT: &[&str] = &["http", "status", "error", "code"];
+
+/// Returns true if an output line reports an overloaded or rate-limited model.
+///
+/// Only stderr is checked, since the assistant's own text may mention any
+/// of these. Markers and statuses must be whole words, and statuses must
+/// come with a word like "HTTP" or "error".
+pub fn is_overload(output: &LLMOutput) -> bool {
+    let LLMOutput::Stderr(line) = output else {
+        return false;
+    };
+    let lower = line.to_lowercase();
+    OVERLOAD_MARKERS.iter().any(|m| contains_word(&lower, m))
+        || (OVERLOAD_STATUSES.iter().any(|s| contains_word(&lower, s))
+            && STATUS_CONTEXT.iter().any(|c| lower.contains(c)))
+}
+
+/// Returns whether `phrase` occurs in `text` without a letter or digit
+/// directly before or after it.
+fn contains_word(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let end = start + phrase.len();
+        !text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+            && !text[end..]
+                .chars()
+                .next()
+                .is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Runner that falls back to other models when one is unavailable.
+pub struct FallbackRunner {
+    inner: Arc<dyn LLMRunner>,
+    fallbacks: Vec<String>,
+    chains: HashMap<String, Vec<String>>,
+    attempt_timeout: Option<Duration>,
+}
+
+impl FallbackRunner {
+    /// Wraps `inner`, trying `fallbacks` in order after the requested model.
+    pub fn new(inner: Arc<dyn LLMRunner>, fallbacks: Vec<String>) -> Self {
+        Self {
+            inner,
+            fallbacks,
+            chains: HashMap::new(),
+            attempt_timeout: None,
+        }
+    }
+
+    /// Wraps `inner`, trying the chain listed for the requested model in
+    /// `chains`. Models without a chain get no fallbacks.
+    pub fn for_models(inner: Arc<dyn LLMRunner>, chains: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            chains,
+            ..Self::new(inner, Vec::new())
+        }
+    }
+
+    /// Abandons an attempt and moves to the next model after `timeout`.
+    pub fn with_attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = Some(timeout);
+        self
+    }
+
+    /// Runs one attempt, forwarding output. Returns `None` on timeout, and
+    /// whether an overload was reported.
+    async fn attempt(
+        &self,
+        config: LLMSpawnConfig,
+        output_tx: &mpsc::Sender<LLMOutput>,
+    ) -> (Option<Result<LLMResult>>, bool) {
+        let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
+
+        let run = async {
+            let spawn = self.inner.spawn(config, tx);
+            match self.attempt_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, spawn).await.ok(),
+                None => Some(spawn.await),
+            }
+        };
+        let forward = async {
+            let mut overloaded = false;
+            while let Some(output) = rx.recv().await {
+                overloaded |= is_overload(&output);
+                let _ = output_tx.send(output).await;
+            }
+            overloaded
+        };
+
+        tokio::join!(run, forward)
+    }
+}
+
+#[async_trait]
+impl LLMRunner for FallbackRunner {
+    async fn spawn(
+        &self,
+        config: LLMSpawnConfig,
+        output_tx: mpsc::Sender<LLMOutput>,
+    ) -> Result<LLMResult> {
+        let fallbacks = config
+            .model
+            .as_deref()
+            .and_then(|model| self.chains.get(model))
+            .unwrap_or(&self.fallbacks);
+        let models: Vec<Option<String>> = std::iter::once(config.model.clone())
+            .chain(fallbacks.iter().cloned().map(Some))
+            .collect();
+        let mut failures = Vec::new();
+
+        for (index, model) in models.iter().enumerate() {
+            let last = index + 1 == models.len();
+            let label = model.as_deref().unwrap_or("default");
+            let attempt = LLMSpawnConfig {
+                model: model.clone(),
+                ..config.clone()
+            };
+
+            match self.attempt(attempt, &output_tx).await {
+                (None, _) => {
+                    tracing::warn!(runner = %self.inner.name(), model = %label, "model timed out");
+                    failures.push(format!("{} timed out", label));
+                }
+                (Some(Ok(result)), true) if !result.success && !last => {
+                    tracing::warn!(
+                        runner = %self.inner.name(),
+                        model = %label,
+                        "model overloaded, falling back"
+                    );
+                    failures.push(format!("{} overloaded", label));
+                }
+                (Some(result), _) => {
+                    let mut result = result?;
+                    result.model = model.clone();
+                    if index > 0 {
+                        tracing::info!(
+                            runner = %self.inner.name(),
+                            model = %label,
+                            requested = %config.model.as_deref().unwrap_or("default"),
+                            "served by fallback model"
+                        );
+                    }
+                    return Ok(result);
+                }
+            }
+        }
+
+        Err(Error::Invocation(failures.join(", ")))
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::process::Command;
+    use std::sync::Mutex;
+
+    use crate::sandbox::SandboxManifest;
+
+    /// How the fake runner behaves for a model.
+    #[derive(Clone, Copy)]
+    enum Behavior {
+        Overloaded,
+        Hang,
+        Succeed,
+        Fail,
+    }
+
+    struct FakeRunner {
+        behaviors: HashMap<&'static str, Behavior>,
+        calls: Mutex<Vec<Option<String>>>,
+    }
+
+    impl FakeRunner {
+        fn new(behaviors: &[(&'static str, Behavior)]) -> Arc<Self> {
+            Arc::new(Self {
+                behaviors: behaviors.iter().copied().collect(),
+                calls: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LLMRunner for FakeRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            self.calls.lock().unwrap().push(config.model.clone());
+            let model = config.model.as_deref().unwrap_or("default");
+            let behavior = self
+                .behaviors
+                .get(model)
+                .copied()
+                .unwrap_or(Behavior::Succeed);
+
+            let success = match behavior {
+                Behavior::Overloaded => {
+                    let line = "API Error: 529 Overloaded".to_string();
+                    let _ = output_tx.send(LLMOutput::Stderr(line)).await;
+                    false
+                }
+                Behavior::Hang => std::future::pending().await,
+                Behavior::Succeed => true,
+                Behavior::Fail => false,
+            };
+
+            Ok(LLMResult {
+                exit_status: Command::new(if success { "true" } else { "false" }).status()?,
+                output_lines: 0,
+                success,
+                model: config.model,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "fake"
+        }
+    }
+
+    fn config(model: &str) -> LLMSpawnConfig {
+        LLMSpawnConfig {
+            prompt: "test".to_string(),
+            working_dir: std::env::temp_dir(),
+            manifest: SandboxManifest::default(),
+            model: Some(model.to_string()),
+            extra_args: Vec::new(),
+            session: None,
+        }
+    }
+
+    fn chain(inner: Arc<FakeRunner>) -> FallbackRunner {
+        FallbackRunner::new(inner, vec!["sonnet".to_string(), "haiku".to_string()])
+    }
+
+    async fn run(runner: &FallbackRunner, model: &str) -> Result<LLMResult> {
+        let (tx, mut rx) = mpsc::channel(100);
+        let drain = async { while rx.recv().await.is_some() {} };
+        tokio::join!(runner.spawn(config(model), tx), drain).0
+    }
+
+    #[test]
+    fn detects_overload_output() {
+        assert!(is_overload(&LLMOutput::Stderr(
+            "HTTP 429 Too Many Requests".into()
+        )));
+        assert!(is_overload(&LLMOutput::Stderr(
+            "API Error: 529 {\"type\":\"error\"}".into()
+        )));
+        assert!(is_overload(&LLMOutput::Stderr("RESOURCE_EXHAUSTED".into())));
+        assert!(is_overload(&LLMOutput::Stderr(
+            "error: rate_limit_error".into()
+        )));
+        assert!(!is_overload(&LLMOutput::Stderr(
+            "compiled 12 crates".into()
+        )));
+        assert!(!is_overload(&LLMOutput::FileRead("429.txt".into())));
+    }
+
+    #[test]
+    fn ordinary_output_is_not_an_overload() {
+        // The assistant's own text never counts
+        assert!(!is_overload(&LLMOutput::Stdout(
+            "The API returns HTTP 429 when overloaded".into()
+        )));
+        // Numbers and words that only contain a marker
+        assert!(!is_overload(&LLMOutput::Stderr(
+            "error: request req_01529af failed".into()
+        )));
+        assert!(!is_overload(&LLMOutput::Stderr(
+            "warning: 4290 lines scanned".into()
+        )));
+        assert!(!is_overload(&LLMOutput::Stderr(
+            "read 429 bytes from cache".into()
+        )));
+        assert!(!is_overload(&LLMOutput::Stderr(
+            "error: OverloadedMethodError in parser".into()
+        )));
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_overload() {
+        let inner = FakeRunner::new(&[("opus", Behavior::Overloaded)]);
+
+        let result = run(&chain(inner.clone()), "opus").await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.model.as_deref(), Some("sonnet"));
+        assert_eq!(inner.calls.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_timeout() {
+        let inner = FakeRunner::new(&[("opus", Behavior::Hang), ("sonnet", Behavior::Hang)]);
+        let runner = chain(inner).with_attempt_timeout(Duration::from_millis(20));
+
+        let result = run(&runner, "opus").await.unwrap();
+
+        assert_eq!(result.model.as_deref(), Some("haiku"));
+    }
+
+    #[tokio::test]
+    async fn ordinary_failure_does_not_fall_back() {
+        let inner = FakeRunner::new(&[("opus", Behavior::Fail)]);
+
+        let result = run(&chain(inner.clone()), "opus").await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.model.as_deref(), Some("opus"));
+        assert_eq!(inner.calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn last_model_overload_is_returned() {
+        let inner = FakeRunner::new(&[
+            ("opus", Behavior::Overloaded),
+            ("sonnet", Behavior::Overloaded),
+            ("haiku", Behavior::Overloaded),
+        ]);
+
+        let result = run(&chain(inner), "opus").await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.model.as_deref(), Some("haiku"));
+    }
+
+    #[tokio::test]
+    async fn chains_are_chosen_by_requested_model() {
+        let inner = FakeRunner::new(&[
+            ("opus", Behavior::Overloaded),
+            ("gemini-pro", Behavior::Overloaded),
+        ]);
+        let chains = HashMap::from([
+            ("opus".to_string(), vec!["sonnet".to_string()]),
+            ("gemini-pro".to_string(), vec!["gemini-flash".to_string()]),
+        ]);
+        let runner = FallbackRunner::for_models(inner.clone(), chains);
+
+        let result = run(&runner, "gemini-pro").await.unwrap();
+        assert_eq!(result.model.as_deref(), Some("gemini-flash"));
+
+        let result = run(&runner, "opus").await.unwrap();
+        assert_eq!(result.model.as_deref(), Some("sonnet"));
+
+        // A model without a chain is tried alone
+        let inner = FakeRunner::new(&[("haiku", Behavior::Overloaded)]);
+        let runner = FallbackRunner::for_models(inner.clone(), HashMap::new());
+        let result = run(&runner, "haiku").await.unwrap();
+        assert!(!result.success);
+        assert_eq!(inner.calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn all_models_timing_out_is_an_error() {
+        let inner = FakeRunner::new(&[
+            ("opus", Behavior::Hang),
+            ("sonnet", Behavior::Hang),
+            ("haiku", Behavior::Hang),
+        ]);
+        let runner = chain(inner).with_attempt_timeout(Duration::from_millis(10));
+
+        let err = run(&runner, "opus").await.unwrap_err();
+
+        assert!(err.to_string().contains("haiku timed out"));
+    }
+}
@@ -1,20 +1,89 @@
 //! Gemini CLI runner.
+//!
+//! Every Gemini invocation goes through [`GeminiRunner`], configured with a
+//! [`GeminiConfig`] for the default model, approval mode, tools allowed
+//! without confirmation, and checkpointing for resumed sessions.
 
 use std::process::Stdio;
+use std::sync::OnceLock;
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::mpsc;
 
 use crate::error::{Error, Result};
+use crate::sandbox::sandbox_environment;
+
+use super::stream::{StreamEvent, StreamParser};
+use super::version::{detected, Capabilities, CliVersion};
+use super::{line_outputs, send_all, LLMOutput, LLMResult, LLMRunner, LLMSpawnConfig};
+
+/// Claude Code tool names and their Gemini CLI equivalents, so manifests
+/// written for either runner grant the same tools.
+const TOOL_NAMES: &[(&str, &str)] = &[
+    ("Read", "read_file"),
+    ("Write", "write_file"),
+    ("Edit", "replace"),
+    ("MultiEdit", "replace"),
+    ("Bash", "run_shell_command"),
+    ("Glob", "glob"),
+    ("Grep", "search_file_content"),
+    ("LS", "list_directory"),
+    ("WebFetch", "web_fetch"),
+    ("WebSearch", "google_web_search"),
+];
+
+/// How Gemini CLI asks before running tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeminiApprovalMode {
+    /// Only the allowed tools run without approval.
+    #[default]
+    Default,
+    /// File edits are approved automatically.
+    AutoEdit,
+    /// Every tool call is approved automatically.
+    Yolo,
+}
 
-use super::{LLMOutput, LLMResult, LLMRunner, LLMSpawnConfig};
+impl GeminiApprovalMode {
+    /// Returns the value of `--approval-mode`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GeminiApprovalMode::Default => "default",
+            GeminiApprovalMode::AutoEdit => "auto_edit",
+            GeminiApprovalMode::Yolo => "yolo",
+        }
+    }
+}
+
+/// Gemini CLI settings (`[spawn.gemini]`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeminiConfig {
+    /// Model used when the spawn does not name one.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// How tool calls are approved.
+    #[serde(default)]
+    pub approval_mode: GeminiApprovalMode,
+    /// Tools allowed in every spawn, on top of the manifest's.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Saves checkpoints so a session can be resumed after file changes.
+    #[serde(default)]
+    pub checkpointing: bool,
+}
 
 /// Runner for Gemini CLI.
 pub struct GeminiRunner {
     /// Path to the gemini CLI binary.
     cli_path: String,
+    /// Invocation settings.
+    config: GeminiConfig,
+    /// Installed CLI version, once detected.
+    version: OnceLock<Option<CliVersion>>,
 }
 
 impl Default for GeminiRunner {
@@ -28,6 +97,8 @@ impl GeminiRunner {
     pub fn new() -> Self {
         Self {
             cli_path: "gemini".to_string(),
+            config: GeminiConfig::default(),
+            version: OnceLock::new(),
         }
     }
 
@@ -35,21 +106,100 @@ impl GeminiRunner {
     pub fn with_cli_path(cli_path: impl Into<String>) -> Self {
         Self {
             cli_path: cli_path.into(),
+            config: GeminiConfig::default(),
+            version: OnceLock::new(),
         }
     }
 
+    /// Sets the model, approval mode, tools, and checkpointing.
+    pub fn with_config(mut self, config: GeminiConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Uses `version` instead of detecting the installed CLI's version.
+    pub fn with_version(self, version: CliVersion) -> Self {
+        Self {
+            version: OnceLock::from(Some(version)),
+            ..self
+        }
+    }
+
+    /// Returns the flags the installed CLI accepts, assuming the current
+    /// release until its version is known.
+    fn capabilities(&self) -> Capabilities {
+        let version = self.version.get().copied().flatten();
+        Capabilities::gemini(version).unwrap_or_else(|_| Capabilities::latest())
+    }
+
+    /// Returns the tools allowed without approval: the configured ones,
+    /// the manifest's tools under their Gemini names, and each allowed
+    /// command as a `run_shell_command(...)` entry.
+    fn allowed_tools(&self, config: &LLMSpawnConfig) -> Vec<String> {
+        let manifest = &config.manifest;
+        let mut tools: Vec<String> = Vec::new();
+        let names = self.config.allowed_tools.iter().cloned().chain(
+            manifest
+                .allowed_tools
+                .iter()
+                .map(|tool| gemini_tool_name(tool).to_string()),
+        );
+        let commands = manifest
+            .allowed_commands
+            .iter()
+            .map(|command| format!("run_shell_command({})", command));
+        for tool in names.chain(commands) {
+            if !tools.contains(&tool) {
+                tools.push(tool);
+            }
+        }
+        tools
+    }
+
     /// Builds the command arguments for spawning Gemini.
     fn build_args(&self, config: &LLMSpawnConfig) -> Vec<String> {
-        let mut args = vec![
-            "--non-interactive".to_string(),
-        ];
+        let capabilities = self.capabilities();
+        let mut args = vec!["--non-interactive".to_string()];
+        // Older releases only print plain text, which is parsed line by line
+        if capabilities.stream_json {
+            args.push("--output-format".to_string());
+            args.push("stream-json".to_string());
+        }
 
-        // Add model if specified
-        if let Some(model) = &config.model {
+        // Add model if specified, or the configured default
+        if let Some(model) = config.model.as_ref().or(self.config.model.as_ref()) {
             args.push("--model".to_string());
             args.push(model.clone());
         }
 
+        match self.config.approval_mode {
+            GeminiApprovalMode::Default => {}
+            mode if capabilities.approval_mode => {
+                args.push("--approval-mode".to_string());
+                args.push(mode.as_str().to_string());
+            }
+            GeminiApprovalMode::Yolo => args.push("--yolo".to_string()),
+            GeminiApprovalMode::AutoEdit => {
+                tracing::warn!("installed gemini CLI has no auto_edit mode; edits need approval")
+            }
+        }
+
+        let tools = self.allowed_tools(config);
+        if !tools.is_empty() {
+            args.push("--allowed-tools".to_string());
+            args.push(tools.join(","));
+        }
+
+        if self.config.checkpointing {
+            args.push("--checkpointing".to_string());
+        }
+
+        // Continue an earlier conversation from its checkpoint
+        if let Some(session) = &config.session {
+            args.push("--resume".to_string());
+            args.push(session.clone());
+        }
+
         // Add sandbox mode based on manifest
         if !config.manifest.allowed_commands.is_empty() {
             args.push("--sandbox".to_string());
@@ -59,6 +209,9 @@ impl GeminiRunner {
             args.push("strict".to_string());
         }
 
+        // Add caller-supplied arguments
+        args.extend(config.extra_args.iter().cloned());
+
         // Add the prompt
         args.push("--prompt".to_string());
         args.push(config.prompt.clone());
@@ -74,6 +227,7 @@ impl LLMRunner for GeminiRunner {
         config: LLMSpawnConfig,
         output_tx: mpsc::Sender<LLMOutput>,
     ) -> Result<LLMResult> {
+        Capabilities::gemini(detected(&self.version, &self.cli_path).await)?;
         let args = self.build_args(&config);
 
         tracing::info!(
@@ -85,9 +239,12 @@ impl LLMRunner for GeminiRunner {
         let mut child = Command::new(&self.cli_path)
             .args(&args)
             .current_dir(&config.working_dir)
+            .env_clear()
+            .envs(sandbox_environment(&config.manifest))
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| Error::SandboxCreation(format!("failed to spawn gemini: {}", e)))?;
 
@@ -98,6 +255,8 @@ impl LLMRunner for GeminiRunner {
         let mut stderr_reader = BufReader::new(stderr).lines();
 
         let mut output_lines = 0;
+        let mut parser = StreamParser::gemini();
+        let mut session_id = config.session.clone();
 
         // Process stdout and stderr concurrently
         loop {
@@ -107,9 +266,13 @@ impl LLMRunner for GeminiRunner {
                         Ok(Some(line)) => {
                             output_lines += 1;
 
-                            // Check for tool calls and file operations
-                            let output = self.parse_output_line(&line);
-                            if output_tx.send(output).await.is_err() {
+                            // Typed stream-json events, or tool calls and file
+                            // operations detected in plain text
+                            let outputs =
+                                line_outputs(&mut parser, &line, &mut session_id, |line| {
+                                    self.parse_output_line(line)
+                                });
+                            if !send_all(&output_tx, outputs).await {
                                 tracing::warn!("output receiver dropped");
                                 break;
                             }
@@ -139,6 +302,12 @@ impl LLMRunner for GeminiRunner {
             }
         }
 
+        let remaining = parser
+            .finish()
+            .into_iter()
+            .flat_map(StreamEvent::into_outputs);
+        send_all(&output_tx, remaining).await;
+
         let status = child
             .wait()
             .await
@@ -148,6 +317,8 @@ impl LLMRunner for GeminiRunner {
             exit_status: status,
             output_lines,
             success: status.success(),
+            model: config.model.clone(),
+            session_id,
         })
     }
 
@@ -156,6 +327,14 @@ impl LLMRunner for GeminiRunner {
     }
 }
 
+/// Returns the Gemini CLI name of a tool given by its Claude Code name.
+fn gemini_tool_name(tool: &str) -> &str {
+    TOOL_NAMES
+        .iter()
+        .find(|(claude, _)| *claude == tool)
+        .map_or(tool, |(_, gemini)| gemini)
+}
+
 impl GeminiRunner {
     /// Parses an output line to detect tool calls and file operations.
     fn parse_output_line(&self, line: &str) -> LLMOutput {
@@ -239,6 +418,8 @@ mod tests {
             working_dir: "/tmp/test".into(),
             manifest: Default::default(),
             model: None,
+            extra_args: Vec::new(),
+            session: None,
         };
 
         let args = runner.build_args(&config);
@@ -258,6 +439,8 @@ mod tests {
             working_dir: "/tmp".into(),
             manifest: Default::default(),
             model: Some("gemini-pro".to_string()),
+            extra_args: Vec::new(),
+            session: None,
         };
 
         let args = runner.build_args(&config);
@@ -269,14 +452,18 @@ mod tests {
     #[test]
     fn gemini_runner_uses_permissive_sandbox_with_commands() {
         let runner = GeminiRunner::new();
-        let mut manifest = crate::sandbox::SandboxManifest::default();
-        manifest.allowed_commands = vec!["npm test".to_string()];
+        let manifest = crate::sandbox::SandboxManifest {
+            allowed_commands: vec!["npm test".to_string()],
+            ..Default::default()
+        };
 
         let config = LLMSpawnConfig {
             prompt: "test".to_string(),
             working_dir: "/tmp".into(),
             manifest,
             model: None,
+            extra_args: Vec::new(),
+            session: None,
         };
 
         let args = runner.build_args(&config);
@@ -285,6 +472,83 @@ mod tests {
         assert!(args.contains(&"permissive".to_string()));
     }
 
+    #[test]
+    fn gemini_runner_applies_config_and_maps_tools() {
+        let runner = GeminiRunner::new().with_config(GeminiConfig {
+            model: Some("gemini-2.5-pro".to_string()),
+            approval_mode: GeminiApprovalMode::AutoEdit,
+            allowed_tools: vec!["glob".to_string()],
+            checkpointing: true,
+        });
+        let manifest = crate::sandbox::SandboxManifest {
+            allowed_tools: vec!["Read".to_string(), "Glob".to_string()],
+            allowed_commands: vec!["cargo test".to_string()],
+            ..Default::default()
+        };
+        let config = LLMSpawnConfig {
+            prompt: "test".to_string(),
+            working_dir: "/tmp".into(),
+            manifest,
+            model: None,
+            extra_args: Vec::new(),
+            session: Some("s-1".to_string()),
+        };
+
+        let args = runner.build_args(&config);
+        let value = |flag: &str| {
+            let i = args.iter().position(|a| a == flag).unwrap();
+            args[i + 1].clone()
+        };
+
+        assert_eq!(value("--model"), "gemini-2.5-pro");
+        assert_eq!(value("--approval-mode"), "auto_edit");
+        assert_eq!(
+            value("--allowed-tools"),
+            "glob,read_file,run_shell_command(cargo test)"
+        );
+        assert_eq!(value("--resume"), "s-1");
+        assert!(args.contains(&"--checkpointing".to_string()));
+
+        let plain = GeminiRunner::new().build_args(&LLMSpawnConfig {
+            model: Some("flash".to_string()),
+            session: None,
+            manifest: Default::default(),
+            ..config
+        });
+        assert!(!plain.contains(&"--approval-mode".to_string()));
+        assert!(!plain.contains(&"--allowed-tools".to_string()));
+        assert!(plain.contains(&"flash".to_string()));
+    }
+
+    #[test]
+    fn gemini_runner_falls_back_to_flags_of_older_releases() {
+        let config = LLMSpawnConfig {
+            prompt: "test".to_string(),
+            working_dir: "/tmp".into(),
+            manifest: Default::default(),
+            model: None,
+            extra_args: Vec::new(),
+            session: None,
+        };
+        let runner = GeminiRunner::new()
+            .with_config(GeminiConfig {
+                approval_mode: GeminiApprovalMode::Yolo,
+                ..Default::default()
+            })
+            .with_version(CliVersion::new(0, 1, 9));
+
+        let args = runner.build_args(&config);
+
+        assert!(args.contains(&"--yolo".to_string()));
+        assert!(!args.contains(&"--approval-mode".to_string()));
+        assert!(!args.contains(&"--output-format".to_string()));
+
+        let current = runner.with_version(CliVersion::new(0, 11, 0));
+        let args = current.build_args(&config);
+        assert!(args.contains(&"--approval-mode".to_string()));
+        assert!(args.contains(&"stream-json".to_string()));
+    }
+
     #[test]
     fn gemini_runner_parses_stdout_line() {
         let runner = GeminiRunner::new();
@@ -1,12 +1,19 @@
 //! LLM runner implementations for spawning CLI-based LLMs.
 //!
-//! Supports Claude Code and Gemini CLI in headless streaming mode.
+//! Supports Claude Code and Gemini CLI in headless streaming mode, parsing
+//! their `stream-json` output into typed events (see [`stream`]).
 
 mod claude;
+mod fallback;
 mod gemini;
+pub mod stream;
+mod version;
 
 pub use claude::ClaudeRunner;
-pub use gemini::GeminiRunner;
+pub use fallback::{is_overload, FallbackRunner};
+pub use gemini::{GeminiApprovalMode, GeminiConfig, GeminiRunner};
+pub use stream::{StreamEvent, StreamParser, TokenUsage};
+pub use version::{Capabilities, CliVersion, CLAUDE_MIN_VERSION, GEMINI_MIN_VERSION};
 
 use std::path::PathBuf;
 use std::process::ExitStatus;
@@ -14,6 +21,7 @@ use std::process::ExitStatus;
 use async_trait::async_trait;
 use tokio::sync::mpsc;
 
+use crate::audit::ToolCallRecord;
 use crate::error::Result;
 use crate::sandbox::SandboxManifest;
 
@@ -30,6 +38,16 @@ pub enum LLMOutput {
     FileRead(PathBuf),
     /// File write detected.
     FileWrite(PathBuf),
+    /// A tool returned.
+    ToolResult {
+        id: Option<String>,
+        output: String,
+        is_error: bool,
+    },
+    /// Tokens used by the run.
+    Usage(TokenUsage),
+    /// The CLI refused a tool call the sandbox does not permit.
+    PermissionDenied { tool: String, args: String },
 }
 
 /// Configuration for spawning an LLM.
@@ -43,6 +61,10 @@ pub struct LLMSpawnConfig {
     pub manifest: SandboxManifest,
     /// Model to use (e.g., "sonnet", "haiku", "opus").
     pub model: Option<String>,
+    /// Additional CLI arguments, passed before the prompt.
+    pub extra_args: Vec<String>,
+    /// Conversation to continue, from an earlier [`LLMResult::session_id`].
+    pub session: Option<String>,
 }
 
 /// Result of an LLM execution.
@@ -54,6 +76,99 @@ pub struct LLMResult {
     pub output_lines: usize,
     /// Whether the LLM completed successfully.
     pub success: bool,
+    /// Model that served the request, if known.
+    pub model: Option<String>,
+    /// Conversation the invocation ran in, if the CLI reported one. Pass it
+    /// as [`LLMSpawnConfig::session`] to continue the conversation.
+    pub session_id: Option<String>,
+}
+
+/// Converts a stdout line into runner output.
+///
+/// Stream-json events are parsed into typed output, and the session id is
+/// taken from the init event. Other lines go through `fallback`.
+fn line_outputs(
+    parser: &mut StreamParser,
+    line: &str,
+    session_id: &mut Option<String>,
+    fallback: impl FnOnce(&str) -> LLMOutput,
+) -> Vec<LLMOutput> {
+    let Some(events) = parser.feed(line) else {
+        return vec![fallback(line)];
+    };
+    let mut outputs = Vec::new();
+    for event in events {
+        if let StreamEvent::Init {
+            session_id: Some(id),
+            ..
+        } = &event
+        {
+            *session_id = Some(id.clone());
+        }
+        outputs.extend(event.into_outputs());
+    }
+    outputs
+}
+
+/// Sends every output, returning false if the receiver was dropped.
+async fn send_all(
+    output_tx: &mpsc::Sender<LLMOutput>,
+    outputs: impl IntoIterator<Item = LLMOutput>,
+) -> bool {
+    for output in outputs {
+        if output_tx.send(output).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Stdout and outcome of an invocation in an existing sandbox.
+#[derive(Debug)]
+pub struct SandboxRun {
+    /// Result reported by the runner.
+    pub result: LLMResult,
+    /// Stdout lines, joined with newlines.
+    pub output: String,
+    /// Tool calls the CLI refused, as `(tool, args)`.
+    pub denied: Vec<(String, String)>,
+    /// Tool calls the CLI made.
+    pub tool_calls: Vec<ToolCallRecord>,
+}
+
+/// Runs `runner` to completion in a sandbox that already exists, such as a
+/// PR's worktree, collecting its stdout.
+///
+/// Tool calls are traced and recorded, and permission denials are logged
+/// and returned, so callers outside the watcher still see what the LLM did.
+pub async fn run_in_sandbox(runner: &dyn LLMRunner, config: LLMSpawnConfig) -> Result<SandboxRun> {
+    let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
+    let (result, (output, denied, tool_calls)) = tokio::join!(runner.spawn(config, tx), async {
+        let mut lines = Vec::new();
+        let mut denied = Vec::new();
+        let mut tool_calls = Vec::new();
+        while let Some(output) = rx.recv().await {
+            match output {
+                LLMOutput::Stdout(line) => lines.push(line),
+                LLMOutput::ToolCall { tool, args } => {
+                    tracing::debug!(runner = %runner.name(), %tool, %args, "tool call");
+                    tool_calls.push(ToolCallRecord::now(tool, args));
+                }
+                LLMOutput::PermissionDenied { tool, args } => {
+                    tracing::warn!(runner = %runner.name(), %tool, %args, "permission denied");
+                    denied.push((tool, args));
+                }
+                _ => {}
+            }
+        }
+        (lines.join("\n"), denied, tool_calls)
+    });
+    Ok(SandboxRun {
+        result: result?,
+        output,
+        denied,
+        tool_calls,
+    })
 }
 
 /// Trait for LLM runners.
@@ -71,3 +186,64 @@ pub trait LLMRunner: Send + Sync {
     /// Returns the name of this runner.
     fn name(&self) -> &str;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runner that prints two lines and is refused one tool call.
+    struct DeniedRunner;
+
+    #[async_trait]
+    impl LLMRunner for DeniedRunner {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<LLMResult> {
+            send_all(
+                &output_tx,
+                [
+                    LLMOutput::Stdout("first".to_string()),
+                    LLMOutput::PermissionDenied {
+                        tool: "Bash".to_string(),
+                        args: "rm -rf /".to_string(),
+                    },
+                    LLMOutput::Stdout("second".to_string()),
+                ],
+            )
+            .await;
+            Ok(LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 2,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "denied"
+        }
+    }
+
+    #[tokio::test]
+    async fn run_in_sandbox_collects_stdout_and_denials() {
+        let config = LLMSpawnConfig {
+            prompt: "go".to_string(),
+            working_dir: PathBuf::from("."),
+            manifest: SandboxManifest::default(),
+            model: None,
+            extra_args: Vec::new(),
+            session: None,
+        };
+
+        let run = run_in_sandbox(&DeniedRunner, config).await.unwrap();
+        assert!(run.result.success);
+        assert_eq!(run.output, "first\nsecond");
+        assert_eq!(
+            run.denied,
+            vec![("Bash".to_string(), "rm -rf /".to_string())]
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Parsing of `stream-json` CLI output into typed events.
+//!
+//! With `--output-format stream-json`, both CLIs print one JSON object per
+//! line as the conversation happens. [`StreamParser`] turns those lines into
+//! [`StreamEvent`]s, so the monitor and watcher see tool calls, results and
+//! token usage as data instead of matching on free text.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::LLMOutput;
+
+/// Tools whose `file_path`/`path` input is a file being read.
+const READ_TOOLS: &[&str] = &["Read", "read_file", "read_many_files"];
+
+/// Tools whose `file_path`/`path` input is a file being written.
+const WRITE_TOOLS: &[&str] = &[
+    "Write",
+    "Edit",
+    "MultiEdit",
+    "NotebookEdit",
+    "write_file",
+    "replace",
+];
+
+/// Token counts reported by a CLI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Prompt tokens, excluding cache reads.
+    pub input_tokens: u64,
+    /// Generated tokens.
+    pub output_tokens: u64,
+    /// Prompt tokens served from the cache.
+    #[serde(default)]
+    pub cache_read_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Returns all tokens processed.
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens
+    }
+
+    /// Adds `other` to these counts.
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+    }
+}
+
+/// One event in a streamed conversation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// The conversation started.
+    Init {
+        session_id: Option<String>,
+        model: Option<String>,
+    },
+    /// Text written by the assistant.
+    Text(String),
+    /// The assistant invoked a tool.
+    ToolUse {
+        id: Option<String>,
+        name: String,
+        input: Value,
+    },
+    /// A tool returned.
+    ToolResult {
+        id: Option<String>,
+        output: String,
+        is_error: bool,
+    },
+    /// Tokens used by the whole run.
+    Usage(TokenUsage),
+    /// The CLI refused a tool call without running it.
+    PermissionDenied { name: String, input: Value },
+}
+
+impl StreamEvent {
+    /// Converts the event into runner output.
+    ///
+    /// Assistant text becomes [`LLMOutput::Stdout`], so callers collecting
+    /// the response keep working. File tools also report the file touched.
+    pub fn into_outputs(self) -> Vec<LLMOutput> {
+        match self {
+            StreamEvent::Init { .. } => Vec::new(),
+            StreamEvent::Text(text) => vec![LLMOutput::Stdout(text)],
+            StreamEvent::ToolUse { name, input, .. } => {
+                let path = ["file_path", "absolute_path", "path"]
+                    .iter()
+                    .find_map(|key| input.get(key).and_then(Value::as_str))
+                    .map(std::path::PathBuf::from);
+                let mut outputs = vec![LLMOutput::ToolCall {
+                    tool: name.clone(),
+                    args: input.to_string(),
+                }];
+                match path {
+                    Some(path) if READ_TOOLS.contains(&name.as_str()) => {
+                        outputs.push(LLMOutput::FileRead(path))
+                    }
+                    Some(path) if WRITE_TOOLS.contains(&name.as_str()) => {
+                        outputs.push(LLMOutput::FileWrite(path))
+                    }
+                    _ => {}
+                }
+                outputs
+            }
+            StreamEvent::ToolResult {
+                id,
+                output,
+                is_error,
+            } => vec![LLMOutput::ToolResult {
+                id,
+                output,
+                is_error,
+            }],
+            StreamEvent::Usage(usage) => vec![LLMOutput::Usage(usage)],
+            StreamEvent::PermissionDenied { name, input } => vec![LLMOutput::PermissionDenied {
+                tool: name,
+                args: input.to_string(),
+            }],
+        }
+    }
+}
+
+/// Which CLI's event schema to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Claude,
+    Gemini,
+}
+
+/// Turns `stream-json` lines into events.
+///
+/// Gemini streams assistant text in deltas; the parser joins consecutive
+/// deltas into one [`StreamEvent::Text`].
+#[derive(Debug)]
+pub struct StreamParser {
+    dialect: Dialect,
+    pending_text: String,
+}
+
+impl StreamParser {
+    /// Creates a parser for Claude Code output.
+    pub fn claude() -> Self {
+        Self {
+            dialect: Dialect::Claude,
+            pending_text: String::new(),
+        }
+    }
+
+    /// Creates a parser for Gemini CLI output.
+    pub fn gemini() -> Self {
+        Self {
+            dialect: Dialect::Gemini,
+            pending_text: String::new(),
+        }
+    }
+
+    /// Parses one output line.
+    ///
+    /// Returns `None` if the line is not a stream-json event, so callers
+    /// can fall back to treating it as plain text.
+    pub fn feed(&mut self, line: &str) -> Option<Vec<StreamEvent>> {
+        let line = line.trim();
+        if !line.starts_with('{') {
+            return None;
+        }
+        let value: Value = serde_json::from_str(line).ok()?;
+        let kind = value.get("type")?.as_str()?.to_string();
+
+        let mut events = Vec::new();
+        match self.dialect {
+            Dialect::Claude => parse_claude(&kind, &value, &mut events),
+            Dialect::Gemini => {
+                let delta = kind == "message"
+                    && value.get("role").and_then(Value::as_str) == Some("assistant")
+                    && value.get("delta").and_then(Value::as_bool) == Some(true);
+                if delta {
+                    if let Some(text) = value.get("content").and_then(Value::as_str) {
+                        self.pending_text.push_str(text);
+                    }
+                    return Some(events);
+                }
+                events.extend(self.finish());
+                parse_gemini(&kind, &value, &mut events);
+            }
+        }
+        Some(events)
+    }
+
+    /// Flushes buffered text at the end of the stream.
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        if self.pending_text.is_empty() {
+            Vec::new()
+        } else {
+            vec![StreamEvent::Text(std::mem::take(&mut self.pending_text))]
+        }
+    }
+}
+
+/// Parses a Claude Code event (`system`, `assistant`, `user`, `result`).
+fn parse_claude(kind: &str, value: &Value, events: &mut Vec<StreamEvent>) {
+    match kind {
+        "system" => events.push(StreamEvent::Init {
+            session_id: string_field(value, "session_id"),
+            model: string_field(value, "model"),
+        }),
+        "assistant" | "user" => {
+            let blocks = value
+                .pointer("/message/content")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for block in blocks {
+                match block.get("type").and_then(Value::as_str) {
+                    // Text in user messages is the prompt, not the response
+                    Some("text") if kind == "assistant" => {
+                        if let Some(text) = block.get("text").and_then(Value::as_str) {
+                            events.push(StreamEvent::Text(text.to_string()));
+                        }
+                    }
+                    Some("tool_use") => events.push(StreamEvent::ToolUse {
+                        id: string_field(&block, "id"),
+                        name: string_field(&block, "name").unwrap_or_default(),
+                        input: block.get("input").cloned().unwrap_or(Value::Null),
+                    }),
+                    Some("tool_result") => events.push(StreamEvent::ToolResult {
+                        id: string_field(&block, "tool_use_id"),
+                        output: content_text(block.get("content")),
+                        is_error: block
+                            .get("is_error")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        "result" => {
+            // Per-message usage is repeated in the result; only count it once
+            if let Some(usage) = value.get("usage") {
+                events.push(StreamEvent::Usage(TokenUsage {
+                    input_tokens: u64_field(usage, "input_tokens"),
+                    output_tokens: u64_field(usage, "output_tokens"),
+                    cache_read_tokens: u64_field(usage, "cache_read_input_tokens"),
+                }));
+            }
+            let denials = value
+                .get("permission_denials")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten();
+            for denial in denials {
+                events.push(StreamEvent::PermissionDenied {
+                    name: string_field(denial, "tool_name").unwrap_or_default(),
+                    input: denial.get("tool_input").cloned().unwrap_or(Value::Null),
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parses a Gemini CLI event (`init`, `message`, `tool_use`, `tool_result`,
+/// `result`).
+fn parse_gemini(kind: &str, value: &Value, events: &mut Vec<StreamEvent>) {
+    match kind {
+        "init" => events.push(StreamEvent::Init {
+            session_id: string_field(value, "session_id"),
+            model: string_field(value, "model"),
+        }),
+        "message" if value.get("role").and_then(Value::as_str) == Some("assistant") => {
+            if let Some(text) = value.get("content").and_then(Value::as_str) {
+                events.push(StreamEvent::Text(text.to_string()));
+            }
+        }
+        "tool_use" => events.push(StreamEvent::ToolUse {
+            id: string_field(value, "tool_id"),
+            name: string_field(value, "tool_name").unwrap_or_default(),
+            input: value.get("parameters").cloned().unwrap_or(Value::Null),
+        }),
+        "tool_result" => events.push(StreamEvent::ToolResult {
+            id: string_field(value, "tool_id"),
+            output: content_text(
+                value
+                    .get("output")
+                    .or_else(|| value.pointer("/error/message")),
+            ),
+            is_error: value.get("status").and_then(Value::as_str) == Some("error"),
+        }),
+        "result" => {
+            if let Some(stats) = value.get("stats") {
+                events.push(StreamEvent::Usage(TokenUsage {
+                    input_tokens: u64_field(stats, "input_tokens"),
+                    output_tokens: u64_field(stats, "output_tokens"),
+                    cache_read_tokens: u64_field(stats, "cached"),
+                }));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn u64_field(value: &Value, key: &str) -> u64 {
+    value.get(key).and_then(Value::as_u64).unwrap_or(0)
+}
+
+/// Returns the text of tool output given as a string or as content blocks.
+fn content_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_claude_conversation() {
+        let mut parser = StreamParser::claude();
+        let lines = [
+            r#"{"type":"system","subtype":"init","session_id":"s-1","model":"claude-sonnet-4"}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Reading"},{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"src/lib.rs"}}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"fn main() {}"}]}]}}"#,
+            r#"{"type":"result","subtype":"success","usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100}}"#,
+        ];
+
+        let events: Vec<_> = lines.iter().flat_map(|l| parser.feed(l).unwrap()).collect();
+
+        assert_eq!(
+            events[0],
+            StreamEvent::Init {
+                session_id: Some("s-1".to_string()),
+                model: Some("claude-sonnet-4".to_string())
+            }
+        );
+        assert_eq!(events[1], StreamEvent::Text("Reading".to_string()));
+        assert!(
+            matches!(&events[2], StreamEvent::ToolUse { name, input, .. }
+            if name == "Read" && input["file_path"] == "src/lib.rs")
+        );
+        assert!(
+            matches!(&events[3], StreamEvent::ToolResult { id, output, is_error: false }
+            if id.as_deref() == Some("t1") && output == "fn main() {}")
+        );
+        assert_eq!(
+            events[4],
+            StreamEvent::Usage(TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5,
+                cache_read_tokens: 100
+            })
+        );
+    }
+
+    #[test]
+    fn parses_claude_permission_denials() {
+        let mut parser = StreamParser::claude();
+        let line = r#"{"type":"result","subtype":"success","permission_denials":[{"tool_name":"Bash","tool_use_id":"t2","tool_input":{"command":"rm -rf target"}}]}"#;
+
+        let events = parser.feed(line).unwrap();
+
+        assert!(
+            matches!(&events[0], StreamEvent::PermissionDenied { name, input }
+            if name == "Bash" && input["command"] == "rm -rf target")
+        );
+    }
+
+    #[test]
+    fn joins_gemini_text_deltas() {
+        let mut parser = StreamParser::gemini();
+        let mut events = Vec::new();
+        for line in [
+            r#"{"type":"init","session_id":"g-1","model":"gemini-2.5-pro"}"#,
+            r#"{"type":"message","role":"assistant","content":"{\"verdict\": ","delta":true}"#,
+            r#"{"type":"message","role":"assistant","content":"\"approved\"}","delta":true}"#,
+            r#"{"type":"tool_use","tool_name":"write_file","tool_id":"w1","parameters":{"file_path":"a.rs"}}"#,
+            r#"{"type":"tool_result","tool_id":"w1","status":"error","error":{"message":"denied"}}"#,
+            r#"{"type":"result","status":"success","stats":{"input_tokens":7,"output_tokens":3}}"#,
+        ] {
+            events.extend(parser.feed(line).unwrap());
+        }
+        events.extend(parser.finish());
+
+        assert_eq!(
+            events[1],
+            StreamEvent::Text(r#"{"verdict": "approved"}"#.to_string())
+        );
+        assert!(
+            matches!(&events[3], StreamEvent::ToolResult { output, is_error: true, .. }
+            if output == "denied")
+        );
+        assert_eq!(
+            events[4],
+            StreamEvent::Usage(TokenUsage {
+                input_tokens: 7,
+                output_tokens: 3,
+                cache_read_tokens: 0
+            })
+        );
+    }
+
+    #[test]
+    fn plain_lines_are_not_events() {
+        let mut parser = StreamParser::claude();
+
+        assert!(parser.feed("Reading src/lib.rs").is_none());
+        assert!(parser.feed("{not json").is_none());
+    }
+
+    #[test]
+    fn file_tools_report_paths() {
+        let outputs = StreamEvent::ToolUse {
+            id: None,
+            name: "Edit".to_string(),
+            input: serde_json::json!({"file_path": "src/main.rs"}),
+        }
+        .into_outputs();
+
+        assert!(matches!(&outputs[0], LLMOutput::ToolCall { tool, .. } if tool == "Edit"));
+        assert!(matches!(&outputs[1], LLMOutput::FileWrite(p) if p.ends_with("main.rs")));
+    }
+}
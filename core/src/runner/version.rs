@@ -0,0 +1,197 @@
+//! Installed LLM CLI versions and the flags they accept.
+//!
+//! Claude Code and Gemini CLI add and rename flags between releases. Each
+//! runner detects the installed version once with `--version`, refuses
+//! versions older than it supports, and builds its arguments from the
+//! [`Capabilities`] of that version, falling back to older flags where a
+//! newer one is missing. An undetectable version is treated as current.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use tokio::process::Command;
+
+use crate::error::{Error, Result};
+
+/// Oldest Claude Code release the runner supports.
+pub const CLAUDE_MIN_VERSION: CliVersion = CliVersion::new(1, 0, 0);
+
+/// First Claude Code release accepting `--session-id`.
+const CLAUDE_SESSION_ID: CliVersion = CliVersion::new(1, 0, 18);
+
+/// Oldest Gemini CLI release the runner supports.
+pub const GEMINI_MIN_VERSION: CliVersion = CliVersion::new(0, 1, 0);
+
+/// First Gemini CLI release accepting `--approval-mode`; older ones only
+/// have `--yolo`.
+const GEMINI_APPROVAL_MODE: CliVersion = CliVersion::new(0, 2, 0);
+
+/// First Gemini CLI release with `--output-format stream-json`; older ones
+/// print plain text.
+const GEMINI_STREAM_JSON: CliVersion = CliVersion::new(0, 11, 0);
+
+/// A `major.minor.patch` CLI version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CliVersion {
+    /// Major version.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+    /// Patch version.
+    pub patch: u32,
+}
+
+impl CliVersion {
+    /// Creates a version.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Finds the first `major.minor[.patch]` version in `text`, e.g. in
+    /// `gh version 2.40.1 (2023-12-13)` or `1.0.30 (Claude Code)`.
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split_whitespace().find_map(|word| {
+            let word = word.trim_start_matches('v');
+            let mut parts = word.split(['.', '-', '+']).map(|p| p.parse::<u32>().ok());
+            let major = parts.next()??;
+            let minor = parts.next()??;
+            let patch = parts.next().flatten().unwrap_or(0);
+            Some(Self::new(major, minor, patch))
+        })
+    }
+
+    /// Runs `cli --version`, returning `None` if it cannot be run or
+    /// prints no version.
+    pub async fn detect(cli: &str) -> Option<Self> {
+        let output = Command::new(cli).arg("--version").output().await.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+impl fmt::Display for CliVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Flags an installed CLI accepts, where they differ between releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Claude Code: `--session-id` names new conversations.
+    pub session_id: bool,
+    /// Gemini CLI: `--approval-mode` rather than `--yolo`.
+    pub approval_mode: bool,
+    /// Gemini CLI: `--output-format stream-json`.
+    pub stream_json: bool,
+}
+
+impl Capabilities {
+    /// Everything the runners know how to use.
+    pub fn latest() -> Self {
+        Self {
+            session_id: true,
+            approval_mode: true,
+            stream_json: true,
+        }
+    }
+
+    /// Returns what a Claude Code version supports, or an error if it is
+    /// older than [`CLAUDE_MIN_VERSION`].
+    pub fn claude(version: Option<CliVersion>) -> Result<Self> {
+        let Some(version) = version else {
+            return Ok(Self::latest());
+        };
+        require("claude", version, CLAUDE_MIN_VERSION)?;
+        Ok(Self {
+            session_id: version >= CLAUDE_SESSION_ID,
+            ..Self::latest()
+        })
+    }
+
+    /// Returns what a Gemini CLI version supports, or an error if it is
+    /// older than [`GEMINI_MIN_VERSION`].
+    pub fn gemini(version: Option<CliVersion>) -> Result<Self> {
+        let Some(version) = version else {
+            return Ok(Self::latest());
+        };
+        require("gemini", version, GEMINI_MIN_VERSION)?;
+        Ok(Self {
+            approval_mode: version >= GEMINI_APPROVAL_MODE,
+            stream_json: version >= GEMINI_STREAM_JSON,
+            ..Self::latest()
+        })
+    }
+}
+
+/// Fails if `version` of `cli` is older than `min`.
+fn require(cli: &str, version: CliVersion, min: CliVersion) -> Result<()> {
+    if version < min {
+        return Err(Error::Config(format!(
+            "{} {} is not supported; upgrade to {} or newer",
+            cli, version, min
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the version of `cli`, detecting it on first use.
+pub(crate) async fn detected(cell: &OnceLock<Option<CliVersion>>, cli: &str) -> Option<CliVersion> {
+    if let Some(version) = cell.get() {
+        return *version;
+    }
+    let version = CliVersion::detect(cli).await;
+    match version {
+        Some(version) => tracing::debug!(cli = %cli, version = %version, "detected CLI version"),
+        None => tracing::debug!(cli = %cli, "could not detect CLI version; assuming current"),
+    }
+    *cell.get_or_init(|| version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_finds_versions_in_cli_output() {
+        assert_eq!(
+            CliVersion::parse("1.0.30 (Claude Code)"),
+            Some(CliVersion::new(1, 0, 30))
+        );
+        assert_eq!(
+            CliVersion::parse("gh version 2.40.1 (2023-12-13)"),
+            Some(CliVersion::new(2, 40, 1))
+        );
+        assert_eq!(
+            CliVersion::parse("v0.12-nightly"),
+            Some(CliVersion::new(0, 12, 0))
+        );
+        assert_eq!(CliVersion::parse("gemini"), None);
+    }
+
+    #[test]
+    fn capabilities_follow_the_version_matrix() {
+        assert_eq!(Capabilities::claude(None).unwrap(), Capabilities::latest());
+        assert!(
+            !Capabilities::claude(Some(CliVersion::new(1, 0, 3)))
+                .unwrap()
+                .session_id
+        );
+        let err = Capabilities::claude(Some(CliVersion::new(0, 2, 9))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "configuration error: claude 0.2.9 is not supported; upgrade to 1.0.0 or newer"
+        );
+
+        let old = Capabilities::gemini(Some(CliVersion::new(0, 1, 5))).unwrap();
+        assert!(!old.approval_mode && !old.stream_json);
+        let new = Capabilities::gemini(Some(CliVersion::new(0, 11, 0))).unwrap();
+        assert!(new.approval_mode && new.stream_json);
+    }
+}
@@ -0,0 +1,271 @@
+//! Shared dependency caches.
+//!
+//! Every sandbox starts without build artifacts, so package managers
+//! re-download crates, npm packages and wheels each time. A manifest can
+//! list [`SharedCache`]s; when the sandbox is created each cache directory
+//! on the host is symlinked into the sandbox and the package manager's
+//! environment variable is pointed at it.
+//!
+//! The link is to the host directory itself, so package managers and shell
+//! commands run in the sandbox write through to the host cache. `writable`
+//! only decides whether the LLM's own file tools may edit the cache; share
+//! a copy through `path` if the host cache must stay untouched.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+use super::provider::SandboxManifest;
+
+/// Directory inside a sandbox where shared caches are linked.
+pub const CACHE_LINK_DIR: &str = ".improbability-drive/caches";
+
+/// Package manager whose cache can be shared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyCache {
+    /// Cargo home (registry index, downloaded crates, git checkouts).
+    Cargo,
+    /// npm download cache.
+    Npm,
+    /// pip wheel and HTTP cache.
+    Pip,
+}
+
+impl DependencyCache {
+    /// Environment variable that points the package manager at its cache.
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            DependencyCache::Cargo => "CARGO_HOME",
+            DependencyCache::Npm => "npm_config_cache",
+            DependencyCache::Pip => "PIP_CACHE_DIR",
+        }
+    }
+
+    /// Name of the link created under [`CACHE_LINK_DIR`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            DependencyCache::Cargo => "cargo",
+            DependencyCache::Npm => "npm",
+            DependencyCache::Pip => "pip",
+        }
+    }
+
+    /// The host's cache directory, honouring the package manager's own
+    /// environment variable before falling back to its default location.
+    pub fn default_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os(self.env_var()) {
+            return Some(PathBuf::from(dir));
+        }
+        let home = PathBuf::from(std::env::var_os("HOME")?);
+        Some(match self {
+            DependencyCache::Cargo => home.join(".cargo"),
+            DependencyCache::Npm => home.join(".npm"),
+            DependencyCache::Pip => std::env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".cache"))
+                .join("pip"),
+        })
+    }
+}
+
+/// A host cache directory shared into sandboxes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedCache {
+    /// Which package manager the cache belongs to.
+    pub kind: DependencyCache,
+
+    /// Cache directory on the host. Defaults to the package manager's.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Whether the LLM's file tools may edit the cache, not just read it.
+    /// Commands run in the sandbox can write to it either way.
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl SharedCache {
+    /// Creates a share of `kind`'s default cache that the LLM's file tools
+    /// can only read.
+    pub fn new(kind: DependencyCache) -> Self {
+        Self {
+            kind,
+            path: None,
+            writable: false,
+        }
+    }
+
+    /// Shares `path` instead of the default cache directory.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Allows the LLM's file tools to edit the cache.
+    pub fn writable(mut self) -> Self {
+        self.writable = true;
+        self
+    }
+
+    /// Resolves the host directory to share.
+    pub fn source(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(|| self.kind.default_dir())
+    }
+}
+
+/// Links the manifest's shared caches into `sandbox` and records the
+/// resulting environment and path permissions in the manifest.
+///
+/// Caches whose directory does not exist on the host are skipped. The link
+/// directory carries its own `.gitignore` so links are never committed.
+pub fn link_shared_caches(sandbox: &Path, manifest: &mut SandboxManifest) -> Result<()> {
+    if manifest.shared_caches.is_empty() {
+        return Ok(());
+    }
+
+    let link_dir = sandbox.join(CACHE_LINK_DIR);
+    std::fs::create_dir_all(&link_dir)?;
+    std::fs::write(link_dir.join(".gitignore"), "*\n")?;
+
+    for cache in manifest.shared_caches.clone() {
+        let Some(source) = cache.source().filter(|dir| dir.is_dir()) else {
+            tracing::warn!(cache = %cache.kind.name(), "shared cache directory not found, skipping");
+            continue;
+        };
+
+        let link = link_dir.join(cache.kind.name());
+        if !link.exists() {
+            symlink_dir(&source, &link)?;
+        }
+
+        manifest
+            .environment
+            .insert(cache.kind.env_var().to_string(), link.display().to_string());
+
+        let pattern = format!("{}/{}/**", CACHE_LINK_DIR, cache.kind.name());
+        let paths = if cache.writable {
+            &mut manifest.writable_paths
+        } else {
+            &mut manifest.readable_paths
+        };
+        if !paths.contains(&pattern) {
+            paths.push(pattern);
+        }
+
+        tracing::debug!(cache = %cache.kind.name(), source = ?source, "linked shared cache");
+    }
+
+    Ok(())
+}
+
+/// Returns the manifest environment that can be passed to a process as-is,
+/// leaving out `${VAR}` placeholders that still await injection.
+pub fn resolved_environment(manifest: &SandboxManifest) -> HashMap<String, String> {
+    manifest
+        .environment
+        .iter()
+        .filter(|(_, value)| !(value.starts_with("${") && value.ends_with('}')))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+#[cfg(unix)]
+fn symlink_dir(source: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(source: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(source, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn shared_cache_deserializes_from_toml() {
+        let cache: SharedCache = toml::from_str(
+            r#"
+            kind = "npm"
+            writable = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(cache, SharedCache::new(DependencyCache::Npm).writable());
+    }
+
+    #[test]
+    fn links_caches_and_sets_environment() {
+        let host = TempDir::new().unwrap();
+        let sandbox = TempDir::new().unwrap();
+        let mut manifest = SandboxManifest {
+            shared_caches: vec![
+                SharedCache::new(DependencyCache::Cargo).with_path(host.path()),
+                SharedCache::new(DependencyCache::Pip)
+                    .with_path(host.path().join("missing"))
+                    .writable(),
+            ],
+            ..Default::default()
+        };
+        std::fs::write(host.path().join("config.toml"), "[net]\n").unwrap();
+
+        link_shared_caches(sandbox.path(), &mut manifest).unwrap();
+
+        let link = sandbox.path().join(CACHE_LINK_DIR).join("cargo");
+        assert!(link.join("config.toml").exists());
+        assert_eq!(
+            manifest.environment.get("CARGO_HOME"),
+            Some(&link.display().to_string())
+        );
+        assert_eq!(
+            manifest.readable_paths,
+            vec![".improbability-drive/caches/cargo/**"]
+        );
+        // Missing caches are skipped rather than failing the sandbox
+        assert!(!manifest.environment.contains_key("PIP_CACHE_DIR"));
+        assert!(manifest.writable_paths.is_empty());
+    }
+
+    #[test]
+    fn linking_twice_is_idempotent() {
+        let host = TempDir::new().unwrap();
+        let sandbox = TempDir::new().unwrap();
+        let mut manifest = SandboxManifest {
+            shared_caches: vec![SharedCache::new(DependencyCache::Npm)
+                .with_path(host.path())
+                .writable()],
+            ..Default::default()
+        };
+
+        link_shared_caches(sandbox.path(), &mut manifest).unwrap();
+        link_shared_caches(sandbox.path(), &mut manifest).unwrap();
+
+        assert_eq!(manifest.writable_paths.len(), 1);
+    }
+
+    #[test]
+    fn resolved_environment_skips_placeholders() {
+        let mut manifest = SandboxManifest::default();
+        manifest
+            .environment
+            .insert("API_KEY".to_string(), "${API_KEY}".to_string());
+        manifest
+            .environment
+            .insert("CARGO_HOME".to_string(), "/tmp/cargo".to_string());
+
+        let env = resolved_environment(&manifest);
+
+        assert_eq!(env.len(), 1);
+        assert_eq!(
+            env.get("CARGO_HOME").map(String::as_str),
+            Some("/tmp/cargo")
+        );
+    }
+}
@@ -0,0 +1,441 @@
+//! Copy-on-write sandbox implementation.
+//!
+//! Worktrees only contain tracked files, so repositories that rely on large
+//! untracked assets (datasets, model weights, build outputs) get a sandbox
+//! without them. [`CowSandbox`] instead layers the whole working directory:
+//! with overlayfs on Linux, or with a reflink clone on filesystems that
+//! support it (APFS, btrfs, XFS). Either way creation is near-instant and
+//! only the files the LLM changes take up space.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::branch::BranchNamer;
+use crate::error::{Error, Result};
+
+use super::cache::link_shared_caches;
+use super::home::isolate_home;
+use super::provider::{Sandbox, SandboxManifest, SandboxProvider};
+use super::registry::default_sandbox_root;
+
+/// How a copy-on-write sandbox is layered over the repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CowStrategy {
+    /// Mount an overlay (via `fuse-overlayfs`) with the repository as the
+    /// read-only lower layer. Linux only.
+    Overlay,
+    /// Clone the repository with `cp` reflinks, falling back to a plain
+    /// copy on filesystems without reflink support.
+    Reflink,
+}
+
+impl CowStrategy {
+    /// Picks overlayfs when it is available, reflinks otherwise.
+    pub fn detect() -> Self {
+        let overlay = cfg!(target_os = "linux")
+            && Command::new("fuse-overlayfs")
+                .arg("--version")
+                .output()
+                .is_ok_and(|o| o.status.success());
+
+        if overlay {
+            CowStrategy::Overlay
+        } else {
+            CowStrategy::Reflink
+        }
+    }
+}
+
+/// A sandbox backed by a copy-on-write view of the repository.
+///
+/// The sandbox is a full repository of its own; work is committed to
+/// `branch_name` there and brought back with [`CowSandboxInstance::publish`].
+pub struct CowSandboxInstance {
+    /// Path the LLM works in (the overlay mount point or the clone).
+    path: PathBuf,
+    /// Directory holding the sandbox and any overlay layers.
+    layer_dir: PathBuf,
+    /// Path to the parent git repository.
+    repo_path: PathBuf,
+    /// Branch checked out in the sandbox.
+    branch_name: String,
+    /// How the sandbox was layered.
+    strategy: CowStrategy,
+    /// The manifest used to create this sandbox.
+    manifest: SandboxManifest,
+    /// Whether the branch was fetched into the parent repository.
+    published: bool,
+    /// Whether the sandbox has been cleaned up.
+    cleaned_up: bool,
+}
+
+impl CowSandboxInstance {
+    /// Returns the branch checked out in this sandbox.
+    pub fn branch_name(&self) -> &str {
+        &self.branch_name
+    }
+
+    /// Returns how this sandbox was layered.
+    pub fn strategy(&self) -> CowStrategy {
+        self.strategy
+    }
+
+    /// Fetches the sandbox branch into the parent repository so it can be
+    /// pushed and merged like a worktree branch.
+    pub fn publish(&mut self) -> Result<()> {
+        let refspec = format!("+{0}:{0}", self.branch_name);
+        let output = Command::new("git")
+            .current_dir(&self.repo_path)
+            .arg("fetch")
+            .arg(&self.path)
+            .arg(&refspec)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(
+                Error::git_command(&["fetch"], &output).context("failed to fetch sandbox branch")
+            );
+        }
+
+        self.published = true;
+        Ok(())
+    }
+}
+
+impl Sandbox for CowSandboxInstance {
+    fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn manifest(&self) -> &SandboxManifest {
+        &self.manifest
+    }
+
+    fn cleanup(&mut self) -> Result<()> {
+        if self.cleaned_up {
+            return Ok(());
+        }
+
+        if self.strategy == CowStrategy::Overlay {
+            let output = Command::new("fusermount")
+                .args(["-u"])
+                .arg(&self.path)
+                .output()?;
+
+            if !output.status.success() {
+                return Err(Error::SandboxCleanup {
+                    path: self.path.clone(),
+                    reason: String::from_utf8_lossy(&output.stderr).to_string(),
+                });
+            }
+        }
+
+        // Drops the upper layer (or the clone) with everything the LLM wrote
+        if self.layer_dir.exists() {
+            std::fs::remove_dir_all(&self.layer_dir).map_err(|e| Error::SandboxCleanup {
+                path: self.layer_dir.clone(),
+                reason: e.to_string(),
+            })?;
+        }
+
+        if self.published {
+            let output = Command::new("git")
+                .current_dir(&self.repo_path)
+                .args(["branch", "-D", &self.branch_name])
+                .output()?;
+
+            if !output.status.success() {
+                // Branch deletion failure is non-fatal - the sandbox is already gone
+                tracing::warn!(
+                    branch = %self.branch_name,
+                    "failed to delete sandbox branch, may need manual cleanup"
+                );
+            }
+        }
+
+        self.cleaned_up = true;
+        Ok(())
+    }
+}
+
+impl Drop for CowSandboxInstance {
+    fn drop(&mut self) {
+        if !self.cleaned_up {
+            if let Err(e) = self.cleanup() {
+                tracing::error!(error = %e, path = ?self.path, "failed to cleanup sandbox on drop");
+            }
+        }
+    }
+}
+
+/// Provider that creates copy-on-write sandboxes of a repository.
+pub struct CowSandbox {
+    /// Path to the git repository.
+    repo_path: PathBuf,
+    /// Base directory for sandboxes. If None, uses a temp directory.
+    base_dir: Option<PathBuf>,
+    /// How sandboxes are layered.
+    strategy: CowStrategy,
+    /// Generates sandbox branch names.
+    branches: BranchNamer,
+}
+
+impl CowSandbox {
+    /// Creates a copy-on-write sandbox provider using the detected strategy.
+    ///
+    /// If `base_dir` is provided, sandboxes are created there.
+    /// Otherwise, a system temp directory is used.
+    pub fn new(repo_path: PathBuf, base_dir: Option<PathBuf>) -> Self {
+        Self {
+            repo_path,
+            base_dir,
+            strategy: CowStrategy::detect(),
+            branches: BranchNamer::new(),
+        }
+    }
+
+    /// Overrides the detected layering strategy.
+    pub fn with_strategy(mut self, strategy: CowStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the namer for sandbox branches.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    fn generate_branch_name(&self) -> String {
+        self.branches.sandbox_branch()
+    }
+
+    fn get_layer_dir(&self, branch_name: &str) -> Result<PathBuf> {
+        let base = match &self.base_dir {
+            Some(dir) => dir.clone(),
+            None => default_sandbox_root(),
+        };
+
+        std::fs::create_dir_all(&base)?;
+
+        Ok(base.join(BranchNamer::dir_name(branch_name)))
+    }
+
+    /// Mounts an overlay of the repository at `layer_dir/merged`.
+    fn mount_overlay(&self, layer_dir: &Path) -> Result<PathBuf> {
+        let upper = layer_dir.join("upper");
+        let work = layer_dir.join("work");
+        let merged = layer_dir.join("merged");
+        for dir in [&upper, &work, &merged] {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let options = format!(
+            "lowerdir={},upperdir={},workdir={}",
+            self.repo_path.display(),
+            upper.display(),
+            work.display()
+        );
+        let output = Command::new("fuse-overlayfs")
+            .args(["-o", &options])
+            .arg(&merged)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::SandboxCreation(format!(
+                "fuse-overlayfs failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        Ok(merged)
+    }
+
+    /// Clones the repository to `layer_dir/clone` using reflinks.
+    fn clone_reflink(&self, layer_dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(layer_dir)?;
+        let clone = layer_dir.join("clone");
+
+        let mut cmd = Command::new("cp");
+        if cfg!(target_os = "macos") {
+            cmd.args(["-c", "-R"]);
+        } else {
+            cmd.args(["-a", "--reflink=auto"]);
+        }
+        let output = cmd.arg(&self.repo_path).arg(&clone).output()?;
+
+        if !output.status.success() {
+            return Err(Error::SandboxCreation(format!(
+                "reflink clone failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        Ok(clone)
+    }
+}
+
+impl SandboxProvider for CowSandbox {
+    type Sandbox = CowSandboxInstance;
+
+    fn create(&self, manifest: SandboxManifest) -> Result<Self::Sandbox> {
+        let branch_name = self.generate_branch_name();
+        let layer_dir = self.get_layer_dir(&branch_name)?;
+
+        let path = match self.strategy {
+            CowStrategy::Overlay => self.mount_overlay(&layer_dir),
+            CowStrategy::Reflink => self.clone_reflink(&layer_dir),
+        };
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&layer_dir);
+                return Err(e);
+            }
+        };
+
+        let mut instance = CowSandboxInstance {
+            path,
+            layer_dir,
+            repo_path: self.repo_path.clone(),
+            branch_name,
+            strategy: self.strategy,
+            manifest,
+            published: false,
+            cleaned_up: false,
+        };
+
+        // Dropping the instance on failure unmounts and removes the layers
+        let output = Command::new("git")
+            .current_dir(&instance.path)
+            .args(["checkout", "-q", "-b", &instance.branch_name])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::SandboxCreation(format!(
+                "git checkout failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        link_shared_caches(&instance.path, &mut instance.manifest)?;
+        isolate_home(&instance.path, &mut instance.manifest)?;
+
+        tracing::info!(
+            path = ?instance.path,
+            branch = %instance.branch_name,
+            strategy = ?instance.strategy,
+            "created copy-on-write sandbox"
+        );
+
+        Ok(instance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("failed to run git");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Helper to create a temp git repo with an untracked asset.
+    fn create_temp_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let repo = temp_dir.path();
+
+        git(repo, &["init", "-q"]);
+        git(repo, &["config", "user.email", "test@test.com"]);
+        git(repo, &["config", "user.name", "Test User"]);
+        std::fs::write(repo.join("README.md"), "# Test Repo\n").unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-q", "-m", "Initial commit"]);
+
+        std::fs::create_dir_all(repo.join("assets")).unwrap();
+        std::fs::write(repo.join("assets/weights.bin"), [0u8; 1024]).unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn cow_sandbox_reflink_includes_untracked_assets() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().unwrap();
+        let provider = CowSandbox::new(
+            git_repo.path().to_path_buf(),
+            Some(sandbox_dir.path().to_path_buf()),
+        )
+        .with_strategy(CowStrategy::Reflink);
+
+        let mut sandbox = provider.create(SandboxManifest::default()).unwrap();
+        let path = sandbox.path().clone();
+
+        assert_eq!(sandbox.strategy(), CowStrategy::Reflink);
+        assert!(path.join("assets/weights.bin").exists());
+        assert_eq!(
+            git(&path, &["rev-parse", "--abbrev-ref", "HEAD"]),
+            sandbox.branch_name()
+        );
+
+        // Writes stay in the sandbox
+        std::fs::write(path.join("assets/weights.bin"), "changed").unwrap();
+        assert_eq!(
+            std::fs::read(git_repo.path().join("assets/weights.bin"))
+                .unwrap()
+                .len(),
+            1024
+        );
+
+        sandbox.cleanup().unwrap();
+        assert!(!path.exists());
+        assert!(std::fs::read_dir(sandbox_dir.path())
+            .unwrap()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn cow_sandbox_publish_fetches_branch_into_repo() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().unwrap();
+        let provider = CowSandbox::new(
+            git_repo.path().to_path_buf(),
+            Some(sandbox_dir.path().to_path_buf()),
+        )
+        .with_strategy(CowStrategy::Reflink);
+
+        let mut sandbox = provider.create(SandboxManifest::default()).unwrap();
+        let path = sandbox.path().clone();
+        let branch = sandbox.branch_name().to_string();
+
+        std::fs::write(path.join("feature.txt"), "new\n").unwrap();
+        git(&path, &["add", "feature.txt"]);
+        git(&path, &["commit", "-q", "-m", "Add feature"]);
+        sandbox.publish().unwrap();
+
+        let log = git(git_repo.path(), &["log", "--format=%s", "-1", &branch]);
+        assert_eq!(log, "Add feature");
+
+        sandbox.cleanup().unwrap();
+        let branches = git(git_repo.path(), &["branch", "--list", &branch]);
+        assert!(branches.is_empty());
+    }
+
+    #[test]
+    fn cow_sandbox_generates_unique_branch_names() {
+        let git_repo = create_temp_git_repo();
+        let provider = CowSandbox::new(git_repo.path().to_path_buf(), None);
+
+        assert_ne!(
+            provider.generate_branch_name(),
+            provider.generate_branch_name()
+        );
+    }
+}
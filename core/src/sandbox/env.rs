@@ -0,0 +1,155 @@
+//! Environment passed to sandboxed LLM processes.
+//!
+//! Runners start the LLM CLI with a cleared environment. Only parent
+//! variables on the allowlist ([`DEFAULT_ENV_ALLOW`] plus the manifest's
+//! `env_allow`) and not on the manifest's `env_deny` are passed on, so
+//! unrelated tokens in the caller's shell do not reach the LLM or the
+//! commands it runs. The manifest's own `environment` is added on top.
+
+use std::collections::HashMap;
+
+use super::cache::resolved_environment;
+use super::provider::SandboxManifest;
+
+/// Parent variables passed to every sandboxed process: what shells, build
+/// tools, and the LLM CLIs need to run and log in. A trailing `*` matches
+/// any suffix.
+pub const DEFAULT_ENV_ALLOW: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "LANG",
+    "LC_*",
+    "TZ",
+    "TMPDIR",
+    "TMP",
+    "TEMP",
+    "XDG_*",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "GOPATH",
+    "NVM_DIR",
+    "ANTHROPIC_*",
+    "CLAUDE_*",
+    "GEMINI_*",
+    "GOOGLE_API_KEY",
+    "GOOGLE_CLOUD_PROJECT",
+    "GOOGLE_GENAI_USE_VERTEXAI",
+];
+
+/// Returns whether `name` matches `pattern`, where a trailing `*` matches
+/// any suffix.
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// Returns whether the parent variable `name` may be passed to processes
+/// in a sandbox created from `manifest`.
+pub fn env_allowed(manifest: &SandboxManifest, name: &str) -> bool {
+    let allowed = DEFAULT_ENV_ALLOW
+        .iter()
+        .copied()
+        .chain(manifest.env_allow.iter().map(String::as_str))
+        .any(|pattern| matches(pattern, name));
+    allowed
+        && !manifest
+            .env_deny
+            .iter()
+            .any(|pattern| matches(pattern, name))
+}
+
+/// Returns the environment for a sandboxed process: the allowed variables
+/// of `parent` plus the manifest's resolved environment.
+pub fn sanitize_environment(
+    manifest: &SandboxManifest,
+    parent: impl IntoIterator<Item = (String, String)>,
+) -> HashMap<String, String> {
+    let mut env: HashMap<String, String> = parent
+        .into_iter()
+        .filter(|(name, _)| env_allowed(manifest, name))
+        .collect();
+    env.extend(resolved_environment(manifest));
+    env
+}
+
+/// Returns the environment for a sandboxed process, built from this
+/// process's environment.
+pub fn sandbox_environment(manifest: &SandboxManifest) -> HashMap<String, String> {
+    sanitize_environment(manifest, std::env::vars())
+}
+
+/// Returns the sorted names of the variables [`sandbox_environment`]
+/// passes, for the audit trail.
+pub fn sandbox_environment_names(manifest: &SandboxManifest) -> Vec<String> {
+    let mut names: Vec<String> = sandbox_environment(manifest).into_keys().collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parent() -> Vec<(String, String)> {
+        [
+            ("PATH", "/usr/bin"),
+            ("LC_ALL", "C"),
+            ("ANTHROPIC_API_KEY", "sk-1"),
+            ("GH_TOKEN", "ghp_1"),
+            ("AWS_SECRET_ACCESS_KEY", "aws"),
+            ("NPM_TOKEN", "npm"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn only_allowed_parent_variables_are_passed() {
+        let manifest = SandboxManifest::default();
+
+        let env = sanitize_environment(&manifest, parent());
+
+        let mut names: Vec<_> = env.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["ANTHROPIC_API_KEY", "LC_ALL", "PATH"]);
+    }
+
+    #[test]
+    fn manifest_lists_extend_and_override_the_defaults() {
+        let mut manifest = SandboxManifest {
+            env_allow: vec!["NPM_*".to_string()],
+            env_deny: vec!["ANTHROPIC_*".to_string(), "NPM_TOKEN".to_string()],
+            ..Default::default()
+        };
+        manifest
+            .environment
+            .insert("RUST_LOG".to_string(), "debug".to_string());
+        manifest
+            .environment
+            .insert("API_KEY".to_string(), "${API_KEY}".to_string());
+
+        let env = sanitize_environment(&manifest, parent());
+
+        assert!(!env.contains_key("ANTHROPIC_API_KEY"));
+        assert!(!env.contains_key("NPM_TOKEN"));
+        assert!(!env.contains_key("GH_TOKEN"));
+        assert!(!env.contains_key("API_KEY"));
+        assert_eq!(env["RUST_LOG"], "debug");
+        assert!(env_allowed(&manifest, "NPM_CONFIG_CACHE"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Home directories of their own for sandboxed LLM CLIs.
+//!
+//! Claude Code and Gemini CLI keep settings, history, MCP servers and
+//! memories under the user's home directory, and the commands an agent runs
+//! can read or rewrite anything there. A manifest with `isolate_home` gets
+//! a fresh home inside the sandbox instead, seeded with only what the CLIs
+//! need to start logged in: their saved credentials, a minimal settings
+//! file, and the git identity to commit with. Toolchains installed under
+//! the real home stay reachable through their own variables.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::git::{GitExecutor, SystemGit};
+
+use super::provider::SandboxManifest;
+
+/// Directory inside a sandbox used as the LLM's home directory.
+pub const HOME_DIR: &str = ".improbability-drive/home";
+
+/// Login files copied from the real home, relative to it.
+const CREDENTIAL_FILES: &[&str] = &[
+    ".claude/.credentials.json",
+    ".gemini/oauth_creds.json",
+    ".gemini/google_accounts.json",
+];
+
+/// Gemini CLI settings that only select how to log in.
+const GEMINI_AUTH_SETTINGS: &[&str] = &["selectedAuthType", "security"];
+
+/// Toolchain variables pointed at the real home's directories, so the
+/// isolated home does not hide installed compilers and package managers.
+const TOOLCHAIN_DIRS: &[(&str, &str)] = &[
+    ("CARGO_HOME", ".cargo"),
+    ("RUSTUP_HOME", ".rustup"),
+    ("NVM_DIR", ".nvm"),
+    ("GOPATH", "go"),
+];
+
+/// Creates the sandbox's home directory if the manifest asks for one and
+/// points `HOME` and the XDG base directories at it.
+///
+/// Does nothing without `isolate_home` or when the real home is unknown.
+/// The directory carries its own `.gitignore` so copied credentials are
+/// never committed, and is removed with the sandbox.
+pub fn isolate_home(sandbox: &Path, manifest: &mut SandboxManifest) -> Result<()> {
+    if !manifest.isolate_home {
+        return Ok(());
+    }
+    let Some(host) = std::env::var_os("HOME").map(PathBuf::from) else {
+        tracing::warn!("HOME is not set, running with the shared home directory");
+        return Ok(());
+    };
+    seed_home(sandbox, &host, manifest)
+}
+
+fn seed_home(sandbox: &Path, host: &Path, manifest: &mut SandboxManifest) -> Result<()> {
+    let home = sandbox.join(HOME_DIR);
+    std::fs::create_dir_all(&home)?;
+    std::fs::write(home.join(".gitignore"), "*\n")?;
+
+    for file in CREDENTIAL_FILES {
+        let source = host.join(file);
+        if source.is_file() {
+            let target = home.join(file);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&source, &target)?;
+        }
+    }
+
+    // Skips Claude Code's first-run onboarding, which would wait for input
+    std::fs::write(
+        home.join(".claude.json"),
+        "{\n  \"hasCompletedOnboarding\": true\n}\n",
+    )?;
+    if let Some(settings) = gemini_auth_settings(host) {
+        std::fs::create_dir_all(home.join(".gemini"))?;
+        std::fs::write(home.join(".gemini/settings.json"), settings)?;
+    }
+    std::fs::write(home.join(".gitconfig"), git_identity(host))?;
+
+    let env = &mut manifest.environment;
+    let path = |relative: &str| home.join(relative).display().to_string();
+    env.insert("HOME".to_string(), home.display().to_string());
+    env.insert("XDG_CONFIG_HOME".to_string(), path(".config"));
+    env.insert("XDG_CACHE_HOME".to_string(), path(".cache"));
+    env.insert("XDG_DATA_HOME".to_string(), path(".local/share"));
+    env.insert("XDG_STATE_HOME".to_string(), path(".local/state"));
+    if std::env::var_os("CLAUDE_CONFIG_DIR").is_some() {
+        env.insert("CLAUDE_CONFIG_DIR".to_string(), path(".claude"));
+    }
+    for (var, dir) in TOOLCHAIN_DIRS {
+        let dir = host.join(dir);
+        if std::env::var_os(var).is_none() && dir.is_dir() {
+            env.entry(var.to_string())
+                .or_insert_with(|| dir.display().to_string());
+        }
+    }
+
+    tracing::debug!(home = ?home, "isolated sandbox home directory");
+    Ok(())
+}
+
+/// Returns the login selection from the real Gemini CLI settings, dropping
+/// everything else.
+fn gemini_auth_settings(host: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(host.join(".gemini/settings.json")).ok()?;
+    let settings: serde_json::Value = serde_json::from_str(&text).ok()?;
+    let kept: serde_json::Map<_, _> = settings
+        .as_object()?
+        .iter()
+        .filter(|(key, _)| GEMINI_AUTH_SETTINGS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    serde_json::to_string_pretty(&kept).ok()
+}
+
+/// Returns a git config holding only the real user's name and email.
+fn git_identity(host: &Path) -> String {
+    let files = [host.join(".gitconfig"), host.join(".config/git/config")];
+    let lookup = |key: &str| {
+        files.iter().filter(|file| file.is_file()).find_map(|file| {
+            let file = file.display().to_string();
+            SystemGit
+                .run(host, &["config", "--file", &file, "--get", key])
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        })
+    };
+
+    let mut config = String::new();
+    for (key, name) in [("user.name", "name"), ("user.email", "email")] {
+        if let Some(value) = lookup(key) {
+            if config.is_empty() {
+                config.push_str("[user]\n");
+            }
+            config.push_str(&format!("\t{} = {}\n", name, value));
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn host_home() -> TempDir {
+        let host = TempDir::new().unwrap();
+        let write = |file: &str, contents: &str| {
+            let path = host.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(".claude/.credentials.json", r#"{"token":"t"}"#);
+        write(".claude/settings.json", r#"{"hooks":{}}"#);
+        write(".claude/CLAUDE.md", "operator memories");
+        write(
+            ".gemini/settings.json",
+            r#"{"selectedAuthType":"oauth-personal","mcpServers":{"x":{}}}"#,
+        );
+        write(
+            ".gitconfig",
+            "[user]\n\tname = Ada\n\temail = ada@example.com\n[credential]\n\thelper = store\n",
+        );
+        std::fs::create_dir(host.path().join(".rustup")).unwrap();
+        host
+    }
+
+    #[test]
+    fn home_is_seeded_with_logins_only() {
+        let host = host_home();
+        let sandbox = TempDir::new().unwrap();
+        let mut manifest = SandboxManifest {
+            isolate_home: true,
+            ..Default::default()
+        };
+
+        seed_home(sandbox.path(), host.path(), &mut manifest).unwrap();
+
+        let home = sandbox.path().join(HOME_DIR);
+        let read = |file: &str| std::fs::read_to_string(home.join(file)).unwrap();
+        assert_eq!(read(".claude/.credentials.json"), r#"{"token":"t"}"#);
+        assert!(!home.join(".claude/settings.json").exists());
+        assert!(!home.join(".claude/CLAUDE.md").exists());
+        assert!(read(".claude.json").contains("hasCompletedOnboarding"));
+        let gemini: serde_json::Value =
+            serde_json::from_str(&read(".gemini/settings.json")).unwrap();
+        assert_eq!(
+            gemini,
+            serde_json::json!({"selectedAuthType": "oauth-personal"})
+        );
+        assert_eq!(
+            read(".gitconfig"),
+            "[user]\n\tname = Ada\n\temail = ada@example.com\n"
+        );
+        assert_eq!(read(".gitignore"), "*\n");
+
+        let env = &manifest.environment;
+        assert_eq!(env["HOME"], home.display().to_string());
+        assert_eq!(
+            env["XDG_CONFIG_HOME"],
+            home.join(".config").display().to_string()
+        );
+        if std::env::var_os("RUSTUP_HOME").is_none() {
+            assert_eq!(
+                env["RUSTUP_HOME"],
+                host.path().join(".rustup").display().to_string()
+            );
+        }
+        assert!(!env.contains_key("NVM_DIR"));
+    }
+
+    #[test]
+    fn shared_home_is_kept_unless_requested() {
+        let sandbox = TempDir::new().unwrap();
+        let mut manifest = SandboxManifest::default();
+
+        isolate_home(sandbox.path(), &mut manifest).unwrap();
+
+        assert!(!sandbox.path().join(HOME_DIR).exists());
+        assert!(manifest.environment.is_empty());
+    }
+}
@@ -1,10 +1,31 @@
 //! Sandbox module for isolated LLM execution environments.
 //!
 //! This module provides the [`SandboxProvider`] trait for creating isolated
-//! sandboxes and the [`WorktreeSandbox`] implementation using git worktrees.
+//! sandboxes, the [`WorktreeSandbox`] implementation using git worktrees,
+//! the [`CowSandbox`] implementation using copy-on-write layers, and the
+//! [`WorkspaceSandbox`] implementation that uses an existing CI checkout.
 
+mod cache;
+mod cow;
+mod env;
+mod home;
 mod provider;
+mod registry;
+mod workspace;
 mod worktree;
 
-pub use provider::{Sandbox, SandboxManifest, SandboxProvider};
-pub use worktree::WorktreeSandbox;
+pub use cache::{
+    link_shared_caches, resolved_environment, DependencyCache, SharedCache, CACHE_LINK_DIR,
+};
+pub use cow::{CowSandbox, CowSandboxInstance, CowStrategy};
+pub use env::{
+    env_allowed, sandbox_environment, sandbox_environment_names, sanitize_environment,
+    DEFAULT_ENV_ALLOW,
+};
+pub use home::{isolate_home, HOME_DIR};
+pub use provider::{Sandbox, SandboxManifest, SandboxProvider, TaskComplexity};
+pub use registry::{
+    default_sandbox_root, LocationRegistry, Locations, DEFAULT_SANDBOX_DIR, LOCATIONS_FILE,
+};
+pub use workspace::{WorkspaceSandbox, WorkspaceSandboxInstance};
+pub use worktree::{WorktreeSandbox, WorktreeSandboxInstance};
@@ -6,6 +6,8 @@ use std::path::PathBuf;
 
 use crate::error::Result;
 
+use super::cache::SharedCache;
+
 /// Pattern for matching paths (glob-style).
 pub type PathPattern = String;
 
@@ -46,11 +48,46 @@ pub struct SandboxManifest {
     /// Environment variables to inject.
     pub environment: HashMap<String, String>,
 
+    /// Parent environment variables passed to the LLM on top of
+    /// [`DEFAULT_ENV_ALLOW`](super::DEFAULT_ENV_ALLOW). A trailing `*`
+    /// matches any suffix; `["*"]` passes the whole environment.
+    #[serde(default)]
+    pub env_allow: Vec<String>,
+
+    /// Parent environment variables never passed to the LLM, even if
+    /// allowed. A trailing `*` matches any suffix.
+    #[serde(default)]
+    pub env_deny: Vec<String>,
+
     /// Secrets to inject (fetched from secure storage, never logged).
     pub secrets: Vec<SecretRef>,
 
     /// Estimated complexity for timeout tuning.
     pub complexity: TaskComplexity,
+
+    /// Paths to check out in the sandbox (sparse checkout). Empty means the
+    /// whole repository.
+    #[serde(default)]
+    pub sparse_paths: Vec<PathPattern>,
+
+    /// Host dependency caches linked into the sandbox.
+    #[serde(default)]
+    pub shared_caches: Vec<SharedCache>,
+
+    /// Gives the LLM CLI its own home directory inside the sandbox, seeded
+    /// with just its login, instead of the operator's.
+    #[serde(default)]
+    pub isolate_home: bool,
+
+    /// Shell commands run in the sandbox before the LLM starts
+    /// (e.g. `npm ci`, `cargo fetch`).
+    #[serde(default)]
+    pub setup_commands: Vec<CommandPattern>,
+
+    /// New files matching these globs are never committed, in addition to
+    /// those ignored by `.gitignore` (e.g. `dist/**`, `**/*.log`).
+    #[serde(default)]
+    pub commit_deny: Vec<PathPattern>,
 }
 
 /// Represents an active sandbox environment.
@@ -89,6 +126,7 @@ mod tests {
         assert!(manifest.environment.is_empty());
         assert!(manifest.secrets.is_empty());
         assert_eq!(manifest.complexity, TaskComplexity::Medium);
+        assert!(manifest.sparse_paths.is_empty());
     }
 
     #[test]
@@ -99,8 +137,15 @@ mod tests {
             allowed_tools: vec!["Read".to_string(), "Write".to_string()],
             allowed_commands: vec!["cargo test".to_string()],
             environment: HashMap::from([("RUST_BACKTRACE".to_string(), "1".to_string())]),
+            env_allow: vec!["NPM_CONFIG_*".to_string()],
+            env_deny: vec!["ANTHROPIC_ADMIN_KEY".to_string()],
             secrets: vec!["API_KEY".to_string()],
             complexity: TaskComplexity::High,
+            sparse_paths: vec!["services/auth/**".to_string()],
+            shared_caches: vec![],
+            isolate_home: true,
+            setup_commands: vec!["cargo fetch".to_string()],
+            commit_deny: vec!["dist/**".to_string()],
         };
 
         assert_eq!(manifest.readable_paths.len(), 2);
@@ -0,0 +1,146 @@
+//! Registry of where sandboxes and spawn logs were written.
+//!
+//! Sandbox and log locations are configurable, so cleanup tools cannot
+//! assume the defaults. Every spawn records the locations it used in
+//! `.improbability-drive/locations.json` in the repository, and cleanup
+//! reads them back from there.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Registry file, relative to the repository root.
+pub const LOCATIONS_FILE: &str = ".improbability-drive/locations.json";
+
+/// Name of the default sandbox directory under the system temp dir.
+pub const DEFAULT_SANDBOX_DIR: &str = "improbability-drive-sandboxes";
+
+/// Returns the directory sandboxes go in when none is configured.
+pub fn default_sandbox_root() -> PathBuf {
+    std::env::temp_dir().join(DEFAULT_SANDBOX_DIR)
+}
+
+/// Sandbox root and logs directory used together by a spawn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locations {
+    /// Directory sandboxes are created in.
+    pub sandbox_root: PathBuf,
+    /// Directory spawn logs are written to.
+    pub logs_dir: PathBuf,
+    /// When these locations were last used, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub last_used: u64,
+}
+
+impl Locations {
+    /// Creates a record of `sandbox_root` and `logs_dir`, used now.
+    pub fn new(sandbox_root: impl Into<PathBuf>, logs_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            sandbox_root: sandbox_root.into(),
+            logs_dir: logs_dir.into(),
+            last_used: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Every sandbox root and logs directory used for a repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationRegistry {
+    /// Recorded locations, most recently used last.
+    #[serde(default)]
+    pub locations: Vec<Locations>,
+}
+
+impl LocationRegistry {
+    /// Loads the registry of `repo_path`, empty if none was written.
+    pub fn load(repo_path: &Path) -> Result<Self> {
+        let path = repo_path.join(LOCATIONS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::Config(format!("invalid locations {}: {}", path.display(), e)))
+    }
+
+    /// Records `locations` as used in `repo_path`.
+    pub fn record(repo_path: &Path, locations: Locations) -> Result<()> {
+        let mut registry = Self::load(repo_path)?;
+        registry.add(locations);
+        registry.save(repo_path)
+    }
+
+    /// Adds `locations`, replacing an earlier record of the same pair.
+    pub fn add(&mut self, locations: Locations) {
+        self.locations.retain(|l| {
+            l.sandbox_root != locations.sandbox_root || l.logs_dir != locations.logs_dir
+        });
+        self.locations.push(locations);
+    }
+
+    /// Writes the registry to `repo_path`.
+    pub fn save(&self, repo_path: &Path) -> Result<()> {
+        let path = repo_path.join(LOCATIONS_FILE);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("failed to serialize locations: {}", e)))?;
+        std::fs::write(&path, json)?;
+        Ok(())
+    }
+
+    /// Returns every recorded sandbox root, without duplicates.
+    pub fn sandbox_roots(&self) -> Vec<&Path> {
+        unique(self.locations.iter().map(|l| l.sandbox_root.as_path()))
+    }
+
+    /// Returns every recorded logs directory, without duplicates.
+    pub fn logs_dirs(&self) -> Vec<&Path> {
+        unique(self.locations.iter().map(|l| l.logs_dir.as_path()))
+    }
+}
+
+fn unique<'a>(paths: impl Iterator<Item = &'a Path>) -> Vec<&'a Path> {
+    let mut out: Vec<&Path> = Vec::new();
+    for path in paths {
+        if !out.contains(&path) {
+            out.push(path);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn record_keeps_one_entry_per_location_pair() {
+        let repo = TempDir::new().unwrap();
+        assert!(LocationRegistry::load(repo.path())
+            .unwrap()
+            .locations
+            .is_empty());
+
+        LocationRegistry::record(repo.path(), Locations::new("/tmp/a", "/logs/x")).unwrap();
+        LocationRegistry::record(repo.path(), Locations::new("/tmp/b", "/logs/x")).unwrap();
+        LocationRegistry::record(repo.path(), Locations::new("/tmp/a", "/logs/x")).unwrap();
+
+        let registry = LocationRegistry::load(repo.path()).unwrap();
+        assert_eq!(registry.locations.len(), 2);
+        assert_eq!(registry.locations[1].sandbox_root, PathBuf::from("/tmp/a"));
+        assert_eq!(
+            registry.sandbox_roots(),
+            vec![Path::new("/tmp/b"), Path::new("/tmp/a")]
+        );
+        assert_eq!(registry.logs_dirs(), vec![Path::new("/logs/x")]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Sandbox that runs in an existing checkout.
+//!
+//! CI runners such as GitHub Actions already give each job a fresh,
+//! disposable checkout, so a worktree adds nothing but a second copy. The
+//! workspace sandbox uses the checkout itself, optionally on a new branch.
+
+use std::path::PathBuf;
+
+use crate::branch::BranchNamer;
+use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+
+use super::provider::{Sandbox, SandboxManifest, SandboxProvider};
+
+/// A sandbox in an existing checkout.
+///
+/// Cleanup leaves the checkout and branch in place; the checkout belongs
+/// to whoever created it.
+pub struct WorkspaceSandboxInstance {
+    /// Path to the checkout.
+    path: PathBuf,
+    /// Branch checked out in the workspace.
+    branch_name: String,
+    /// The manifest used to create this sandbox.
+    manifest: SandboxManifest,
+}
+
+impl WorkspaceSandboxInstance {
+    /// Returns the branch checked out in the workspace.
+    pub fn branch_name(&self) -> &str {
+        &self.branch_name
+    }
+}
+
+impl Sandbox for WorkspaceSandboxInstance {
+    fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn manifest(&self) -> &SandboxManifest {
+        &self.manifest
+    }
+
+    fn cleanup(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Provider that uses an existing checkout as the sandbox.
+pub struct WorkspaceSandbox {
+    /// Path to the checkout.
+    path: PathBuf,
+    /// Whether each sandbox starts a new branch.
+    new_branch: bool,
+    /// Generates sandbox branch names.
+    branches: BranchNamer,
+}
+
+impl WorkspaceSandbox {
+    /// Creates a provider for the checkout at `path`. Each sandbox starts a
+    /// new branch from `HEAD`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            new_branch: true,
+            branches: BranchNamer::new(),
+        }
+    }
+
+    /// Sets the namer for new sandbox branches.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    /// Keeps the branch that is checked out, e.g. a PR's head branch in a
+    /// fixer round.
+    pub fn on_current_branch(mut self) -> Self {
+        self.new_branch = false;
+        self
+    }
+}
+
+impl SandboxProvider for WorkspaceSandbox {
+    type Sandbox = WorkspaceSandboxInstance;
+
+    fn create(&self, manifest: SandboxManifest) -> Result<Self::Sandbox> {
+        if !self.path.join(".git").exists() {
+            return Err(Error::SandboxCreation(format!(
+                "{} is not a git checkout",
+                self.path.display()
+            )));
+        }
+
+        let branch_name = if self.new_branch {
+            let branch = self.branches.sandbox_branch();
+            SystemGit
+                .run(&self.path, &["checkout", "-q", "-b", &branch])
+                .map_err(|e| Error::SandboxCreation(e.to_string()))?;
+            branch
+        } else {
+            SystemGit
+                .run(&self.path, &["rev-parse", "--abbrev-ref", "HEAD"])
+                .map_err(|e| Error::SandboxCreation(e.to_string()))?
+                .trim()
+                .to_string()
+        };
+
+        tracing::info!(
+            path = ?self.path,
+            branch = %branch_name,
+            "using workspace as sandbox"
+        );
+
+        Ok(WorkspaceSandboxInstance {
+            path: self.path.clone(),
+            branch_name,
+            manifest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for args in [
+            vec!["init", "-q", "-b", "main"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+            vec!["commit", "-q", "--allow-empty", "-m", "Initial"],
+        ] {
+            SystemGit.run(dir.path(), &args).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn create_starts_a_branch_in_the_checkout() {
+        let repo = create_test_repo();
+        let provider = WorkspaceSandbox::new(repo.path());
+
+        let mut sandbox = provider.create(SandboxManifest::default()).unwrap();
+
+        assert_eq!(sandbox.path(), &repo.path().to_path_buf());
+        assert!(sandbox.branch_name().starts_with("spawn-sandbox-"));
+        assert_eq!(
+            SystemGit
+                .run(repo.path(), &["rev-parse", "--abbrev-ref", "HEAD"])
+                .unwrap()
+                .trim(),
+            sandbox.branch_name()
+        );
+        sandbox.cleanup().unwrap();
+        assert!(repo.path().exists());
+    }
+
+    #[test]
+    fn on_current_branch_keeps_head() {
+        let repo = create_test_repo();
+        let provider = WorkspaceSandbox::new(repo.path()).on_current_branch();
+
+        let sandbox = provider.create(SandboxManifest::default()).unwrap();
+
+        assert_eq!(sandbox.branch_name(), "main");
+    }
+
+    #[test]
+    fn create_fails_outside_a_checkout() {
+        let dir = TempDir::new().unwrap();
+
+        let result = WorkspaceSandbox::new(dir.path()).create(SandboxManifest::default());
+
+        assert!(matches!(result, Err(Error::SandboxCreation(_))));
+    }
+}
@@ -1,11 +1,17 @@
 //! Git worktree-based sandbox implementation.
 
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use crate::branch::BranchNamer;
 use crate::error::{Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::instructions;
 
+use super::cache::link_shared_caches;
+use super::home::isolate_home;
 use super::provider::{Sandbox, SandboxManifest, SandboxProvider};
+use super::registry::default_sandbox_root;
 
 /// A sandbox implemented using git worktrees.
 ///
@@ -22,6 +28,15 @@ pub struct WorktreeSandboxInstance {
     manifest: SandboxManifest,
     /// Whether the sandbox has been cleaned up.
     cleaned_up: bool,
+    /// Runs git.
+    git: Arc<dyn GitExecutor>,
+}
+
+impl WorktreeSandboxInstance {
+    /// Returns the branch checked out in this worktree.
+    pub fn branch_name(&self) -> &str {
+        &self.branch_name
+    }
 }
 
 impl Sandbox for WorktreeSandboxInstance {
@@ -39,27 +54,24 @@ impl Sandbox for WorktreeSandboxInstance {
         }
 
         // Remove the worktree (must run from parent repo)
-        let output = Command::new("git")
-            .current_dir(&self.repo_path)
-            .args(["worktree", "remove", "--force"])
-            .arg(&self.path)
-            .output()?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        let path = path_str(&self.path)?;
+        let output = self
+            .git
+            .output(&self.repo_path, &["worktree", "remove", "--force", path])?;
+
+        if !output.success() {
             return Err(Error::SandboxCleanup {
                 path: self.path.clone(),
-                reason: stderr.to_string(),
+                reason: output.stderr,
             });
         }
 
         // Delete the branch (must run from parent repo)
-        let output = Command::new("git")
-            .current_dir(&self.repo_path)
-            .args(["branch", "-D", &self.branch_name])
-            .output()?;
+        let output = self
+            .git
+            .output(&self.repo_path, &["branch", "-D", &self.branch_name])?;
 
-        if !output.status.success() {
+        if !output.success() {
             // Branch deletion failure is non-fatal - worktree is already gone
             tracing::warn!(
                 branch = %self.branch_name,
@@ -88,8 +100,10 @@ pub struct WorktreeSandbox {
     repo_path: PathBuf,
     /// Base directory for worktrees. If None, uses a temp directory.
     base_dir: Option<PathBuf>,
-    /// Counter for generating unique branch names.
-    counter: std::sync::atomic::AtomicU64,
+    /// Generates sandbox branch names.
+    branches: BranchNamer,
+    /// Runs git.
+    git: Arc<dyn GitExecutor>,
 }
 
 impl WorktreeSandbox {
@@ -102,76 +116,152 @@ impl WorktreeSandbox {
         Self {
             repo_path,
             base_dir,
-            counter: std::sync::atomic::AtomicU64::new(0),
+            branches: BranchNamer::new(),
+            git: Arc::new(SystemGit),
         }
     }
 
+    /// Sets the namer for sandbox branches.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    /// Runs git through `git` instead of the system binary.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
     fn generate_branch_name(&self) -> String {
-        let id = self
-            .counter
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        format!("spawn-sandbox-{}-{}", timestamp, id)
+        self.branches.sandbox_branch()
     }
 
     fn get_worktree_path(&self, branch_name: &str) -> Result<PathBuf> {
         let base = match &self.base_dir {
             Some(dir) => dir.clone(),
-            None => std::env::temp_dir().join("improbability-drive-sandboxes"),
+            None => default_sandbox_root(),
         };
 
         // Ensure base directory exists
         std::fs::create_dir_all(&base)?;
 
-        Ok(base.join(branch_name))
+        Ok(base.join(BranchNamer::dir_name(branch_name)))
     }
-}
 
-impl SandboxProvider for WorktreeSandbox {
-    type Sandbox = WorktreeSandboxInstance;
-
-    fn create(&self, manifest: SandboxManifest) -> Result<Self::Sandbox> {
+    /// Creates a sandbox whose branch starts at `base_ref` instead of `HEAD`.
+    ///
+    /// Used to start dependent tasks from the merged output of the tasks
+    /// they depend on.
+    pub fn create_from(
+        &self,
+        manifest: SandboxManifest,
+        base_ref: &str,
+    ) -> Result<WorktreeSandboxInstance> {
         let branch_name = self.generate_branch_name();
         let worktree_path = self.get_worktree_path(&branch_name)?;
 
-        // Create the worktree with a new branch (run from repo dir)
-        let output = Command::new("git")
-            .current_dir(&self.repo_path)
-            .args(["worktree", "add", "-b", &branch_name])
-            .arg(&worktree_path)
-            .arg("HEAD")
-            .output()?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        // Create the worktree with a new branch (run from repo dir). Sparse
+        // sandboxes skip the checkout until the sparse patterns are set.
+        let sparse = !manifest.sparse_paths.is_empty();
+        let mut args = vec!["worktree", "add"];
+        if sparse {
+            args.push("--no-checkout");
+        }
+        args.extend([
+            "-b",
+            branch_name.as_str(),
+            path_str(&worktree_path)?,
+            base_ref,
+        ]);
+        let output = self.git.output(&self.repo_path, &args)?;
+
+        if !output.success() {
             return Err(Error::SandboxCreation(format!(
                 "git worktree add failed: {}",
-                stderr
+                output.stderr
             )));
         }
 
-        tracing::info!(
-            path = ?worktree_path,
-            branch = %branch_name,
-            "created sandbox worktree"
-        );
-
-        Ok(WorktreeSandboxInstance {
+        let mut instance = WorktreeSandboxInstance {
             path: worktree_path,
             repo_path: self.repo_path.clone(),
             branch_name,
             manifest,
             cleaned_up: false,
-        })
+            git: self.git.clone(),
+        };
+
+        // Dropping the instance on failure removes the half-created worktree
+        if sparse {
+            sparse_checkout(
+                self.git.as_ref(),
+                &instance.path,
+                &instance.branch_name,
+                &instance.manifest.sparse_paths,
+            )?;
+        }
+        link_shared_caches(&instance.path, &mut instance.manifest)?;
+        isolate_home(&instance.path, &mut instance.manifest)?;
+        instructions::carry_into(&self.repo_path, &instance.path)?;
+
+        tracing::info!(
+            path = ?instance.path,
+            branch = %instance.branch_name,
+            base = %base_ref,
+            sparse,
+            "created sandbox worktree"
+        );
+
+        Ok(instance)
+    }
+}
+
+/// Limits a `--no-checkout` worktree to `patterns`, then checks it out.
+///
+/// Patterns use gitignore syntax (non-cone mode). Files at the repository
+/// root are always included so top-level manifests and configs are present.
+fn sparse_checkout(
+    git: &dyn GitExecutor,
+    worktree: &Path,
+    branch: &str,
+    patterns: &[String],
+) -> Result<()> {
+    let mut set = vec!["sparse-checkout", "set", "--no-cone", "/*", "!/*/"];
+    set.extend(patterns.iter().map(String::as_str));
+
+    for args in [set, vec!["checkout", "-q", branch]] {
+        let output = git.output(worktree, &args)?;
+
+        if !output.success() {
+            return Err(Error::SandboxCreation(format!(
+                "git {} failed: {}",
+                args[0], output.stderr
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| Error::InvalidPath(path.to_path_buf()))
+}
+
+impl SandboxProvider for WorktreeSandbox {
+    type Sandbox = WorktreeSandboxInstance;
+
+    fn create(&self, manifest: SandboxManifest) -> Result<Self::Sandbox> {
+        self.create_from(manifest, "HEAD")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::{GitOutput, MockGit};
+    use std::process::Command;
     use tempfile::TempDir;
 
     /// Helper to create a temp git repo for testing.
@@ -299,6 +389,40 @@ mod tests {
             .expect("second cleanup should be idempotent");
     }
 
+    #[test]
+    fn worktree_sandbox_create_from_starts_at_base_ref() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+
+        // Commit a file on a separate branch
+        let repo = git_repo.path();
+        Command::new("git")
+            .args(["checkout", "-q", "-b", "feature"])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        std::fs::write(repo.join("feature.txt"), "feature\n").unwrap();
+        for args in [
+            vec!["add", "."],
+            vec!["commit", "-q", "-m", "Add feature"],
+            vec!["checkout", "-q", "-"],
+        ] {
+            Command::new("git").args(&args).current_dir(repo).output().unwrap();
+        }
+
+        let provider =
+            WorktreeSandbox::new(repo.to_path_buf(), Some(sandbox_dir.path().to_path_buf()));
+
+        let from_head = provider.create(SandboxManifest::default()).unwrap();
+        let from_feature = provider
+            .create_from(SandboxManifest::default(), "feature")
+            .unwrap();
+
+        assert!(!from_head.path().join("feature.txt").exists());
+        assert!(from_feature.path().join("feature.txt").exists());
+        assert!(from_feature.branch_name().starts_with("spawn-sandbox-"));
+    }
+
     #[test]
     fn worktree_sandbox_exposes_manifest() {
         let git_repo = create_temp_git_repo();
@@ -322,4 +446,68 @@ mod tests {
         assert_eq!(sandbox.manifest().readable_paths, manifest.readable_paths);
         assert_eq!(sandbox.manifest().allowed_tools, manifest.allowed_tools);
     }
+
+    #[test]
+    fn worktree_sandbox_sparse_checkout_limits_paths() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+
+        let repo = git_repo.path();
+        for dir in ["services/auth", "services/billing", "web"] {
+            std::fs::create_dir_all(repo.join(dir)).unwrap();
+            std::fs::write(repo.join(dir).join("mod.rs"), "// code\n").unwrap();
+        }
+        for args in [vec!["add", "."], vec!["commit", "-q", "-m", "Add services"]] {
+            Command::new("git").args(&args).current_dir(repo).output().unwrap();
+        }
+
+        let provider =
+            WorktreeSandbox::new(repo.to_path_buf(), Some(sandbox_dir.path().to_path_buf()));
+        let manifest = SandboxManifest {
+            sparse_paths: vec!["services/auth/".to_string()],
+            ..Default::default()
+        };
+
+        let mut sandbox = provider.create(manifest).expect("failed to create sandbox");
+        let path = sandbox.path().clone();
+
+        assert!(path.join("README.md").exists());
+        assert!(path.join("services/auth/mod.rs").exists());
+        assert!(!path.join("services/billing").exists());
+        assert!(!path.join("web").exists());
+
+        // The main checkout is not affected
+        assert!(repo.join("web/mod.rs").exists());
+        let full = provider.create(SandboxManifest::default()).unwrap();
+        assert!(full.path().join("web/mod.rs").exists());
+
+        sandbox.cleanup().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn failed_worktree_removal_keeps_the_branch() {
+        let git = Arc::new(MockGit::new().on(
+            &["worktree", "remove"],
+            GitOutput::failed(128, "fatal: not a working tree"),
+        ));
+        let mut sandbox = WorktreeSandboxInstance {
+            path: PathBuf::from("/sandboxes/task-1"),
+            repo_path: PathBuf::from("/repo"),
+            branch_name: "drive/task-1".to_string(),
+            manifest: SandboxManifest::default(),
+            cleaned_up: false,
+            git: git.clone(),
+        };
+
+        let err = sandbox.cleanup().unwrap_err();
+        // Keep the drop from trying again
+        sandbox.cleaned_up = true;
+
+        assert!(err.to_string().contains("not a working tree"));
+        assert_eq!(
+            git.calls(),
+            [vec!["worktree", "remove", "--force", "/sandboxes/task-1"]]
+        );
+    }
 }
@@ -0,0 +1,374 @@
+//! Project scaffolds for new-project prompts.
+//!
+//! An empty repository gives the planning LLM nothing to anchor on, and the
+//! first build task spends its budget on boilerplate. A scaffold writes a
+//! small, compilable skeleton for a common stack so planning starts from a
+//! project that already builds.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Built-in project template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scaffold {
+    /// Rust library crate.
+    RustLib,
+    /// Rust HTTP service using Axum and Tokio.
+    AxumService,
+    /// Next.js application with TypeScript.
+    NextApp,
+    /// Python package with pytest.
+    PythonPackage,
+}
+
+/// Every built-in scaffold.
+pub const SCAFFOLDS: &[Scaffold] = &[
+    Scaffold::RustLib,
+    Scaffold::AxumService,
+    Scaffold::NextApp,
+    Scaffold::PythonPackage,
+];
+
+impl Scaffold {
+    /// Looks up a scaffold by its configuration name (e.g. `axum-service`).
+    pub fn from_name(name: &str) -> Result<Self> {
+        SCAFFOLDS
+            .iter()
+            .copied()
+            .find(|s| s.name() == name)
+            .ok_or_else(|| {
+                let known: Vec<_> = SCAFFOLDS.iter().map(|s| s.name()).collect();
+                Error::Config(format!(
+                    "unknown scaffold '{}' - known: {}",
+                    name,
+                    known.join(", ")
+                ))
+            })
+    }
+
+    /// Picks a scaffold from keywords in a new-project prompt.
+    pub fn detect(prompt: &str) -> Option<Self> {
+        let prompt = prompt.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| prompt.contains(w));
+
+        if has(&["next.js", "nextjs", "react app", "frontend"]) {
+            Some(Scaffold::NextApp)
+        } else if has(&["python", "pypi", "django", "flask", "fastapi"]) {
+            Some(Scaffold::PythonPackage)
+        } else if has(&[
+            "axum",
+            "web service",
+            "http service",
+            "rest api",
+            "api server",
+        ]) && has(&["rust"])
+        {
+            Some(Scaffold::AxumService)
+        } else if has(&["rust"]) {
+            Some(Scaffold::RustLib)
+        } else {
+            None
+        }
+    }
+
+    /// Configuration name of the scaffold.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scaffold::RustLib => "rust-lib",
+            Scaffold::AxumService => "axum-service",
+            Scaffold::NextApp => "next-app",
+            Scaffold::PythonPackage => "python-package",
+        }
+    }
+
+    /// Files in the scaffold as `(path, content)`, with `{name}` and
+    /// `{module}` placeholders.
+    fn files(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Scaffold::RustLib => RUST_LIB,
+            Scaffold::AxumService => AXUM_SERVICE,
+            Scaffold::NextApp => NEXT_APP,
+            Scaffold::PythonPackage => PYTHON_PACKAGE,
+        }
+    }
+
+    /// Writes the scaffold into `dir` for a project called `name`.
+    ///
+    /// Files that already exist are left untouched. Returns the files that
+    /// were written, relative to `dir`.
+    pub fn apply(&self, dir: &Path, name: &str) -> Result<Vec<PathBuf>> {
+        let module = name.replace('-', "_");
+        let mut written = Vec::new();
+
+        for (path, content) in self.files() {
+            let relative = PathBuf::from(path.replace("{module}", &module));
+            let target = dir.join(&relative);
+            if target.exists() {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let content = content.replace("{name}", name).replace("{module}", &module);
+            std::fs::write(&target, content)?;
+            written.push(relative);
+        }
+
+        tracing::info!(scaffold = %self.name(), files = written.len(), "applied scaffold");
+        Ok(written)
+    }
+}
+
+const RUST_LIB: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+"#,
+    ),
+    (
+        "src/lib.rs",
+        r#"//! {name}
+
+/// Returns the crate name.
+pub fn name() -> &'static str {
+    "{name}"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_is_crate_name() {
+        assert_eq!(name(), "{name}");
+    }
+}
+"#,
+    ),
+    (".gitignore", "/target\n"),
+];
+
+const AXUM_SERVICE: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+axum = "0.7"
+tokio = { version = "1", features = ["full"] }
+"#,
+    ),
+    (
+        "src/main.rs",
+        r#"use axum::{routing::get, Router};
+
+fn app() -> Router {
+    Router::new().route("/health", get(|| async { "ok" }))
+}
+
+#[tokio::main]
+async fn main() {
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
+        .await
+        .expect("failed to bind");
+    axum::serve(listener, app()).await.expect("server error");
+}
+"#,
+    ),
+    (".gitignore", "/target\n"),
+];
+
+const NEXT_APP: &[(&str, &str)] = &[
+    (
+        "package.json",
+        r#"{
+  "name": "{name}",
+  "version": "0.1.0",
+  "private": true,
+  "scripts": {
+    "dev": "next dev",
+    "build": "next build",
+    "start": "next start",
+    "test": "next lint"
+  },
+  "dependencies": {
+    "next": "^14.2.0",
+    "react": "^18.3.0",
+    "react-dom": "^18.3.0"
+  },
+  "devDependencies": {
+    "@types/node": "^20.0.0",
+    "@types/react": "^18.3.0",
+    "eslint": "^8.57.0",
+    "eslint-config-next": "^14.2.0",
+    "typescript": "^5.4.0"
+  }
+}
+"#,
+    ),
+    (
+        "tsconfig.json",
+        r#"{
+  "compilerOptions": {
+    "target": "es2017",
+    "lib": ["dom", "dom.iterable", "esnext"],
+    "strict": true,
+    "noEmit": true,
+    "esModuleInterop": true,
+    "module": "esnext",
+    "moduleResolution": "bundler",
+    "resolveJsonModule": true,
+    "isolatedModules": true,
+    "jsx": "preserve",
+    "incremental": true,
+    "plugins": [{ "name": "next" }]
+  },
+  "include": ["next-env.d.ts", "**/*.ts", "**/*.tsx", ".next/types/**/*.ts"],
+  "exclude": ["node_modules"]
+}
+"#,
+    ),
+    (
+        ".eslintrc.json",
+        "{ \"extends\": \"next/core-web-vitals\" }\n",
+    ),
+    (
+        "app/layout.tsx",
+        r#"export const metadata = { title: "{name}" };
+
+export default function RootLayout({ children }: { children: React.ReactNode }) {
+  return (
+    <html lang="en">
+      <body>{children}</body>
+    </html>
+  );
+}
+"#,
+    ),
+    (
+        "app/page.tsx",
+        r#"export default function Home() {
+  return <main>{name}</main>;
+}
+"#,
+    ),
+    (".gitignore", "/node_modules\n/.next\n"),
+];
+
+const PYTHON_PACKAGE: &[(&str, &str)] = &[
+    (
+        "pyproject.toml",
+        r#"[build-system]
+requires = ["setuptools>=68"]
+build-backend = "setuptools.build_meta"
+
+[project]
+name = "{name}"
+version = "0.1.0"
+requires-python = ">=3.10"
+
+[project.optional-dependencies]
+test = ["pytest"]
+"#,
+    ),
+    (
+        "src/{module}/__init__.py",
+        r#""""{name}."""
+
+__version__ = "0.1.0"
+"#,
+    ),
+    (
+        "tests/test_version.py",
+        r#"import {module}
+
+
+def test_version():
+    assert {module}.__version__ == "0.1.0"
+"#,
+    ),
+    (".gitignore", "__pycache__/\n*.egg-info/\n.venv/\n"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detects_stack_from_prompt() {
+        assert_eq!(
+            Scaffold::detect("Build a REST API in Rust with axum"),
+            Some(Scaffold::AxumService)
+        );
+        assert_eq!(
+            Scaffold::detect("A rust library for parsing TOML"),
+            Some(Scaffold::RustLib)
+        );
+        assert_eq!(
+            Scaffold::detect("Next.js dashboard for metrics"),
+            Some(Scaffold::NextApp)
+        );
+        assert_eq!(
+            Scaffold::detect("python CLI that resizes images"),
+            Some(Scaffold::PythonPackage)
+        );
+        assert_eq!(Scaffold::detect("Write a haiku"), None);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for scaffold in SCAFFOLDS {
+            assert_eq!(Scaffold::from_name(scaffold.name()).unwrap(), *scaffold);
+        }
+        assert!(Scaffold::from_name("cobol-app").is_err());
+    }
+
+    #[test]
+    fn apply_substitutes_names_and_keeps_existing_files() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "custom\n").unwrap();
+
+        let written = Scaffold::PythonPackage
+            .apply(dir.path(), "image-tools")
+            .unwrap();
+
+        assert!(written.contains(&PathBuf::from("src/image_tools/__init__.py")));
+        assert!(!written.contains(&PathBuf::from(".gitignore")));
+        let test = std::fs::read_to_string(dir.path().join("tests/test_version.py")).unwrap();
+        assert!(test.starts_with("import image_tools\n"));
+        let gitignore = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        assert_eq!(gitignore, "custom\n");
+    }
+
+    #[test]
+    fn rust_lib_scaffold_compiles() {
+        let dir = TempDir::new().unwrap();
+        Scaffold::RustLib.apply(dir.path(), "demo-lib").unwrap();
+
+        let output = std::process::Command::new("cargo")
+            .args(["test", "--offline", "--quiet"])
+            .current_dir(dir.path())
+            .env("CARGO_TARGET_DIR", dir.path().join("target"))
+            .output()
+            .expect("failed to run cargo");
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
@@ -68,7 +68,7 @@ impl SecretsManager {
             self.redaction_patterns.push(value);
             // Sort by length descending so longer patterns are replaced first
             self.redaction_patterns
-                .sort_by(|a, b| b.len().cmp(&a.len()));
+                .sort_by_key(|p| std::cmp::Reverse(p.len()));
         }
 
         Ok(())
@@ -0,0 +1,345 @@
+//! Security review findings.
+//!
+//! The security reviewer reports findings as a JSON array with severity,
+//! location, CWE, and recommendation. Keyword heuristics are only used
+//! when the reviewer's output contains no parseable findings.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prompt::{budget, PromptTemplates, TemplateVars};
+use crate::team::{ReviewSeverity, ReviewSuggestion};
+
+/// Model assumed for token estimation when none is configured.
+const DEFAULT_REVIEWER_MODEL: &str = "gemini-cli";
+
+/// Phrases that mark a line of free-form output as a security finding.
+const SECURITY_KEYWORDS: &[&str] = &[
+    "cwe-",
+    "vulnerab",
+    "injection",
+    "xss",
+    "cross-site",
+    "csrf",
+    "hardcoded secret",
+    "hardcoded password",
+    "hardcoded credential",
+    "path traversal",
+    "remote code execution",
+    "insecure deserialization",
+    "privilege escalation",
+];
+
+/// A security issue reported by the reviewer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityFinding {
+    /// Severity of the issue.
+    #[serde(default = "default_severity")]
+    pub severity: ReviewSeverity,
+    /// File the issue is in, if known.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Line number, if known.
+    #[serde(default)]
+    pub line: Option<u32>,
+    /// CWE identifier (e.g., "CWE-89").
+    #[serde(default)]
+    pub cwe: Option<String>,
+    /// Description of the issue.
+    pub issue: String,
+    /// How to fix the issue.
+    #[serde(default)]
+    pub recommendation: String,
+    /// Whether the finding came from keyword heuristics rather than
+    /// structured output.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub heuristic: bool,
+}
+
+fn default_severity() -> ReviewSeverity {
+    ReviewSeverity::High
+}
+
+impl SecurityFinding {
+    /// Converts the finding into a review suggestion for gating and fixing.
+    pub fn to_suggestion(&self) -> ReviewSuggestion {
+        let issue = match &self.cwe {
+            Some(cwe) => format!("[{}] {}", cwe, self.issue),
+            None => self.issue.clone(),
+        };
+        ReviewSuggestion {
+            file: self.file.clone().unwrap_or_default(),
+            line: self.line,
+            issue,
+            suggestion: self.recommendation.clone(),
+            severity: self.severity,
+        }
+    }
+}
+
+/// Parses security findings from reviewer output.
+///
+/// Accepts a JSON array of findings or an object with a `findings` array,
+/// optionally inside a fenced code block. Entries missing an `issue` are
+/// skipped. If no JSON is found, falls back to [`heuristic_findings`].
+pub fn parse_security_findings(output: &str) -> Vec<SecurityFinding> {
+    match parse_structured(output) {
+        Some(findings) => findings,
+        None => {
+            tracing::warn!("security review had no structured findings, using heuristics");
+            heuristic_findings(output)
+        }
+    }
+}
+
+/// Parses structured findings, or `None` if the output holds no findings JSON.
+fn parse_structured(output: &str) -> Option<Vec<SecurityFinding>> {
+    let candidates = [('{', '}'), ('[', ']')];
+    let value = candidates.iter().find_map(|(open, close)| {
+        let start = output.find(*open)?;
+        let end = output.rfind(*close)?;
+        (start < end)
+            .then(|| serde_json::from_str::<serde_json::Value>(&output[start..=end]).ok())
+            .flatten()
+    })?;
+
+    let items = match &value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(map) => map.get("findings")?.as_array()?,
+        _ => return None,
+    };
+
+    Some(
+        items
+            .iter()
+            .filter_map(|item| {
+                let mut item = item.clone();
+                // Accept "severity" values the enum does not know as the default
+                if let Some(obj) = item.as_object_mut() {
+                    if obj.get("severity").is_some_and(|s| {
+                        serde_json::from_value::<ReviewSeverity>(s.clone()).is_err()
+                    }) {
+                        obj.remove("severity");
+                    }
+                }
+                serde_json::from_value(item).ok()
+            })
+            .collect(),
+    )
+}
+
+/// Extracts findings from free-form output by keyword matching.
+///
+/// Each matching line becomes a medium-severity finding. A leading
+/// `path:line` reference and a `CWE-<n>` mention are picked up if present.
+pub fn heuristic_findings(output: &str) -> Vec<SecurityFinding> {
+    output
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*', ' ']))
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            SECURITY_KEYWORDS.iter().any(|k| lower.contains(k))
+        })
+        .map(|line| {
+            let (file, line_no) = location(line);
+            SecurityFinding {
+                severity: ReviewSeverity::Medium,
+                file,
+                line: line_no,
+                cwe: cwe(line),
+                issue: line.to_string(),
+                recommendation: String::new(),
+                heuristic: true,
+            }
+        })
+        .collect()
+}
+
+/// Finds a leading `path:line` reference.
+fn location(line: &str) -> (Option<String>, Option<u32>) {
+    let token = line
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_matches(|c| c == '`' || c == ':' || c == ',');
+    match token.rsplit_once(':') {
+        Some((path, num)) if path.contains('.') => match num.parse() {
+            Ok(n) => (Some(path.to_string()), Some(n)),
+            Err(_) => (None, None),
+        },
+        _ => (None, None),
+    }
+}
+
+/// Finds a `CWE-<n>` mention.
+fn cwe(line: &str) -> Option<String> {
+    let upper = line.to_uppercase();
+    let start = upper.find("CWE-")?;
+    let digits: String = upper[start + 4..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    (!digits.is_empty()).then(|| format!("CWE-{}", digits))
+}
+
+/// Builder for security review prompts.
+pub struct SecurityReviewPromptBuilder {
+    original_prompt: String,
+    git_diff: String,
+    model: Option<String>,
+    max_diff_tokens: Option<usize>,
+}
+
+impl SecurityReviewPromptBuilder {
+    /// Creates a new security review prompt builder.
+    pub fn new(original_prompt: impl Into<String>) -> Self {
+        Self {
+            original_prompt: original_prompt.into(),
+            git_diff: String::new(),
+            model: None,
+            max_diff_tokens: None,
+        }
+    }
+
+    /// Sets the git diff of changes to review.
+    pub fn with_diff(mut self, diff: impl Into<String>) -> Self {
+        self.git_diff = diff.into();
+        self
+    }
+
+    /// Sets the reviewer model, used for token estimation.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Limits the diff to roughly `max_tokens` tokens.
+    pub fn with_max_diff_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_diff_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Builds the prompt using the built-in template.
+    pub fn build(&self) -> String {
+        self.build_with(&PromptTemplates::builtin())
+    }
+
+    /// Builds the prompt using the given template registry.
+    pub fn build_with(&self, templates: &PromptTemplates) -> String {
+        let model = self.model.as_deref().unwrap_or(DEFAULT_REVIEWER_MODEL);
+        let diff = match self.max_diff_tokens {
+            Some(max_tokens) => budget::truncate_diff(&self.git_diff, max_tokens, model),
+            None => self.git_diff.clone(),
+        };
+
+        let mut vars = TemplateVars::new();
+        vars.insert("original_prompt", self.original_prompt.clone());
+        vars.insert("diff", diff);
+        let prompt = templates.render(PromptTemplates::SECURITY_REVIEW, &vars);
+        budget::warn_if_over_window(&prompt, model);
+        prompt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_findings_object() {
+        let output = r#"
+            Review complete.
+            ```json
+            {
+              "findings": [
+                {
+                  "severity": "critical",
+                  "file": "src/db.rs",
+                  "line": 42,
+                  "cwe": "CWE-89",
+                  "issue": "SQL built with format!",
+                  "recommendation": "Use bound parameters"
+                },
+                {"severity": "low", "issue": "Verbose error page"}
+              ]
+            }
+            ```
+        "#;
+
+        let findings = parse_security_findings(output);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, ReviewSeverity::Critical);
+        assert_eq!(findings[0].file.as_deref(), Some("src/db.rs"));
+        assert_eq!(findings[0].line, Some(42));
+        assert_eq!(findings[0].cwe.as_deref(), Some("CWE-89"));
+        assert!(!findings[0].heuristic);
+        assert_eq!(findings[1].severity, ReviewSeverity::Low);
+        assert!(findings[1].file.is_none());
+    }
+
+    #[test]
+    fn parses_bare_array_and_defaults_unknown_severity() {
+        let output = r#"[{"severity": "severe", "issue": "Token in logs"}, {"file": "x.rs"}]"#;
+
+        let findings = parse_security_findings(output);
+
+        // The entry without an issue is skipped
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, ReviewSeverity::High);
+    }
+
+    #[test]
+    fn empty_findings_do_not_fall_back() {
+        let output = r#"No injection issues found. {"findings": []}"#;
+        assert!(parse_security_findings(output).is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_heuristics_without_json() {
+        let output = "Summary of review\n\
+                      - src/api.rs:17 SQL injection via string concatenation (CWE-89)\n\
+                      - The code style is fine\n\
+                      * Possible XSS in template rendering\n";
+
+        let findings = parse_security_findings(output);
+
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().all(|f| f.heuristic));
+        assert_eq!(findings[0].file.as_deref(), Some("src/api.rs"));
+        assert_eq!(findings[0].line, Some(17));
+        assert_eq!(findings[0].cwe.as_deref(), Some("CWE-89"));
+        assert_eq!(findings[0].severity, ReviewSeverity::Medium);
+        assert!(findings[1].file.is_none());
+    }
+
+    #[test]
+    fn finding_converts_to_suggestion() {
+        let finding = SecurityFinding {
+            severity: ReviewSeverity::Critical,
+            file: Some("src/auth.rs".to_string()),
+            line: Some(3),
+            cwe: Some("CWE-798".to_string()),
+            issue: "Hardcoded API key".to_string(),
+            recommendation: "Read it from the environment".to_string(),
+            heuristic: false,
+        };
+
+        let suggestion = finding.to_suggestion();
+
+        assert_eq!(suggestion.file, "src/auth.rs");
+        assert_eq!(suggestion.issue, "[CWE-798] Hardcoded API key");
+        assert_eq!(suggestion.severity, ReviewSeverity::Critical);
+    }
+
+    #[test]
+    fn prompt_requests_findings_json() {
+        let prompt = SecurityReviewPromptBuilder::new("Add login")
+            .with_diff("+ let key = \"abc\";")
+            .build();
+
+        assert!(prompt.contains("Add login"));
+        assert!(prompt.contains("+ let key"));
+        assert!(prompt.contains("\"cwe\""));
+        assert!(prompt.contains("\"recommendation\""));
+    }
+}
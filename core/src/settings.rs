@@ -0,0 +1,929 @@
+//! Layered settings loaded from TOML files, environment, and CLI flags.
+//!
+//! Settings are resolved in this order (later layers win):
+//!
+//! 1. Built-in defaults
+//! 2. User config — `~/.config/improbability-drive/config.toml`
+//! 3. Project config — `.improbability-drive/config.toml` in the repository
+//! 4. The selected profile (`[profiles.<name>]`) from the merged files
+//! 5. Environment variables (`SPAWN_RUNNER`, `CRUISE_DEBUG`, ...)
+//! 6. CLI overrides
+//!
+//! Tables are merged key by key, so a project config only needs to list the
+//! values it changes. Relative sandbox and log directories are resolved
+//! against the repository once all layers are applied.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Validate, ValidationResult, KNOWN_LLMS};
+use crate::branch::BranchNamer;
+use crate::commit::CommitMessages;
+use crate::context::ContextConfig;
+use crate::cruise::{create_runner_with, CruiseConfig};
+use crate::error::{Error, Result};
+use crate::github_actions::ActionsConfig;
+use crate::patch::WriteMode;
+use crate::queue::QueueConfig;
+use crate::report::ReportConfig;
+use crate::runner::{FallbackRunner, GeminiConfig, LLMRunner};
+use crate::sandbox::{default_sandbox_root, Locations};
+use crate::spawn::{SpawnConfig, SpawnMode};
+use crate::stats::StatsConfig;
+use crate::team::SpawnTeamConfig;
+
+/// Path of the user config file, relative to the config home.
+pub const USER_CONFIG_FILE: &str = "improbability-drive/config.toml";
+
+/// Path of the project config file, relative to the repository root.
+pub const PROJECT_CONFIG_FILE: &str = ".improbability-drive/config.toml";
+
+/// Environment variable selecting the LLM runner ("claude" or "gemini").
+pub const ENV_RUNNER: &str = "SPAWN_RUNNER";
+
+/// Environment variable enabling debug output.
+pub const ENV_DEBUG: &str = "CRUISE_DEBUG";
+
+/// Environment variable selecting a settings profile.
+pub const ENV_PROFILE: &str = "IMPROBABILITY_DRIVE_PROFILE";
+
+/// Environment variable setting the directory sandboxes are created in.
+pub const ENV_SANDBOX_DIR: &str = "IMPROBABILITY_DRIVE_SANDBOX_DIR";
+
+/// Environment variable setting the directory spawn logs are written to.
+pub const ENV_LOGS_DIR: &str = "IMPROBABILITY_DRIVE_LOGS_DIR";
+
+/// Known sandbox provider identifiers.
+pub const KNOWN_SANDBOX_PROVIDERS: &[&str] = &["worktree", "workspace"];
+
+/// Spawn settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnSettings {
+    /// Prompt handling mode.
+    #[serde(default)]
+    pub mode: SpawnMode,
+    /// Idle timeout in seconds.
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: u64,
+    /// Total timeout in seconds.
+    #[serde(default = "default_total_timeout")]
+    pub total_timeout: u64,
+    /// LLM runner for spawned instances (e.g., "claude-code").
+    #[serde(default = "default_llm")]
+    pub default_llm: String,
+    /// Model passed to the runner, if any (e.g., "sonnet").
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Maximum permission escalations allowed.
+    #[serde(default = "default_max_escalations")]
+    pub max_permission_escalations: u32,
+    /// Fallback models to try, in order, when a model is overloaded
+    /// (e.g., `opus = ["sonnet", "haiku"]`).
+    #[serde(default)]
+    pub fallbacks: HashMap<String, Vec<String>>,
+    /// Seconds before an invocation is abandoned for the next fallback model.
+    #[serde(default)]
+    pub fallback_timeout: Option<u64>,
+    /// Open a draft PR with the partial work of failed or timed-out spawns.
+    #[serde(default)]
+    pub partial_pr: bool,
+    /// Context pack appended to the primary's prompt.
+    #[serde(default)]
+    pub context: ContextConfig,
+    /// How spawned LLMs change files: `direct` or `patch`.
+    #[serde(default)]
+    pub write_mode: WriteMode,
+    /// Gemini CLI invocation settings.
+    #[serde(default)]
+    pub gemini: GeminiConfig,
+}
+
+fn default_idle_timeout() -> u64 {
+    120
+}
+
+fn default_total_timeout() -> u64 {
+    1800
+}
+
+fn default_llm() -> String {
+    "claude-code".to_string()
+}
+
+fn default_max_escalations() -> u32 {
+    1
+}
+
+impl Default for SpawnSettings {
+    fn default() -> Self {
+        Self {
+            mode: SpawnMode::default(),
+            idle_timeout: default_idle_timeout(),
+            total_timeout: default_total_timeout(),
+            default_llm: default_llm(),
+            model: None,
+            max_permission_escalations: default_max_escalations(),
+            fallbacks: HashMap::new(),
+            fallback_timeout: None,
+            partial_pr: false,
+            context: ContextConfig::default(),
+            write_mode: WriteMode::default(),
+            gemini: GeminiConfig::default(),
+        }
+    }
+}
+
+/// Sandbox settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxSettings {
+    /// Sandbox provider identifier.
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    /// Directory for sandboxes (defaults to the system temp dir).
+    #[serde(default)]
+    pub sandbox_dir: Option<PathBuf>,
+    /// Gives each repository its own subdirectory of the sandbox directory.
+    #[serde(default)]
+    pub per_repo: bool,
+    /// Gives spawned LLM CLIs a home directory of their own in each sandbox.
+    #[serde(default)]
+    pub isolate_home: bool,
+}
+
+fn default_provider() -> String {
+    "worktree".to_string()
+}
+
+impl Default for SandboxSettings {
+    fn default() -> Self {
+        Self {
+            provider: default_provider(),
+            sandbox_dir: None,
+            per_repo: false,
+            isolate_home: false,
+        }
+    }
+}
+
+/// Logging settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingSettings {
+    /// Tracing filter level (e.g., "info", "debug").
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Directory for spawn logs, relative to the repository root.
+    #[serde(default = "default_logs_dir")]
+    pub logs_dir: PathBuf,
+    /// Enables verbose debug output.
+    #[serde(default)]
+    pub debug: bool,
+    /// Run report written at the end of cruise and spawn-team runs.
+    #[serde(default)]
+    pub report: ReportConfig,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_logs_dir() -> PathBuf {
+    PathBuf::from(".improbability-drive/spawns")
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            logs_dir: default_logs_dir(),
+            debug: false,
+            report: ReportConfig::default(),
+        }
+    }
+}
+
+/// Cheap models for lightweight auxiliary calls, such as checking whether
+/// a fix resolves a review finding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuxModelConfig {
+    /// Routes auxiliary calls to the cheap models.
+    #[serde(default = "default_aux_enabled")]
+    pub enabled: bool,
+    /// Cheap model for each LLM runner, overriding the built-in choice.
+    #[serde(default)]
+    pub models: HashMap<String, String>,
+}
+
+fn default_aux_enabled() -> bool {
+    true
+}
+
+impl Default for AuxModelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_aux_enabled(),
+            models: HashMap::new(),
+        }
+    }
+}
+
+impl AuxModelConfig {
+    /// Returns the cheap model for `llm`, or `None` if routing is disabled
+    /// or the runner has no known cheap model.
+    pub fn model_for(&self, llm: &str) -> Option<&str> {
+        if !self.enabled {
+            return None;
+        }
+        self.models.get(llm).map(String::as_str).or(match llm {
+            "claude-code" => Some("haiku"),
+            "gemini-cli" => Some("gemini-2.5-flash"),
+            _ => None,
+        })
+    }
+}
+
+/// Fully resolved settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// Name of the applied profile, if any.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Spawn settings.
+    #[serde(default)]
+    pub spawn: SpawnSettings,
+    /// Spawn-team settings.
+    #[serde(default, rename = "spawn-team")]
+    pub team: SpawnTeamConfig,
+    /// Sandbox settings.
+    #[serde(default)]
+    pub sandbox: SandboxSettings,
+    /// Logging settings.
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    /// Cruise-control settings.
+    #[serde(default)]
+    pub cruise: CruiseConfig,
+    /// Spawn queue settings.
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Branch naming settings.
+    #[serde(default)]
+    pub branches: BranchNamer,
+    /// Commit message settings.
+    #[serde(default)]
+    pub commits: CommitMessages,
+    /// GitHub Actions mode settings.
+    #[serde(default)]
+    pub github_actions: ActionsConfig,
+    /// Run statistics settings.
+    #[serde(default)]
+    pub stats: StatsConfig,
+    /// Models for auxiliary LLM calls.
+    #[serde(default)]
+    pub aux_models: AuxModelConfig,
+}
+
+impl Settings {
+    /// Loads settings for a repository from the standard locations and the
+    /// process environment.
+    pub fn load(repo_path: &Path) -> Result<Self> {
+        SettingsLoader::new().with_repo(repo_path).load()
+    }
+
+    /// Builds a spawn configuration for `prompt` from these settings.
+    pub fn spawn_config(&self, prompt: impl Into<String>) -> SpawnConfig {
+        let mut config = SpawnConfig::new(prompt)
+            .with_mode(self.spawn.mode)
+            .with_idle_timeout(Duration::from_secs(self.spawn.idle_timeout))
+            .with_total_timeout(Duration::from_secs(self.spawn.total_timeout))
+            .with_partial_pr(self.spawn.partial_pr)
+            .with_context(self.spawn.context.clone())
+            .with_write_mode(self.spawn.write_mode);
+        config.max_permission_escalations = self.spawn.max_permission_escalations;
+        config.model = self.spawn.model.clone();
+        config
+    }
+
+    /// Creates the runner for an LLM identifier with these settings,
+    /// falling back through `spawn.fallbacks` when a model is unavailable.
+    pub fn create_runner(&self, llm: &str) -> Result<Arc<dyn LLMRunner>> {
+        Ok(self.with_fallbacks(create_runner_with(llm, &self.spawn.gemini)?))
+    }
+
+    /// Returns the model for checking whether a fix resolves a finding: the
+    /// `verify` phase override, else the reviewer's cheap auxiliary model,
+    /// else the reviewer model.
+    pub fn verify_model(&self) -> Option<&str> {
+        self.team
+            .models
+            .verify
+            .as_deref()
+            .or_else(|| self.aux_models.model_for(&self.team.reviewer_llm))
+            .or(self.team.reviewer_model.as_deref())
+    }
+
+    /// Wraps `runner` with the fallback chains in `spawn.fallbacks`, picked
+    /// by the model each invocation requests.
+    ///
+    /// Returns the runner unchanged if no model has fallbacks.
+    pub fn with_fallbacks(&self, runner: Arc<dyn LLMRunner>) -> Arc<dyn LLMRunner> {
+        if self.spawn.fallbacks.values().all(|chain| chain.is_empty()) {
+            return runner;
+        }
+
+        let mut fallback = FallbackRunner::for_models(runner, self.spawn.fallbacks.clone());
+        if let Some(secs) = self.spawn.fallback_timeout {
+            fallback = fallback.with_attempt_timeout(Duration::from_secs(secs));
+        }
+        Arc::new(fallback)
+    }
+
+    /// Returns the directory sandboxes are created in.
+    pub fn sandbox_dir(&self) -> PathBuf {
+        self.sandbox
+            .sandbox_dir
+            .clone()
+            .unwrap_or_else(default_sandbox_root)
+    }
+
+    /// Returns the sandbox and logs directories, for the location registry.
+    pub fn locations(&self) -> Locations {
+        Locations::new(self.sandbox_dir(), self.logging.logs_dir.clone())
+    }
+
+    /// Makes relative sandbox and logs directories relative to `repo_path`,
+    /// and moves sandboxes into a per-repository subdirectory if enabled.
+    fn resolve_locations(&mut self, repo_path: &Path) {
+        if self.logging.logs_dir.is_relative() {
+            self.logging.logs_dir = repo_path.join(&self.logging.logs_dir);
+        }
+        let mut sandbox_dir = self.sandbox_dir();
+        if sandbox_dir.is_relative() {
+            sandbox_dir = repo_path.join(sandbox_dir);
+        }
+        if self.sandbox.per_repo {
+            if let Some(name) = repo_path.file_name() {
+                sandbox_dir = sandbox_dir.join(name);
+            }
+        }
+        self.sandbox.sandbox_dir = Some(sandbox_dir);
+    }
+}
+
+impl Validate for Settings {
+    fn validate(&self) -> ValidationResult {
+        let mut result = ValidationResult::default();
+
+        if self.spawn.idle_timeout >= self.spawn.total_timeout {
+            result.add_error("spawn.idle_timeout must be less than spawn.total_timeout");
+        }
+
+        if !KNOWN_LLMS.contains(&self.spawn.default_llm.as_str()) {
+            result.add_warning(format!(
+                "unknown spawn.default_llm '{}' - known: {:?}",
+                self.spawn.default_llm, KNOWN_LLMS
+            ));
+        }
+
+        if !KNOWN_SANDBOX_PROVIDERS.contains(&self.sandbox.provider.as_str()) {
+            result.add_error(format!(
+                "unknown sandbox.provider '{}' - known: {:?}",
+                self.sandbox.provider, KNOWN_SANDBOX_PROVIDERS
+            ));
+        }
+
+        result.merge(self.team.validate());
+        result.merge(self.queue.validate());
+        result.merge(self.branches.validate());
+        result.merge(self.commits.validate());
+        result.merge(self.github_actions.validate());
+        result
+    }
+}
+
+/// Values supplied on the command line, applied after all other layers.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsOverrides {
+    /// Profile to apply.
+    pub profile: Option<String>,
+    /// LLM runner for spawned instances.
+    pub runner: Option<String>,
+    /// Model passed to the runner.
+    pub model: Option<String>,
+    /// Idle timeout in seconds.
+    pub idle_timeout: Option<u64>,
+    /// Total timeout in seconds.
+    pub total_timeout: Option<u64>,
+    /// Enables debug output.
+    pub debug: Option<bool>,
+    /// Directory sandboxes are created in.
+    pub sandbox_dir: Option<PathBuf>,
+    /// Directory spawn logs are written to.
+    pub logs_dir: Option<PathBuf>,
+}
+
+/// Loads [`Settings`] from config files, environment, and CLI overrides.
+#[derive(Debug, Clone)]
+pub struct SettingsLoader {
+    user_config: Option<PathBuf>,
+    repo_path: Option<PathBuf>,
+    env: HashMap<String, String>,
+    overrides: SettingsOverrides,
+}
+
+impl Default for SettingsLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsLoader {
+    /// Creates a loader using the default user config path and the process
+    /// environment.
+    pub fn new() -> Self {
+        Self {
+            user_config: default_user_config_path(),
+            repo_path: None,
+            env: std::env::vars().collect(),
+            overrides: SettingsOverrides::default(),
+        }
+    }
+
+    /// Sets the user config file (or disables it with `None`).
+    pub fn with_user_config(mut self, path: Option<PathBuf>) -> Self {
+        self.user_config = path;
+        self
+    }
+
+    /// Sets the repository whose project config should be applied.
+    pub fn with_repo(mut self, repo_path: impl Into<PathBuf>) -> Self {
+        self.repo_path = Some(repo_path.into());
+        self
+    }
+
+    /// Replaces the environment used for overrides.
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Sets CLI overrides.
+    pub fn with_overrides(mut self, overrides: SettingsOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Resolves the settings.
+    pub fn load(&self) -> Result<Settings> {
+        let mut merged = toml::Table::new();
+
+        if let Some(path) = &self.user_config {
+            if let Some(table) = read_table(path)? {
+                merge_tables(&mut merged, table);
+            }
+        }
+        if let Some(repo) = &self.repo_path {
+            if let Some(table) = read_table(&repo.join(PROJECT_CONFIG_FILE))? {
+                merge_tables(&mut merged, table);
+            }
+        }
+
+        let profiles = merged.remove("profiles");
+        let file_profile = merged.remove("profile");
+
+        let profile = self
+            .overrides
+            .profile
+            .clone()
+            .or_else(|| self.env.get(ENV_PROFILE).cloned())
+            .or_else(|| file_profile.and_then(|v| v.as_str().map(String::from)))
+            .filter(|p| !p.is_empty());
+
+        if let Some(name) = &profile {
+            let table = profiles
+                .as_ref()
+                .and_then(|p| p.get(name))
+                .and_then(|p| p.as_table())
+                .ok_or_else(|| Error::Config(format!("unknown settings profile '{}'", name)))?;
+            merge_tables(&mut merged, table.clone());
+        }
+
+        let mut settings: Settings = toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e| Error::Config(format!("invalid settings: {}", e)))?;
+        settings.profile = profile;
+
+        self.apply_env(&mut settings)?;
+        self.apply_overrides(&mut settings);
+        if let Some(repo) = &self.repo_path {
+            settings.resolve_locations(repo);
+        }
+
+        tracing::debug!(profile = ?settings.profile, "resolved settings");
+        Ok(settings)
+    }
+
+    fn apply_env(&self, settings: &mut Settings) -> Result<()> {
+        if let Some(runner) = self.env.get(ENV_RUNNER).filter(|v| !v.is_empty()) {
+            settings.spawn.default_llm = normalize_runner(runner)?;
+        }
+        if let Some(debug) = self.env.get(ENV_DEBUG) {
+            settings.logging.debug = is_truthy(debug);
+        }
+        if let Some(dir) = self.env.get(ENV_SANDBOX_DIR).filter(|v| !v.is_empty()) {
+            settings.sandbox.sandbox_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(dir) = self.env.get(ENV_LOGS_DIR).filter(|v| !v.is_empty()) {
+            settings.logging.logs_dir = PathBuf::from(dir);
+        }
+        Ok(())
+    }
+
+    fn apply_overrides(&self, settings: &mut Settings) {
+        let overrides = &self.overrides;
+        if let Some(runner) = &overrides.runner {
+            settings.spawn.default_llm = runner.clone();
+        }
+        if let Some(model) = &overrides.model {
+            settings.spawn.model = Some(model.clone());
+        }
+        if let Some(idle) = overrides.idle_timeout {
+            settings.spawn.idle_timeout = idle;
+        }
+        if let Some(total) = overrides.total_timeout {
+            settings.spawn.total_timeout = total;
+        }
+        if let Some(debug) = overrides.debug {
+            settings.logging.debug = debug;
+        }
+        if let Some(dir) = &overrides.sandbox_dir {
+            settings.sandbox.sandbox_dir = Some(dir.clone());
+        }
+        if let Some(dir) = &overrides.logs_dir {
+            settings.logging.logs_dir = dir.clone();
+        }
+    }
+}
+
+/// Returns the user config path, honoring `XDG_CONFIG_HOME`.
+pub fn default_user_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join(USER_CONFIG_FILE))
+}
+
+/// Maps a runner name or alias to its identifier.
+fn normalize_runner(runner: &str) -> Result<String> {
+    match runner.to_lowercase().as_str() {
+        "claude" | "claude-code" => Ok("claude-code".to_string()),
+        "gemini" | "gemini-cli" => Ok("gemini-cli".to_string()),
+        other => Err(Error::Config(format!(
+            "unknown {} '{}' - expected claude or gemini",
+            ENV_RUNNER, other
+        ))),
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+}
+
+/// Reads a TOML file into a table, returning `None` if it does not exist.
+fn read_table(path: &Path) -> Result<Option<toml::Table>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let table = content
+        .parse::<toml::Table>()
+        .map_err(|e| Error::Config(format!("failed to parse {}: {}", path.display(), e)))?;
+    tracing::debug!(path = ?path, "loaded settings file");
+    Ok(Some(table))
+}
+
+/// Recursively merges `overlay` into `base`; overlay values win.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(incoming)) => {
+                merge_tables(existing, incoming);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::team::CoordinationMode;
+    use tempfile::TempDir;
+
+    fn loader(user: &TempDir, repo: &TempDir) -> SettingsLoader {
+        SettingsLoader::new()
+            .with_user_config(Some(user.path().join("config.toml")))
+            .with_repo(repo.path())
+            .with_env(HashMap::new())
+    }
+
+    fn write_project_config(repo: &TempDir, content: &str) {
+        let path = repo.path().join(PROJECT_CONFIG_FILE);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn defaults_without_config_files() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+
+        let settings = loader(&user, &repo).load().unwrap();
+
+        assert_eq!(settings.profile, None);
+        assert_eq!(settings.spawn.default_llm, "claude-code");
+        assert_eq!(settings.spawn.idle_timeout, 120);
+        assert_eq!(settings.sandbox.provider, "worktree");
+        assert_eq!(settings.team.reviewer_llm, "gemini-cli");
+        assert!(!settings.logging.debug);
+    }
+
+    #[test]
+    fn project_config_overrides_user_config() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        fs::write(
+            user.path().join("config.toml"),
+            "[spawn]\nidle_timeout = 60\ntotal_timeout = 600\n",
+        )
+        .unwrap();
+        write_project_config(&repo, "[spawn]\ntotal_timeout = 900\n");
+
+        let settings = loader(&user, &repo).load().unwrap();
+
+        // Tables are merged key by key
+        assert_eq!(settings.spawn.idle_timeout, 60);
+        assert_eq!(settings.spawn.total_timeout, 900);
+    }
+
+    #[test]
+    fn profile_from_file_is_applied() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        write_project_config(
+            &repo,
+            r#"
+            profile = "thorough"
+
+            [spawn-team]
+            mode = "sequential"
+
+            [profiles.thorough.spawn-team]
+            mode = "ping-pong"
+            max_iterations = 5
+
+            [profiles.thorough.cruise.building]
+            max_parallel = 1
+            "#,
+        );
+
+        let settings = loader(&user, &repo).load().unwrap();
+
+        assert_eq!(settings.profile.as_deref(), Some("thorough"));
+        assert_eq!(settings.team.mode, CoordinationMode::PingPong);
+        assert_eq!(settings.team.max_iterations, 5);
+        assert_eq!(settings.cruise.building.max_parallel, 1);
+    }
+
+    #[test]
+    fn cli_profile_beats_env_and_file() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        write_project_config(
+            &repo,
+            r#"
+            profile = "a"
+            [profiles.a.spawn]
+            model = "haiku"
+            [profiles.b.spawn]
+            model = "sonnet"
+            [profiles.c.spawn]
+            model = "opus"
+            "#,
+        );
+
+        let env: HashMap<_, _> = [(ENV_PROFILE.to_string(), "b".to_string())].into();
+        let from_env = loader(&user, &repo).with_env(env.clone()).load().unwrap();
+        assert_eq!(from_env.spawn.model.as_deref(), Some("sonnet"));
+
+        let from_cli = loader(&user, &repo)
+            .with_env(env)
+            .with_overrides(SettingsOverrides {
+                profile: Some("c".to_string()),
+                ..Default::default()
+            })
+            .load()
+            .unwrap();
+        assert_eq!(from_cli.spawn.model.as_deref(), Some("opus"));
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+
+        let result = loader(&user, &repo)
+            .with_overrides(SettingsOverrides {
+                profile: Some("missing".to_string()),
+                ..Default::default()
+            })
+            .load();
+
+        assert!(matches!(result, Err(Error::Config(msg)) if msg.contains("missing")));
+    }
+
+    #[test]
+    fn env_overrides_runner_and_debug() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        let env: HashMap<_, _> = [
+            (ENV_RUNNER.to_string(), "gemini".to_string()),
+            (ENV_DEBUG.to_string(), "1".to_string()),
+        ]
+        .into();
+
+        let settings = loader(&user, &repo).with_env(env).load().unwrap();
+
+        assert_eq!(settings.spawn.default_llm, "gemini-cli");
+        assert!(settings.logging.debug);
+    }
+
+    #[test]
+    fn env_rejects_unknown_runner() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        let env: HashMap<_, _> = [(ENV_RUNNER.to_string(), "gpt".to_string())].into();
+
+        assert!(loader(&user, &repo).with_env(env).load().is_err());
+    }
+
+    #[test]
+    fn cli_overrides_beat_env() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        let env: HashMap<_, _> = [(ENV_RUNNER.to_string(), "gemini".to_string())].into();
+
+        let settings = loader(&user, &repo)
+            .with_env(env)
+            .with_overrides(SettingsOverrides {
+                runner: Some("claude-code".to_string()),
+                idle_timeout: Some(30),
+                ..Default::default()
+            })
+            .load()
+            .unwrap();
+
+        assert_eq!(settings.spawn.default_llm, "claude-code");
+        assert_eq!(settings.spawn.idle_timeout, 30);
+    }
+
+    #[test]
+    fn auxiliary_calls_use_cheap_models() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+
+        let settings = loader(&user, &repo).load().unwrap();
+        assert_eq!(settings.verify_model(), Some("gemini-2.5-flash"));
+        assert_eq!(settings.aux_models.model_for("claude-code"), Some("haiku"));
+
+        write_project_config(
+            &repo,
+            r#"
+            [spawn-team]
+            reviewer_llm = "claude-code"
+            reviewer_model = "opus"
+
+            [aux_models.models]
+            claude-code = "sonnet"
+            "#,
+        );
+        let mut settings = loader(&user, &repo).load().unwrap();
+        assert_eq!(settings.verify_model(), Some("sonnet"));
+
+        settings.aux_models.enabled = false;
+        assert_eq!(settings.verify_model(), Some("opus"));
+        settings.team.models.verify = Some("haiku".to_string());
+        assert_eq!(settings.verify_model(), Some("haiku"));
+    }
+
+    #[test]
+    fn locations_resolve_against_repo() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        write_project_config(
+            &repo,
+            r#"
+            [sandbox]
+            sandbox_dir = "/srv/sandboxes"
+            per_repo = true
+            "#,
+        );
+
+        let settings = loader(&user, &repo).load().unwrap();
+        assert_eq!(
+            settings.sandbox_dir(),
+            Path::new("/srv/sandboxes").join(repo.path().file_name().unwrap())
+        );
+        assert_eq!(
+            settings.logging.logs_dir,
+            repo.path().join(".improbability-drive/spawns")
+        );
+
+        let env: HashMap<_, _> = [
+            (ENV_SANDBOX_DIR.to_string(), "sandboxes".to_string()),
+            (ENV_LOGS_DIR.to_string(), "/var/log/drive".to_string()),
+        ]
+        .into();
+        let settings = loader(&user, &repo)
+            .with_env(env)
+            .with_overrides(SettingsOverrides {
+                logs_dir: Some(PathBuf::from("logs")),
+                ..Default::default()
+            })
+            .load()
+            .unwrap();
+        let name = repo.path().file_name().unwrap();
+        assert_eq!(
+            settings.sandbox_dir(),
+            repo.path().join("sandboxes").join(name)
+        );
+        assert_eq!(settings.logging.logs_dir, repo.path().join("logs"));
+    }
+
+    #[test]
+    fn invalid_toml_reports_path() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        write_project_config(&repo, "[spawn\n");
+
+        let err = loader(&user, &repo).load().unwrap_err();
+        assert!(err.to_string().contains("config.toml"));
+    }
+
+    #[test]
+    fn spawn_config_uses_settings() {
+        let mut settings = Settings::default();
+        settings.spawn.idle_timeout = 45;
+        settings.spawn.max_permission_escalations = 3;
+        settings.spawn.partial_pr = true;
+        settings.spawn.model = Some("opus".to_string());
+
+        let config = settings.spawn_config("do it");
+
+        assert_eq!(config.prompt, "do it");
+        assert_eq!(config.model.as_deref(), Some("opus"));
+        assert_eq!(config.idle_timeout, Duration::from_secs(45));
+        assert_eq!(config.max_permission_escalations, 3);
+        assert!(config.partial_pr);
+    }
+
+    #[test]
+    fn fallbacks_parse_and_wrap_runner() {
+        let user = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        write_project_config(
+            &repo,
+            "[spawn]\nfallback_timeout = 300\n[spawn.fallbacks]\nopus = [\"sonnet\", \"haiku\"]\n",
+        );
+
+        let settings = loader(&user, &repo).load().unwrap();
+
+        assert_eq!(settings.spawn.fallbacks["opus"], vec!["sonnet", "haiku"]);
+        assert_eq!(settings.spawn.fallback_timeout, Some(300));
+
+        let runner: Arc<dyn LLMRunner> = Arc::new(crate::runner::ClaudeRunner::new());
+        let wrapped = settings.with_fallbacks(runner.clone());
+        assert!(!Arc::ptr_eq(&wrapped, &runner));
+        let unchanged = Settings::default().with_fallbacks(runner.clone());
+        assert!(Arc::ptr_eq(&unchanged, &runner));
+        assert_eq!(settings.create_runner("claude-code").unwrap().name(), "claude-code");
+    }
+
+    #[test]
+    fn validate_flags_bad_settings() {
+        let mut settings = Settings::default();
+        assert!(settings.validate().is_valid());
+
+        settings.sandbox.provider = "docker".to_string();
+        settings.spawn.idle_timeout = settings.spawn.total_timeout;
+        let result = settings.validate();
+        assert_eq!(result.errors.len(), 2);
+    }
+}
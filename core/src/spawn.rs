@@ -2,13 +2,26 @@
 //!
 //! This module provides the entry point for spawning sandboxed LLM instances.
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, Result};
-use crate::sandbox::{Sandbox, SandboxManifest, SandboxProvider};
+use crate::audit::{OBSERVABILITY_FILE, TOOL_CALLS_FILE};
+use crate::branch::BranchNamer;
+use crate::cancel::{open_partial_pr, CancellationToken};
+use crate::commit::CommitMessages;
+use crate::context::ContextConfig;
+use crate::error::{exit_code, Error, Result};
+use crate::git::{GitExecutor, SystemGit};
+use crate::logs::{EVENTS_FILE, STDERR_FILE, STDOUT_FILE};
+use crate::patch::WriteMode;
+use crate::sandbox::{sandbox_environment, Sandbox, SandboxManifest, SandboxProvider};
 
 /// Mode for prompt handling.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -27,6 +40,10 @@ pub struct SpawnConfig {
     /// The prompt to send to the spawned LLM.
     pub prompt: String,
 
+    /// Model the LLM runs with; `None` uses the CLI's default.
+    #[serde(default)]
+    pub model: Option<String>,
+
     /// Mode for prompt handling.
     #[serde(default)]
     pub mode: SpawnMode,
@@ -42,6 +59,24 @@ pub struct SpawnConfig {
     /// Maximum permission escalations allowed.
     #[serde(default = "default_max_escalations")]
     pub max_permission_escalations: u32,
+
+    /// Whether a failed or timed-out spawn pushes its partial work to a
+    /// `wip/` branch and opens a draft PR labelled `incomplete`.
+    #[serde(default)]
+    pub partial_pr: bool,
+
+    /// Issues the spawn's PR closes when merged, e.g. `owner/repo#12`.
+    #[serde(default)]
+    pub closes_issues: Vec<String>,
+
+    /// Context pack appended to the prompt before the LLM starts.
+    #[serde(default)]
+    pub context: ContextConfig,
+
+    /// Whether the LLM edits files itself, outputs a diff that is applied
+    /// for it, or only reports its findings.
+    #[serde(default)]
+    pub write_mode: WriteMode,
 }
 
 fn default_idle_timeout() -> Duration {
@@ -61,19 +96,39 @@ impl SpawnConfig {
     pub fn new(prompt: impl Into<String>) -> Self {
         Self {
             prompt: prompt.into(),
+            model: None,
             mode: SpawnMode::default(),
             idle_timeout: default_idle_timeout(),
             total_timeout: default_total_timeout(),
             max_permission_escalations: default_max_escalations(),
+            partial_pr: false,
+            closes_issues: Vec::new(),
+            context: ContextConfig::default(),
+            write_mode: WriteMode::default(),
         }
     }
 
+    /// Creates a read-only investigation spawn, e.g. to audit a module or
+    /// explain a failure.
+    ///
+    /// The LLM gets only read-only tools and answers with a Markdown report
+    /// instead of commits or a PR. See [`crate::investigation`].
+    pub fn read_only(prompt: impl Into<String>) -> Self {
+        Self::new(prompt).with_write_mode(WriteMode::ReadOnly)
+    }
+
     /// Sets the spawn mode.
     pub fn with_mode(mut self, mode: SpawnMode) -> Self {
         self.mode = mode;
         self
     }
 
+    /// Sets the model the LLM runs with.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
     /// Sets the idle timeout.
     pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
         self.idle_timeout = timeout;
@@ -85,18 +140,99 @@ impl SpawnConfig {
         self.total_timeout = timeout;
         self
     }
+
+    /// Sets whether partial work from a failed spawn is opened as a draft PR.
+    pub fn with_partial_pr(mut self, enabled: bool) -> Self {
+        self.partial_pr = enabled;
+        self
+    }
+
+    /// Sets how the context pack is built.
+    pub fn with_context(mut self, context: ContextConfig) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Sets how the LLM changes files.
+    pub fn with_write_mode(mut self, mode: WriteMode) -> Self {
+        self.write_mode = mode;
+        self
+    }
+
+    /// Links the spawn's PR to an issue it closes.
+    pub fn with_closing_issue(mut self, issue: impl Into<String>) -> Self {
+        self.closes_issues.push(issue.into());
+        self
+    }
 }
 
 /// Status of a completed spawn operation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub enum SpawnStatus {
     /// Spawn completed successfully.
     Success,
-    /// Spawn failed due to an error.
+    /// Spawn failed for a reason not covered by another status.
     Failed,
     /// Spawn was terminated due to timeout.
+    #[serde(alias = "timedout")]
     TimedOut,
+    /// A permission the LLM needed could not be granted.
+    PermissionDenied,
+    /// Every model was rate limited or overloaded.
+    RateLimited,
+    /// The run's overall time budget ran out.
+    BudgetExceeded,
+    /// The run was cancelled.
+    Cancelled,
+    /// The sandbox could not be created or provisioned.
+    SandboxError,
+}
+
+impl SpawnStatus {
+    /// Returns true for [`SpawnStatus::Success`].
+    pub fn is_success(self) -> bool {
+        self == SpawnStatus::Success
+    }
+
+    /// Returns the CLI exit code for a run that ended with this status.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            SpawnStatus::Success => 0,
+            SpawnStatus::Failed => exit_code::FAILURE,
+            SpawnStatus::TimedOut | SpawnStatus::BudgetExceeded => exit_code::TIMEOUT,
+            SpawnStatus::PermissionDenied => exit_code::CONFIG,
+            SpawnStatus::RateLimited => exit_code::LLM,
+            SpawnStatus::Cancelled => exit_code::CANCELLED,
+            SpawnStatus::SandboxError => exit_code::SANDBOX,
+        }
+    }
+}
+
+impl From<&Error> for SpawnStatus {
+    fn from(error: &Error) -> Self {
+        match error.root() {
+            Error::SandboxCreation(_) | Error::SandboxCleanup { .. } | Error::InvalidPath(_) => {
+                SpawnStatus::SandboxError
+            }
+            Error::Cancelled(_) => SpawnStatus::Cancelled,
+            Error::ApprovalTimeout(_) => SpawnStatus::TimedOut,
+            _ => SpawnStatus::Failed,
+        }
+    }
+}
+
+/// How a file changed during a spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// The file was created.
+    Added,
+    /// The file's contents or mode changed.
+    #[default]
+    Modified,
+    /// The file was removed.
+    Deleted,
 }
 
 /// Information about a file change made during spawn.
@@ -104,6 +240,9 @@ pub enum SpawnStatus {
 pub struct FileChange {
     /// Path to the changed file (relative to worktree).
     pub path: PathBuf,
+    /// Whether the file was added, modified or deleted.
+    #[serde(default)]
+    pub kind: ChangeKind,
     /// Lines added.
     pub additions: u32,
     /// Lines removed.
@@ -119,6 +258,130 @@ pub struct CommitInfo {
     pub message: String,
 }
 
+/// Totals of the files a spawn changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ChangeSummary {
+    /// Files created.
+    pub added: usize,
+    /// Files modified.
+    pub modified: usize,
+    /// Files deleted.
+    pub deleted: usize,
+    /// Lines added across all files.
+    pub additions: u32,
+    /// Lines removed across all files.
+    pub deletions: u32,
+}
+
+impl ChangeSummary {
+    /// Totals `files`.
+    pub fn from_files(files: &[FileChange]) -> Self {
+        files.iter().fold(Self::default(), |mut summary, file| {
+            match file.kind {
+                ChangeKind::Added => summary.added += 1,
+                ChangeKind::Modified => summary.modified += 1,
+                ChangeKind::Deleted => summary.deleted += 1,
+            }
+            summary.additions += file.additions;
+            summary.deletions += file.deletions;
+            summary
+        })
+    }
+
+    /// Number of files changed.
+    pub fn files(&self) -> usize {
+        self.added + self.modified + self.deleted
+    }
+
+    /// Lines added plus lines removed.
+    pub fn churn(&self) -> u32 {
+        self.additions + self.deletions
+    }
+}
+
+impl fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} file{} changed ({} added, {} modified, {} deleted), +{} -{}",
+            self.files(),
+            if self.files() == 1 { "" } else { "s" },
+            self.added,
+            self.modified,
+            self.deleted,
+            self.additions,
+            self.deletions
+        )
+    }
+}
+
+/// Lists the files changed and commits made on the sandbox branch at
+/// `worktree` since `base`.
+///
+/// Renames are reported as a deletion and an addition; binary files count
+/// no lines.
+pub fn branch_changes(
+    git: &dyn GitExecutor,
+    worktree: &Path,
+    base: &str,
+) -> Result<(Vec<FileChange>, Vec<CommitInfo>)> {
+    let range = format!("{}..HEAD", base);
+    let statuses = git.run(
+        worktree,
+        &["diff", "--name-status", "--no-renames", "-z", base, "HEAD"],
+    )?;
+    let numstat = git.run(
+        worktree,
+        &["diff", "--numstat", "--no-renames", "-z", base, "HEAD"],
+    )?;
+
+    let mut lines = HashMap::new();
+    for entry in numstat.split('\0').filter(|e| !e.is_empty()) {
+        let mut fields = entry.splitn(3, '\t');
+        let (Some(added), Some(removed), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        lines.insert(
+            path.to_string(),
+            (added.parse().unwrap_or(0), removed.parse().unwrap_or(0)),
+        );
+    }
+
+    let mut files = Vec::new();
+    let mut fields = statuses.split('\0').filter(|e| !e.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let kind = match status {
+            "A" => ChangeKind::Added,
+            "D" => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        };
+        let (additions, deletions) = lines.get(path).copied().unwrap_or((0, 0));
+        files.push(FileChange {
+            path: PathBuf::from(path),
+            kind,
+            additions,
+            deletions,
+        });
+    }
+
+    let log = git.run(
+        worktree,
+        &["log", "--reverse", "--format=%H%x1f%B%x1e", &range],
+    )?;
+    let commits = log
+        .split('\x1e')
+        .filter_map(|entry| entry.trim().split_once('\x1f'))
+        .map(|(hash, message)| CommitInfo {
+            hash: hash.to_string(),
+            message: message.trim().to_string(),
+        })
+        .collect();
+
+    Ok((files, commits))
+}
+
 /// Paths to spawn log files.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpawnLogs {
@@ -128,6 +391,14 @@ pub struct SpawnLogs {
     pub stderr: PathBuf,
     /// Path to events log.
     pub events: PathBuf,
+    /// Path to the setup command log.
+    pub setup: PathBuf,
+    /// Path to the tool-call audit trail.
+    #[serde(default)]
+    pub tool_calls: PathBuf,
+    /// Path to the saved observability record.
+    #[serde(default)]
+    pub observability: PathBuf,
 }
 
 /// Result of a spawn operation.
@@ -151,16 +422,58 @@ pub struct SpawnResult {
     pub logs: SpawnLogs,
 }
 
+impl SpawnResult {
+    /// Totals the files this spawn changed.
+    pub fn change_summary(&self) -> ChangeSummary {
+        ChangeSummary::from_files(&self.files_changed)
+    }
+}
+
 /// Spawner that creates and manages sandboxed LLM instances.
 pub struct Spawner<P: SandboxProvider> {
     provider: P,
     logs_dir: PathBuf,
+    cancel: CancellationToken,
+    git: Arc<dyn GitExecutor>,
+    branches: BranchNamer,
+    commit_messages: CommitMessages,
 }
 
 impl<P: SandboxProvider> Spawner<P> {
     /// Creates a new spawner with the given sandbox provider.
     pub fn new(provider: P, logs_dir: PathBuf) -> Self {
-        Self { provider, logs_dir }
+        Self {
+            provider,
+            logs_dir,
+            cancel: CancellationToken::new(),
+            git: Arc::new(SystemGit),
+            branches: BranchNamer::new(),
+            commit_messages: CommitMessages::default(),
+        }
+    }
+
+    /// Runs git through `git` instead of the system binary.
+    pub fn with_git(mut self, git: Arc<dyn GitExecutor>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Refuses to start new spawns once `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Sets the namer for the `wip/` branches behind partial PRs.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    /// Sets how partial work is committed before its PR is opened.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.commit_messages = messages;
+        self
     }
 
     /// Spawns a sandboxed LLM with the given configuration.
@@ -168,6 +481,8 @@ impl<P: SandboxProvider> Spawner<P> {
     /// This is the basic spawn implementation without the watcher agent.
     /// It creates a sandbox, but does not actually run an LLM yet.
     pub fn spawn(&self, config: SpawnConfig, manifest: SandboxManifest) -> Result<SpawnResult> {
+        self.cancel.check()?;
+
         // Generate spawn ID
         let spawn_id = uuid::Uuid::new_v4().to_string();
 
@@ -177,9 +492,12 @@ impl<P: SandboxProvider> Spawner<P> {
 
         // Create log files
         let logs = SpawnLogs {
-            stdout: spawn_logs_dir.join("stdout.log"),
-            stderr: spawn_logs_dir.join("stderr.log"),
-            events: spawn_logs_dir.join("events.jsonl"),
+            stdout: spawn_logs_dir.join(STDOUT_FILE),
+            stderr: spawn_logs_dir.join(STDERR_FILE),
+            events: spawn_logs_dir.join(EVENTS_FILE),
+            setup: spawn_logs_dir.join("setup.log"),
+            tool_calls: spawn_logs_dir.join(TOOL_CALLS_FILE),
+            observability: spawn_logs_dir.join(OBSERVABILITY_FILE),
         };
 
         // Write config to logs
@@ -196,7 +514,22 @@ impl<P: SandboxProvider> Spawner<P> {
 
         // Create sandbox
         let start_time = std::time::Instant::now();
-        let mut sandbox = self.provider.create(manifest)?;
+        let mut sandbox = match self.provider.create(manifest) {
+            Ok(sandbox) => sandbox,
+            Err(e) => {
+                tracing::warn!(spawn_id = %spawn_id, error = %e, "sandbox creation failed");
+                return Ok(SpawnResult {
+                    status: SpawnStatus::from(&e),
+                    spawn_id,
+                    duration: start_time.elapsed(),
+                    files_changed: vec![],
+                    commits: vec![],
+                    summary: e.to_string(),
+                    pr_url: None,
+                    logs,
+                });
+            }
+        };
 
         tracing::info!(
             spawn_id = %spawn_id,
@@ -204,6 +537,27 @@ impl<P: SandboxProvider> Spawner<P> {
             mode = ?config.mode,
             "created spawn sandbox"
         );
+        // Changes are reported against the commit the sandbox started from
+        let base = self.git.run(sandbox.path(), &["rev-parse", "HEAD"]).ok();
+
+        // Provision the sandbox before any model tokens are spent
+        if let Err(failure) = run_setup(sandbox.path(), sandbox.manifest(), &logs.setup) {
+            tracing::warn!(spawn_id = %spawn_id, error = %failure, "sandbox setup failed");
+            let duration = start_time.elapsed();
+            let pr_url = self.keep_partial_work(&config, sandbox.path(), &failure);
+            sandbox.cleanup()?;
+
+            return Ok(SpawnResult {
+                status: SpawnStatus::SandboxError,
+                spawn_id,
+                duration,
+                files_changed: vec![],
+                commits: vec![],
+                summary: format!("{}; see {}", failure, logs.setup.display()),
+                pr_url,
+                logs,
+            });
+        }
 
         // TODO: In Phase 2, this is where the watcher agent would:
         // 1. Launch the LLM runner
@@ -213,14 +567,22 @@ impl<P: SandboxProvider> Spawner<P> {
 
         // For now, just clean up and return a basic result
         let duration = start_time.elapsed();
+        let (files_changed, commits) = match &base {
+            Some(base) => branch_changes(self.git.as_ref(), sandbox.path(), base.trim())
+                .unwrap_or_else(|e| {
+                    tracing::warn!(spawn_id = %spawn_id, error = %e, "could not list spawn changes");
+                    (vec![], vec![])
+                }),
+            None => (vec![], vec![]),
+        };
         sandbox.cleanup()?;
 
         Ok(SpawnResult {
             status: SpawnStatus::Success,
             spawn_id,
             duration,
-            files_changed: vec![],
-            commits: vec![],
+            files_changed,
+            commits,
             summary: format!(
                 "Sandbox created and cleaned up successfully. Prompt: {}",
                 config.prompt
@@ -229,11 +591,80 @@ impl<P: SandboxProvider> Spawner<P> {
             logs,
         })
     }
+
+    /// Opens a draft PR for the work left in the sandbox of a failed spawn
+    /// if `config` asks for one. Failures are logged, not returned, so the
+    /// spawn's own result is still reported. Returns the PR's URL.
+    fn keep_partial_work(
+        &self,
+        config: &SpawnConfig,
+        sandbox: &Path,
+        reason: &str,
+    ) -> Option<String> {
+        if !config.partial_pr || config.write_mode == WriteMode::ReadOnly {
+            return None;
+        }
+        match open_partial_pr(
+            sandbox,
+            &config.prompt,
+            reason,
+            &self.branches,
+            &self.commit_messages,
+        ) {
+            Ok(pr) => pr.map(|pr| pr.url),
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to open PR for partial work");
+                None
+            }
+        }
+    }
+}
+
+/// Runs the manifest's setup commands in `sandbox` with the sandbox
+/// environment, appending their output to `log_path`. Stops at the first
+/// failing command.
+fn run_setup(
+    sandbox: &Path,
+    manifest: &SandboxManifest,
+    log_path: &Path,
+) -> std::result::Result<(), String> {
+    if manifest.setup_commands.is_empty() {
+        return Ok(());
+    }
+
+    let mut log = std::fs::File::create(log_path).map_err(|e| e.to_string())?;
+    let env = sandbox_environment(manifest);
+
+    for command in &manifest.setup_commands {
+        tracing::info!(command = %command, "running sandbox setup command");
+        let output = Command::new("sh")
+            .current_dir(sandbox)
+            .args(["-c", command])
+            .env_clear()
+            .envs(&env)
+            .output()
+            .map_err(|e| format!("setup command `{}` could not start: {}", command, e))?;
+
+        let _ = writeln!(log, "$ {}", command);
+        let _ = log.write_all(&output.stdout);
+        let _ = log.write_all(&output.stderr);
+        let _ = writeln!(log, "[{}]", output.status);
+
+        if !output.status.success() {
+            return Err(format!(
+                "setup command `{}` failed ({})",
+                command, output.status
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::GitOutput;
     use crate::sandbox::WorktreeSandbox;
     use std::process::Command;
     use tempfile::TempDir;
@@ -302,6 +733,19 @@ mod tests {
         assert_eq!(config.total_timeout, Duration::from_secs(300));
     }
 
+    #[test]
+    fn read_only_spawn_reports_instead_of_writing() {
+        let config = SpawnConfig::read_only("audit the auth module");
+
+        assert_eq!(config.prompt, "audit the auth module");
+        assert_eq!(config.write_mode, WriteMode::ReadOnly);
+        assert!(!config.write_mode.grants_write_tools());
+        assert_eq!(
+            serde_json::to_string(&config.write_mode).unwrap(),
+            "\"read-only\""
+        );
+    }
+
     #[test]
     fn spawn_mode_serializes_correctly() {
         assert_eq!(serde_json::to_string(&SpawnMode::Aisp).unwrap(), "\"aisp\"");
@@ -311,6 +755,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn spawn_status_serializes_as_snake_case() {
+        assert_eq!(
+            serde_json::to_string(&SpawnStatus::PermissionDenied).unwrap(),
+            "\"permission_denied\""
+        );
+        assert_eq!(
+            serde_json::from_str::<SpawnStatus>("\"timedout\"").unwrap(),
+            SpawnStatus::TimedOut
+        );
+        assert_eq!(
+            SpawnStatus::from(&Error::Cancelled("interrupted".to_string())),
+            SpawnStatus::Cancelled
+        );
+    }
+
+    #[test]
+    fn sandbox_creation_failure_is_reported_as_sandbox_error() {
+        let not_a_repo = TempDir::new().expect("failed to create dir");
+        let logs_dir = TempDir::new().expect("failed to create logs dir");
+
+        let provider = WorktreeSandbox::new(not_a_repo.path().to_path_buf(), None);
+        let spawner = Spawner::new(provider, logs_dir.path().to_path_buf());
+        let result = spawner
+            .spawn(SpawnConfig::new("test spawn"), SandboxManifest::default())
+            .expect("spawn should report the failure");
+
+        assert_eq!(result.status, SpawnStatus::SandboxError);
+        assert!(!result.status.is_success());
+    }
+
     #[test]
     fn spawner_creates_logs_directory() {
         let git_repo = create_temp_git_repo();
@@ -366,4 +841,249 @@ mod tests {
         let manifest_content = std::fs::read_to_string(&manifest_path).unwrap();
         assert!(manifest_content.contains("Read"));
     }
+
+    #[test]
+    fn cancelled_spawner_does_not_create_sandbox() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+        let logs_dir = TempDir::new().expect("failed to create logs dir");
+
+        let provider = WorktreeSandbox::new(
+            git_repo.path().to_path_buf(),
+            Some(sandbox_dir.path().to_path_buf()),
+        );
+        let token = CancellationToken::new();
+        token.cancel("interrupted");
+        let spawner =
+            Spawner::new(provider, logs_dir.path().to_path_buf()).with_cancellation(token);
+
+        let result = spawner.spawn(SpawnConfig::new("test spawn"), SandboxManifest::default());
+
+        assert!(matches!(result, Err(Error::Cancelled(_))));
+        assert_eq!(std::fs::read_dir(logs_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn spawner_runs_setup_commands_before_llm() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+        let logs_dir = TempDir::new().expect("failed to create logs dir");
+
+        let provider = WorktreeSandbox::new(
+            git_repo.path().to_path_buf(),
+            Some(sandbox_dir.path().to_path_buf()),
+        );
+        let spawner = Spawner::new(provider, logs_dir.path().to_path_buf());
+        let mut manifest = SandboxManifest {
+            setup_commands: vec![
+                "echo bootstrapped".to_string(),
+                "test -f README.md".to_string(),
+            ],
+            ..Default::default()
+        };
+        manifest
+            .environment
+            .insert("GREETING".to_string(), "hello".to_string());
+        manifest.setup_commands.push("echo $GREETING".to_string());
+
+        let result = spawner
+            .spawn(SpawnConfig::new("test spawn"), manifest)
+            .expect("spawn failed");
+
+        assert_eq!(result.status, SpawnStatus::Success);
+        let log = std::fs::read_to_string(&result.logs.setup).unwrap();
+        assert!(log.contains("$ echo bootstrapped\nbootstrapped"));
+        assert!(log.contains("hello"));
+    }
+
+    #[test]
+    fn failing_setup_command_aborts_spawn() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+        let logs_dir = TempDir::new().expect("failed to create logs dir");
+
+        let provider = WorktreeSandbox::new(
+            git_repo.path().to_path_buf(),
+            Some(sandbox_dir.path().to_path_buf()),
+        );
+        let spawner = Spawner::new(provider, logs_dir.path().to_path_buf());
+        let manifest = SandboxManifest {
+            setup_commands: vec![
+                "echo missing lockfile >&2; exit 3".to_string(),
+                "touch should-not-run".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let result = spawner
+            .spawn(SpawnConfig::new("test spawn"), manifest)
+            .expect("spawn failed");
+
+        assert_eq!(result.status, SpawnStatus::SandboxError);
+        assert!(result.summary.contains("failed (exit status: 3)"));
+        let log = std::fs::read_to_string(&result.logs.setup).unwrap();
+        assert!(log.contains("missing lockfile"));
+        assert!(!log.contains("should-not-run"));
+        // The sandbox is cleaned up
+        assert_eq!(std::fs::read_dir(sandbox_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn failed_spawn_keeps_partial_work_when_asked() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+        let logs_dir = TempDir::new().expect("failed to create logs dir");
+        let wip_branches = || {
+            let output = Command::new("git")
+                .args(["branch", "--list", "wip/*"])
+                .current_dir(git_repo.path())
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+        let manifest = || SandboxManifest {
+            setup_commands: vec!["echo half > partial.txt; exit 1".to_string()],
+            ..Default::default()
+        };
+        let spawner = || {
+            let provider = WorktreeSandbox::new(
+                git_repo.path().to_path_buf(),
+                Some(sandbox_dir.path().to_path_buf()),
+            );
+            Spawner::new(provider, logs_dir.path().to_path_buf())
+        };
+
+        let result = spawner()
+            .spawn(SpawnConfig::new("add login"), manifest())
+            .unwrap();
+        assert_eq!(result.status, SpawnStatus::SandboxError);
+        assert!(wip_branches().is_empty());
+
+        // Without a GitHub remote the push fails after the branch is made
+        let result = spawner()
+            .spawn(
+                SpawnConfig::new("add login").with_partial_pr(true),
+                manifest(),
+            )
+            .unwrap();
+        assert_eq!(result.status, SpawnStatus::SandboxError);
+        assert!(result.pr_url.is_none());
+        let branch = wip_branches().trim().to_string();
+        assert!(branch.starts_with("wip/add-login-"), "{}", branch);
+        let files = Command::new("git")
+            .args(["ls-tree", "--name-only", &branch])
+            .current_dir(git_repo.path())
+            .output()
+            .unwrap();
+        assert!(String::from_utf8_lossy(&files.stdout).contains("partial.txt"));
+    }
+
+    #[test]
+    fn branch_changes_classify_files_and_list_commits() {
+        let git = crate::git::MockGit::new()
+            .on(
+                &["diff", "--name-status"],
+                GitOutput::ok("A\0src/new.rs\0M\0README.md\0D\0old.txt\0M\0logo.png\0"),
+            )
+            .on(
+                &["diff", "--numstat"],
+                GitOutput::ok(
+                    [
+                        "12\t0\tsrc/new.rs",
+                        "3\t1\tREADME.md",
+                        "0\t4\told.txt",
+                        "-\t-\tlogo.png",
+                    ]
+                    .join("\0"),
+                ),
+            )
+            .on(
+                &["log"],
+                GitOutput::ok(
+                    "abc\x1fAdd parser\n\x1e\ndef\x1fDrop old notes\n\nTask-Id: T-2\n\x1e\n",
+                ),
+            );
+
+        let (files, commits) = branch_changes(&git, Path::new("/sandbox"), "base").unwrap();
+
+        let kinds: Vec<_> = files.iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ChangeKind::Added,
+                ChangeKind::Modified,
+                ChangeKind::Deleted,
+                ChangeKind::Modified
+            ]
+        );
+        assert_eq!((files[1].additions, files[1].deletions), (3, 1));
+        let summary = ChangeSummary::from_files(&files);
+        assert_eq!(summary.churn(), 20);
+        assert_eq!(
+            summary.to_string(),
+            "4 files changed (1 added, 2 modified, 1 deleted), +15 -5"
+        );
+        let messages: Vec<_> = commits.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages, ["Add parser", "Drop old notes\n\nTask-Id: T-2"]);
+        assert_eq!(git.calls()[2].last().unwrap(), "base..HEAD");
+    }
+
+    #[test]
+    fn spawner_reports_changes_made_in_the_sandbox() {
+        let git_repo = create_temp_git_repo();
+        let sandbox_dir = TempDir::new().expect("failed to create sandbox dir");
+        let logs_dir = TempDir::new().expect("failed to create logs dir");
+
+        let provider = WorktreeSandbox::new(
+            git_repo.path().to_path_buf(),
+            Some(sandbox_dir.path().to_path_buf()),
+        );
+        let spawner = Spawner::new(provider, logs_dir.path().to_path_buf());
+        let manifest = SandboxManifest {
+            setup_commands: vec![
+                "printf 'a\\nb\\n' > notes.txt && git rm -q README.md".to_string(),
+                "git add notes.txt && git commit -qm 'Replace readme'".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let result = spawner
+            .spawn(SpawnConfig::new("test spawn"), manifest)
+            .expect("spawn failed");
+
+        assert_eq!(result.commits.len(), 1);
+        assert_eq!(result.commits[0].message, "Replace readme");
+        let summary = result.change_summary();
+        assert_eq!((summary.added, summary.deleted), (1, 1));
+        assert_eq!((summary.additions, summary.deletions), (2, 1));
+    }
+
+    #[test]
+    fn setup_commands_get_only_the_sandbox_environment() {
+        let sandbox = TempDir::new().unwrap();
+        std::env::set_var("IMPROBABILITY_SETUP_VISIBLE", "yes");
+        std::env::set_var("IMPROBABILITY_SETUP_TOKEN", "secret");
+        std::env::set_var("IMPROBABILITY_UNLISTED", "secret");
+        let manifest = SandboxManifest {
+            setup_commands: vec!["env > env.txt".to_string()],
+            env_allow: vec!["IMPROBABILITY_SETUP_*".to_string()],
+            env_deny: vec!["IMPROBABILITY_SETUP_TOKEN".to_string()],
+            ..Default::default()
+        };
+
+        let result = run_setup(sandbox.path(), &manifest, &sandbox.path().join("setup.log"));
+        for name in [
+            "IMPROBABILITY_SETUP_VISIBLE",
+            "IMPROBABILITY_SETUP_TOKEN",
+            "IMPROBABILITY_UNLISTED",
+        ] {
+            std::env::remove_var(name);
+        }
+
+        result.unwrap();
+        let env = std::fs::read_to_string(sandbox.path().join("env.txt")).unwrap();
+        assert!(env.contains("IMPROBABILITY_SETUP_VISIBLE=yes"));
+        assert!(!env.contains("IMPROBABILITY_SETUP_TOKEN"));
+        assert!(!env.contains("IMPROBABILITY_UNLISTED"));
+    }
 }
@@ -0,0 +1,633 @@
+//! Historical run analytics.
+//!
+//! Every finished run records a row of metrics — duration, review rounds,
+//! estimated cost, final verdict, failure reason — and one row per review
+//! domain in a local SQLite database at [`STATS_DB`]. `stats` aggregates
+//! them into trends: success rate and cost per kind of run, average fix
+//! rounds per domain, the most common failure modes, and weekly totals.
+//!
+//! The database is driven through the `sqlite3` command-line shell, so
+//! recording is skipped with a warning where it is not installed.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::cruise::status::format_secs;
+use crate::cruise::task::TaskStatus;
+use crate::error::{Error, Result};
+use crate::report::RunReport;
+use crate::review_loop::DomainReviewOutcome;
+use crate::spawn::SpawnResult;
+use crate::team::ReviewVerdict;
+
+/// Path of the stats database, relative to the repository root.
+pub const STATS_DB: &str = ".improbability-drive/stats.db";
+
+/// Longest failure reason stored; longer ones are cut at a word boundary.
+const MAX_REASON_LEN: usize = 120;
+
+/// Failure modes listed in a report.
+const TOP_FAILURES: usize = 5;
+
+/// Weeks listed in a report's trend.
+const TREND_WEEKS: usize = 8;
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS runs (
+    run_id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    duration_secs REAL NOT NULL,
+    success INTEGER NOT NULL,
+    iterations INTEGER NOT NULL,
+    cost_usd REAL,
+    verdict TEXT,
+    failure_reason TEXT
+);
+CREATE TABLE IF NOT EXISTS domain_reviews (
+    run_id TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    fix_rounds INTEGER NOT NULL,
+    approved INTEGER NOT NULL,
+    PRIMARY KEY (run_id, domain)
+);
+";
+
+/// Settings for recording run statistics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsConfig {
+    /// Whether finished runs are recorded.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+        }
+    }
+}
+
+/// Review rounds of one domain in a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainMetrics {
+    /// Review domain (e.g., "security").
+    pub domain: String,
+    /// Fix rounds run for the domain's findings.
+    pub fix_rounds: u32,
+    /// Whether the domain ended without blocking findings.
+    pub approved: bool,
+}
+
+impl DomainMetrics {
+    /// Summarizes a domain's review passes.
+    pub fn from_outcome(outcome: &DomainReviewOutcome) -> Self {
+        Self {
+            domain: outcome.domain.clone(),
+            fix_rounds: outcome.passes.iter().filter(|p| p.fixed).count() as u32,
+            approved: outcome.approved,
+        }
+    }
+}
+
+/// Metrics of one finished run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMetrics {
+    /// Run or spawn ID.
+    pub run_id: String,
+    /// Kind of run, e.g. `spawn` or `cruise`.
+    pub kind: String,
+    /// Unix time the run started.
+    pub started_at: u64,
+    /// Wall-clock duration in seconds.
+    pub duration_secs: f64,
+    /// Whether the run succeeded.
+    pub success: bool,
+    /// Review rounds until approval, or until the run gave up.
+    pub iterations: u32,
+    /// Estimated model cost in USD, if known.
+    pub cost_usd: Option<f64>,
+    /// Final review verdict, if reviewed.
+    pub verdict: Option<String>,
+    /// Why the run failed, shortened to its first line.
+    pub failure_reason: Option<String>,
+    /// Per-domain review rounds.
+    pub domains: Vec<DomainMetrics>,
+}
+
+impl RunMetrics {
+    /// Creates metrics for a successful run that just finished after
+    /// `duration`.
+    pub fn new(kind: impl Into<String>, run_id: impl Into<String>, duration: Duration) -> Self {
+        Self {
+            run_id: run_id.into(),
+            kind: kind.into(),
+            started_at: now().saturating_sub(duration.as_secs()),
+            duration_secs: duration.as_secs_f64(),
+            success: true,
+            iterations: 0,
+            cost_usd: None,
+            verdict: None,
+            failure_reason: None,
+            domains: Vec::new(),
+        }
+    }
+
+    /// Collects the metrics of a spawn.
+    pub fn from_spawn(result: &SpawnResult) -> Self {
+        let mut metrics = Self::new("spawn", &result.spawn_id, result.duration);
+        if !result.status.is_success() {
+            metrics = metrics.with_failure(label(&result.status));
+        }
+        metrics
+    }
+
+    /// Collects the metrics of a spawn-team or cruise run from its report.
+    pub fn from_report(report: &RunReport) -> Self {
+        let mut metrics = Self::new(&report.kind, &report.run_id, report.duration);
+        metrics.iterations = report.reviews.len() as u32;
+        metrics.cost_usd = report.estimated_cost();
+        metrics.verdict = report.reviews.last().map(|r| label(&r.verdict));
+        if !report.success {
+            let reason = report
+                .tasks
+                .iter()
+                .filter(|t| t.status == TaskStatus::Blocked)
+                .find_map(|t| t.error.clone())
+                .or_else(|| match report.reviews.last() {
+                    Some(review) if review.verdict != ReviewVerdict::Approved => {
+                        Some(format!("review {}", label(&review.verdict)))
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| report.summary.clone());
+            metrics = metrics.with_failure(reason);
+        }
+        metrics
+    }
+
+    /// Marks the run failed for `reason`.
+    pub fn with_failure(mut self, reason: impl AsRef<str>) -> Self {
+        self.success = false;
+        self.failure_reason = Some(failure_mode(reason.as_ref()));
+        self
+    }
+
+    /// Sets the final review verdict.
+    pub fn with_verdict(mut self, verdict: &ReviewVerdict) -> Self {
+        self.verdict = Some(label(verdict));
+        self
+    }
+
+    /// Adds the per-domain review rounds; the run's iterations become the
+    /// most passes any domain needed.
+    pub fn with_domains(mut self, outcomes: &[DomainReviewOutcome]) -> Self {
+        self.iterations = outcomes
+            .iter()
+            .map(|o| o.passes.len() as u32)
+            .max()
+            .unwrap_or(self.iterations);
+        self.domains = outcomes.iter().map(DomainMetrics::from_outcome).collect();
+        self
+    }
+}
+
+/// Local SQLite store of [`RunMetrics`].
+#[derive(Debug, Clone)]
+pub struct StatsStore {
+    db: PathBuf,
+}
+
+impl StatsStore {
+    /// Returns the database path for a repository.
+    pub fn path(repo_path: &Path) -> PathBuf {
+        repo_path.join(STATS_DB)
+    }
+
+    /// Opens the repository's store, creating the database if needed.
+    pub fn open(repo_path: &Path) -> Result<Self> {
+        let db = Self::path(repo_path);
+        if let Some(parent) = db.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let store = Self { db };
+        store.execute(SCHEMA)?;
+        Ok(store)
+    }
+
+    /// Records a finished run, replacing an earlier record of the same ID.
+    pub fn record(&self, metrics: &RunMetrics) -> Result<()> {
+        let run_id = quote(&metrics.run_id);
+        let mut sql = format!(
+            "BEGIN;\n\
+             INSERT OR REPLACE INTO runs VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {});\n\
+             DELETE FROM domain_reviews WHERE run_id = {};\n",
+            run_id,
+            quote(&metrics.kind),
+            metrics.started_at,
+            metrics.duration_secs,
+            metrics.success as u8,
+            metrics.iterations,
+            metrics
+                .cost_usd
+                .map_or_else(|| "NULL".to_string(), |c| c.to_string()),
+            metrics
+                .verdict
+                .as_deref()
+                .map_or_else(|| "NULL".to_string(), quote),
+            metrics
+                .failure_reason
+                .as_deref()
+                .map_or_else(|| "NULL".to_string(), quote),
+            run_id
+        );
+        for domain in &metrics.domains {
+            sql.push_str(&format!(
+                "INSERT OR REPLACE INTO domain_reviews VALUES ({}, {}, {}, {});\n",
+                run_id,
+                quote(&domain.domain),
+                domain.fix_rounds,
+                domain.approved as u8
+            ));
+        }
+        sql.push_str("COMMIT;\n");
+        self.execute(&sql)
+    }
+
+    /// Aggregates the recorded runs.
+    pub fn report(&self) -> Result<StatsReport> {
+        Ok(StatsReport {
+            kinds: self.query(
+                "SELECT kind, COUNT(*) AS runs, SUM(success) AS succeeded, \
+                 AVG(duration_secs) AS avg_duration_secs, AVG(iterations) AS avg_iterations, \
+                 AVG(cost_usd) AS avg_cost_usd \
+                 FROM runs GROUP BY kind ORDER BY runs DESC, kind;",
+            )?,
+            domains: self.query(
+                "SELECT domain, COUNT(*) AS reviews, AVG(fix_rounds) AS avg_fix_rounds, \
+                 AVG(approved) AS approval_rate \
+                 FROM domain_reviews GROUP BY domain ORDER BY avg_fix_rounds DESC, domain;",
+            )?,
+            failures: self.query(&format!(
+                "SELECT failure_reason AS reason, COUNT(*) AS count \
+                 FROM runs WHERE failure_reason IS NOT NULL \
+                 GROUP BY failure_reason ORDER BY count DESC, reason LIMIT {};",
+                TOP_FAILURES
+            ))?,
+            weeks: self.query(&format!(
+                "SELECT strftime('%Y-W%W', started_at, 'unixepoch') AS week, \
+                 COUNT(*) AS runs, SUM(success) AS succeeded, AVG(iterations) AS avg_iterations \
+                 FROM runs GROUP BY week ORDER BY week DESC LIMIT {};",
+                TREND_WEEKS
+            ))?,
+        })
+    }
+
+    /// Runs `sql` for its effects.
+    fn execute(&self, sql: &str) -> Result<()> {
+        self.sqlite(&[], sql).map(|_| ())
+    }
+
+    /// Runs a query and parses its rows.
+    fn query<T: DeserializeOwned>(&self, sql: &str) -> Result<Vec<T>> {
+        let output = self.sqlite(&["-json"], sql)?;
+        if output.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(&output).map_err(|e| Error::json("stats query results", e))
+    }
+
+    /// Feeds `sql` to the `sqlite3` shell and returns its output.
+    fn sqlite(&self, flags: &[&str], sql: &str) -> Result<String> {
+        let mut child = Command::new("sqlite3")
+            .args(["-bail"])
+            .args(flags)
+            .arg(&self.db)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::spawn_failed("sqlite3", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(sql.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            return Err(Error::command("sqlite3", &output));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Totals for one kind of run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KindStats {
+    /// Kind of run.
+    pub kind: String,
+    /// Runs recorded.
+    pub runs: u64,
+    /// Runs that succeeded.
+    pub succeeded: u64,
+    /// Mean duration in seconds.
+    pub avg_duration_secs: f64,
+    /// Mean review rounds.
+    pub avg_iterations: f64,
+    /// Mean estimated cost of the runs with a cost.
+    pub avg_cost_usd: Option<f64>,
+}
+
+/// Review rounds of one domain across runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainStats {
+    /// Review domain.
+    pub domain: String,
+    /// Runs that reviewed the domain.
+    pub reviews: u64,
+    /// Mean fix rounds per run.
+    pub avg_fix_rounds: f64,
+    /// Fraction of runs the domain approved.
+    pub approval_rate: f64,
+}
+
+/// A failure reason and how often it occurred.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureStats {
+    /// The failure reason.
+    pub reason: String,
+    /// Runs that failed for it.
+    pub count: u64,
+}
+
+/// Runs started in one week.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeekStats {
+    /// ISO-style week label, e.g. `2026-W41`.
+    pub week: String,
+    /// Runs started.
+    pub runs: u64,
+    /// Runs that succeeded.
+    pub succeeded: u64,
+    /// Mean review rounds.
+    pub avg_iterations: f64,
+}
+
+/// Trends across the recorded runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsReport {
+    /// Totals per kind of run, most frequent first.
+    pub kinds: Vec<KindStats>,
+    /// Fix rounds per review domain, most rounds first.
+    pub domains: Vec<DomainStats>,
+    /// Most common failure reasons.
+    pub failures: Vec<FailureStats>,
+    /// Recent weeks, newest first.
+    pub weeks: Vec<WeekStats>,
+}
+
+impl StatsReport {
+    /// Returns whether no runs are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    /// Renders the report for the terminal.
+    pub fn render(&self) -> String {
+        if self.is_empty() {
+            return "No runs recorded\n".to_string();
+        }
+
+        let mut out = String::from("Runs\n");
+        for kind in &self.kinds {
+            out.push_str(&format!(
+                "  {:<12} {:>4} runs  {:>3.0}% succeeded  avg {}  {:.1} review rounds",
+                kind.kind,
+                kind.runs,
+                percent(kind.succeeded, kind.runs),
+                format_secs(kind.avg_duration_secs.round() as u64),
+                kind.avg_iterations
+            ));
+            if let Some(cost) = kind.avg_cost_usd {
+                out.push_str(&format!("  ${:.2}/run", cost));
+            }
+            out.push('\n');
+        }
+
+        if !self.domains.is_empty() {
+            out.push_str("\nFix rounds per review domain\n");
+            for domain in &self.domains {
+                out.push_str(&format!(
+                    "  {:<16} {:.1} avg over {} review(s), {:.0}% approved\n",
+                    domain.domain,
+                    domain.avg_fix_rounds,
+                    domain.reviews,
+                    domain.approval_rate * 100.0
+                ));
+            }
+        }
+
+        if !self.failures.is_empty() {
+            out.push_str("\nMost common failures\n");
+            for failure in &self.failures {
+                out.push_str(&format!("  {:>4}x {}\n", failure.count, failure.reason));
+            }
+        }
+
+        if !self.weeks.is_empty() {
+            out.push_str("\nWeekly trend\n");
+            for week in &self.weeks {
+                out.push_str(&format!(
+                    "  {}  {:>4} runs  {:>3.0}% succeeded  {:.1} review rounds\n",
+                    week.week,
+                    week.runs,
+                    percent(week.succeeded, week.runs),
+                    week.avg_iterations
+                ));
+            }
+        }
+
+        out
+    }
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Returns the serde name of a unit enum variant, e.g. `timed_out`.
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Shortens a failure message to its first line so that repeated
+/// failures group together.
+fn failure_mode(reason: &str) -> String {
+    let line = reason.lines().next().unwrap_or_default().trim();
+    if line.len() <= MAX_REASON_LEN {
+        return line.to_string();
+    }
+    let mut end = MAX_REASON_LEN;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = line[..end].rfind(' ').unwrap_or(end);
+    format!("{}...", &line[..cut])
+}
+
+/// Quotes `value` as an SQL string literal.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cruise::result::TaskResult;
+    use crate::review_loop::ReviewPass;
+    use crate::team::ReviewResult;
+    use tempfile::TempDir;
+
+    fn has_sqlite() -> bool {
+        Command::new("sqlite3").arg("-version").output().is_ok()
+    }
+
+    fn pass(iteration: u32, verdict: ReviewVerdict, fixed: bool) -> ReviewPass {
+        ReviewPass {
+            iteration,
+            review: ReviewResult {
+                verdict,
+                suggestions: vec![],
+                summary: String::new(),
+            },
+            blocking: 0,
+            fixed,
+        }
+    }
+
+    #[test]
+    fn metrics_from_reports_and_domains() {
+        let mut report =
+            RunReport::new("cruise", "run-1", "Add auth").with_duration(Duration::from_secs(600));
+        report.tasks = vec![TaskResult {
+            task_id: "CRUISE-002".to_string(),
+            status: TaskStatus::Blocked,
+            pr_url: None,
+            duration: Duration::from_secs(60),
+            error: Some("Tests failed: 3 failing\n\nfull output...".to_string()),
+        }];
+        let metrics = RunMetrics::from_report(&report);
+        assert!(!metrics.success);
+        assert_eq!(metrics.duration_secs, 600.0);
+        assert_eq!(
+            metrics.failure_reason.as_deref(),
+            Some("Tests failed: 3 failing")
+        );
+
+        let security = DomainReviewOutcome {
+            domain: "security".to_string(),
+            passes: vec![
+                pass(1, ReviewVerdict::NeedsChanges, true),
+                pass(2, ReviewVerdict::NeedsChanges, true),
+                pass(3, ReviewVerdict::Approved, false),
+            ],
+            approved: true,
+            skipped: None,
+        };
+        let metrics = RunMetrics::new("review", "pr-7", Duration::from_secs(30))
+            .with_verdict(&ReviewVerdict::Approved)
+            .with_domains(&[security]);
+        assert_eq!(metrics.iterations, 3);
+        assert_eq!(metrics.verdict.as_deref(), Some("approved"));
+        assert_eq!(metrics.domains[0].fix_rounds, 2);
+
+        let long = format!("{} tail", "word ".repeat(40));
+        assert!(failure_mode(&long).len() <= MAX_REASON_LEN + 3);
+        assert!(failure_mode(&long).ends_with("word..."));
+    }
+
+    #[test]
+    fn records_and_aggregates_runs() {
+        if !has_sqlite() {
+            return;
+        }
+        let dir = TempDir::new().unwrap();
+        let store = StatsStore::open(dir.path()).unwrap();
+        assert!(store.report().unwrap().is_empty());
+
+        let domain = |domain: &str, fix_rounds| DomainMetrics {
+            domain: domain.to_string(),
+            fix_rounds,
+            approved: true,
+        };
+        let mut first = RunMetrics::new("spawn-team", "run-1", Duration::from_secs(100));
+        first.iterations = 2;
+        first.cost_usd = Some(1.5);
+        first.domains = vec![domain("security", 3), domain("style", 0)];
+        let mut second = RunMetrics::new("spawn-team", "run-2", Duration::from_secs(300))
+            .with_failure("Reviewer's verdict: needs_changes");
+        second.iterations = 4;
+        second.domains = vec![domain("security", 1)];
+        let third = RunMetrics::new("spawn", "run-3", Duration::from_secs(50))
+            .with_failure("Reviewer's verdict: needs_changes");
+        for metrics in [&first, &second, &third] {
+            store.record(metrics).unwrap();
+        }
+        // Recording a run again replaces it
+        store.record(&first).unwrap();
+
+        let report = store.report().unwrap();
+        assert_eq!(report.kinds.len(), 2);
+        let team = &report.kinds[0];
+        assert_eq!(
+            (team.kind.as_str(), team.runs, team.succeeded),
+            ("spawn-team", 2, 1)
+        );
+        assert_eq!(team.avg_duration_secs, 200.0);
+        assert_eq!(team.avg_iterations, 3.0);
+        assert_eq!(team.avg_cost_usd, Some(1.5));
+        assert_eq!(report.kinds[1].avg_cost_usd, None);
+
+        assert_eq!(report.domains[0].domain, "security");
+        assert_eq!(report.domains[0].reviews, 2);
+        assert_eq!(report.domains[0].avg_fix_rounds, 2.0);
+
+        assert_eq!(
+            report.failures,
+            vec![FailureStats {
+                reason: "Reviewer's verdict: needs_changes".to_string(),
+                count: 2,
+            }]
+        );
+        assert_eq!(report.weeks[0].runs, 3);
+
+        let rendered = report.render();
+        assert!(rendered.contains("50% succeeded"));
+        assert!(rendered.contains("security         2.0 avg over 2 review(s)"));
+        assert!(rendered.contains("2x Reviewer's verdict: needs_changes"));
+    }
+}
@@ -1,10 +1,23 @@
 //! Spawn-team coordination for multi-LLM workflows.
 //!
 //! Supports sequential and ping-pong coordination modes
-//! for primary/reviewer LLM interactions.
+//! for primary/reviewer LLM interactions, and pipelines of further roles
+//! (see [`TeamRole`]).
+
+use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::benchmark::BenchmarkConfig;
+use crate::checks::CiConfig;
+use crate::diff::DiffConfig;
+use crate::patch;
+use crate::prompt::{budget, PromptTemplates, TemplateVars};
+use crate::verification::VerificationConfig;
+
+/// Model assumed for token estimation when none is configured.
+const DEFAULT_REVIEWER_MODEL: &str = "gemini-cli";
+
 /// Coordination mode for spawn-team.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -13,6 +26,7 @@ pub enum CoordinationMode {
     #[default]
     Sequential,
     /// Ping-pong mode: Iterative back-and-forth until approved.
+    #[serde(alias = "ping-pong")]
     PingPong,
 }
 
@@ -31,6 +45,46 @@ pub struct SpawnTeamConfig {
     /// Reviewer LLM identifier (e.g., "gemini-cli").
     #[serde(default = "default_reviewer_llm")]
     pub reviewer_llm: String,
+    /// Model passed to the primary LLM (e.g., "opus").
+    #[serde(default)]
+    pub primary_model: Option<String>,
+    /// Model passed to the reviewer LLM (e.g., "gemini-2.5-pro").
+    #[serde(default)]
+    pub reviewer_model: Option<String>,
+    /// Per-phase model overrides.
+    #[serde(default)]
+    pub models: PhaseModels,
+    /// Open PRs as drafts and mark them ready once review approves.
+    #[serde(default)]
+    pub draft_prs: bool,
+    /// Which review findings force a fix round.
+    #[serde(default)]
+    pub gating: ReviewGating,
+    /// Which changes each review domain sees.
+    #[serde(default)]
+    pub diff: DiffConfig,
+    /// Review domains to skip, force, or route changed paths to.
+    #[serde(default)]
+    pub review_domains: DomainSelection,
+    /// Continue the same LLM conversations across fix and review rounds.
+    #[serde(default)]
+    pub reuse_sessions: bool,
+    /// Fixing of CI failures after a push.
+    #[serde(default)]
+    pub ci: CiConfig,
+    /// Local verification before changes are pushed.
+    #[serde(default)]
+    pub verify: VerificationConfig,
+    /// Benchmarks shown to the performance review domain.
+    #[serde(default)]
+    pub benchmarks: BenchmarkConfig,
+    /// Roles run in turn on the task. Without any, the team is the primary
+    /// as implementer and the reviewer.
+    #[serde(default)]
+    pub roles: Vec<TeamRole>,
+    /// Test writer run after the implementer, before reviews.
+    #[serde(default)]
+    pub tester: TesterConfig,
 }
 
 fn default_max_iterations() -> u32 {
@@ -52,8 +106,213 @@ impl Default for SpawnTeamConfig {
             max_iterations: default_max_iterations(),
             primary_llm: default_primary_llm(),
             reviewer_llm: default_reviewer_llm(),
+            primary_model: None,
+            reviewer_model: None,
+            models: PhaseModels::default(),
+            draft_prs: false,
+            gating: ReviewGating::default(),
+            diff: DiffConfig::default(),
+            review_domains: DomainSelection::default(),
+            reuse_sessions: false,
+            ci: CiConfig::default(),
+            verify: VerificationConfig::default(),
+            benchmarks: BenchmarkConfig::default(),
+            roles: Vec::new(),
+            tester: TesterConfig::default(),
+        }
+    }
+}
+
+impl SpawnTeamConfig {
+    /// Returns the model for fix rounds: the `fix` override, else the
+    /// primary model.
+    pub fn fix_model(&self) -> Option<&str> {
+        self.models.fix.as_deref().or(self.primary_model.as_deref())
+    }
+
+    /// Returns the model for review passes: the `review` override, else the
+    /// reviewer model.
+    pub fn review_model(&self) -> Option<&str> {
+        self.models
+            .review
+            .as_deref()
+            .or(self.reviewer_model.as_deref())
+    }
+
+    /// Returns the model that checks whether a fix resolves a finding: the
+    /// `verify` override, else the reviewer model.
+    pub fn verify_model(&self) -> Option<&str> {
+        self.models
+            .verify
+            .as_deref()
+            .or(self.reviewer_model.as_deref())
+    }
+
+    /// Returns the roles to run, sorted by their order. Roles with the same
+    /// order keep their configured order. An enabled [`TesterConfig`] adds
+    /// a tester after the implementer.
+    pub fn role_pipeline(&self) -> Vec<TeamRole> {
+        let mut roles = if self.roles.is_empty() {
+            let mut implementer = TeamRole::new("implementer", &self.primary_llm);
+            implementer.model = self.primary_model.clone();
+            let mut reviewer = TeamRole::new("reviewer", &self.reviewer_llm);
+            reviewer.model = self.review_model().map(String::from);
+            vec![implementer, reviewer]
+        } else {
+            self.roles.clone()
+        };
+        roles.sort_by_key(|role| role.order);
+
+        if self.tester.enabled && !roles.iter().any(|role| role.name == TESTER_ROLE) {
+            let llm = self.tester.llm.as_deref().unwrap_or(&self.primary_llm);
+            let mut tester = TeamRole::new(TESTER_ROLE, llm);
+            // The primary model only suits the primary's own CLI
+            tester.model = self.tester.model.clone().or_else(|| {
+                (llm == self.primary_llm)
+                    .then(|| self.primary_model.clone())
+                    .flatten()
+            });
+            let at = roles
+                .iter()
+                .position(|role| role.name == "implementer")
+                .map(|i| i + 1)
+                .or_else(|| roles.iter().position(|role| role.name == "reviewer"))
+                .unwrap_or(roles.len());
+            tester.order = roles.get(at).or(roles.last()).map_or(0, |role| role.order);
+            roles.insert(at, tester);
+        }
+        roles
+    }
+
+    /// Returns whether the team runs a role pipeline rather than a single
+    /// primary.
+    pub fn uses_role_pipeline(&self) -> bool {
+        !self.roles.is_empty() || self.tester.enabled
+    }
+}
+
+/// Name of the role that writes tests and must pass the test gate.
+pub const TESTER_ROLE: &str = "tester";
+
+/// Settings for the dedicated test writer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TesterConfig {
+    /// Runs a tester after the implementer, unless a `tester` role is
+    /// already configured.
+    #[serde(default)]
+    pub enabled: bool,
+    /// LLM that writes the tests; defaults to the primary.
+    #[serde(default)]
+    pub llm: Option<String>,
+    /// Model passed to the LLM; defaults to the primary model.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Commands that must pass after the tester before reviews start.
+    #[serde(default)]
+    pub gate: VerificationConfig,
+}
+
+/// Built-in roles and their instructions.
+const BUILTIN_ROLES: &[(&str, &str)] = &[
+    (
+        "architect",
+        "You are the architect. Study the repository and design how to carry out the task: \
+         the modules to change, the interfaces to add, and the order of the work. Do not modify \
+         any files; answer with the design.",
+    ),
+    (
+        "implementer",
+        "You are the implementer. Carry out the task, following the design from earlier roles \
+         where there is one.",
+    ),
+    (
+        "tester",
+        "You are the tester. Write or extend tests covering the changes made for the task, run \
+         them, and fix what fails until they pass.",
+    ),
+    (
+        "reviewer",
+        "You are the reviewer. Review the changes made for the task for bugs, missing \
+         requirements, and deviations from the repository's conventions. Do not modify any \
+         files; list the problems you find, or say the changes are ready.",
+    ),
+];
+
+/// Roles that only read the repository unless configured otherwise.
+const READ_ONLY_ROLES: &[&str] = &["architect", "reviewer"];
+
+/// One role in a spawn-team pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamRole {
+    /// Role name, e.g. "architect" or "tester".
+    pub name: String,
+    /// LLM that plays the role.
+    #[serde(default = "default_primary_llm")]
+    pub llm: String,
+    /// Model passed to the LLM.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Instructions for the role. Built-in roles have their own.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Position in the pipeline; lower runs first.
+    #[serde(default)]
+    pub order: u32,
+    /// Whether the role may only read the repository. Defaults to true for
+    /// the architect and reviewer.
+    #[serde(default)]
+    pub read_only: Option<bool>,
+}
+
+impl TeamRole {
+    /// Creates a role played by `llm`.
+    pub fn new(name: impl Into<String>, llm: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            llm: llm.into(),
+            model: None,
+            prompt: None,
+            order: 0,
+            read_only: None,
         }
     }
+
+    /// Returns whether this is one of the built-in roles.
+    pub fn is_builtin(&self) -> bool {
+        BUILTIN_ROLES.iter().any(|(name, _)| *name == self.name)
+    }
+
+    /// Returns the role's instructions: the configured prompt, else the
+    /// built-in one.
+    pub fn instructions(&self) -> Option<&str> {
+        self.prompt.as_deref().or_else(|| {
+            BUILTIN_ROLES
+                .iter()
+                .find(|(name, _)| *name == self.name)
+                .map(|(_, prompt)| *prompt)
+        })
+    }
+
+    /// Returns whether the role may only read the repository.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+            .unwrap_or_else(|| READ_ONLY_ROLES.contains(&self.name.as_str()))
+    }
+}
+
+/// Models for individual phases of a team run, overriding the primary and
+/// reviewer models.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseModels {
+    /// Model for fix rounds.
+    #[serde(default)]
+    pub fix: Option<String>,
+    /// Model for review passes.
+    #[serde(default)]
+    pub review: Option<String>,
+    /// Model for checking fixes against findings.
+    #[serde(default)]
+    pub verify: Option<String>,
 }
 
 /// Verdict from a reviewer.
@@ -68,6 +327,31 @@ pub enum ReviewVerdict {
     Failed,
 }
 
+/// Severity of a review finding, from least to most severe.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewSeverity {
+    /// Style or wording preference.
+    Nit,
+    /// Minor issue.
+    Low,
+    /// Issue worth fixing, but not blocking.
+    Medium,
+    /// Bug or missing requirement.
+    High,
+    /// Security hole, data loss, or broken build.
+    Critical,
+}
+
+/// Severity assumed for findings the reviewer did not rate.
+///
+/// Unrated findings block, as all findings did before severities existed.
+fn default_severity() -> ReviewSeverity {
+    ReviewSeverity::High
+}
+
 /// A suggestion from the reviewer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewSuggestion {
@@ -79,6 +363,219 @@ pub struct ReviewSuggestion {
     pub issue: String,
     /// Suggested fix.
     pub suggestion: String,
+    /// Severity of the issue.
+    #[serde(default = "default_severity")]
+    pub severity: ReviewSeverity,
+}
+
+/// What to do with a finding of a given severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateAction {
+    /// Require a fix round.
+    Block,
+    /// Record the finding without fixing it.
+    Log,
+    /// Drop the finding.
+    Ignore,
+}
+
+/// Severity thresholds for one review domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewGatePolicy {
+    /// Findings at or above this severity force a fix round.
+    #[serde(default = "default_block_at")]
+    pub block_at: ReviewSeverity,
+    /// Findings at or above this severity (but below `block_at`) are logged.
+    #[serde(default = "default_log_at")]
+    pub log_at: ReviewSeverity,
+}
+
+fn default_block_at() -> ReviewSeverity {
+    ReviewSeverity::High
+}
+
+fn default_log_at() -> ReviewSeverity {
+    ReviewSeverity::Medium
+}
+
+impl Default for ReviewGatePolicy {
+    fn default() -> Self {
+        Self {
+            block_at: default_block_at(),
+            log_at: default_log_at(),
+        }
+    }
+}
+
+impl ReviewGatePolicy {
+    /// Returns the action for a finding of the given severity.
+    pub fn action_for(&self, severity: ReviewSeverity) -> GateAction {
+        if severity >= self.block_at {
+            GateAction::Block
+        } else if severity >= self.log_at {
+            GateAction::Log
+        } else {
+            GateAction::Ignore
+        }
+    }
+}
+
+/// Gating policies, with optional overrides per review domain.
+///
+/// Domains are named after the review focus, e.g. `"security"` or
+/// `"general_polish"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewGating {
+    /// Policy for domains without an override.
+    #[serde(default)]
+    pub default: ReviewGatePolicy,
+    /// Per-domain overrides.
+    #[serde(default)]
+    pub domains: HashMap<String, ReviewGatePolicy>,
+}
+
+impl ReviewGating {
+    /// Returns the policy for a review domain.
+    pub fn policy(&self, domain: &str) -> &ReviewGatePolicy {
+        self.domains.get(domain).unwrap_or(&self.default)
+    }
+
+    /// Sorts a review's findings by the domain's policy.
+    pub fn gate(&self, domain: &str, review: &ReviewResult) -> GatedReview {
+        let policy = self.policy(domain);
+        let mut gated = GatedReview::default();
+
+        for suggestion in &review.suggestions {
+            match policy.action_for(suggestion.severity) {
+                GateAction::Block => gated.blocking.push(suggestion.clone()),
+                GateAction::Log => {
+                    tracing::info!(
+                        domain = %domain,
+                        file = %suggestion.file,
+                        severity = ?suggestion.severity,
+                        issue = %suggestion.issue,
+                        "non-blocking review finding"
+                    );
+                    gated.logged.push(suggestion.clone());
+                }
+                GateAction::Ignore => gated.ignored += 1,
+            }
+        }
+
+        // A rejection without findings (e.g., a bare "NEEDS CHANGES") still blocks
+        gated.needs_fix_round = !gated.blocking.is_empty()
+            || (review.verdict == ReviewVerdict::NeedsChanges && review.suggestions.is_empty());
+        gated
+    }
+}
+
+/// Which review domains run for a change.
+///
+/// Path patterns use `**` for any number of directories and `*` within
+/// one path segment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainSelection {
+    /// Domains that are never reviewed.
+    #[serde(default)]
+    pub skip: Vec<String>,
+    /// Domains always reviewed, whatever the changed paths.
+    #[serde(default)]
+    pub force: Vec<String>,
+    /// Domains reviewed only when a changed file matches one of their path
+    /// patterns, e.g. `performance = ["src/**"]`.
+    #[serde(default)]
+    pub relevant_paths: HashMap<String, Vec<String>>,
+    /// Domains skipped when every changed file matches one of their path
+    /// patterns, e.g. `security = ["docs/**", "**/*.md"]`.
+    #[serde(default)]
+    pub skip_when_only: HashMap<String, Vec<String>>,
+    /// Rules sending changed files to review domains.
+    #[serde(default)]
+    pub routes: Vec<DomainRoute>,
+}
+
+/// Sends changed files matching `paths` to review domains.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainRoute {
+    /// Path patterns the route applies to.
+    pub paths: Vec<String>,
+    /// Domains that review matching files.
+    pub domains: Vec<String>,
+    /// Matching files are reviewed by `domains` instead of, rather than as
+    /// well as, the default domains.
+    #[serde(default)]
+    pub only: bool,
+}
+
+impl DomainSelection {
+    /// Returns the domains that review a change to `changed`, sorted.
+    ///
+    /// Files no route matches are reviewed by `defaults`; routed files by
+    /// their routes' domains, plus `defaults` unless a route is `only`.
+    /// Forced domains are always included. Without routes or changed
+    /// files, every default domain is kept.
+    pub fn route(&self, defaults: &[String], changed: &[String]) -> Vec<String> {
+        let mut domains = self.force.clone();
+        if self.routes.is_empty() || changed.is_empty() {
+            domains.extend_from_slice(defaults);
+        }
+        for path in changed {
+            let routes: Vec<&DomainRoute> = self
+                .routes
+                .iter()
+                .filter(|r| r.paths.iter().any(|p| patch::glob_matches(p, path)))
+                .collect();
+            if !routes.iter().any(|r| r.only) {
+                domains.extend_from_slice(defaults);
+            }
+            domains.extend(routes.iter().flat_map(|r| r.domains.iter().cloned()));
+        }
+        domains.sort_unstable();
+        domains.dedup();
+        domains
+    }
+
+    /// Returns whether deciding on `domain` needs the changed files.
+    pub fn checks_paths(&self, domain: &str) -> bool {
+        !self.force.iter().any(|d| d == domain)
+            && (self.relevant_paths.contains_key(domain)
+                || self.skip_when_only.contains_key(domain))
+    }
+
+    /// Returns why `domain` is skipped for a change to `changed`, or `None`
+    /// if it is reviewed.
+    pub fn skip_reason(&self, domain: &str, changed: &[String]) -> Option<String> {
+        let matches = |patterns: &[String], path: &String| {
+            patterns.iter().any(|p| patch::glob_matches(p, path))
+        };
+        if self.force.iter().any(|d| d == domain) {
+            return None;
+        }
+        if self.skip.iter().any(|d| d == domain) {
+            return Some("skipped by configuration".to_string());
+        }
+        if let Some(patterns) = self.relevant_paths.get(domain) {
+            if !changed.iter().any(|path| matches(patterns, path)) {
+                return Some(format!("no changes to {}", patterns.join(", ")));
+            }
+        }
+        let patterns = self.skip_when_only.get(domain)?;
+        (!changed.is_empty() && changed.iter().all(|path| matches(patterns, path)))
+            .then(|| format!("only {} changed", patterns.join(", ")))
+    }
+}
+
+/// Review findings sorted by a gating policy.
+#[derive(Debug, Clone, Default)]
+pub struct GatedReview {
+    /// Findings that must be fixed.
+    pub blocking: Vec<ReviewSuggestion>,
+    /// Findings recorded but not fixed.
+    pub logged: Vec<ReviewSuggestion>,
+    /// Number of findings dropped.
+    pub ignored: usize,
+    /// Whether a fix round is required.
+    pub needs_fix_round: bool,
 }
 
 /// Result of a review phase.
@@ -124,6 +621,10 @@ pub struct SpawnTeamResult {
 pub struct ReviewPromptBuilder {
     original_prompt: String,
     git_diff: String,
+    focus: Option<String>,
+    conventions: Option<String>,
+    model: Option<String>,
+    max_diff_tokens: Option<usize>,
 }
 
 impl ReviewPromptBuilder {
@@ -132,6 +633,10 @@ impl ReviewPromptBuilder {
         Self {
             original_prompt: original_prompt.into(),
             git_diff: String::new(),
+            focus: None,
+            conventions: None,
+            model: None,
+            max_diff_tokens: None,
         }
     }
 
@@ -141,38 +646,58 @@ impl ReviewPromptBuilder {
         self
     }
 
-    /// Builds the review prompt.
+    /// Narrows the review to one domain's concerns.
+    pub fn with_focus(mut self, focus: impl Into<String>) -> Self {
+        self.focus = Some(focus.into());
+        self
+    }
+
+    /// Adds the project's agent instructions for the reviewer to check
+    /// changes against. See [`crate::instructions::summarize`].
+    pub fn with_conventions(mut self, conventions: impl Into<String>) -> Self {
+        let conventions = conventions.into();
+        self.conventions = (!conventions.trim().is_empty()).then_some(conventions);
+        self
+    }
+
+    /// Sets the reviewer model, used for token estimation.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Limits the diff to roughly `max_tokens` tokens.
+    ///
+    /// Large diffs keep every file header and drop middle hunks first.
+    pub fn with_max_diff_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_diff_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Builds the review prompt using the built-in template.
     pub fn build(&self) -> String {
-        let mut prompt = String::new();
-
-        prompt.push_str("## Code Review Request\n\n");
-        prompt.push_str("Please review the following changes and provide feedback.\n\n");
-
-        prompt.push_str("### Original Task\n\n");
-        prompt.push_str(&self.original_prompt);
-        prompt.push_str("\n\n");
-
-        prompt.push_str("### Changes Made\n\n");
-        prompt.push_str("```diff\n");
-        prompt.push_str(&self.git_diff);
-        prompt.push_str("\n```\n\n");
-
-        prompt.push_str("### Response Format\n\n");
-        prompt.push_str("Respond with a JSON object:\n");
-        prompt.push_str("```json\n");
-        prompt.push_str("{\n");
-        prompt.push_str("  \"verdict\": \"approved\" | \"needs_changes\",\n");
-        prompt.push_str("  \"suggestions\": [\n");
-        prompt.push_str("    {\n");
-        prompt.push_str("      \"file\": \"path/to/file\",\n");
-        prompt.push_str("      \"line\": 42,\n");
-        prompt.push_str("      \"issue\": \"description of issue\",\n");
-        prompt.push_str("      \"suggestion\": \"how to fix it\"\n");
-        prompt.push_str("    }\n");
-        prompt.push_str("  ]\n");
-        prompt.push_str("}\n");
-        prompt.push_str("```\n");
+        self.build_with(&PromptTemplates::builtin())
+    }
 
+    /// Builds the review prompt using the given template registry.
+    pub fn build_with(&self, templates: &PromptTemplates) -> String {
+        let model = self.model.as_deref().unwrap_or(DEFAULT_REVIEWER_MODEL);
+        let diff = match self.max_diff_tokens {
+            Some(max_tokens) => budget::truncate_diff(&self.git_diff, max_tokens, model),
+            None => self.git_diff.clone(),
+        };
+
+        let mut vars = TemplateVars::new();
+        vars.insert("original_prompt", self.original_prompt.clone());
+        vars.insert("diff", diff);
+        if let Some(focus) = &self.focus {
+            vars.insert("focus", focus.clone());
+        }
+        if let Some(conventions) = &self.conventions {
+            vars.insert("conventions", conventions.clone());
+        }
+        let prompt = templates.render(PromptTemplates::REVIEW, &vars);
+        budget::warn_if_over_window(&prompt, model);
         prompt
     }
 }
@@ -198,29 +723,60 @@ impl FixPromptBuilder {
         self
     }
 
-    /// Builds the fix prompt.
+    /// Builds the fix prompt using the built-in template.
     pub fn build(&self) -> String {
-        let mut prompt = String::new();
-
-        prompt.push_str("## Fix Request\n\n");
-        prompt.push_str("Address the following review feedback:\n\n");
-
-        prompt.push_str("### Original Task\n\n");
-        prompt.push_str(&self.original_prompt);
-        prompt.push_str("\n\n");
+        self.build_with(&PromptTemplates::builtin())
+    }
 
-        prompt.push_str("### Issues to Fix\n\n");
+    /// Builds the fix prompt using the given template registry.
+    pub fn build_with(&self, templates: &PromptTemplates) -> String {
+        let mut issues = String::new();
         for (i, suggestion) in self.suggestions.iter().enumerate() {
-            prompt.push_str(&format!("{}. **{}**", i + 1, suggestion.file));
+            issues.push_str(&format!("{}. **{}**", i + 1, suggestion.file));
             if let Some(line) = suggestion.line {
-                prompt.push_str(&format!(" (line {})", line));
+                issues.push_str(&format!(" (line {})", line));
             }
-            prompt.push_str("\n");
-            prompt.push_str(&format!("   - Issue: {}\n", suggestion.issue));
-            prompt.push_str(&format!("   - Suggestion: {}\n\n", suggestion.suggestion));
+            issues.push('\n');
+            issues.push_str(&format!("   - Issue: {}\n", suggestion.issue));
+            issues.push_str(&format!("   - Suggestion: {}\n\n", suggestion.suggestion));
         }
 
-        prompt
+        let mut vars = TemplateVars::new();
+        vars.insert("original_prompt", self.original_prompt.clone());
+        vars.insert("issues", issues);
+        templates.render(PromptTemplates::FIX, &vars)
+    }
+}
+
+/// Builder for review prompts in GitHub mode.
+///
+/// The reviewer reads the PR itself and posts its review as a PR comment
+/// carrying a machine-readable artifact (see [`crate::pr::parse_review_comment`]).
+pub struct GitHubReviewPromptBuilder {
+    original_prompt: String,
+    pr_url: String,
+}
+
+impl GitHubReviewPromptBuilder {
+    /// Creates a new GitHub review prompt builder.
+    pub fn new(original_prompt: impl Into<String>, pr_url: impl Into<String>) -> Self {
+        Self {
+            original_prompt: original_prompt.into(),
+            pr_url: pr_url.into(),
+        }
+    }
+
+    /// Builds the prompt using the built-in template.
+    pub fn build(&self) -> String {
+        self.build_with(&PromptTemplates::builtin())
+    }
+
+    /// Builds the prompt using the given template registry.
+    pub fn build_with(&self, templates: &PromptTemplates) -> String {
+        let mut vars = TemplateVars::new();
+        vars.insert("original_prompt", self.original_prompt.clone());
+        vars.insert("pr_url", self.pr_url.clone());
+        templates.render(PromptTemplates::GITHUB_REVIEW, &vars)
     }
 }
 
@@ -233,7 +789,13 @@ pub fn parse_review_response(response: &str) -> Option<ReviewResult> {
 
     // Parse the JSON
     let parsed: serde_json::Value = serde_json::from_str(json_str).ok()?;
+    review_from_json(&parsed)
+}
 
+/// Builds a review result from a parsed JSON object.
+///
+/// Findings are read from `suggestions`, or `findings` if that is absent.
+pub(crate) fn review_from_json(parsed: &serde_json::Value) -> Option<ReviewResult> {
     let verdict = match parsed.get("verdict")?.as_str()? {
         "approved" => ReviewVerdict::Approved,
         "needs_changes" => ReviewVerdict::NeedsChanges,
@@ -241,7 +803,11 @@ pub fn parse_review_response(response: &str) -> Option<ReviewResult> {
     };
 
     let mut suggestions = Vec::new();
-    if let Some(arr) = parsed.get("suggestions").and_then(|v| v.as_array()) {
+    let items = parsed
+        .get("suggestions")
+        .or_else(|| parsed.get("findings"))
+        .and_then(|v| v.as_array());
+    if let Some(arr) = items {
         for item in arr {
             if let (Some(file), Some(issue), Some(suggestion)) = (
                 item.get("file").and_then(|v| v.as_str()),
@@ -253,6 +819,10 @@ pub fn parse_review_response(response: &str) -> Option<ReviewResult> {
                     line: item.get("line").and_then(|v| v.as_u64()).map(|l| l as u32),
                     issue: issue.to_string(),
                     suggestion: suggestion.to_string(),
+                    severity: item
+                        .get("severity")
+                        .and_then(|v| serde_json::from_value(v.clone()).ok())
+                        .unwrap_or_else(default_severity),
                 });
             }
         }
@@ -261,7 +831,11 @@ pub fn parse_review_response(response: &str) -> Option<ReviewResult> {
     Some(ReviewResult {
         verdict,
         suggestions,
-        summary: String::new(),
+        summary: parsed
+            .get("summary")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
     })
 }
 
@@ -274,6 +848,21 @@ mod tests {
         assert_eq!(CoordinationMode::default(), CoordinationMode::Sequential);
     }
 
+    #[test]
+    fn default_team_is_implementer_then_reviewer() {
+        let config = SpawnTeamConfig {
+            reviewer_model: Some("gemini-2.5-pro".to_string()),
+            ..Default::default()
+        };
+
+        let roles = config.role_pipeline();
+
+        assert_eq!(roles[0], TeamRole::new("implementer", "claude-code"));
+        assert_eq!(roles[1].name, "reviewer");
+        assert_eq!(roles[1].model.as_deref(), Some("gemini-2.5-pro"));
+        assert!(roles[1].is_read_only());
+    }
+
     #[test]
     fn spawn_team_config_has_sensible_defaults() {
         let config = SpawnTeamConfig::default();
@@ -318,6 +907,18 @@ mod tests {
         assert!(prompt.contains("+ new code"));
         assert!(prompt.contains("- old code"));
         assert!(prompt.contains("verdict"));
+        assert!(!prompt.contains("### Project Conventions"));
+    }
+
+    #[test]
+    fn review_prompt_builder_includes_conventions() {
+        let prompt = ReviewPromptBuilder::new("Fix the auth bug")
+            .with_diff("+ new code")
+            .with_conventions("From `CLAUDE.md`:\n\n- Never log tokens\n")
+            .build();
+
+        assert!(prompt.contains("### Project Conventions"));
+        assert!(prompt.contains("- Never log tokens"));
     }
 
     #[test]
@@ -327,6 +928,7 @@ mod tests {
             line: Some(42),
             issue: "Missing error handling".to_string(),
             suggestion: "Add Result return type".to_string(),
+            severity: ReviewSeverity::High,
         }];
 
         let prompt = FixPromptBuilder::new("Implement auth")
@@ -339,6 +941,40 @@ mod tests {
         assert!(prompt.contains("Add Result return type"));
     }
 
+    #[test]
+    fn review_prompt_builder_truncates_large_diff() {
+        let mut diff = String::from("diff --git a/big.rs b/big.rs\n--- a/big.rs\n+++ b/big.rs\n");
+        for h in 0..20 {
+            diff.push_str(&format!("@@ -{h},1 +{h},1 @@\n"));
+            diff.push_str(&"+ added line\n".repeat(50));
+        }
+
+        let prompt = ReviewPromptBuilder::new("Refactor")
+            .with_diff(diff.clone())
+            .with_model("sonnet")
+            .with_max_diff_tokens(500)
+            .build();
+
+        assert!(prompt.contains("diff --git a/big.rs b/big.rs"));
+        assert!(prompt.contains(budget::TRUNCATION_MARKER));
+        assert!(prompt.len() < diff.len());
+    }
+
+    #[test]
+    fn review_prompt_builder_uses_custom_template() {
+        let mut templates = PromptTemplates::builtin();
+        templates.insert(crate::prompt::PromptTemplate::new(
+            PromptTemplates::REVIEW,
+            "Task: {{original_prompt}}\nDiff: {{diff}}",
+        ));
+
+        let prompt = ReviewPromptBuilder::new("Fix the auth bug")
+            .with_diff("+ new code")
+            .build_with(&templates);
+
+        assert_eq!(prompt, "Task: Fix the auth bug\nDiff: + new code");
+    }
+
     #[test]
     fn parse_review_response_extracts_approved() {
         let response = r#"
@@ -398,6 +1034,195 @@ mod tests {
         assert!(result.suggestions[0].line.is_none());
     }
 
+    #[test]
+    fn parse_review_response_reads_findings_and_summary() {
+        let response = r#"{
+            "verdict": "needs_changes",
+            "summary": "One bug",
+            "findings": [{"file": "a.rs", "issue": "Off by one", "suggestion": "Use <="}]
+        }"#;
+
+        let result = parse_review_response(response).unwrap();
+        assert_eq!(result.summary, "One bug");
+        assert_eq!(result.suggestions.len(), 1);
+        assert_eq!(result.suggestions[0].issue, "Off by one");
+    }
+
+    fn finding(file: &str, severity: ReviewSeverity) -> ReviewSuggestion {
+        ReviewSuggestion {
+            file: file.to_string(),
+            line: None,
+            issue: "issue".to_string(),
+            suggestion: "fix".to_string(),
+            severity,
+        }
+    }
+
+    #[test]
+    fn parse_review_response_reads_severity() {
+        let response = r#"{
+            "verdict": "needs_changes",
+            "suggestions": [
+                {"file": "a.rs", "issue": "i", "suggestion": "s", "severity": "nit"},
+                {"file": "b.rs", "issue": "i", "suggestion": "s"},
+                {"file": "c.rs", "issue": "i", "suggestion": "s", "severity": "bogus"}
+            ]
+        }"#;
+
+        let result = parse_review_response(response).unwrap();
+        assert_eq!(result.suggestions[0].severity, ReviewSeverity::Nit);
+        assert_eq!(result.suggestions[1].severity, ReviewSeverity::High);
+        assert_eq!(result.suggestions[2].severity, ReviewSeverity::High);
+    }
+
+    #[test]
+    fn gate_policy_blocks_logs_and_ignores() {
+        let policy = ReviewGatePolicy::default();
+
+        assert_eq!(policy.action_for(ReviewSeverity::Critical), GateAction::Block);
+        assert_eq!(policy.action_for(ReviewSeverity::High), GateAction::Block);
+        assert_eq!(policy.action_for(ReviewSeverity::Medium), GateAction::Log);
+        assert_eq!(policy.action_for(ReviewSeverity::Low), GateAction::Ignore);
+        assert_eq!(policy.action_for(ReviewSeverity::Nit), GateAction::Ignore);
+    }
+
+    #[test]
+    fn gating_uses_domain_overrides() {
+        let mut gating = ReviewGating::default();
+        gating.domains.insert(
+            "general_polish".to_string(),
+            ReviewGatePolicy {
+                block_at: ReviewSeverity::Critical,
+                log_at: ReviewSeverity::Low,
+            },
+        );
+        let review = ReviewResult {
+            verdict: ReviewVerdict::NeedsChanges,
+            suggestions: vec![
+                finding("a.rs", ReviewSeverity::High),
+                finding("b.rs", ReviewSeverity::Nit),
+            ],
+            summary: String::new(),
+        };
+
+        let security = gating.gate("security", &review);
+        assert!(security.needs_fix_round);
+        assert_eq!(security.blocking.len(), 1);
+        assert_eq!(security.ignored, 1);
+
+        let polish = gating.gate("general_polish", &review);
+        assert!(!polish.needs_fix_round);
+        assert_eq!(polish.logged.len(), 1);
+        assert_eq!(polish.ignored, 1);
+    }
+
+    #[test]
+    fn gating_blocks_rejection_without_findings() {
+        let review = ReviewResult {
+            verdict: ReviewVerdict::NeedsChanges,
+            suggestions: vec![],
+            summary: String::new(),
+        };
+
+        assert!(ReviewGating::default().gate("security", &review).needs_fix_round);
+    }
+
+    #[test]
+    fn gating_deserializes_from_toml() {
+        let gating: ReviewGating = toml::from_str(
+            r#"
+            [default]
+            block_at = "medium"
+
+            [domains.general_polish]
+            block_at = "critical"
+            log_at = "high"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(gating.default.block_at, ReviewSeverity::Medium);
+        assert_eq!(gating.default.log_at, ReviewSeverity::Medium);
+        assert_eq!(
+            gating.policy("general_polish").block_at,
+            ReviewSeverity::Critical
+        );
+    }
+
+    #[test]
+    fn phase_models_override_primary_and_reviewer_models() {
+        let config: SpawnTeamConfig = toml::from_str(
+            r#"
+            primary_model = "opus"
+            reviewer_model = "gemini-2.5-pro"
+
+            [models]
+            verify = "gemini-2.5-flash"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.fix_model(), Some("opus"));
+        assert_eq!(config.review_model(), Some("gemini-2.5-pro"));
+        assert_eq!(config.verify_model(), Some("gemini-2.5-flash"));
+        assert_eq!(SpawnTeamConfig::default().fix_model(), None);
+    }
+
+    #[test]
+    fn routes_send_changed_paths_to_domains() {
+        let selection: DomainSelection = toml::from_str(
+            r#"
+            force = ["general_polish"]
+
+            [[routes]]
+            paths = ["auth/**", "**/*.sql"]
+            domains = ["security"]
+
+            [[routes]]
+            paths = ["docs/**", "**/*.md"]
+            domains = ["general_polish"]
+            only = true
+
+            [[routes]]
+            paths = ["Dockerfile", ".github/workflows/**"]
+            domains = ["infra"]
+            "#,
+        )
+        .unwrap();
+        let defaults = vec!["general_polish".to_string(), "performance".to_string()];
+        let route = |paths: &[&str]| {
+            let changed: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+            selection.route(&defaults, &changed)
+        };
+
+        assert_eq!(
+            route(&["docs/guide.md", "README.md"]),
+            vec!["general_polish"]
+        );
+        assert_eq!(
+            route(&["auth/login.rs", "db/schema.sql"]),
+            vec!["general_polish", "performance", "security"]
+        );
+        assert_eq!(
+            route(&["Dockerfile", "README.md"]),
+            vec!["general_polish", "infra", "performance"]
+        );
+        assert_eq!(route(&[]), defaults);
+    }
+
+    #[test]
+    fn github_review_prompt_requests_artifact() {
+        let prompt = GitHubReviewPromptBuilder::new(
+            "Add login",
+            "https://github.com/epiphytic/demo/pull/3",
+        )
+        .build();
+
+        assert!(prompt.contains("Add login"));
+        assert!(prompt.contains("https://github.com/epiphytic/demo/pull/3"));
+        assert!(prompt.contains(crate::pr::REVIEW_ARTIFACT_MARKER));
+    }
+
     #[test]
     fn parse_review_response_returns_none_for_invalid() {
         let result = parse_review_response("not json at all");
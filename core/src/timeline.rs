@@ -0,0 +1,438 @@
+//! Chronological record of a run, and its replay.
+//!
+//! The watcher and review loop append [`TimelineEvent`]s to the spawn's
+//! `events.jsonl` as they send prompts, retry, ask questions, and review.
+//! `replay <spawn-id>` merges them with the tool-call audit trail into a
+//! [`Timeline`] and renders it as a narrative, optionally with a chart of
+//! where the time went.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditLog, TOOL_CALLS_FILE};
+use crate::error::Result;
+use crate::logs::EVENTS_FILE;
+use crate::team::ReviewVerdict;
+
+/// Longest prompt or target excerpt shown, in characters.
+const MAX_EXCERPT_CHARS: usize = 80;
+
+/// Width of the longest bar in the timing chart.
+const CHART_WIDTH: usize = 30;
+
+/// Something that happened during a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A prompt was sent to the LLM.
+    Prompt { iteration: u32, prompt: String },
+    /// The attempt `iteration` ended and the run was restarted.
+    Retry { iteration: u32, reason: String },
+    /// The LLM asked a human a question.
+    Question { question: String },
+    /// A human answered the LLM's question.
+    Answer { answer: String },
+    /// The LLM called a tool.
+    ToolCall { tool: String, target: String },
+    /// A review pass started.
+    Review {
+        domain: String,
+        iteration: u32,
+        /// Model the reviewer was asked to use, if one was configured.
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// A review pass ended.
+    Verdict {
+        domain: String,
+        verdict: ReviewVerdict,
+        blocking: usize,
+    },
+    /// The run ended.
+    Finished {
+        success: bool,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+/// An event and when it happened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl TimelineEvent {
+    /// Creates an event that happened now.
+    pub fn now(kind: EventKind) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            kind,
+        }
+    }
+}
+
+/// Events of one run, optionally mirrored to a file.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    /// File each event is appended to, if any.
+    path: Option<PathBuf>,
+    /// Events recorded so far.
+    events: Vec<TimelineEvent>,
+}
+
+impl EventLog {
+    /// Creates an in-memory event log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an event log that also appends to `path` as JSON lines.
+    pub fn to_file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            events: Vec::new(),
+        }
+    }
+
+    /// Records an event that happened now.
+    ///
+    /// A failure to write the file is logged rather than returned, so an
+    /// unwritable log directory does not stop the run.
+    pub fn record(&mut self, kind: EventKind) {
+        let event = TimelineEvent::now(kind);
+        if let Some(path) = &self.path {
+            if let Err(e) = append(path, &event) {
+                tracing::warn!(path = %path.display(), error = %e, "failed to write event log");
+            }
+        }
+        self.events.push(event);
+    }
+
+    /// Returns the events recorded so far.
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+}
+
+fn append(path: &Path, event: &TimelineEvent) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let line = serde_json::to_string(event)?;
+    writeln!(file, "{}", line)
+}
+
+/// A run's events in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timeline {
+    /// Events, oldest first.
+    pub events: Vec<TimelineEvent>,
+}
+
+impl Timeline {
+    /// Builds a timeline from `events` in any order.
+    pub fn new(mut events: Vec<TimelineEvent>) -> Self {
+        events.sort_by_key(|e| e.timestamp_ms);
+        Self { events }
+    }
+
+    /// Loads the event log and tool-call audit trail in a spawn's log
+    /// directory. Lines that cannot be parsed are skipped.
+    pub fn load(spawn_dir: &Path) -> Result<Self> {
+        let mut events = Vec::new();
+        let path = spawn_dir.join(EVENTS_FILE);
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            events.extend(
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<TimelineEvent>(line).ok()),
+            );
+        }
+        let path = spawn_dir.join(TOOL_CALLS_FILE);
+        if path.exists() {
+            events.extend(
+                AuditLog::load(&path)?
+                    .into_iter()
+                    .map(|record| TimelineEvent {
+                        timestamp_ms: record.timestamp_ms,
+                        kind: EventKind::ToolCall {
+                            target: record.target(),
+                            tool: record.tool,
+                        },
+                    }),
+            );
+        }
+        Ok(Self::new(events))
+    }
+
+    /// Renders the timeline as a narrative, one event per line, with a
+    /// chart of time per phase if `timing` is set.
+    pub fn render(&self, timing: bool) -> String {
+        let Some(start) = self.events.first().map(|e| e.timestamp_ms) else {
+            return "No events recorded for this run.\n".to_string();
+        };
+        let end = self.events.last().map_or(start, |e| e.timestamp_ms);
+
+        let mut out = format!(
+            "Run timeline ({} events over {})\n\n",
+            self.events.len(),
+            format_duration(end - start)
+        );
+        for event in &self.events {
+            out.push_str(&format!(
+                "  +{:>8}  {}\n",
+                format_offset(event.timestamp_ms - start),
+                describe(&event.kind)
+            ));
+        }
+
+        if timing {
+            let phases = self.phases();
+            let longest = phases.iter().map(|(_, ms)| *ms).max().unwrap_or(0);
+            let width = phases.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+            out.push_str("\nTime by phase\n\n");
+            for (label, ms) in &phases {
+                let bar = if longest == 0 {
+                    0
+                } else {
+                    ((*ms as f64 / longest as f64) * CHART_WIDTH as f64).ceil() as usize
+                };
+                out.push_str(&format!(
+                    "  {:<width$}  {:<chart$}  {}\n",
+                    label,
+                    "█".repeat(bar),
+                    format_duration(*ms),
+                    width = width,
+                    chart = CHART_WIDTH
+                ));
+            }
+        }
+        out
+    }
+
+    /// Splits the run at prompts, questions, answers, and review passes,
+    /// returning each phase's label and length in milliseconds.
+    fn phases(&self) -> Vec<(String, u64)> {
+        let end = self.events.last().map_or(0, |e| e.timestamp_ms);
+        let starts: Vec<(String, u64)> = self
+            .events
+            .iter()
+            .filter_map(|event| {
+                let label = match &event.kind {
+                    EventKind::Prompt { iteration, .. } => format!("attempt {}", iteration),
+                    EventKind::Question { .. } => "waiting for an answer".to_string(),
+                    EventKind::Answer { .. } => "resumed after answer".to_string(),
+                    EventKind::Review {
+                        domain, iteration, ..
+                    } => {
+                        format!("review {} #{}", domain, iteration)
+                    }
+                    _ => return None,
+                };
+                Some((label, event.timestamp_ms))
+            })
+            .collect();
+
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, (label, at))| {
+                let until = starts.get(i + 1).map_or(end, |(_, next)| *next);
+                (label.clone(), until - at)
+            })
+            .collect()
+    }
+}
+
+/// Describes an event in one line.
+fn describe(kind: &EventKind) -> String {
+    match kind {
+        EventKind::Prompt { iteration, prompt } => {
+            format!("Prompt sent (attempt {}): {}", iteration, excerpt(prompt))
+        }
+        EventKind::Retry { iteration, reason } => {
+            format!("Retrying after attempt {}: {}", iteration, excerpt(reason))
+        }
+        EventKind::Question { question } => format!("LLM asked: {}", excerpt(question)),
+        EventKind::Answer { answer } => format!("Human answered: {}", excerpt(answer)),
+        EventKind::ToolCall { tool, target } if is_commit(tool, target) => {
+            format!("Commit: {}", excerpt(target))
+        }
+        EventKind::ToolCall { tool, target } => format!("{} {}", tool, excerpt(target)),
+        EventKind::Review {
+            domain,
+            iteration,
+            model,
+        } => match model {
+            Some(model) => format!("Review started ({}, pass {}, {})", domain, iteration, model),
+            None => format!("Review started ({}, pass {})", domain, iteration),
+        },
+        EventKind::Verdict {
+            domain,
+            verdict,
+            blocking,
+        } => {
+            let verdict = match verdict {
+                ReviewVerdict::Approved => "approved".to_string(),
+                ReviewVerdict::NeedsChanges => format!("needs changes, {} blocking", blocking),
+                ReviewVerdict::Failed => "review failed".to_string(),
+            };
+            format!("Verdict ({}): {}", domain, verdict)
+        }
+        EventKind::Finished { success, reason } => match (success, reason) {
+            (true, _) => "Finished: success".to_string(),
+            (false, Some(reason)) => format!("Finished: failed ({})", excerpt(reason)),
+            (false, None) => "Finished: failed".to_string(),
+        },
+    }
+}
+
+/// Returns whether a tool call made a git commit.
+fn is_commit(tool: &str, target: &str) -> bool {
+    matches!(tool, "Bash" | "run_shell_command") && target.contains("git commit")
+}
+
+/// Returns the first line of `text`, shortened for the narrative.
+fn excerpt(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or("");
+    if line.chars().count() > MAX_EXCERPT_CHARS {
+        let cut: String = line.chars().take(MAX_EXCERPT_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Formats an offset from the start as `m:ss.s`.
+fn format_offset(ms: u64) -> String {
+    format!(
+        "{}:{:02}.{}",
+        ms / 60_000,
+        (ms / 1000) % 60,
+        (ms / 100) % 10
+    )
+}
+
+/// Formats a duration as `1h 02m`, `3m 05s`, or `4.2s`.
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, (secs / 60) % 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn at(ms: u64, kind: EventKind) -> TimelineEvent {
+        TimelineEvent {
+            timestamp_ms: 1_000_000 + ms,
+            kind,
+        }
+    }
+
+    #[test]
+    fn events_and_tool_calls_are_merged_in_order() {
+        let dir = TempDir::new().unwrap();
+        let mut events = EventLog::to_file(dir.path().join(EVENTS_FILE));
+        events.record(EventKind::Prompt {
+            iteration: 1,
+            prompt: "Add a login page".to_string(),
+        });
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        AuditLog::to_file(dir.path().join(TOOL_CALLS_FILE))
+            .record("Bash", r#"{"command":"git commit -m 'Add login'"}"#);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        events.record(EventKind::Finished {
+            success: true,
+            reason: None,
+        });
+
+        let timeline = Timeline::load(dir.path()).unwrap();
+        let kinds: Vec<_> = timeline.events.iter().map(|e| describe(&e.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "Prompt sent (attempt 1): Add a login page",
+                "Commit: git commit -m 'Add login'",
+                "Finished: success",
+            ]
+        );
+        assert!(Timeline::load(&dir.path().join("missing"))
+            .unwrap()
+            .events
+            .is_empty());
+    }
+
+    #[test]
+    fn render_narrates_and_charts_phases() {
+        let timeline = Timeline::new(vec![
+            at(
+                65_000,
+                EventKind::Verdict {
+                    domain: "security".to_string(),
+                    verdict: ReviewVerdict::NeedsChanges,
+                    blocking: 2,
+                },
+            ),
+            at(
+                0,
+                EventKind::Prompt {
+                    iteration: 1,
+                    prompt: "Add a login page\nwith details".to_string(),
+                },
+            ),
+            at(
+                60_000,
+                EventKind::Review {
+                    domain: "security".to_string(),
+                    iteration: 1,
+                    model: Some("opus".to_string()),
+                },
+            ),
+            at(
+                90_000,
+                EventKind::Finished {
+                    success: false,
+                    reason: Some("timed out".to_string()),
+                },
+            ),
+        ]);
+
+        let out = timeline.render(true);
+        assert!(out.starts_with("Run timeline (4 events over 1m 30s)"));
+        assert!(out.contains("  +  0:00.0  Prompt sent (attempt 1): Add a login page\n"));
+        assert!(out.contains("  +  1:00.0  Review started (security, pass 1, opus)\n"));
+        assert!(out.contains("  +  1:05.0  Verdict (security): needs changes, 2 blocking\n"));
+        assert!(out.contains("  +  1:30.0  Finished: failed (timed out)\n"));
+        assert!(out.contains(&format!(
+            "  attempt 1           {}  1m 00s\n",
+            "█".repeat(CHART_WIDTH)
+        )));
+        assert!(out.contains("  review security #1  ███████████████"));
+        assert!(!timeline.render(false).contains("Time by phase"));
+        assert_eq!(
+            Timeline::default().render(true),
+            "No events recorded for this run.\n"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Local verification before pushing.
+//!
+//! Commands such as `cargo check`, `cargo test --lib`, or a linter run in
+//! the sandbox before changes are committed and pushed, so broken code is
+//! caught locally instead of by CI on the PR. A failed command is turned
+//! into a "fix the build" prompt for the LLM that wrote the change.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::checks::summarize_log;
+use crate::prompt::{PromptTemplates, TemplateVars};
+use crate::sandbox::{sandbox_environment, SandboxManifest};
+
+/// Settings for verifying changes before they are pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationConfig {
+    /// Shell commands run in the sandbox, in order; empty skips verification.
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Attempts at fixing a failed verification before giving up on the push.
+    #[serde(default = "default_max_fix_attempts")]
+    pub max_fix_attempts: u32,
+    /// Lines of a failed command's output passed to the fixer.
+    #[serde(default = "default_max_output_lines")]
+    pub max_output_lines: usize,
+    /// Seconds a command may run before it is killed and counted as failed.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_max_fix_attempts() -> u32 {
+    2
+}
+
+fn default_max_output_lines() -> usize {
+    60
+}
+
+fn default_timeout() -> u64 {
+    1800
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            max_fix_attempts: default_max_fix_attempts(),
+            max_output_lines: default_max_output_lines(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+impl VerificationConfig {
+    /// Returns whether any verification command is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    /// Runs the commands in `worktree` with the sandbox environment of
+    /// `manifest`, stopping at the first that fails. A command still running
+    /// after `timeout` seconds is killed and counts as failed.
+    pub async fn run(
+        &self,
+        worktree: &Path,
+        manifest: &SandboxManifest,
+    ) -> Option<VerificationFailure> {
+        let env = sandbox_environment(manifest);
+        let limit = Duration::from_secs(self.timeout);
+
+        for command in &self.commands {
+            tracing::info!(command = %command, "running verification command");
+            let output = Command::new("sh")
+                .current_dir(worktree)
+                .args(["-c", command])
+                .env_clear()
+                .envs(&env)
+                .kill_on_drop(true)
+                .output();
+
+            let output = match tokio::time::timeout(limit, output).await {
+                Ok(Ok(output)) if output.status.success() => continue,
+                Ok(Ok(output)) => format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+                Ok(Err(e)) => format!("command could not start: {}", e),
+                Err(_) => {
+                    tracing::warn!(command = %command, timeout = self.timeout, "verification command timed out");
+                    format!("error: command timed out after {}s", self.timeout)
+                }
+            };
+            return Some(VerificationFailure {
+                command: command.clone(),
+                output: summarize_log(&output, self.max_output_lines),
+            });
+        }
+
+        None
+    }
+}
+
+/// A verification command that failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationFailure {
+    /// The command.
+    pub command: String,
+    /// The error lines of its output.
+    pub output: String,
+}
+
+impl VerificationFailure {
+    /// Renders the "fix the build" prompt for the task described by
+    /// `original_prompt`.
+    pub fn prompt(&self, original_prompt: &str, templates: &PromptTemplates) -> String {
+        let mut vars = TemplateVars::new();
+        vars.insert("original_prompt", original_prompt.to_string());
+        vars.insert("command", self.command.clone());
+        vars.insert("output", self.output.clone());
+        templates.render(PromptTemplates::FIX_BUILD, &vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn config(commands: &[&str]) -> VerificationConfig {
+        VerificationConfig {
+            commands: commands.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn passing_commands_verify() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn ok() {}").unwrap();
+
+        let failure = config(&["test -f lib.rs", "true"])
+            .run(dir.path(), &SandboxManifest::default())
+            .await;
+
+        assert_eq!(failure, None);
+        assert!(!VerificationConfig::default().is_enabled());
+    }
+
+    #[tokio::test]
+    async fn first_failure_stops_verification() {
+        let dir = TempDir::new().unwrap();
+        let manifest = SandboxManifest {
+            environment: [("LINT_LEVEL".to_string(), "strict".to_string())].into(),
+            ..Default::default()
+        };
+
+        let failure = config(&[
+            "echo \"error: lint $LINT_LEVEL failed\" >&2; exit 1",
+            "touch ran-second",
+        ])
+        .run(dir.path(), &manifest)
+        .await
+        .unwrap();
+
+        assert_eq!(failure.output, "error: lint strict failed");
+        assert!(!dir.path().join("ran-second").exists());
+        let prompt = failure.prompt("Add login", &PromptTemplates::builtin());
+        assert!(prompt.contains("Add login"));
+        assert!(prompt.contains("$ echo"));
+        assert!(prompt.contains("error: lint strict failed"));
+    }
+
+    #[tokio::test]
+    async fn hung_commands_time_out() {
+        let dir = TempDir::new().unwrap();
+        let verification = VerificationConfig {
+            timeout: 1,
+            ..config(&["sleep 30", "touch ran-second"])
+        };
+
+        let started = std::time::Instant::now();
+        let failure = verification
+            .run(dir.path(), &SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(failure.command, "sleep 30");
+        assert_eq!(failure.output, "error: command timed out after 1s");
+        assert!(!dir.path().join("ran-second").exists());
+    }
+}
@@ -8,11 +8,25 @@ use std::sync::Arc;
 
 use tokio::sync::mpsc;
 
+use crate::audit::{AuditLog, ModelUse, PermissionGrant, PermissionRequest, SpawnObservability};
+use crate::branch::BranchNamer;
+use crate::cancel::{open_partial_pr, preserve_partial_work, CancellationToken};
+use crate::commit::CommitMessages;
+use crate::context::ContextConfig;
 use crate::error::Result;
-use crate::monitor::{ProgressMonitor, ProgressSummary, TimeoutConfig, TimeoutReason};
+use crate::investigation;
+use crate::monitor::{
+    BudgetPhase, LoopConfig, LoopDetector, ProgressMonitor, ProgressSummary, TimeoutBudget,
+    TimeoutConfig, TimeoutReason,
+};
+use crate::patch::{self, WriteMode};
 use crate::permissions::{PermissionDetector, PermissionError, PermissionFix};
-use crate::runner::{LLMOutput, LLMRunner, LLMSpawnConfig};
-use crate::sandbox::{Sandbox, SandboxManifest, SandboxProvider};
+use crate::pr::PullRequest;
+use crate::question::{self, AnswerChannel};
+use crate::runner::{is_overload, LLMOutput, LLMRunner, LLMSpawnConfig};
+use crate::sandbox::{sandbox_environment_names, Sandbox, SandboxManifest, SandboxProvider};
+use crate::spawn::{SpawnConfig, SpawnStatus};
+use crate::timeline::{EventKind, EventLog};
 
 /// Recovery strategy for permission errors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -26,23 +40,70 @@ pub enum RecoveryStrategy {
     Interactive,
 }
 
+/// Appended to the prompt when a stalled LLM is restarted.
+const STALL_NUDGE: &str = "\n\nYour previous attempt stalled: it produced output but made no \
+tool calls or file changes for several minutes. Stop deliberating and act: read the files \
+you need, make the change, and finish.";
+
 /// Configuration for the watcher agent.
 #[derive(Debug, Clone)]
 pub struct WatcherConfig {
+    /// Model the LLM runs with; `None` uses the CLI's default.
+    pub model: Option<String>,
     /// Timeout configuration.
     pub timeout: TimeoutConfig,
     /// Recovery strategy.
     pub recovery_strategy: RecoveryStrategy,
-    /// Maximum permission escalations for moderate mode.
+    /// Maximum permission escalations for moderate mode. Also caps how
+    /// often a stalled or looping LLM is restarted, in every mode.
     pub max_escalations: u32,
+    /// Budget for the whole run. When set, each invocation's total timeout
+    /// is the primary phase's deadline rather than `timeout.total_timeout`.
+    pub budget: Option<TimeoutBudget>,
+    /// Repeated tool call detection. `None` disables it.
+    pub loop_detection: Option<LoopConfig>,
+    /// Push the work of a failed or timed-out spawn to a `wip/` branch and
+    /// open a draft PR for it.
+    pub partial_pr: bool,
+    /// Context pack appended to the prompt before the first attempt.
+    pub context: ContextConfig,
+    /// Whether the LLM edits files itself, outputs a diff, or only reports.
+    pub write_mode: WriteMode,
+    /// Most questions the LLM may ask a human in one run.
+    pub max_questions: u32,
 }
 
 impl Default for WatcherConfig {
     fn default() -> Self {
         Self {
+            model: None,
             timeout: TimeoutConfig::default(),
             recovery_strategy: RecoveryStrategy::Moderate,
             max_escalations: 1,
+            budget: None,
+            loop_detection: Some(LoopConfig::default()),
+            partial_pr: false,
+            context: ContextConfig::default(),
+            write_mode: WriteMode::default(),
+            max_questions: 3,
+        }
+    }
+}
+
+impl From<&SpawnConfig> for WatcherConfig {
+    fn from(config: &SpawnConfig) -> Self {
+        Self {
+            model: config.model.clone(),
+            timeout: TimeoutConfig {
+                idle_timeout: config.idle_timeout,
+                total_timeout: config.total_timeout,
+                ..TimeoutConfig::default()
+            },
+            max_escalations: config.max_permission_escalations,
+            partial_pr: config.partial_pr,
+            context: config.context.clone(),
+            write_mode: config.write_mode,
+            ..Self::default()
         }
     }
 }
@@ -60,6 +121,35 @@ pub struct WatcherResult {
     pub applied_fixes: Vec<PermissionFix>,
     /// Reason for termination, if any.
     pub termination_reason: Option<TerminationReason>,
+    /// Tool calls and runtime permission requests, across all attempts.
+    pub observability: SpawnObservability,
+    /// Draft PR holding the partial work of a failed spawn, if one was opened.
+    pub partial_pr: Option<PullRequest>,
+    /// Findings of a read-only spawn, in Markdown.
+    pub report: Option<String>,
+}
+
+impl WatcherResult {
+    /// Returns the spawn status this result maps to.
+    pub fn status(&self) -> SpawnStatus {
+        match &self.termination_reason {
+            Some(TerminationReason::Success) if self.success => SpawnStatus::Success,
+            Some(TerminationReason::Timeout(TimeoutReason::Total))
+                if self
+                    .progress
+                    .budget_remaining_secs
+                    .is_some_and(|secs| secs <= 0.0) =>
+            {
+                SpawnStatus::BudgetExceeded
+            }
+            Some(TerminationReason::Timeout(_)) => SpawnStatus::TimedOut,
+            Some(TerminationReason::PermissionError(_))
+            | Some(TerminationReason::EscalationLimitReached) => SpawnStatus::PermissionDenied,
+            Some(TerminationReason::RateLimited(_)) => SpawnStatus::RateLimited,
+            Some(TerminationReason::Cancelled { .. }) => SpawnStatus::Cancelled,
+            _ => SpawnStatus::Failed,
+        }
+    }
 }
 
 /// Reason the watcher terminated the spawn.
@@ -69,12 +159,25 @@ pub enum TerminationReason {
     Success,
     /// LLM exited with error.
     LLMError(String),
+    /// Every model was rate limited or overloaded.
+    RateLimited(String),
     /// Timeout occurred.
     Timeout(TimeoutReason),
     /// Unrecoverable permission error.
     PermissionError(String),
     /// Escalation limit reached.
     EscalationLimitReached,
+    /// The LLM kept repeating this tool call.
+    Loop(String),
+    /// In patch mode, the LLM's diff was missing or could not be applied.
+    PatchRejected(String),
+    /// The LLM stopped on this question and no answer came.
+    Unanswered(String),
+    /// The run was cancelled. Partial work is kept on `partial_branch`.
+    Cancelled {
+        reason: String,
+        partial_branch: Option<String>,
+    },
 }
 
 /// The watcher agent that orchestrates spawn lifecycle.
@@ -87,6 +190,20 @@ pub struct WatcherAgent<P: SandboxProvider, R: LLMRunner> {
     detector: PermissionDetector,
     /// Configuration.
     config: WatcherConfig,
+    /// Token that stops the run when cancelled.
+    cancel: CancellationToken,
+    /// Names branches that preserve cancelled work.
+    branches: BranchNamer,
+    /// How partial work is committed before its PR is opened.
+    commit_messages: CommitMessages,
+    /// File the tool-call audit trail is appended to.
+    audit_path: Option<PathBuf>,
+    /// File the run's timeline events are appended to.
+    events_path: Option<PathBuf>,
+    /// File the run's observability record is saved to when it ends.
+    observability_path: Option<PathBuf>,
+    /// Where the LLM's questions are posted, if it may ask any.
+    answers: Option<Arc<dyn AnswerChannel>>,
 }
 
 impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
@@ -97,9 +214,66 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
             runner: Arc::new(runner),
             detector: PermissionDetector::new(),
             config,
+            cancel: CancellationToken::new(),
+            branches: BranchNamer::new(),
+            commit_messages: CommitMessages::default(),
+            audit_path: None,
+            events_path: None,
+            observability_path: None,
+            answers: None,
         }
     }
 
+    /// Stops the run, killing the LLM, when `token` is cancelled.
+    ///
+    /// Work left in the sandbox is committed to a branch before cleanup.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Sets the namer for branches that preserve cancelled work.
+    pub fn with_branch_namer(mut self, namer: BranchNamer) -> Self {
+        self.branches = namer;
+        self
+    }
+
+    /// Sets how partial work is committed and its history rewritten before
+    /// a PR is opened for it.
+    pub fn with_commit_messages(mut self, messages: CommitMessages) -> Self {
+        self.commit_messages = messages;
+        self
+    }
+
+    /// Appends every tool call to `path` as it happens, e.g. the spawn's
+    /// [`TOOL_CALLS_FILE`](crate::audit::TOOL_CALLS_FILE).
+    pub fn with_audit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audit_path = Some(path.into());
+        self
+    }
+
+    /// Appends the run's prompts, retries, questions, and outcome to `path`
+    /// as they happen, e.g. the spawn's
+    /// [`EVENTS_FILE`](crate::logs::EVENTS_FILE).
+    pub fn with_event_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.events_path = Some(path.into());
+        self
+    }
+
+    /// Saves the run's [`SpawnObservability`] to `path` when it ends, e.g.
+    /// the spawn's [`OBSERVABILITY_FILE`](crate::audit::OBSERVABILITY_FILE).
+    pub fn with_observability_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.observability_path = Some(path.into());
+        self
+    }
+
+    /// Lets the LLM stop on a question, posted to `channel`. The LLM is
+    /// resumed in the same sandbox with the answer.
+    pub fn with_answer_channel(mut self, channel: impl AnswerChannel + 'static) -> Self {
+        self.answers = Some(Arc::new(channel));
+        self
+    }
+
     /// Runs a spawn with full lifecycle management.
     pub async fn run(
         &self,
@@ -110,104 +284,429 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
         let mut permission_errors = Vec::new();
         let mut applied_fixes = Vec::new();
         let mut escalation_count = 0;
+        let mut questions_asked = 0;
+        let mut audit = match &self.audit_path {
+            Some(path) => AuditLog::to_file(path),
+            None => AuditLog::new(),
+        };
+        let mut events = match &self.events_path {
+            Some(path) => EventLog::to_file(path),
+            None => EventLog::new(),
+        };
+        let mut observability = SpawnObservability::default();
+        let mut iteration = 0;
+        let mut prompt = prompt;
+
+        'run: loop {
+            iteration += 1;
 
-        loop {
             // Create sandbox
             let mut sandbox = self.provider.create(manifest.clone())?;
 
-            // Run LLM with monitoring
-            let result = self
-                .run_with_monitoring(&prompt, sandbox.path().clone(), &manifest)
-                .await;
-
-            // Cleanup sandbox
-            sandbox.cleanup()?;
+            // Point the LLM at the relevant code before it starts exploring
+            if iteration == 1 && self.config.context.enabled {
+                let pack = self.config.context.build(sandbox.path(), &prompt);
+                prompt = pack.inject(&prompt);
+            }
+            let mode = self.config.write_mode;
+            if iteration == 1 {
+                match mode {
+                    WriteMode::Direct => {}
+                    WriteMode::Patch => prompt.push_str(patch::PATCH_INSTRUCTIONS),
+                    WriteMode::ReadOnly => prompt.push_str(investigation::REPORT_INSTRUCTIONS),
+                }
+                if self.answers.is_some() {
+                    prompt.push_str(question::QUESTION_INSTRUCTIONS);
+                }
+            }
 
-            match result {
-                Ok((progress, None)) => {
-                    // Success!
-                    return Ok(WatcherResult {
-                        success: true,
-                        progress,
-                        permission_errors,
-                        applied_fixes,
-                        termination_reason: Some(TerminationReason::Success),
-                    });
+            // Run LLM with monitoring. Without write tools, a patch is
+            // applied from its answer or the answer is the report.
+            let run_manifest = if mode.grants_write_tools() {
+                manifest.clone()
+            } else {
+                patch::read_only(&manifest)
+            };
+            let mut messages = Vec::new();
+            let mut invocation = Invocation::default();
+            let mut turn_prompt = prompt.clone();
+            events.record(EventKind::Prompt {
+                iteration,
+                prompt: prompt.clone(),
+            });
+            let mut unanswered = None;
+            let result = loop {
+                let result = self
+                    .run_with_monitoring(
+                        &turn_prompt,
+                        sandbox.path().clone(),
+                        &run_manifest,
+                        &mut audit,
+                        &mut messages,
+                        &mut invocation,
+                    )
+                    .await;
+                if let Some(model) = invocation.model.take() {
+                    observability.models.push(ModelUse { iteration, model });
                 }
-                Ok((progress, Some(timeout_reason))) => {
-                    // Timeout
-                    return Ok(WatcherResult {
-                        success: false,
-                        progress,
-                        permission_errors,
-                        applied_fixes,
-                        termination_reason: Some(TerminationReason::Timeout(timeout_reason)),
-                    });
+
+                // An LLM that stopped on a question resumes with the answer
+                let (Ok((_, None)), Some(channel)) = (&result, &self.answers) else {
+                    break result;
+                };
+                let Some(asked) = question::parse_question(&messages) else {
+                    break result;
+                };
+                events.record(EventKind::Question {
+                    question: asked.clone(),
+                });
+                let Some(answer) = self
+                    .ask(channel.as_ref(), &asked, &mut questions_asked)
+                    .await
+                else {
+                    unanswered = Some(asked);
+                    break result;
+                };
+                events.record(EventKind::Answer {
+                    answer: answer.clone(),
+                });
+                let clarification = question::answer_prompt(&asked, &answer);
+                prompt.push_str(&clarification);
+                // Without a conversation to continue, start over with the
+                // clarified prompt; the sandbox keeps the work so far
+                turn_prompt = match invocation.session {
+                    Some(_) => clarification.trim_start().to_string(),
+                    None => prompt.clone(),
+                };
+            };
+            observability.tool_calls = audit.records().to_vec();
+            observability.environment = sandbox_environment_names(&run_manifest);
+
+            if let Some(reason) = self.cancel.reason() {
+                // A read-only spawn has no work to keep
+                let partial_branch = match mode {
+                    WriteMode::ReadOnly => Ok(None),
+                    _ => preserve_partial_work(sandbox.path(), &reason, &self.branches),
                 }
-                Err(WatcherError::PermissionErrors(errors, progress)) => {
-                    // Handle permission errors based on strategy
-                    for error in &errors {
-                        permission_errors.push(error.clone());
-
-                        match &error.fix {
-                            PermissionFix::CannotFix(reason) => {
-                                return Ok(WatcherResult {
+                .unwrap_or_else(|e| {
+                    tracing::warn!(error = %e, "failed to preserve partial work");
+                    None
+                });
+                sandbox.cleanup()?;
+                events.record(EventKind::Finished {
+                    success: false,
+                    reason: Some(format!("cancelled: {}", reason)),
+                });
+                self.save_observability(&observability);
+                return Ok(WatcherResult {
+                    success: false,
+                    progress: match result {
+                        Ok((progress, _))
+                        | Err(WatcherError::PermissionErrors(_, progress))
+                        | Err(WatcherError::LLMError(_, progress))
+                        | Err(WatcherError::RateLimited(_, progress))
+                        | Err(WatcherError::Looping(_, progress)) => progress,
+                    },
+                    permission_errors,
+                    applied_fixes,
+                    termination_reason: Some(TerminationReason::Cancelled {
+                        reason,
+                        partial_branch,
+                    }),
+                    observability,
+                    partial_pr: None,
+                    report: None,
+                });
+            }
+
+            let mut finished = 'attempt: {
+                match result {
+                    Ok((progress, None)) => {
+                        if let Some(asked) = unanswered {
+                            break 'attempt WatcherResult {
+                                success: false,
+                                progress,
+                                permission_errors,
+                                applied_fixes,
+                                termination_reason: Some(TerminationReason::Unanswered(asked)),
+                                observability,
+                                partial_pr: None,
+                                report: None,
+                            };
+                        }
+                        if mode == WriteMode::Patch {
+                            if let Err(e) =
+                                patch::apply_output(sandbox.path(), &messages, &manifest)
+                            {
+                                tracing::warn!(error = %e, "could not apply the LLM's patch");
+                                break 'attempt WatcherResult {
                                     success: false,
                                     progress,
                                     permission_errors,
                                     applied_fixes,
-                                    termination_reason: Some(TerminationReason::PermissionError(
-                                        reason.clone(),
+                                    termination_reason: Some(TerminationReason::PatchRejected(
+                                        e.to_string(),
                                     )),
-                                });
+                                    observability,
+                                    partial_pr: None,
+                                    report: None,
+                                };
                             }
-                            fix => {
-                                // Check escalation limit for moderate mode
-                                if self.config.recovery_strategy == RecoveryStrategy::Moderate
-                                    && escalation_count >= self.config.max_escalations
-                                {
-                                    return Ok(WatcherResult {
+                        }
+
+                        // Success!
+                        break 'attempt WatcherResult {
+                            success: true,
+                            progress,
+                            permission_errors,
+                            applied_fixes,
+                            termination_reason: Some(TerminationReason::Success),
+                            observability,
+                            partial_pr: None,
+                            report: (mode == WriteMode::ReadOnly)
+                                .then(|| investigation::extract_report(&messages))
+                                .flatten(),
+                        };
+                    }
+                    Ok((_, Some(TimeoutReason::Stalled)))
+                        if escalation_count < self.config.max_escalations =>
+                    {
+                        // Restart the stalled LLM with a nudge to act
+                        tracing::warn!(iteration, "LLM stalled without progress, restarting");
+                        events.record(EventKind::Retry {
+                            iteration,
+                            reason: "stalled without progress".to_string(),
+                        });
+                        if !prompt.ends_with(STALL_NUDGE) {
+                            prompt.push_str(STALL_NUDGE);
+                        }
+                        escalation_count += 1;
+                    }
+                    Ok((progress, Some(timeout_reason))) => {
+                        // Timeout
+                        break 'attempt WatcherResult {
+                            success: false,
+                            progress,
+                            permission_errors,
+                            applied_fixes,
+                            termination_reason: Some(TerminationReason::Timeout(timeout_reason)),
+                            observability,
+                            partial_pr: None,
+                            report: None,
+                        };
+                    }
+                    Err(WatcherError::PermissionErrors(errors, progress)) => {
+                        // Handle permission errors based on strategy
+                        for error in &errors {
+                            permission_errors.push(error.clone());
+                            observability.permissions_requested.push(PermissionRequest {
+                                iteration,
+                                error: error.clone(),
+                            });
+
+                            // Without write tools, never grant the LLM a way to write
+                            let fix = if mode.grants_write_tools() {
+                                error.fix.clone()
+                            } else {
+                                patch::restrict_fix(&error.fix)
+                            };
+                            match &fix {
+                                PermissionFix::CannotFix(reason) => {
+                                    break 'attempt WatcherResult {
                                         success: false,
                                         progress,
                                         permission_errors,
                                         applied_fixes,
                                         termination_reason: Some(
-                                            TerminationReason::EscalationLimitReached,
+                                            TerminationReason::PermissionError(reason.clone()),
                                         ),
-                                    });
+                                        observability,
+                                        partial_pr: None,
+                                        report: None,
+                                    };
                                 }
+                                fix => {
+                                    // Check escalation limit for moderate mode
+                                    if self.config.recovery_strategy == RecoveryStrategy::Moderate
+                                        && escalation_count >= self.config.max_escalations
+                                    {
+                                        break 'attempt WatcherResult {
+                                            success: false,
+                                            progress,
+                                            permission_errors,
+                                            applied_fixes,
+                                            termination_reason: Some(
+                                                TerminationReason::EscalationLimitReached,
+                                            ),
+                                            observability,
+                                            partial_pr: None,
+                                            report: None,
+                                        };
+                                    }
 
-                                // Apply fix
-                                self.apply_fix(&mut manifest, fix);
-                                applied_fixes.push(fix.clone());
-                                escalation_count += 1;
+                                    // Apply fix
+                                    self.apply_fix(&mut manifest, fix);
+                                    applied_fixes.push(fix.clone());
+                                    observability.permissions_granted.push(PermissionGrant {
+                                        iteration,
+                                        fix: fix.clone(),
+                                    });
+                                    escalation_count += 1;
+                                }
                             }
                         }
+                        events.record(EventKind::Retry {
+                            iteration,
+                            reason: format!("granted {} permission(s)", errors.len()),
+                        });
+                        // Continue loop with updated manifest
+                    }
+                    Err(WatcherError::Looping(call, _))
+                        if escalation_count < self.config.max_escalations =>
+                    {
+                        // Restart the looping LLM, steering it off the repeated call
+                        tracing::warn!(iteration, call = %call, "LLM is looping, restarting");
+                        events.record(EventKind::Retry {
+                            iteration,
+                            reason: format!("kept repeating `{}`", call),
+                        });
+                        prompt.push_str(&loop_nudge(&call));
+                        escalation_count += 1;
+                    }
+                    Err(WatcherError::Looping(call, progress)) => {
+                        break 'attempt WatcherResult {
+                            success: false,
+                            progress,
+                            permission_errors,
+                            applied_fixes,
+                            termination_reason: Some(TerminationReason::Loop(call)),
+                            observability,
+                            partial_pr: None,
+                            report: None,
+                        };
+                    }
+                    Err(WatcherError::RateLimited(msg, progress)) => {
+                        break 'attempt WatcherResult {
+                            success: false,
+                            progress,
+                            permission_errors,
+                            applied_fixes,
+                            termination_reason: Some(TerminationReason::RateLimited(msg)),
+                            observability,
+                            partial_pr: None,
+                            report: None,
+                        };
+                    }
+                    Err(WatcherError::LLMError(msg, progress)) => {
+                        break 'attempt WatcherResult {
+                            success: false,
+                            progress,
+                            permission_errors,
+                            applied_fixes,
+                            termination_reason: Some(TerminationReason::LLMError(msg)),
+                            observability,
+                            partial_pr: None,
+                            report: None,
+                        };
                     }
-                    // Continue loop with updated manifest
-                }
-                Err(WatcherError::LLMError(msg, progress)) => {
-                    return Ok(WatcherResult {
-                        success: false,
-                        progress,
-                        permission_errors,
-                        applied_fixes,
-                        termination_reason: Some(TerminationReason::LLMError(msg)),
-                    });
                 }
+
+                // Retry with the updated manifest or prompt
+                sandbox.cleanup()?;
+                continue 'run;
+            };
+
+            if self.config.partial_pr && !finished.success && mode != WriteMode::ReadOnly {
+                let reason = describe_failure(finished.termination_reason.as_ref());
+                finished.partial_pr = open_partial_pr(
+                    sandbox.path(),
+                    &prompt,
+                    &reason,
+                    &self.branches,
+                    &self.commit_messages,
+                )
+                .unwrap_or_else(|e| {
+                    tracing::warn!(error = %e, "failed to open PR for partial work");
+                    None
+                });
+            }
+            sandbox.cleanup()?;
+            events.record(EventKind::Finished {
+                success: finished.success,
+                reason: (!finished.success)
+                    .then(|| describe_failure(finished.termination_reason.as_ref())),
+            });
+            self.save_observability(&finished.observability);
+            return Ok(finished);
+        }
+    }
+
+    /// Saves `observability` if a file was configured. A failure is logged
+    /// rather than returned, so it does not change the run's outcome.
+    fn save_observability(&self, observability: &SpawnObservability) {
+        if let Some(path) = &self.observability_path {
+            if let Err(e) = observability.save(path) {
+                tracing::warn!(path = %path.display(), error = %e, "failed to save observability");
+            }
+        }
+    }
+
+    /// Asks a human the LLM's question. Returns `None` once the question
+    /// limit is reached, when nobody answers in time, or on cancellation.
+    async fn ask(
+        &self,
+        channel: &dyn AnswerChannel,
+        asked: &str,
+        questions_asked: &mut u32,
+    ) -> Option<String> {
+        if *questions_asked >= self.config.max_questions {
+            tracing::warn!(question = %asked, "question limit reached, not asking");
+            return None;
+        }
+        *questions_asked += 1;
+
+        tracing::info!(channel = channel.name(), question = %asked, "LLM asked a question");
+        let answer = tokio::select! {
+            answer = channel.ask(asked) => answer,
+            _ = self.cancel.cancelled() => return None,
+        };
+        match answer {
+            Ok(Some(answer)) => Some(answer),
+            Ok(None) => {
+                tracing::warn!(question = %asked, "no answer before the timeout");
+                None
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to ask the LLM's question");
+                None
             }
         }
     }
 
     /// Runs the LLM with progress monitoring.
+    ///
+    /// Continues the conversation in `invocation`, if any, and replaces it
+    /// with the one the LLM ran in, along with the model that answered.
     async fn run_with_monitoring(
         &self,
         prompt: &str,
         working_dir: PathBuf,
         manifest: &SandboxManifest,
+        audit: &mut AuditLog,
+        messages: &mut Vec<String>,
+        invocation: &mut Invocation,
     ) -> std::result::Result<(ProgressSummary, Option<TimeoutReason>), WatcherError> {
-        let mut monitor = ProgressMonitor::new(self.config.timeout);
+        let mut monitor = match &self.config.budget {
+            Some(budget) => ProgressMonitor::new(TimeoutConfig {
+                stall_timeout: self.config.timeout.stall_timeout,
+                ..budget.timeout_config(BudgetPhase::Primary, self.config.timeout.idle_timeout)
+            })
+            .with_budget(budget.clone()),
+            None => ProgressMonitor::new(self.config.timeout),
+        };
+        let mut loops = self.config.loop_detection.map(LoopDetector::new);
         let mut detected_errors = Vec::new();
+        let mut overloaded = false;
 
         // Create output channel
         let (tx, mut rx) = mpsc::channel::<LLMOutput>(100);
@@ -217,15 +716,30 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
             prompt: prompt.to_string(),
             working_dir,
             manifest: manifest.clone(),
-            model: None,
+            model: self.config.model.clone(),
+            extra_args: Vec::new(),
+            session: invocation.session.take(),
         };
 
         // Spawn LLM in background
         let runner = self.runner.clone();
         let llm_handle = tokio::spawn(async move { runner.spawn(spawn_config, tx).await });
 
-        // Process output with monitoring
-        while let Some(output) = rx.recv().await {
+        // Process output with monitoring. The deadline is enforced even when
+        // the LLM is silent; aborting drops the child, which kills it.
+        loop {
+            let output = tokio::select! {
+                output = rx.recv() => match output {
+                    Some(output) => Some(output),
+                    None => break,
+                },
+                _ = tokio::time::sleep(monitor.time_until_timeout()) => None,
+                _ = self.cancel.cancelled() => {
+                    llm_handle.abort();
+                    return Ok((ProgressSummary::from(&monitor), None));
+                }
+            };
+
             // Check for timeout
             if let Some(reason) = monitor.check_timeout() {
                 // Cancel LLM
@@ -233,10 +747,18 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
                 return Ok((ProgressSummary::from(&monitor), Some(reason)));
             }
 
+            let Some(output) = output else {
+                continue;
+            };
+            overloaded |= is_overload(&output);
+
             // Process output
             match &output {
                 LLMOutput::Stdout(line) => {
                     monitor.record_output(1);
+                    if !self.config.write_mode.grants_write_tools() || self.answers.is_some() {
+                        messages.push(line.clone());
+                    }
 
                     // Check for permission errors
                     if let Some(error) = self.detector.analyze(line) {
@@ -257,18 +779,64 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
                 LLMOutput::FileWrite(path) => {
                     monitor.record_file_write(path.clone());
                 }
-                LLMOutput::ToolCall { .. } => {
+                LLMOutput::ToolCall { tool, args } => {
+                    monitor.record_tool_call();
+                    audit.record(tool, args);
+
+                    if let Some(call) = loops.as_mut().and_then(|l| l.observe(tool, args)) {
+                        llm_handle.abort();
+                        return Err(WatcherError::Looping(call, ProgressSummary::from(&monitor)));
+                    }
+                }
+                LLMOutput::ToolResult {
+                    output, is_error, ..
+                } => {
                     monitor.touch();
+
+                    // Denied tool calls report the permission error here
+                    if *is_error {
+                        if let Some(error) = self.detector.analyze(output) {
+                            detected_errors.push(error);
+                        }
+                    }
+                }
+                LLMOutput::Usage(usage) => {
+                    monitor.record_usage(usage);
+                }
+                LLMOutput::PermissionDenied { tool, args } => {
+                    // The denied call's error result may already have matched
+                    let error = PermissionError::tool_denied(tool, args);
+                    if !detected_errors
+                        .iter()
+                        .any(|e: &PermissionError| e.error_type == error.error_type)
+                    {
+                        detected_errors.push(error);
+                    }
                 }
             }
         }
 
         // Wait for LLM to finish
-        let llm_result = llm_handle.await.map_err(|e| {
-            WatcherError::LLMError(format!("LLM task panicked: {}", e), ProgressSummary::from(&monitor))
-        })?.map_err(|e| {
-            WatcherError::LLMError(format!("LLM error: {}", e), ProgressSummary::from(&monitor))
-        })?;
+        let llm_result = llm_handle
+            .await
+            .map_err(|e| {
+                WatcherError::LLMError(
+                    format!("LLM task panicked: {}", e),
+                    ProgressSummary::from(&monitor),
+                )
+            })?
+            .map_err(|e| {
+                if overloaded {
+                    WatcherError::RateLimited(e.to_string(), ProgressSummary::from(&monitor))
+                } else {
+                    WatcherError::LLMError(
+                        format!("LLM error: {}", e),
+                        ProgressSummary::from(&monitor),
+                    )
+                }
+            })?;
+        invocation.session = llm_result.session_id.clone();
+        invocation.model = llm_result.model.clone();
 
         // Check for permission errors
         if !detected_errors.is_empty() {
@@ -279,6 +847,12 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
         }
 
         // Check exit status
+        if !llm_result.success && overloaded {
+            return Err(WatcherError::RateLimited(
+                "model was rate limited or overloaded".to_string(),
+                ProgressSummary::from(&monitor),
+            ));
+        }
         if !llm_result.success {
             return Err(WatcherError::LLMError(
                 "LLM exited with non-zero status".to_string(),
@@ -330,10 +904,57 @@ impl<P: SandboxProvider + 'static, R: LLMRunner + 'static> WatcherAgent<P, R> {
     }
 }
 
+/// What the runner reported about an LLM invocation.
+#[derive(Default)]
+struct Invocation {
+    /// Conversation to continue in the next invocation.
+    session: Option<String>,
+    /// Model that answered, if the runner reported one.
+    model: Option<String>,
+}
+
 /// Internal error type for watcher operations.
 enum WatcherError {
     PermissionErrors(Vec<PermissionError>, ProgressSummary),
     LLMError(String, ProgressSummary),
+    RateLimited(String, ProgressSummary),
+    Looping(String, ProgressSummary),
+}
+
+/// Describes why a spawn stopped, for the partial-work PR.
+fn describe_failure(reason: Option<&TerminationReason>) -> String {
+    match reason {
+        Some(TerminationReason::Timeout(TimeoutReason::Idle)) => {
+            "no activity before the idle timeout".to_string()
+        }
+        Some(TerminationReason::Timeout(TimeoutReason::Stalled)) => {
+            "no tool calls or file changes before the stall timeout".to_string()
+        }
+        Some(TerminationReason::Timeout(TimeoutReason::Total)) => {
+            "the total timeout ran out".to_string()
+        }
+        Some(TerminationReason::LLMError(msg)) => msg.clone(),
+        Some(TerminationReason::RateLimited(msg)) => format!("rate limited: {}", msg),
+        Some(TerminationReason::PermissionError(msg)) => format!("permission error: {}", msg),
+        Some(TerminationReason::EscalationLimitReached) => {
+            "permission escalation limit reached".to_string()
+        }
+        Some(TerminationReason::Loop(call)) => format!("kept repeating `{}`", call),
+        Some(TerminationReason::PatchRejected(msg)) => msg.clone(),
+        Some(TerminationReason::Unanswered(asked)) => format!("no answer to: {}", asked),
+        Some(TerminationReason::Cancelled { reason, .. }) => format!("cancelled: {}", reason),
+        Some(TerminationReason::Success) | None => "unknown failure".to_string(),
+    }
+}
+
+/// Returns the note appended to the prompt when an LLM looping on `call`
+/// is restarted.
+fn loop_nudge(call: &str) -> String {
+    format!(
+        "\n\nYour previous attempt kept repeating `{}` without making progress. Do not run \
+         it again unchanged: read its output, change your approach, and continue.",
+        call
+    )
 }
 
 #[cfg(test)]
@@ -346,6 +967,26 @@ mod tests {
 
         assert_eq!(config.recovery_strategy, RecoveryStrategy::Moderate);
         assert_eq!(config.max_escalations, 1);
+        assert!(!config.partial_pr);
+    }
+
+    #[test]
+    fn watcher_config_follows_spawn_config() {
+        let spawn = SpawnConfig::new("do it")
+            .with_model("opus")
+            .with_idle_timeout(std::time::Duration::from_secs(30))
+            .with_partial_pr(true);
+
+        let config = WatcherConfig::from(&spawn);
+
+        assert_eq!(config.model.as_deref(), Some("opus"));
+
+        assert_eq!(
+            config.timeout.idle_timeout,
+            std::time::Duration::from_secs(30)
+        );
+        assert_eq!(config.timeout.total_timeout, spawn.total_timeout);
+        assert!(config.partial_pr);
     }
 
     #[test]
@@ -366,6 +1007,699 @@ mod tests {
         );
     }
 
+    struct TempSandbox {
+        path: PathBuf,
+        manifest: SandboxManifest,
+    }
+
+    impl Sandbox for TempSandbox {
+        fn path(&self) -> &PathBuf {
+            &self.path
+        }
+
+        fn manifest(&self) -> &SandboxManifest {
+            &self.manifest
+        }
+
+        fn cleanup(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct TempProvider;
+
+    impl SandboxProvider for TempProvider {
+        type Sandbox = TempSandbox;
+
+        fn create(&self, manifest: SandboxManifest) -> Result<TempSandbox> {
+            Ok(TempSandbox {
+                path: std::env::temp_dir(),
+                manifest,
+            })
+        }
+    }
+
+    /// Runner that never produces output or exits.
+    struct SilentRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for SilentRunner {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            _output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            std::future::pending().await
+        }
+
+        fn name(&self) -> &str {
+            "silent"
+        }
+    }
+
+    /// Runner that runs one shell command and exits.
+    struct BashRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for BashRunner {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            let _ = output_tx
+                .send(LLMOutput::ToolCall {
+                    tool: "Bash".to_string(),
+                    args: r#"{"command":"cargo test"}"#.to_string(),
+                })
+                .await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "bash"
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_written_to_audit_log() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(crate::audit::TOOL_CALLS_FILE);
+        let saved = dir.path().join(crate::audit::OBSERVABILITY_FILE);
+        let watcher = WatcherAgent::new(TempProvider, BashRunner, WatcherConfig::default())
+            .with_audit_log(&path)
+            .with_observability_file(&saved);
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        let calls = &result.observability.tool_calls;
+        assert_eq!(calls[0].target(), "cargo test");
+        assert_eq!(&AuditLog::load(&path).unwrap(), calls);
+        assert_eq!(&SpawnObservability::load(&saved).unwrap().tool_calls, calls);
+    }
+
+    /// Runner whose first attempt is denied a shell command.
+    struct DeniedOnceRunner(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl LLMRunner for DeniedOnceRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            let attempt = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if attempt == 0 {
+                assert!(config.manifest.allowed_commands.is_empty());
+                let _ = output_tx
+                    .send(LLMOutput::PermissionDenied {
+                        tool: "Bash".to_string(),
+                        args: r#"{"command":"npm test"}"#.to_string(),
+                    })
+                    .await;
+            }
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "denied-once"
+        }
+    }
+
+    #[tokio::test]
+    async fn runtime_denials_are_recorded_per_iteration() {
+        let runner = DeniedOnceRunner(Default::default());
+        let watcher = WatcherAgent::new(TempProvider, runner, WatcherConfig::default());
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        let observability = &result.observability;
+        assert_eq!(observability.permissions_requested[0].iteration, 1);
+        assert_eq!(
+            observability.permissions_granted,
+            vec![PermissionGrant {
+                iteration: 1,
+                fix: PermissionFix::AllowCommand("npm test".to_string()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_are_written_to_event_log() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let runner = DeniedOnceRunner(Default::default());
+        let watcher = WatcherAgent::new(TempProvider, runner, WatcherConfig::default())
+            .with_event_log(dir.path().join(crate::logs::EVENTS_FILE));
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        let timeline = crate::timeline::Timeline::load(dir.path()).unwrap();
+        let kinds: Vec<_> = timeline.events.into_iter().map(|e| e.kind).collect();
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                EventKind::Prompt { iteration: 1, .. },
+                EventKind::Retry { iteration: 1, .. },
+                EventKind::Prompt { iteration: 2, .. },
+                EventKind::Finished { success: true, .. },
+            ]
+        ));
+    }
+
+    #[tokio::test]
+    async fn patch_mode_never_grants_commands() {
+        let config = WatcherConfig {
+            write_mode: WriteMode::Patch,
+            ..Default::default()
+        };
+        let runner = DeniedOnceRunner(Default::default());
+        let watcher = WatcherAgent::new(TempProvider, runner, config);
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(!result.success);
+        assert!(matches!(
+            result.termination_reason,
+            Some(TerminationReason::PermissionError(_))
+        ));
+        assert!(result.observability.permissions_granted.is_empty());
+    }
+
+    /// Runner in patch mode that answers without a diff.
+    struct NoDiffRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for NoDiffRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            assert_eq!(config.manifest.allowed_tools, patch::PATCH_MODE_TOOLS);
+            assert!(config.prompt.ends_with(patch::PATCH_INSTRUCTIONS));
+            let _ = output_tx
+                .send(LLMOutput::Stdout("I changed the file.".to_string()))
+                .await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "no-diff"
+        }
+    }
+
+    #[tokio::test]
+    async fn patch_mode_fails_without_a_diff() {
+        let config = WatcherConfig {
+            write_mode: WriteMode::Patch,
+            ..Default::default()
+        };
+        let manifest = SandboxManifest {
+            allowed_tools: vec!["Edit".to_string()],
+            ..Default::default()
+        };
+        let watcher = WatcherAgent::new(TempProvider, NoDiffRunner, config);
+
+        let result = watcher.run("do it".to_string(), manifest).await.unwrap();
+
+        assert!(!result.success);
+        assert!(matches!(
+            result.termination_reason,
+            Some(TerminationReason::PatchRejected(_))
+        ));
+        assert_eq!(result.status(), SpawnStatus::Failed);
+    }
+
+    /// Runner that investigates and answers with a report.
+    struct ReportRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for ReportRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            assert_eq!(config.manifest.allowed_tools, patch::PATCH_MODE_TOOLS);
+            assert!(config.prompt.ends_with(investigation::REPORT_INSTRUCTIONS));
+            for message in ["Reading src/lib.rs", "## Report\n\n- No issues found"] {
+                let _ = output_tx.send(LLMOutput::Stdout(message.to_string())).await;
+            }
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 2,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "report"
+        }
+    }
+
+    #[tokio::test]
+    async fn read_only_spawn_returns_report() {
+        let config = WatcherConfig::from(&SpawnConfig::read_only("audit it"));
+        let watcher = WatcherAgent::new(TempProvider, ReportRunner, config);
+
+        let result = watcher
+            .run("audit it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(
+            result.report.as_deref(),
+            Some("## Report\n\n- No issues found\n")
+        );
+        assert!(result.partial_pr.is_none());
+    }
+
+    /// Runner that asks a question, then finishes once resumed.
+    struct QuestionRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for QuestionRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            let message = match config.session.as_deref() {
+                None => {
+                    assert!(config.prompt.ends_with(question::QUESTION_INSTRUCTIONS));
+                    "Two tables fit.\nQUESTION: Which table holds sessions?"
+                }
+                Some(session) => {
+                    assert_eq!(session, "s1");
+                    assert!(config.prompt.contains("A human answered: users"));
+                    "Done."
+                }
+            };
+            let _ = output_tx.send(LLMOutput::Stdout(message.to_string())).await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 1,
+                success: true,
+                model: None,
+                session_id: Some("s1".to_string()),
+            })
+        }
+
+        fn name(&self) -> &str {
+            "question"
+        }
+    }
+
+    /// Channel that always gives the same answer.
+    struct FixedAnswer(Option<&'static str>);
+
+    #[async_trait::async_trait]
+    impl AnswerChannel for FixedAnswer {
+        async fn ask(&self, question: &str) -> Result<Option<String>> {
+            assert_eq!(question, "Which table holds sessions?");
+            Ok(self.0.map(str::to_string))
+        }
+
+        fn name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    #[tokio::test]
+    async fn question_is_answered_and_llm_resumed() {
+        let watcher = WatcherAgent::new(TempProvider, QuestionRunner, WatcherConfig::default())
+            .with_answer_channel(FixedAnswer(Some("users")));
+
+        let result = watcher
+            .run("add sessions".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+    }
+
+    #[tokio::test]
+    async fn unanswered_question_fails_the_spawn() {
+        let watcher = WatcherAgent::new(TempProvider, QuestionRunner, WatcherConfig::default())
+            .with_answer_channel(FixedAnswer(None));
+
+        let result = watcher
+            .run("add sessions".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(!result.success);
+        assert_eq!(
+            result.termination_reason,
+            Some(TerminationReason::Unanswered(
+                "Which table holds sessions?".to_string()
+            ))
+        );
+        assert_eq!(result.status(), SpawnStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn silent_llm_is_killed_at_budget_deadline() {
+        let config = WatcherConfig {
+            budget: Some(TimeoutBudget::new(std::time::Duration::from_millis(100))),
+            ..Default::default()
+        };
+        let watcher = WatcherAgent::new(TempProvider, SilentRunner, config);
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(!result.success);
+        assert_eq!(
+            result.termination_reason,
+            Some(TerminationReason::Timeout(TimeoutReason::Total))
+        );
+        assert!(result.progress.budget_remaining_secs.unwrap() < 0.1);
+        assert_eq!(result.status(), SpawnStatus::TimedOut);
+    }
+
+    /// Runner that talks without acting until it is nudged.
+    struct ChattyRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for ChattyRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            if !config.prompt.ends_with(STALL_NUDGE) {
+                loop {
+                    let _ = output_tx
+                        .send(LLMOutput::Stdout("Let me think about this...".to_string()))
+                        .await;
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                }
+            }
+            let _ = output_tx
+                .send(LLMOutput::FileWrite(PathBuf::from("src/lib.rs")))
+                .await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "chatty"
+        }
+    }
+
+    fn stall_config(max_escalations: u32) -> WatcherConfig {
+        WatcherConfig {
+            timeout: TimeoutConfig {
+                idle_timeout: std::time::Duration::from_secs(3600),
+                total_timeout: std::time::Duration::from_secs(3600),
+                stall_timeout: Some(std::time::Duration::from_millis(50)),
+            },
+            max_escalations,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn stalled_llm_is_restarted_with_nudge() {
+        let watcher = WatcherAgent::new(TempProvider, ChattyRunner, stall_config(1));
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(
+            result.progress.files_written,
+            vec![PathBuf::from("src/lib.rs")]
+        );
+    }
+
+    #[tokio::test]
+    async fn stall_without_escalations_left_times_out() {
+        let watcher = WatcherAgent::new(TempProvider, ChattyRunner, stall_config(0));
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(!result.success);
+        assert_eq!(
+            result.termination_reason,
+            Some(TerminationReason::Timeout(TimeoutReason::Stalled))
+        );
+    }
+
+    /// Runner that reruns a failing command until told to stop.
+    struct LoopingRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for LoopingRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            let steered = config.prompt.contains("kept repeating");
+            for _ in 0..if steered { 1 } else { 10 } {
+                let _ = output_tx
+                    .send(LLMOutput::ToolCall {
+                        tool: "Bash".to_string(),
+                        args: r#"{"command":"npm test"}"#.to_string(),
+                    })
+                    .await;
+            }
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "looping"
+        }
+    }
+
+    #[tokio::test]
+    async fn looping_llm_is_restarted_with_steering() {
+        let watcher = WatcherAgent::new(TempProvider, LoopingRunner, WatcherConfig::default());
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.status(), SpawnStatus::Success);
+        assert_eq!(result.progress.tool_calls, 1);
+    }
+
+    #[tokio::test]
+    async fn looping_llm_without_escalations_left_is_stopped() {
+        let config = WatcherConfig {
+            max_escalations: 0,
+            ..Default::default()
+        };
+        let watcher = WatcherAgent::new(TempProvider, LoopingRunner, config);
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(!result.success);
+        assert_eq!(
+            result.termination_reason,
+            Some(TerminationReason::Loop(
+                r#"Bash {"command":"npm test"}"#.to_string()
+            ))
+        );
+        assert_eq!(result.observability.tool_calls.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn cancellation_kills_running_llm() {
+        let token = CancellationToken::new();
+        let watcher = WatcherAgent::new(TempProvider, SilentRunner, WatcherConfig::default())
+            .with_cancellation(token.clone());
+
+        let canceller = async {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            token.cancel("interrupted");
+        };
+        let (result, _) = tokio::join!(
+            watcher.run("do it".to_string(), SandboxManifest::default()),
+            canceller
+        );
+        let result = result.unwrap();
+
+        assert!(!result.success);
+        assert!(matches!(
+            result.termination_reason,
+            Some(TerminationReason::Cancelled { ref reason, .. }) if reason == "interrupted"
+        ));
+        assert_eq!(result.status(), SpawnStatus::Cancelled);
+    }
+
+    /// Runner whose model is overloaded.
+    struct OverloadedRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for OverloadedRunner {
+        async fn spawn(
+            &self,
+            _config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            let _ = output_tx
+                .send(LLMOutput::Stderr("API Error: 529 Overloaded".to_string()))
+                .await;
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("false").status()?,
+                output_lines: 1,
+                success: false,
+                model: None,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "overloaded"
+        }
+    }
+
+    #[tokio::test]
+    async fn overloaded_model_is_reported_as_rate_limited() {
+        let watcher = WatcherAgent::new(TempProvider, OverloadedRunner, WatcherConfig::default());
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            result.termination_reason,
+            Some(TerminationReason::RateLimited(_))
+        ));
+        assert_eq!(result.status(), SpawnStatus::RateLimited);
+    }
+
+    /// Runner whose default model is overloaded and whose other models
+    /// answer.
+    struct DefaultOverloadedRunner;
+
+    #[async_trait::async_trait]
+    impl LLMRunner for DefaultOverloadedRunner {
+        async fn spawn(
+            &self,
+            config: LLMSpawnConfig,
+            output_tx: mpsc::Sender<LLMOutput>,
+        ) -> Result<crate::runner::LLMResult> {
+            if config.model.is_none() {
+                return OverloadedRunner.spawn(config, output_tx).await;
+            }
+            Ok(crate::runner::LLMResult {
+                exit_status: std::process::Command::new("true").status()?,
+                output_lines: 0,
+                success: true,
+                model: config.model,
+                session_id: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "default-overloaded"
+        }
+    }
+
+    #[tokio::test]
+    async fn configured_model_reaches_the_runner() {
+        let config = WatcherConfig {
+            model: Some("sonnet".to_string()),
+            ..WatcherConfig::default()
+        };
+        let watcher = WatcherAgent::new(TempProvider, DefaultOverloadedRunner, config);
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.observability.models[0].model, "sonnet");
+    }
+
+    #[tokio::test]
+    async fn serving_model_is_recorded() {
+        let runner = crate::runner::FallbackRunner::new(
+            Arc::new(DefaultOverloadedRunner),
+            vec!["sonnet".to_string()],
+        );
+        let watcher = WatcherAgent::new(TempProvider, runner, WatcherConfig::default());
+
+        let result = watcher
+            .run("do it".to_string(), SandboxManifest::default())
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(
+            result.observability.models,
+            vec![ModelUse {
+                iteration: 1,
+                model: "sonnet".to_string()
+            }]
+        );
+    }
+
     #[test]
     fn apply_fix_adds_read_path() {
         // We can't easily create a WatcherAgent without real providers,